@@ -0,0 +1,189 @@
+//! gRPC client for the auth service's `auth.AuthService`.
+//!
+//! For services that check tokens without holding the JWT secret. Unlike
+//! [`validate_access_token`](madome_auth_types::token::validate_access_token),
+//! introspection also rejects refresh tokens whose session was signed out.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use madome_auth_client::AuthClient;
+//! use madome_core::config::Secret;
+//! use madome_core::grpc::ServiceCredential;
+//! use tonic::transport::Endpoint;
+//!
+//! let channel = Endpoint::from_static("http://auth.example.com:50051").connect_lazy();
+//! let credential = ServiceCredential::new("gateway", Secret::new("gateway-secret".to_owned()));
+//! let client = AuthClient::new(channel, credential);
+//!
+//! if let Some(info) = client.introspect("token from the cookie").await? {
+//!     println!("user {} (role {})", info.user_id, info.user_role);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::Channel;
+use tonic::{Code, Status};
+use uuid::Uuid;
+
+use madome_auth_types::token::TokenInfo;
+use madome_core::grpc::ServiceCredential;
+use madome_proto::auth::{
+    IntrospectTokenRequest, Introspection, ResolveLegacyIdRequest, RevokeSessionRequest,
+    auth_service_client::AuthServiceClient,
+};
+
+/// Cheap to clone; clones share the channel.
+#[derive(Clone)]
+pub struct AuthClient {
+    inner: AuthServiceClient<InterceptedService<Channel, ServiceCredential>>,
+}
+
+impl AuthClient {
+    pub fn new(channel: Channel, credential: ServiceCredential) -> Self {
+        Self {
+            inner: AuthServiceClient::with_interceptor(channel, credential),
+        }
+    }
+
+    /// Holder of `token`, or `None` if it is invalid, expired or revoked.
+    pub async fn introspect(&self, token: &str) -> Result<Option<TokenInfo>, Status> {
+        let reply = self
+            .inner
+            .clone()
+            .introspect_token(IntrospectTokenRequest {
+                token: token.to_owned(),
+            })
+            .await?
+            .into_inner();
+        if !reply.active {
+            return Ok(None);
+        }
+        token_info(reply).map(Some)
+    }
+
+    /// Sign out `session_id` of `user_id`; `NOT_FOUND` if there is no such session.
+    pub async fn revoke_session(&self, user_id: Uuid, session_id: Uuid) -> Result<(), Status> {
+        self.inner
+            .clone()
+            .revoke_session(RevokeSessionRequest {
+                user_id: user_id.to_string(),
+                session_id: session_id.to_string(),
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Current id of the user behind a legacy numeric id, or `None` if there
+    /// is none. Answers never change, so they may be cached indefinitely.
+    pub async fn resolve_legacy_id(&self, legacy_id: i64) -> Result<Option<Uuid>, Status> {
+        let reply = match self
+            .inner
+            .clone()
+            .resolve_legacy_id(ResolveLegacyIdRequest { legacy_id })
+            .await
+        {
+            Ok(reply) => reply.into_inner(),
+            Err(status) if status.code() == Code::NotFound => return Ok(None),
+            Err(status) => return Err(status),
+        };
+        reply
+            .user_id
+            .parse()
+            .map(Some)
+            .map_err(|_| Status::internal("malformed legacy id reply"))
+    }
+}
+
+fn token_info(reply: Introspection) -> Result<TokenInfo, Status> {
+    let invalid = || Status::internal("malformed introspection reply");
+    let impersonator = reply
+        .impersonator_id
+        .map(|id| id.parse::<Uuid>())
+        .transpose()
+        .map_err(|_| invalid())?;
+    Ok(TokenInfo {
+        user_id: reply.user_id.parse().map_err(|_| invalid())?,
+        user_role: reply.user_role.try_into().map_err(|_| invalid())?,
+        access_token_exp: reply.exp,
+        impersonator,
+        // Only API tokens carry scopes, and never none.
+        scopes: (!reply.scopes.is_empty()).then_some(reply.scopes),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use madome_core::config::Secret;
+    use madome_testing::grpc::MockAuthServer;
+    use tonic::transport::Endpoint;
+
+    use super::*;
+
+    fn client(url: String) -> AuthClient {
+        let channel = Endpoint::from_shared(url).unwrap().connect_lazy();
+        let credential = ServiceCredential::new("gateway", Secret::new("secret".to_owned()));
+        AuthClient::new(channel, credential)
+    }
+
+    #[tokio::test]
+    async fn should_introspect_active_and_unknown_tokens() {
+        let user_id = Uuid::from_u128(1);
+        let server = MockAuthServer::new()
+            .with_token("good", user_id, 1)
+            .with_introspection(
+                "mdm_script",
+                Introspection {
+                    active: true,
+                    user_id: user_id.to_string(),
+                    scopes: vec!["tastes.read".to_owned()],
+                    ..Default::default()
+                },
+            )
+            .spawn()
+            .await;
+        let client = client(server.url());
+
+        let info = client.introspect("good").await.unwrap().unwrap();
+        assert_eq!(info.user_id, user_id);
+        assert_eq!(info.user_role, 1);
+        assert_eq!(info.impersonator, None);
+        assert_eq!(info.scopes, None);
+
+        let info = client.introspect("mdm_script").await.unwrap().unwrap();
+        assert_eq!(info.scopes.unwrap(), ["tastes.read"]);
+
+        assert!(client.introspect("unknown").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn should_revoke_session_once() {
+        let (user_id, session_id) = (Uuid::from_u128(1), Uuid::from_u128(2));
+        let server = MockAuthServer::new()
+            .with_session(user_id, session_id)
+            .spawn()
+            .await;
+        let client = client(server.url());
+
+        client.revoke_session(user_id, session_id).await.unwrap();
+        let status = client
+            .revoke_session(user_id, session_id)
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn should_resolve_known_legacy_ids_only() {
+        let user_id = Uuid::from_u128(1);
+        let server = MockAuthServer::new()
+            .with_legacy_id(42, user_id)
+            .spawn()
+            .await;
+        let client = client(server.url());
+
+        assert_eq!(client.resolve_legacy_id(42).await.unwrap(), Some(user_id));
+        assert_eq!(client.resolve_legacy_id(43).await.unwrap(), None);
+    }
+}
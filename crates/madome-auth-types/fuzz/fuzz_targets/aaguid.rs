@@ -0,0 +1,13 @@
+//! AAGUID extraction on arbitrary attestation objects.
+//!
+//! The bytes come straight from the client at passkey registration, before
+//! webauthn-rs has verified anything about them.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use madome_auth::webauthn_util::parse_aaguid;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_aaguid(data);
+});
@@ -0,0 +1,17 @@
+//! `validate_access_token` on arbitrary cookie values.
+//!
+//! Seeds are signed with [`SECRET`], so mutations of them reach the claim
+//! decoding behind the signature check.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use madome_auth_types::token::validate_access_token;
+
+const SECRET: &str = "fuzz-secret";
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(value) = std::str::from_utf8(data) {
+        let _ = validate_access_token(value, SECRET);
+    }
+});
@@ -0,0 +1,25 @@
+//! Raw `Cookie` header bytes through the jar the auth handlers read, then
+//! the access token it yields through `validate_access_token`.
+
+#![no_main]
+
+use axum_extra::extract::cookie::CookieJar;
+use http::header::{COOKIE, HeaderMap, HeaderValue};
+use libfuzzer_sys::fuzz_target;
+use madome_auth_types::cookie::{MADOME_ACCESS_TOKEN, MADOME_REFRESH_TOKEN};
+use madome_auth_types::token::validate_access_token;
+
+const SECRET: &str = "fuzz-secret";
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(value) = HeaderValue::from_bytes(data) else {
+        return;
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(COOKIE, value);
+    let jar = CookieJar::from_headers(&headers);
+    if let Some(cookie) = jar.get(MADOME_ACCESS_TOKEN) {
+        let _ = validate_access_token(cookie.value(), SECRET);
+    }
+    let _ = jar.get(MADOME_REFRESH_TOKEN).map(|c| c.value().len());
+});
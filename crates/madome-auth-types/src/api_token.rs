@@ -0,0 +1,85 @@
+//! Per-user API tokens for scripts and third-party clients.
+//!
+//! Unlike the browser's JWTs these are opaque, long-lived and revocable: the
+//! auth service stores a hash of each and answers for it over
+//! `IntrospectToken`. The `mdm_` prefix tells them apart from JWTs without a
+//! lookup, and the id inside finds the stored hash in one read:
+//!
+//! ```
+//! use madome_auth_types::api_token::{format_api_token, parse_api_token};
+//! use uuid::Uuid;
+//!
+//! let id = Uuid::from_u128(7);
+//! let token = format_api_token(id, "s3cret");
+//! let parsed = parse_api_token(&token).unwrap();
+//! assert_eq!((parsed.id, parsed.secret), (id, "s3cret"));
+//! assert!(parse_api_token("eyJhbGciOiJIUzI1NiJ9.e30.sig").is_none());
+//! ```
+//!
+//! A token grants only its scopes. The gateway forwards them in
+//! [`TOKEN_SCOPES_HEADER`], which [`IdentityHeaders`](crate::identity::IdentityHeaders)
+//! reads; browser requests carry no such header and are not restricted.
+
+use uuid::Uuid;
+
+pub const API_TOKEN_PREFIX: &str = "mdm_";
+
+/// Space-separated scopes of the API token a request was made with.
+pub const TOKEN_SCOPES_HEADER: &str = "x-madome-token-scopes";
+
+/// Scopes an API token can be issued with.
+pub const API_TOKEN_SCOPES: &[&str] = &[
+    "library.read",
+    "tastes.read",
+    "tastes.write",
+    "histories.read",
+    "histories.write",
+];
+
+/// The parts of a well-formed API token. Says nothing about whether it exists.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParsedApiToken<'a> {
+    pub id: Uuid,
+    pub secret: &'a str,
+}
+
+pub fn format_api_token(id: Uuid, secret: &str) -> String {
+    format!("{API_TOKEN_PREFIX}{}_{secret}", id.simple())
+}
+
+/// `None` for anything that is not shaped like an API token, JWTs included.
+pub fn parse_api_token(token: &str) -> Option<ParsedApiToken<'_>> {
+    let (id, secret) = token.strip_prefix(API_TOKEN_PREFIX)?.split_once('_')?;
+    if id.len() != 32 || secret.is_empty() {
+        return None;
+    }
+    Some(ParsedApiToken {
+        id: Uuid::try_parse(id).ok()?,
+        secret,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_reject_tokens_of_the_wrong_shape() {
+        let id = Uuid::from_u128(7).simple().to_string();
+        for token in [
+            format!("{id}_secret"),
+            format!("mdm_{id}_"),
+            format!("mdm_{id}"),
+            "mdm_7_secret".to_owned(),
+            format!("mdm_{}_secret", Uuid::from_u128(7)),
+        ] {
+            assert_eq!(parse_api_token(&token), None, "{token}");
+        }
+    }
+
+    #[test]
+    fn should_keep_underscores_in_the_secret() {
+        let token = format_api_token(Uuid::from_u128(7), "a_b-c");
+        assert_eq!(parse_api_token(&token).unwrap().secret, "a_b-c");
+    }
+}
@@ -1,6 +1,7 @@
 //! Cookie builders for access and refresh tokens.
 //!
-//! All cookie attributes match the legacy system exactly (Compat requirement).
+//! With [`CookieConfig::new`] all cookie attributes match the legacy system
+//! exactly (Compat requirement). Other values are for local development only.
 
 use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
 use time::Duration;
@@ -11,20 +12,131 @@ pub const MADOME_ACCESS_TOKEN: &str = "madome_access_token";
 /// Cookie name for the refresh token.
 pub const MADOME_REFRESH_TOKEN: &str = "madome_refresh_token";
 
-/// Access-token JWT lifetime in seconds (4 hours).
+/// Default access-token JWT lifetime in seconds (4 hours).
 pub const ACCESS_TOKEN_EXP: u64 = 14400;
 
-/// Cookie Max-Age for both tokens in seconds (7 days).
+/// Default refresh-token lifetime, and cookie Max-Age for both tokens, in
+/// seconds (7 days).
 pub const REFRESH_TOKEN_EXP: u64 = 604800;
 
-/// Set the access-token cookie on the jar.
+/// Refresh-token lifetime cap, in seconds (1 day), of a sign-in without
+/// "remember me".
+pub const SESSION_REFRESH_TOKEN_EXP: u64 = 86400;
+
+/// Token lifetimes in seconds. The default is the legacy pair
+/// ([`ACCESS_TOKEN_EXP`], [`REFRESH_TOKEN_EXP`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenLifetimes {
+    pub access_secs: u64,
+    /// Also the Max-Age of both token cookies, so the access cookie is still
+    /// there to be replaced when the refresh flow runs.
+    pub refresh_secs: u64,
+}
+
+impl Default for TokenLifetimes {
+    fn default() -> Self {
+        Self {
+            access_secs: ACCESS_TOKEN_EXP,
+            refresh_secs: REFRESH_TOKEN_EXP,
+        }
+    }
+}
+
+impl TokenLifetimes {
+    /// Refresh-token lifetime of a sign-in. Without "remember me" it is capped
+    /// at [`SESSION_REFRESH_TOKEN_EXP`], but never below the access token's.
+    ///
+    /// ```
+    /// use madome_auth_types::cookie::TokenLifetimes;
+    ///
+    /// let lifetimes = TokenLifetimes::default();
+    /// assert_eq!(lifetimes.refresh_for(true), 604800);
+    /// assert_eq!(lifetimes.refresh_for(false), 86400);
+    /// assert_eq!(lifetimes.cookie_max_age(false), None);
+    /// ```
+    pub fn refresh_for(&self, remember: bool) -> u64 {
+        if remember {
+            self.refresh_secs
+        } else {
+            self.refresh_secs
+                .min(SESSION_REFRESH_TOKEN_EXP)
+                .max(self.access_secs)
+        }
+    }
+
+    /// Max-Age of the token cookies; `None` without "remember me", so the
+    /// browser drops them when it closes.
+    pub fn cookie_max_age(&self, remember: bool) -> Option<u64> {
+        remember.then_some(self.refresh_secs)
+    }
+}
+
+/// Attributes shared by the cookies the services set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CookieConfig {
+    /// `Secure` attribute; browsers drop secure cookies set over plain HTTP.
+    pub secure: bool,
+    pub same_site: SameSite,
+    /// `Domain` attribute (root domain, e.g. "example.com").
+    pub domain: String,
+    /// `Path` of the access-token cookie.
+    pub access_path: String,
+    /// `Path` of the refresh-token cookie; only the refresh endpoint needs it.
+    pub refresh_path: String,
+}
+
+impl CookieConfig {
+    /// Production attributes for `domain`: `Secure`, `SameSite=Lax`, access
+    /// token on `/` and refresh token on `/auth/token`.
+    pub fn new(domain: impl Into<String>) -> Self {
+        Self {
+            secure: true,
+            same_site: SameSite::Lax,
+            domain: domain.into(),
+            access_path: "/".to_owned(),
+            refresh_path: "/auth/token".to_owned(),
+        }
+    }
+
+    /// Cookie `name=value` on `path` with the shared attributes; without
+    /// `max_age` it is a browser-session cookie. `HttpOnly` is left to the
+    /// caller.
+    pub fn cookie(
+        &self,
+        name: &'static str,
+        value: String,
+        path: &str,
+        max_age: Option<Duration>,
+    ) -> Cookie<'static> {
+        let mut cookie = Cookie::build((name, value))
+            .path(path.to_owned())
+            .domain(self.domain.clone())
+            .secure(self.secure)
+            .same_site(self.same_site)
+            .build();
+        if let Some(max_age) = max_age {
+            cookie.set_max_age(max_age);
+        }
+        cookie
+    }
+}
+
+/// Set the access-token cookie on the jar, kept for `max_age_secs` or, with
+/// `None`, until the browser closes.
 ///
 /// ```
 /// use axum_extra::extract::cookie::CookieJar;
-/// use madome_auth_types::cookie::{set_access_token_cookie, MADOME_ACCESS_TOKEN};
+/// use madome_auth_types::cookie::{
+///     CookieConfig, REFRESH_TOKEN_EXP, set_access_token_cookie, MADOME_ACCESS_TOKEN,
+/// };
 ///
-/// let jar = CookieJar::new();
-/// let jar = set_access_token_cookie(jar, "token_value".to_string(), "example.com".to_string());
+/// let config = CookieConfig::new("example.com");
+/// let jar = set_access_token_cookie(
+///     CookieJar::new(),
+///     "token_value".to_string(),
+///     &config,
+///     Some(REFRESH_TOKEN_EXP),
+/// );
 /// let cookie = jar.get(MADOME_ACCESS_TOKEN).unwrap();
 /// assert_eq!(cookie.path(), Some("/"));
 /// assert_eq!(cookie.domain(), Some("example.com"));
@@ -32,42 +144,70 @@ pub const REFRESH_TOKEN_EXP: u64 = 604800;
 /// assert!(cookie.http_only().unwrap_or(false));
 /// assert!(cookie.secure().unwrap_or(false));
 /// ```
-pub fn set_access_token_cookie(jar: CookieJar, value: String, domain: String) -> CookieJar {
-    let cookie = Cookie::build((MADOME_ACCESS_TOKEN, value))
-        .path("/")
-        .domain(domain)
-        .max_age(Duration::seconds(REFRESH_TOKEN_EXP as i64))
-        .http_only(true)
-        .secure(true)
-        .same_site(SameSite::Lax)
-        .build();
+pub fn set_access_token_cookie(
+    jar: CookieJar,
+    value: String,
+    config: &CookieConfig,
+    max_age_secs: Option<u64>,
+) -> CookieJar {
+    let mut cookie = config.cookie(
+        MADOME_ACCESS_TOKEN,
+        value,
+        &config.access_path,
+        max_age_secs.map(|secs| Duration::seconds(secs as i64)),
+    );
+    cookie.set_http_only(true);
     jar.add(cookie)
 }
 
-/// Set the refresh-token cookie on the jar.
+/// Set the refresh-token cookie on the jar, kept for `max_age_secs` or, with
+/// `None`, until the browser closes.
 ///
 /// ```
-/// use axum_extra::extract::cookie::CookieJar;
-/// use madome_auth_types::cookie::{set_refresh_token_cookie, MADOME_REFRESH_TOKEN};
+/// use axum_extra::extract::cookie::{CookieJar, SameSite};
+/// use madome_auth_types::cookie::{
+///     CookieConfig, REFRESH_TOKEN_EXP, set_refresh_token_cookie, MADOME_REFRESH_TOKEN,
+/// };
 ///
-/// let jar = CookieJar::new();
-/// let jar = set_refresh_token_cookie(jar, "refresh_value".to_string(), "example.com".to_string());
+/// let config = CookieConfig::new("example.com");
+/// let jar = set_refresh_token_cookie(
+///     CookieJar::new(),
+///     "refresh_value".to_string(),
+///     &config,
+///     Some(REFRESH_TOKEN_EXP),
+/// );
 /// let cookie = jar.get(MADOME_REFRESH_TOKEN).unwrap();
 /// assert_eq!(cookie.path(), Some("/auth/token"));
 /// assert_eq!(cookie.domain(), Some("example.com"));
 /// assert_eq!(cookie.max_age(), Some(time::Duration::seconds(604800)));
 /// assert!(cookie.http_only().unwrap_or(false));
 /// assert!(cookie.secure().unwrap_or(false));
+///
+/// // Plain-HTTP development server
+/// let dev = CookieConfig { secure: false, same_site: SameSite::Strict, ..CookieConfig::new("localhost") };
+/// let jar = set_refresh_token_cookie(CookieJar::new(), "refresh_value".to_string(), &dev, Some(3600));
+/// let cookie = jar.get(MADOME_REFRESH_TOKEN).unwrap();
+/// assert_eq!(cookie.secure(), Some(false));
+/// assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+/// assert_eq!(cookie.max_age(), Some(time::Duration::hours(1)));
+///
+/// // Sign-in without "remember me"
+/// let jar = set_refresh_token_cookie(CookieJar::new(), "refresh_value".to_string(), &config, None);
+/// assert_eq!(jar.get(MADOME_REFRESH_TOKEN).unwrap().max_age(), None);
 /// ```
-pub fn set_refresh_token_cookie(jar: CookieJar, value: String, domain: String) -> CookieJar {
-    let cookie = Cookie::build((MADOME_REFRESH_TOKEN, value))
-        .path("/auth/token")
-        .domain(domain)
-        .max_age(Duration::seconds(REFRESH_TOKEN_EXP as i64))
-        .http_only(true)
-        .secure(true)
-        .same_site(SameSite::Lax)
-        .build();
+pub fn set_refresh_token_cookie(
+    jar: CookieJar,
+    value: String,
+    config: &CookieConfig,
+    max_age_secs: Option<u64>,
+) -> CookieJar {
+    let mut cookie = config.cookie(
+        MADOME_REFRESH_TOKEN,
+        value,
+        &config.refresh_path,
+        max_age_secs.map(|secs| Duration::seconds(secs as i64)),
+    );
+    cookie.set_http_only(true);
     jar.add(cookie)
 }
 
@@ -76,35 +216,33 @@ pub fn set_refresh_token_cookie(jar: CookieJar, value: String, domain: String) -
 /// ```
 /// use axum_extra::extract::cookie::CookieJar;
 /// use madome_auth_types::cookie::{
-///     clear_cookies, set_access_token_cookie, set_refresh_token_cookie,
+///     clear_cookies, set_access_token_cookie, set_refresh_token_cookie, CookieConfig,
 ///     MADOME_ACCESS_TOKEN, MADOME_REFRESH_TOKEN,
 /// };
 ///
-/// let jar = CookieJar::new();
-/// let jar = set_access_token_cookie(jar, "a".to_string(), "example.com".to_string());
-/// let jar = set_refresh_token_cookie(jar, "r".to_string(), "example.com".to_string());
-/// let jar = clear_cookies(jar, "example.com".to_string());
+/// let config = CookieConfig::new("example.com");
+/// let jar = set_access_token_cookie(CookieJar::new(), "a".to_string(), &config, Some(60));
+/// let jar = set_refresh_token_cookie(jar, "r".to_string(), &config, None);
+/// let jar = clear_cookies(jar, &config);
 /// let access = jar.get(MADOME_ACCESS_TOKEN).unwrap();
 /// let refresh = jar.get(MADOME_REFRESH_TOKEN).unwrap();
 /// assert_eq!(access.max_age(), Some(time::Duration::ZERO));
 /// assert_eq!(refresh.max_age(), Some(time::Duration::ZERO));
 /// ```
-pub fn clear_cookies(jar: CookieJar, domain: String) -> CookieJar {
-    let access = Cookie::build((MADOME_ACCESS_TOKEN, ""))
-        .path("/")
-        .domain(domain.clone())
-        .max_age(Duration::ZERO)
-        .http_only(true)
-        .secure(true)
-        .same_site(SameSite::Lax)
-        .build();
-    let refresh = Cookie::build((MADOME_REFRESH_TOKEN, ""))
-        .path("/auth/token")
-        .domain(domain)
-        .max_age(Duration::ZERO)
-        .http_only(true)
-        .secure(true)
-        .same_site(SameSite::Lax)
-        .build();
+pub fn clear_cookies(jar: CookieJar, config: &CookieConfig) -> CookieJar {
+    let mut access = config.cookie(
+        MADOME_ACCESS_TOKEN,
+        String::new(),
+        &config.access_path,
+        Some(Duration::ZERO),
+    );
+    access.set_http_only(true);
+    let mut refresh = config.cookie(
+        MADOME_REFRESH_TOKEN,
+        String::new(),
+        &config.refresh_path,
+        Some(Duration::ZERO),
+    );
+    refresh.set_http_only(true);
     jar.add(access).add(refresh)
 }
@@ -5,7 +5,11 @@ use http::StatusCode;
 use http::request::Parts;
 use uuid::Uuid;
 
-/// User identity injected by the gateway via `x-madome-user-id` and `x-madome-user-role` headers.
+use crate::api_token::TOKEN_SCOPES_HEADER;
+
+/// User identity injected by the gateway via `x-madome-user-id` and `x-madome-user-role` headers,
+/// plus `x-madome-impersonator-id` on impersonated requests and `x-madome-token-scopes` on
+/// requests made with an API token.
 ///
 /// Returns 401 if `x-madome-user-id` is absent or cannot be parsed as UUID.
 /// Role enforcement (403) is done by handlers after extraction.
@@ -13,6 +17,20 @@ use uuid::Uuid;
 pub struct IdentityHeaders {
     pub user_id: Uuid,
     pub user_role: u8,
+    /// Staff user acting as `user_id`, from `x-madome-impersonator-id`.
+    pub impersonator: Option<Uuid>,
+    /// Scopes of the API token the request was made with; `None` for browser
+    /// tokens, which may do anything the role allows.
+    pub scopes: Option<Vec<String>>,
+}
+
+impl IdentityHeaders {
+    /// Whether the request may act within `scope`.
+    pub fn allows(&self, scope: &str) -> bool {
+        self.scopes
+            .as_ref()
+            .is_none_or(|scopes| scopes.iter().any(|s| s == scope))
+    }
 }
 
 impl<S> FromRequestParts<S> for IdentityHeaders
@@ -40,10 +58,34 @@ where
             .and_then(|v| v.to_str().ok())
             .and_then(|s| s.parse::<u8>().ok());
 
+        let impersonator = parts
+            .headers
+            .get("x-madome-impersonator-id")
+            .map(|v| v.to_str().ok().and_then(|s| s.parse::<Uuid>().ok()));
+
+        let scopes = parts.headers.get(TOKEN_SCOPES_HEADER).map(|v| {
+            v.to_str()
+                .ok()
+                .map(|s| s.split_whitespace().map(str::to_owned).collect::<Vec<_>>())
+        });
+
         async move {
             let user_id = user_id.ok_or(StatusCode::UNAUTHORIZED)?;
             let user_role = user_role.ok_or(StatusCode::UNAUTHORIZED)?;
-            Ok(Self { user_id, user_role })
+            // A garbled impersonator must not be mistaken for an ordinary request.
+            let impersonator = impersonator
+                .map(|v| v.ok_or(StatusCode::UNAUTHORIZED))
+                .transpose()?;
+            // Nor may garbled scopes lift an API token's restriction.
+            let scopes = scopes
+                .map(|v| v.ok_or(StatusCode::UNAUTHORIZED))
+                .transpose()?;
+            Ok(Self {
+                user_id,
+                user_role,
+                impersonator,
+                scopes,
+            })
         }
     }
 }
@@ -76,6 +118,60 @@ mod tests {
         let identity = result.unwrap();
         assert_eq!(identity.user_id, user_id);
         assert_eq!(identity.user_role, 1);
+        assert_eq!(identity.impersonator, None);
+        assert_eq!(identity.scopes, None);
+        assert!(identity.allows("tastes.write"));
+    }
+
+    #[tokio::test]
+    async fn should_restrict_api_token_requests_to_their_scopes() {
+        let user_id = Uuid::new_v4();
+        let identity = extract_identity(vec![
+            ("x-madome-user-id", &user_id.to_string()),
+            ("x-madome-user-role", "0"),
+            ("x-madome-token-scopes", "tastes.read library.read"),
+        ])
+        .await
+        .unwrap();
+
+        assert!(identity.allows("tastes.read"));
+        assert!(!identity.allows("tastes.write"));
+
+        let identity = extract_identity(vec![
+            ("x-madome-user-id", &user_id.to_string()),
+            ("x-madome-user-role", "0"),
+            ("x-madome-token-scopes", ""),
+        ])
+        .await
+        .unwrap();
+        assert!(!identity.allows("tastes.read"));
+    }
+
+    #[tokio::test]
+    async fn should_extract_impersonator() {
+        let user_id = Uuid::new_v4();
+        let staff_id = Uuid::new_v4();
+        let identity = extract_identity(vec![
+            ("x-madome-user-id", &user_id.to_string()),
+            ("x-madome-user-role", "0"),
+            ("x-madome-impersonator-id", &staff_id.to_string()),
+        ])
+        .await
+        .unwrap();
+
+        assert_eq!(identity.impersonator, Some(staff_id));
+    }
+
+    #[tokio::test]
+    async fn should_reject_invalid_impersonator() {
+        let user_id = Uuid::new_v4();
+        let result = extract_identity(vec![
+            ("x-madome-user-id", &user_id.to_string()),
+            ("x-madome-user-role", "0"),
+            ("x-madome-impersonator-id", "staff"),
+        ])
+        .await;
+        assert_eq!(result.unwrap_err(), StatusCode::UNAUTHORIZED);
     }
 
     #[tokio::test]
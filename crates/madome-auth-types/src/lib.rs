@@ -1,7 +1,12 @@
 //! Auth types shared across Madome services.
 //!
-//! Provides JWT validation, cookie builders, and the `IdentityHeaders` extractor.
+//! Provides JWT validation, cookie builders, the `IdentityHeaders` extractor,
+//! scoped machine tokens for internal tools, magic sign-in link tokens and the
+//! format of per-user API tokens.
 
+pub mod api_token;
 pub mod cookie;
 pub mod identity;
+pub mod login_link;
+pub mod machine;
 pub mod token;
@@ -0,0 +1,102 @@
+//! Tokens of magic sign-in links.
+//!
+//! Auth stores only the link id and expiry in its `login_link_created` outbox
+//! event; the mail worker signs the token when it sends the mail, so the
+//! plain token never sits in the outbox. Its `aud` claim keeps it apart from
+//! session tokens in both directions.
+//!
+//! ```
+//! use madome_auth_types::login_link::{callback_path, issue_login_link_token, validate_login_link_token};
+//!
+//! let link_id = uuid::Uuid::new_v4();
+//! let token = issue_login_link_token(link_id, 4_102_444_800, "secret").unwrap();
+//! assert_eq!(validate_login_link_token(&token, "secret").unwrap(), link_id);
+//! assert!(callback_path(&token).starts_with("/auth/link/callback?token="));
+//! ```
+
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::token::AuthError;
+
+/// `aud` claim of login link tokens.
+pub const LOGIN_LINK_AUDIENCE: &str = "login_link";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LoginLinkClaims {
+    /// Login link id.
+    sub: Uuid,
+    aud: String,
+    exp: u64,
+}
+
+/// Sign the token of link `link_id`, expiring with it at `exp` (Unix seconds).
+pub fn issue_login_link_token(
+    link_id: Uuid,
+    exp: u64,
+    secret: &str,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let claims = LoginLinkClaims {
+        sub: link_id,
+        aud: LOGIN_LINK_AUDIENCE.to_owned(),
+        exp,
+    };
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )
+}
+
+/// Validate a login link token (signature, audience, expiry) and return the link id.
+pub fn validate_login_link_token(token: &str, secret: &str) -> Result<Uuid, AuthError> {
+    let mut validation = Validation::new(jsonwebtoken::Algorithm::HS256);
+    validation.set_audience(&[LOGIN_LINK_AUDIENCE]);
+    validation.set_required_spec_claims(&["exp", "sub", "aud"]);
+
+    let claims = decode::<LoginLinkClaims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &validation,
+    )
+    .map_err(|e| match e.kind() {
+        jsonwebtoken::errors::ErrorKind::ExpiredSignature => AuthError::Expired,
+        jsonwebtoken::errors::ErrorKind::InvalidSignature => AuthError::InvalidSignature,
+        _ => AuthError::Malformed,
+    })?
+    .claims;
+
+    Ok(claims.sub)
+}
+
+/// Path of the callback redeeming `token`; the mail prefixes the public API origin.
+pub fn callback_path(token: &str) -> String {
+    format!("/auth/link/callback?token={token}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::validate_access_token;
+
+    const TEST_SECRET: &str = "test-secret-key-for-unit-tests";
+    const FAR_FUTURE: u64 = 4_102_444_800;
+
+    #[test]
+    fn should_not_accept_login_link_token_as_access_token() {
+        let token = issue_login_link_token(Uuid::new_v4(), FAR_FUTURE, TEST_SECRET).unwrap();
+        assert!(validate_access_token(&token, TEST_SECRET).is_err());
+    }
+
+    #[test]
+    fn should_reject_expired_or_foreign_login_link_token() {
+        let expired = issue_login_link_token(Uuid::new_v4(), 1_000_000, TEST_SECRET).unwrap();
+        let err = validate_login_link_token(&expired, TEST_SECRET).unwrap_err();
+        assert!(matches!(err, AuthError::Expired));
+
+        let foreign = issue_login_link_token(Uuid::new_v4(), FAR_FUTURE, "other").unwrap();
+        let err = validate_login_link_token(&foreign, TEST_SECRET).unwrap_err();
+        assert!(matches!(err, AuthError::InvalidSignature));
+    }
+}
@@ -0,0 +1,145 @@
+//! Scoped tokens for internal tools (machine credentials).
+//!
+//! A machine token names a client (e.g. "image-gen") instead of a user and
+//! carries the scopes it was issued for. Its `aud` claim keeps it apart from
+//! user tokens: [`validate_access_token`](crate::token::validate_access_token)
+//! refuses it, and [`validate_machine_token`] refuses user tokens.
+//!
+//! ```
+//! use madome_auth_types::machine::{issue_machine_token, validate_machine_token};
+//!
+//! let scopes = vec!["library.renew".to_owned()];
+//! let token = issue_machine_token("sync", &scopes, 4_102_444_800, "secret").unwrap();
+//! let info = validate_machine_token(&token, "secret").unwrap();
+//! assert_eq!(info.client, "sync");
+//! assert!(info.has_scope("library.renew"));
+//! assert!(!info.has_scope("users.read"));
+//! ```
+
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use serde::{Deserialize, Serialize};
+
+use crate::token::AuthError;
+
+/// `aud` claim of machine tokens.
+pub const MACHINE_AUDIENCE: &str = "machine";
+
+/// Client and scopes of a validated machine token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachineTokenInfo {
+    pub client: String,
+    pub scopes: Vec<String>,
+    pub exp: u64,
+}
+
+impl MachineTokenInfo {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MachineClaims {
+    /// Client name.
+    sub: String,
+    aud: String,
+    /// Space-separated, as in OAuth 2.0.
+    scope: String,
+    exp: u64,
+}
+
+/// Sign a machine token for `client` with `scopes`, expiring at `exp` (Unix
+/// seconds).
+pub fn issue_machine_token(
+    client: &str,
+    scopes: &[String],
+    exp: u64,
+    secret: &str,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let claims = MachineClaims {
+        sub: client.to_owned(),
+        aud: MACHINE_AUDIENCE.to_owned(),
+        scope: scopes.join(" "),
+        exp,
+    };
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )
+}
+
+/// Validate a machine token (signature, audience, expiry).
+pub fn validate_machine_token(token: &str, secret: &str) -> Result<MachineTokenInfo, AuthError> {
+    let mut validation = Validation::new(jsonwebtoken::Algorithm::HS256);
+    validation.set_audience(&[MACHINE_AUDIENCE]);
+    validation.set_required_spec_claims(&["exp", "sub", "aud"]);
+
+    let claims = decode::<MachineClaims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &validation,
+    )
+    .map_err(|e| match e.kind() {
+        jsonwebtoken::errors::ErrorKind::ExpiredSignature => AuthError::Expired,
+        jsonwebtoken::errors::ErrorKind::InvalidSignature => AuthError::InvalidSignature,
+        _ => AuthError::Malformed,
+    })?
+    .claims;
+
+    Ok(MachineTokenInfo {
+        client: claims.sub,
+        scopes: claims.scope.split_whitespace().map(str::to_owned).collect(),
+        exp: claims.exp,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::validate_access_token;
+
+    const TEST_SECRET: &str = "test-secret-key-for-unit-tests";
+    const FAR_FUTURE: u64 = 4_102_444_800;
+
+    #[test]
+    fn should_not_accept_machine_token_as_access_token() {
+        let token = issue_machine_token("sync", &[], FAR_FUTURE, TEST_SECRET).unwrap();
+        assert!(validate_access_token(&token, TEST_SECRET).is_err());
+    }
+
+    #[test]
+    fn should_not_accept_user_token_as_machine_token() {
+        #[derive(Serialize)]
+        struct UserClaims {
+            sub: String,
+            role: u8,
+            exp: u64,
+        }
+        let user = UserClaims {
+            sub: uuid::Uuid::new_v4().to_string(),
+            role: 2,
+            exp: FAR_FUTURE,
+        };
+        let token = encode(
+            &Header::default(),
+            &user,
+            &EncodingKey::from_secret(TEST_SECRET.as_bytes()),
+        )
+        .unwrap();
+
+        let err = validate_machine_token(&token, TEST_SECRET).unwrap_err();
+        assert!(matches!(err, AuthError::Malformed));
+    }
+
+    #[test]
+    fn should_reject_expired_or_foreign_machine_token() {
+        let expired = issue_machine_token("sync", &[], 1_000_000, TEST_SECRET).unwrap();
+        let err = validate_machine_token(&expired, TEST_SECRET).unwrap_err();
+        assert!(matches!(err, AuthError::Expired));
+
+        let foreign = issue_machine_token("sync", &[], FAR_FUTURE, "other").unwrap();
+        let err = validate_machine_token(&foreign, TEST_SECRET).unwrap_err();
+        assert!(matches!(err, AuthError::InvalidSignature));
+    }
+}
@@ -10,6 +10,12 @@ pub struct TokenInfo {
     pub user_id: Uuid,
     pub user_role: u8,
     pub access_token_exp: u64,
+    /// Staff user acting as `user_id`, set on tokens minted by
+    /// `POST /auth/impersonate/{user_id}`.
+    pub impersonator: Option<Uuid>,
+    /// Scopes of an API token (see [`api_token`](crate::api_token)), learned
+    /// by introspection. `None` for access tokens, which are not restricted.
+    pub scopes: Option<Vec<String>>,
 }
 
 /// Errors returned by [`validate_access_token`].
@@ -21,6 +27,9 @@ pub enum AuthError {
     Expired,
     #[error("malformed token")]
     Malformed,
+    /// A validly signed refresh token presented where an access token belongs.
+    #[error("not an access token")]
+    NotAccessToken,
 }
 
 /// JWT claims struct used for encoding/decoding.
@@ -32,6 +41,16 @@ struct AccessClaims {
     role: u8,
     /// Expiration timestamp (seconds since epoch).
     exp: u64,
+    /// Impersonating staff user ID as string; absent on ordinary tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    impersonator: Option<String>,
+    /// `access` or `refresh`; absent on tokens issued before the claim.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    typ: Option<String>,
+    /// Session id, set on refresh tokens only, including those issued before
+    /// `typ`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sid: Option<String>,
 }
 
 /// Validate an access-token cookie value. Pure function — no axum/tower dependency.
@@ -55,16 +74,28 @@ pub fn validate_access_token(cookie_value: &str, secret: &str) -> Result<TokenIn
     })?;
 
     let claims = token_data.claims;
+    // Refresh tokens are signed with the same key and must not pass for the
+    // short-lived token they are exchanged for.
+    if claims.typ.as_deref() == Some("refresh") || claims.sid.is_some() {
+        return Err(AuthError::NotAccessToken);
+    }
 
     let user_id = claims
         .sub
         .parse::<Uuid>()
         .map_err(|_| AuthError::Malformed)?;
+    let impersonator = claims
+        .impersonator
+        .map(|s| s.parse::<Uuid>())
+        .transpose()
+        .map_err(|_| AuthError::Malformed)?;
 
     Ok(TokenInfo {
         user_id,
         user_role: claims.role,
         access_token_exp: claims.exp,
+        impersonator,
+        scopes: None,
     })
 }
 
@@ -80,6 +111,9 @@ mod tests {
             sub: sub.to_string(),
             role,
             exp,
+            impersonator: None,
+            typ: Some("access".to_owned()),
+            sid: None,
         };
         encode(
             &Header::default(),
@@ -106,6 +140,31 @@ mod tests {
         let info = validate_access_token(&token, TEST_SECRET).unwrap();
         assert_eq!(info.user_id, user_id);
         assert_eq!(info.user_role, 1);
+        assert_eq!(info.impersonator, None);
+    }
+
+    #[test]
+    fn should_expose_impersonator_claim() {
+        let user_id = Uuid::new_v4();
+        let staff_id = Uuid::new_v4();
+        let claims = AccessClaims {
+            sub: user_id.to_string(),
+            role: 0,
+            exp: future_exp(),
+            impersonator: Some(staff_id.to_string()),
+            typ: Some("access".to_owned()),
+            sid: None,
+        };
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(TEST_SECRET.as_bytes()),
+        )
+        .unwrap();
+
+        let info = validate_access_token(&token, TEST_SECRET).unwrap();
+        assert_eq!(info.user_id, user_id);
+        assert_eq!(info.impersonator, Some(staff_id));
     }
 
     #[test]
@@ -127,6 +186,60 @@ mod tests {
         assert!(matches!(err, AuthError::InvalidSignature));
     }
 
+    #[test]
+    fn should_reject_refresh_token() {
+        let refresh = |typ: Option<&str>, sid: Option<String>| {
+            let claims = AccessClaims {
+                sub: Uuid::new_v4().to_string(),
+                role: 0,
+                exp: future_exp(),
+                impersonator: None,
+                typ: typ.map(str::to_owned),
+                sid,
+            };
+            encode(
+                &Header::default(),
+                &claims,
+                &EncodingKey::from_secret(TEST_SECRET.as_bytes()),
+            )
+            .unwrap()
+        };
+
+        let session = Some(Uuid::new_v4().to_string());
+        for token in [
+            refresh(Some("refresh"), session.clone()),
+            // Issued before `typ`.
+            refresh(None, session),
+        ] {
+            let err = validate_access_token(&token, TEST_SECRET).unwrap_err();
+            assert!(matches!(err, AuthError::NotAccessToken));
+        }
+    }
+
+    #[test]
+    fn should_accept_token_without_typ() {
+        let user_id = Uuid::new_v4();
+        let claims = AccessClaims {
+            sub: user_id.to_string(),
+            role: 0,
+            exp: future_exp(),
+            impersonator: None,
+            typ: None,
+            sid: None,
+        };
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(TEST_SECRET.as_bytes()),
+        )
+        .unwrap();
+
+        assert_eq!(
+            validate_access_token(&token, TEST_SECRET).unwrap().user_id,
+            user_id
+        );
+    }
+
     #[test]
     fn should_reject_malformed_token() {
         let err = validate_access_token("not-a-jwt", TEST_SECRET).unwrap_err();
@@ -0,0 +1,204 @@
+//! Storage for uploaded files (avatars, ...), behind the [`BlobStore`] port.
+//!
+//! Blobs are addressed by a relative key such as `avatars/{user_id}/256.webp`
+//! and served by a static file host; [`BlobStore::url`] gives the public URL a
+//! response can carry. Keys are checked by [`BlobKey::new`], so a key built
+//! from user input cannot leave the store's root.
+//!
+//! ```
+//! use madome_core::blob::{BlobKey, BlobStore, FsBlobStore};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! # let root = std::env::temp_dir().join(format!("madome-blob-doc-{}", std::process::id()));
+//! let store = FsBlobStore::new(&root, "https://example.com/files");
+//! let key = BlobKey::new("avatars/42/256.webp")?;
+//! store.put(&key, b"RIFF....WEBP".to_vec()).await?;
+//! assert_eq!(store.url(&key), "https://example.com/files/avatars/42/256.webp");
+//! # std::fs::remove_dir_all(&root)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use uuid::Uuid;
+
+/// Why a key was refused.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid blob key {0:?}")]
+pub struct InvalidBlobKey(pub String);
+
+/// A relative path of `/`-separated segments of `[A-Za-z0-9_.-]`, none of
+/// them empty, `.` or `..`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlobKey(String);
+
+impl BlobKey {
+    pub fn new(key: impl Into<String>) -> Result<Self, InvalidBlobKey> {
+        let key = key.into();
+        let valid = key.len() <= 512
+            && key.split('/').all(|segment| {
+                !matches!(segment, "" | "." | "..")
+                    && segment
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'-'))
+            });
+        if valid {
+            Ok(Self(key))
+        } else {
+            Err(InvalidBlobKey(key))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for BlobKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Where uploaded files are kept.
+pub trait BlobStore: Send + Sync + 'static {
+    /// Store `bytes` under `key`, replacing what was there. Readers see the
+    /// old or the new content, never a partial write.
+    fn put(&self, key: &BlobKey, bytes: Vec<u8>)
+    -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    /// `None` if nothing is stored under `key`.
+    fn get(&self, key: &BlobKey) -> impl Future<Output = anyhow::Result<Option<Vec<u8>>>> + Send;
+
+    /// Whether something was stored under `key`.
+    fn delete(&self, key: &BlobKey) -> impl Future<Output = anyhow::Result<bool>> + Send;
+
+    /// Public URL of `key`, whether or not it exists yet.
+    fn url(&self, key: &BlobKey) -> String;
+}
+
+/// Blobs as files under a directory (a volume the file host serves).
+#[derive(Debug, Clone)]
+pub struct FsBlobStore {
+    root: PathBuf,
+    public_url: String,
+}
+
+impl FsBlobStore {
+    /// `public_url` is where the file host serves `root`, e.g.
+    /// `https://{FILE_HOST}/files`.
+    pub fn new(root: impl Into<PathBuf>, public_url: &str) -> Self {
+        Self {
+            root: root.into(),
+            public_url: public_url.trim_end_matches('/').to_owned(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path(&self, key: &BlobKey) -> PathBuf {
+        self.root.join(key.as_str())
+    }
+}
+
+impl BlobStore for FsBlobStore {
+    async fn put(&self, key: &BlobKey, bytes: Vec<u8>) -> anyhow::Result<()> {
+        let path = self.path(key);
+        let dir = path.parent().unwrap_or(&self.root);
+        tokio::fs::create_dir_all(dir).await?;
+        // Written aside and renamed over, so the replacement is atomic.
+        let staging = dir.join(format!(".{}.tmp", Uuid::new_v4()));
+        tokio::fs::write(&staging, bytes).await?;
+        if let Err(e) = tokio::fs::rename(&staging, &path).await {
+            tokio::fs::remove_file(&staging).await.ok();
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &BlobKey) -> anyhow::Result<Option<Vec<u8>>> {
+        match tokio::fs::read(self.path(key)).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &BlobKey) -> anyhow::Result<bool> {
+        match tokio::fs::remove_file(self.path(key)).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn url(&self, key: &BlobKey) -> String {
+        format!("{}/{}", self.public_url, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(name: &str) -> FsBlobStore {
+        let root = std::env::temp_dir().join(format!("madome-blob-{name}-{}", std::process::id()));
+        FsBlobStore::new(root, "https://example.com/files/")
+    }
+
+    #[test]
+    fn should_refuse_keys_leaving_the_root() {
+        for key in [
+            "",
+            "/etc/passwd",
+            "../x",
+            "a/../../x",
+            "a//b",
+            "a/./b",
+            "a\\b",
+            "a b",
+            "é",
+        ] {
+            assert!(BlobKey::new(key).is_err(), "{key:?} accepted");
+        }
+        assert!(BlobKey::new("avatars/0d6f/256.webp").is_ok());
+    }
+
+    #[tokio::test]
+    async fn should_replace_read_and_delete_blobs() {
+        let store = store("crud");
+        let key = BlobKey::new("avatars/1/64.webp").unwrap();
+        assert_eq!(store.get(&key).await.unwrap(), None);
+
+        store.put(&key, b"first".to_vec()).await.unwrap();
+        store.put(&key, b"second".to_vec()).await.unwrap();
+        assert_eq!(
+            store.get(&key).await.unwrap().as_deref(),
+            Some(&b"second"[..])
+        );
+
+        assert!(store.delete(&key).await.unwrap());
+        assert!(!store.delete(&key).await.unwrap());
+        let leftovers = std::fs::read_dir(store.root().join("avatars/1"))
+            .unwrap()
+            .count();
+        assert_eq!(leftovers, 0);
+        std::fs::remove_dir_all(store.root()).unwrap();
+    }
+
+    #[test]
+    fn should_build_public_url_under_base() {
+        let key = BlobKey::new("avatars/1/64.webp").unwrap();
+        assert_eq!(
+            store("url").url(&key),
+            "https://example.com/files/avatars/1/64.webp"
+        );
+    }
+}
@@ -0,0 +1,259 @@
+//! Write-behind buffering for frequent overwrites of the same row.
+//!
+//! A [`Coalescer`] keeps only the latest value written for each key and hands
+//! the pending values to its flush function every period, so a reader turning
+//! pages writes once per period instead of once per page:
+//!
+//! ```
+//! use std::time::Duration;
+//! use madome_core::coalesce::Coalescer;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! // (user, book) -> page
+//! let history = Coalescer::new("history", Duration::from_secs(5), |batch: Vec<_>| async move {
+//!     // e.g. one multi-row upsert
+//!     assert_eq!(batch, [((1, 42), 3)]);
+//!     Ok(())
+//! });
+//! history.write((1, 42), 1);
+//! history.write((1, 42), 3);
+//! // On shutdown: nothing pending is lost.
+//! history.shutdown().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Writes that must not wait, such as a jump to a distant page, go through
+//! [`Coalescer::write_through`]. Flushes never overlap, so a key's values
+//! reach the flush function in the order they were written. A failed flush
+//! keeps its values pending for the next one unless newer values arrived.
+//! Values still pending when the process dies are lost; the period bounds how
+//! much.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+type FlushFn<K, V> =
+    dyn Fn(Vec<(K, V)>) -> Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>> + Send + Sync;
+
+struct Shared<K, V> {
+    name: &'static str,
+    pending: Mutex<HashMap<K, V>>,
+    /// Held for the whole of a flush, so flushes run one at a time.
+    flushing: tokio::sync::Mutex<()>,
+    flush: Box<FlushFn<K, V>>,
+}
+
+/// Latest value per key, flushed in the background. Call
+/// [`Coalescer::shutdown`] before exiting; dropping it stops the background
+/// flushes and discards what is pending.
+pub struct Coalescer<K, V> {
+    shared: Arc<Shared<K, V>>,
+    ticker: JoinHandle<()>,
+}
+
+impl<K, V> Coalescer<K, V>
+where
+    K: Eq + Hash + Clone + Send + 'static,
+    V: Clone + Send + 'static,
+{
+    /// Start flushing every `every`. `flush` gets each key at most once per
+    /// call; `name` labels its log lines.
+    pub fn new<F, Fut>(name: &'static str, every: Duration, flush: F) -> Self
+    where
+        F: Fn(Vec<(K, V)>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        let shared = Arc::new(Shared {
+            name,
+            pending: Mutex::default(),
+            flushing: tokio::sync::Mutex::new(()),
+            flush: Box::new(move |batch| Box::pin(flush(batch))),
+        });
+        let ticker = tokio::spawn({
+            let shared = Arc::clone(&shared);
+            async move {
+                let mut interval = tokio::time::interval(every);
+                interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    // The values stay pending for the next tick.
+                    if let Err(e) = shared.flush().await {
+                        let error = format!("{e:#}");
+                        tracing::warn!(coalescer = shared.name, %error, "flush failed");
+                    }
+                }
+            }
+        });
+        Self { shared, ticker }
+    }
+
+    /// Replace the pending value of `key`.
+    pub fn write(&self, key: K, value: V) {
+        self.shared.pending().insert(key, value);
+    }
+
+    /// Flush `value` for `key` now, dropping the older value pending for it.
+    pub async fn write_through(&self, key: K, value: V) -> anyhow::Result<()> {
+        let _flushing = self.shared.flushing.lock().await;
+        self.shared.pending().remove(&key);
+        (self.shared.flush)(vec![(key, value)]).await
+    }
+
+    /// Number of keys waiting for the next flush.
+    pub fn pending(&self) -> usize {
+        self.shared.pending().len()
+    }
+
+    /// Flush everything pending now.
+    pub async fn flush(&self) -> anyhow::Result<()> {
+        self.shared.flush().await
+    }
+
+    /// Stop the background flushes and flush what is left.
+    pub async fn shutdown(self) -> anyhow::Result<()> {
+        self.ticker.abort();
+        self.shared.flush().await
+    }
+}
+
+impl<K, V> Shared<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    fn pending(&self) -> std::sync::MutexGuard<'_, HashMap<K, V>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    async fn flush(&self) -> anyhow::Result<()> {
+        let _flushing = self.flushing.lock().await;
+        let batch: Vec<(K, V)> = self.pending().drain().collect();
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let retry = batch.clone();
+        (self.flush)(batch).await.inspect_err(|_| {
+            let mut pending = self.pending();
+            for (key, value) in retry {
+                pending.entry(key).or_insert(value);
+            }
+        })
+    }
+}
+
+impl<K, V> Drop for Coalescer<K, V> {
+    fn drop(&mut self) {
+        self.ticker.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Flushed = Arc<Mutex<Vec<Vec<(&'static str, u32)>>>>;
+
+    /// A coalescer that never ticks on its own during a test, recording its
+    /// flushes; `fail` makes the next flush fail.
+    fn recording() -> (Coalescer<&'static str, u32>, Flushed, Arc<Mutex<bool>>) {
+        let flushed = Flushed::default();
+        let fail = Arc::new(Mutex::new(false));
+        let coalescer = Coalescer::new("test", Duration::from_secs(3600), {
+            let (flushed, fail) = (Arc::clone(&flushed), Arc::clone(&fail));
+            move |mut batch: Vec<(&'static str, u32)>| {
+                let (flushed, fail) = (Arc::clone(&flushed), Arc::clone(&fail));
+                async move {
+                    if std::mem::take(&mut *fail.lock().unwrap()) {
+                        anyhow::bail!("database down");
+                    }
+                    batch.sort_unstable();
+                    flushed.lock().unwrap().push(batch);
+                    Ok(())
+                }
+            }
+        });
+        (coalescer, flushed, fail)
+    }
+
+    #[tokio::test]
+    async fn should_flush_only_the_latest_value_per_key() {
+        let (coalescer, flushed, _) = recording();
+        for page in 1..=20 {
+            coalescer.write("user1:book1", page);
+        }
+        coalescer.write("user2:book1", 7);
+        assert_eq!(coalescer.pending(), 2);
+
+        coalescer.flush().await.unwrap();
+        coalescer.flush().await.unwrap();
+        assert_eq!(
+            *flushed.lock().unwrap(),
+            [vec![("user1:book1", 20), ("user2:book1", 7)]]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_write_through_and_drop_the_older_pending_value() {
+        let (coalescer, flushed, _) = recording();
+        coalescer.write("user1:book1", 3);
+        coalescer.write("user1:book2", 1);
+        coalescer.write_through("user1:book1", 250).await.unwrap();
+
+        assert_eq!(coalescer.pending(), 1);
+        assert_eq!(*flushed.lock().unwrap(), [vec![("user1:book1", 250)]]);
+    }
+
+    #[tokio::test]
+    async fn should_persist_pending_values_on_shutdown() {
+        let (coalescer, flushed, _) = recording();
+        coalescer.write("user1:book1", 12);
+        coalescer.shutdown().await.unwrap();
+        assert_eq!(*flushed.lock().unwrap(), [vec![("user1:book1", 12)]]);
+    }
+
+    #[tokio::test]
+    async fn should_retry_failed_flush_without_overwriting_newer_values() {
+        let (coalescer, flushed, fail) = recording();
+        coalescer.write("user1:book1", 4);
+        coalescer.write("user1:book2", 9);
+        *fail.lock().unwrap() = true;
+        assert!(coalescer.flush().await.is_err());
+
+        coalescer.write("user1:book1", 5);
+        coalescer.flush().await.unwrap();
+        assert_eq!(
+            *flushed.lock().unwrap(),
+            [vec![("user1:book1", 5), ("user1:book2", 9)]]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_flush_in_the_background_every_period() {
+        let flushed = Flushed::default();
+        let coalescer = Coalescer::new("test", Duration::from_millis(20), {
+            let flushed = Arc::clone(&flushed);
+            move |batch| {
+                flushed.lock().unwrap().push(batch);
+                async { Ok(()) }
+            }
+        });
+        coalescer.write("user1:book1", 2);
+        for _ in 0..100 {
+            if coalescer.pending() == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(*flushed.lock().unwrap(), [vec![("user1:book1", 2)]]);
+    }
+}
@@ -1,13 +1,577 @@
-/// Trait for loading service configuration from environment variables.
+//! Typed service settings, validated in one pass at startup.
+//!
+//! Settings are read from three layers, later ones winning:
+//!
+//! 1. `.env` in the working directory, if present;
+//! 2. the file named by `MADOME_CONFIG_FILE` (same `KEY=value` format), if set;
+//! 3. the process environment.
+//!
+//! Field `database_url` is read from `DATABASE_URL` (keys are matched
+//! case-insensitively). Every missing or unparsable key is collected before
+//! failing, so one startup attempt reports all of them:
+//!
+//! ```text
+//! invalid configuration:
+//!   DATABASE_URL: missing
+//!   AUTH_PORT: invalid digit found in string
+//! ```
+//!
+//! Wrap credentials in [`Secret`] so they never reach logs through `Debug`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::path::Path;
+
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use serde::{Deserialize, forward_to_deserialize_any};
+
+/// Env var naming an extra settings file layered between `.env` and the environment.
+pub const CONFIG_FILE_VAR: &str = "MADOME_CONFIG_FILE";
+
+/// Trait for loading service configuration.
 ///
-/// Implementors should derive `serde::Deserialize` and then call
-/// `Config::from_env()` to load configuration at startup.
+/// Implementors derive `serde::Deserialize` and call `Config::from_env()` at
+/// startup (or [`Config::load`] to handle the errors themselves).
+pub trait Config: Sized + DeserializeOwned {
+    /// Load from the layered sources, reporting every problem at once.
+    fn load() -> Result<Self, ConfigErrors> {
+        let config: Self = from_vars(layered_vars()?)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks that span settings or go beyond parsing one, run by the loaders
+    /// once every key has deserialized. Return all problems found.
+    fn validate(&self) -> Result<(), ConfigErrors> {
+        Ok(())
+    }
+
+    /// # Panics
+    ///
+    /// Panics with the full list of missing/invalid settings if loading fails.
+    fn from_env() -> Self {
+        Self::load().unwrap_or_else(|e| panic!("{e}"))
+    }
+}
+
+/// One problem found while loading settings.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ConfigError {
+    #[error("{0}: missing")]
+    Missing(String),
+    #[error("{key}: {message}")]
+    Invalid { key: String, message: String },
+    #[error("{path}: {message}")]
+    Source { path: String, message: String },
+    #[error("{0}")]
+    Other(String),
+}
+
+/// Every problem found while loading settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigErrors(pub Vec<ConfigError>);
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid configuration:")?;
+        for error in &self.0 {
+            write!(f, "\n  {error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+/// A setting whose value is redacted from `Debug` output.
 ///
-/// # Panics
+/// ```
+/// use madome_core::config::Secret;
 ///
-/// Panics if any required env var is missing or cannot be deserialized.
-pub trait Config: Sized + serde::de::DeserializeOwned {
-    fn from_env() -> Self {
-        envy::from_env().expect("failed to load config from environment")
+/// let secret = Secret::new("hunter2".to_owned());
+/// assert_eq!(format!("{secret:?}"), "Secret(***)");
+/// assert_eq!(secret.expose(), "hunter2");
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+/// Merge `.env`, the `MADOME_CONFIG_FILE` file and the process environment.
+pub fn layered_vars() -> Result<HashMap<String, String>, ConfigErrors> {
+    let mut vars = HashMap::new();
+    let mut errors = Vec::new();
+
+    if Path::new(".env").exists() {
+        read_file(".env", &mut vars, &mut errors);
+    }
+    let config_file = std::env::var(CONFIG_FILE_VAR)
+        .ok()
+        .or_else(|| vars.get(CONFIG_FILE_VAR).cloned());
+    if let Some(path) = config_file {
+        read_file(&path, &mut vars, &mut errors);
+    }
+    vars.extend(std::env::vars());
+
+    if errors.is_empty() {
+        Ok(vars)
+    } else {
+        Err(ConfigErrors(errors))
+    }
+}
+
+fn read_file(path: &str, vars: &mut HashMap<String, String>, errors: &mut Vec<ConfigError>) {
+    let source_error = |e: dotenvy::Error| ConfigError::Source {
+        path: path.to_owned(),
+        message: e.to_string(),
+    };
+    match dotenvy::from_path_iter(path) {
+        Ok(iter) => {
+            for item in iter {
+                match item {
+                    Ok((key, value)) => {
+                        vars.insert(key, value);
+                    }
+                    Err(e) => errors.push(source_error(e)),
+                }
+            }
+        }
+        Err(e) => errors.push(source_error(e)),
+    }
+}
+
+/// Deserialize `T` from `vars`, collecting every missing or invalid key.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use madome_core::config::{ConfigError, from_vars};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Settings {
+///     database_url: String,
+///     port: u16,
+/// }
+///
+/// let vars = HashMap::from([("PORT".to_owned(), "http".to_owned())]);
+/// let errors = from_vars::<Settings>(vars).err().unwrap();
+/// assert_eq!(errors.0.len(), 2);
+/// assert!(errors.0.contains(&ConfigError::Missing("DATABASE_URL".to_owned())));
+/// assert!(errors.0.iter().any(|e| matches!(e, ConfigError::Invalid { key, .. } if key == "PORT")));
+/// ```
+pub fn from_vars<T: DeserializeOwned>(vars: HashMap<String, String>) -> Result<T, ConfigErrors> {
+    let vars: BTreeMap<String, String> = vars
+        .into_iter()
+        .map(|(k, v)| (k.to_ascii_lowercase(), v))
+        .collect();
+    // Keys already reported; later passes feed them a type-appropriate dummy
+    // value so deserialization can get past them and find the next problem.
+    let mut placeholders = BTreeSet::new();
+    let mut errors = Vec::new();
+
+    loop {
+        let key = match T::deserialize(Vars {
+            vars: &vars,
+            placeholders: &placeholders,
+        }) {
+            Ok(value) if errors.is_empty() => return Ok(value),
+            Ok(_) => return Err(ConfigErrors(errors)),
+            Err(LoadError::Missing(field)) => {
+                errors.push(ConfigError::Missing(field.to_ascii_uppercase()));
+                field.to_owned()
+            }
+            Err(LoadError::Invalid { key, message }) => {
+                errors.push(ConfigError::Invalid {
+                    key: key.to_ascii_uppercase(),
+                    message,
+                });
+                key
+            }
+            Err(LoadError::Other(message)) => {
+                errors.push(ConfigError::Other(message));
+                return Err(ConfigErrors(errors));
+            }
+        };
+        if !placeholders.insert(key) {
+            return Err(ConfigErrors(errors));
+        }
+    }
+}
+
+#[derive(Debug)]
+enum LoadError {
+    Missing(&'static str),
+    Invalid { key: String, message: String },
+    Other(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(field) => write!(f, "missing field `{field}`"),
+            Self::Invalid { message, .. } | Self::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl de::Error for LoadError {
+    fn custom<M: fmt::Display>(msg: M) -> Self {
+        Self::Other(msg.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Self::Missing(field)
+    }
+}
+
+/// The whole settings map, seen as a struct.
+struct Vars<'a> {
+    vars: &'a BTreeMap<String, String>,
+    placeholders: &'a BTreeSet<String>,
+}
+
+impl<'de> de::Deserializer<'de> for Vars<'_> {
+    type Error = LoadError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, LoadError> {
+        let present = self
+            .vars
+            .iter()
+            .filter(|(k, _)| !self.placeholders.contains(*k))
+            .map(|(k, v)| (k.as_str(), Some(v.as_str())));
+        let dummies = self.placeholders.iter().map(|k| (k.as_str(), None));
+        visitor.visit_map(VarsAccess {
+            entries: present.chain(dummies).collect::<Vec<_>>().into_iter(),
+            key: "",
+            value: None,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map struct
+        enum identifier ignored_any
+    }
+}
+
+struct VarsAccess<'a> {
+    entries: std::vec::IntoIter<(&'a str, Option<&'a str>)>,
+    key: &'a str,
+    value: Option<&'a str>,
+}
+
+impl<'de> de::MapAccess<'de> for VarsAccess<'_> {
+    type Error = LoadError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, LoadError> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        self.key = key;
+        self.value = value;
+        seed.deserialize(key.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, LoadError> {
+        match self.value {
+            Some(value) => seed
+                .deserialize(Value(value))
+                .map_err(|e| LoadError::Invalid {
+                    key: self.key.to_owned(),
+                    message: e.to_string(),
+                }),
+            None => seed.deserialize(Placeholder),
+        }
+    }
+}
+
+/// One setting's string value, parsed into whatever the field needs.
+struct Value<'a>(&'a str);
+
+macro_rules! parse_value {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, LoadError> {
+                let parsed = self.0.trim().parse().map_err(de::Error::custom)?;
+                visitor.$visit(parsed)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Value<'_> {
+    type Error = LoadError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, LoadError> {
+        visitor.visit_str(self.0)
+    }
+
+    parse_value! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, LoadError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, LoadError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Comma-separated list.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, LoadError> {
+        let items = self.0.split(',').map(str::trim).filter(|s| !s.is_empty());
+        visitor.visit_seq(SeqDeserializer::new(items.map(Value)))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, LoadError> {
+        visitor.visit_enum(self.0.trim().into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, LoadError> for Value<'_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Stand-in for a setting already reported as missing or invalid.
+struct Placeholder;
+
+macro_rules! zero_value {
+    ($($method:ident => $visit:ident($zero:expr),)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, LoadError> {
+                visitor.$visit($zero)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Placeholder {
+    type Error = LoadError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, LoadError> {
+        visitor.visit_unit()
+    }
+
+    zero_value! {
+        deserialize_bool => visit_bool(false),
+        deserialize_i8 => visit_i8(0),
+        deserialize_i16 => visit_i16(0),
+        deserialize_i32 => visit_i32(0),
+        deserialize_i64 => visit_i64(0),
+        deserialize_u8 => visit_u8(0),
+        deserialize_u16 => visit_u16(0),
+        deserialize_u32 => visit_u32(0),
+        deserialize_u64 => visit_u64(0),
+        deserialize_f32 => visit_f32(0.0),
+        deserialize_f64 => visit_f64(0.0),
+        deserialize_char => visit_char(' '),
+        deserialize_str => visit_str(""),
+        deserialize_string => visit_str(""),
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, LoadError> {
+        visitor.visit_none()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, LoadError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, LoadError> {
+        visitor.visit_seq(SeqDeserializer::new(std::iter::empty::<Value>()))
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, LoadError> {
+        visitor.visit_map(MapDeserializer::new(std::iter::empty::<(Value, Value)>()))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, LoadError> {
+        let first = variants.first().copied().unwrap_or_default();
+        visitor.visit_enum(first.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        bytes byte_buf unit unit_struct tuple tuple_struct struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Settings {
+        database_url: Secret<String>,
+        jwt_secret: Secret<String>,
+        #[serde(default = "default_port")]
+        port: u16,
+        #[serde(default)]
+        mode: Mode,
+        #[serde(default)]
+        replica_url: Option<String>,
+        #[serde(default)]
+        origins: Vec<String>,
+    }
+
+    #[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Mode {
+        #[default]
+        Production,
+        Development,
+    }
+
+    fn default_port() -> u16 {
+        3112
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| ((*k).to_owned(), (*v).to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn should_load_typed_settings_with_defaults() {
+        let settings: Settings = from_vars(vars(&[
+            ("DATABASE_URL", "postgres://localhost/db"),
+            ("JWT_SECRET", "secret"),
+            ("MODE", "development"),
+            ("ORIGINS", "https://a.example.com, https://b.example.com"),
+            ("PATH", "/usr/bin"),
+        ]))
+        .unwrap();
+
+        assert_eq!(settings.database_url.expose(), "postgres://localhost/db");
+        assert_eq!(settings.jwt_secret.expose(), "secret");
+        assert_eq!(settings.port, 3112);
+        assert_eq!(settings.mode, Mode::Development);
+        assert_eq!(settings.replica_url, None);
+        assert_eq!(settings.origins.len(), 2);
+    }
+
+    #[test]
+    fn should_report_every_missing_and_invalid_setting() {
+        let errors = from_vars::<Settings>(vars(&[("PORT", "http"), ("MODE", "staging")]))
+            .err()
+            .unwrap();
+
+        let keys: BTreeSet<String> = errors
+            .0
+            .iter()
+            .map(|e| match e {
+                ConfigError::Missing(key) => format!("missing {key}"),
+                ConfigError::Invalid { key, .. } => format!("invalid {key}"),
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(
+            keys,
+            BTreeSet::from(
+                [
+                    "missing DATABASE_URL",
+                    "missing JWT_SECRET",
+                    "invalid PORT",
+                    "invalid MODE"
+                ]
+                .map(str::to_owned)
+            )
+        );
+    }
+
+    #[test]
+    fn should_list_errors_one_per_line() {
+        let errors = ConfigErrors(vec![
+            ConfigError::Missing("DATABASE_URL".to_owned()),
+            ConfigError::Invalid {
+                key: "PORT".to_owned(),
+                message: "invalid digit found in string".to_owned(),
+            },
+        ]);
+
+        assert_eq!(
+            errors.to_string(),
+            "invalid configuration:\n  DATABASE_URL: missing\n  PORT: invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn should_redact_secrets_in_debug() {
+        let settings: Settings = from_vars(vars(&[
+            ("DATABASE_URL", "postgres://user:pw@localhost/db"),
+            ("JWT_SECRET", "secret"),
+        ]))
+        .unwrap();
+
+        let debug = format!("{settings:?}");
+        assert!(!debug.contains("pw@"));
+        assert!(!debug.contains("\"secret\""));
     }
 }
@@ -0,0 +1,182 @@
+//! CORS for frontends that call a service directly instead of through the
+//! gateway.
+//!
+//! Only configured origins are allowed, with credentials so the token cookies
+//! are sent along. With no origins configured no CORS headers are sent at all,
+//! and browsers keep cross-origin scripts out as before.
+//!
+//! ```
+//! use axum::{Router, routing::get};
+//! use madome_core::cors::cors_layer;
+//!
+//! let origins = vec!["https://example.com".to_owned()];
+//! let mut app: Router = Router::new().route("/", get(|| async {}));
+//! if let Some(cors) = cors_layer(&origins).unwrap() {
+//!     app = app.layer(cors);
+//! }
+//! ```
+
+use std::time::Duration;
+
+use axum::http::header::{ACCEPT, CONTENT_TYPE};
+use axum::http::{HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::csrf::CSRF_HEADER;
+use crate::idempotency::IDEMPOTENCY_KEY_HEADER;
+use crate::middleware::REQUEST_ID_HEADER;
+
+/// Response headers a cross-origin script may read.
+pub const EXPOSED_HEADERS: [&str; 10] = [
+    "ratelimit-limit",
+    "ratelimit-remaining",
+    "ratelimit-reset",
+    "retry-after",
+    "x-madome-access-token-expires",
+    "x-madome-next-cursor",
+    "x-madome-passkey-authentication-id",
+    "x-madome-passkey-registration-id",
+    "x-madome-total-count",
+    "x-madome-trace-id",
+];
+
+/// How long browsers may cache a preflight response (10 minutes).
+pub const PREFLIGHT_MAX_AGE: Duration = Duration::from_secs(600);
+
+/// Invalid entry in the allowed origins.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid CORS origin {0:?}: expected scheme://host[:port] without a path")]
+pub struct InvalidOrigin(pub String);
+
+/// Build the CORS layer for `allowed_origins`, or `None` if the list is empty.
+///
+/// Origins must be exact (`https://example.com`): browsers send no path or
+/// trailing slash, and a wildcard cannot be combined with credentials.
+pub fn cors_layer(allowed_origins: &[String]) -> Result<Option<CorsLayer>, InvalidOrigin> {
+    if allowed_origins.is_empty() {
+        return Ok(None);
+    }
+    let origins = allowed_origins
+        .iter()
+        .map(|origin| parse_origin(origin))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let layer = CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_credentials(true)
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        .allow_headers([
+            ACCEPT,
+            CONTENT_TYPE,
+            HeaderName::from_static(CSRF_HEADER),
+            HeaderName::from_static(IDEMPOTENCY_KEY_HEADER),
+            REQUEST_ID_HEADER,
+        ])
+        .expose_headers(EXPOSED_HEADERS.map(HeaderName::from_static))
+        .max_age(PREFLIGHT_MAX_AGE);
+    Ok(Some(layer))
+}
+
+fn parse_origin(origin: &str) -> Result<HeaderValue, InvalidOrigin> {
+    let invalid = || InvalidOrigin(origin.to_owned());
+    let rest = origin
+        .strip_prefix("https://")
+        .or_else(|| origin.strip_prefix("http://"))
+        .ok_or_else(invalid)?;
+    if rest.is_empty() || rest.contains(['/', '*', '?', '#']) {
+        return Err(invalid());
+    }
+    HeaderValue::from_str(origin).map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::header::{
+        ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_ORIGIN,
+        ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_REQUEST_HEADERS,
+        ACCESS_CONTROL_REQUEST_METHOD, ORIGIN,
+    };
+    use axum::http::{Request, StatusCode};
+    use axum::routing::{get, patch};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        let origins = vec!["https://example.com".to_owned()];
+        Router::new()
+            .route("/token", get(|| async {}).merge(patch(|| async {})))
+            .layer(cors_layer(&origins).unwrap().unwrap())
+    }
+
+    #[test]
+    fn should_disable_cors_without_origins() {
+        assert!(cors_layer(&[]).unwrap().is_none());
+    }
+
+    #[test]
+    fn should_reject_wildcard_path_and_bare_host() {
+        for origin in [
+            "*",
+            "https://*.example.com",
+            "https://example.com/",
+            "example.com",
+        ] {
+            assert_eq!(
+                cors_layer(&[origin.to_owned()]).unwrap_err(),
+                InvalidOrigin(origin.to_owned())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn should_allow_listed_origin_with_credentials() {
+        let req = Request::get("/token")
+            .header(ORIGIN, "https://example.com")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().oneshot(req).await.unwrap();
+        let headers = response.headers();
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "https://example.com");
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert!(
+            headers[ACCESS_CONTROL_EXPOSE_HEADERS]
+                .to_str()
+                .unwrap()
+                .contains("x-madome-trace-id")
+        );
+    }
+
+    #[tokio::test]
+    async fn should_not_allow_other_origin() {
+        let req = Request::get("/token")
+            .header(ORIGIN, "https://evil.example.com")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().oneshot(req).await.unwrap();
+        assert!(!response.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn should_answer_preflight_with_csrf_header() {
+        let req = Request::options("/token")
+            .header(ORIGIN, "https://example.com")
+            .header(ACCESS_CONTROL_REQUEST_METHOD, "PATCH")
+            .header(ACCESS_CONTROL_REQUEST_HEADERS, CSRF_HEADER)
+            .body(Body::empty())
+            .unwrap();
+        let response = app().oneshot(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://example.com"
+        );
+    }
+}
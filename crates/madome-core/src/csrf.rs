@@ -0,0 +1,175 @@
+//! Double-submit CSRF protection for cookie-authenticated routes.
+//!
+//! The client fetches a token (`GET /auth/csrf`), which is also set as a
+//! script-readable cookie, and echoes it in the `x-madome-csrf` header on every
+//! state-changing request. A cross-site form post carries the cookie but cannot
+//! read it, so the header is missing or wrong and the request is rejected.
+//!
+//! Enforcement is per route: wrap only the routes that need it.
+//!
+//! ```
+//! use axum::{Router, middleware, routing::delete};
+//! use madome_core::csrf::require_csrf;
+//!
+//! async fn delete_thing() {}
+//!
+//! let protected: Router = Router::new()
+//!     .route("/things/{id}", delete(delete_thing))
+//!     .route_layer(middleware::from_fn(require_csrf));
+//! ```
+
+use axum::extract::Request;
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum_extra::extract::cookie::{Cookie, CookieJar};
+use madome_auth_types::cookie::CookieConfig;
+use subtle::ConstantTimeEq;
+use time::Duration;
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+/// Cookie carrying the CSRF token. Not `HttpOnly`: the client must read it.
+pub const CSRF_COOKIE: &str = "madome_csrf";
+
+/// Request header the client echoes the token in.
+pub const CSRF_HEADER: &str = "x-madome-csrf";
+
+/// CSRF cookie Max-Age in seconds (7 days, same as the token cookies).
+pub const CSRF_TOKEN_EXP: i64 = 604800;
+
+/// Generate a fresh CSRF token (64 hex chars from two v4 UUIDs).
+///
+/// ```
+/// let token = madome_core::csrf::generate_csrf_token();
+/// assert_eq!(token.len(), 64);
+/// assert_ne!(token, madome_core::csrf::generate_csrf_token());
+/// ```
+pub fn generate_csrf_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Set the CSRF cookie on the jar, on `/` with the attributes of `config`.
+///
+/// ```
+/// use axum_extra::extract::cookie::CookieJar;
+/// use madome_auth_types::cookie::CookieConfig;
+/// use madome_core::csrf::{set_csrf_cookie, CSRF_COOKIE};
+///
+/// let config = CookieConfig::new("example.com");
+/// let jar = set_csrf_cookie(CookieJar::new(), "token".to_string(), &config);
+/// let cookie = jar.get(CSRF_COOKIE).unwrap();
+/// assert_eq!(cookie.path(), Some("/"));
+/// assert_eq!(cookie.domain(), Some("example.com"));
+/// assert_eq!(cookie.max_age(), Some(time::Duration::seconds(604800)));
+/// assert!(!cookie.http_only().unwrap_or(false));
+/// assert!(cookie.secure().unwrap_or(false));
+/// ```
+pub fn set_csrf_cookie(jar: CookieJar, value: String, config: &CookieConfig) -> CookieJar {
+    let mut cookie = config.cookie(
+        CSRF_COOKIE,
+        value,
+        "/",
+        Some(Duration::seconds(CSRF_TOKEN_EXP)),
+    );
+    cookie.set_http_only(false);
+    jar.add(cookie)
+}
+
+/// Middleware rejecting state-changing requests whose `x-madome-csrf` header
+/// does not match the `madome_csrf` cookie (403). Safe methods pass through.
+///
+/// Apply with `.route_layer(axum::middleware::from_fn(require_csrf))`.
+pub async fn require_csrf(req: Request, next: Next) -> Response {
+    if is_safe_method(req.method()) {
+        return next.run(req).await;
+    }
+
+    let jar = CookieJar::from_headers(req.headers());
+    let cookie = jar.get(CSRF_COOKIE).map(Cookie::value);
+    let header = req.headers().get(CSRF_HEADER).and_then(|v| v.to_str().ok());
+
+    match (cookie, header) {
+        (Some(cookie), Some(header))
+            if !cookie.is_empty() && bool::from(cookie.as_bytes().ct_eq(header.as_bytes())) =>
+        {
+            next.run(req).await
+        }
+        _ => AppError::Forbidden.into_response(),
+    }
+}
+
+fn is_safe_method(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::{Request as HttpRequest, StatusCode, header::COOKIE};
+    use axum::middleware;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/", get(|| async {}).post(|| async {}).delete(|| async {}))
+            .route_layer(middleware::from_fn(require_csrf))
+    }
+
+    async fn send(method: Method, cookie: Option<&str>, header: Option<&str>) -> StatusCode {
+        let mut req = HttpRequest::builder().method(method).uri("/");
+        if let Some(cookie) = cookie {
+            req = req.header(COOKIE, format!("{CSRF_COOKIE}={cookie}"));
+        }
+        if let Some(header) = header {
+            req = req.header(CSRF_HEADER, header);
+        }
+        app()
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn should_pass_safe_method_without_token() {
+        assert_eq!(send(Method::GET, None, None).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_pass_mutation_with_matching_token() {
+        let status = send(Method::POST, Some("abc"), Some("abc")).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_reject_mutation_without_header() {
+        let status = send(Method::DELETE, Some("abc"), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn should_reject_mutation_without_cookie() {
+        let status = send(Method::POST, None, Some("abc")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn should_reject_mutation_with_mismatched_token() {
+        let status = send(Method::POST, Some("abc"), Some("abd")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn should_reject_mutation_with_empty_token() {
+        let status = send(Method::POST, Some(""), Some("")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+}
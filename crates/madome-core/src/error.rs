@@ -1,6 +1,7 @@
-use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 
+use crate::error_catalog::{ErrorBody, ErrorKind};
+
 /// Common application error variants.
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
@@ -16,22 +17,28 @@ pub enum AppError {
     Internal(#[from] anyhow::Error),
 }
 
+impl AppError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            AppError::Unauthorized => ErrorKind::Unauthorized,
+            AppError::Forbidden => ErrorKind::Forbidden,
+            AppError::NotFound => ErrorKind::NotFound,
+            AppError::Conflict => ErrorKind::Conflict,
+            AppError::Internal(_) => ErrorKind::Internal,
+        }
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let status = match &self {
-            AppError::Unauthorized => StatusCode::UNAUTHORIZED,
-            AppError::Forbidden => StatusCode::FORBIDDEN,
-            AppError::NotFound => StatusCode::NOT_FOUND,
-            AppError::Conflict => StatusCode::CONFLICT,
-            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
-        };
-        (status, self.to_string()).into_response()
+        ErrorBody::new(self.kind(), self.to_string()).into_response()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axum::http::StatusCode;
     use axum::response::IntoResponse;
 
     #[test]
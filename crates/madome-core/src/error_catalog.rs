@@ -0,0 +1,148 @@
+//! Error codes shared by every service, and the JSON body errors are sent in.
+//!
+//! Service error enums map each variant to an [`ErrorKind`] and build an
+//! [`ErrorBody`] from it, so clients match on `kind` instead of on status codes
+//! or message text:
+//!
+//! ```json
+//! { "kind": "not_found", "message": "not found" }
+//! ```
+
+use axum::Json;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::validation::FieldError;
+
+/// Machine-readable error code. The wire value (`kind`) is never renamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    Conflict,
+    Validation,
+    TooManyRequests,
+    Internal,
+}
+
+impl ErrorKind {
+    /// Every registered kind, in status order.
+    pub const ALL: [ErrorKind; 8] = [
+        Self::BadRequest,
+        Self::Unauthorized,
+        Self::Forbidden,
+        Self::NotFound,
+        Self::Conflict,
+        Self::Validation,
+        Self::TooManyRequests,
+        Self::Internal,
+    ];
+
+    pub fn status(self) -> StatusCode {
+        match self {
+            Self::BadRequest => StatusCode::BAD_REQUEST,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::Conflict => StatusCode::CONFLICT,
+            Self::Validation => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Wire value, e.g. `"not_found"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::BadRequest => "bad_request",
+            Self::Unauthorized => "unauthorized",
+            Self::Forbidden => "forbidden",
+            Self::NotFound => "not_found",
+            Self::Conflict => "conflict",
+            Self::Validation => "validation",
+            Self::TooManyRequests => "too_many_requests",
+            Self::Internal => "internal",
+        }
+    }
+}
+
+/// JSON body of every error response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErrorBody {
+    pub kind: ErrorKind,
+    /// Human-readable; never parsed by clients.
+    pub message: String,
+    /// Request id for log lookup, when known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    /// Field errors for [`ErrorKind::Validation`], and for [`ErrorKind::BadRequest`]
+    /// when the query string does not parse.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl ErrorBody {
+    /// ```
+    /// use madome_core::error_catalog::{ErrorBody, ErrorKind};
+    ///
+    /// let body = ErrorBody::new(ErrorKind::NotFound, "not found");
+    /// assert_eq!(
+    ///     serde_json::to_string(&body).unwrap(),
+    ///     r#"{"kind":"not_found","message":"not found"}"#
+    /// );
+    /// ```
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            trace_id: None,
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl IntoResponse for ErrorBody {
+    /// The body is also left in the response extensions so the trace-id
+    /// middleware can fill in `trace_id` after the handler returns.
+    fn into_response(self) -> Response {
+        let mut response = (self.kind.status(), Json(&self)).into_response();
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn should_serialize_kind_as_its_wire_value() {
+        for kind in ErrorKind::ALL {
+            let json = serde_json::to_string(&kind).unwrap();
+            assert_eq!(json, format!("\"{}\"", kind.as_str()));
+        }
+    }
+
+    #[test]
+    fn should_map_every_kind_to_a_distinct_status() {
+        let statuses: HashSet<_> = ErrorKind::ALL.iter().map(|k| k.status()).collect();
+        assert_eq!(statuses.len(), ErrorKind::ALL.len());
+    }
+
+    #[test]
+    fn should_respond_with_kind_status_and_json_body() {
+        let response = ErrorBody::new(ErrorKind::Conflict, "conflict").into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(
+            response.headers()[axum::http::header::CONTENT_TYPE],
+            "application/json"
+        );
+    }
+}
@@ -0,0 +1,292 @@
+//! Asynchronous domain events between services (`user.deleted`,
+//! `book.renewed`, ...).
+//!
+//! Delivery is at-least-once: a handler may see the same event again after a
+//! crash or a failed attempt, so handlers must be idempotent (typically a
+//! dedup table keyed by [`Delivery::id`]). An event that keeps failing is moved
+//! to a dead-letter stream after [`DeliveryPolicy::max_deliveries`] attempts
+//! instead of blocking the stream.
+//!
+//! [`Publisher`] and [`Consumer`] are ports; the Redis Streams implementation
+//! ([`RedisStreams`], [`RedisStreamConsumer`]) is behind the `redis` feature.
+//!
+//! ```ignore
+//! // Producer (infra layer)
+//! events::publish(&streams, &BookRenewed { old_book_id, new_book_id }).await?;
+//!
+//! // Consumer (service main)
+//! let consumer = RedisStreamConsumer::new(conn, BookRenewed::STREAM, "users", &hostname);
+//! consumer.ensure_group().await?;
+//! tokio::spawn(events::run(consumer, handler, DeliveryPolicy::default()));
+//! ```
+
+use std::future::Future;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+#[cfg(feature = "redis")]
+mod redis_streams;
+#[cfg(feature = "redis")]
+pub use redis_streams::{RedisStreamConsumer, RedisStreams};
+
+/// A typed event and the stream it is published to.
+pub trait Event: Serialize + DeserializeOwned {
+    /// Stream name, e.g. `"book.renewed"`.
+    const STREAM: &'static str;
+}
+
+/// Appends events to a stream.
+pub trait Publisher: Clone + Send + Sync + 'static {
+    /// Append `payload` to `stream`, returning the assigned event id.
+    fn publish(
+        &self,
+        stream: &str,
+        payload: Vec<u8>,
+    ) -> impl Future<Output = anyhow::Result<String>> + Send;
+}
+
+/// Serialize `event` as JSON and publish it to its stream.
+pub async fn publish<P: Publisher, E: Event>(publisher: &P, event: &E) -> anyhow::Result<String> {
+    publisher
+        .publish(E::STREAM, serde_json::to_vec(event)?)
+        .await
+}
+
+/// One delivery of an event to a consumer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delivery {
+    /// Event id; stable across redeliveries.
+    pub id: String,
+    pub payload: Vec<u8>,
+    /// How many times this event has been delivered, this one included.
+    pub deliveries: u64,
+}
+
+impl Delivery {
+    pub fn decode<E: Event>(&self) -> anyhow::Result<E> {
+        Ok(serde_json::from_slice(&self.payload)?)
+    }
+}
+
+/// Reads one stream as a member of a consumer group.
+pub trait Consumer: Send + Sync + 'static {
+    /// Next deliveries, waiting briefly when none are available. Unacked
+    /// deliveries come back here once they have been idle long enough.
+    fn fetch(&self, max: usize) -> impl Future<Output = anyhow::Result<Vec<Delivery>>> + Send;
+
+    /// Mark a delivery as processed.
+    fn ack(&self, delivery: &Delivery) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    /// Move a delivery to the dead-letter stream and ack it.
+    fn dead_letter(
+        &self,
+        delivery: &Delivery,
+        error: &str,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+}
+
+/// Processes deliveries of one stream.
+pub trait EventHandler: Send + Sync + 'static {
+    fn handle(&self, delivery: &Delivery) -> impl Future<Output = anyhow::Result<()>> + Send;
+}
+
+/// Retry limits for [`run`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeliveryPolicy {
+    /// Deliveries fetched per round.
+    pub batch_size: usize,
+    /// Failed attempts before an event is dead-lettered.
+    pub max_deliveries: u64,
+    /// Pause after the consumer itself fails (e.g. Redis unreachable).
+    pub backoff: Duration,
+}
+
+impl Default for DeliveryPolicy {
+    fn default() -> Self {
+        Self {
+            batch_size: 16,
+            max_deliveries: 5,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Fetch one batch and run `handler` on each delivery.
+///
+/// Success acks the delivery. A failure leaves it pending for redelivery, or
+/// dead-letters it once it has been delivered `max_deliveries` times. Returns
+/// the number of deliveries fetched.
+pub async fn process_batch<C: Consumer, H: EventHandler>(
+    consumer: &C,
+    handler: &H,
+    policy: &DeliveryPolicy,
+) -> anyhow::Result<usize> {
+    let deliveries = consumer.fetch(policy.batch_size).await?;
+    for delivery in &deliveries {
+        match handler.handle(delivery).await {
+            Ok(()) => consumer.ack(delivery).await?,
+            Err(e) if delivery.deliveries >= policy.max_deliveries => {
+                tracing::error!(id = %delivery.id, error = %format!("{e:#}"), "event dead-lettered");
+                consumer.dead_letter(delivery, &format!("{e:#}")).await?;
+            }
+            Err(e) => {
+                tracing::warn!(id = %delivery.id, attempt = delivery.deliveries, error = %format!("{e:#}"), "event handler failed; will retry");
+            }
+        }
+    }
+    Ok(deliveries.len())
+}
+
+/// Consume forever. Spawn once per stream at startup.
+pub async fn run<C: Consumer, H: EventHandler>(consumer: C, handler: H, policy: DeliveryPolicy) {
+    loop {
+        if let Err(e) = process_batch(&consumer, &handler, &policy).await {
+            tracing::error!(error = %format!("{e:#}"), "event consumer failed");
+            tokio::time::sleep(policy.backoff).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct BookRenewed {
+        old_book_id: u32,
+        new_book_id: u32,
+    }
+
+    impl Event for BookRenewed {
+        const STREAM: &'static str = "book.renewed";
+    }
+
+    /// Single-stream bus: unacked deliveries are redelivered on the next fetch.
+    #[derive(Clone, Default)]
+    struct MemoryBus {
+        inner: Arc<Mutex<MemoryState>>,
+    }
+
+    #[derive(Default)]
+    struct MemoryState {
+        next_id: u64,
+        queue: VecDeque<Delivery>,
+        in_flight: Vec<Delivery>,
+        acked: Vec<String>,
+        dead: Vec<(String, String)>,
+    }
+
+    impl Publisher for MemoryBus {
+        async fn publish(&self, _stream: &str, payload: Vec<u8>) -> anyhow::Result<String> {
+            let mut state = self.inner.lock().unwrap();
+            state.next_id += 1;
+            let id = format!("{}-0", state.next_id);
+            state.queue.push_back(Delivery {
+                id: id.clone(),
+                payload,
+                deliveries: 0,
+            });
+            Ok(id)
+        }
+    }
+
+    impl Consumer for MemoryBus {
+        async fn fetch(&self, max: usize) -> anyhow::Result<Vec<Delivery>> {
+            let mut state = self.inner.lock().unwrap();
+            let retry = std::mem::take(&mut state.in_flight);
+            state.queue.extend(retry);
+            let count = max.min(state.queue.len());
+            let batch: Vec<_> = state
+                .queue
+                .drain(..count)
+                .map(|d| Delivery {
+                    deliveries: d.deliveries + 1,
+                    ..d
+                })
+                .collect();
+            state.in_flight.extend(batch.iter().cloned());
+            Ok(batch)
+        }
+
+        async fn ack(&self, delivery: &Delivery) -> anyhow::Result<()> {
+            let mut state = self.inner.lock().unwrap();
+            state.in_flight.retain(|d| d.id != delivery.id);
+            state.acked.push(delivery.id.clone());
+            Ok(())
+        }
+
+        async fn dead_letter(&self, delivery: &Delivery, error: &str) -> anyhow::Result<()> {
+            let mut state = self.inner.lock().unwrap();
+            state.in_flight.retain(|d| d.id != delivery.id);
+            state.dead.push((delivery.id.clone(), error.to_owned()));
+            Ok(())
+        }
+    }
+
+    /// Fails for `new_book_id == 0`, records the rest.
+    #[derive(Default)]
+    struct Recorder {
+        seen: Mutex<Vec<BookRenewed>>,
+    }
+
+    impl EventHandler for Recorder {
+        async fn handle(&self, delivery: &Delivery) -> anyhow::Result<()> {
+            let event: BookRenewed = delivery.decode()?;
+            anyhow::ensure!(event.new_book_id != 0, "book 0 does not exist");
+            self.seen.lock().unwrap().push(event);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn should_ack_handled_events() {
+        let bus = MemoryBus::default();
+        let event = BookRenewed {
+            old_book_id: 1,
+            new_book_id: 2,
+        };
+        let id = publish(&bus, &event).await.unwrap();
+        let handler = Recorder::default();
+
+        let fetched = process_batch(&bus, &handler, &DeliveryPolicy::default())
+            .await
+            .unwrap();
+
+        assert_eq!(fetched, 1);
+        assert_eq!(*handler.seen.lock().unwrap(), [event]);
+        assert_eq!(bus.inner.lock().unwrap().acked, [id]);
+    }
+
+    #[tokio::test]
+    async fn should_redeliver_failed_event_then_dead_letter_it() {
+        let bus = MemoryBus::default();
+        let bad = BookRenewed {
+            old_book_id: 1,
+            new_book_id: 0,
+        };
+        let id = publish(&bus, &bad).await.unwrap();
+        let handler = Recorder::default();
+        let policy = DeliveryPolicy {
+            max_deliveries: 3,
+            ..DeliveryPolicy::default()
+        };
+
+        for _ in 0..2 {
+            process_batch(&bus, &handler, &policy).await.unwrap();
+            assert!(bus.inner.lock().unwrap().dead.is_empty());
+        }
+        process_batch(&bus, &handler, &policy).await.unwrap();
+
+        let state = bus.inner.lock().unwrap();
+        assert_eq!(state.dead, [(id, "book 0 does not exist".to_owned())]);
+        assert!(state.in_flight.is_empty() && state.queue.is_empty());
+        assert!(state.acked.is_empty());
+    }
+}
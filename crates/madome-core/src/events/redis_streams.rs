@@ -0,0 +1,204 @@
+use std::time::Duration;
+
+use redis::aio::MultiplexedConnection;
+use redis::streams::{StreamAutoClaimReply, StreamId, StreamPendingCountReply, StreamReadReply};
+
+use super::{Consumer, Delivery, Publisher};
+
+/// Entry field holding the JSON payload.
+const PAYLOAD_FIELD: &str = "payload";
+
+/// Publishes to Redis Streams with `XADD`, trimming each stream to roughly
+/// `max_len` entries.
+#[derive(Clone)]
+pub struct RedisStreams {
+    conn: MultiplexedConnection,
+    max_len: usize,
+}
+
+impl RedisStreams {
+    pub fn new(conn: MultiplexedConnection) -> Self {
+        Self {
+            conn,
+            max_len: 100_000,
+        }
+    }
+
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+}
+
+impl Publisher for RedisStreams {
+    async fn publish(&self, stream: &str, payload: Vec<u8>) -> anyhow::Result<String> {
+        let id: String = redis::cmd("XADD")
+            .arg(stream)
+            .arg("MAXLEN")
+            .arg("~")
+            .arg(self.max_len)
+            .arg("*")
+            .arg(PAYLOAD_FIELD)
+            .arg(payload)
+            .query_async(&mut self.conn.clone())
+            .await?;
+        Ok(id)
+    }
+}
+
+/// Consumer-group reader for one stream.
+///
+/// New entries are read with `XREADGROUP`; entries left unacked for
+/// `claim_after` (a crashed consumer, or a failed attempt) are taken over with
+/// `XAUTOCLAIM` and delivered again. Dead letters go to `{stream}:dead`.
+#[derive(Clone)]
+pub struct RedisStreamConsumer {
+    conn: MultiplexedConnection,
+    stream: String,
+    group: String,
+    consumer: String,
+    block: Duration,
+    claim_after: Duration,
+}
+
+impl RedisStreamConsumer {
+    /// `consumer` must be unique within `group` (e.g. the pod hostname). Give
+    /// each consumer its own connection: `XREADGROUP BLOCK` holds it while waiting.
+    pub fn new(conn: MultiplexedConnection, stream: &str, group: &str, consumer: &str) -> Self {
+        Self {
+            conn,
+            stream: stream.to_owned(),
+            group: group.to_owned(),
+            consumer: consumer.to_owned(),
+            block: Duration::from_secs(5),
+            claim_after: Duration::from_secs(60),
+        }
+    }
+
+    /// How long an unacked delivery stays with its consumer before redelivery.
+    pub fn claim_after(mut self, claim_after: Duration) -> Self {
+        self.claim_after = claim_after;
+        self
+    }
+
+    /// Create the stream and group if missing. New groups start at the end of
+    /// the stream.
+    pub async fn ensure_group(&self) -> anyhow::Result<()> {
+        let created: redis::RedisResult<()> = redis::cmd("XGROUP")
+            .arg("CREATE")
+            .arg(&self.stream)
+            .arg(&self.group)
+            .arg("$")
+            .arg("MKSTREAM")
+            .query_async(&mut self.conn.clone())
+            .await;
+        match created {
+            Err(e) if e.code() == Some("BUSYGROUP") => Ok(()),
+            other => Ok(other?),
+        }
+    }
+
+    fn dead_letter_stream(&self) -> String {
+        format!("{}:dead", self.stream)
+    }
+
+    async fn claim(&self, max: usize) -> anyhow::Result<Vec<Delivery>> {
+        let mut conn = self.conn.clone();
+        let claimed: StreamAutoClaimReply = redis::cmd("XAUTOCLAIM")
+            .arg(&self.stream)
+            .arg(&self.group)
+            .arg(&self.consumer)
+            .arg(self.claim_after.as_millis() as u64)
+            .arg("0-0")
+            .arg("COUNT")
+            .arg(max)
+            .query_async(&mut conn)
+            .await?;
+        if claimed.claimed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // XAUTOCLAIM bumped each delivery counter; read them back.
+        let mut deliveries = Vec::with_capacity(claimed.claimed.len());
+        for entry in claimed.claimed {
+            let pending: StreamPendingCountReply = redis::cmd("XPENDING")
+                .arg(&self.stream)
+                .arg(&self.group)
+                .arg(&entry.id)
+                .arg(&entry.id)
+                .arg(1)
+                .query_async(&mut conn)
+                .await?;
+            let count = pending.ids.first().map_or(1, |p| p.times_delivered as u64);
+            deliveries.push(delivery(entry, count));
+        }
+        Ok(deliveries)
+    }
+}
+
+fn delivery(entry: StreamId, deliveries: u64) -> Delivery {
+    let payload: Vec<u8> = entry.get(PAYLOAD_FIELD).unwrap_or_default();
+    Delivery {
+        id: entry.id,
+        payload,
+        deliveries,
+    }
+}
+
+impl Consumer for RedisStreamConsumer {
+    async fn fetch(&self, max: usize) -> anyhow::Result<Vec<Delivery>> {
+        let claimed = self.claim(max).await?;
+        if !claimed.is_empty() {
+            return Ok(claimed);
+        }
+
+        let reply: Option<StreamReadReply> = redis::cmd("XREADGROUP")
+            .arg("GROUP")
+            .arg(&self.group)
+            .arg(&self.consumer)
+            .arg("COUNT")
+            .arg(max)
+            .arg("BLOCK")
+            .arg(self.block.as_millis() as u64)
+            .arg("STREAMS")
+            .arg(&self.stream)
+            .arg(">")
+            .query_async(&mut self.conn.clone())
+            .await?;
+        Ok(reply
+            .into_iter()
+            .flat_map(|r| r.keys)
+            .flat_map(|k| k.ids)
+            .map(|entry| delivery(entry, 1))
+            .collect())
+    }
+
+    async fn ack(&self, delivery: &Delivery) -> anyhow::Result<()> {
+        let _: u64 = redis::cmd("XACK")
+            .arg(&self.stream)
+            .arg(&self.group)
+            .arg(&delivery.id)
+            .query_async(&mut self.conn.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn dead_letter(&self, delivery: &Delivery, error: &str) -> anyhow::Result<()> {
+        let _: String = redis::cmd("XADD")
+            .arg(self.dead_letter_stream())
+            .arg("*")
+            .arg(PAYLOAD_FIELD)
+            .arg(&delivery.payload)
+            .arg("source_id")
+            .arg(&delivery.id)
+            .arg("group")
+            .arg(&self.group)
+            .arg("deliveries")
+            .arg(delivery.deliveries)
+            .arg("error")
+            .arg(error)
+            .query_async(&mut self.conn.clone())
+            .await?;
+        self.ack(delivery).await
+    }
+}
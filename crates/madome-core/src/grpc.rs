@@ -0,0 +1,729 @@
+//! Caller authentication for service-to-service gRPC.
+//!
+//! Each calling service holds a shared secret. Clients attach it with
+//! [`ServiceCredential`] (a tonic [`Interceptor`]); servers wrap their tonic
+//! router in [`GrpcAuthLayer`], which checks the secret and then a per-method
+//! allowlist. Methods without an allowlist entry are denied.
+//!
+//! ```
+//! use madome_core::config::Secret;
+//! use madome_core::grpc::{GrpcAuthLayer, GrpcAuthPolicy, ServiceCredential};
+//!
+//! // Server side (users): library may renew books, auth may look users up.
+//! let policy = GrpcAuthPolicy::new()
+//!     .caller("library", Secret::new("library-secret".to_owned()))
+//!     .caller("auth", Secret::new("auth-secret".to_owned()))
+//!     .allow("/user.UserService/RenewBook", &["library"])
+//!     .allow("/user.UserService/", &["auth"]);
+//! let layer = GrpcAuthLayer::new(policy);
+//!
+//! // Client side (library): `UserServiceClient::with_interceptor(channel, credential)`.
+//! let credential = ServiceCredential::new("library", Secret::new("library-secret".to_owned()));
+//! ```
+//!
+//! Internal tools that are not services (image-gen, sync) authenticate with a
+//! machine token instead ([`madome_auth_types::machine`]), sent by
+//! [`MachineCredential`] as `authorization: Bearer`. The policy checks it
+//! against the scope each method requires, never against the caller allowlist:
+//!
+//! ```
+//! # use madome_core::config::Secret;
+//! # use madome_core::grpc::GrpcAuthPolicy;
+//! let policy = GrpcAuthPolicy::new()
+//!     .machine_tokens(Secret::new("jwt-secret".to_owned()))
+//!     .require_scope("/library.LibraryService/RenewBook", "library.renew");
+//! ```
+//!
+//! Both interceptors also forward the [`current_request_id`] as
+//! `x-request-id`, and servers put [`GrpcTraceLayer`] outside the auth layer so
+//! the call is logged under the same trace id as the HTTP request behind it:
+//! `Server::builder().layer(GrpcTraceLayer).layer(GrpcAuthLayer::new(policy))`.
+//!
+//! [`GrpcTlsConfig`] adds mutual TLS underneath: both ends present a
+//! certificate signed by the internal CA, so traffic is encrypted and only
+//! internal services can connect at all.
+//!
+//! Servers answer failures with `Status::from(ErrorBody)`, which picks the code
+//! from the [`ErrorKind`](crate::error_catalog::ErrorKind) and sends the kind
+//! itself as [`ERROR_KIND_METADATA`]; callers read it back with [`error_kind`]
+//! to tell a missing record from a broken dependency without parsing messages.
+
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use anyhow::Context as _;
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderMap, Request, Response};
+use madome_auth_types::machine::validate_machine_token;
+use serde::Deserialize;
+use subtle::ConstantTimeEq;
+use tonic::Status;
+use tonic::metadata::MetadataValue;
+use tonic::service::Interceptor;
+use tonic::transport::{Certificate, ClientTlsConfig, Identity, ServerTlsConfig};
+use tower::{Layer, Service};
+use tracing::Instrument;
+use uuid::Uuid;
+
+use crate::config::{Config, Secret};
+use crate::middleware::{REQUEST_ID_HEADER, current_request_id, with_request_id};
+
+mod status;
+pub use status::{ERROR_KIND_METADATA, error_kind, grpc_code};
+
+/// Metadata key naming the calling service.
+pub const SERVICE_NAME_METADATA: &str = "x-madome-service";
+
+/// Metadata key carrying the calling service's shared secret.
+pub const SERVICE_TOKEN_METADATA: &str = "x-madome-service-token";
+
+/// Metadata key carrying the id of the request that led to the call; the same
+/// name as the HTTP [`REQUEST_ID_HEADER`].
+pub const REQUEST_ID_METADATA: &str = "x-request-id";
+
+/// Attach the [`current_request_id`], if any, to an outgoing call.
+fn forward_request_id(request: &mut tonic::Request<()>) {
+    if let Some(id) = current_request_id().and_then(|id| id.parse().ok()) {
+        request.metadata_mut().insert(REQUEST_ID_METADATA, id);
+    }
+}
+
+/// Client interceptor attaching this service's name and secret to every call.
+#[derive(Clone)]
+pub struct ServiceCredential {
+    name: MetadataValue<tonic::metadata::Ascii>,
+    token: Secret<MetadataValue<tonic::metadata::Ascii>>,
+}
+
+impl ServiceCredential {
+    /// # Panics
+    ///
+    /// Panics if `name` or `token` is not a valid metadata value (visible ASCII).
+    pub fn new(name: &str, token: Secret<String>) -> Self {
+        Self {
+            name: name.parse().expect("invalid gRPC service name"),
+            token: Secret::new(token.expose().parse().expect("invalid gRPC service token")),
+        }
+    }
+}
+
+impl Interceptor for ServiceCredential {
+    fn call(&mut self, mut request: tonic::Request<()>) -> Result<tonic::Request<()>, Status> {
+        let metadata = request.metadata_mut();
+        metadata.insert(SERVICE_NAME_METADATA, self.name.clone());
+        metadata.insert(SERVICE_TOKEN_METADATA, self.token.expose().clone());
+        forward_request_id(&mut request);
+        Ok(request)
+    }
+}
+
+/// Client interceptor sending a machine token as `authorization: Bearer`.
+#[derive(Clone)]
+pub struct MachineCredential {
+    authorization: Secret<MetadataValue<tonic::metadata::Ascii>>,
+}
+
+impl MachineCredential {
+    /// # Panics
+    ///
+    /// Panics if `token` is not a valid metadata value (visible ASCII).
+    pub fn new(token: Secret<String>) -> Self {
+        let value = format!("Bearer {}", token.expose());
+        Self {
+            authorization: Secret::new(value.parse().expect("invalid machine token")),
+        }
+    }
+}
+
+impl Interceptor for MachineCredential {
+    fn call(&mut self, mut request: tonic::Request<()>) -> Result<tonic::Request<()>, Status> {
+        request
+            .metadata_mut()
+            .insert(AUTHORIZATION.as_str(), self.authorization.expose().clone());
+        forward_request_id(&mut request);
+        Ok(request)
+    }
+}
+
+/// Known callers and which of them may call which method.
+#[derive(Debug, Clone, Default)]
+pub struct GrpcAuthPolicy {
+    callers: HashMap<String, Secret<String>>,
+    /// Full method path (`/pkg.Service/Method`) or service prefix (`/pkg.Service/`).
+    allow: HashMap<String, HashSet<String>>,
+    /// Key machine tokens are signed with; `None` refuses them.
+    machine_secret: Option<Secret<String>>,
+    /// Scope a machine token needs, by full method path or service prefix.
+    scopes: HashMap<String, String>,
+}
+
+impl GrpcAuthPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a caller and its shared secret.
+    pub fn caller(mut self, name: &str, token: Secret<String>) -> Self {
+        self.callers.insert(name.to_owned(), token);
+        self
+    }
+
+    /// Let `callers` invoke `method`: a full path, or a service prefix ending in `/`.
+    pub fn allow(mut self, method: &str, callers: &[&str]) -> Self {
+        self.allow
+            .entry(method.to_owned())
+            .or_default()
+            .extend(callers.iter().map(|c| (*c).to_owned()));
+        self
+    }
+
+    /// Accept machine tokens signed with `secret` (the auth service's JWT secret).
+    pub fn machine_tokens(mut self, secret: Secret<String>) -> Self {
+        self.machine_secret = Some(secret);
+        self
+    }
+
+    /// Let machine tokens holding `scope` invoke `method`: a full path, or a
+    /// service prefix ending in `/`. A full path takes precedence.
+    pub fn require_scope(mut self, method: &str, scope: &str) -> Self {
+        self.scopes.insert(method.to_owned(), scope.to_owned());
+        self
+    }
+
+    /// Resolve the caller from `headers` and check it may call `path`.
+    ///
+    /// A machine token resolves to `machine:{client}`, which no allowlist
+    /// entry matches.
+    pub fn authorize(&self, path: &str, headers: &HeaderMap) -> Result<String, Status> {
+        if let Some(token) = bearer_token(headers) {
+            return self.authorize_machine(path, token);
+        }
+        let name = headers
+            .get(SERVICE_NAME_METADATA)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| Status::unauthenticated("missing service credential"))?;
+        let token = headers
+            .get(SERVICE_TOKEN_METADATA)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| Status::unauthenticated("missing service credential"))?;
+        match self.callers.get(name) {
+            Some(expected) if bool::from(expected.expose().as_bytes().ct_eq(token.as_bytes())) => {}
+            _ => return Err(Status::unauthenticated("invalid service credential")),
+        }
+
+        let service_prefix = path.rfind('/').map(|i| &path[..=i]);
+        let allowed = [Some(path), service_prefix]
+            .into_iter()
+            .flatten()
+            .filter_map(|key| self.allow.get(key))
+            .any(|callers| callers.contains(name));
+        if allowed {
+            Ok(name.to_owned())
+        } else {
+            Err(Status::permission_denied(format!(
+                "{name} may not call {path}"
+            )))
+        }
+    }
+
+    fn authorize_machine(&self, path: &str, token: &str) -> Result<String, Status> {
+        let secret = self
+            .machine_secret
+            .as_ref()
+            .ok_or_else(|| Status::unauthenticated("machine tokens are not accepted"))?;
+        let info = validate_machine_token(token, secret.expose())
+            .map_err(|_| Status::unauthenticated("invalid machine token"))?;
+
+        let service_prefix = path.rfind('/').map(|i| &path[..=i]);
+        let scope = [Some(path), service_prefix]
+            .into_iter()
+            .flatten()
+            .find_map(|key| self.scopes.get(key))
+            .ok_or_else(|| Status::permission_denied(format!("{path} takes no machine tokens")))?;
+        if info.has_scope(scope) {
+            Ok(format!("machine:{}", info.client))
+        } else {
+            Err(Status::permission_denied(format!(
+                "{} lacks scope {scope}",
+                info.client
+            )))
+        }
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+/// Parse `GRPC_CALLER_TOKENS` (`name=secret,...`) into callers for
+/// [`GrpcAuthPolicy::caller`].
+pub fn parse_caller_tokens(
+    tokens: &Secret<String>,
+) -> anyhow::Result<Vec<(String, Secret<String>)>> {
+    tokens
+        .expose()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('=') {
+            Some((name, token)) if !name.is_empty() && !token.is_empty() => {
+                Ok((name.trim().to_owned(), Secret::new(token.trim().to_owned())))
+            }
+            // The entry holds a secret; name only its position.
+            _ => anyhow::bail!("GRPC_CALLER_TOKENS entries must be name=secret"),
+        })
+        .collect()
+}
+
+/// Identity of an authenticated caller, left in the request extensions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallerService(pub String);
+
+/// Tower layer enforcing a [`GrpcAuthPolicy`] in front of a tonic server.
+///
+/// Apply with `Server::builder().layer(GrpcAuthLayer::new(policy))`.
+#[derive(Clone)]
+pub struct GrpcAuthLayer {
+    policy: Arc<GrpcAuthPolicy>,
+}
+
+impl GrpcAuthLayer {
+    pub fn new(policy: GrpcAuthPolicy) -> Self {
+        Self {
+            policy: Arc::new(policy),
+        }
+    }
+}
+
+impl<S> Layer<S> for GrpcAuthLayer {
+    type Service = GrpcAuth<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcAuth {
+            inner,
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct GrpcAuth<S> {
+    inner: S,
+    policy: Arc<GrpcAuthPolicy>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for GrpcAuth<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        match self.policy.authorize(req.uri().path(), req.headers()) {
+            Ok(caller) => {
+                req.extensions_mut().insert(CallerService(caller));
+                Box::pin(self.inner.call(req))
+            }
+            Err(status) => {
+                tracing::warn!(path = req.uri().path(), %status, "gRPC call rejected");
+                Box::pin(std::future::ready(Ok(status.into_http())))
+            }
+        }
+    }
+}
+
+/// Tower layer running each gRPC call under the caller's request id.
+///
+/// The id comes from `x-request-id` metadata, or is generated when the caller
+/// sent none. The call runs inside a `grpc` span carrying `trace_id` and
+/// `method`, with the id as the [`current_request_id`] so calls it makes in
+/// turn forward it, and the response echoes it as `x-request-id`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GrpcTraceLayer;
+
+impl<S> Layer<S> for GrpcTraceLayer {
+    type Service = GrpcTrace<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcTrace { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct GrpcTrace<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for GrpcTrace<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    ResBody: Send + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let trace_id = req
+            .headers()
+            .get(REQUEST_ID_METADATA)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        let span = tracing::info_span!("grpc", trace_id = %trace_id, method = req.uri().path());
+        // Inner layers may log before returning their future (a rejected call does).
+        let future = span.in_scope(|| self.inner.call(req));
+
+        Box::pin(
+            with_request_id(trace_id.clone(), async move {
+                let mut response = future.await?;
+                if let Ok(value) = trace_id.parse() {
+                    response.headers_mut().insert(REQUEST_ID_HEADER, value);
+                }
+                Ok(response)
+            })
+            .instrument(span),
+        )
+    }
+}
+
+/// Mutual TLS for gRPC servers and channels. All three PEM paths are set
+/// together, or none of them (plaintext, for local development).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GrpcTlsConfig {
+    /// `GRPC_TLS_CERT`: this service's certificate chain.
+    #[serde(default)]
+    pub grpc_tls_cert: Option<PathBuf>,
+    /// `GRPC_TLS_KEY`: the certificate's private key.
+    #[serde(default)]
+    pub grpc_tls_key: Option<PathBuf>,
+    /// `GRPC_TLS_CA`: CA that signs every service certificate.
+    #[serde(default)]
+    pub grpc_tls_ca: Option<PathBuf>,
+}
+
+impl Config for GrpcTlsConfig {}
+
+impl GrpcTlsConfig {
+    /// For `Server::builder().tls_config(..)`: presents our certificate and
+    /// rejects clients without one signed by the CA. `None` when TLS is off.
+    pub fn server(&self) -> anyhow::Result<Option<ServerTlsConfig>> {
+        let Some((identity, ca)) = self.load()? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            ServerTlsConfig::new().identity(identity).client_ca_root(ca),
+        ))
+    }
+
+    /// For `Channel::from_shared(..)?.tls_config(..)`: verifies the server
+    /// as `domain` against the CA and presents our certificate. `None` when TLS is off.
+    pub fn client(&self, domain: &str) -> anyhow::Result<Option<ClientTlsConfig>> {
+        let Some((identity, ca)) = self.load()? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            ClientTlsConfig::new()
+                .domain_name(domain)
+                .ca_certificate(ca)
+                .identity(identity),
+        ))
+    }
+
+    fn load(&self) -> anyhow::Result<Option<(Identity, Certificate)>> {
+        match (&self.grpc_tls_cert, &self.grpc_tls_key, &self.grpc_tls_ca) {
+            (None, None, None) => Ok(None),
+            (Some(cert), Some(key), Some(ca)) => {
+                let identity = Identity::from_pem(read_pem(cert)?, read_pem(key)?);
+                Ok(Some((identity, Certificate::from_pem(read_pem(ca)?))))
+            }
+            _ => anyhow::bail!("GRPC_TLS_CERT, GRPC_TLS_KEY and GRPC_TLS_CA must be set together"),
+        }
+    }
+}
+
+fn read_pem(path: &Path) -> anyhow::Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use tonic::Code;
+
+    fn policy() -> GrpcAuthPolicy {
+        GrpcAuthPolicy::new()
+            .caller("library", Secret::new("library-secret".to_owned()))
+            .caller("auth", Secret::new("auth-secret".to_owned()))
+            .allow("/user.UserService/RenewBook", &["library"])
+            .allow("/user.UserService/", &["auth"])
+    }
+
+    fn headers(name: &str, token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(SERVICE_NAME_METADATA, HeaderValue::from_str(name).unwrap());
+        headers.insert(
+            SERVICE_TOKEN_METADATA,
+            HeaderValue::from_str(token).unwrap(),
+        );
+        headers
+    }
+
+    fn code(result: Result<String, Status>) -> Code {
+        result.map_or_else(|s| s.code(), |_| Code::Ok)
+    }
+
+    #[test]
+    fn should_allow_listed_method() {
+        let caller = policy()
+            .authorize(
+                "/user.UserService/RenewBook",
+                &headers("library", "library-secret"),
+            )
+            .unwrap();
+        assert_eq!(caller, "library");
+    }
+
+    #[test]
+    fn should_allow_by_service_prefix() {
+        let result =
+            policy().authorize("/user.UserService/GetUser", &headers("auth", "auth-secret"));
+        assert_eq!(code(result), Code::Ok);
+    }
+
+    #[test]
+    fn should_deny_method_outside_allowlist() {
+        let result = policy().authorize(
+            "/user.UserService/GetUser",
+            &headers("library", "library-secret"),
+        );
+        assert_eq!(code(result), Code::PermissionDenied);
+    }
+
+    #[test]
+    fn should_reject_wrong_or_missing_secret() {
+        let wrong = policy().authorize("/user.UserService/GetUser", &headers("auth", "nope"));
+        assert_eq!(code(wrong), Code::Unauthenticated);
+
+        let missing = policy().authorize("/user.UserService/GetUser", &HeaderMap::new());
+        assert_eq!(code(missing), Code::Unauthenticated);
+    }
+
+    #[test]
+    fn should_parse_caller_tokens() {
+        let callers =
+            parse_caller_tokens(&Secret::new("gateway=g-secret, users=u-secret,".to_owned()))
+                .unwrap();
+        let names: Vec<&str> = callers.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["gateway", "users"]);
+        assert_eq!(callers[1].1.expose(), "u-secret");
+
+        let error = parse_caller_tokens(&Secret::new("gateway".to_owned())).unwrap_err();
+        assert!(!error.to_string().contains("gateway"));
+    }
+
+    #[test]
+    fn should_attach_credential_metadata() {
+        let mut credential =
+            ServiceCredential::new("library", Secret::new("library-secret".to_owned()));
+        let request = credential.call(tonic::Request::new(())).unwrap();
+        let headers = request.metadata().clone().into_headers();
+
+        let caller = policy().authorize("/user.UserService/RenewBook", &headers);
+        assert_eq!(caller.unwrap(), "library");
+    }
+
+    #[tokio::test]
+    async fn should_forward_current_request_id() {
+        let mut credential =
+            ServiceCredential::new("library", Secret::new("library-secret".to_owned()));
+        let request = credential.call(tonic::Request::new(())).unwrap();
+        assert!(request.metadata().get(REQUEST_ID_METADATA).is_none());
+
+        let request = with_request_id("req-123".to_owned(), async {
+            credential.call(tonic::Request::new(())).unwrap()
+        })
+        .await;
+        assert_eq!(
+            request.metadata().get(REQUEST_ID_METADATA).unwrap(),
+            "req-123"
+        );
+    }
+
+    #[tokio::test]
+    async fn should_serve_call_under_callers_request_id() {
+        use tower::ServiceExt;
+
+        let inner = tower::service_fn(|_: Request<()>| async {
+            Ok::<_, std::convert::Infallible>(Response::new(current_request_id()))
+        });
+        let service = GrpcTraceLayer.layer(inner);
+
+        let mut forwarded = Request::new(());
+        forwarded
+            .headers_mut()
+            .insert(REQUEST_ID_HEADER, HeaderValue::from_static("req-123"));
+        let response = service.clone().oneshot(forwarded).await.unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "req-123");
+        assert_eq!(response.into_body().as_deref(), Some("req-123"));
+
+        let response = service.oneshot(Request::new(())).await.unwrap();
+        let generated = response.headers()[REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_owned();
+        assert!(Uuid::parse_str(&generated).is_ok());
+        assert_eq!(response.into_body(), Some(generated));
+    }
+
+    fn machine_policy() -> GrpcAuthPolicy {
+        policy()
+            .machine_tokens(Secret::new("jwt-secret".to_owned()))
+            .require_scope("/library.LibraryService/RenewBook", "library.renew")
+            .require_scope("/user.UserService/", "users.read")
+    }
+
+    fn machine_headers(client: &str, scopes: &[&str], secret: &str) -> HeaderMap {
+        use madome_auth_types::machine::issue_machine_token;
+
+        let scopes: Vec<String> = scopes.iter().map(|s| (*s).to_owned()).collect();
+        let token = issue_machine_token(client, &scopes, 4_102_444_800, secret).unwrap();
+        let mut credential = MachineCredential::new(Secret::new(token));
+        credential
+            .call(tonic::Request::new(()))
+            .unwrap()
+            .metadata()
+            .clone()
+            .into_headers()
+    }
+
+    #[test]
+    fn should_allow_machine_token_with_required_scope() {
+        let headers = machine_headers("sync", &["library.renew"], "jwt-secret");
+        let caller = machine_policy()
+            .authorize("/library.LibraryService/RenewBook", &headers)
+            .unwrap();
+        assert_eq!(caller, "machine:sync");
+    }
+
+    #[test]
+    fn should_deny_machine_token_without_scope_or_outside_scoped_methods() {
+        let headers = machine_headers("sync", &["library.renew"], "jwt-secret");
+        let policy = machine_policy();
+        let other_scope = policy.authorize("/user.UserService/GetUser", &headers);
+        assert_eq!(code(other_scope), Code::PermissionDenied);
+        let unscoped = policy.authorize("/notification.NotificationService/Create", &headers);
+        assert_eq!(code(unscoped), Code::PermissionDenied);
+    }
+
+    #[test]
+    fn should_reject_user_token_or_unconfigured_machine_tokens() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_static("Bearer not-a-machine-token"),
+        );
+        let result = machine_policy().authorize("/user.UserService/GetUser", &headers);
+        assert_eq!(code(result), Code::Unauthenticated);
+
+        let headers = machine_headers("sync", &["users.read"], "jwt-secret");
+        let result = policy().authorize("/user.UserService/GetUser", &headers);
+        assert_eq!(code(result), Code::Unauthenticated);
+    }
+
+    #[tokio::test]
+    async fn should_answer_rejected_call_with_grpc_status() {
+        use tower::ServiceExt;
+
+        let inner = tower::service_fn(|req: Request<()>| async move {
+            let caller = req.extensions().get::<CallerService>().cloned();
+            Ok::<_, std::convert::Infallible>(Response::new(caller.map(|c| c.0)))
+        });
+        let service = GrpcAuthLayer::new(policy()).layer(inner);
+
+        let mut allowed = Request::new(());
+        *allowed.uri_mut() = "/user.UserService/GetUser".parse().unwrap();
+        *allowed.headers_mut() = headers("auth", "auth-secret");
+        let response = service.clone().oneshot(allowed).await.unwrap();
+        assert_eq!(response.into_body().as_deref(), Some("auth"));
+
+        let mut denied = Request::new(());
+        *denied.uri_mut() = "/user.UserService/GetUser".parse().unwrap();
+        let response = service.oneshot(denied).await.unwrap();
+        assert_eq!(response.headers()["grpc-status"], "16");
+        assert_eq!(response.into_body(), None);
+    }
+
+    fn tls(cert: Option<&Path>, key: Option<&Path>, ca: Option<&Path>) -> GrpcTlsConfig {
+        GrpcTlsConfig {
+            grpc_tls_cert: cert.map(Path::to_path_buf),
+            grpc_tls_key: key.map(Path::to_path_buf),
+            grpc_tls_ca: ca.map(Path::to_path_buf),
+        }
+    }
+
+    #[test]
+    fn should_disable_tls_when_unconfigured() {
+        let config = GrpcTlsConfig::default();
+        assert!(config.server().unwrap().is_none());
+        assert!(config.client("users").unwrap().is_none());
+    }
+
+    #[test]
+    fn should_reject_partial_tls_config() {
+        let pem = std::env::temp_dir().join("madome-grpc-partial.pem");
+        let config = tls(Some(&pem), None, None);
+        let error = config.server().unwrap_err();
+        assert!(error.to_string().contains("must be set together"));
+    }
+
+    #[test]
+    fn should_build_tls_configs_from_pem_files() {
+        let dir = std::env::temp_dir().join(format!("madome-grpc-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pem = dir.join("dummy.pem");
+        std::fs::write(
+            &pem,
+            "-----BEGIN CERTIFICATE-----\n-----END CERTIFICATE-----\n",
+        )
+        .unwrap();
+        let config = tls(Some(&pem), Some(&pem), Some(&pem));
+
+        assert!(config.server().unwrap().is_some());
+        assert!(config.client("users").unwrap().is_some());
+
+        let missing = tls(Some(&dir.join("missing.pem")), Some(&pem), Some(&pem));
+        assert!(
+            missing
+                .server()
+                .unwrap_err()
+                .to_string()
+                .contains("missing.pem")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::{Code, Status};
+
+use crate::error_catalog::{ErrorBody, ErrorKind};
+
+/// Metadata key carrying the [`ErrorKind`] wire value of a failed call.
+pub const ERROR_KIND_METADATA: &str = "x-madome-error-kind";
+
+/// gRPC code a service answers an error of `kind` with.
+pub fn grpc_code(kind: ErrorKind) -> Code {
+    match kind {
+        ErrorKind::BadRequest | ErrorKind::Validation => Code::InvalidArgument,
+        ErrorKind::Unauthorized => Code::Unauthenticated,
+        ErrorKind::Forbidden => Code::PermissionDenied,
+        ErrorKind::NotFound => Code::NotFound,
+        ErrorKind::Conflict => Code::AlreadyExists,
+        ErrorKind::TooManyRequests => Code::ResourceExhausted,
+        ErrorKind::Internal => Code::Internal,
+    }
+}
+
+/// Kind of a failed call, from [`ERROR_KIND_METADATA`] when the server sent
+/// it and from the code otherwise (a transport failure, a server without the
+/// mapping). Codes with no matching kind, such as `UNAVAILABLE`, are
+/// [`ErrorKind::Internal`].
+pub fn error_kind(status: &Status) -> ErrorKind {
+    let sent = status
+        .metadata()
+        .get(ERROR_KIND_METADATA)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|wire| ErrorKind::ALL.into_iter().find(|k| k.as_str() == wire));
+    sent.unwrap_or(match status.code() {
+        Code::InvalidArgument | Code::OutOfRange => ErrorKind::BadRequest,
+        Code::Unauthenticated => ErrorKind::Unauthorized,
+        Code::PermissionDenied => ErrorKind::Forbidden,
+        Code::NotFound => ErrorKind::NotFound,
+        Code::AlreadyExists | Code::Aborted | Code::FailedPrecondition => ErrorKind::Conflict,
+        Code::ResourceExhausted => ErrorKind::TooManyRequests,
+        _ => ErrorKind::Internal,
+    })
+}
+
+/// The status for an error body, with its kind in [`ERROR_KIND_METADATA`].
+/// Field errors are appended to the message as `field (code)`.
+impl From<ErrorBody> for Status {
+    fn from(body: ErrorBody) -> Self {
+        let mut message = body.message;
+        for (i, error) in body.errors.iter().enumerate() {
+            message.push_str(if i == 0 { ": " } else { ", " });
+            message.push_str(&format!("{} ({})", error.field, error.code));
+        }
+        let mut metadata = MetadataMap::new();
+        metadata.insert(
+            ERROR_KIND_METADATA,
+            MetadataValue::from_static(body.kind.as_str()),
+        );
+        Status::with_metadata(grpc_code(body.kind), message, metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::FieldError;
+
+    #[test]
+    fn should_round_trip_every_kind_through_a_status() {
+        for kind in ErrorKind::ALL {
+            let status = Status::from(ErrorBody::new(kind, "failed"));
+            assert_eq!(status.code(), grpc_code(kind));
+            assert_eq!(error_kind(&status), kind);
+        }
+    }
+
+    #[test]
+    fn should_fall_back_to_the_code_without_kind_metadata() {
+        assert_eq!(
+            error_kind(&Status::not_found("book 42")),
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            error_kind(&Status::unavailable("connection refused")),
+            ErrorKind::Internal
+        );
+    }
+
+    #[test]
+    fn should_list_field_errors_in_the_message() {
+        let body = ErrorBody {
+            errors: vec![
+                FieldError {
+                    field: "handle".to_owned(),
+                    code: "too_long",
+                },
+                FieldError {
+                    field: "email".to_owned(),
+                    code: "invalid",
+                },
+            ],
+            ..ErrorBody::new(ErrorKind::Validation, "validation failed")
+        };
+        assert_eq!(
+            Status::from(body).message(),
+            "validation failed: handle (too_long), email (invalid)"
+        );
+    }
+}
@@ -1,13 +1,40 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use axum::Extension;
 use axum::http::StatusCode;
 
+/// Whether every server of the process is accepting requests; set by the
+/// [`Supervisor`](crate::supervisor::Supervisor). Clones share the flag.
+#[derive(Debug, Clone, Default)]
+pub struct Readiness(Arc<AtomicBool>);
+
+impl Readiness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, ready: bool) {
+        self.0.store(ready, Ordering::Relaxed);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
 /// Handler for `GET /healthz` — liveness check.
 pub async fn healthz() -> StatusCode {
     StatusCode::OK
 }
 
-/// Handler for `GET /readyz` — readiness check (override per service as needed).
-pub async fn readyz() -> StatusCode {
-    StatusCode::OK
+/// Handler for `GET /readyz` — readiness check. 503 while a [`Readiness`]
+/// added to the router as an extension is unset; 200 without one.
+pub async fn readyz(readiness: Option<Extension<Readiness>>) -> StatusCode {
+    match readiness {
+        Some(Extension(readiness)) if !readiness.is_ready() => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::OK,
+    }
 }
 
 #[cfg(test)]
@@ -21,6 +48,17 @@ mod tests {
 
     #[tokio::test]
     async fn readyz_returns_200() {
-        assert_eq!(readyz().await, StatusCode::OK);
+        assert_eq!(readyz(None).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn readyz_returns_503_until_ready() {
+        let readiness = Readiness::new();
+        assert_eq!(
+            readyz(Some(Extension(readiness.clone()))).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        readiness.set(true);
+        assert_eq!(readyz(Some(Extension(readiness))).await, StatusCode::OK);
     }
 }
@@ -0,0 +1,401 @@
+//! `Idempotency-Key` support for retried mutations.
+//!
+//! The first request carrying a key reserves it, runs the handler and stores the
+//! response; a retry with the same key and the same request replays that
+//! response instead of running the handler again. Reusing a key for a different
+//! request is rejected with 422, and a retry that arrives while the first
+//! attempt is still running gets 409.
+//!
+//! `Set-Cookie` is never stored or replayed: a replay would hand whoever knows
+//! the key the first caller's session. Routes that sign in therefore do not
+//! belong behind this middleware.
+//!
+//! The store is a port so this crate stays free of Redis; services implement
+//! [`IdempotencyStore`] next to their other caches and wrap the chosen routes
+//! with `.route_layer(axum::middleware::from_fn_with_state(store, idempotency::<Store>))`.
+
+use std::future::Future;
+
+use axum::body::{Body, to_bytes};
+use axum::extract::{Request, State};
+use axum::http::header::SET_COOKIE;
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error_catalog::{ErrorBody, ErrorKind};
+use crate::validation::ValidationErrors;
+
+/// Request header carrying the client-chosen key.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// How long a key and its stored response are kept (24 hours).
+pub const IDEMPOTENCY_TTL_SECS: u64 = 86400;
+
+/// Keys longer than this are rejected with 400.
+const MAX_KEY_LEN: usize = 255;
+
+/// Request and response bodies larger than this are not buffered (1 MiB).
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Key-value storage for idempotency records, with expiry.
+pub trait IdempotencyStore: Clone + Send + Sync + 'static {
+    /// Store `value` under `key` unless the key exists. Returns `false` if it did.
+    fn set_nx(
+        &self,
+        key: &str,
+        value: &[u8],
+        ttl_secs: u64,
+    ) -> impl Future<Output = anyhow::Result<bool>> + Send;
+
+    /// Overwrite `key` with `value`.
+    fn set(
+        &self,
+        key: &str,
+        value: &[u8],
+        ttl_secs: u64,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    fn get(&self, key: &str) -> impl Future<Output = anyhow::Result<Option<Vec<u8>>>> + Send;
+
+    fn delete(&self, key: &str) -> impl Future<Output = anyhow::Result<()>> + Send;
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IdempotencyRecord {
+    /// SHA-256 of method, URI and body of the request that reserved the key.
+    fingerprint: String,
+    /// `None` while the first attempt is in flight.
+    response: Option<StoredResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredResponse {
+    status: u16,
+    headers: Vec<(String, Vec<u8>)>,
+    body: Vec<u8>,
+}
+
+/// Middleware implementing `Idempotency-Key` on the wrapped routes.
+///
+/// Requests without the header pass through untouched. Keys are scoped to the
+/// gateway-injected `x-madome-user-id` when present. 5xx responses are not
+/// stored, so the client may retry them with the same key. If the store is
+/// unavailable the request runs without idempotency rather than failing.
+pub async fn idempotency<S: IdempotencyStore>(
+    State(store): State<S>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(client_key) = req.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return next.run(req).await;
+    };
+    let client_key = match client_key.to_str() {
+        Ok(k) if !k.is_empty() && k.len() <= MAX_KEY_LEN => k.to_owned(),
+        _ => {
+            return ErrorBody::new(ErrorKind::BadRequest, "invalid idempotency-key")
+                .into_response();
+        }
+    };
+    let scope = req
+        .headers()
+        .get("x-madome-user-id")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("anonymous");
+    let key = format!("idempotency:{scope}:{client_key}");
+
+    let (parts, body) = req.into_parts();
+    let Ok(body) = to_bytes(body, MAX_BODY_BYTES).await else {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    };
+    let fingerprint = fingerprint(parts.method.as_str(), &parts.uri.to_string(), &body);
+    let req = Request::from_parts(parts, Body::from(body));
+
+    let pending = IdempotencyRecord {
+        fingerprint: fingerprint.clone(),
+        response: None,
+    };
+    match reserve(&store, &key, &pending).await {
+        Ok(None) => {}
+        Ok(Some(existing)) => return replay(existing, &fingerprint),
+        Err(e) => {
+            tracing::warn!(error = %e, "idempotency store unavailable; running request without it");
+            return next.run(req).await;
+        }
+    }
+
+    let response = next.run(req).await;
+    if response.status().is_server_error() {
+        if let Err(e) = store.delete(&key).await {
+            tracing::warn!(error = %e, "failed to release idempotency key");
+        }
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let Ok(body) = to_bytes(body, MAX_BODY_BYTES).await else {
+        // Too large to store; the handler already ran, so keep the key reserved
+        // (retries get 409) instead of letting them run it again.
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let record = IdempotencyRecord {
+        fingerprint,
+        response: Some(StoredResponse {
+            status: parts.status.as_u16(),
+            headers: parts
+                .headers
+                .iter()
+                .filter(|(name, _)| *name != SET_COOKIE)
+                .map(|(name, value)| (name.to_string(), value.as_bytes().to_vec()))
+                .collect(),
+            body: body.to_vec(),
+        }),
+    };
+    if let Err(e) = store_record(&store, &key, &record).await {
+        tracing::warn!(error = %e, "failed to store idempotent response");
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Reserve `key`, or return the record already holding it.
+async fn reserve<S: IdempotencyStore>(
+    store: &S,
+    key: &str,
+    pending: &IdempotencyRecord,
+) -> anyhow::Result<Option<IdempotencyRecord>> {
+    let value = serde_json::to_vec(pending)?;
+    if store.set_nx(key, &value, IDEMPOTENCY_TTL_SECS).await? {
+        return Ok(None);
+    }
+    match store.get(key).await? {
+        Some(existing) => Ok(Some(serde_json::from_slice(&existing)?)),
+        // Expired between the two calls: treat as still in flight.
+        None => Ok(Some(IdempotencyRecord {
+            fingerprint: pending.fingerprint.clone(),
+            response: None,
+        })),
+    }
+}
+
+async fn store_record<S: IdempotencyStore>(
+    store: &S,
+    key: &str,
+    record: &IdempotencyRecord,
+) -> anyhow::Result<()> {
+    store
+        .set(key, &serde_json::to_vec(record)?, IDEMPOTENCY_TTL_SECS)
+        .await
+}
+
+fn replay(existing: IdempotencyRecord, fingerprint: &str) -> Response {
+    if existing.fingerprint != fingerprint {
+        let mut errors = ValidationErrors::new();
+        errors.add(IDEMPOTENCY_KEY_HEADER, "reused");
+        return errors.into_response();
+    }
+    let Some(stored) = existing.response else {
+        return ErrorBody::new(
+            ErrorKind::Conflict,
+            "request with this idempotency-key is in progress",
+        )
+        .into_response();
+    };
+
+    let mut headers = HeaderMap::new();
+    // Records written before cookies were filtered out may still hold them.
+    for (name, value) in stored.headers {
+        if let (Ok(name), Ok(value)) = (HeaderName::try_from(name), HeaderValue::from_bytes(&value))
+        {
+            if name != SET_COOKIE {
+                headers.append(name, value);
+            }
+        }
+    }
+    let status = StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK);
+    (status, headers, stored.body).into_response()
+}
+
+fn fingerprint(method: &str, uri: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_bytes());
+    hasher.update([0]);
+    hasher.update(uri.as_bytes());
+    hasher.update([0]);
+    hasher.update(body);
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use axum::Router;
+    use axum::http::Request as HttpRequest;
+    use axum::middleware;
+    use axum::routing::post;
+    use tower::ServiceExt;
+
+    #[derive(Clone, Default)]
+    struct MemoryStore(Arc<Mutex<HashMap<String, Vec<u8>>>>);
+
+    impl IdempotencyStore for MemoryStore {
+        async fn set_nx(&self, key: &str, value: &[u8], _ttl: u64) -> anyhow::Result<bool> {
+            let mut map = self.0.lock().unwrap();
+            if map.contains_key(key) {
+                return Ok(false);
+            }
+            map.insert(key.to_owned(), value.to_vec());
+            Ok(true)
+        }
+
+        async fn set(&self, key: &str, value: &[u8], _ttl: u64) -> anyhow::Result<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(key.to_owned(), value.to_vec());
+            Ok(())
+        }
+
+        async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+            Ok(self.0.lock().unwrap().get(key).cloned())
+        }
+
+        async fn delete(&self, key: &str) -> anyhow::Result<()> {
+            self.0.lock().unwrap().remove(key);
+            Ok(())
+        }
+    }
+
+    /// Router whose handler counts its runs and answers with `status`.
+    fn app(store: MemoryStore, calls: Arc<AtomicUsize>, status: StatusCode) -> Router {
+        Router::new()
+            .route(
+                "/things",
+                post(move |body: String| async move {
+                    let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                    (status, [(SET_COOKIE, "a=b")], format!("{body}:{n}"))
+                }),
+            )
+            .route_layer(middleware::from_fn_with_state(
+                store,
+                idempotency::<MemoryStore>,
+            ))
+    }
+
+    async fn send(app: &Router, key: Option<&str>, body: &str) -> (StatusCode, HeaderMap, String) {
+        let mut req = HttpRequest::post("/things");
+        if let Some(key) = key {
+            req = req.header(IDEMPOTENCY_KEY_HEADER, key);
+        }
+        let res = app
+            .clone()
+            .oneshot(req.body(Body::from(body.to_owned())).unwrap())
+            .await
+            .unwrap();
+        let status = res.status();
+        let headers = res.headers().clone();
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (status, headers, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn should_replay_stored_response_without_cookies_on_retry() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app(MemoryStore::default(), calls.clone(), StatusCode::CREATED);
+
+        let first = send(&app, Some("k1"), "x").await;
+        let retry = send(&app, Some("k1"), "x").await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(first.0, StatusCode::CREATED);
+        assert_eq!(retry.0, StatusCode::CREATED);
+        assert_eq!(first.1[SET_COOKIE], "a=b");
+        assert!(!retry.1.contains_key(SET_COOKIE));
+        assert_eq!(retry.2, "x:1");
+    }
+
+    #[tokio::test]
+    async fn should_pass_through_without_key() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app(MemoryStore::default(), calls.clone(), StatusCode::CREATED);
+
+        send(&app, None, "x").await;
+        let second = send(&app, None, "x").await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(second.2, "x:2");
+    }
+
+    #[tokio::test]
+    async fn should_reject_key_reused_with_different_body() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app(MemoryStore::default(), calls.clone(), StatusCode::CREATED);
+
+        send(&app, Some("k1"), "x").await;
+        let reused = send(&app, Some("k1"), "y").await;
+
+        assert_eq!(reused.0, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn should_return_conflict_while_first_attempt_in_flight() {
+        let store = MemoryStore::default();
+        let pending = IdempotencyRecord {
+            fingerprint: fingerprint("POST", "/things", b"x"),
+            response: None,
+        };
+        store
+            .set(
+                "idempotency:anonymous:k1",
+                &serde_json::to_vec(&pending).unwrap(),
+                1,
+            )
+            .await
+            .unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app(store, calls.clone(), StatusCode::CREATED);
+
+        let retry = send(&app, Some("k1"), "x").await;
+
+        assert_eq!(retry.0, StatusCode::CONFLICT);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn should_release_key_after_server_error() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let store = MemoryStore::default();
+        let app = app(
+            store.clone(),
+            calls.clone(),
+            StatusCode::SERVICE_UNAVAILABLE,
+        );
+
+        send(&app, Some("k1"), "x").await;
+        send(&app, Some("k1"), "x").await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(store.0.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_reject_overlong_key() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app(MemoryStore::default(), calls.clone(), StatusCode::CREATED);
+
+        let res = send(&app, Some(&"k".repeat(256)), "x").await;
+
+        assert_eq!(res.0, StatusCode::BAD_REQUEST);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+}
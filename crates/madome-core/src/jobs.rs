@@ -0,0 +1,450 @@
+//! Periodic background jobs (rollups, retention, secret refresh, ...).
+//!
+//! A [`Scheduler`] runs each [`Job`] on its [`Schedule`] in its own task:
+//!
+//! ```no_run
+//! # async fn rollup() -> anyhow::Result<()> { Ok(()) }
+//! # fn run() -> Result<(), madome_core::jobs::CronError> {
+//! use std::time::Duration;
+//! use madome_core::jobs::{Job, Schedule, Scheduler};
+//!
+//! let jobs = Scheduler::new()
+//!     .job(Job::new("tag_popularity_rollup", Schedule::cron("*/15 * * * *")?, rollup)
+//!         .jitter(Duration::from_secs(30)))
+//!     .start();
+//! // Jobs stop when `jobs` is dropped; keep it for the life of the service.
+//! # drop(jobs);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! - **Ticks** are wall-clock aligned: `Schedule::every(15 min)` fires at :00,
+//!   :15, ... on every replica alike, and cron expressions are evaluated in UTC.
+//! - **Locks**: with [`Scheduler::lock`], a replica runs a tick only after
+//!   claiming the key `jobs:{name}:{tick}`, so each tick runs on one replica.
+//!   The key is left to expire instead of being released; a run that outlasts
+//!   its period can still overlap the next tick elsewhere. Jobs marked
+//!   [`Job::every_replica`] (e.g. refreshing an in-process secret) skip the lock.
+//! - **Jitter** delays each run by a random amount up to the job's jitter, so
+//!   jobs sharing a schedule do not all hit the database at once.
+//! - **Isolation**: a run that fails or panics is logged and counted; the job
+//!   runs again on its next tick.
+//! - **Metrics**: [`RunningJobs::metrics`] returns the counters of each job,
+//!   and every run logs a `job finished` event under the `jobs` target.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use time::OffsetDateTime;
+use tokio::task::JoinHandle;
+
+mod cron;
+
+pub use cron::{Cron, CronError};
+
+/// Claims a tick of a job for this replica. Across replicas, use
+/// `lock::DistributedLock` (feature `redis`).
+pub trait JobLock: Send + Sync + 'static {
+    /// Set `key` unless it exists, expiring after `ttl`. `Ok(false)` means
+    /// another replica holds it.
+    fn try_acquire(
+        &self,
+        key: &str,
+        ttl: Duration,
+    ) -> impl Future<Output = anyhow::Result<bool>> + Send;
+}
+
+/// Always grants the lock: every replica runs every tick.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoLock;
+
+impl JobLock for NoLock {
+    async fn try_acquire(&self, _key: &str, _ttl: Duration) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+}
+
+/// When a job runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// At every multiple of the period since the Unix epoch.
+    Every(Duration),
+    Cron(Cron),
+}
+
+impl Schedule {
+    /// # Panics
+    ///
+    /// If `period` is zero.
+    pub fn every(period: Duration) -> Self {
+        assert!(!period.is_zero(), "job period must be positive");
+        Self::Every(period)
+    }
+
+    /// A five-field cron expression (`minute hour day-of-month month
+    /// day-of-week`), or `@hourly`, `@daily`, `@weekly`, `@monthly`.
+    pub fn cron(expr: &str) -> Result<Self, CronError> {
+        Cron::parse(expr).map(Self::Cron)
+    }
+
+    /// First tick strictly after `after`.
+    pub fn next_after(&self, after: OffsetDateTime) -> Option<OffsetDateTime> {
+        match self {
+            Self::Every(period) => {
+                let period = period.as_nanos() as i128;
+                let now = after.unix_timestamp_nanos();
+                let tick = (now.div_euclid(period) + 1) * period;
+                OffsetDateTime::from_unix_timestamp_nanos(tick).ok()
+            }
+            Self::Cron(cron) => cron.next_after(after),
+        }
+    }
+}
+
+type RunFn = dyn Fn() -> Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>> + Send + Sync;
+
+/// A named task and its schedule.
+pub struct Job {
+    name: &'static str,
+    schedule: Schedule,
+    run: Arc<RunFn>,
+    jitter: Duration,
+    lock_ttl: Duration,
+    every_replica: bool,
+}
+
+impl Job {
+    /// `run` is called once per tick; a new future each time.
+    pub fn new<F, Fut>(name: &'static str, schedule: Schedule, run: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        Self {
+            name,
+            schedule,
+            run: Arc::new(move || Box::pin(run())),
+            jitter: Duration::ZERO,
+            lock_ttl: Duration::from_secs(600),
+            every_replica: false,
+        }
+    }
+
+    /// Delay each run by a random duration up to `jitter`.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// How long a claimed tick stays claimed (default 10 min). It must exceed
+    /// the jitter plus the clock skew between replicas.
+    pub fn lock_ttl(mut self, ttl: Duration) -> Self {
+        self.lock_ttl = ttl;
+        self
+    }
+
+    /// Run on every replica, without the scheduler's lock.
+    pub fn every_replica(mut self) -> Self {
+        self.every_replica = true;
+        self
+    }
+}
+
+impl fmt::Debug for Job {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Job")
+            .field("name", &self.name)
+            .field("schedule", &self.schedule)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Counters of one job since the scheduler started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JobMetrics {
+    pub succeeded: u64,
+    pub failed: u64,
+    pub panicked: u64,
+    /// Ticks claimed by another replica.
+    pub skipped: u64,
+    pub last_duration: Duration,
+}
+
+#[derive(Default)]
+struct Counters {
+    succeeded: AtomicU64,
+    failed: AtomicU64,
+    panicked: AtomicU64,
+    skipped: AtomicU64,
+    last_duration_micros: AtomicU64,
+}
+
+impl Counters {
+    fn snapshot(&self) -> JobMetrics {
+        JobMetrics {
+            succeeded: self.succeeded.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            panicked: self.panicked.load(Ordering::Relaxed),
+            skipped: self.skipped.load(Ordering::Relaxed),
+            last_duration: Duration::from_micros(self.last_duration_micros.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Collects jobs and starts them.
+pub struct Scheduler<L = NoLock> {
+    lock: Arc<L>,
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    /// A scheduler without a lock; use [`Scheduler::lock`] when the service
+    /// runs several replicas.
+    pub fn new() -> Self {
+        Self {
+            lock: Arc::new(NoLock),
+            jobs: Vec::new(),
+        }
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L: JobLock> Scheduler<L> {
+    /// Claim each tick through `lock` before running it.
+    pub fn lock<M: JobLock>(self, lock: M) -> Scheduler<M> {
+        Scheduler {
+            lock: Arc::new(lock),
+            jobs: self.jobs,
+        }
+    }
+
+    pub fn job(mut self, job: Job) -> Self {
+        self.jobs.push(job);
+        self
+    }
+
+    /// Spawn one task per job. Must be called within a Tokio runtime.
+    pub fn start(self) -> RunningJobs {
+        let jobs = self
+            .jobs
+            .into_iter()
+            .map(|job| {
+                let counters = Arc::new(Counters::default());
+                let name = job.name;
+                let task = tokio::spawn(drive(job, Arc::clone(&self.lock), Arc::clone(&counters)));
+                (name, counters, task)
+            })
+            .collect();
+        RunningJobs { jobs }
+    }
+}
+
+/// Started jobs; dropping it stops them (a run in progress is aborted).
+pub struct RunningJobs {
+    jobs: Vec<(&'static str, Arc<Counters>, JoinHandle<()>)>,
+}
+
+impl RunningJobs {
+    /// Counters of each job, in the order the jobs were added.
+    pub fn metrics(&self) -> Vec<(&'static str, JobMetrics)> {
+        self.jobs
+            .iter()
+            .map(|(name, counters, _)| (*name, counters.snapshot()))
+            .collect()
+    }
+}
+
+impl Drop for RunningJobs {
+    fn drop(&mut self) {
+        for (_, _, task) in &self.jobs {
+            task.abort();
+        }
+    }
+}
+
+impl fmt::Debug for RunningJobs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.metrics()).finish()
+    }
+}
+
+async fn drive<L: JobLock>(job: Job, lock: Arc<L>, counters: Arc<Counters>) {
+    loop {
+        let now = OffsetDateTime::now_utc();
+        let Some(tick) = job.schedule.next_after(now) else {
+            tracing::warn!(target: "jobs", job = job.name, "schedule never fires again");
+            return;
+        };
+        let wait: Duration = (tick - now).try_into().unwrap_or_default();
+        tokio::time::sleep(wait + random_up_to(job.jitter)).await;
+
+        if !job.every_replica {
+            let key = format!(
+                "jobs:{}:{}",
+                job.name,
+                tick.unix_timestamp_nanos() / 1_000_000
+            );
+            match lock.try_acquire(&key, job.lock_ttl).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    counters.skipped.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+                // Skipping is safer than running on every replica at once.
+                Err(e) => {
+                    counters.failed.fetch_add(1, Ordering::Relaxed);
+                    tracing::error!(target: "jobs", job = job.name, error = %format!("{e:#}"), "cannot claim job tick");
+                    continue;
+                }
+            }
+        }
+
+        let started = Instant::now();
+        // A separate task, so that a panic ends only this run.
+        let outcome = tokio::spawn((job.run)()).await;
+        let elapsed = started.elapsed();
+        counters
+            .last_duration_micros
+            .store(elapsed.as_micros() as u64, Ordering::Relaxed);
+        let duration_ms = elapsed.as_millis() as u64;
+        match outcome {
+            Ok(Ok(())) => {
+                counters.succeeded.fetch_add(1, Ordering::Relaxed);
+                tracing::info!(target: "jobs", job = job.name, duration_ms, outcome = "ok", "job finished");
+            }
+            Ok(Err(e)) => {
+                counters.failed.fetch_add(1, Ordering::Relaxed);
+                tracing::error!(target: "jobs", job = job.name, duration_ms, outcome = "error", error = %format!("{e:#}"), "job finished");
+            }
+            Err(e) => {
+                counters.panicked.fetch_add(1, Ordering::Relaxed);
+                tracing::error!(target: "jobs", job = job.name, duration_ms, outcome = "panic", error = %e, "job finished");
+            }
+        }
+    }
+}
+
+fn random_up_to(max: Duration) -> Duration {
+    if max.is_zero() {
+        return Duration::ZERO;
+    }
+    let nanos = uuid::Uuid::new_v4().as_u128() % (max.as_nanos() + 1);
+    Duration::from_nanos(nanos as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+
+    use time::macros::datetime;
+
+    use super::*;
+
+    /// Grants each key once, like `SET NX` on a shared Redis.
+    #[derive(Clone, Default)]
+    struct SharedLock(Arc<Mutex<HashSet<String>>>);
+
+    impl JobLock for SharedLock {
+        async fn try_acquire(&self, key: &str, _ttl: Duration) -> anyhow::Result<bool> {
+            Ok(self.0.lock().unwrap().insert(key.to_owned()))
+        }
+    }
+
+    async fn wait_for(jobs: &RunningJobs, done: impl Fn(&[(&str, JobMetrics)]) -> bool) {
+        while !done(&jobs.metrics()) {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[test]
+    fn should_align_interval_ticks_to_the_clock() {
+        let schedule = Schedule::every(Duration::from_secs(900));
+        assert_eq!(
+            schedule.next_after(datetime!(2026-03-31 10:07:30 UTC)),
+            Some(datetime!(2026-03-31 10:15 UTC))
+        );
+        assert_eq!(
+            schedule.next_after(datetime!(2026-03-31 10:15 UTC)),
+            Some(datetime!(2026-03-31 10:30 UTC))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_keep_running_after_a_failure_or_panic() {
+        let calls = Arc::new(AtomicU64::new(0));
+        let counted = Arc::clone(&calls);
+        let jobs = Scheduler::new()
+            .job(Job::new(
+                "flaky",
+                Schedule::every(Duration::from_millis(10)),
+                move || {
+                    let call = counted.fetch_add(1, Ordering::SeqCst);
+                    async move {
+                        match call {
+                            0 => anyhow::bail!("first run fails"),
+                            1 => panic!("second run panics"),
+                            _ => Ok(()),
+                        }
+                    }
+                },
+            ))
+            .start();
+
+        wait_for(&jobs, |m| m[0].1.succeeded >= 1).await;
+        let metrics = jobs.metrics()[0].1;
+        assert_eq!((metrics.failed, metrics.panicked), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn should_run_each_tick_on_one_replica() {
+        let lock = SharedLock::default();
+        let replica = || {
+            Scheduler::new()
+                .lock(lock.clone())
+                .job(Job::new(
+                    "rollup",
+                    Schedule::every(Duration::from_millis(20)),
+                    || async { Ok(()) },
+                ))
+                .start()
+        };
+        let (a, b) = (replica(), replica());
+
+        // Both replicas wake for the same ticks; the one that loses the key skips.
+        let both = |m: &RunningJobs| m.metrics()[0].1;
+        while both(&a).skipped + both(&b).skipped < 2 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let claimed = lock.0.lock().unwrap().len() as u64;
+        assert!(both(&a).succeeded + both(&b).succeeded <= claimed);
+    }
+
+    #[tokio::test]
+    async fn should_run_every_replica_jobs_without_the_lock() {
+        let lock = SharedLock::default();
+        let jobs = Scheduler::new()
+            .lock(lock.clone())
+            .job(
+                Job::new(
+                    "refresh",
+                    Schedule::every(Duration::from_millis(10)),
+                    || async { Ok(()) },
+                )
+                .every_replica(),
+            )
+            .start();
+
+        wait_for(&jobs, |m| m[0].1.succeeded >= 2).await;
+        assert!(lock.0.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+use time::{Date, OffsetDateTime};
+
+/// Why a cron expression was refused.
+#[derive(Debug, thiserror::Error)]
+#[error("invalid cron expression {expr:?}: {reason}")]
+pub struct CronError {
+    expr: String,
+    reason: &'static str,
+}
+
+/// A parsed five-field cron expression, evaluated in UTC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Whether the day-of-month / day-of-week fields were anything but `*`;
+    /// when both are, a day matching either one fires, as in cron(8).
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+impl Cron {
+    pub fn parse(expr: &str) -> Result<Self, CronError> {
+        let err = |reason| CronError {
+            expr: expr.to_owned(),
+            reason,
+        };
+        let expanded = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, dom, month, dow] = fields[..] else {
+            return Err(err("expected 5 fields"));
+        };
+
+        // Sunday is both 0 and 7.
+        let mut days_of_week = parse_field(dow, 0, 7).map_err(err)?;
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week |= 1;
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59).map_err(err)?,
+            hours: parse_field(hour, 0, 23).map_err(err)?,
+            days_of_month: parse_field(dom, 1, 31).map_err(err)?,
+            months: parse_field(month, 1, 12).map_err(err)?,
+            days_of_week,
+            dom_restricted: !dom.starts_with('*'),
+            dow_restricted: !dow.starts_with('*'),
+        })
+    }
+
+    /// First matching minute strictly after `after`, or `None` if there is
+    /// none within five years (e.g. `0 0 30 2 *`).
+    pub fn next_after(&self, after: OffsetDateTime) -> Option<OffsetDateTime> {
+        let mut t =
+            after.replace_nanosecond(0).ok()?.replace_second(0).ok()? + time::Duration::MINUTE;
+        let last_year = t.year() + 5;
+        while t.year() <= last_year {
+            if !has(self.months, t.month() as u8) {
+                let (year, month) = match t.month() {
+                    time::Month::December => (t.year() + 1, time::Month::January),
+                    month => (t.year(), month.next()),
+                };
+                t = midnight(Date::from_calendar_date(year, month, 1).ok()?);
+            } else if !self.day_matches(t.date()) {
+                t = midnight(t.date().next_day()?);
+            } else if !has(self.hours, t.hour()) {
+                t = t.replace_minute(0).ok()? + time::Duration::HOUR;
+            } else if !has(self.minutes, t.minute()) {
+                t += time::Duration::MINUTE;
+            } else {
+                return Some(t);
+            }
+        }
+        None
+    }
+
+    fn day_matches(&self, date: Date) -> bool {
+        let dom = has(self.days_of_month, date.day());
+        let dow = has(self.days_of_week, date.weekday().number_days_from_sunday());
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+}
+
+fn has(set: u64, value: u8) -> bool {
+    set & (1 << value) != 0
+}
+
+fn midnight(date: Date) -> OffsetDateTime {
+    date.midnight().assume_utc()
+}
+
+/// One field as a bit set: `*`, `n`, `a-b`, each optionally `/step`, comma-separated.
+fn parse_field(field: &str, min: u8, max: u8) -> Result<u64, &'static str> {
+    let mut set = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, Some(step.parse::<u8>().map_err(|_| "bad step")?)),
+            None => (part, None),
+        };
+        if step == Some(0) {
+            return Err("step must be positive");
+        }
+        let number = |s: &str| s.parse::<u8>().map_err(|_| "bad number");
+        let (lo, hi) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((lo, hi)) => (number(lo)?, number(hi)?),
+            // `n/step` runs from n to the end of the range.
+            None if step.is_some() => (number(range)?, max),
+            None => {
+                let n = number(range)?;
+                (n, n)
+            }
+        };
+        if lo < min || hi > max || lo > hi {
+            return Err("value out of range");
+        }
+        for value in (lo..=hi).step_by(step.unwrap_or(1).into()) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    fn next(expr: &str, after: OffsetDateTime) -> OffsetDateTime {
+        Cron::parse(expr).unwrap().next_after(after).unwrap()
+    }
+
+    #[test]
+    fn should_find_next_matching_minute() {
+        let at = datetime!(2026-03-31 10:07:30 UTC);
+        assert_eq!(next("*/15 * * * *", at), datetime!(2026-03-31 10:15 UTC));
+        assert_eq!(next("5 * * * *", at), datetime!(2026-03-31 11:05 UTC));
+        assert_eq!(next("@daily", at), datetime!(2026-04-01 0:00 UTC));
+        assert_eq!(next("0 3 * 12 *", at), datetime!(2026-12-01 3:00 UTC));
+        // Strictly after: a matching minute itself is skipped.
+        assert_eq!(
+            next("* * * * *", datetime!(2026-03-31 10:07 UTC)),
+            datetime!(2026-03-31 10:08 UTC)
+        );
+    }
+
+    #[test]
+    fn should_match_either_day_field_when_both_are_restricted() {
+        // 2026-04-01 is a Wednesday; the next Sunday is the 5th.
+        let at = datetime!(2026-04-01 12:00 UTC);
+        assert_eq!(next("0 0 * * 7", at), datetime!(2026-04-05 0:00 UTC));
+        assert_eq!(next("0 0 3 * 0", at), datetime!(2026-04-03 0:00 UTC));
+        assert_eq!(next("0 0 10 * *", at), datetime!(2026-04-10 0:00 UTC));
+    }
+
+    #[test]
+    fn should_reject_malformed_expressions() {
+        for expr in [
+            "",
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "x * * * *",
+        ] {
+            assert!(Cron::parse(expr).is_err(), "{expr:?} accepted");
+        }
+        let never = Cron::parse("0 0 30 2 *").unwrap();
+        assert_eq!(never.next_after(datetime!(2026-01-01 0:00 UTC)), None);
+    }
+}
@@ -1,5 +1,28 @@
+pub mod blob;
+pub mod coalesce;
 pub mod config;
+pub mod cors;
+pub mod csrf;
 pub mod error;
+pub mod error_catalog;
+pub mod events;
+pub mod grpc;
 pub mod health;
+pub mod idempotency;
+pub mod jobs;
+#[cfg(feature = "redis")]
+pub mod lock;
 pub mod middleware;
-pub mod tracing;
+pub mod pagination;
+pub mod permission;
+pub mod query;
+pub mod query_metrics;
+pub mod rate_limit;
+pub mod secrets;
+pub mod shadow;
+pub mod startup;
+pub mod supervisor;
+pub mod telemetry;
+pub mod validation;
+pub mod wakeup;
+pub mod webhook;
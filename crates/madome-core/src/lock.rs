@@ -0,0 +1,215 @@
+//! Locks shared by the replicas of a service, held in Redis.
+//!
+//! ```ignore
+//! let lock = DistributedLock::new(conn);
+//! if let Some(guard) = lock.try_acquire("outbox:relay", Duration::from_secs(30)).await? {
+//!     let guard = guard.auto_extend();
+//!     relay_batch().await?;
+//!     guard.release().await?;
+//! } // else another replica is relaying
+//! ```
+//!
+//! A lock is a key set with `SET NX PX` to a random token. Extending and
+//! releasing compare the token first, so a replica whose lock expired cannot
+//! extend or delete the lock another replica took over. The TTL bounds how
+//! long a crashed holder blocks the others; work that may outlast it should
+//! use [`LockGuard::auto_extend`] and check [`LockGuard::is_lost`].
+//!
+//! [`DistributedLock`] is also a [`JobLock`](crate::jobs::JobLock) for the
+//! job scheduler.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use redis::aio::MultiplexedConnection;
+use tokio::task::JoinHandle;
+
+use crate::jobs::JobLock;
+
+/// Deletes the key if it still holds our token.
+const RELEASE: &str = r"if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0";
+
+/// Resets the TTL if the key still holds our token.
+const EXTEND: &str = r"if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+end
+return 0";
+
+/// Hands out [`LockGuard`]s on a Redis shared by the replicas.
+#[derive(Clone)]
+pub struct DistributedLock {
+    conn: MultiplexedConnection,
+}
+
+impl DistributedLock {
+    pub fn new(conn: MultiplexedConnection) -> Self {
+        Self { conn }
+    }
+
+    /// Take `key` for `ttl` unless someone holds it; `None` if they do.
+    pub async fn try_acquire(&self, key: &str, ttl: Duration) -> anyhow::Result<Option<LockGuard>> {
+        let token = uuid::Uuid::new_v4().to_string();
+        if !set_nx(&self.conn, key, &token, ttl).await? {
+            return Ok(None);
+        }
+        Ok(Some(LockGuard {
+            conn: self.conn.clone(),
+            key: key.to_owned(),
+            token,
+            ttl,
+            lost: Arc::new(AtomicBool::new(false)),
+            extender: None,
+            released: false,
+        }))
+    }
+}
+
+impl JobLock for DistributedLock {
+    /// The tick stays claimed until `ttl` runs out, even after the run.
+    async fn try_acquire(&self, key: &str, ttl: Duration) -> anyhow::Result<bool> {
+        set_nx(&self.conn, key, &uuid::Uuid::new_v4().to_string(), ttl).await
+    }
+}
+
+async fn set_nx(
+    conn: &MultiplexedConnection,
+    key: &str,
+    token: &str,
+    ttl: Duration,
+) -> anyhow::Result<bool> {
+    let reply: Option<String> = redis::cmd("SET")
+        .arg(key)
+        .arg(token)
+        .arg("NX")
+        .arg("PX")
+        .arg(ttl.as_millis() as u64)
+        .query_async(&mut conn.clone())
+        .await?;
+    Ok(reply.is_some())
+}
+
+/// A held lock. Dropping it releases the lock in the background; call
+/// [`LockGuard::release`] to release it before going on.
+pub struct LockGuard {
+    conn: MultiplexedConnection,
+    key: String,
+    token: String,
+    ttl: Duration,
+    lost: Arc<AtomicBool>,
+    extender: Option<JoinHandle<()>>,
+    released: bool,
+}
+
+impl LockGuard {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Reset the TTL. `false` means the lock expired and may belong to
+    /// someone else now.
+    pub async fn extend(&self) -> anyhow::Result<bool> {
+        extend(&self.conn, &self.key, &self.token, self.ttl).await
+    }
+
+    /// Extend the lock every third of its TTL until it is released. If an
+    /// extension finds the lock gone, [`LockGuard::is_lost`] turns true and
+    /// extending stops.
+    pub fn auto_extend(mut self) -> Self {
+        let (conn, key, token, ttl) = (
+            self.conn.clone(),
+            self.key.clone(),
+            self.token.clone(),
+            self.ttl,
+        );
+        let lost = Arc::clone(&self.lost);
+        self.extender = Some(tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(ttl / 3).await;
+                match extend(&conn, &key, &token, ttl).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        tracing::warn!(lock = %key, "lock lost before release");
+                        lost.store(true, Ordering::SeqCst);
+                        return;
+                    }
+                    // Retried on the next round; the TTL still has two thirds left.
+                    Err(e) => {
+                        tracing::warn!(lock = %key, error = %format!("{e:#}"), "cannot extend lock")
+                    }
+                }
+            }
+        }));
+        self
+    }
+
+    /// Whether auto-extension found the lock expired. Work protected by it
+    /// should stop.
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::SeqCst)
+    }
+
+    /// Release the lock. `false` means it had already expired.
+    pub async fn release(mut self) -> anyhow::Result<bool> {
+        self.released = true;
+        if let Some(extender) = self.extender.take() {
+            extender.abort();
+        }
+        release(&self.conn, &self.key, &self.token).await
+    }
+}
+
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        if let Some(extender) = self.extender.take() {
+            extender.abort();
+        }
+        if self.released {
+            return;
+        }
+        // Best effort; the TTL frees the lock anyway.
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let (conn, key, token) = (
+                self.conn.clone(),
+                std::mem::take(&mut self.key),
+                std::mem::take(&mut self.token),
+            );
+            runtime.spawn(async move {
+                if let Err(e) = release(&conn, &key, &token).await {
+                    tracing::warn!(lock = %key, error = %format!("{e:#}"), "cannot release lock");
+                }
+            });
+        }
+    }
+}
+
+async fn extend(
+    conn: &MultiplexedConnection,
+    key: &str,
+    token: &str,
+    ttl: Duration,
+) -> anyhow::Result<bool> {
+    let extended: i64 = redis::cmd("EVAL")
+        .arg(EXTEND)
+        .arg(1)
+        .arg(key)
+        .arg(token)
+        .arg(ttl.as_millis() as u64)
+        .query_async(&mut conn.clone())
+        .await?;
+    Ok(extended == 1)
+}
+
+async fn release(conn: &MultiplexedConnection, key: &str, token: &str) -> anyhow::Result<bool> {
+    let deleted: i64 = redis::cmd("EVAL")
+        .arg(RELEASE)
+        .arg(1)
+        .arg(key)
+        .arg(token)
+        .query_async(&mut conn.clone())
+        .await?;
+    Ok(deleted == 1)
+}
@@ -1,6 +1,32 @@
+use std::task::{Context, Poll};
+
+use axum::body::Body;
+use axum::extract::{MatchedPath, Request};
+use axum::http::header::CONTENT_LENGTH;
+use axum::http::{HeaderName, HeaderValue, Method};
+use axum::middleware::Next;
+use axum::response::Response;
+use tower::layer::util::{Identity, Stack};
+use tower::{Layer, Service, ServiceBuilder};
+use tower_http::body::Limited;
+use tower_http::compression::CompressionLayer;
+use tower_http::limit::{RequestBodyLimit, ResponseBody, ResponseFuture};
 use tower_http::request_id::{MakeRequestId, RequestId, SetRequestIdLayer};
+use tracing::Instrument;
 use uuid::Uuid;
 
+use crate::error_catalog::ErrorBody;
+
+/// Request header carrying the per-request id (set by [`request_id_layer`]).
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Response header echoing the request id so users can quote it in reports.
+pub const TRACE_ID_HEADER: HeaderName = HeaderName::from_static("x-madome-trace-id");
+
+/// Request header the gateway sets to the staff user id when the access token
+/// carries an `impersonator` claim.
+pub const IMPERSONATOR_HEADER: HeaderName = HeaderName::from_static("x-madome-impersonator-id");
+
 #[derive(Clone, Default)]
 pub struct MakeUuidRequestId;
 
@@ -13,8 +39,396 @@ impl MakeRequestId for MakeUuidRequestId {
 
 /// Build the request-id layer. Apply with `.layer(request_id_layer())` in router.
 pub fn request_id_layer() -> SetRequestIdLayer<MakeUuidRequestId> {
-    SetRequestIdLayer::new(
-        axum::http::HeaderName::from_static("x-request-id"),
-        MakeUuidRequestId,
-    )
+    SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeUuidRequestId)
+}
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: String;
+}
+
+/// Id of the request this task is serving, as set by [`trace_id`] or
+/// [`crate::grpc::GrpcTraceLayer`]. gRPC clients forward it to the next service.
+///
+/// Tasks spawned while serving a request do not inherit it; wrap their future
+/// in [`with_request_id`] to keep the correlation.
+pub fn current_request_id() -> Option<String> {
+    CURRENT_REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Run `fut` with `id` as its [`current_request_id`].
+pub async fn with_request_id<F: Future>(id: String, fut: F) -> F::Output {
+    CURRENT_REQUEST_ID.scope(id, fut).await
+}
+
+/// Request bodies larger than this are refused with 413 unless the route has a
+/// [`BodyLimit`] (1 MiB).
+pub const DEFAULT_BODY_LIMIT: usize = 1024 * 1024;
+
+/// Request body limit of one route, replacing [`DEFAULT_BODY_LIMIT`].
+#[derive(Debug, Clone)]
+pub struct BodyLimit {
+    pub method: Method,
+    /// Route template exactly as registered, e.g. `/auth/sessions/{id}`.
+    pub path: &'static str,
+    pub bytes: usize,
+}
+
+impl BodyLimit {
+    pub const fn new(method: Method, path: &'static str, bytes: usize) -> Self {
+        Self {
+            method,
+            path,
+            bytes,
+        }
+    }
+}
+
+/// Layers every service router ends with, outermost first.
+pub type DefaultStack = ServiceBuilder<
+    Stack<
+        RouteBodyLimitLayer,
+        Stack<CompressionLayer, Stack<SetRequestIdLayer<MakeUuidRequestId>, Identity>>,
+    >,
+>;
+
+/// Build the shared outer stack: [`request_id_layer`], gzip/brotli response
+/// compression (as the client's `Accept-Encoding` allows) and request body
+/// limits (`limits` over [`DEFAULT_BODY_LIMIT`]).
+///
+/// Apply it last, around [`trace_id`]: that middleware rewrites error bodies,
+/// so it must run before they are compressed. Bodies refused by their
+/// `Content-Length` get a plain 413 without a trace id; bodies that turn out
+/// too large while streaming are refused by the extractor (also 413).
+///
+/// ```
+/// use axum::{Router, http::Method, middleware, routing::post};
+/// use madome_core::middleware::{BodyLimit, default_stack, trace_id};
+///
+/// static BODY_LIMITS: &[BodyLimit] = &[BodyLimit::new(Method::POST, "/upload", 8 << 20)];
+///
+/// let app: Router = Router::new()
+///     .route("/upload", post(|| async {}))
+///     .layer(middleware::from_fn(trace_id))
+///     .layer(default_stack(BODY_LIMITS));
+/// ```
+pub fn default_stack(limits: &'static [BodyLimit]) -> DefaultStack {
+    ServiceBuilder::new()
+        .layer(request_id_layer())
+        .layer(CompressionLayer::new())
+        .layer(RouteBodyLimitLayer { limits })
+}
+
+/// Applies [`RequestBodyLimit`] with the limit of the matched route.
+#[derive(Debug, Clone)]
+pub struct RouteBodyLimitLayer {
+    limits: &'static [BodyLimit],
+}
+
+impl<S> Layer<S> for RouteBodyLimitLayer {
+    type Service = RouteBodyLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RouteBodyLimitService {
+            inner,
+            limits: self.limits,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RouteBodyLimitService<S> {
+    inner: S,
+    limits: &'static [BodyLimit],
+}
+
+impl<S> RouteBodyLimitService<S> {
+    fn limit_for<B>(&self, req: &axum::http::Request<B>) -> usize {
+        let Some(path) = req.extensions().get::<MatchedPath>() else {
+            return DEFAULT_BODY_LIMIT;
+        };
+        self.limits
+            .iter()
+            .find(|l| l.method == req.method() && l.path == path.as_str())
+            .map_or(DEFAULT_BODY_LIMIT, |l| l.bytes)
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<axum::http::Request<ReqBody>> for RouteBodyLimitService<S>
+where
+    S: Service<axum::http::Request<Limited<ReqBody>>, Response = axum::http::Response<ResBody>>
+        + Clone,
+    ResBody: axum::body::HttpBody,
+{
+    type Response = axum::http::Response<ResponseBody<ResBody>>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: axum::http::Request<ReqBody>) -> Self::Future {
+        let limit = self.limit_for(&req);
+        // Keep the instance `poll_ready` was called on.
+        let clone = self.inner.clone();
+        let inner = std::mem::replace(&mut self.inner, clone);
+        RequestBodyLimit::new(inner, limit).call(req)
+    }
+}
+
+/// Middleware exposing the request id as the trace id.
+///
+/// Runs the request inside a `request` span carrying `trace_id`, `method` and `path`, sets the
+/// `x-madome-trace-id` response header, and fills `trace_id` into error bodies. The id is also
+/// the [`current_request_id`] while the handler runs, so gRPC calls it makes carry it along.
+/// Install inside [`request_id_layer`] or [`default_stack`] (i.e. add it to the
+/// router before it) so the id is already set:
+///
+/// ```
+/// use axum::{Router, middleware, routing::get};
+/// use madome_core::middleware::{request_id_layer, trace_id};
+///
+/// let app: Router = Router::new()
+///     .route("/", get(|| async {}))
+///     .layer(middleware::from_fn(trace_id))
+///     .layer(request_id_layer());
+/// ```
+pub async fn trace_id(req: Request, next: Next) -> Response {
+    let trace_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let span = tracing::info_span!(
+        "request",
+        trace_id = %trace_id,
+        method = %req.method(),
+        path = req.uri().path(),
+    );
+    let response = with_request_id(trace_id.clone(), next.run(req))
+        .instrument(span)
+        .await;
+
+    let mut response = match response.extensions().get::<ErrorBody>().cloned() {
+        Some(body) => with_trace_id(response, body, &trace_id),
+        None => response,
+    };
+    if let Ok(value) = HeaderValue::from_str(&trace_id) {
+        response.headers_mut().insert(TRACE_ID_HEADER, value);
+    }
+    response
+}
+
+/// Middleware writing one `audit` event per impersonated request.
+///
+/// Requests without [`IMPERSONATOR_HEADER`] pass through untouched. Install it
+/// inside [`trace_id`] so the event is recorded in the request span and carries
+/// its trace id.
+pub async fn audit_impersonation(req: Request, next: Next) -> Response {
+    let Some(impersonator) = req
+        .headers()
+        .get(IMPERSONATOR_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned)
+    else {
+        return next.run(req).await;
+    };
+    let user_id = req
+        .headers()
+        .get("x-madome-user-id")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+    let method = req.method().clone();
+    let path = req.uri().path().to_owned();
+
+    let response = next.run(req).await;
+    tracing::info!(
+        target: "audit",
+        %impersonator,
+        %user_id,
+        %method,
+        %path,
+        status = response.status().as_u16(),
+        "impersonated request",
+    );
+    response
+}
+
+/// Re-serialize an error response's body with `trace_id` set, keeping status and headers.
+fn with_trace_id(response: Response, body: ErrorBody, trace_id: &str) -> Response {
+    let (mut parts, _) = response.into_parts();
+    let body = ErrorBody {
+        trace_id: Some(trace_id.to_owned()),
+        ..body
+    };
+    let json = serde_json::to_vec(&body).expect("ErrorBody always serializes");
+    parts.headers.remove(CONTENT_LENGTH);
+    parts.extensions.insert(body);
+    Response::from_parts(parts, Body::from(json))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::to_bytes;
+    use axum::http::{Request as HttpRequest, StatusCode};
+    use axum::middleware;
+    use axum::routing::{get, post};
+    use tower::ServiceExt;
+
+    use crate::error::AppError;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/ok", get(|| async { "ok" }))
+            .route("/missing", get(|| async { AppError::NotFound }))
+            .layer(middleware::from_fn(audit_impersonation))
+            .layer(middleware::from_fn(trace_id))
+            .layer(request_id_layer())
+    }
+
+    async fn get_path(path: &str, request_id: Option<&str>) -> Response {
+        let mut req = HttpRequest::get(path);
+        if let Some(id) = request_id {
+            req = req.header(REQUEST_ID_HEADER, id);
+        }
+        app()
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_echo_incoming_request_id_as_trace_id() {
+        let response = get_path("/ok", Some("req-123")).await;
+        assert_eq!(response.headers()[TRACE_ID_HEADER], "req-123");
+    }
+
+    #[tokio::test]
+    async fn should_generate_trace_id_when_none_sent() {
+        let response = get_path("/ok", None).await;
+        let trace_id = response.headers()[TRACE_ID_HEADER].to_str().unwrap();
+        assert!(Uuid::parse_str(trace_id).is_ok());
+    }
+
+    #[tokio::test]
+    async fn should_expose_request_id_to_handler() {
+        let app = Router::new()
+            .route(
+                "/id",
+                get(|| async { current_request_id().unwrap_or_default() }),
+            )
+            .layer(middleware::from_fn(trace_id))
+            .layer(request_id_layer());
+        let req = HttpRequest::get("/id")
+            .header(REQUEST_ID_HEADER, "req-789")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(req).await.unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"req-789");
+        assert_eq!(current_request_id(), None);
+    }
+
+    #[tokio::test]
+    async fn should_pass_impersonated_request_through() {
+        let req = HttpRequest::get("/ok")
+            .header(IMPERSONATOR_HEADER, Uuid::new_v4().to_string())
+            .body(Body::empty())
+            .unwrap();
+        let response = app().oneshot(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    static LIMITS: &[BodyLimit] = &[BodyLimit::new(
+        Method::POST,
+        "/upload",
+        4 * DEFAULT_BODY_LIMIT,
+    )];
+
+    fn stacked_app() -> Router {
+        let echo_len = post(|body: axum::body::Bytes| async move { body.len().to_string() });
+        Router::new()
+            .route("/echo", echo_len.clone())
+            .route("/upload", echo_len)
+            .route("/large", get(|| async { "madome ".repeat(1000) }))
+            .layer(middleware::from_fn(trace_id))
+            .layer(default_stack(LIMITS))
+    }
+
+    async fn get_path_stacked(path: &str) -> Response {
+        let req = HttpRequest::get(path).body(Body::empty()).unwrap();
+        stacked_app().oneshot(req).await.unwrap()
+    }
+
+    /// Without `content_length` the limit is only enforced while reading, as
+    /// for chunked uploads.
+    async fn post_bytes(path: &str, len: usize, content_length: bool) -> Response {
+        let mut req = HttpRequest::post(path);
+        if content_length {
+            req = req.header(CONTENT_LENGTH, len);
+        }
+        let req = req.body(Body::from(vec![b'a'; len])).unwrap();
+        stacked_app().oneshot(req).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_compress_large_response_when_accepted() {
+        let req = HttpRequest::get("/large")
+            .header("accept-encoding", "gzip")
+            .body(Body::empty())
+            .unwrap();
+        let response = stacked_app().oneshot(req).await.unwrap();
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(body.len() < 7000);
+    }
+
+    #[tokio::test]
+    async fn should_not_compress_without_accept_encoding() {
+        let response = get_path_stacked("/large").await;
+        assert!(!response.headers().contains_key("content-encoding"));
+        assert!(response.headers().contains_key(TRACE_ID_HEADER));
+    }
+
+    #[tokio::test]
+    async fn should_accept_body_within_default_limit() {
+        let response = post_bytes("/echo", DEFAULT_BODY_LIMIT, true).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_reject_body_over_default_limit_by_content_length() {
+        let response = post_bytes("/echo", DEFAULT_BODY_LIMIT + 1, true).await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn should_reject_streamed_body_over_default_limit() {
+        let response = post_bytes("/echo", DEFAULT_BODY_LIMIT + 1, false).await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn should_apply_route_override() {
+        let response = post_bytes("/upload", 2 * DEFAULT_BODY_LIMIT, true).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = post_bytes("/upload", 4 * DEFAULT_BODY_LIMIT + 1, false).await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn should_include_trace_id_in_error_body() {
+        let response = get_path("/missing", Some("req-456")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(
+            std::str::from_utf8(&body).unwrap(),
+            r#"{"kind":"not_found","message":"not found","trace_id":"req-456"}"#
+        );
+    }
 }
@@ -0,0 +1,43 @@
+use axum::http::{HeaderName, HeaderValue};
+use madome_domain::pagination::KeysetCursor;
+
+/// Response header carrying the total number of rows behind a paginated list.
+pub const TOTAL_COUNT_HEADER: HeaderName = HeaderName::from_static("x-madome-total-count");
+
+/// Header pair for a list response, e.g. `(total_count_header(page.total), Json(page.items))`.
+pub fn total_count_header(total: u64) -> [(HeaderName, HeaderValue); 1] {
+    [(TOTAL_COUNT_HEADER, HeaderValue::from(total))]
+}
+
+/// Response header carrying the `after` cursor of the next page of a
+/// keyset-paginated list; absent on the last page.
+pub const NEXT_CURSOR_HEADER: HeaderName = HeaderName::from_static("x-madome-next-cursor");
+
+/// Header pair for a keyset-paginated list response, e.g.
+/// `(next_cursor_header(page.next.as_ref()), Json(page.items))`.
+pub fn next_cursor_header(next: Option<&KeysetCursor>) -> Option<[(HeaderName, HeaderValue); 1]> {
+    let value = HeaderValue::from_str(&next?.to_string()).expect("cursors are visible ASCII");
+    Some([(NEXT_CURSOR_HEADER, value)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+
+    #[test]
+    fn should_set_total_count_header() {
+        let response = (total_count_header(42), "[]").into_response();
+        assert_eq!(response.headers()[TOTAL_COUNT_HEADER], "42");
+    }
+
+    #[test]
+    fn should_set_next_cursor_header_unless_last_page() {
+        let cursor = KeysetCursor::new(1, "book:2");
+        let response = (next_cursor_header(Some(&cursor)), "[]").into_response();
+        assert_eq!(response.headers()[NEXT_CURSOR_HEADER], cursor.to_string());
+
+        let response = (next_cursor_header(None), "[]").into_response();
+        assert!(!response.headers().contains_key(NEXT_CURSOR_HEADER));
+    }
+}
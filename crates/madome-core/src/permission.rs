@@ -0,0 +1,267 @@
+//! Declarative route → minimum-role enforcement.
+//!
+//! Each service lists every route it serves in one [`RoutePermission`] table
+//! in its router module and installs [`require_role`] over all of them. Keeping
+//! the whole matrix in one place lets a test snapshot it (see [`render`]), so a
+//! route that silently becomes reachable by a lower role shows up as a diff.
+//!
+//! Requests made with an API token (those carrying
+//! [`TOKEN_SCOPES_HEADER`]) only reach role routes opened to one of the
+//! token's scopes with [`RoutePermission::scoped`]; every other role route is
+//! for browser sessions only.
+//!
+//! ```
+//! use axum::{Router, http::Method, middleware, routing::get};
+//! use madome_core::permission::{Access, RoutePermission, require_role};
+//! use madome_domain::user::UserRole;
+//!
+//! static PERMISSIONS: &[RoutePermission] = &[
+//!     RoutePermission::new(Method::GET, "/public", Access::Public),
+//!     RoutePermission::new(Method::GET, "/admin", Access::Role(UserRole::Bot)),
+//!     RoutePermission::new(Method::GET, "/library", Access::Role(UserRole::Normal))
+//!         .scoped("library.read"),
+//! ];
+//!
+//! let app: Router = Router::new()
+//!     .route("/public", get(|| async {}))
+//!     .route("/admin", get(|| async {}))
+//!     .route("/library", get(|| async {}))
+//!     .route_layer(middleware::from_fn_with_state(PERMISSIONS, require_role));
+//! ```
+
+use std::fmt::Write;
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use madome_auth_types::api_token::TOKEN_SCOPES_HEADER;
+use madome_domain::user::UserRole;
+
+use crate::error::AppError;
+
+/// Who may call a route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// No gateway identity required; the handler authenticates by itself
+    /// (token cookies, sign-in codes) or not at all.
+    Public,
+    /// Gateway identity with at least this role.
+    Role(UserRole),
+}
+
+/// One row of a service's permission matrix.
+#[derive(Debug, Clone)]
+pub struct RoutePermission {
+    pub method: Method,
+    /// Route template exactly as registered, e.g. `/auth/sessions/{id}`.
+    pub path: &'static str,
+    pub access: Access,
+    /// API token scope that opens a role route to API tokens; `None` keeps
+    /// the route to browser sessions.
+    pub scope: Option<&'static str>,
+}
+
+impl RoutePermission {
+    pub const fn new(method: Method, path: &'static str, access: Access) -> Self {
+        Self {
+            method,
+            path,
+            access,
+            scope: None,
+        }
+    }
+
+    /// Also serve API tokens granted `scope`.
+    pub const fn scoped(mut self, scope: &'static str) -> Self {
+        self.scope = Some(scope);
+        self
+    }
+}
+
+/// Entry for `method path`. `HEAD` falls back to the `GET` entry, as axum
+/// serves it with the `GET` handler.
+pub fn lookup<'a>(
+    matrix: &'a [RoutePermission],
+    method: &Method,
+    path: &str,
+) -> Option<&'a RoutePermission> {
+    let find = |m: &Method| matrix.iter().find(|p| p.method == m && p.path == path);
+    match find(method) {
+        None if method == Method::HEAD => find(&Method::GET),
+        found => found,
+    }
+}
+
+/// One `METHOD path access` line per entry, in table order.
+pub fn render(matrix: &[RoutePermission]) -> String {
+    let mut out = String::new();
+    for p in matrix {
+        let access = match p.access {
+            Access::Public => "public".to_owned(),
+            Access::Role(role) => format!("role>={} ({role:?})", role.as_u8()),
+        };
+        let access = match p.scope {
+            Some(scope) => format!("{access} scope={scope}"),
+            None => access,
+        };
+        let _ = writeln!(out, "{:<6} {} {access}", p.method.as_str(), p.path);
+    }
+    out
+}
+
+/// Middleware enforcing the matrix passed as state.
+///
+/// Routes missing from the matrix are refused (403) rather than served, so a
+/// new route cannot ship without a decision about who may call it. A role
+/// route answers 401 without the `x-madome-user-role` header and 403 when the
+/// role is too low, or when an API token calls it without the route's scope.
+///
+/// Install with `.route_layer(...)` after the routes are added; a plain
+/// `.layer(...)` runs before routing and sees no matched path.
+pub async fn require_role(
+    State(matrix): State<&'static [RoutePermission]>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(path) = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str)
+    else {
+        return AppError::Forbidden.into_response();
+    };
+    let Some(permission) = lookup(matrix, req.method(), path) else {
+        tracing::error!(method = %req.method(), path, "route missing from permission matrix");
+        return AppError::Forbidden.into_response();
+    };
+
+    let min_role = match permission.access {
+        Access::Public => return next.run(req).await,
+        Access::Role(role) => role,
+    };
+    let role = req
+        .headers()
+        .get("x-madome-user-role")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.parse::<u8>().ok());
+    match role {
+        None => return AppError::Unauthorized.into_response(),
+        Some(role) if role < min_role.as_u8() => return AppError::Forbidden.into_response(),
+        Some(_) => {}
+    }
+
+    // Garbled scopes grant nothing rather than lifting the restriction.
+    if let Some(scopes) = req.headers().get(TOKEN_SCOPES_HEADER) {
+        let granted = permission.scope.is_some_and(|scope| {
+            scopes
+                .to_str()
+                .is_ok_and(|s| s.split_whitespace().any(|s| s == scope))
+        });
+        if !granted {
+            return AppError::Forbidden.into_response();
+        }
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::{Request as HttpRequest, StatusCode};
+    use axum::middleware;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    static MATRIX: &[RoutePermission] = &[
+        RoutePermission::new(Method::GET, "/open", Access::Public),
+        RoutePermission::new(Method::GET, "/items/{id}", Access::Role(UserRole::Normal)),
+        RoutePermission::new(Method::GET, "/admin", Access::Role(UserRole::Bot)),
+        RoutePermission::new(Method::GET, "/library", Access::Role(UserRole::Normal))
+            .scoped("library.read"),
+    ];
+
+    fn app() -> Router {
+        Router::new()
+            .route("/open", get(|| async {}))
+            .route("/items/{id}", get(|| async {}))
+            .route("/admin", get(|| async {}))
+            .route("/library", get(|| async {}))
+            .route("/unlisted", get(|| async {}))
+            .route_layer(middleware::from_fn_with_state(MATRIX, require_role))
+    }
+
+    async fn status(path: &str, role: Option<&str>) -> StatusCode {
+        scoped_status(path, role, None).await
+    }
+
+    async fn scoped_status(path: &str, role: Option<&str>, scopes: Option<&str>) -> StatusCode {
+        let mut req = HttpRequest::get(path);
+        if let Some(role) = role {
+            req = req.header("x-madome-user-role", role);
+        }
+        if let Some(scopes) = scopes {
+            req = req.header(TOKEN_SCOPES_HEADER, scopes);
+        }
+        app()
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn should_serve_public_route_without_identity() {
+        assert_eq!(status("/open", None).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_match_templated_route() {
+        assert_eq!(status("/items/42", Some("0")).await, StatusCode::OK);
+        assert_eq!(status("/items/42", None).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn should_forbid_role_below_minimum() {
+        assert_eq!(status("/admin", Some("1")).await, StatusCode::FORBIDDEN);
+        assert_eq!(status("/admin", Some("2")).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_refuse_route_missing_from_matrix() {
+        assert_eq!(status("/unlisted", Some("2")).await, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn should_refuse_api_token_on_unscoped_route() {
+        let scoped = |path| scoped_status(path, Some("2"), Some("library.read"));
+        assert_eq!(scoped("/admin").await, StatusCode::FORBIDDEN);
+        assert_eq!(scoped("/items/42").await, StatusCode::FORBIDDEN);
+        assert_eq!(scoped("/open").await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_serve_api_token_granted_route_scope() {
+        let status = |scopes| scoped_status("/library", Some("0"), Some(scopes));
+        assert_eq!(status("tastes.read library.read").await, StatusCode::OK);
+        assert_eq!(status("tastes.read").await, StatusCode::FORBIDDEN);
+        assert_eq!(status("").await, StatusCode::FORBIDDEN);
+        assert_eq!(
+            scoped_status("/library", Some("0"), None).await,
+            StatusCode::OK
+        );
+    }
+
+    #[test]
+    fn should_render_one_line_per_entry() {
+        assert_eq!(
+            render(MATRIX),
+            "GET    /open public\n\
+             GET    /items/{id} role>=0 (Normal)\n\
+             GET    /admin role>=2 (Bot)\n\
+             GET    /library role>=0 (Normal) scope=library.read\n"
+        );
+    }
+}
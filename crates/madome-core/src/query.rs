@@ -0,0 +1,140 @@
+//! Query-string extractor with bracketed arrays and field-level 400 errors.
+//!
+//! axum's `Query<T>` (serde_urlencoded) cannot read `book-ids[]=1&book-ids[]=2`
+//! and answers a bad value with a plain-text 400. [`QsQuery<T>`] parses with
+//! serde_qs instead and rejects with an [`ErrorBody`] of kind `bad_request`
+//! whose `errors` name the offending parameter:
+//!
+//! ```json
+//! { "kind": "bad_request", "message": "invalid query string",
+//!   "errors": [ { "field": "per-page", "code": "invalid" } ] }
+//! ```
+//!
+//! Field names are the wire names, so kebab-case parameters are declared with
+//! `#[serde(rename = "per-page")]` on the query struct as before.
+
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::response::{IntoResponse, Response};
+use serde::de::DeserializeOwned;
+
+use crate::error_catalog::{ErrorBody, ErrorKind};
+use crate::validation::{ValidationErrors, deserialize_error};
+
+/// Nesting allowed in parameter names (`a[b][c]`); deeper input is rejected.
+const MAX_DEPTH: usize = 5;
+
+/// Query string deserialized with serde_qs; use in place of `Query<T>`.
+///
+/// ```
+/// use madome_core::query::parse_query;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Tastes {
+///     #[serde(rename = "book-ids", default)]
+///     book_ids: Vec<u32>,
+/// }
+///
+/// let q: Tastes = parse_query("book-ids[]=1&book-ids[]=2").unwrap();
+/// assert_eq!(q.book_ids, [1, 2]);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QsQuery<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for QsQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parse_query(parts.uri.query().unwrap_or_default())
+            .map(Self)
+            .map_err(|errors| {
+                ErrorBody {
+                    errors: errors.errors,
+                    ..ErrorBody::new(ErrorKind::BadRequest, "invalid query string")
+                }
+                .into_response()
+            })
+    }
+}
+
+/// Parse a raw query string (without `?`) the way [`QsQuery`] does.
+///
+/// Percent-encoded brackets (`book-ids%5B%5D=1`) are accepted, as browsers and
+/// `URLSearchParams` send them that way.
+pub fn parse_query<T: DeserializeOwned>(query: &str) -> Result<T, ValidationErrors> {
+    let config = serde_qs::Config::new(MAX_DEPTH, false);
+    let deserializer =
+        serde_qs::Deserializer::with_config(&config, query.as_bytes()).map_err(|_| {
+            let mut errors = ValidationErrors::new();
+            errors.add("query", "invalid");
+            errors
+        })?;
+    serde_path_to_error::deserialize(deserializer).map_err(|e| deserialize_error(e, "query"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::{Body, to_bytes};
+    use axum::extract::Request;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use serde::Deserialize;
+    use tower::ServiceExt;
+
+    #[derive(Debug, Deserialize)]
+    struct ListQuery {
+        #[serde(rename = "per-page", default)]
+        per_page: Option<u32>,
+        #[serde(rename = "book-ids", default)]
+        book_ids: Vec<u32>,
+        #[serde(rename = "sort-by")]
+        sort_by: String,
+    }
+
+    async fn send(query: &str) -> (StatusCode, String) {
+        let app = Router::new().route(
+            "/",
+            get(|QsQuery(q): QsQuery<ListQuery>| async move {
+                format!("{:?} {:?} {}", q.per_page, q.book_ids, q.sort_by)
+            }),
+        );
+        let req = Request::get(format!("/?{query}"))
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        let status = res.status();
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn should_read_bracketed_and_percent_encoded_arrays() {
+        let (status, body) = send("sort-by=random&book-ids[]=1&book-ids%5B%5D=2").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "None [1, 2] random");
+    }
+
+    #[tokio::test]
+    async fn should_name_invalid_parameter() {
+        let (status, body) = send("sort-by=random&per-page=many").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body,
+            r#"{"kind":"bad_request","message":"invalid query string","errors":[{"field":"per-page","code":"invalid"}]}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn should_name_missing_parameter() {
+        let (status, body) = send("per-page=10").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains(r#"{"field":"sort-by","code":"required"}"#));
+    }
+}
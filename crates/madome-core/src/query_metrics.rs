@@ -0,0 +1,318 @@
+//! Latency of database queries, by the context string each repository call
+//! names.
+//!
+//! Repositories await their queries through [`TimedQuery::timed`], which
+//! attaches the context to errors the way `anyhow::Context::context` does and
+//! records the call in [`QueryMetrics::global`]:
+//!
+//! ```
+//! use madome_core::query_metrics::{QueryMetrics, TimedQuery};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! // e.g. `users::Entity::find_by_id(id).one(&self.db)`
+//! let query = async { Ok::<_, std::io::Error>(Some(42)) };
+//! let user = query.timed("find user by id").await?;
+//! assert_eq!(user, Some(42));
+//!
+//! let (context, latency) = &QueryMetrics::global().snapshot()[0];
+//! assert_eq!((*context, latency.count), ("find user by id", 1));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A call slower than the slow-query threshold (500 ms unless changed with
+//! [`QueryMetrics::set_slow_threshold`]) logs a `slow query` warning under the
+//! `slow_query` target. It is logged inside the request span, so it carries
+//! the trace id. [`QueryMetrics::report_job`] logs the latency of every context
+//! on a schedule.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Context as _;
+
+use crate::jobs::{Job, Schedule};
+
+/// Upper bounds of the histogram buckets in milliseconds; slower calls land
+/// in one last open bucket.
+const BUCKETS_MS: [u64; 12] = [1, 2, 5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000];
+
+/// Slow-query threshold of [`QueryMetrics::global`] until it is changed.
+pub const DEFAULT_SLOW_THRESHOLD: Duration = Duration::from_millis(500);
+
+/// Latency of one query context.
+#[derive(Debug, Default)]
+pub struct LatencyHistogram {
+    buckets: [AtomicU64; BUCKETS_MS.len() + 1],
+    failed: AtomicU64,
+    sum_micros: AtomicU64,
+    max_micros: AtomicU64,
+}
+
+impl LatencyHistogram {
+    pub fn record(&self, elapsed: Duration, failed: bool) {
+        let ms = elapsed.as_millis();
+        let bucket = BUCKETS_MS
+            .iter()
+            .position(|&bound| ms < u128::from(bound))
+            .unwrap_or(BUCKETS_MS.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        if failed {
+            self.failed.fetch_add(1, Ordering::Relaxed);
+        }
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+        self.max_micros.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> LatencySnapshot {
+        let buckets: Vec<u64> = self
+            .buckets
+            .iter()
+            .map(|b| b.load(Ordering::Relaxed))
+            .collect();
+        let count = buckets.iter().sum();
+        let max = Duration::from_micros(self.max_micros.load(Ordering::Relaxed));
+        let quantile = |q: f64| {
+            let rank = (count as f64 * q).ceil().max(1.0) as u64;
+            let mut seen = 0;
+            for (i, n) in buckets.iter().enumerate() {
+                seen += n;
+                if seen >= rank {
+                    return BUCKETS_MS
+                        .get(i)
+                        .map_or(max, |&ms| Duration::from_millis(ms).min(max));
+                }
+            }
+            max
+        };
+        LatencySnapshot {
+            count,
+            failed: self.failed.load(Ordering::Relaxed),
+            mean: match count {
+                0 => Duration::ZERO,
+                n => Duration::from_micros(self.sum_micros.load(Ordering::Relaxed) / n),
+            },
+            p50: quantile(0.5),
+            p95: quantile(0.95),
+            p99: quantile(0.99),
+            max,
+        }
+    }
+}
+
+/// Counters of one query context since the process started.
+///
+/// Percentiles are the upper bound of the bucket they fall in (capped at
+/// `max`), so they overstate by at most one bucket.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencySnapshot {
+    pub count: u64,
+    pub failed: u64,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+/// Latency histograms by query context, and the slow-query threshold.
+#[derive(Debug)]
+pub struct QueryMetrics {
+    /// In milliseconds; `u64::MAX` disables slow-query logging.
+    slow_threshold_ms: AtomicU64,
+    by_context: Mutex<HashMap<&'static str, Arc<LatencyHistogram>>>,
+}
+
+static GLOBAL: LazyLock<QueryMetrics> =
+    LazyLock::new(|| QueryMetrics::new(Some(DEFAULT_SLOW_THRESHOLD)));
+
+impl QueryMetrics {
+    /// `slow_threshold` of `None` logs no slow queries.
+    pub fn new(slow_threshold: Option<Duration>) -> Self {
+        let metrics = Self {
+            slow_threshold_ms: AtomicU64::new(u64::MAX),
+            by_context: Mutex::default(),
+        };
+        metrics.set_slow_threshold(slow_threshold);
+        metrics
+    }
+
+    /// The registry [`TimedQuery::timed`] records into.
+    pub fn global() -> &'static Self {
+        &GLOBAL
+    }
+
+    pub fn set_slow_threshold(&self, threshold: Option<Duration>) {
+        let ms = threshold.map_or(u64::MAX, |t| {
+            u64::try_from(t.as_millis()).unwrap_or(u64::MAX)
+        });
+        self.slow_threshold_ms.store(ms, Ordering::Relaxed);
+    }
+
+    /// Await `query`, recording its latency under `context` and attaching
+    /// `context` to its error.
+    pub async fn time<T, E>(
+        &self,
+        context: &'static str,
+        query: impl Future<Output = Result<T, E>>,
+    ) -> anyhow::Result<T>
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let started = Instant::now();
+        let result = query.await;
+        let elapsed = started.elapsed();
+        self.record(context, elapsed, result.is_err());
+        result.context(context)
+    }
+
+    pub fn record(&self, context: &'static str, elapsed: Duration, failed: bool) {
+        let histogram = Arc::clone(
+            self.by_context
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .entry(context)
+                .or_default(),
+        );
+        histogram.record(elapsed, failed);
+
+        if elapsed.as_millis() >= u128::from(self.slow_threshold_ms.load(Ordering::Relaxed)) {
+            tracing::warn!(
+                target: "slow_query",
+                context,
+                elapsed_ms = elapsed.as_millis() as u64,
+                failed,
+                "slow query",
+            );
+        }
+    }
+
+    /// Every context recorded so far, sorted by name.
+    pub fn snapshot(&self) -> Vec<(&'static str, LatencySnapshot)> {
+        let mut snapshot: Vec<_> = self
+            .by_context
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(context, histogram)| (*context, histogram.snapshot()))
+            .collect();
+        snapshot.sort_unstable_by_key(|(context, _)| *context);
+        snapshot
+    }
+
+    /// Job logging one `query latency` event per context under the
+    /// `query_metrics` target. Counts are cumulative since startup.
+    pub fn report_job(&'static self, every: Duration) -> Job {
+        Job::new(
+            "query_metrics_report",
+            Schedule::every(every),
+            move || async move {
+                for (context, latency) in self.snapshot() {
+                    tracing::info!(
+                        target: "query_metrics",
+                        context,
+                        count = latency.count,
+                        failed = latency.failed,
+                        mean_ms = latency.mean.as_secs_f64() * 1000.0,
+                        p50_ms = latency.p50.as_millis() as u64,
+                        p95_ms = latency.p95.as_millis() as u64,
+                        p99_ms = latency.p99.as_millis() as u64,
+                        max_ms = latency.max.as_millis() as u64,
+                        "query latency",
+                    );
+                }
+                Ok(())
+            },
+        )
+        .every_replica()
+    }
+}
+
+/// [`QueryMetrics::time`] on [`QueryMetrics::global`], as a method on the
+/// query future.
+pub trait TimedQuery<T, E>: Future<Output = Result<T, E>> + Sized {
+    fn timed(self, context: &'static str) -> impl Future<Output = anyhow::Result<T>>;
+}
+
+impl<F, T, E> TimedQuery<T, E> for F
+where
+    F: Future<Output = Result<T, E>>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    async fn timed(self, context: &'static str) -> anyhow::Result<T> {
+        QueryMetrics::global().time(context, self).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn should_report_bucket_bounds_as_percentiles() {
+        let histogram = LatencyHistogram::default();
+        for _ in 0..90 {
+            histogram.record(ms(3), false);
+        }
+        for _ in 0..9 {
+            histogram.record(ms(40), false);
+        }
+        histogram.record(ms(1800), true);
+
+        let latency = histogram.snapshot();
+        assert_eq!((latency.count, latency.failed), (100, 1));
+        assert_eq!(latency.p50, ms(5));
+        assert_eq!(latency.p95, ms(50));
+        assert_eq!(latency.p99, ms(50));
+        assert_eq!(latency.max, ms(1800));
+    }
+
+    #[test]
+    fn should_cap_percentiles_at_max() {
+        let histogram = LatencyHistogram::default();
+        histogram.record(ms(7000), false);
+        histogram.record(ms(120), false);
+
+        let latency = histogram.snapshot();
+        assert_eq!(latency.p50, ms(250));
+        assert_eq!(latency.p99, ms(7000));
+        assert_eq!(
+            LatencyHistogram::default().snapshot(),
+            LatencySnapshot::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn should_time_queries_by_context_and_keep_error_context() {
+        let metrics = QueryMetrics::new(None);
+        let found = metrics
+            .time("find user", async { Ok::<_, std::io::Error>(1) })
+            .await
+            .unwrap();
+        assert_eq!(found, 1);
+
+        let error = metrics
+            .time("delete user", async {
+                Err::<(), _>(std::io::Error::other("connection reset"))
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(format!("{error:#}"), "delete user: connection reset");
+
+        let contexts: Vec<_> = metrics
+            .snapshot()
+            .into_iter()
+            .map(|(context, latency)| (context, latency.count, latency.failed))
+            .collect();
+        assert_eq!(contexts, [("delete user", 1, 1), ("find user", 1, 0)]);
+    }
+}
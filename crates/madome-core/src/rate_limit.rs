@@ -0,0 +1,101 @@
+//! `RateLimit-*` response headers for throttled routes.
+//!
+//! A route that counts requests against a quota returns its [`RateLimit`]
+//! alongside the response, success or `429` alike, so clients can slow down
+//! before they are refused:
+//!
+//! ```
+//! use axum::http::StatusCode;
+//! use axum::response::IntoResponse;
+//! use madome_core::error_catalog::{ErrorBody, ErrorKind};
+//! use madome_core::rate_limit::RateLimit;
+//!
+//! let refused = (
+//!     RateLimit::exhausted(5, 600),
+//!     ErrorBody::new(ErrorKind::TooManyRequests, "too many requests"),
+//! )
+//!     .into_response();
+//! assert_eq!(refused.status(), StatusCode::TOO_MANY_REQUESTS);
+//! assert_eq!(refused.headers()["ratelimit-remaining"], "0");
+//! assert_eq!(refused.headers()["retry-after"], "600");
+//! ```
+//!
+//! The header names follow the IETF `RateLimit` header fields draft. Browsers
+//! may read them cross-origin (see [`crate::cors::EXPOSED_HEADERS`]).
+
+use axum::http::header::RETRY_AFTER;
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use axum::response::{IntoResponseParts, ResponseParts};
+
+/// Requests allowed in the current window.
+pub const RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+/// Requests left in the current window.
+pub const RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+/// Seconds until the window resets.
+pub const RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+/// State of one quota after the current request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub limit: u64,
+    pub remaining: u64,
+    /// Seconds until `remaining` is back to `limit`; an upper bound is fine.
+    pub reset_secs: u64,
+}
+
+impl RateLimit {
+    /// `used` of `limit` spent; `remaining` saturates at zero.
+    pub fn new(limit: u64, used: u64, reset_secs: u64) -> Self {
+        Self {
+            limit,
+            remaining: limit.saturating_sub(used),
+            reset_secs,
+        }
+    }
+
+    /// Nothing left until `reset_secs` have passed.
+    pub fn exhausted(limit: u64, reset_secs: u64) -> Self {
+        Self::new(limit, limit, reset_secs)
+    }
+
+    /// Write the headers, plus `Retry-After` once the quota is spent.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        headers.insert(RATELIMIT_LIMIT, HeaderValue::from(self.limit));
+        headers.insert(RATELIMIT_REMAINING, HeaderValue::from(self.remaining));
+        headers.insert(RATELIMIT_RESET, HeaderValue::from(self.reset_secs));
+        if self.remaining == 0 {
+            headers.insert(RETRY_AFTER, HeaderValue::from(self.reset_secs));
+        }
+    }
+}
+
+impl IntoResponseParts for RateLimit {
+    type Error = std::convert::Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        self.apply(res.headers_mut());
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_write_quota_headers_without_retry_after_while_requests_remain() {
+        let mut headers = HeaderMap::new();
+        RateLimit::new(5, 2, 600).apply(&mut headers);
+
+        assert_eq!(headers[RATELIMIT_LIMIT], "5");
+        assert_eq!(headers[RATELIMIT_REMAINING], "3");
+        assert_eq!(headers[RATELIMIT_RESET], "600");
+        assert!(!headers.contains_key(RETRY_AFTER));
+    }
+
+    #[test]
+    fn should_saturate_remaining_when_over_the_limit() {
+        assert_eq!(RateLimit::new(5, 7, 60).remaining, 0);
+        assert_eq!(RateLimit::new(5, 7, 60), RateLimit::exhausted(5, 60));
+    }
+}
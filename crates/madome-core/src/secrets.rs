@@ -0,0 +1,563 @@
+//! Where credentials come from, and keeping them current after rotation.
+//!
+//! `SECRETS_PROVIDER` picks the source:
+//!
+//! | Value | Reads `NAME` from |
+//! |-------|-------------------|
+//! | `env` (default) | the process environment |
+//! | `file` | `$SECRETS_DIR/NAME` (default `/run/secrets`), as mounted by Docker, Kubernetes or a CSI driver |
+//! | `vault` | field `NAME` of the KV v2 secret `$VAULT_SECRET_PATH` on `$VAULT_ADDR`, with `$VAULT_TOKEN` (feature `vault`) |
+//!
+//! [`load_config`] loads a [`Config`] with the named settings fetched from the
+//! provider, and [`RefreshingSecret`] re-fetches one periodically so a rotated
+//! value is picked up without a restart.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::config::{Config, ConfigError, ConfigErrors, Secret, from_vars, layered_vars};
+use crate::jobs::{Job, Schedule};
+
+/// Why a secret could not be fetched.
+#[derive(Debug, thiserror::Error)]
+pub enum SecretError {
+    #[error("{0}: not found")]
+    NotFound(String),
+    #[error("{name}: {source}")]
+    Io {
+        name: String,
+        source: std::io::Error,
+    },
+    #[error("{name}: {message}")]
+    Backend { name: String, message: String },
+}
+
+/// Source of secret values, looked up by setting name (e.g. `JWT_SECRET`).
+pub trait SecretProvider: Send + Sync + 'static {
+    fn fetch(&self, name: &str)
+    -> impl Future<Output = Result<Secret<String>, SecretError>> + Send;
+}
+
+/// Reads secrets from the process environment.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnvProvider;
+
+impl SecretProvider for EnvProvider {
+    async fn fetch(&self, name: &str) -> Result<Secret<String>, SecretError> {
+        std::env::var(name)
+            .map(Secret::new)
+            .map_err(|_| SecretError::NotFound(name.to_owned()))
+    }
+}
+
+/// Reads each secret from a file named after it, with trailing newlines removed.
+#[derive(Debug, Clone)]
+pub struct FileProvider {
+    pub dir: PathBuf,
+}
+
+impl SecretProvider for FileProvider {
+    async fn fetch(&self, name: &str) -> Result<Secret<String>, SecretError> {
+        match tokio::fs::read_to_string(self.dir.join(name)).await {
+            Ok(value) => Ok(Secret::new(value.trim_end_matches(['\r', '\n']).to_owned())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(SecretError::NotFound(name.to_owned()))
+            }
+            Err(source) => Err(SecretError::Io {
+                name: name.to_owned(),
+                source,
+            }),
+        }
+    }
+}
+
+/// Reads fields of one HashiCorp Vault KV v2 secret.
+#[cfg(feature = "vault")]
+#[derive(Debug, Clone)]
+pub struct VaultProvider {
+    /// e.g. `https://vault.example.com:8200`
+    pub addr: String,
+    pub token: Secret<String>,
+    /// Secrets engine mount, e.g. `secret`.
+    pub mount: String,
+    /// Secret path under the mount, e.g. `madome/auth`.
+    pub path: String,
+    pub http: reqwest::Client,
+}
+
+#[cfg(feature = "vault")]
+impl SecretProvider for VaultProvider {
+    async fn fetch(&self, name: &str) -> Result<Secret<String>, SecretError> {
+        #[derive(Deserialize)]
+        struct Response {
+            data: Data,
+        }
+        #[derive(Deserialize)]
+        struct Data {
+            data: HashMap<String, String>,
+        }
+
+        let backend = |e: reqwest::Error| SecretError::Backend {
+            name: name.to_owned(),
+            message: e.to_string(),
+        };
+        let url = format!(
+            "{}/v1/{}/data/{}",
+            self.addr.trim_end_matches('/'),
+            self.mount,
+            self.path
+        );
+        let response: Response = self
+            .http
+            .get(url)
+            .header("X-Vault-Token", self.token.expose())
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(backend)?
+            .json()
+            .await
+            .map_err(backend)?;
+        response
+            .data
+            .data
+            .get(name)
+            .cloned()
+            .map(Secret::new)
+            .ok_or_else(|| SecretError::NotFound(name.to_owned()))
+    }
+}
+
+/// The provider chosen by `SECRETS_PROVIDER`.
+#[derive(Debug, Clone)]
+pub enum Provider {
+    Env(EnvProvider),
+    File(FileProvider),
+    #[cfg(feature = "vault")]
+    Vault(VaultProvider),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ProviderKind {
+    #[default]
+    Env,
+    File,
+    Vault,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(not(feature = "vault"), allow(dead_code))]
+struct ProviderSettings {
+    #[serde(default)]
+    secrets_provider: ProviderKind,
+    #[serde(default = "default_secrets_dir")]
+    secrets_dir: PathBuf,
+    #[serde(default)]
+    vault_addr: Option<String>,
+    #[serde(default)]
+    vault_token: Option<Secret<String>>,
+    /// `<mount>/<path>`, e.g. `secret/madome/auth`.
+    #[serde(default)]
+    vault_secret_path: Option<String>,
+}
+
+fn default_secrets_dir() -> PathBuf {
+    PathBuf::from("/run/secrets")
+}
+
+impl Provider {
+    /// Choose the provider from the layered settings (see [`crate::config`]).
+    pub fn from_env() -> Result<Self, ConfigErrors> {
+        Self::from_vars(layered_vars()?)
+    }
+
+    pub fn from_vars(vars: HashMap<String, String>) -> Result<Self, ConfigErrors> {
+        let settings: ProviderSettings = from_vars(vars)?;
+        match settings.secrets_provider {
+            ProviderKind::Env => Ok(Self::Env(EnvProvider)),
+            ProviderKind::File => Ok(Self::File(FileProvider {
+                dir: settings.secrets_dir,
+            })),
+            ProviderKind::Vault => vault(settings),
+        }
+    }
+}
+
+#[cfg(feature = "vault")]
+fn vault(settings: ProviderSettings) -> Result<Provider, ConfigErrors> {
+    let mut errors = Vec::new();
+    let mut require = |key: &str, value: Option<String>| {
+        value.unwrap_or_else(|| {
+            errors.push(ConfigError::Missing(key.to_owned()));
+            String::new()
+        })
+    };
+    let addr = require("VAULT_ADDR", settings.vault_addr);
+    let token = require("VAULT_TOKEN", settings.vault_token.map(Secret::into_inner));
+    let secret_path = require("VAULT_SECRET_PATH", settings.vault_secret_path);
+    if !errors.is_empty() {
+        return Err(ConfigErrors(errors));
+    }
+    let Some((mount, path)) = secret_path.split_once('/') else {
+        return Err(ConfigErrors(vec![ConfigError::Invalid {
+            key: "VAULT_SECRET_PATH".to_owned(),
+            message: "expected <mount>/<path>".to_owned(),
+        }]));
+    };
+    Ok(Provider::Vault(VaultProvider {
+        addr,
+        token: Secret::new(token),
+        mount: mount.to_owned(),
+        path: path.to_owned(),
+        http: reqwest::Client::new(),
+    }))
+}
+
+#[cfg(not(feature = "vault"))]
+fn vault(_settings: ProviderSettings) -> Result<Provider, ConfigErrors> {
+    Err(ConfigErrors(vec![ConfigError::Invalid {
+        key: "SECRETS_PROVIDER".to_owned(),
+        message: "built without the `vault` feature".to_owned(),
+    }]))
+}
+
+impl SecretProvider for Provider {
+    async fn fetch(&self, name: &str) -> Result<Secret<String>, SecretError> {
+        match self {
+            Self::Env(p) => p.fetch(name).await,
+            Self::File(p) => p.fetch(name).await,
+            #[cfg(feature = "vault")]
+            Self::Vault(p) => p.fetch(name).await,
+        }
+    }
+}
+
+/// Load `C` from the layered settings, with each of `names` fetched from
+/// `provider` first.
+///
+/// A name the provider does not have falls back to the layered settings, so
+/// local `.env` files keep working; one it fails to read is reported with the
+/// other configuration errors, [`Config::validate`]'s included.
+pub async fn load_config<C: Config, P: SecretProvider>(
+    provider: &P,
+    names: &[&str],
+) -> Result<C, ConfigErrors> {
+    let mut vars = layered_vars()?;
+    let mut errors = Vec::new();
+    for name in names {
+        match provider.fetch(name).await {
+            Ok(value) => {
+                vars.insert((*name).to_owned(), value.into_inner());
+            }
+            Err(SecretError::NotFound(_)) => {}
+            Err(e) => errors.push(ConfigError::Source {
+                path: (*name).to_owned(),
+                message: e.to_string(),
+            }),
+        }
+    }
+    match from_vars::<C>(vars) {
+        Ok(config) => {
+            if let Err(ConfigErrors(more)) = config.validate() {
+                errors.extend(more);
+            }
+            if errors.is_empty() {
+                Ok(config)
+            } else {
+                Err(ConfigErrors(errors))
+            }
+        }
+        Err(ConfigErrors(more)) => {
+            errors.extend(more);
+            Err(ConfigErrors(errors))
+        }
+    }
+}
+
+/// A secret shared across the service and replaced in place when it rotates.
+///
+/// Clones share the value. Read it with [`RefreshingSecret::current`] each
+/// time it is needed rather than holding on to the result. A signing key built
+/// with [`RefreshingSecret::keep_previous`] also remembers the value it
+/// replaced, so what was signed before a rotation still verifies for a while.
+#[derive(Clone)]
+pub struct RefreshingSecret {
+    keys: Arc<RwLock<Keys>>,
+    grace: Duration,
+}
+
+struct Keys {
+    current: Secret<String>,
+    /// The replaced value and when it stopped being current.
+    previous: Option<(Secret<String>, Instant)>,
+}
+
+impl RefreshingSecret {
+    /// A secret that is never refreshed unless its [`RefreshingSecret::refresh_job`] is scheduled.
+    pub fn new(value: Secret<String>) -> Self {
+        Self {
+            keys: Arc::new(RwLock::new(Keys {
+                current: value,
+                previous: None,
+            })),
+            grace: Duration::ZERO,
+        }
+    }
+
+    /// Keep each replaced value available from [`RefreshingSecret::previous`]
+    /// for `grace` after the rotation; make it at least as long as anything
+    /// signed with the secret lives.
+    pub fn keep_previous(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    /// Seed the previous value, e.g. the key a restart rotated away from. It
+    /// counts as replaced now.
+    pub fn with_previous(self, previous: Secret<String>) -> Self {
+        self.write().previous = Some((previous, Instant::now()));
+        self
+    }
+
+    pub fn current(&self) -> Secret<String> {
+        self.read().current.clone()
+    }
+
+    /// The value replaced by the last rotation, until its grace period ends.
+    pub fn previous(&self) -> Option<Secret<String>> {
+        self.read()
+            .previous
+            .as_ref()
+            .filter(|(_, retired)| retired.elapsed() < self.grace)
+            .map(|(value, _)| value.clone())
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Keys> {
+        self.keys.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Keys> {
+        self.keys.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn replace(&self, value: Secret<String>) -> bool {
+        let mut keys = self.write();
+        if keys.current == value {
+            return false;
+        }
+        let retired = std::mem::replace(&mut keys.current, value);
+        keys.previous = Some((retired, Instant::now()));
+        true
+    }
+
+    /// Job re-fetching `name` from `provider` every `every` and swapping in
+    /// changed values; add it to a [`Scheduler`](crate::jobs::Scheduler).
+    ///
+    /// `on_change` runs after each swap, e.g. to hand new database credentials
+    /// to a connection pool. A failed fetch fails the run and keeps the old
+    /// value. The job is named after the secret and runs on every replica,
+    /// since each process holds its own copy.
+    ///
+    /// The replaced value stays readable from [`RefreshingSecret::previous`]
+    /// for the grace period set with [`RefreshingSecret::keep_previous`].
+    pub fn refresh_job<P: SecretProvider>(
+        &self,
+        provider: Arc<P>,
+        name: &'static str,
+        every: Duration,
+        on_change: impl Fn(&Secret<String>) + Send + Sync + 'static,
+    ) -> Job {
+        let secret = self.clone();
+        let on_change = Arc::new(on_change);
+        Job::new(name, Schedule::every(every), move || {
+            let (secret, provider, on_change) = (
+                secret.clone(),
+                Arc::clone(&provider),
+                Arc::clone(&on_change),
+            );
+            async move {
+                let value = provider.fetch(name).await?;
+                if secret.replace(value.clone()) {
+                    tracing::info!(secret = name, "secret rotated");
+                    on_change(&value);
+                }
+                Ok(())
+            }
+        })
+        .every_replica()
+    }
+}
+
+impl fmt::Debug for RefreshingSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RefreshingSecret(***)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jobs::Scheduler;
+    use std::path::Path;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Serves whatever `values` holds at the time of the fetch.
+    #[derive(Default)]
+    struct MapProvider {
+        values: Mutex<HashMap<String, String>>,
+        fetches: AtomicUsize,
+    }
+
+    impl MapProvider {
+        fn set(&self, name: &str, value: &str) {
+            self.values
+                .lock()
+                .unwrap()
+                .insert(name.to_owned(), value.to_owned());
+        }
+    }
+
+    impl SecretProvider for MapProvider {
+        async fn fetch(&self, name: &str) -> Result<Secret<String>, SecretError> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            self.values
+                .lock()
+                .unwrap()
+                .get(name)
+                .cloned()
+                .map(Secret::new)
+                .ok_or_else(|| SecretError::NotFound(name.to_owned()))
+        }
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| ((*k).to_owned(), (*v).to_owned()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn should_read_secret_file_without_trailing_newline() {
+        let dir = std::env::temp_dir().join(format!("madome-secrets-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("JWT_SECRET"), "s3cret\n").unwrap();
+        let provider = FileProvider { dir: dir.clone() };
+
+        let value = provider.fetch("JWT_SECRET").await.unwrap();
+        let missing = provider.fetch("DATABASE_URL").await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(value.expose(), "s3cret");
+        assert!(matches!(missing, Err(SecretError::NotFound(name)) if name == "DATABASE_URL"));
+    }
+
+    #[test]
+    fn should_choose_provider_from_settings() {
+        let env = Provider::from_vars(vars(&[])).unwrap();
+        assert!(matches!(env, Provider::Env(_)));
+
+        let file = Provider::from_vars(vars(&[
+            ("SECRETS_PROVIDER", "file"),
+            ("SECRETS_DIR", "/etc/madome"),
+        ]))
+        .unwrap();
+        assert!(matches!(file, Provider::File(p) if p.dir == Path::new("/etc/madome")));
+
+        assert!(Provider::from_vars(vars(&[("SECRETS_PROVIDER", "keychain")])).is_err());
+    }
+
+    #[cfg(feature = "vault")]
+    #[test]
+    fn should_require_vault_settings() {
+        let errors = Provider::from_vars(vars(&[("SECRETS_PROVIDER", "vault")]))
+            .err()
+            .unwrap();
+        assert_eq!(errors.0.len(), 3);
+
+        let vault = Provider::from_vars(vars(&[
+            ("SECRETS_PROVIDER", "vault"),
+            ("VAULT_ADDR", "https://vault.example.com"),
+            ("VAULT_TOKEN", "token"),
+            ("VAULT_SECRET_PATH", "secret/madome/auth"),
+        ]))
+        .unwrap();
+        assert!(
+            matches!(vault, Provider::Vault(p) if p.mount == "secret" && p.path == "madome/auth")
+        );
+    }
+
+    #[tokio::test]
+    async fn should_swap_in_rotated_value_and_notify() {
+        let provider = Arc::new(MapProvider::default());
+        provider.set("JWT_SECRET", "old");
+        let secret = RefreshingSecret::new(provider.fetch("JWT_SECRET").await.unwrap());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let notified = Arc::clone(&seen);
+        let jobs = Scheduler::new()
+            .job(secret.refresh_job(
+                Arc::clone(&provider),
+                "JWT_SECRET",
+                Duration::from_millis(10),
+                move |value| notified.lock().unwrap().push(value.expose().clone()),
+            ))
+            .start();
+        provider.set("JWT_SECRET", "new");
+        while secret.current().expose() != "new" {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        // A few more unchanged fetches must not notify again.
+        let fetched = provider.fetches.load(Ordering::SeqCst);
+        while provider.fetches.load(Ordering::SeqCst) < fetched + 2 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        drop(jobs);
+
+        assert_eq!(*seen.lock().unwrap(), vec!["new".to_owned()]);
+    }
+
+    #[tokio::test]
+    async fn should_keep_previous_value_for_grace_period() {
+        let provider = Arc::new(MapProvider::default());
+        provider.set("JWT_SECRET", "new");
+        let kept = RefreshingSecret::new(Secret::new("old".to_owned()))
+            .keep_previous(Duration::from_secs(3600));
+        let dropped = RefreshingSecret::new(Secret::new("old".to_owned()));
+        assert!(kept.previous().is_none());
+
+        let refresh = |secret: &RefreshingSecret| {
+            secret.refresh_job(
+                Arc::clone(&provider),
+                "JWT_SECRET",
+                Duration::from_millis(10),
+                |_| {},
+            )
+        };
+        let jobs = Scheduler::new()
+            .job(refresh(&kept))
+            .job(refresh(&dropped))
+            .start();
+        while kept.current().expose() != "new" || dropped.current().expose() != "new" {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        drop(jobs);
+
+        assert_eq!(kept.previous().unwrap().expose(), "old");
+        assert!(dropped.previous().is_none());
+    }
+
+    #[test]
+    fn should_redact_refreshing_secret_in_debug() {
+        let secret = RefreshingSecret::new(Secret::new("hunter2".to_owned()));
+        assert_eq!(format!("{secret:?}"), "RefreshingSecret(***)");
+    }
+}
@@ -0,0 +1,252 @@
+//! Shadow calls against the legacy system, for comparing behavior before a
+//! path group is cut over.
+//!
+//! After serving a request, the new implementation hands its own result and
+//! the equivalent legacy call to a [`Shadow`]. The legacy call runs in the
+//! background and never affects the response; the two results are compared and
+//! counted, and a divergence is logged with both values under the `shadow`
+//! target:
+//!
+//! ```
+//! use madome_core::shadow::Shadow;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let shadow = Shadow::new("check_token", 1);
+//! // e.g. the status the handler answered with, and the legacy call for the
+//! // same request
+//! let ours = 200u16;
+//! shadow.compare(ours, async { Ok(200u16) });
+//! # shadow.idle().await;
+//! assert_eq!(shadow.counts().matched, 1);
+//! # }
+//! ```
+//!
+//! Only one in `one_in` requests is mirrored (`0` = none), at most
+//! [`MAX_IN_FLIGHT`] legacy calls run at once and each gets a timeout;
+//! requests beyond either limit are counted as skipped or failed, never queued.
+//! Compare outcomes (status codes, validity verdicts, ids), not values that
+//! differ by design such as freshly signed tokens.
+
+use std::fmt::Debug;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use tokio::sync::Semaphore;
+use tracing::Instrument;
+
+use crate::jobs::{Job, Schedule};
+
+/// Legacy calls allowed to run at once per [`Shadow`].
+pub const MAX_IN_FLIGHT: usize = 64;
+
+/// Time a legacy call gets before it counts as failed, unless changed with
+/// [`Shadow::timeout`].
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Outcomes of the shadow calls since startup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShadowCounts {
+    pub matched: u64,
+    pub diverged: u64,
+    /// The legacy call errored or timed out.
+    pub failed: u64,
+    /// Not sampled, or dropped because too many calls were in flight.
+    pub skipped: u64,
+}
+
+#[derive(Debug)]
+struct Counters {
+    seen: AtomicU64,
+    matched: AtomicU64,
+    diverged: AtomicU64,
+    failed: AtomicU64,
+    skipped: AtomicU64,
+    in_flight: Arc<Semaphore>,
+}
+
+/// Mirrors one kind of call to the legacy system. Clones share the counters.
+#[derive(Debug, Clone)]
+pub struct Shadow {
+    name: &'static str,
+    one_in: u64,
+    timeout: Duration,
+    counters: Arc<Counters>,
+}
+
+impl Shadow {
+    /// `name` labels the logs; `one_in` of `0` mirrors nothing.
+    pub fn new(name: &'static str, one_in: u32) -> Self {
+        Self {
+            name,
+            one_in: u64::from(one_in),
+            timeout: DEFAULT_TIMEOUT,
+            counters: Arc::new(Counters {
+                seen: AtomicU64::new(0),
+                matched: AtomicU64::new(0),
+                diverged: AtomicU64::new(0),
+                failed: AtomicU64::new(0),
+                skipped: AtomicU64::new(0),
+                in_flight: Arc::new(Semaphore::new(MAX_IN_FLIGHT)),
+            }),
+        }
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Whether any call is mirrored at all; lets callers skip building the
+    /// legacy request.
+    pub fn is_enabled(&self) -> bool {
+        self.one_in > 0
+    }
+
+    /// Run `legacy` in the background, if this call is sampled, and compare
+    /// its result with `ours`. Returns immediately.
+    pub fn compare<T, F>(&self, ours: T, legacy: F)
+    where
+        T: PartialEq + Debug + Send + 'static,
+        F: Future<Output = anyhow::Result<T>> + Send + 'static,
+    {
+        let counters = &self.counters;
+        let sampled =
+            self.one_in > 0 && counters.seen.fetch_add(1, Ordering::Relaxed) % self.one_in == 0;
+        let permit = if sampled {
+            Arc::clone(&counters.in_flight).try_acquire_owned().ok()
+        } else {
+            None
+        };
+        let Some(permit) = permit else {
+            counters.skipped.fetch_add(1, Ordering::Relaxed);
+            return;
+        };
+
+        let (name, timeout, counters) = (self.name, self.timeout, Arc::clone(counters));
+        tokio::spawn(
+            async move {
+                let _permit = permit;
+                match tokio::time::timeout(timeout, legacy).await {
+                    Ok(Ok(theirs)) if theirs == ours => {
+                        counters.matched.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Ok(Ok(theirs)) => {
+                        counters.diverged.fetch_add(1, Ordering::Relaxed);
+                        tracing::warn!(
+                            target: "shadow",
+                            shadow = name,
+                            ours = ?ours,
+                            legacy = ?theirs,
+                            "legacy result diverged",
+                        );
+                    }
+                    Ok(Err(e)) => {
+                        counters.failed.fetch_add(1, Ordering::Relaxed);
+                        let error = format!("{e:#}");
+                        tracing::info!(target: "shadow", shadow = name, %error, "legacy call failed");
+                    }
+                    Err(_) => {
+                        counters.failed.fetch_add(1, Ordering::Relaxed);
+                        tracing::info!(target: "shadow", shadow = name, "legacy call timed out");
+                    }
+                }
+            }
+            .in_current_span(),
+        );
+    }
+
+    pub fn counts(&self) -> ShadowCounts {
+        let counters = &self.counters;
+        ShadowCounts {
+            matched: counters.matched.load(Ordering::Relaxed),
+            diverged: counters.diverged.load(Ordering::Relaxed),
+            failed: counters.failed.load(Ordering::Relaxed),
+            skipped: counters.skipped.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Wait until no legacy call is in flight.
+    pub async fn idle(&self) {
+        let all = u32::try_from(MAX_IN_FLIGHT).unwrap_or(u32::MAX);
+        // The semaphore is never closed.
+        let _ = self.counters.in_flight.acquire_many(all).await;
+    }
+
+    /// Job logging the counts under the `shadow` target, for dashboards of
+    /// divergence during the migration window.
+    pub fn report_job(&self, every: Duration) -> Job {
+        let shadow = self.clone();
+        Job::new("shadow_report", Schedule::every(every), move || {
+            let shadow = shadow.clone();
+            async move {
+                let counts = shadow.counts();
+                tracing::info!(
+                    target: "shadow",
+                    shadow = shadow.name,
+                    matched = counts.matched,
+                    diverged = counts.diverged,
+                    failed = counts.failed,
+                    skipped = counts.skipped,
+                    "shadow comparison",
+                );
+                Ok(())
+            }
+        })
+        .every_replica()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn should_count_matches_divergences_and_failures() {
+        let shadow = Shadow::new("test", 1);
+        shadow.compare(200u16, async { Ok(200) });
+        shadow.compare(200u16, async { Ok(401) });
+        shadow.compare(200u16, async { Err(anyhow::anyhow!("connection refused")) });
+        shadow.idle().await;
+
+        let counts = shadow.counts();
+        assert_eq!(
+            (
+                counts.matched,
+                counts.diverged,
+                counts.failed,
+                counts.skipped
+            ),
+            (1, 1, 1, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_mirror_one_in_n_calls_and_none_when_disabled() {
+        let shadow = Shadow::new("test", 3);
+        for _ in 0..6 {
+            shadow.compare((), async { Ok(()) });
+        }
+        shadow.idle().await;
+        assert_eq!((shadow.counts().matched, shadow.counts().skipped), (2, 4));
+
+        let disabled = Shadow::new("test", 0);
+        assert!(!disabled.is_enabled());
+        disabled.compare((), async { Ok(()) });
+        assert_eq!(disabled.counts().skipped, 1);
+    }
+
+    #[tokio::test]
+    async fn should_fail_slow_legacy_calls_and_skip_beyond_the_in_flight_cap() {
+        let shadow = Shadow::new("test", 1).timeout(Duration::from_millis(10));
+        for _ in 0..=MAX_IN_FLIGHT {
+            shadow.compare((), std::future::pending());
+        }
+        assert_eq!(shadow.counts().skipped, 1);
+
+        shadow.idle().await;
+        assert_eq!(shadow.counts().failed, MAX_IN_FLIGHT as u64);
+    }
+}
@@ -0,0 +1,174 @@
+//! Waiting for dependencies before serving.
+//!
+//! Under docker-compose, and while a rollout restarts pods, Postgres, Redis or
+//! a gRPC peer may still be starting when a service boots. [`wait_for`] retries
+//! the connect step with exponential backoff instead of crashing on the first
+//! refused connection; a dependency that never comes up still fails startup,
+//! just later and with every attempt logged.
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! use madome_core::startup::{Backoff, wait_for};
+//!
+//! let stream = wait_for("upstream", Backoff::default(), || {
+//!     tokio::net::TcpStream::connect("example.com:5432")
+//! })
+//! .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::Display;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::net::TcpListener;
+use tonic::transport::{Channel, Endpoint};
+
+/// Retry schedule for [`wait_for`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    /// Total tries, including the first. `0` is treated as `1`.
+    pub attempts: u32,
+    /// Delay after the first failure; doubled after each further one.
+    pub initial: Duration,
+    /// Upper bound for a single delay.
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    /// 10 tries over roughly 30 s (250 ms, 500 ms, 1 s, 2 s, 4 s, then 5 s).
+    fn default() -> Self {
+        Self {
+            attempts: 10,
+            initial: Duration::from_millis(250),
+            max: Duration::from_secs(5),
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay after failed try `n` (1-based): `initial * 2^(n-1)`, capped at `max`.
+    pub fn delay(&self, n: u32) -> Duration {
+        let factor = 2u32.saturating_pow(n.saturating_sub(1));
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+/// Run `connect` until it succeeds or `backoff.attempts` tries have failed.
+///
+/// Each failure is logged at `warn` with the dependency name; the last error is
+/// returned once the tries run out.
+pub async fn wait_for<T, E, F, Fut>(what: &str, backoff: Backoff, mut connect: F) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let attempts = backoff.attempts.max(1);
+    let mut n = 1;
+    loop {
+        match connect().await {
+            Ok(value) => {
+                if n > 1 {
+                    tracing::info!(dependency = what, attempt = n, "dependency ready");
+                }
+                return Ok(value);
+            }
+            Err(e) if n >= attempts => {
+                tracing::error!(dependency = what, attempt = n, error = %e, "dependency unavailable, giving up");
+                return Err(e);
+            }
+            Err(e) => {
+                let delay = backoff.delay(n);
+                tracing::warn!(
+                    dependency = what,
+                    attempt = n,
+                    of = attempts,
+                    retry_in_ms = delay.as_millis() as u64,
+                    error = %e,
+                    "dependency not ready",
+                );
+                tokio::time::sleep(delay).await;
+                n += 1;
+            }
+        }
+    }
+}
+
+/// Connect a gRPC channel to `endpoint`, retrying per `backoff`.
+///
+/// Clients are otherwise built with `connect_lazy`, which defers a dead peer to
+/// the first request; call this once at startup to fail before serving instead.
+pub async fn wait_for_channel(
+    what: &str,
+    endpoint: &Endpoint,
+    backoff: Backoff,
+) -> Result<Channel, tonic::transport::Error> {
+    wait_for(what, backoff, || endpoint.connect()).await
+}
+
+/// Bind a listener on `addr`, retrying per `backoff`.
+///
+/// During a rollout the old pod (or a restarted process) can hold the port for
+/// a moment after it stopped accepting; `AddrInUse` then clears by itself.
+pub async fn bind(what: &str, addr: SocketAddr, backoff: Backoff) -> std::io::Result<TcpListener> {
+    wait_for(&format!("{what} port {addr}"), backoff, || {
+        TcpListener::bind(addr)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    fn fast(attempts: u32) -> Backoff {
+        Backoff {
+            attempts,
+            initial: Duration::from_millis(1),
+            max: Duration::from_millis(2),
+        }
+    }
+
+    #[test]
+    fn should_double_delay_up_to_max() {
+        let backoff = Backoff::default();
+        let delays: Vec<u128> = (1..=6).map(|n| backoff.delay(n).as_millis()).collect();
+        assert_eq!(delays, [250, 500, 1000, 2000, 4000, 5000]);
+        assert_eq!(backoff.delay(u32::MAX), backoff.max);
+    }
+
+    #[tokio::test]
+    async fn should_return_once_dependency_comes_up() {
+        let tries = AtomicU32::new(0);
+
+        let result = wait_for("db", fast(5), || async {
+            match tries.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err("connection refused"),
+                _ => Ok("connected"),
+            }
+        })
+        .await;
+
+        assert_eq!(result, Ok("connected"));
+        assert_eq!(tries.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn should_give_up_with_last_error_after_all_attempts() {
+        let tries = AtomicU32::new(0);
+
+        let result: Result<(), String> = wait_for("redis", fast(3), || async {
+            let n = tries.fetch_add(1, Ordering::SeqCst) + 1;
+            Err(format!("refused #{n}"))
+        })
+        .await;
+
+        assert_eq!(result, Err("refused #3".to_owned()));
+        assert_eq!(tries.load(Ordering::SeqCst), 3);
+    }
+}
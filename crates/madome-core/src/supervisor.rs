@@ -0,0 +1,250 @@
+//! Running a service's servers (HTTP, gRPC) as one unit.
+//!
+//! A [`Supervisor`] starts every server with a shared [`Shutdown`] signal and
+//! reports the process ready while all of them run. When one stops or fails,
+//! or on SIGTERM / Ctrl-C, it marks the process unready, signals the others
+//! and gives them a grace period to drain, so a pod is never left serving HTTP
+//! with its gRPC server gone:
+//!
+//! ```no_run
+//! # async fn run(router: axum::Router) -> anyhow::Result<()> {
+//! use madome_core::health::Readiness;
+//! use madome_core::startup::{Backoff, bind};
+//! use madome_core::supervisor::Supervisor;
+//!
+//! let readiness = Readiness::new();
+//! let router = router.layer(axum::Extension(readiness.clone()));
+//! let listener = bind("http", ([0, 0, 0, 0], 3112).into(), Backoff::default()).await?;
+//! Supervisor::new(readiness)
+//!     .serve("http", |shutdown| {
+//!         axum::serve(listener, router)
+//!             .with_graceful_shutdown(shutdown.signalled())
+//!             .into_future()
+//!     })
+//!     .run()
+//!     .await
+//! # }
+//! ```
+//!
+//! Listeners are bound before the supervisor starts, with [`bind`] retrying a
+//! port the previous pod has not released yet.
+//!
+//! [`bind`]: crate::startup::bind
+
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use tokio::sync::watch;
+use tokio::task::{JoinError, JoinSet};
+
+use crate::health::Readiness;
+
+/// How long servers get to finish in-flight requests by default.
+pub const DEFAULT_GRACE: Duration = Duration::from_secs(30);
+
+/// Fires when the supervisor shuts its servers down.
+#[derive(Debug, Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+impl Shutdown {
+    /// Resolve once shutdown starts; for `with_graceful_shutdown` and
+    /// `serve_with_incoming_shutdown`.
+    pub async fn signalled(mut self) {
+        // The sender lives as long as the supervisor; if it is gone, so is
+        // anyone to wait for.
+        let _ = self.0.wait_for(|&stop| stop).await;
+    }
+}
+
+/// Servers that start, stop and report readiness together.
+pub struct Supervisor {
+    readiness: Readiness,
+    grace: Duration,
+    shutdown: watch::Sender<bool>,
+    servers: JoinSet<(&'static str, anyhow::Result<()>)>,
+}
+
+impl Supervisor {
+    pub fn new(readiness: Readiness) -> Self {
+        Self {
+            readiness,
+            grace: DEFAULT_GRACE,
+            shutdown: watch::Sender::new(false),
+            servers: JoinSet::new(),
+        }
+    }
+
+    /// Time left to the servers after the shutdown signal before they are
+    /// aborted.
+    pub fn grace(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    /// Start a server. It should return once `shutdown` fires; returning
+    /// earlier, successfully or not, shuts the other servers down.
+    pub fn serve<F, Fut, E>(mut self, name: &'static str, serve: F) -> Self
+    where
+        F: FnOnce(Shutdown) -> Fut,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Into<anyhow::Error>,
+    {
+        let server = serve(Shutdown(self.shutdown.subscribe()));
+        self.servers
+            .spawn(async move { (name, server.await.map_err(Into::into)) });
+        self
+    }
+
+    /// Serve until a server stops or the process is asked to terminate.
+    pub async fn run(self) -> anyhow::Result<()> {
+        self.run_until(terminate_signal()).await
+    }
+
+    /// Serve until a server stops or `stop` resolves, then shut every server
+    /// down. Errors if a server failed or stopped on its own.
+    pub async fn run_until(mut self, stop: impl Future<Output = ()>) -> anyhow::Result<()> {
+        self.readiness.set(true);
+        tracing::info!(servers = self.servers.len(), "serving");
+        let mut result = tokio::select! {
+            Some(joined) = self.servers.join_next() => match joined {
+                Ok((name, Ok(()))) => Err(anyhow!("{name} server stopped unexpectedly")),
+                other => stopped(other),
+            },
+            () = stop => Ok(()),
+        };
+        self.readiness.set(false);
+        match &result {
+            Ok(()) => tracing::info!("shutting down"),
+            Err(e) => tracing::error!(error = %format!("{e:#}"), "shutting down"),
+        }
+
+        self.shutdown.send_replace(true);
+        let drain = async {
+            while let Some(joined) = self.servers.join_next().await {
+                let stopped = stopped(joined);
+                if result.is_ok() {
+                    result = stopped;
+                }
+            }
+        };
+        if tokio::time::timeout(self.grace, drain).await.is_err() {
+            tracing::warn!(
+                grace_ms = self.grace.as_millis() as u64,
+                "servers still running after the grace period, aborting"
+            );
+            self.servers.abort_all();
+        }
+        result
+    }
+}
+
+fn stopped(joined: Result<(&'static str, anyhow::Result<()>), JoinError>) -> anyhow::Result<()> {
+    match joined {
+        Ok((name, result)) => result.map_err(|e| e.context(format!("{name} server failed"))),
+        Err(e) => Err(anyhow!("server task panicked: {e}")),
+    }
+}
+
+/// Resolve on SIGTERM (what Kubernetes and docker send) or Ctrl-C.
+pub async fn terminate_signal() {
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        () = terminate => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+
+    /// A server that runs until shutdown and records that it was told to stop.
+    fn until_shutdown(
+        stopped: &Arc<AtomicBool>,
+    ) -> impl FnOnce(Shutdown) -> std::pin::Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>>
+    {
+        let stopped = Arc::clone(stopped);
+        move |shutdown| {
+            Box::pin(async move {
+                shutdown.signalled().await;
+                stopped.store(true, Ordering::SeqCst);
+                Ok(())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn should_stop_every_server_when_one_fails() {
+        let readiness = Readiness::new();
+        let http_stopped = Arc::new(AtomicBool::new(false));
+        let error = Supervisor::new(readiness.clone())
+            .serve("http", until_shutdown(&http_stopped))
+            .serve("grpc", |_| async { Err(anyhow!("address in use")) })
+            .run_until(std::future::pending())
+            .await
+            .unwrap_err();
+
+        assert_eq!(format!("{error:#}"), "grpc server failed: address in use");
+        assert!(http_stopped.load(Ordering::SeqCst));
+        assert!(!readiness.is_ready());
+    }
+
+    #[tokio::test]
+    async fn should_treat_a_server_returning_early_as_a_failure() {
+        let error = Supervisor::new(Readiness::new())
+            .serve("grpc", |_| async { Ok::<_, anyhow::Error>(()) })
+            .run_until(std::future::pending())
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "grpc server stopped unexpectedly");
+    }
+
+    #[tokio::test]
+    async fn should_be_ready_while_serving_and_drain_on_stop() {
+        let readiness = Readiness::new();
+        let (http_stopped, grpc_stopped) = (Arc::default(), Arc::default());
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let supervisor = Supervisor::new(readiness.clone())
+            .serve("http", until_shutdown(&http_stopped))
+            .serve("grpc", until_shutdown(&grpc_stopped));
+        let running = tokio::spawn(supervisor.run_until(async {
+            let _ = stopped.await;
+        }));
+
+        for _ in 0..100 {
+            if readiness.is_ready() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        assert!(readiness.is_ready());
+        stop.send(()).unwrap();
+        running.await.unwrap().unwrap();
+        assert!(!readiness.is_ready());
+        assert!(http_stopped.load(Ordering::SeqCst) && grpc_stopped.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn should_abort_servers_that_outlive_the_grace_period() {
+        Supervisor::new(Readiness::new())
+            .grace(Duration::from_millis(10))
+            .serve("http", |_| std::future::pending::<anyhow::Result<()>>())
+            .run_until(async {})
+            .await
+            .unwrap();
+    }
+}
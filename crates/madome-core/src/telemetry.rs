@@ -0,0 +1,320 @@
+//! Process-wide log setup shared by every service.
+//!
+//! ```no_run
+//! madome_core::telemetry::init("auth", env!("CARGO_PKG_VERSION"));
+//! ```
+//!
+//! Settings (read through [`crate::config`]):
+//!
+//! - `RUST_LOG`: `EnvFilter` directives (default `info`);
+//! - `LOG_FORMAT`: `json` (default) or `text` for local development;
+//! - `LOG_SAMPLE`: comma-separated `path_prefix=N` rules. Requests whose path
+//!   starts with the prefix log one in every `N` (`0` = none). Warnings and
+//!   errors are always logged. The path comes from the `request` span opened
+//!   by [`crate::middleware::trace_id`].
+//!
+//! JSON lines carry `service.name` and `service.version` ahead of the usual
+//! fields.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Deserialize;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Metadata, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{self, FmtContext, FormatEvent, FormatFields, MakeWriter};
+use tracing_subscriber::layer::{Context, Filter, Layered, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry};
+
+use crate::config::Config;
+
+/// Logging settings; see the module docs for the variables.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TelemetryConfig {
+    #[serde(default)]
+    pub log_format: LogFormat,
+    #[serde(default)]
+    pub log_sample: Vec<SampleRule>,
+}
+
+impl Config for TelemetryConfig {}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    #[default]
+    Json,
+    Text,
+}
+
+/// Log one in every `every` requests under `prefix` (`0` = none).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct SampleRule {
+    pub prefix: String,
+    pub every: u64,
+}
+
+impl TryFrom<String> for SampleRule {
+    type Error = String;
+
+    fn try_from(rule: String) -> Result<Self, String> {
+        let (prefix, every) = rule
+            .split_once('=')
+            .ok_or_else(|| format!("expected `path_prefix=N`, got `{rule}`"))?;
+        let every = every.trim().parse().map_err(|e| format!("`{rule}`: {e}"))?;
+        Ok(Self {
+            prefix: prefix.trim().to_owned(),
+            every,
+        })
+    }
+}
+
+/// Install the global subscriber for `service`. Call once at startup.
+///
+/// Safe to call multiple times — subsequent calls are silently ignored.
+///
+/// # Panics
+///
+/// Panics if `LOG_FORMAT` or `LOG_SAMPLE` is invalid.
+pub fn init(service: &'static str, version: &'static str) {
+    let config = TelemetryConfig::from_env();
+    let _ = subscriber(service, version, &config, std::io::stdout).try_init();
+}
+
+type Base = Layered<EnvFilter, Registry>;
+
+/// Build the subscriber `init` installs, writing to `writer`.
+pub fn subscriber<W>(
+    service: &'static str,
+    version: &'static str,
+    config: &TelemetryConfig,
+    writer: W,
+) -> Layered<Box<dyn Layer<Base> + Send + Sync>, Base>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let sampler = RouteSampler::new(config.log_sample.clone());
+    let layer: Box<dyn Layer<Base> + Send + Sync> = match config.log_format {
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_writer(writer)
+            .map_event_format(|inner| ServiceFields::new(inner, service, version))
+            .with_filter(sampler)
+            .boxed(),
+        LogFormat::Text => fmt::layer()
+            .with_writer(writer)
+            .with_filter(sampler)
+            .boxed(),
+    };
+    tracing_subscriber::registry().with(env_filter).with(layer)
+}
+
+/// JSON event format prefixed with the service identity.
+struct ServiceFields<F> {
+    inner: F,
+    prefix: String,
+}
+
+impl<F> ServiceFields<F> {
+    fn new(inner: F, service: &str, version: &str) -> Self {
+        let prefix = format!(
+            "{{\"service.name\":{},\"service.version\":{},",
+            serde_json::Value::from(service),
+            serde_json::Value::from(version),
+        );
+        Self { inner, prefix }
+    }
+}
+
+impl<S, N, F> FormatEvent<S, N> for ServiceFields<F>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+    F: FormatEvent<S, N>,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> std::fmt::Result {
+        let mut line = String::new();
+        self.inner
+            .format_event(ctx, Writer::new(&mut line), event)?;
+        match line.strip_prefix('{') {
+            Some(rest) => {
+                writer.write_str(&self.prefix)?;
+                writer.write_str(rest)
+            }
+            None => writer.write_str(&line),
+        }
+    }
+}
+
+/// Marks sampled-out `request` spans and hides their sub-WARN events.
+struct RouteSampler {
+    rules: Vec<(SampleRule, AtomicU64)>,
+}
+
+/// Extension on a `request` span whose events are dropped.
+struct Muted;
+
+impl RouteSampler {
+    fn new(rules: Vec<SampleRule>) -> Self {
+        Self {
+            rules: rules
+                .into_iter()
+                .map(|rule| (rule, AtomicU64::new(0)))
+                .collect(),
+        }
+    }
+
+    fn keep(&self, path: &str) -> bool {
+        match self
+            .rules
+            .iter()
+            .find(|(rule, _)| path.starts_with(&rule.prefix))
+        {
+            Some((rule, seen)) => {
+                rule.every != 0 && seen.fetch_add(1, Ordering::Relaxed) % rule.every == 0
+            }
+            None => true,
+        }
+    }
+}
+
+impl<S> Filter<S> for RouteSampler
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn enabled(&self, meta: &Metadata<'_>, cx: &Context<'_, S>) -> bool {
+        if self.rules.is_empty() || *meta.level() <= Level::WARN {
+            return true;
+        }
+        cx.lookup_current().is_none_or(|span| {
+            span.scope()
+                .all(|span| span.extensions().get::<Muted>().is_none())
+        })
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, cx: Context<'_, S>) {
+        if self.rules.is_empty() || attrs.metadata().name() != "request" {
+            return;
+        }
+        let mut path = PathVisitor(None);
+        attrs.record(&mut path);
+        if let Some(path) = path.0
+            && !self.keep(&path)
+            && let Some(span) = cx.span(id)
+        {
+            span.extensions_mut().insert(Muted);
+        }
+    }
+}
+
+struct PathVisitor(Option<String>);
+
+impl Visit for PathVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "path" {
+            self.0 = Some(value.to_owned());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "path" {
+            self.0 = Some(format!("{value:?}"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn capture(config: TelemetryConfig, f: impl FnOnce()) -> Vec<serde_json::Value> {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = subscriber("auth", "1.2.3", &config, move || writer.clone());
+        tracing::subscriber::with_default(subscriber, f);
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    fn request(path: &str, f: impl FnOnce()) {
+        tracing::info_span!("request", path).in_scope(f);
+    }
+
+    #[test]
+    fn should_prefix_json_lines_with_service_identity() {
+        let lines = capture(TelemetryConfig::default(), || tracing::info!("hello"));
+
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["service.name"], "auth");
+        assert_eq!(lines[0]["service.version"], "1.2.3");
+        assert_eq!(lines[0]["fields"]["message"], "hello");
+    }
+
+    #[test]
+    fn should_sample_requests_under_noisy_prefix() {
+        let config = TelemetryConfig {
+            log_sample: vec![
+                SampleRule::try_from("/healthz=0".to_owned()).unwrap(),
+                SampleRule::try_from("/auth/token=2".to_owned()).unwrap(),
+            ],
+            ..TelemetryConfig::default()
+        };
+
+        let lines = capture(config, || {
+            request("/healthz", || tracing::info!("probe"));
+            request("/healthz", || tracing::warn!("probe failed"));
+            for _ in 0..4 {
+                request("/auth/token", || tracing::info!("token"));
+            }
+            request("/auth/sessions", || tracing::info!("sessions"));
+        });
+
+        let messages: Vec<_> = lines
+            .iter()
+            .map(|l| l["fields"]["message"].as_str().unwrap())
+            .collect();
+        assert_eq!(messages, ["probe failed", "token", "token", "sessions"]);
+    }
+
+    #[test]
+    fn should_reject_malformed_sample_rule() {
+        assert!(SampleRule::try_from("/healthz".to_owned()).is_err());
+        assert!(SampleRule::try_from("/healthz=often".to_owned()).is_err());
+    }
+
+    #[test]
+    fn should_ignore_repeated_init() {
+        init("auth", "1.2.3");
+        init("auth", "1.2.3");
+    }
+}
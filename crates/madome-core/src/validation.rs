@@ -0,0 +1,253 @@
+//! Request body validation with field-level 422 errors.
+//!
+//! Wire format (an [`ErrorBody`] of kind `validation`):
+//!
+//! ```json
+//! { "kind": "validation", "message": "validation failed",
+//!   "errors": [ { "field": "handle", "code": "too_long" } ] }
+//! ```
+//!
+//! Handlers take [`ValidatedJson<T>`] instead of `Json<T>`; `T` implements
+//! [`Validate`]. Type errors from deserialization are reported the same way
+//! (`required` / `invalid`), so clients handle a single shape.
+
+use axum::Json;
+use axum::extract::{FromRequest, Request};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::error_catalog::{ErrorBody, ErrorKind};
+
+/// One invalid field. `code` is a stable machine-readable reason.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FieldError {
+    pub field: String,
+    pub code: &'static str,
+}
+
+/// Every invalid field of a request, answered as 422.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ValidationErrors {
+    pub errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, field: impl Into<String>, code: &'static str) {
+        self.errors.push(FieldError {
+            field: field.into(),
+            code,
+        });
+    }
+
+    /// `Ok(())` when no error was added.
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Add `required` if `value` is blank, `too_long` if it exceeds `max` chars.
+    ///
+    /// ```
+    /// use madome_core::validation::ValidationErrors;
+    ///
+    /// let mut errors = ValidationErrors::new();
+    /// errors.check_length("name", "  ", 10);
+    /// errors.check_length("handle", "abcdefghijk", 10);
+    /// errors.check_length("bio", "ok", 10);
+    /// let codes: Vec<_> = errors.errors.iter().map(|e| (e.field.as_str(), e.code)).collect();
+    /// assert_eq!(codes, [("name", "required"), ("handle", "too_long")]);
+    /// ```
+    pub fn check_length(&mut self, field: &str, value: &str, max: usize) {
+        if value.trim().is_empty() {
+            self.add(field, "required");
+        } else if value.chars().count() > max {
+            self.add(field, "too_long");
+        }
+    }
+
+    /// Add `required` if blank, `invalid_email` unless `value` looks like
+    /// `local@domain` (at most 254 chars, RFC 5321).
+    pub fn check_email(&mut self, field: &str, value: &str) {
+        if value.trim().is_empty() {
+            self.add(field, "required");
+            return;
+        }
+        let valid = value.len() <= 254
+            && !value.chars().any(char::is_whitespace)
+            && value
+                .split_once('@')
+                .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'));
+        if !valid {
+            self.add(field, "invalid_email");
+        }
+    }
+}
+
+impl From<ValidationErrors> for ErrorBody {
+    fn from(errors: ValidationErrors) -> Self {
+        Self {
+            errors: errors.errors,
+            ..ErrorBody::new(ErrorKind::Validation, "validation failed")
+        }
+    }
+}
+
+impl IntoResponse for ValidationErrors {
+    fn into_response(self) -> Response {
+        ErrorBody::from(self).into_response()
+    }
+}
+
+/// Semantic checks run after a body deserialized successfully.
+pub trait Validate {
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+/// `Json<T>` that also runs [`Validate`], rejecting with [`ValidationErrors`].
+///
+/// A missing `content-type` (415) and malformed JSON (400) keep axum's
+/// responses; those are not field errors.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<serde_json::Value>::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let body: T = serde_path_to_error::deserialize(value)
+            .map_err(|e| deserialize_error(e, "body").into_response())?;
+        body.validate().map_err(IntoResponse::into_response)?;
+        Ok(Self(body))
+    }
+}
+
+/// One field error for a failed deserialization: `required` for a missing
+/// field, `invalid` otherwise. `root` names the whole input when the error has
+/// no path (e.g. `body`, `query`).
+pub(crate) fn deserialize_error<E: std::fmt::Display>(
+    e: serde_path_to_error::Error<E>,
+    root: &str,
+) -> ValidationErrors {
+    let path = e.path().to_string();
+    let message = e.into_inner().to_string();
+    let mut errors = ValidationErrors::new();
+    // serde reports a missing field at its parent's path, naming it in the message.
+    if let Some(name) = message
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.split_once('`'))
+        .map(|(name, _)| name)
+    {
+        let field = if path == "." {
+            name.to_owned()
+        } else {
+            format!("{path}.{name}")
+        };
+        errors.add(field, "required");
+    } else {
+        errors.add(if path == "." { root.to_owned() } else { path }, "invalid");
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::{Body, to_bytes};
+    use axum::http::StatusCode;
+    use axum::http::header::CONTENT_TYPE;
+    use axum::routing::post;
+    use serde::Deserialize;
+    use tower::ServiceExt;
+
+    #[derive(Deserialize)]
+    struct SignUp {
+        email: String,
+        handle: String,
+    }
+
+    impl Validate for SignUp {
+        fn validate(&self) -> Result<(), ValidationErrors> {
+            let mut errors = ValidationErrors::new();
+            errors.check_email("email", &self.email);
+            errors.check_length("handle", &self.handle, 5);
+            errors.into_result()
+        }
+    }
+
+    async fn send(body: &str) -> (StatusCode, String) {
+        let app = Router::new().route(
+            "/",
+            post(|ValidatedJson(_): ValidatedJson<SignUp>| async {}),
+        );
+        let req = Request::post("/")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_owned()))
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        let status = res.status();
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn should_accept_valid_body() {
+        let (status, _) = send(r#"{"email":"a@example.com","handle":"abc"}"#).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_list_every_invalid_field() {
+        let (status, body) = send(r#"{"email":"nope","handle":"abcdef"}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            body,
+            r#"{"kind":"validation","message":"validation failed","errors":[{"field":"email","code":"invalid_email"},{"field":"handle","code":"too_long"}]}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn should_report_missing_field_as_required() {
+        let (status, body) = send(r#"{"email":"a@example.com"}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body.ends_with(r#""errors":[{"field":"handle","code":"required"}]}"#));
+    }
+
+    #[tokio::test]
+    async fn should_report_wrong_type_as_invalid() {
+        let (status, body) = send(r#"{"email":"a@example.com","handle":7}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body.ends_with(r#""errors":[{"field":"handle","code":"invalid"}]}"#));
+    }
+
+    #[tokio::test]
+    async fn should_keep_bad_request_for_malformed_json() {
+        let (status, _) = send("{").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn should_reject_malformed_emails() {
+        for email in ["a@b", "@example.com", "a b@example.com", "plain"] {
+            let mut errors = ValidationErrors::new();
+            errors.check_email("email", email);
+            assert_eq!(errors.errors.len(), 1, "{email} should be invalid");
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Per-key wakeups for requests waiting on new data (live notification
+//! streams, long polls).
+//!
+//! A waiting request subscribes to its key *before* reading what is already
+//! there, then waits for a wakeup and reads again; a wake between the read and
+//! the wait is not lost:
+//!
+//! ```
+//! use madome_core::wakeup::Wakeups;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let wakeups = Wakeups::new();
+//! let mut subscription = wakeups.subscribe("user-1");
+//! // read notifications newer than the client's last event id ...
+//! wakeups.wake(&"user-1"); // by whoever creates a notification for user-1
+//! subscription.woken().await; // ... and read again
+//! # }
+//! ```
+//!
+//! A wakeup carries no data, and several wakes before the subscriber gets to
+//! wait count as one: the subscriber reads from the source of truth anyway.
+//! [`Wakeups`] reach subscribers in this process only; with several replicas,
+//! [`RedisWakeups`] (behind the `redis` feature) relays them over Redis
+//! pub/sub.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use tokio::sync::watch;
+
+#[cfg(feature = "redis")]
+mod redis_pubsub;
+#[cfg(feature = "redis")]
+pub use redis_pubsub::RedisWakeups;
+
+/// Subscribers by key. Cloning shares the same subscribers.
+pub struct Wakeups<K> {
+    keys: Arc<Mutex<HashMap<K, watch::Sender<u64>>>>,
+}
+
+impl<K> Clone for Wakeups<K> {
+    fn clone(&self) -> Self {
+        Self {
+            keys: Arc::clone(&self.keys),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone> Default for Wakeups<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Eq + Hash + Clone> Wakeups<K> {
+    pub fn new() -> Self {
+        Self {
+            keys: Arc::default(),
+        }
+    }
+
+    /// Wait for wakes of `key` from now on.
+    pub fn subscribe(&self, key: K) -> Subscription<K> {
+        let receiver = self
+            .keys()
+            .entry(key.clone())
+            .or_insert_with(|| watch::channel(0).0)
+            .subscribe();
+        Subscription {
+            receiver: Some(receiver),
+            key,
+            wakeups: self.clone(),
+        }
+    }
+
+    /// Wake every subscriber of `key`; a no-op when there is none.
+    pub fn wake(&self, key: &K) {
+        if let Some(sender) = self.keys().get(key) {
+            sender.send_modify(|n| *n = n.wrapping_add(1));
+        }
+    }
+
+    /// Wake every subscriber of every key, e.g. after wakes may have been
+    /// missed.
+    pub fn wake_all(&self) {
+        for sender in self.keys().values() {
+            sender.send_modify(|n| *n = n.wrapping_add(1));
+        }
+    }
+
+    /// Keys with at least one subscriber.
+    pub fn subscribed_keys(&self) -> usize {
+        self.keys().len()
+    }
+
+    fn keys(&self) -> MutexGuard<'_, HashMap<K, watch::Sender<u64>>> {
+        self.keys.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// One subscriber; dropping it unsubscribes.
+pub struct Subscription<K: Eq + Hash + Clone> {
+    /// Only `None` while dropping.
+    receiver: Option<watch::Receiver<u64>>,
+    key: K,
+    wakeups: Wakeups<K>,
+}
+
+impl<K: Eq + Hash + Clone> Subscription<K> {
+    /// Return once `key` has been woken since the subscription started or
+    /// since this last returned.
+    pub async fn woken(&mut self) {
+        let receiver = self.receiver.as_mut().expect("subscription is alive");
+        // The map keeps the sender while any receiver is left, so this
+        // cannot fail.
+        let _ = receiver.changed().await;
+    }
+}
+
+impl<K: Eq + Hash + Clone> Drop for Subscription<K> {
+    fn drop(&mut self) {
+        let mut keys = self.wakeups.keys();
+        // Dropped under the lock, so of two last subscribers leaving at once
+        // the second one sees a count of zero.
+        drop(self.receiver.take());
+        if keys
+            .get(&self.key)
+            .is_some_and(|sender| sender.receiver_count() == 0)
+        {
+            keys.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    async fn is_woken(subscription: &mut Subscription<&'static str>) -> bool {
+        tokio::time::timeout(Duration::from_millis(20), subscription.woken())
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn should_wake_only_subscribers_of_the_key() {
+        let wakeups = Wakeups::new();
+        let mut alice = wakeups.subscribe("alice");
+        let mut alice_again = wakeups.subscribe("alice");
+        let mut bob = wakeups.subscribe("bob");
+
+        wakeups.wake(&"alice");
+        assert!(is_woken(&mut alice).await);
+        assert!(is_woken(&mut alice_again).await);
+        assert!(!is_woken(&mut bob).await);
+    }
+
+    #[tokio::test]
+    async fn should_keep_a_wake_that_came_before_the_wait_and_merge_repeats() {
+        let wakeups = Wakeups::new();
+        let mut subscription = wakeups.subscribe("alice");
+        wakeups.wake(&"alice");
+        wakeups.wake(&"alice");
+
+        assert!(is_woken(&mut subscription).await);
+        assert!(!is_woken(&mut subscription).await);
+    }
+
+    #[tokio::test]
+    async fn should_wake_every_key_at_once() {
+        let wakeups = Wakeups::new();
+        let mut alice = wakeups.subscribe("alice");
+        let mut bob = wakeups.subscribe("bob");
+
+        wakeups.wake_all();
+        assert!(is_woken(&mut alice).await);
+        assert!(is_woken(&mut bob).await);
+    }
+
+    #[tokio::test]
+    async fn should_forget_keys_once_their_last_subscriber_is_gone() {
+        let wakeups = Wakeups::new();
+        let first = wakeups.subscribe("alice");
+        let second = wakeups.subscribe("alice");
+        drop(first);
+        assert_eq!(wakeups.subscribed_keys(), 1);
+        drop(second);
+        assert_eq!(wakeups.subscribed_keys(), 0);
+
+        wakeups.wake(&"alice");
+        let mut late = wakeups.subscribe("alice");
+        assert!(!is_woken(&mut late).await);
+    }
+}
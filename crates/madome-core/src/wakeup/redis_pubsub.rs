@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use redis::aio::{MultiplexedConnection, PubSub};
+use tokio::task::JoinHandle;
+
+use super::{Subscription, Wakeups};
+
+/// [`Wakeups`] shared by the replicas over Redis pub/sub.
+///
+/// [`RedisWakeups::wake`] publishes the key on the channel, and every
+/// replica, this one included, wakes its own subscribers of the keys it
+/// receives. Wakes published while a replica is reconnecting to Redis do not
+/// reach it, so once back it wakes all its subscribers; they read again and
+/// find what they missed.
+pub struct RedisWakeups {
+    local: Wakeups<String>,
+    conn: MultiplexedConnection,
+    channel: String,
+    relay: JoinHandle<()>,
+}
+
+impl RedisWakeups {
+    /// Subscribe to `channel` (e.g. `"wakeups:notifications"`). Wakes
+    /// published once this returns are received.
+    pub async fn connect(client: redis::Client, channel: &str) -> anyhow::Result<Self> {
+        let conn = client.get_multiplexed_async_connection().await?;
+        let pubsub = subscribe(&client, channel).await?;
+        let local = Wakeups::new();
+        let relay = tokio::spawn(relay(client, channel.to_owned(), pubsub, local.clone()));
+        Ok(Self {
+            local,
+            conn,
+            channel: channel.to_owned(),
+            relay,
+        })
+    }
+
+    pub fn subscribe(&self, key: impl Into<String>) -> Subscription<String> {
+        self.local.subscribe(key.into())
+    }
+
+    /// Wake the subscribers of `key` on every replica.
+    pub async fn wake(&self, key: &str) -> anyhow::Result<()> {
+        let _receivers: u64 = redis::cmd("PUBLISH")
+            .arg(&self.channel)
+            .arg(key)
+            .query_async(&mut self.conn.clone())
+            .await?;
+        Ok(())
+    }
+}
+
+impl Drop for RedisWakeups {
+    fn drop(&mut self) {
+        self.relay.abort();
+    }
+}
+
+async fn subscribe(client: &redis::Client, channel: &str) -> redis::RedisResult<PubSub> {
+    let mut pubsub = client.get_async_pubsub().await?;
+    pubsub.subscribe(channel).await?;
+    Ok(pubsub)
+}
+
+async fn relay(client: redis::Client, channel: String, pubsub: PubSub, local: Wakeups<String>) {
+    let mut pubsub = pubsub;
+    loop {
+        let mut messages = pubsub.into_on_message();
+        while let Some(message) = messages.next().await {
+            match message.get_payload::<String>() {
+                Ok(key) => local.wake(&key),
+                Err(e) => tracing::warn!(%channel, error = %e, "malformed wakeup"),
+            }
+        }
+
+        tracing::warn!(%channel, "wakeup subscription lost, reconnecting");
+        let mut delay = Duration::from_millis(100);
+        pubsub = loop {
+            tokio::time::sleep(delay).await;
+            match subscribe(&client, &channel).await {
+                Ok(pubsub) => break pubsub,
+                Err(e) => {
+                    tracing::warn!(%channel, error = %e, "cannot resubscribe to wakeups");
+                    delay = (delay * 2).min(Duration::from_secs(10));
+                }
+            }
+        };
+        local.wake_all();
+    }
+}
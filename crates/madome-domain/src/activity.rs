@@ -1,6 +1,13 @@
-//! User activity domain types: tastes, histories, notifications.
+//! User activity domain types: tastes, histories, notifications and the
+//! activity feed that records them.
+
+use std::fmt;
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::pagination::Sort;
 
 /// Category of a user taste (like/dislike).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -10,6 +17,20 @@ pub enum TasteKind {
     BookTag,
 }
 
+/// Why a user disliked something, given with a dislike taste.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DislikeReason {
+    NotInterested,
+    LowQuality,
+    Disturbing,
+    WrongTags,
+    Other,
+}
+
+/// Longest `note` a taste may carry, in characters.
+pub const MAX_TASTE_NOTE_CHARS: usize = 200;
+
 /// Category of a user reading history entry.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -17,6 +38,144 @@ pub enum HistoryKind {
     Book,
 }
 
+/// Sort order for the `GET /users/@me/tastes` listing (`sort-by`).
+///
+/// Hyphenated wire strings like [`BookSortBy`](crate::book::BookSortBy).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TasteSortBy {
+    CreatedAt(Sort),
+    Random,
+    /// Highest [`taste_affinity`] first. Only liked books have a score;
+    /// other tastes follow, newest first.
+    Affinity,
+}
+
+impl Default for TasteSortBy {
+    fn default() -> Self {
+        Self::CreatedAt(Sort::Desc)
+    }
+}
+
+impl TasteSortBy {
+    const VARIANTS: &'static [&'static str] =
+        &["created-at-desc", "created-at-asc", "random", "affinity"];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::CreatedAt(Sort::Desc) => "created-at-desc",
+            Self::CreatedAt(Sort::Asc) => "created-at-asc",
+            Self::Random => "random",
+            Self::Affinity => "affinity",
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TasteSortBy {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        match s.as_str() {
+            "created-at-desc" => Ok(Self::CreatedAt(Sort::Desc)),
+            "created-at-asc" => Ok(Self::CreatedAt(Sort::Asc)),
+            "random" => Ok(Self::Random),
+            "affinity" => Ok(Self::Affinity),
+            other => Err(serde::de::Error::unknown_variant(other, Self::VARIANTS)),
+        }
+    }
+}
+
+impl Serialize for TasteSortBy {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// Days for a like's weight in [`taste_affinity`] to halve.
+pub const TASTE_AFFINITY_HALF_LIFE_DAYS: f64 = 90.0;
+/// Days for a read's weight in [`taste_affinity`] to halve; reading moves
+/// on faster than liking.
+pub const READ_AFFINITY_HALF_LIFE_DAYS: f64 = 14.0;
+
+/// Score of a liked book for [`TasteSortBy::Affinity`], between 0 and 2:
+/// how recently it was liked plus how recently it was last read (`None` if
+/// it never was), each decaying by half over its half-life.
+///
+/// The users repository computes the same expression in SQL; this is the
+/// reference it is tested against.
+pub fn taste_affinity(liked_days_ago: f64, read_days_ago: Option<f64>) -> f64 {
+    let decay = |days: f64, half_life: f64| 0.5f64.powf(days.max(0.0) / half_life);
+    decay(liked_days_ago, TASTE_AFFINITY_HALF_LIFE_DAYS)
+        + read_days_ago.map_or(0.0, |days| decay(days, READ_AFFINITY_HALF_LIFE_DAYS))
+}
+
+/// Sort order for the `GET /users/@me/histories` listing (`sort-by`).
+///
+/// A history entry is bumped on every read, so the default is most recently
+/// read first rather than first read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistorySortBy {
+    CreatedAt(Sort),
+    UpdatedAt(Sort),
+    Random,
+}
+
+impl Default for HistorySortBy {
+    fn default() -> Self {
+        Self::UpdatedAt(Sort::Desc)
+    }
+}
+
+impl HistorySortBy {
+    const VARIANTS: &'static [&'static str] = &[
+        "created-at-desc",
+        "created-at-asc",
+        "updated-at-desc",
+        "updated-at-asc",
+        "random",
+    ];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::CreatedAt(Sort::Desc) => "created-at-desc",
+            Self::CreatedAt(Sort::Asc) => "created-at-asc",
+            Self::UpdatedAt(Sort::Desc) => "updated-at-desc",
+            Self::UpdatedAt(Sort::Asc) => "updated-at-asc",
+            Self::Random => "random",
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for HistorySortBy {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        match s.as_str() {
+            "created-at-desc" => Ok(Self::CreatedAt(Sort::Desc)),
+            "created-at-asc" => Ok(Self::CreatedAt(Sort::Asc)),
+            "updated-at-desc" => Ok(Self::UpdatedAt(Sort::Desc)),
+            "updated-at-asc" => Ok(Self::UpdatedAt(Sort::Asc)),
+            "random" => Ok(Self::Random),
+            other => Err(serde::de::Error::unknown_variant(other, Self::VARIANTS)),
+        }
+    }
+}
+
+impl Serialize for HistorySortBy {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
 /// Category of a user notification.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -24,10 +183,81 @@ pub enum NotificationKind {
     Book,
 }
 
+/// Category of an activity feed entry: which kind of action produced it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    Taste,
+    History,
+    Notification,
+}
+
+impl fmt::Display for ActivityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Taste => "taste",
+            Self::History => "history",
+            Self::Notification => "notification",
+        };
+        f.write_str(s)
+    }
+}
+
+/// Error returned when a string cannot be parsed as an [`ActivityKind`].
+#[derive(Debug, Error)]
+#[error("unknown activity kind: {0:?}")]
+pub struct UnknownActivityKind(pub String);
+
+impl FromStr for ActivityKind {
+    type Err = UnknownActivityKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "taste" => Ok(Self::Taste),
+            "history" => Ok(Self::History),
+            "notification" => Ok(Self::Notification),
+            other => Err(UnknownActivityKind(other.to_owned())),
+        }
+    }
+}
+
+/// Which row survives when a book id is renewed and the user already has a
+/// taste, history or notification on the new id.
+///
+/// The more recently written row wins, `is_dislike` and `page` included. On a
+/// tie the row already on the new id stays, so replaying a renewal changes
+/// nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenewMerge {
+    /// Move the old row's values onto the new id, replacing the row there.
+    KeepOld,
+    /// Keep the row on the new id and drop the old one.
+    KeepNew,
+}
+
+impl RenewMerge {
+    /// Decide by when each row was last written (`updated_at`, or
+    /// `created_at` for rows that are never updated).
+    pub fn by_recency<T: Ord>(old_written_at: T, new_written_at: T) -> Self {
+        if old_written_at > new_written_at {
+            Self::KeepOld
+        } else {
+            Self::KeepNew
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn should_keep_the_more_recent_row_and_the_new_one_on_ties() {
+        assert_eq!(RenewMerge::by_recency(2, 1), RenewMerge::KeepOld);
+        assert_eq!(RenewMerge::by_recency(1, 2), RenewMerge::KeepNew);
+        assert_eq!(RenewMerge::by_recency(1, 1), RenewMerge::KeepNew);
+    }
+
     #[test]
     fn should_serialize_taste_kind_as_snake_case() {
         assert_eq!(serde_json::to_string(&TasteKind::Book).unwrap(), "\"book\"");
@@ -49,6 +279,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_serialize_dislike_reason_as_snake_case() {
+        assert_eq!(
+            serde_json::to_string(&DislikeReason::NotInterested).unwrap(),
+            "\"not_interested\""
+        );
+        assert_eq!(
+            serde_json::from_str::<DislikeReason>("\"wrong_tags\"").unwrap(),
+            DislikeReason::WrongTags
+        );
+        assert!(serde_json::from_str::<DislikeReason>("\"boring\"").is_err());
+    }
+
     #[test]
     fn should_serialize_history_kind_as_snake_case() {
         assert_eq!(
@@ -64,4 +307,72 @@ mod tests {
             "\"book\""
         );
     }
+
+    #[test]
+    fn should_round_trip_every_taste_sort_by_variant() {
+        for &wire in TasteSortBy::VARIANTS {
+            let json = format!("\"{wire}\"");
+            let sort: TasteSortBy = serde_json::from_str(&json).unwrap();
+            assert_eq!(serde_json::to_string(&sort).unwrap(), json);
+        }
+        assert_eq!(TasteSortBy::default(), TasteSortBy::CreatedAt(Sort::Desc));
+    }
+
+    #[test]
+    fn should_rank_a_recent_read_above_an_old_one() {
+        assert_eq!(taste_affinity(0.0, Some(0.0)), 2.0);
+        assert_eq!(taste_affinity(90.0, None), 0.5);
+        assert!(taste_affinity(60.0, Some(1.0)) > taste_affinity(1.0, Some(60.0)));
+        assert!(taste_affinity(10.0, Some(30.0)) > taste_affinity(10.0, None));
+    }
+
+    #[test]
+    fn should_round_trip_every_history_sort_by_variant() {
+        for &wire in HistorySortBy::VARIANTS {
+            let json = format!("\"{wire}\"");
+            let sort: HistorySortBy = serde_json::from_str(&json).unwrap();
+            assert_eq!(serde_json::to_string(&sort).unwrap(), json);
+        }
+        assert_eq!(
+            HistorySortBy::default(),
+            HistorySortBy::UpdatedAt(Sort::Desc)
+        );
+    }
+
+    #[test]
+    fn should_reject_unknown_activity_sort_by() {
+        assert!(serde_json::from_str::<TasteSortBy>("\"updated-at-desc\"").is_err());
+        assert!(serde_json::from_str::<HistorySortBy>("\"id-desc\"").is_err());
+    }
+
+    #[test]
+    fn should_deserialize_sort_by_into_typed_query() {
+        #[derive(Deserialize)]
+        struct Query {
+            #[serde(rename = "sort-by", default)]
+            sort_by: HistorySortBy,
+        }
+
+        let q: Query = serde_json::from_str(r#"{"sort-by":"created-at-asc"}"#).unwrap();
+        assert_eq!(q.sort_by, HistorySortBy::CreatedAt(Sort::Asc));
+        let q: Query = serde_json::from_str("{}").unwrap();
+        assert_eq!(q.sort_by, HistorySortBy::default());
+    }
+
+    #[test]
+    fn should_round_trip_activity_kind_through_display_and_from_str() {
+        for kind in [
+            ActivityKind::Taste,
+            ActivityKind::History,
+            ActivityKind::Notification,
+        ] {
+            assert_eq!(kind.to_string().parse::<ActivityKind>().unwrap(), kind);
+            assert_eq!(serde_json::to_string(&kind).unwrap(), format!("\"{kind}\""));
+        }
+    }
+
+    #[test]
+    fn should_return_error_for_unknown_activity_kind() {
+        assert!("shelf".parse::<ActivityKind>().is_err());
+    }
 }
@@ -1,5 +1,6 @@
 //! Book tag domain types.
 
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::str::FromStr;
 
@@ -56,6 +57,56 @@ impl FromStr for BookTagKind {
     }
 }
 
+/// Tags a user dislikes, for hiding books that carry any of them (the
+/// `hide_disliked` setting).
+///
+/// Built from the user's book-tag dislikes (`taste_book_tags` rows with
+/// `is_dislike`); [`DislikedTags::default`] hides nothing, which is what a
+/// user without `hide_disliked` gets.
+///
+/// ```
+/// use madome_domain::book_tag::{BookTagKind, DislikedTags};
+///
+/// let disliked = DislikedTags::from_iter([(BookTagKind::Female, "tentacles".to_owned())]);
+/// let mut books = vec![(1, vec![(BookTagKind::Female, "tentacles")]), (2, vec![])];
+/// books.retain(|(_, tags)| !disliked.hides(tags.iter().copied()));
+/// assert_eq!(books, [(2, vec![])]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DislikedTags {
+    by_kind: HashMap<BookTagKind, HashSet<String>>,
+}
+
+impl DislikedTags {
+    pub fn is_empty(&self) -> bool {
+        self.by_kind.is_empty()
+    }
+
+    pub fn contains(&self, kind: BookTagKind, name: &str) -> bool {
+        self.by_kind
+            .get(&kind)
+            .is_some_and(|names| names.contains(name))
+    }
+
+    /// Whether a book with `tags` should be hidden.
+    pub fn hides<'a>(&self, tags: impl IntoIterator<Item = (BookTagKind, &'a str)>) -> bool {
+        !self.is_empty()
+            && tags
+                .into_iter()
+                .any(|(kind, name)| self.contains(kind, name))
+    }
+}
+
+impl FromIterator<(BookTagKind, String)> for DislikedTags {
+    fn from_iter<I: IntoIterator<Item = (BookTagKind, String)>>(tags: I) -> Self {
+        let mut by_kind: HashMap<BookTagKind, HashSet<String>> = HashMap::new();
+        for (kind, name) in tags {
+            by_kind.entry(kind).or_default().insert(name);
+        }
+        Self { by_kind }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -115,4 +166,16 @@ mod tests {
     fn should_return_error_for_unknown_tag_kind() {
         assert!("unknown".parse::<BookTagKind>().is_err());
     }
+
+    #[test]
+    fn should_hide_books_with_a_disliked_tag_of_the_same_kind() {
+        let disliked = DislikedTags::from_iter([(BookTagKind::Female, "glasses".to_owned())]);
+        assert!(disliked.hides([
+            (BookTagKind::Misc, "full color"),
+            (BookTagKind::Female, "glasses"),
+        ]));
+        // Same name under another kind is a different tag.
+        assert!(!disliked.hides([(BookTagKind::Male, "glasses")]));
+        assert!(!DislikedTags::default().hides([(BookTagKind::Female, "glasses")]));
+    }
 }
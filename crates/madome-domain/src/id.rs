@@ -1,4 +1,7 @@
 //! Newtype wrappers for domain identifiers.
+//!
+//! With the `sea-orm` feature the wrappers are also column value types, so
+//! infra code can filter on them and read them out of query results directly.
 
 use std::fmt;
 use std::str::FromStr;
@@ -8,6 +11,8 @@ use uuid::Uuid;
 
 /// Identifies a user account.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::DeriveValueType))]
+#[serde(transparent)]
 pub struct UserId(pub Uuid);
 
 impl fmt::Display for UserId {
@@ -30,8 +35,16 @@ impl From<Uuid> for UserId {
     }
 }
 
+impl From<UserId> for Uuid {
+    fn from(id: UserId) -> Self {
+        id.0
+    }
+}
+
 /// Identifies a book in the library.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::DeriveValueType))]
+#[serde(transparent)]
 pub struct BookId(pub u32);
 
 impl fmt::Display for BookId {
@@ -54,8 +67,16 @@ impl From<u32> for BookId {
     }
 }
 
+impl From<BookId> for u32 {
+    fn from(id: BookId) -> Self {
+        id.0
+    }
+}
+
 /// Identifies an email authentication code.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::DeriveValueType))]
+#[serde(transparent)]
 pub struct AuthcodeId(pub Uuid);
 
 impl fmt::Display for AuthcodeId {
@@ -78,6 +99,12 @@ impl From<Uuid> for AuthcodeId {
     }
 }
 
+impl From<AuthcodeId> for Uuid {
+    fn from(id: AuthcodeId) -> Self {
+        id.0
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -113,4 +140,11 @@ mod tests {
         let json = serde_json::to_string(&id).unwrap();
         assert_eq!(json, "\"550e8400-e29b-41d4-a716-446655440000\"");
     }
+
+    #[test]
+    fn should_deserialize_book_id_from_bare_number() {
+        let id: BookId = serde_json::from_str("42").unwrap();
+        assert_eq!(id, BookId(42));
+        assert_eq!(u32::from(id), 42);
+    }
 }
@@ -1,6 +1,10 @@
 //! Pagination and sort direction types.
 
+use std::fmt;
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 /// Generic sort direction.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -10,6 +14,39 @@ pub enum Sort {
     Asc,
 }
 
+/// Seed for a stable random ordering (`sort-by=random&seed=`).
+///
+/// The same seed yields the same permutation on every page, so paging through a
+/// random list never repeats or skips rows. Repositories order by a hash of the
+/// row id mixed with the seed (`hashint8extended(id, seed), id` in PostgreSQL)
+/// rather than `RANDOM()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RandomSeed(pub u32);
+
+impl RandomSeed {
+    /// Deterministic sort key for `id` under this seed.
+    ///
+    /// In-memory counterpart of the SQL ordering for mocks and merged result
+    /// sets. Stable per seed, but not the same permutation PostgreSQL produces.
+    ///
+    /// ```
+    /// use madome_domain::pagination::RandomSeed;
+    ///
+    /// let seed = RandomSeed(7);
+    /// assert_eq!(seed.sort_key(42), seed.sort_key(42));
+    /// assert_ne!(seed.sort_key(42), RandomSeed(8).sort_key(42));
+    /// ```
+    pub fn sort_key(self, id: i64) -> u64 {
+        // SplitMix64 finalizer: cheap and well mixed for sequential ids.
+        let mut z = (id as u64) ^ (u64::from(self.0) << 32 | u64::from(self.0));
+        z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
 /// Pagination parameters shared across all list endpoints.
 ///
 /// - `per_page`: 1–100, default 25
@@ -51,6 +88,162 @@ impl PageRequest {
     }
 }
 
+/// One page of a list plus the total number of matching rows.
+///
+/// Repositories compute `total` in the same round trip as the page (e.g.
+/// `COUNT(*) OVER()`), so handlers can emit `x-madome-total-count` without a
+/// separate count query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Paged<T> {
+    pub items: Vec<T>,
+    pub total: u64,
+}
+
+impl<T> Paged<T> {
+    pub fn new(items: Vec<T>, total: u64) -> Self {
+        Self { items, total }
+    }
+
+    /// Convert every item, keeping `total`.
+    ///
+    /// ```
+    /// use madome_domain::pagination::Paged;
+    ///
+    /// let page = Paged::new(vec![1, 2], 10).map(|n| n * 2);
+    /// assert_eq!(page.items, vec![2, 4]);
+    /// assert_eq!(page.total, 10);
+    /// ```
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Paged<U> {
+        Paged {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+        }
+    }
+}
+
+impl PageRequest {
+    /// Row offset of this page (`(page - 1) * per_page`).
+    pub fn offset(self) -> u64 {
+        u64::from(self.page.saturating_sub(1)) * u64::from(self.per_page)
+    }
+}
+
+/// Position after the last row of a keyset-paginated page (`?after=`).
+///
+/// Lists that grow too long for `OFFSET` page by their sort key instead: the
+/// next page is the rows after `(created_at, key)` of the last row sent, where
+/// `key` breaks ties between rows created in the same microsecond (e.g. the
+/// composite key of a taste). Clients treat the string form as opaque.
+///
+/// ```
+/// use madome_domain::pagination::KeysetCursor;
+///
+/// let cursor = KeysetCursor::new(1_760_000_000_000_000, "book:42");
+/// let after: KeysetCursor = cursor.to_string().parse().unwrap();
+/// assert_eq!(after, cursor);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeysetCursor {
+    /// `created_at` of the last row, in microseconds since the Unix epoch.
+    pub created_at_micros: i64,
+    pub key: String,
+}
+
+impl KeysetCursor {
+    pub fn new(created_at_micros: i64, key: impl Into<String>) -> Self {
+        Self {
+            created_at_micros,
+            key: key.into(),
+        }
+    }
+}
+
+/// `{created_at_micros}.{key as hex}`, so any key is safe in a query string.
+impl fmt::Display for KeysetCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.", self.created_at_micros)?;
+        self.key.bytes().try_for_each(|b| write!(f, "{b:02x}"))
+    }
+}
+
+/// Error returned when an `after` cursor was not produced by [`KeysetCursor`].
+#[derive(Debug, Error)]
+#[error("invalid cursor: {0:?}")]
+pub struct InvalidCursor(pub String);
+
+impl FromStr for KeysetCursor {
+    type Err = InvalidCursor;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidCursor(s.to_owned());
+        let (micros, key) = s.split_once('.').ok_or_else(invalid)?;
+        if key.len() % 2 != 0 || !key.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let key = (0..key.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&key[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| invalid())?;
+        Ok(Self {
+            created_at_micros: micros.parse().map_err(|_| invalid())?,
+            key: String::from_utf8(key).map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl TryFrom<String> for KeysetCursor {
+    type Error = InvalidCursor;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<KeysetCursor> for String {
+    fn from(cursor: KeysetCursor) -> Self {
+        cursor.to_string()
+    }
+}
+
+/// One keyset-paginated page; `next` is `None` on the last page.
+///
+/// There is no `total`: counting every row is the cost keyset pagination
+/// avoids.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeysetPage<T> {
+    pub items: Vec<T>,
+    pub next: Option<KeysetCursor>,
+}
+
+impl<T> KeysetPage<T> {
+    /// Page of `limit` rows from a query that fetched up to `limit + 1`: the
+    /// extra row only tells that there is a next page, which starts after
+    /// the `cursor` of the last row kept.
+    pub fn from_overfetch(
+        mut rows: Vec<T>,
+        limit: usize,
+        cursor: impl Fn(&T) -> KeysetCursor,
+    ) -> Self {
+        let next = if rows.len() > limit {
+            rows.truncate(limit);
+            rows.last().map(cursor)
+        } else {
+            None
+        };
+        Self { items: rows, next }
+    }
+
+    /// Convert every item, keeping `next`.
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> KeysetPage<U> {
+        KeysetPage {
+            items: self.items.into_iter().map(f).collect(),
+            next: self.next,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -122,6 +315,73 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_compute_offset_from_page() {
+        let p = PageRequest {
+            per_page: 25,
+            page: 3,
+        };
+        assert_eq!(p.offset(), 50);
+        assert_eq!(PageRequest::default().offset(), 0);
+    }
+
+    #[test]
+    fn should_keep_total_when_mapping_paged() {
+        let page = Paged::new(vec!["a", "b"], 7).map(str::len);
+        assert_eq!(page, Paged::new(vec![1, 1], 7));
+    }
+
+    #[test]
+    fn should_order_ids_stably_for_same_seed() {
+        let order = |seed: RandomSeed| {
+            let mut ids: Vec<i64> = (1..=50).collect();
+            ids.sort_by_key(|&id| seed.sort_key(id));
+            ids
+        };
+        assert_eq!(order(RandomSeed(1)), order(RandomSeed(1)));
+        assert_ne!(order(RandomSeed(1)), order(RandomSeed(2)));
+        assert_ne!(order(RandomSeed(1)), (1..=50).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn should_deserialize_random_seed_from_number() {
+        let seed: RandomSeed = serde_json::from_str("123").unwrap();
+        assert_eq!(seed, RandomSeed(123));
+    }
+
+    #[test]
+    fn should_round_trip_cursor_through_query_string() {
+        let cursor = KeysetCursor::new(-5, "book_tag:artist:a b/ç");
+        let encoded = cursor.to_string();
+        assert!(
+            encoded
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'.' || b == b'-')
+        );
+
+        let json = serde_json::to_string(&cursor).unwrap();
+        assert_eq!(json, format!("\"{encoded}\""));
+        assert_eq!(serde_json::from_str::<KeysetCursor>(&json).unwrap(), cursor);
+    }
+
+    #[test]
+    fn should_reject_malformed_cursor() {
+        for s in ["", "123", "abc.00", "1.0", "1.zz", "1.ff"] {
+            assert!(s.parse::<KeysetCursor>().is_err(), "{s:?} accepted");
+        }
+    }
+
+    #[test]
+    fn should_point_next_cursor_at_last_kept_row() {
+        let cursor = |n: &i64| KeysetCursor::new(*n, "");
+        let page = KeysetPage::from_overfetch(vec![30, 20, 10], 2, cursor);
+        assert_eq!(page.items, [30, 20]);
+        assert_eq!(page.next, Some(KeysetCursor::new(20, "")));
+
+        let last = KeysetPage::from_overfetch(vec![30, 20], 2, cursor);
+        assert_eq!(last.next, None);
+    }
+
     #[test]
     fn should_serialize_sort_as_kebab_case() {
         assert_eq!(serde_json::to_string(&Sort::Desc).unwrap(), "\"desc\"");
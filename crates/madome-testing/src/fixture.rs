@@ -1,6 +1,8 @@
-//! Contract fixture loader.
+//! Contract fixture loader and seed datasets.
 //!
-//! Loads golden files from `contracts/http/` for contract assertion tests.
+//! Loads golden files from `contracts/http/` for contract assertion tests, and
+//! names the [`Dataset`]s contract fixtures can seed before their request
+//! (`"setup": {"fixtures": ["auth/user"]}`).
 
 use std::path::Path;
 
@@ -38,3 +40,98 @@ impl Fixture {
             .unwrap_or_else(|e| panic!("invalid JSON in fixture {}: {}", relative_path, e))
     }
 }
+
+/// `users.id` of the normal user seeded by `auth/user`.
+pub const USER_ID: &str = "00000000-0000-7000-8000-00000000c001";
+/// `users.id` of the staff (bot) user seeded by `auth/staff`.
+pub const STAFF_ID: &str = "00000000-0000-7000-8000-00000000c002";
+/// Unused, unexpired sign-in code for `user@example.com`, seeded by `auth/user_with_code`.
+pub const USER_AUTHCODE: &str = "CONTRACT0001";
+
+/// Named seed SQL for one service's database.
+///
+/// Datasets are idempotent: loading one twice, or loading datasets that share
+/// rows, leaves the same state. Each load restores the rows it owns, so a
+/// single-use code is usable again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dataset {
+    /// `{service}/{name}`, as fixtures refer to it.
+    pub name: &'static str,
+    /// Service whose database the SQL runs in.
+    pub service: &'static str,
+    pub sql: &'static str,
+}
+
+pub const DATASETS: &[Dataset] = &[
+    Dataset {
+        name: "auth/user",
+        service: "auth",
+        sql: "INSERT INTO users (id, email, role) \
+              VALUES ('00000000-0000-7000-8000-00000000c001', 'user@example.com', 0) \
+              ON CONFLICT (id) DO UPDATE SET email = EXCLUDED.email, role = 0, deleted_at = NULL;",
+    },
+    Dataset {
+        name: "auth/staff",
+        service: "auth",
+        sql: "INSERT INTO users (id, email, role) \
+              VALUES ('00000000-0000-7000-8000-00000000c002', 'staff@example.com', 2) \
+              ON CONFLICT (id) DO UPDATE SET email = EXCLUDED.email, role = 2, deleted_at = NULL;",
+    },
+    Dataset {
+        name: "auth/user_with_code",
+        service: "auth",
+        sql: "INSERT INTO users (id, email, role) \
+              VALUES ('00000000-0000-7000-8000-00000000c001', 'user@example.com', 0) \
+              ON CONFLICT (id) DO UPDATE SET email = EXCLUDED.email, role = 0, deleted_at = NULL; \
+              INSERT INTO auth_codes (id, user_id, code, expires_at, used_at, created_at) \
+              VALUES ('00000000-0000-7000-8000-00000000c101', '00000000-0000-7000-8000-00000000c001', \
+                      'CONTRACT0001', now() + interval '1 hour', NULL, now()) \
+              ON CONFLICT (id) DO UPDATE SET expires_at = EXCLUDED.expires_at, used_at = NULL;",
+    },
+];
+
+/// The dataset called `name`, e.g. `auth/user`.
+pub fn dataset(name: &str) -> Option<&'static Dataset> {
+    DATASETS.iter().find(|d| d.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_prefix_dataset_names_with_their_service() {
+        for d in DATASETS {
+            assert!(
+                d.name.starts_with(&format!("{}/", d.service)),
+                "{} is not under {}/",
+                d.name,
+                d.service
+            );
+        }
+    }
+
+    #[test]
+    fn should_have_unique_dataset_names() {
+        for (i, d) in DATASETS.iter().enumerate() {
+            assert!(
+                DATASETS[i + 1..].iter().all(|other| other.name != d.name),
+                "duplicate dataset {}",
+                d.name
+            );
+        }
+    }
+
+    #[test]
+    fn should_seed_the_documented_ids() {
+        assert!(dataset("auth/user").unwrap().sql.contains(USER_ID));
+        assert!(dataset("auth/staff").unwrap().sql.contains(STAFF_ID));
+        assert!(
+            dataset("auth/user_with_code")
+                .unwrap()
+                .sql
+                .contains(USER_AUTHCODE)
+        );
+        assert_eq!(dataset("auth/nobody"), None);
+    }
+}
@@ -1,13 +1,731 @@
-//! Mock gRPC server helpers.
+//! In-process gRPC mock servers.
 //!
-//! Provides an in-process tonic server with configurable responses for testing
-//! services that call other services via gRPC.
+//! Services that call a peer over gRPC point their client at one of these
+//! instead of hand-rolling a port mock per test:
 //!
-//! Full implementations are added per-service as each Unit is built.
-//! This file contains the shared skeleton.
+//! ```no_run
+//! # async fn run() {
+//! use madome_testing::grpc::MockLibraryServer;
+//!
+//! let library = MockLibraryServer::new().with_book(1).with_book(2).spawn().await;
+//! let endpoint = library.url(); // "http://127.0.0.1:{ephemeral port}"
+//! // ... build the service under test with `endpoint` ...
+//! assert!(library.renewals().is_empty());
+//! # }
+//! ```
+//!
+//! Servers answer from the data given to the builder and record the calls
+//! that change state (`RenewBook`, `RenewBooks`), so tests can assert on
+//! them. A server stops when its [`MockServer`] handle is dropped.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use futures::Stream;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tonic::transport::Server;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+use madome_proto::auth::{
+    self, IntrospectTokenRequest, Introspection, ResolveLegacyIdReply, ResolveLegacyIdRequest,
+    RevokeSessionRequest,
+    auth_service_server::{AuthService, AuthServiceServer},
+};
+use madome_proto::library::{
+    self, Book, BookIdsReply, BookIdsRequest, BookList, SearchBooksRequest,
+    library_service_server::{LibraryService, LibraryServiceServer},
+};
+use madome_proto::user::{
+    self, BookTagTaste, BookTaste, GetTasteCountsRequest, GetTastesRequest, GetUserRequest,
+    GetUsersByIdsRequest, RenewBooksReply, RenewBooksRequest, StreamTastesRequest, Taste,
+    TasteCount, TasteCountList, TasteList, User, UserList,
+    taste::Kind,
+    user_service_server::{UserService, UserServiceServer},
+};
+
+/// `(old_book_id, new_book_id)` of each rename received, in call order.
+pub type Renewals = Arc<Mutex<Vec<(u32, u32)>>>;
+
+/// A running mock server; dropping it shuts the server down.
+pub struct MockServer {
+    addr: SocketAddr,
+    renewals: Renewals,
+    _shutdown: oneshot::Sender<()>,
+}
+
+impl MockServer {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// `http://` URL to hand to a tonic `Endpoint`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Renames received so far, batched ones included.
+    pub fn renewals(&self) -> Vec<(u32, u32)> {
+        self.renewals.lock().unwrap().clone()
+    }
+}
+
+/// Serve `router` on an ephemeral loopback port until the handle is dropped.
+async fn spawn_server(router: tonic::transport::server::Router, renewals: Renewals) -> MockServer {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind mock gRPC server");
+    let addr = listener.local_addr().expect("mock gRPC server address");
+    let (shutdown, signal) = oneshot::channel::<()>();
+    tokio::spawn(async move {
+        router
+            .serve_with_incoming_shutdown(TcpIncoming::from(listener), async {
+                let _ = signal.await;
+            })
+            .await
+            .expect("mock gRPC server failed");
+    });
+    MockServer {
+        addr,
+        renewals,
+        _shutdown: shutdown,
+    }
+}
+
+// ── LibraryService ───────────────────────────────────────────────────────────
+
+/// Builder for a mock `library.LibraryService`.
+#[derive(Clone, Default)]
+pub struct MockLibraryServer {
+    books: BTreeMap<u32, Book>,
+    renewals: Renewals,
+}
+
+impl MockLibraryServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A book with placeholder title, kind `doujinshi` and thumbnail path.
+    pub fn with_book(self, id: u32) -> Self {
+        self.with_book_detail(Book {
+            id,
+            title: format!("Book {id}"),
+            kind: "doujinshi".to_owned(),
+            thumbnail_path: format!("/image/library/{id}/thumbnail.jpg"),
+            published_at: None,
+        })
+    }
+
+    pub fn with_book_detail(mut self, book: Book) -> Self {
+        self.books.insert(book.id, book);
+        self
+    }
+
+    pub async fn spawn(self) -> MockServer {
+        let renewals = self.renewals.clone();
+        let router = Server::builder().add_service(LibraryServiceServer::new(self));
+        spawn_server(router, renewals).await
+    }
+}
+
+#[tonic::async_trait]
+impl LibraryService for MockLibraryServer {
+    async fn renew_book(
+        &self,
+        request: Request<library::RenewBookRequest>,
+    ) -> Result<Response<library::Empty>, Status> {
+        let req = request.into_inner();
+        self.renewals
+            .lock()
+            .unwrap()
+            .push((req.old_book_id, req.new_book_id));
+        Ok(Response::new(library::Empty {}))
+    }
 
-/// Marker trait for mock gRPC service implementations.
+    async fn has_books(
+        &self,
+        request: Request<BookIdsRequest>,
+    ) -> Result<Response<BookIdsReply>, Status> {
+        let book_ids = request
+            .into_inner()
+            .book_ids
+            .into_iter()
+            .filter(|id| self.books.contains_key(id))
+            .collect();
+        Ok(Response::new(BookIdsReply { book_ids }))
+    }
+
+    async fn get_books(
+        &self,
+        request: Request<BookIdsRequest>,
+    ) -> Result<Response<BookList>, Status> {
+        let books = request
+            .into_inner()
+            .book_ids
+            .iter()
+            .filter_map(|id| self.books.get(id).cloned())
+            .collect();
+        Ok(Response::new(BookList { books }))
+    }
+
+    /// Case-insensitive substring match on titles, in id order.
+    async fn search_books(
+        &self,
+        request: Request<SearchBooksRequest>,
+    ) -> Result<Response<BookIdsReply>, Status> {
+        let req = request.into_inner();
+        let query = req.query.trim().to_lowercase();
+        if query.is_empty() {
+            return Err(Status::invalid_argument("query is empty"));
+        }
+        let limit = match req.limit {
+            0 | 1001.. => 1000,
+            limit => limit as usize,
+        };
+        let book_ids = self
+            .books
+            .values()
+            .filter(|book| book.title.to_lowercase().contains(&query))
+            .map(|book| book.id)
+            .take(limit)
+            .collect();
+        Ok(Response::new(BookIdsReply { book_ids }))
+    }
+}
+
+// ── AuthService ──────────────────────────────────────────────────────────────
+
+/// Builder for a mock `auth.AuthService`.
+///
+/// Tokens not added with [`MockAuthServer::with_token`] introspect as inactive.
+/// Sessions are revoked once; revoking again is `NOT_FOUND`. Legacy ids not
+/// added with [`MockAuthServer::with_legacy_id`] resolve as `NOT_FOUND`.
+#[derive(Clone, Default)]
+pub struct MockAuthServer {
+    tokens: BTreeMap<String, Introspection>,
+    sessions: Arc<Mutex<BTreeSet<(String, String)>>>,
+    legacy_ids: BTreeMap<i64, Uuid>,
+}
+
+impl MockAuthServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An active access token of `user_id`, expiring in 2100.
+    pub fn with_token(self, token: &str, user_id: Uuid, role: u32) -> Self {
+        self.with_introspection(
+            token,
+            Introspection {
+                active: true,
+                user_id: user_id.to_string(),
+                user_role: role,
+                exp: 4_102_444_800,
+                impersonator_id: None,
+                scopes: Vec::new(),
+            },
+        )
+    }
+
+    pub fn with_introspection(mut self, token: &str, introspection: Introspection) -> Self {
+        self.tokens.insert(token.to_owned(), introspection);
+        self
+    }
+
+    pub fn with_session(self, user_id: Uuid, session_id: Uuid) -> Self {
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert((user_id.to_string(), session_id.to_string()));
+        self
+    }
+
+    pub fn with_legacy_id(mut self, legacy_id: i64, user_id: Uuid) -> Self {
+        self.legacy_ids.insert(legacy_id, user_id);
+        self
+    }
+
+    pub async fn spawn(self) -> MockServer {
+        let router = Server::builder().add_service(AuthServiceServer::new(self));
+        spawn_server(router, Renewals::default()).await
+    }
+}
+
+#[tonic::async_trait]
+impl AuthService for MockAuthServer {
+    async fn introspect_token(
+        &self,
+        request: Request<IntrospectTokenRequest>,
+    ) -> Result<Response<Introspection>, Status> {
+        let token = request.into_inner().token;
+        let introspection = self.tokens.get(&token).cloned().unwrap_or_default();
+        Ok(Response::new(introspection))
+    }
+
+    async fn revoke_session(
+        &self,
+        request: Request<RevokeSessionRequest>,
+    ) -> Result<Response<auth::Empty>, Status> {
+        let req = request.into_inner();
+        if self
+            .sessions
+            .lock()
+            .unwrap()
+            .remove(&(req.user_id, req.session_id))
+        {
+            Ok(Response::new(auth::Empty {}))
+        } else {
+            Err(Status::not_found("session not found"))
+        }
+    }
+
+    async fn resolve_legacy_id(
+        &self,
+        request: Request<ResolveLegacyIdRequest>,
+    ) -> Result<Response<ResolveLegacyIdReply>, Status> {
+        let legacy_id = request.into_inner().legacy_id;
+        let user_id = self
+            .legacy_ids
+            .get(&legacy_id)
+            .ok_or_else(|| Status::not_found("legacy id not found"))?;
+        Ok(Response::new(ResolveLegacyIdReply {
+            user_id: user_id.to_string(),
+        }))
+    }
+}
+
+// ── UserService ──────────────────────────────────────────────────────────────
+
+/// Builder for a mock `user.UserService`.
 ///
-/// Concrete mock servers for UserService, LibraryService, and NotificationService
-/// are added in their respective service test modules (Units D, E, G).
-pub trait MockGrpcService: Send + Sync + 'static {}
+/// Tastes are kept per user in the order they are added, which counts as
+/// oldest first for `sort_by`.
+#[derive(Clone, Default)]
+pub struct MockUserServer {
+    users: BTreeMap<String, User>,
+    tastes: BTreeMap<String, Vec<Taste>>,
+    renewals: Renewals,
+}
+
+impl MockUserServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A user with placeholder name, email and handle derived from the id.
+    pub fn with_user(self, id: Uuid, role: u32) -> Self {
+        let short = &id.simple().to_string()[..8];
+        self.with_user_detail(User {
+            id: id.to_string(),
+            name: format!("user-{short}"),
+            email: format!("{short}@example.com"),
+            handle: format!("user-{short}"),
+            role,
+            created_at: "2026-01-01T00:00:00Z".to_owned(),
+            updated_at: "2026-01-01T00:00:00Z".to_owned(),
+        })
+    }
+
+    pub fn with_user_detail(mut self, user: User) -> Self {
+        self.users.insert(user.id.clone(), user);
+        self
+    }
+
+    pub fn with_book_taste(self, user_id: Uuid, book_id: u32, is_dislike: bool) -> Self {
+        self.with_taste(
+            user_id,
+            Kind::Book(BookTaste {
+                book_id,
+                is_dislike,
+            }),
+        )
+    }
+
+    pub fn with_book_tag_taste(
+        self,
+        user_id: Uuid,
+        tag_kind: &str,
+        tag_name: &str,
+        is_dislike: bool,
+    ) -> Self {
+        self.with_taste(
+            user_id,
+            Kind::BookTag(BookTagTaste {
+                tag_kind: tag_kind.to_owned(),
+                tag_name: tag_name.to_owned(),
+                is_dislike,
+            }),
+        )
+    }
+
+    fn with_taste(mut self, user_id: Uuid, kind: Kind) -> Self {
+        self.tastes
+            .entry(user_id.to_string())
+            .or_default()
+            .push(Taste { kind: Some(kind) });
+        self
+    }
+
+    pub async fn spawn(self) -> MockServer {
+        let renewals = self.renewals.clone();
+        let router = Server::builder().add_service(UserServiceServer::new(self));
+        spawn_server(router, renewals).await
+    }
+
+    /// The user's tastes, newest first, optionally dislikes only.
+    fn tastes_of(&self, user_id: &str, dislikes_only: bool) -> Vec<Taste> {
+        let is_dislike = |t: &Taste| match &t.kind {
+            Some(Kind::Book(b)) => b.is_dislike,
+            Some(Kind::BookTag(t)) => t.is_dislike,
+            None => false,
+        };
+        self.tastes
+            .get(user_id)
+            .into_iter()
+            .flatten()
+            .rev()
+            .filter(|t| !dislikes_only || is_dislike(t))
+            .cloned()
+            .collect()
+    }
+}
+
+type TasteStream = Pin<Box<dyn Stream<Item = Result<TasteList, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl UserService for MockUserServer {
+    type StreamTastesStream = TasteStream;
+
+    async fn get_user(&self, request: Request<GetUserRequest>) -> Result<Response<User>, Status> {
+        let user_id = request.into_inner().user_id;
+        self.users
+            .get(&user_id)
+            .cloned()
+            .map(Response::new)
+            .ok_or_else(|| Status::not_found(format!("user {user_id}")))
+    }
+
+    async fn get_users_by_ids(
+        &self,
+        request: Request<GetUsersByIdsRequest>,
+    ) -> Result<Response<UserList>, Status> {
+        let user_ids = request.into_inner().user_ids;
+        if user_ids.len() > 1000 {
+            return Err(Status::invalid_argument("at most 1000 user ids"));
+        }
+        let mut seen = BTreeSet::new();
+        let users = user_ids
+            .iter()
+            .filter(|id| seen.insert(*id))
+            .filter_map(|id| self.users.get(id).cloned())
+            .collect();
+        Ok(Response::new(UserList { users }))
+    }
+
+    async fn get_tastes(
+        &self,
+        request: Request<GetTastesRequest>,
+    ) -> Result<Response<TasteList>, Status> {
+        let req = request.into_inner();
+        let mut tastes = self.tastes_of(&req.user_id, req.dislikes_only);
+        if req.sort_by == "created-at-asc" {
+            tastes.reverse();
+        }
+
+        let total = tastes.len() as u64;
+        let per_page = req.per_page.unwrap_or(100).clamp(1, 100) as usize;
+        let page = req.page.unwrap_or(1).max(1) as usize;
+        let tastes = tastes
+            .into_iter()
+            .skip((page - 1) * per_page)
+            .take(per_page)
+            .collect();
+        Ok(Response::new(TasteList { tastes, total }))
+    }
+
+    async fn stream_tastes(
+        &self,
+        request: Request<StreamTastesRequest>,
+    ) -> Result<Response<Self::StreamTastesStream>, Status> {
+        let req = request.into_inner();
+        let chunk_size = match req.chunk_size {
+            0 => 500,
+            n => n.min(1000) as usize,
+        };
+        let chunks: Vec<_> = self
+            .tastes_of(&req.user_id, req.dislikes_only)
+            .chunks(chunk_size)
+            .map(|chunk| {
+                Ok(TasteList {
+                    tastes: chunk.to_vec(),
+                    total: 0,
+                })
+            })
+            .collect();
+        Ok(Response::new(Box::pin(futures::stream::iter(chunks))))
+    }
+
+    async fn renew_book(
+        &self,
+        request: Request<user::RenewBookRequest>,
+    ) -> Result<Response<user::Empty>, Status> {
+        let req = request.into_inner();
+        self.renewals
+            .lock()
+            .unwrap()
+            .push((req.old_book_id, req.new_book_id));
+        Ok(Response::new(user::Empty {}))
+    }
+
+    /// Records each rename like `RenewBook`; the mock holds no rows to move.
+    async fn renew_books(
+        &self,
+        request: Request<RenewBooksRequest>,
+    ) -> Result<Response<RenewBooksReply>, Status> {
+        let renewals = request.into_inner().renewals;
+        if renewals.len() > 1000 {
+            return Err(Status::invalid_argument("at most 1000 renewals"));
+        }
+        self.renewals
+            .lock()
+            .unwrap()
+            .extend(renewals.iter().map(|r| (r.old_book_id, r.new_book_id)));
+        Ok(Response::new(RenewBooksReply {
+            renewed: renewals.len() as u32,
+            rows_moved: 0,
+        }))
+    }
+
+    async fn get_taste_counts(
+        &self,
+        request: Request<GetTasteCountsRequest>,
+    ) -> Result<Response<TasteCountList>, Status> {
+        let mut seen = BTreeSet::new();
+        let counts = request
+            .into_inner()
+            .book_ids
+            .into_iter()
+            .filter(|id| seen.insert(*id))
+            .map(|book_id| {
+                let mut count = TasteCount {
+                    book_id,
+                    likes: 0,
+                    dislikes: 0,
+                };
+                for taste in self.tastes.values().flatten() {
+                    if let Some(Kind::Book(b)) = &taste.kind
+                        && b.book_id == book_id
+                    {
+                        if b.is_dislike {
+                            count.dislikes += 1;
+                        } else {
+                            count.likes += 1;
+                        }
+                    }
+                }
+                count
+            })
+            .collect();
+        Ok(Response::new(TasteCountList { counts }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use madome_proto::library::library_service_client::LibraryServiceClient;
+    use madome_proto::user::user_service_client::UserServiceClient;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn should_answer_known_books_and_record_renewals() {
+        let server = MockLibraryServer::new()
+            .with_book(1)
+            .with_book(3)
+            .spawn()
+            .await;
+        let mut client = LibraryServiceClient::connect(server.url()).await.unwrap();
+
+        let has = client
+            .has_books(BookIdsRequest {
+                book_ids: vec![1, 2, 3],
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(has.book_ids, [1, 3]);
+
+        let books = client
+            .get_books(BookIdsRequest { book_ids: vec![3] })
+            .await
+            .unwrap()
+            .into_inner()
+            .books;
+        assert_eq!(books[0].title, "Book 3");
+
+        let search = |query: &str, limit| SearchBooksRequest {
+            query: query.to_owned(),
+            limit,
+        };
+        let found = client.search_books(search("BOOK 3", 0)).await.unwrap();
+        assert_eq!(found.into_inner().book_ids, [3]);
+        let found = client.search_books(search("book", 1)).await.unwrap();
+        assert_eq!(found.into_inner().book_ids, [1]);
+        let status = client.search_books(search(" ", 0)).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        client
+            .renew_book(library::RenewBookRequest {
+                old_book_id: 1,
+                new_book_id: 4,
+            })
+            .await
+            .unwrap();
+        assert_eq!(server.renewals(), [(1, 4)]);
+    }
+
+    #[tokio::test]
+    async fn should_page_and_stream_tastes_newest_first() {
+        let user_id = Uuid::from_u128(1);
+        let server = MockUserServer::new()
+            .with_user(user_id, 0)
+            .with_book_taste(user_id, 10, false)
+            .with_book_tag_taste(user_id, "female", "x", true)
+            .with_book_taste(user_id, 11, true)
+            .spawn()
+            .await;
+        let mut client = UserServiceClient::connect(server.url()).await.unwrap();
+
+        let page = client
+            .get_tastes(GetTastesRequest {
+                user_id: user_id.to_string(),
+                dislikes_only: true,
+                page: Some(1),
+                per_page: Some(1),
+                sort_by: String::new(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(page.total, 2);
+        assert_eq!(
+            page.tastes[0].kind,
+            Some(Kind::Book(BookTaste {
+                book_id: 11,
+                is_dislike: true
+            }))
+        );
+
+        let chunks: Vec<_> = client
+            .stream_tastes(StreamTastesRequest {
+                user_id: user_id.to_string(),
+                dislikes_only: false,
+                chunk_size: 2,
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .map(|chunk| chunk.unwrap().tastes.len())
+            .collect()
+            .await;
+        assert_eq!(chunks, [2, 1]);
+    }
+
+    #[tokio::test]
+    async fn should_answer_known_users_in_request_order() {
+        let (alice, bob) = (Uuid::from_u128(1), Uuid::from_u128(2));
+        let server = MockUserServer::new()
+            .with_user(alice, 0)
+            .with_user(bob, 0)
+            .spawn()
+            .await;
+        let mut client = UserServiceClient::connect(server.url()).await.unwrap();
+
+        let ids = [bob, Uuid::from_u128(3), alice, bob].map(|id| id.to_string());
+        let users: Vec<_> = client
+            .get_users_by_ids(GetUsersByIdsRequest {
+                user_ids: ids.to_vec(),
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .users
+            .into_iter()
+            .map(|u| u.id)
+            .collect();
+        assert_eq!(users, [bob.to_string(), alice.to_string()]);
+
+        let status = client
+            .get_users_by_ids(GetUsersByIdsRequest {
+                user_ids: vec![alice.to_string(); 1001],
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn should_record_batched_renewals_in_order() {
+        let server = MockUserServer::new().spawn().await;
+        let mut client = UserServiceClient::connect(server.url()).await.unwrap();
+
+        let renewals = [(1, 2), (2, 3)]
+            .map(|(old_book_id, new_book_id)| user::RenewBookRequest {
+                old_book_id,
+                new_book_id,
+            })
+            .to_vec();
+        let reply = client
+            .renew_books(RenewBooksRequest { renewals })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(reply.renewed, 2);
+        assert_eq!(server.renewals(), [(1, 2), (2, 3)]);
+    }
+
+    #[tokio::test]
+    async fn should_count_book_tastes_across_users() {
+        let (alice, bob) = (Uuid::from_u128(1), Uuid::from_u128(2));
+        let server = MockUserServer::new()
+            .with_book_taste(alice, 10, false)
+            .with_book_taste(bob, 10, true)
+            .with_book_taste(bob, 11, false)
+            .spawn()
+            .await;
+        let mut client = UserServiceClient::connect(server.url()).await.unwrap();
+
+        let counts: Vec<_> = client
+            .get_taste_counts(GetTasteCountsRequest {
+                book_ids: vec![10, 12, 10],
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .counts
+            .into_iter()
+            .map(|c| (c.book_id, c.likes, c.dislikes))
+            .collect();
+        assert_eq!(counts, [(10, 1, 1), (12, 0, 0)]);
+    }
+
+    #[tokio::test]
+    async fn should_return_not_found_for_unknown_user() {
+        let server = MockUserServer::new().spawn().await;
+        let mut client = UserServiceClient::connect(server.url()).await.unwrap();
+
+        let status = client
+            .get_user(GetUserRequest {
+                user_id: Uuid::nil().to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+}
@@ -1,8 +1,11 @@
 //! Test utilities for Madome services.
 //!
-//! Provides `MockAuthServer`, `TestApp`, fixture loader, and gRPC mock helpers.
+//! Provides `MockAuthServer`, `TestApp`, fixture loader and seed datasets,
+//! in-process gRPC mock servers for `LibraryService` and `UserService`, and
+//! proptest strategies for domain and wire types.
 //! Import in `#[cfg(test)]` blocks only — never in production code.
 
 pub mod auth;
 pub mod fixture;
 pub mod grpc;
+pub mod strategy;
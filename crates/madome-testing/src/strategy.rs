@@ -0,0 +1,365 @@
+//! proptest strategies for domain and wire types.
+//!
+//! Every strategy yields values the owning service would accept, so property
+//! tests only need their own generators for the invalid side. The exceptions
+//! are [`page_request`] and [`role_wire`], which range over the whole wire
+//! domain because clamping and rejection are what they exist to test.
+//!
+//! ```
+//! use madome_testing::strategy;
+//! use proptest::prelude::*;
+//!
+//! proptest!(|(page in strategy::page_request())| {
+//!     let page = page.clamped();
+//!     prop_assert!((1..=100).contains(&page.per_page));
+//! });
+//! ```
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, SecondsFormat};
+use jsonwebtoken::{EncodingKey, Header, encode};
+use madome_auth_types::token::TokenInfo;
+use madome_domain::activity::{HistorySortBy, TasteKind, TasteSortBy};
+use madome_domain::book::{BookKind, BookSortBy, SearchBookSortBy};
+use madome_domain::book_tag::BookTagKind;
+use madome_domain::id::{AuthcodeId, BookId, UserId};
+use madome_domain::pagination::{PageRequest, RandomSeed, Sort};
+use madome_domain::user::UserRole;
+use madome_proto::notification::{self, CreateNotificationRequest};
+use madome_proto::user::{BookTagTaste, BookTaste, Taste, User, taste::Kind};
+use proptest::prelude::*;
+use serde::Serialize;
+use uuid::Uuid;
+
+pub fn uuid() -> impl Strategy<Value = Uuid> {
+    any::<u128>().prop_map(Uuid::from_u128)
+}
+
+pub fn user_id() -> impl Strategy<Value = UserId> {
+    uuid().prop_map(UserId)
+}
+
+/// Book ids as the library assigns them: never 0.
+pub fn book_id() -> impl Strategy<Value = BookId> {
+    (1..=u32::MAX).prop_map(BookId)
+}
+
+pub fn authcode_id() -> impl Strategy<Value = AuthcodeId> {
+    uuid().prop_map(AuthcodeId)
+}
+
+pub fn user_role() -> impl Strategy<Value = UserRole> {
+    prop_oneof![
+        Just(UserRole::Normal),
+        Just(UserRole::Developer),
+        Just(UserRole::Bot),
+    ]
+}
+
+/// Any `u8` role wire value, known or not.
+pub fn role_wire() -> impl Strategy<Value = u8> {
+    any::<u8>()
+}
+
+pub fn sort() -> impl Strategy<Value = Sort> {
+    prop_oneof![Just(Sort::Desc), Just(Sort::Asc)]
+}
+
+pub fn book_kind() -> impl Strategy<Value = BookKind> {
+    prop_oneof![
+        Just(BookKind::Doujinshi),
+        Just(BookKind::Manga),
+        Just(BookKind::GameCg),
+        Just(BookKind::ArtistCg),
+        Just(BookKind::ImageSet),
+    ]
+}
+
+pub fn book_tag_kind() -> impl Strategy<Value = BookTagKind> {
+    prop_oneof![
+        Just(BookTagKind::Artist),
+        Just(BookTagKind::Group),
+        Just(BookTagKind::Series),
+        Just(BookTagKind::Character),
+        Just(BookTagKind::Female),
+        Just(BookTagKind::Male),
+        Just(BookTagKind::Misc),
+    ]
+}
+
+/// Tag names as the library stores them: lowercase words joined by spaces.
+pub fn book_tag_name() -> impl Strategy<Value = String> {
+    "[a-z0-9]{1,12}( [a-z0-9]{1,12}){0,2}"
+}
+
+pub fn taste_kind() -> impl Strategy<Value = TasteKind> {
+    prop_oneof![Just(TasteKind::Book), Just(TasteKind::BookTag)]
+}
+
+pub fn book_sort_by() -> impl Strategy<Value = BookSortBy> {
+    prop_oneof![
+        sort().prop_map(BookSortBy::Id),
+        sort().prop_map(BookSortBy::PublishedAt),
+        sort().prop_map(BookSortBy::CheckedAt),
+        sort().prop_map(BookSortBy::UpdatedAt),
+        Just(BookSortBy::Random),
+    ]
+}
+
+pub fn search_book_sort_by() -> impl Strategy<Value = SearchBookSortBy> {
+    prop_oneof![
+        Just(SearchBookSortBy::RankDesc),
+        sort().prop_map(SearchBookSortBy::Id),
+    ]
+}
+
+pub fn taste_sort_by() -> impl Strategy<Value = TasteSortBy> {
+    prop_oneof![
+        sort().prop_map(TasteSortBy::CreatedAt),
+        Just(TasteSortBy::Random),
+        Just(TasteSortBy::Affinity),
+    ]
+}
+
+pub fn history_sort_by() -> impl Strategy<Value = HistorySortBy> {
+    prop_oneof![
+        sort().prop_map(HistorySortBy::CreatedAt),
+        sort().prop_map(HistorySortBy::UpdatedAt),
+        Just(HistorySortBy::Random),
+    ]
+}
+
+/// Any `per-page`/`page` pair, including the out-of-range ones
+/// [`PageRequest::clamped`] fixes up.
+pub fn page_request() -> impl Strategy<Value = PageRequest> {
+    (any::<u32>(), any::<u32>()).prop_map(|(per_page, page)| PageRequest { per_page, page })
+}
+
+pub fn random_seed() -> impl Strategy<Value = RandomSeed> {
+    any::<u32>().prop_map(RandomSeed)
+}
+
+/// Addresses that pass `ValidationErrors::check_email`, all under
+/// reserved example domains.
+pub fn email() -> impl Strategy<Value = String> {
+    "[a-z0-9][a-z0-9._+-]{0,20}@example\\.(com|org|net)"
+}
+
+/// Lowercase handles, 3–16 characters, starting with a letter.
+pub fn handle() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9_]{2,15}"
+}
+
+/// Auth codes in the alphabet and length the auth service issues.
+pub fn authcode() -> impl Strategy<Value = String> {
+    "[A-Z0-9]{12}"
+}
+
+/// RFC 3339 UTC timestamps between 2020 and 2030, second precision.
+pub fn timestamp() -> impl Strategy<Value = String> {
+    (1_577_836_800i64..1_893_456_000).prop_map(|secs| {
+        DateTime::from_timestamp(secs, 0)
+            .expect("in range")
+            .to_rfc3339_opts(SecondsFormat::Secs, true)
+    })
+}
+
+/// A `UserService.GetUser` reply; `updated_at` is never before `created_at`.
+pub fn user() -> impl Strategy<Value = User> {
+    (
+        uuid(),
+        "[A-Za-z][A-Za-z0-9 ]{0,19}",
+        email(),
+        handle(),
+        user_role(),
+        timestamp(),
+        timestamp(),
+    )
+        .prop_map(|(id, name, email, handle, role, a, b)| {
+            // Same format and width, so string order is time order.
+            let (created_at, updated_at) = if a <= b { (a, b) } else { (b, a) };
+            User {
+                id: id.to_string(),
+                name,
+                email,
+                handle,
+                role: role.as_u8().into(),
+                created_at,
+                updated_at,
+            }
+        })
+}
+
+pub fn book_taste() -> impl Strategy<Value = BookTaste> {
+    (book_id(), any::<bool>()).prop_map(|(book_id, is_dislike)| BookTaste {
+        book_id: book_id.0,
+        is_dislike,
+    })
+}
+
+pub fn book_tag_taste() -> impl Strategy<Value = BookTagTaste> {
+    (book_tag_kind(), book_tag_name(), any::<bool>()).prop_map(|(kind, tag_name, is_dislike)| {
+        BookTagTaste {
+            tag_kind: kind.to_string(),
+            tag_name,
+            is_dislike,
+        }
+    })
+}
+
+/// A book or book-tag taste, as `GetTastes`/`StreamTastes` return them.
+pub fn taste() -> impl Strategy<Value = Taste> {
+    prop_oneof![
+        book_taste().prop_map(Kind::Book),
+        book_tag_taste().prop_map(Kind::BookTag),
+    ]
+    .prop_map(|kind| Taste { kind: Some(kind) })
+}
+
+/// A `NotificationService.CreateNotification` request for a new book with up
+/// to five of its tags.
+pub fn notification_book() -> impl Strategy<Value = CreateNotificationRequest> {
+    let tag = (book_tag_kind(), book_tag_name()).prop_map(|(kind, name)| notification::BookTag {
+        kind: kind.to_string(),
+        name,
+    });
+    (uuid(), book_id(), prop::collection::vec(tag, 0..=5)).prop_map(|(user_id, book_id, tags)| {
+        CreateNotificationRequest {
+            user_id: user_id.to_string(),
+            book_id: book_id.0,
+            book_tags: tags,
+        }
+    })
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock before UNIX epoch")
+        .as_secs()
+}
+
+/// Access-token claims that expire 2 minutes to a day from now, a quarter of
+/// them impersonated.
+pub fn token_info() -> impl Strategy<Value = TokenInfo> {
+    (
+        uuid(),
+        user_role(),
+        120u64..86_400,
+        prop::option::weighted(0.25, uuid()),
+    )
+        .prop_map(|(user_id, role, ttl, impersonator)| TokenInfo {
+            user_id,
+            user_role: role.as_u8(),
+            access_token_exp: now_secs() + ttl,
+            impersonator,
+            scopes: None,
+        })
+}
+
+/// Claims that expired 2 minutes to a day ago, past the validator's leeway.
+pub fn expired_token_info() -> impl Strategy<Value = TokenInfo> {
+    (token_info(), 120u64..86_400).prop_map(|(mut info, ago)| {
+        info.access_token_exp = now_secs() - ago;
+        info
+    })
+}
+
+#[derive(Serialize)]
+struct AccessClaims {
+    sub: String,
+    role: u8,
+    exp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    impersonator: Option<String>,
+}
+
+/// Sign `info` as an HS256 access token the way the auth service does.
+pub fn access_token(info: &TokenInfo, secret: &str) -> String {
+    let claims = AccessClaims {
+        sub: info.user_id.to_string(),
+        role: info.user_role,
+        exp: info.access_token_exp,
+        impersonator: info.impersonator.map(|id| id.to_string()),
+    };
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )
+    .expect("HS256 encoding does not fail")
+}
+
+#[cfg(test)]
+mod tests {
+    use madome_auth_types::token::{AuthError, validate_access_token};
+    use madome_core::validation::ValidationErrors;
+    use serde::de::DeserializeOwned;
+
+    use super::*;
+
+    const SECRET: &str = "strategy-test-secret";
+
+    fn round_trip<T: Serialize + DeserializeOwned>(value: &T) -> T {
+        serde_json::from_value(serde_json::to_value(value).unwrap()).unwrap()
+    }
+
+    proptest! {
+        #[test]
+        fn should_round_trip_sort_by_wire_strings(
+            book in book_sort_by(),
+            search in search_book_sort_by(),
+            taste in taste_sort_by(),
+            history in history_sort_by(),
+        ) {
+            prop_assert_eq!(round_trip(&book), book);
+            prop_assert_eq!(round_trip(&search), search);
+            prop_assert_eq!(round_trip(&taste), taste);
+            prop_assert_eq!(round_trip(&history), history);
+        }
+
+        #[test]
+        fn should_clamp_any_page_request_into_bounds(page in page_request()) {
+            let clamped = page.clamped();
+            prop_assert!((1..=100).contains(&clamped.per_page));
+            prop_assert!(clamped.page >= 1);
+            prop_assert_eq!(clamped.clamped(), clamped);
+        }
+
+        #[test]
+        fn should_round_trip_role_wire_value(wire in role_wire()) {
+            match UserRole::from_u8(wire) {
+                Some(role) => prop_assert_eq!(role.as_u8(), wire),
+                None => prop_assert!(wire > 2),
+            }
+        }
+
+        #[test]
+        fn should_generate_emails_that_pass_validation(email in email()) {
+            let mut errors = ValidationErrors::new();
+            errors.check_email("email", &email);
+            prop_assert!(errors.into_result().is_ok());
+        }
+
+        #[test]
+        fn should_generate_parseable_tag_kinds(taste in book_tag_taste()) {
+            prop_assert!(taste.tag_kind.parse::<BookTagKind>().is_ok());
+        }
+
+        #[test]
+        fn should_validate_signed_access_token(info in token_info()) {
+            let validated = validate_access_token(&access_token(&info, SECRET), SECRET).unwrap();
+            prop_assert_eq!(validated.user_id, info.user_id);
+            prop_assert_eq!(validated.user_role, info.user_role);
+            prop_assert_eq!(validated.access_token_exp, info.access_token_exp);
+            prop_assert_eq!(validated.impersonator, info.impersonator);
+        }
+
+        #[test]
+        fn should_reject_expired_access_token(info in expired_token_info()) {
+            let result = validate_access_token(&access_token(&info, SECRET), SECRET);
+            prop_assert!(matches!(result, Err(AuthError::Expired)));
+        }
+    }
+}
@@ -0,0 +1,375 @@
+// Generated by `cargo run -p client-gen`; do not edit by hand.
+// The contract harness fails when this file is out of date.
+
+export interface ApiTokenResponse {
+  created_at: string;
+  id: string;
+  /** Last time the token was presented, to within an hour. */
+  last_used_at?: string | null;
+  name: string;
+  scopes: string[];
+}
+
+export interface AuthConfigResponse {
+  /** Access-token lifetime in seconds. */
+  access_token_ttl_secs: number;
+  /** Refresh-token lifetime in seconds; after it the user signs in again. */
+  refresh_token_ttl_secs: number;
+}
+
+export interface CheckTokenResponse {
+  access_token_exp: number;
+  user_id: string;
+  user_role: number;
+}
+
+export interface CreateApiTokenRequest {
+  /** What the token is for, e.g. "backup script". */
+  name: string;
+  /**
+   * Any of `library.read`, `tastes.read`, `tastes.write`,
+   * `histories.read` and `histories.write`.
+   */
+  scopes: string[];
+}
+
+export interface CreateAuthcodeRequest {
+  email: string;
+}
+
+export interface CreateLoginLinkRequest {
+  email: string;
+}
+
+export interface CreateMachineTokenRequest {
+  /** Tool the token is for, e.g. "image-gen": lowercase letters, digits and `-`. */
+  client: string;
+  /** e.g. "library.renew": lowercase letters, digits, `.`, `_` and `-`. */
+  scopes: string[];
+  /** Lifetime in seconds; 1 day by default, at most 30 days. */
+  ttl_secs?: number | null;
+}
+
+export interface CreateTokenRequest {
+  code: string;
+  email: string;
+  /**
+   * Stay signed in across browser restarts (default). `false` gives session
+   * cookies and a refresh token valid for 1 day at most.
+   */
+  remember?: boolean;
+}
+
+export type CreatedApiTokenResponse = ApiTokenResponse & {
+  /** Bearer token for scripts. Shown only here; it cannot be read back. */
+  token: string;
+};
+
+export interface CsrfResponse {
+  csrf_token: string;
+}
+
+export interface DailyStatsResponse {
+  /** Users who used a session that day (refreshed their tokens). */
+  active_users: number;
+  day: string;
+  /** Sessions started that day. */
+  sign_ins: number;
+}
+
+/** JSON body of every error response. */
+export interface ErrorBody {
+  /**
+   * Field errors for [`ErrorKind::Validation`], and for [`ErrorKind::BadRequest`]
+   * when the query string does not parse.
+   */
+  errors?: FieldError[];
+  kind: ErrorKind;
+  /** Human-readable; never parsed by clients. */
+  message: string;
+  /** Request id for log lookup, when known. */
+  trace_id?: string | null;
+}
+
+/** Machine-readable error code. The wire value (`kind`) is never renamed. */
+export type ErrorKind = "bad_request" | "unauthorized" | "forbidden" | "not_found" | "conflict" | "validation" | "too_many_requests" | "internal";
+
+/** One invalid field. `code` is a stable machine-readable reason. */
+export interface FieldError {
+  code: string;
+  field: string;
+}
+
+export interface ImpersonateResponse {
+  /** Access token carrying the `impersonator` claim; no refresh token is issued. */
+  access_token: string;
+  access_token_exp: number;
+  user_id: string;
+  user_role: number;
+}
+
+export interface MachineTokenResponse {
+  client: string;
+  /** Expiry (Unix seconds). */
+  exp: number;
+  scopes: string[];
+  /** Bearer token for the scoped gRPC methods. */
+  token: string;
+}
+
+export interface OutboxEventResponse {
+  attempts: number;
+  created_at: string;
+  failed_at?: string | null;
+  id: string;
+  idempotency_key: string;
+  kind: string;
+  /** Error of the most recent failed attempt. */
+  last_error?: string | null;
+  next_attempt_at: string;
+  /** Event payload; secrets such as sign-in codes read `[redacted]`. */
+  payload: unknown;
+  processed_at?: string | null;
+  status: OutboxEventStatus;
+}
+
+export type OutboxEventStatus = "pending" | "processed" | "failed";
+
+export interface PasskeyResponse {
+  /** The authenticator can sync this passkey to other devices. */
+  backup_eligible: boolean;
+  /**
+   * The passkey was backed up when last used. A passkey that is not is
+   * lost with its device.
+   */
+  backup_state: boolean;
+  created_at: string;
+  /** Unpadded base64url. */
+  credential_id: string;
+  /** Transports reported at registration, e.g. `internal`, `hybrid`, `usb`. */
+  transports: string[];
+}
+
+export interface SessionResponse {
+  created_at: string;
+  id: string;
+  ip?: string | null;
+  last_used_at: string;
+  user_agent?: string | null;
+}
+
+export interface StartOAuthResponse {
+  authorize_url: string;
+}
+
+export interface ApiResponse<T> {
+  status: number;
+  headers: Headers;
+  data: T;
+}
+
+/** Non-2xx response; `body` is the parsed error body, if any. */
+export class ApiError extends Error {
+  constructor(
+    readonly status: number,
+    readonly body: ErrorBody | undefined,
+  ) {
+    super(`HTTP ${status}`);
+  }
+}
+
+export interface ClientOptions {
+  /** Origin of the gateway, e.g. `https://example.com`. */
+  baseUrl: string;
+  fetch?: typeof fetch;
+  /** Sent with every request, e.g. `x-madome-csrf`. */
+  headers?: Record<string, string>;
+}
+
+type Params = Record<string, string | number | boolean | null | undefined>;
+
+export abstract class BaseClient {
+  private readonly baseUrl: string;
+  private readonly fetchImpl: typeof fetch;
+  private readonly headers: Record<string, string>;
+
+  constructor(options: ClientOptions) {
+    this.baseUrl = options.baseUrl.replace(/\/+$/, "");
+    this.fetchImpl = options.fetch ?? globalThis.fetch.bind(globalThis);
+    this.headers = options.headers ?? {};
+  }
+
+  protected async request<T>(
+    method: string,
+    path: string,
+    query?: Params,
+    headers?: Params,
+    body?: unknown,
+  ): Promise<ApiResponse<T>> {
+    const url = new URL(this.baseUrl + path);
+    for (const [key, value] of Object.entries(query ?? {})) {
+      if (value != null) url.searchParams.set(key, String(value));
+    }
+    const sent: Record<string, string> = { ...this.headers };
+    for (const [key, value] of Object.entries(headers ?? {})) {
+      if (value != null) sent[key] = String(value);
+    }
+    if (body !== undefined) sent["content-type"] = "application/json";
+
+    const response = await this.fetchImpl(url, {
+      method,
+      headers: sent,
+      body: body === undefined ? undefined : JSON.stringify(body),
+      credentials: "include",
+    });
+    const text = await response.text();
+    const data = text ? JSON.parse(text) : undefined;
+    if (!response.ok) throw new ApiError(response.status, data);
+    return { status: response.status, headers: response.headers, data };
+  }
+}
+
+export class AuthClient extends BaseClient {
+  /** Outbox events in one delivery state, newest first (staff only). */
+  listOutboxEvents(query?: { status?: "pending" | "processed" | "failed"; "per-page"?: number; page?: number }): Promise<ApiResponse<OutboxEventResponse[]>> {
+    return this.request<OutboxEventResponse[]>("GET", "/auth/admin/outbox", query);
+  }
+
+  /** Discard a failed event without delivering it (staff only). */
+  deleteOutboxEvent(id: string): Promise<ApiResponse<void>> {
+    return this.request<void>("DELETE", `/auth/admin/outbox/${encodeURIComponent(id)}`);
+  }
+
+  /** Requeue a failed event for immediate delivery (staff only). */
+  retryOutboxEvent(id: string): Promise<ApiResponse<void>> {
+    return this.request<void>("POST", `/auth/admin/outbox/${encodeURIComponent(id)}/retry`);
+  }
+
+  /** Daily sign-in activity, oldest day first (staff only). */
+  listDailyStats(query?: { from?: string; to?: string }): Promise<ApiResponse<DailyStatsResponse[]>> {
+    return this.request<DailyStatsResponse[]>("GET", "/auth/admin/stats", query);
+  }
+
+  /** API tokens of the caller, newest first. The tokens themselves are not shown. */
+  listApiTokens(): Promise<ApiResponse<ApiTokenResponse[]>> {
+    return this.request<ApiTokenResponse[]>("GET", "/auth/api-tokens");
+  }
+
+  /** Issue a long-lived API token limited to `scopes`. */
+  createApiToken(body: CreateApiTokenRequest): Promise<ApiResponse<CreatedApiTokenResponse>> {
+    return this.request<CreatedApiTokenResponse>("POST", "/auth/api-tokens", undefined, undefined, body);
+  }
+
+  /** Revoke an API token. */
+  deleteApiToken(id: string): Promise<ApiResponse<void>> {
+    return this.request<void>("DELETE", `/auth/api-tokens/${encodeURIComponent(id)}`);
+  }
+
+  /** Mail a one-time sign-in code. */
+  createAuthcode(body: CreateAuthcodeRequest, headers?: { "idempotency-key"?: string | null }): Promise<ApiResponse<void>> {
+    return this.request<void>("POST", "/auth/code", undefined, headers, body);
+  }
+
+  /** Effective token lifetimes, so the frontend can schedule refreshes. */
+  getConfig(): Promise<ApiResponse<AuthConfigResponse>> {
+    return this.request<AuthConfigResponse>("GET", "/auth/config");
+  }
+
+  /**
+   * Issue a fresh double-submit token, both as the `madome_csrf` cookie and in
+   * the body for clients that cannot read cookies.
+   */
+  getCsrfToken(): Promise<ApiResponse<CsrfResponse>> {
+    return this.request<CsrfResponse>("GET", "/auth/csrf");
+  }
+
+  /** Issue a short-lived access token acting as another user (staff only). */
+  impersonate(userId: string): Promise<ApiResponse<ImpersonateResponse>> {
+    return this.request<ImpersonateResponse>("POST", `/auth/impersonate/${encodeURIComponent(userId)}`);
+  }
+
+  /** Mail a single-use magic sign-in link. */
+  createLoginLink(body: CreateLoginLinkRequest, headers?: { "idempotency-key"?: string | null }): Promise<ApiResponse<void>> {
+    return this.request<void>("POST", "/auth/link", undefined, headers, body);
+  }
+
+  /** Redeem a magic link, sign in and go to the app. */
+  loginLinkCallback(query: { token: string }): Promise<ApiResponse<void>> {
+    return this.request<void>("GET", "/auth/link/callback", query);
+  }
+
+  /** Issue a scoped token for an internal tool (staff only). */
+  createMachineToken(body: CreateMachineTokenRequest): Promise<ApiResponse<MachineTokenResponse>> {
+    return this.request<MachineTokenResponse>("POST", "/auth/machine-tokens", undefined, undefined, body);
+  }
+
+  /** Provider redirect target: finish sign-in and send the browser to the app. */
+  oauthCallback(provider: string, query: { code: string; state: string }): Promise<ApiResponse<void>> {
+    return this.request<void>("GET", `/auth/oauth/${encodeURIComponent(provider)}/callback`, query);
+  }
+
+  /** Begin provider sign-in; the client navigates to `authorize_url`. */
+  startOauth(provider: string): Promise<ApiResponse<StartOAuthResponse>> {
+    return this.request<StartOAuthResponse>("POST", `/auth/oauth/${encodeURIComponent(provider)}/start`);
+  }
+
+  /** Begin passkey sign-in; the body is WebAuthn `RequestChallengeResponse`. */
+  startAuthentication(query: { email: string }): Promise<ApiResponse<unknown>> {
+    return this.request<unknown>("POST", "/auth/passkey/authentication", query);
+  }
+
+  /** Finish passkey sign-in with the authenticator's `PublicKeyCredential`. */
+  finishAuthentication(query: { "authentication-id": string; email: string; remember?: boolean }, body: unknown): Promise<ApiResponse<void>> {
+    return this.request<void>("PATCH", "/auth/passkey/authentication", query, undefined, body);
+  }
+
+  /** Begin registering a passkey; the body is WebAuthn `CreationChallengeResponse`. */
+  startRegistration(): Promise<ApiResponse<unknown>> {
+    return this.request<unknown>("POST", "/auth/passkey/registration");
+  }
+
+  /** Finish registration with the authenticator's `RegisterPublicKeyCredential`. */
+  finishRegistration(query: { "registration-id": string }, body: unknown): Promise<ApiResponse<void>> {
+    return this.request<void>("PATCH", "/auth/passkey/registration", query, undefined, body);
+  }
+
+  /** Passkeys registered by the caller. */
+  listPasskeys(): Promise<ApiResponse<PasskeyResponse[]>> {
+    return this.request<PasskeyResponse[]>("GET", "/auth/passkeys");
+  }
+
+  /** Remove one of the caller's passkeys. */
+  deletePasskey(credentialId: string): Promise<ApiResponse<void>> {
+    return this.request<void>("DELETE", `/auth/passkeys/${encodeURIComponent(credentialId)}`);
+  }
+
+  /** Signed-in devices of the caller, most recently used first. */
+  listSessions(): Promise<ApiResponse<SessionResponse[]>> {
+    return this.request<SessionResponse[]>("GET", "/auth/sessions");
+  }
+
+  /** Sign a device out; its refresh token stops working. */
+  deleteSession(id: string): Promise<ApiResponse<void>> {
+    return this.request<void>("DELETE", `/auth/sessions/${encodeURIComponent(id)}`);
+  }
+
+  /** Validate the access token cookie. */
+  checkToken(query?: { role?: number }): Promise<ApiResponse<CheckTokenResponse>> {
+    return this.request<CheckTokenResponse>("GET", "/auth/token", query);
+  }
+
+  /** Exchange an emailed code for a token pair. */
+  createToken(body: CreateTokenRequest): Promise<ApiResponse<void>> {
+    return this.request<void>("POST", "/auth/token", undefined, undefined, body);
+  }
+
+  /** Rotate the token pair using the refresh token cookie. */
+  refreshToken(): Promise<ApiResponse<void>> {
+    return this.request<void>("PATCH", "/auth/token");
+  }
+
+  /** Sign out: revoke the current session and clear the token cookies. */
+  revokeToken(): Promise<ApiResponse<void>> {
+    return this.request<void>("DELETE", "/auth/token");
+  }
+}
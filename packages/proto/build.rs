@@ -1,6 +1,7 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let fds = protox::compile(
         [
+            "proto/auth.proto",
             "proto/user.proto",
             "proto/library.proto",
             "proto/notification.proto",
@@ -1,3 +1,7 @@
+pub mod auth {
+    tonic::include_proto!("auth");
+}
+
 pub mod user {
     tonic::include_proto!("user");
 }
@@ -4,6 +4,15 @@ mod m20260301_000001_create_users;
 mod m20260301_000002_create_auth_codes;
 mod m20260301_000003_create_passkeys;
 mod m20260301_000004_create_outbox_events;
+mod m20261016_000001_create_sessions;
+mod m20261016_000002_create_login_links;
+mod m20261016_000003_create_linked_identities;
+mod m20261016_000004_add_users_deleted_at;
+mod m20261016_000005_add_passkey_backup_flags;
+mod m20261016_000006_hash_auth_codes;
+mod m20261016_000007_create_id_aliases;
+mod m20261016_000008_create_api_tokens;
+mod m20261016_000009_create_auth_daily_stats;
 
 pub struct Migrator;
 
@@ -15,6 +24,15 @@ impl MigratorTrait for Migrator {
             Box::new(m20260301_000002_create_auth_codes::Migration),
             Box::new(m20260301_000003_create_passkeys::Migration),
             Box::new(m20260301_000004_create_outbox_events::Migration),
+            Box::new(m20261016_000001_create_sessions::Migration),
+            Box::new(m20261016_000002_create_login_links::Migration),
+            Box::new(m20261016_000003_create_linked_identities::Migration),
+            Box::new(m20261016_000004_add_users_deleted_at::Migration),
+            Box::new(m20261016_000005_add_passkey_backup_flags::Migration),
+            Box::new(m20261016_000006_hash_auth_codes::Migration),
+            Box::new(m20261016_000007_create_id_aliases::Migration),
+            Box::new(m20261016_000008_create_api_tokens::Migration),
+            Box::new(m20261016_000009_create_auth_daily_stats::Migration),
         ]
     }
 }
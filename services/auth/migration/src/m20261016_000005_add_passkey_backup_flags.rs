@@ -0,0 +1,71 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Passkeys registered before this migration report neither flag and no
+        // transports; `backup_state` catches up on their next sign-in.
+        let no_transports = match manager.get_database_backend() {
+            DbBackend::Postgres => Expr::cust("'[]'::jsonb"),
+            _ => Expr::cust("'[]'"),
+        };
+        // One column per statement: SQLite cannot add several at once.
+        for column in [
+            ColumnDef::new(Passkeys::BackupEligible)
+                .boolean()
+                .not_null()
+                .default(false)
+                .to_owned(),
+            ColumnDef::new(Passkeys::BackupState)
+                .boolean()
+                .not_null()
+                .default(false)
+                .to_owned(),
+            ColumnDef::new(Passkeys::Transports)
+                .json_binary()
+                .not_null()
+                .default(no_transports)
+                .to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Passkeys::Table)
+                        .add_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            Passkeys::BackupEligible,
+            Passkeys::BackupState,
+            Passkeys::Transports,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Passkeys::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+enum Passkeys {
+    Table,
+    BackupEligible,
+    BackupState,
+    Transports,
+}
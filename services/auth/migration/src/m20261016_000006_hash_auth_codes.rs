@@ -0,0 +1,46 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Codes live two minutes, so outstanding ones are simply blanked
+        // rather than hashed: a blank hash never matches.
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(AuthCodes::Table)
+                    .value(AuthCodes::Code, "")
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AuthCodes::Table)
+                    .rename_column(AuthCodes::Code, AuthCodes::CodeHash)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AuthCodes::Table)
+                    .rename_column(AuthCodes::CodeHash, AuthCodes::Code)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum AuthCodes {
+    Table,
+    Code,
+    CodeHash,
+}
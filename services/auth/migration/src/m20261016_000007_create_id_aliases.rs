@@ -0,0 +1,83 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(IdAliases::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(IdAliases::LegacyId)
+                            .big_integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(IdAliases::UserId)
+                            .uuid()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(IdAliases::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(IdAliases::Table, IdAliases::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Users imported before this table existed only have the import
+        // tool's own mapping; copy it over. The tool only writes to Postgres.
+        if manager.get_database_backend() != DbBackend::Postgres {
+            return Ok(());
+        }
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "DO $$ BEGIN
+                    IF to_regclass('legacy_import_ids') IS NOT NULL THEN
+                        INSERT INTO id_aliases (legacy_id, user_id, created_at)
+                        SELECT i.legacy_id, i.new_id, now()
+                        FROM legacy_import_ids i JOIN users u ON u.id = i.new_id
+                        WHERE i.kind = 'user'
+                        ON CONFLICT DO NOTHING;
+                    END IF;
+                END $$",
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(IdAliases::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum IdAliases {
+    Table,
+    LegacyId,
+    UserId,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum Users {
+    Table,
+    Id,
+}
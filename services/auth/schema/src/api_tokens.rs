@@ -0,0 +1,39 @@
+use madome_domain::id::UserId;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Long-lived API token a user issued for a script or third-party client.
+/// Only a hash of the secret is kept; the token itself is shown once.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "api_tokens")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub user_id: UserId,
+    /// Label the user gave the token.
+    pub name: String,
+    /// Base64url SHA-256 of the token's secret part.
+    pub secret_hash: String,
+    /// JSON array of scope names the token grants.
+    pub scopes: Json,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
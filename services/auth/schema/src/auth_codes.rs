@@ -1,3 +1,4 @@
+use madome_domain::id::UserId;
 use sea_orm::entity::prelude::*;
 
 /// One-time authentication code sent to a user via email.
@@ -7,8 +8,9 @@ use sea_orm::entity::prelude::*;
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: Uuid,
-    pub user_id: Uuid,
-    pub code: String,
+    pub user_id: UserId,
+    /// HMAC-SHA256 of the code, unpadded base64url.
+    pub code_hash: String,
     pub expires_at: chrono::DateTime<chrono::Utc>,
     pub used_at: Option<chrono::DateTime<chrono::Utc>>,
     pub created_at: chrono::DateTime<chrono::Utc>,
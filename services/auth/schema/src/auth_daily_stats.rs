@@ -0,0 +1,21 @@
+use sea_orm::entity::prelude::*;
+
+/// Per-day usage figures, rolled up from `sessions` by a periodic job so the
+/// admin dashboard never scans the sessions table.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "auth_daily_stats")]
+pub struct Model {
+    /// UTC day.
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub day: Date,
+    /// Users with a session used that day.
+    pub active_users: i64,
+    /// Sessions started that day.
+    pub sign_ins: i64,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,33 @@
+use madome_domain::id::UserId;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Numeric id a user had in the legacy system, still sent by old clients.
+/// One alias per user; never changed once written.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "id_aliases")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub legacy_id: i64,
+    #[sea_orm(unique)]
+    pub user_id: UserId,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
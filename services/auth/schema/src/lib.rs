@@ -1,4 +1,10 @@
+pub mod api_tokens;
 pub mod auth_codes;
+pub mod auth_daily_stats;
+pub mod id_aliases;
+pub mod linked_identities;
+pub mod login_links;
 pub mod outbox_events;
 pub mod passkeys;
+pub mod sessions;
 pub mod users;
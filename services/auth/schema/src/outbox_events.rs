@@ -7,6 +7,7 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: Uuid,
     pub kind: String,
+    #[sea_orm(column_type = "JsonBinary")]
     pub payload: Json,
     #[sea_orm(unique)]
     pub idempotency_key: String,
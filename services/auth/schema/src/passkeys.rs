@@ -1,17 +1,25 @@
+use madome_domain::id::UserId;
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
 /// WebAuthn passkey credential stored for a user.
-#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "passkeys")]
 pub struct Model {
     /// Raw credential ID bytes (primary key, as provided by the authenticator).
     #[sea_orm(primary_key, auto_increment = false)]
     pub credential_id: Vec<u8>,
-    pub user_id: Uuid,
+    pub user_id: UserId,
     /// AAGUID from the authenticator's attestation data.
     pub aaguid: Uuid,
     /// JSON-serialized `webauthn_rs::Passkey` (counter updates are persisted here).
     pub credential: Vec<u8>,
+    /// WebAuthn backup-eligible (BE) flag from registration.
+    pub backup_eligible: bool,
+    /// WebAuthn backup-state (BS) flag, refreshed on every sign-in.
+    pub backup_state: bool,
+    /// JSON array of transport names reported at registration.
+    pub transports: Json,
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
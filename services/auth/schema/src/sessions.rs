@@ -0,0 +1,38 @@
+use madome_domain::id::UserId;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Refresh-token session (one row per signed-in device).
+/// The refresh token carries the session id in its `sid` claim; deleting the row
+/// makes the next refresh for that device fail with 401.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "sessions")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub user_id: UserId,
+    /// `User-Agent` header captured when the session was issued.
+    pub user_agent: Option<String>,
+    /// Client IP captured when the session was issued.
+    pub ip: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,8 +1,9 @@
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
 /// Minimal user record owned by the auth service.
 /// Stores only the fields needed for authentication (email lookup, role assertion).
-#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "users")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
@@ -10,14 +11,23 @@ pub struct Model {
     #[sea_orm(unique)]
     pub email: String,
     pub role: i16,
+    /// Set while the account is soft-deleted (restorable for 30 days). Such a
+    /// user cannot sign in and their tokens stop refreshing.
+    pub deleted_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::auth_codes::Entity")]
     AuthCodes,
+    #[sea_orm(has_many = "super::linked_identities::Entity")]
+    LinkedIdentities,
+    #[sea_orm(has_many = "super::login_links::Entity")]
+    LoginLinks,
     #[sea_orm(has_many = "super::passkeys::Entity")]
     Passkeys,
+    #[sea_orm(has_many = "super::sessions::Entity")]
+    Sessions,
 }
 
 impl Related<super::auth_codes::Entity> for Entity {
@@ -26,10 +36,28 @@ impl Related<super::auth_codes::Entity> for Entity {
     }
 }
 
+impl Related<super::linked_identities::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::LinkedIdentities.def()
+    }
+}
+
+impl Related<super::login_links::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::LoginLinks.def()
+    }
+}
+
 impl Related<super::passkeys::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Passkeys.def()
     }
 }
 
+impl Related<super::sessions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Sessions.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
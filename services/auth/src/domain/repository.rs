@@ -1,42 +1,67 @@
 #![allow(async_fn_in_trait)]
 
+use chrono::NaiveDate;
+use madome_domain::id::UserId;
+use madome_domain::pagination::{PageRequest, Paged};
 use uuid::Uuid;
 
-use crate::domain::types::{AuthCode, AuthUser, OutboxEvent, PasskeyRecord};
+use crate::domain::types::{
+    ApiToken, AuthCode, AuthUser, DailyStats, LinkedIdentity, LoginLink, OAuthProfile, OutboxEntry,
+    OutboxEvent, OutboxStatus, PasskeyRecord, Session,
+};
 use crate::error::AuthServiceError;
 
 /// Repository for auth-service users (email + role only).
+///
+/// Soft-deleted users are not returned, so every sign-in and refresh path
+/// treats them as unknown.
 pub trait UserRepository: Send + Sync {
     async fn find_by_email(&self, email: &str) -> Result<Option<AuthUser>, AuthServiceError>;
-    async fn find_by_id(&self, id: Uuid) -> Result<Option<AuthUser>, AuthServiceError>;
+    async fn find_by_id(&self, id: UserId) -> Result<Option<AuthUser>, AuthServiceError>;
 }
 
 /// Repository for one-time auth codes.
 pub trait AuthCodeRepository: Send + Sync {
     /// Count active (unused and unexpired) codes for a user.
-    async fn count_active(&self, user_id: Uuid) -> Result<u64, AuthServiceError>;
+    async fn count_active(&self, user_id: UserId) -> Result<u64, AuthServiceError>;
 
     /// Insert a new auth code and an outbox event atomically (same transaction).
+    ///
+    /// The event, which carries the plain code for the mail, is written first.
     async fn create_with_outbox(
         &self,
         code: &AuthCode,
         event: &OutboxEvent,
     ) -> Result<(), AuthServiceError>;
 
-    /// Find a valid (unused, unexpired) code by user + code string.
+    /// Find a valid (unused, unexpired) code of a user by its hash.
+    ///
+    /// Hashes are compared in constant time.
     async fn find_valid(
         &self,
-        user_id: Uuid,
-        code: &str,
+        user_id: UserId,
+        code_hash: &str,
     ) -> Result<Option<AuthCode>, AuthServiceError>;
 
     /// Mark a code as used (sets used_at = now).
     async fn mark_used(&self, id: Uuid) -> Result<(), AuthServiceError>;
 }
 
+/// Counter of wrong auth codes submitted per user (Redis, expiring).
+pub trait AuthCodeAttemptStore: Send + Sync {
+    /// Failures counted in the current window.
+    async fn failures(&self, user_id: UserId) -> Result<u32, AuthServiceError>;
+
+    /// Count one more failure and return the new total.
+    async fn record_failure(&self, user_id: UserId) -> Result<u32, AuthServiceError>;
+
+    /// Forget all failures (called after a successful sign-in).
+    async fn clear(&self, user_id: UserId) -> Result<(), AuthServiceError>;
+}
+
 /// Repository for WebAuthn passkey credentials.
 pub trait PasskeyRepository: Send + Sync {
-    async fn list_by_user(&self, user_id: Uuid) -> Result<Vec<PasskeyRecord>, AuthServiceError>;
+    async fn list_by_user(&self, user_id: UserId) -> Result<Vec<PasskeyRecord>, AuthServiceError>;
 
     async fn find_by_id(
         &self,
@@ -45,29 +70,205 @@ pub trait PasskeyRepository: Send + Sync {
 
     async fn create(&self, record: &PasskeyRecord) -> Result<(), AuthServiceError>;
 
+    /// Insert a passkey and an outbox event atomically (same transaction).
+    ///
+    /// The event is skipped if its idempotency key already exists.
+    async fn create_with_outbox(
+        &self,
+        record: &PasskeyRecord,
+        event: &OutboxEvent,
+    ) -> Result<(), AuthServiceError>;
+
     /// Delete a passkey. Returns `true` if deleted, `false` if not found.
-    async fn delete(&self, credential_id: &[u8], user_id: Uuid) -> Result<bool, AuthServiceError>;
+    async fn delete(&self, credential_id: &[u8], user_id: UserId)
+    -> Result<bool, AuthServiceError>;
 
-    /// Replace an existing passkey credential (used to update counter after authentication).
+    /// Replace an existing passkey credential and its backup state (used to
+    /// update counter and flags after authentication).
     async fn update_credential(
         &self,
         credential_id: &[u8],
         credential: &[u8],
+        backup_state: bool,
+    ) -> Result<(), AuthServiceError>;
+}
+
+/// Repository for magic login links.
+pub trait LoginLinkRepository: Send + Sync {
+    /// Count active (unused and unexpired) links for a user.
+    async fn count_active(&self, user_id: UserId) -> Result<u64, AuthServiceError>;
+
+    /// Insert a new login link and an outbox event atomically (same transaction).
+    async fn create_with_outbox(
+        &self,
+        link: &LoginLink,
+        event: &OutboxEvent,
+    ) -> Result<(), AuthServiceError>;
+
+    /// Mark a link as used if it is still unused and unexpired.
+    ///
+    /// Returns the consumed link, or `None` if it does not exist or was already
+    /// used/expired. The check and update are a single statement so a link can
+    /// only be consumed once under concurrent requests.
+    async fn consume(&self, id: Uuid) -> Result<Option<LoginLink>, AuthServiceError>;
+}
+
+/// Repository for refresh-token sessions (one per signed-in device).
+pub trait SessionRepository: Send + Sync {
+    async fn create(&self, session: &Session) -> Result<(), AuthServiceError>;
+
+    /// Insert a session and an outbox event atomically (same transaction).
+    ///
+    /// The event is skipped if its idempotency key already exists.
+    async fn create_with_outbox(
+        &self,
+        session: &Session,
+        event: &OutboxEvent,
     ) -> Result<(), AuthServiceError>;
+
+    /// Find a session owned by `user_id`. Sessions of other users are never returned.
+    async fn find_by_id(
+        &self,
+        id: Uuid,
+        user_id: UserId,
+    ) -> Result<Option<Session>, AuthServiceError>;
+
+    /// List a user's sessions, most recently used first.
+    async fn list_by_user(&self, user_id: UserId) -> Result<Vec<Session>, AuthServiceError>;
+
+    /// Set `last_used_at = now` (called on every refresh).
+    async fn touch(&self, id: Uuid) -> Result<(), AuthServiceError>;
+
+    /// Delete a session. Returns `true` if deleted, `false` if not found.
+    async fn delete(&self, id: Uuid, user_id: UserId) -> Result<bool, AuthServiceError>;
+}
+
+/// Repository for per-user API tokens.
+pub trait ApiTokenRepository: Send + Sync {
+    async fn count_by_user(&self, user_id: UserId) -> Result<u64, AuthServiceError>;
+
+    async fn create(&self, token: &ApiToken) -> Result<(), AuthServiceError>;
+
+    /// Find a token by id, whoever owns it (used to validate presented tokens).
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<ApiToken>, AuthServiceError>;
+
+    /// List a user's tokens, newest first.
+    async fn list_by_user(&self, user_id: UserId) -> Result<Vec<ApiToken>, AuthServiceError>;
+
+    /// Set `last_used_at = now` unless it was set within
+    /// [`API_TOKEN_TOUCH_INTERVAL_SECS`](crate::domain::types::API_TOKEN_TOUCH_INTERVAL_SECS),
+    /// so busy scripts do not write on every request.
+    async fn touch(&self, id: Uuid) -> Result<(), AuthServiceError>;
+
+    /// Delete a token. Returns `true` if deleted, `false` if not found.
+    async fn delete(&self, id: Uuid, user_id: UserId) -> Result<bool, AuthServiceError>;
+}
+
+/// Repository for external OAuth/OIDC accounts linked to users.
+pub trait LinkedIdentityRepository: Send + Sync {
+    async fn find_by_subject(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<LinkedIdentity>, AuthServiceError>;
+
+    /// Accounts linked to a user, oldest first.
+    async fn list_by_user(&self, user_id: UserId) -> Result<Vec<LinkedIdentity>, AuthServiceError>;
+
+    async fn create(&self, identity: &LinkedIdentity) -> Result<(), AuthServiceError>;
+}
+
+/// Numeric user ids of the legacy system, mapped to current ones for old
+/// clients during the migration window.
+///
+/// Aliases are written by the migration that creates them and by
+/// `tools/import-legacy`, never by the service.
+pub trait IdAliasRepository: Send + Sync {
+    /// Current id of the user behind a legacy id. Soft-deleted users resolve
+    /// to `None`, like an unknown id.
+    async fn resolve(&self, legacy_id: i64) -> Result<Option<UserId>, AuthServiceError>;
+}
+
+/// Operator access to stored outbox events (inspection and dead-letter replay).
+pub trait OutboxRepository: Send + Sync {
+    /// Events in `status`, newest first.
+    async fn list(
+        &self,
+        status: OutboxStatus,
+        page: PageRequest,
+    ) -> Result<Paged<OutboxEntry>, AuthServiceError>;
+
+    /// Requeue a failed event: clears `failed_at`, resets `attempts` and makes
+    /// it due now. `last_error` is kept until the next attempt overwrites it.
+    ///
+    /// Returns `false` if there is no failed event with this id.
+    async fn retry_failed(&self, id: Uuid) -> Result<bool, AuthServiceError>;
+
+    /// Discard a failed event. Returns `false` if there is no failed event with this id.
+    async fn delete_failed(&self, id: Uuid) -> Result<bool, AuthServiceError>;
+}
+
+/// Pre-aggregated usage figures for the admin dashboard.
+pub trait StatsRepository: Send + Sync {
+    /// Recount `day` from the sessions and store the figures.
+    ///
+    /// Sessions only remember when they were last used, so once a user is
+    /// active again the next day their earlier day loses them. Stored figures
+    /// are therefore never lowered: the largest count seen for a day stands.
+    async fn rollup(&self, day: NaiveDate) -> Result<DailyStats, AuthServiceError>;
+
+    /// Stored days from `from` to `to` (inclusive), oldest first. Days never
+    /// rolled up are missing.
+    async fn list(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DailyStats>, AuthServiceError>;
+}
+
+/// Cache for in-flight OAuth authorizations, keyed by the `state` parameter (Redis, short TTL).
+pub trait OAuthStateCache: Send + Sync {
+    async fn set_state(&self, state: &str, pending_json: &[u8]) -> Result<(), AuthServiceError>;
+
+    /// Get and delete in one step, so a `state` value can be redeemed only once.
+    async fn take_state(&self, state: &str) -> Result<Option<Vec<u8>>, AuthServiceError>;
+}
+
+/// Client for the configured OAuth/OIDC providers.
+///
+/// Both methods return `NotFound` for a provider that is not configured.
+pub trait OAuthClient: Send + Sync {
+    /// Build the provider's authorization URL (authorization-code flow with PKCE S256).
+    fn authorize_url(
+        &self,
+        provider: &str,
+        state: &str,
+        code_challenge: &str,
+    ) -> Result<String, AuthServiceError>;
+
+    /// Exchange an authorization code and fetch the account's profile.
+    ///
+    /// Returns `Unauthorized` if the provider rejects the code.
+    async fn exchange_code(
+        &self,
+        provider: &str,
+        code: &str,
+        code_verifier: &str,
+    ) -> Result<OAuthProfile, AuthServiceError>;
 }
 
 /// Cache for WebAuthn ceremony states (Redis, short TTL).
 pub trait PasskeyCache: Send + Sync {
     async fn set_registration_state(
         &self,
-        user_id: Uuid,
+        user_id: UserId,
         reg_id: &str,
         state_json: &[u8],
     ) -> Result<(), AuthServiceError>;
 
     async fn take_registration_state(
         &self,
-        user_id: Uuid,
+        user_id: UserId,
         reg_id: &str,
     ) -> Result<Option<Vec<u8>>, AuthServiceError>;
 
@@ -1,11 +1,12 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+use madome_domain::id::UserId;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 /// Minimal user record owned by the auth service (email + role for auth decisions).
 #[derive(Debug, Clone)]
 pub struct AuthUser {
-    pub id: Uuid,
+    pub id: UserId,
     pub email: String,
     pub role: u8,
 }
@@ -14,8 +15,9 @@ pub struct AuthUser {
 #[derive(Debug, Clone)]
 pub struct AuthCode {
     pub id: Uuid,
-    pub user_id: Uuid,
-    pub code: String,
+    pub user_id: UserId,
+    /// HMAC of the code; the code itself is only in the outbox mail event.
+    pub code_hash: String,
     pub expires_at: DateTime<Utc>,
     pub used_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
@@ -27,17 +29,96 @@ impl AuthCode {
     }
 }
 
+/// Single-use magic login link (emailed as a signed URL token).
+#[derive(Debug, Clone)]
+pub struct LoginLink {
+    pub id: Uuid,
+    pub user_id: UserId,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl LoginLink {
+    pub fn is_valid(&self) -> bool {
+        self.used_at.is_none() && self.expires_at > Utc::now()
+    }
+}
+
+/// External OAuth/OIDC account linked to a user.
+#[derive(Debug, Clone)]
+pub struct LinkedIdentity {
+    pub id: Uuid,
+    pub user_id: UserId,
+    /// Provider name as configured (e.g. "google", "github").
+    pub provider: String,
+    /// Provider-issued stable account id (OIDC `sub`).
+    pub subject: String,
+    /// Verified email the account was linked by.
+    pub email: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Provider account returned after a successful authorization-code exchange.
+#[derive(Debug, Clone)]
+pub struct OAuthProfile {
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+}
+
 /// Stored WebAuthn passkey credential.
 #[derive(Debug, Clone)]
 pub struct PasskeyRecord {
     pub credential_id: Vec<u8>,
-    pub user_id: Uuid,
+    pub user_id: UserId,
     pub aaguid: Uuid,
     /// JSON-serialized `webauthn_rs::Passkey` (with counter).
     pub credential: Vec<u8>,
+    /// The authenticator can sync this credential (WebAuthn BE flag).
+    pub backup_eligible: bool,
+    /// The credential was backed up when last used (WebAuthn BS flag).
+    pub backup_state: bool,
+    /// Transports the client reported at registration (`usb`, `internal`, …).
+    pub transports: Vec<String>,
     pub created_at: DateTime<Utc>,
 }
 
+/// Refresh-token session for one signed-in device.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: UserId,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+}
+
+/// Long-lived API token of a user, for scripts and third-party clients.
+#[derive(Debug, Clone)]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub user_id: UserId,
+    pub name: String,
+    /// Base64url SHA-256 of the secret; the token itself is only in the create response.
+    pub secret_hash: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Usage figures of one UTC day, as rolled up from sessions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DailyStats {
+    pub day: NaiveDate,
+    /// Users with a session used that day. Access tokens are not seen, so a
+    /// user active on one access token all day counts only if it was refreshed.
+    pub active_users: u64,
+    /// Sessions started that day (sign-ins of any kind).
+    pub sign_ins: u64,
+}
+
 /// Outbox event for async delivery (e.g. authcode email).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OutboxEvent {
@@ -47,6 +128,41 @@ pub struct OutboxEvent {
     pub idempotency_key: String,
 }
 
+/// Delivery state of a stored outbox event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutboxStatus {
+    /// Waiting for its next delivery attempt.
+    Pending,
+    Processed,
+    /// Dead-lettered: the worker gave up retrying. Kept until an operator
+    /// replays or discards it.
+    Failed,
+}
+
+/// Outbox event as stored, with its delivery bookkeeping.
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    pub event: OutboxEvent,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub processed_at: Option<DateTime<Utc>>,
+    pub failed_at: Option<DateTime<Utc>>,
+}
+
+impl OutboxEntry {
+    pub fn status(&self) -> OutboxStatus {
+        if self.failed_at.is_some() {
+            OutboxStatus::Failed
+        } else if self.processed_at.is_some() {
+            OutboxStatus::Processed
+        } else {
+            OutboxStatus::Pending
+        }
+    }
+}
+
 /// Maximum number of active (unused, unexpired) auth codes per user.
 pub const MAX_ACTIVE_AUTHCODES: u64 = 5;
 
@@ -56,5 +172,43 @@ pub const AUTHCODE_LEN: usize = 12;
 /// Auth code time-to-live in seconds.
 pub const AUTHCODE_TTL_SECS: i64 = 120;
 
+/// Wrong auth codes a user may submit before code sign-in is locked.
+pub const MAX_AUTHCODE_ATTEMPTS: u32 = 5;
+
+/// Failed attempts are counted over this window, and a lock lasts this long
+/// from the failure that triggered it.
+pub const AUTHCODE_LOCKOUT_SECS: i64 = 900;
+
+/// Maximum number of active (unused, unexpired) login links per user.
+pub const MAX_ACTIVE_LOGIN_LINKS: u64 = 5;
+
+/// Login link time-to-live in seconds.
+pub const LOGIN_LINK_TTL_SECS: i64 = 600;
+
+/// Outbox event kind emitted when a user signs in from an unseen device.
+pub const LOGIN_NEW_DEVICE_EVENT: &str = "login.new_device";
+
+/// At most one new-device notification is emitted per user within this window.
+pub const NEW_DEVICE_NOTIFY_WINDOW_SECS: i64 = 3600;
+
+/// Outbox event kind emitted when a user's only passkey is not backed up,
+/// so losing the device would lock them out of passkey sign-in.
+pub const PASSKEY_NOT_BACKED_UP_EVENT: &str = "passkey.not_backed_up";
+
+/// Maximum number of API tokens per user.
+pub const MAX_API_TOKENS: u64 = 20;
+
+/// `last_used_at` of an API token is written at most once per this many seconds.
+pub const API_TOKEN_TOUCH_INTERVAL_SECS: i64 = 3600;
+
+/// How often the daily stats are rolled up, in seconds.
+pub const STATS_ROLLUP_INTERVAL_SECS: i64 = 900;
+
+/// Longest range of days the admin stats endpoint returns.
+pub const MAX_STATS_DAYS: i64 = 366;
+
+/// OAuth `state` TTL in seconds (time allowed at the provider's consent screen).
+pub const OAUTH_STATE_TTL_SECS: usize = 600;
+
 /// WebAuthn session state TTL in seconds (same as authcode TTL).
 pub const PASSKEY_STATE_TTL_SECS: usize = 120;
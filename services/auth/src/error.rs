@@ -1,30 +1,74 @@
-use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
+use madome_core::error_catalog::{ErrorBody, ErrorKind};
+use madome_core::rate_limit::RateLimit;
+use madome_core::validation::ValidationErrors;
 
-/// Auth service error variants mapped to HTTP status codes.
+/// Auth service error variants, answered as an [`ErrorBody`] of the matching kind.
 #[derive(Debug, thiserror::Error)]
 pub enum AuthServiceError {
     #[error("not found")]
     NotFound,
     #[error("unauthorized")]
     Unauthorized,
+    #[error("forbidden")]
+    Forbidden,
+    /// Over a per-user quota (active codes or links).
     #[error("too many requests")]
-    TooManyRequests,
+    TooManyRequests(RateLimit),
+    /// Too many wrong auth codes; code sign-in is locked for a while.
+    #[error("too many attempts")]
+    TooManyAttempts(RateLimit),
     #[error("bad request: {0}")]
     BadRequest(String),
+    /// Field-level input errors, answered as 422 with the `errors` array filled.
+    #[error("validation failed")]
+    Validation(ValidationErrors),
     #[error("internal error")]
     Internal(#[from] anyhow::Error),
 }
 
+impl AuthServiceError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::NotFound => ErrorKind::NotFound,
+            Self::Unauthorized => ErrorKind::Unauthorized,
+            Self::Forbidden => ErrorKind::Forbidden,
+            Self::TooManyRequests(_) | Self::TooManyAttempts(_) => ErrorKind::TooManyRequests,
+            Self::BadRequest(_) => ErrorKind::BadRequest,
+            Self::Validation(_) => ErrorKind::Validation,
+            Self::Internal(_) => ErrorKind::Internal,
+        }
+    }
+
+    /// Quota to report in the `RateLimit-*` headers, for throttling errors.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        match self {
+            Self::TooManyRequests(limit) | Self::TooManyAttempts(limit) => Some(*limit),
+            _ => None,
+        }
+    }
+}
+
+impl From<AuthServiceError> for ErrorBody {
+    fn from(err: AuthServiceError) -> Self {
+        match err {
+            AuthServiceError::Validation(errors) => errors.into(),
+            other => ErrorBody::new(other.kind(), other.to_string()),
+        }
+    }
+}
+
 impl IntoResponse for AuthServiceError {
     fn into_response(self) -> Response {
-        let status = match &self {
-            Self::NotFound => StatusCode::NOT_FOUND,
-            Self::Unauthorized => StatusCode::UNAUTHORIZED,
-            Self::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
-            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
-            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
-        };
-        (status, self.to_string()).into_response()
+        match self.rate_limit() {
+            Some(limit) => (limit, ErrorBody::from(self)).into_response(),
+            None => ErrorBody::from(self).into_response(),
+        }
+    }
+}
+
+impl From<ValidationErrors> for AuthServiceError {
+    fn from(errors: ValidationErrors) -> Self {
+        Self::Validation(errors)
     }
 }
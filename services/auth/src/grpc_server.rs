@@ -0,0 +1,158 @@
+//! `auth.AuthService` gRPC server: token (JWT and API token) introspection, session revocation and
+//! legacy id resolution for services that do not hold the JWT secret.
+//!
+//! Every caller registered in the policy may call every method.
+
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+use madome_auth_types::api_token::API_TOKEN_PREFIX;
+use madome_core::config::Secret;
+use madome_core::error_catalog::{ErrorBody, ErrorKind};
+use madome_core::grpc::GrpcAuthPolicy;
+use madome_domain::id::UserId;
+use madome_proto::auth::{
+    Empty, IntrospectTokenRequest, Introspection, ResolveLegacyIdReply, ResolveLegacyIdRequest,
+    RevokeSessionRequest,
+    auth_service_server::{AuthService, AuthServiceServer},
+};
+
+use crate::error::AuthServiceError;
+use crate::state::AppState;
+use crate::usecase::api_token::IntrospectApiTokenUseCase;
+use crate::usecase::id_alias::ResolveLegacyIdUseCase;
+use crate::usecase::session::DeleteSessionUseCase;
+use crate::usecase::token::IntrospectTokenUseCase;
+
+/// Method prefix of the service, for [`GrpcAuthPolicy::allow`].
+pub const AUTH_SERVICE_PREFIX: &str = "/auth.AuthService/";
+
+/// Tonic service to add to a `Server::builder()`.
+pub fn service(state: AppState) -> AuthServiceServer<AuthGrpc> {
+    AuthServiceServer::new(AuthGrpc { state })
+}
+
+/// Policy letting each of `callers` (from `GRPC_CALLER_TOKENS`) call the service.
+pub fn policy(callers: &[(String, Secret<String>)]) -> GrpcAuthPolicy {
+    let names: Vec<&str> = callers.iter().map(|(name, _)| name.as_str()).collect();
+    callers
+        .iter()
+        .fold(GrpcAuthPolicy::new(), |policy, (name, token)| {
+            policy.caller(name, token.clone())
+        })
+        .allow(AUTH_SERVICE_PREFIX, &names)
+}
+
+pub struct AuthGrpc {
+    state: AppState,
+}
+
+#[tonic::async_trait]
+impl AuthService for AuthGrpc {
+    async fn introspect_token(
+        &self,
+        request: Request<IntrospectTokenRequest>,
+    ) -> Result<Response<Introspection>, Status> {
+        let token = request.into_inner().token;
+        if token.starts_with(API_TOKEN_PREFIX) {
+            let uc = IntrospectApiTokenUseCase {
+                tokens: self.state.api_token_repo(),
+                users: self.state.user_repo(),
+            };
+            let reply = match uc.execute(&token).await.map_err(status)? {
+                Some(info) => Introspection {
+                    active: true,
+                    user_id: info.user_id.to_string(),
+                    user_role: info.user_role.into(),
+                    scopes: info.scopes,
+                    ..Default::default()
+                },
+                None => Introspection::default(),
+            };
+            return Ok(Response::new(reply));
+        }
+
+        let uc = IntrospectTokenUseCase {
+            jwt_secret: self.state.jwt_secret.current().into_inner(),
+            previous_jwt_secret: self.state.jwt_secret.previous().map(Secret::into_inner),
+        };
+        let reply = match uc.execute(&token) {
+            Some(info) => Introspection {
+                active: true,
+                user_id: info.user_id.to_string(),
+                user_role: info.user_role.into(),
+                exp: info.exp,
+                impersonator_id: info.impersonator.map(|id| id.to_string()),
+                scopes: Vec::new(),
+            },
+            None => Introspection::default(),
+        };
+        Ok(Response::new(reply))
+    }
+
+    async fn revoke_session(
+        &self,
+        request: Request<RevokeSessionRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+        let user_id: UserId = request
+            .user_id
+            .parse()
+            .map_err(|_| invalid_argument("user_id is not a UUID"))?;
+        let session_id: Uuid = request
+            .session_id
+            .parse()
+            .map_err(|_| invalid_argument("session_id is not a UUID"))?;
+
+        let uc = DeleteSessionUseCase {
+            sessions: self.state.session_repo(),
+        };
+        uc.execute(session_id, user_id).await.map_err(status)?;
+        tracing::info!(
+            target: "audit",
+            user_id = %user_id,
+            session_id = %session_id,
+            "session revoked over gRPC"
+        );
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn resolve_legacy_id(
+        &self,
+        request: Request<ResolveLegacyIdRequest>,
+    ) -> Result<Response<ResolveLegacyIdReply>, Status> {
+        let uc = ResolveLegacyIdUseCase {
+            aliases: self.state.id_alias_repo(),
+        };
+        let user_id =
+            uc.execute(request.into_inner().legacy_id)
+                .await
+                .map_err(|err| match err {
+                    AuthServiceError::NotFound => {
+                        ErrorBody::new(ErrorKind::NotFound, "legacy id not found").into()
+                    }
+                    other => status(other),
+                })?;
+        Ok(Response::new(ResolveLegacyIdReply {
+            user_id: user_id.to_string(),
+        }))
+    }
+}
+
+/// The shared mapping, so callers can match on [`error_kind`](madome_core::grpc::error_kind).
+fn status(err: AuthServiceError) -> Status {
+    match err {
+        AuthServiceError::NotFound => {
+            ErrorBody::new(ErrorKind::NotFound, "session not found").into()
+        }
+        AuthServiceError::Internal(e) => {
+            tracing::error!(error = %e, "auth gRPC call failed");
+            ErrorBody::new(ErrorKind::Internal, "internal error").into()
+        }
+        other => ErrorBody::from(other).into(),
+    }
+}
+
+fn invalid_argument(message: &str) -> Status {
+    ErrorBody::new(ErrorKind::BadRequest, message).into()
+}
@@ -0,0 +1,187 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use madome_auth_types::api_token::API_TOKEN_SCOPES;
+use madome_auth_types::identity::IdentityHeaders;
+use madome_core::error_catalog::ErrorBody;
+use madome_core::validation::{Validate, ValidatedJson, ValidationErrors};
+
+use crate::error::AuthServiceError;
+use crate::state::AppState;
+use crate::usecase::api_token::{
+    ApiTokenInfo, CreateApiTokenInput, CreateApiTokenUseCase, DeleteApiTokenUseCase,
+    ListApiTokensUseCase,
+};
+
+/// Upper bound on token names.
+const MAX_NAME_LEN: usize = 64;
+
+#[derive(Serialize, ToSchema)]
+pub struct ApiTokenResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    /// Last time the token was presented, to within an hour.
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl From<ApiTokenInfo> for ApiTokenResponse {
+    fn from(t: ApiTokenInfo) -> Self {
+        Self {
+            id: t.id,
+            name: t.name,
+            scopes: t.scopes,
+            created_at: t.created_at,
+            last_used_at: t.last_used_at,
+        }
+    }
+}
+
+// ── POST /auth/api-tokens ─────────────────────────────────────────────────────
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateApiTokenRequest {
+    /// What the token is for, e.g. "backup script".
+    pub name: String,
+    /// Any of `library.read`, `tastes.read`, `tastes.write`,
+    /// `histories.read` and `histories.write`.
+    pub scopes: Vec<String>,
+}
+
+impl Validate for CreateApiTokenRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        errors.check_length("name", &self.name, MAX_NAME_LEN);
+        if self.scopes.is_empty() {
+            errors.add("scopes", "required");
+        }
+        for (i, scope) in self.scopes.iter().enumerate() {
+            if !API_TOKEN_SCOPES.contains(&scope.as_str()) {
+                errors.add(format!("scopes[{i}]"), "invalid_scope");
+            } else if self.scopes[..i].contains(scope) {
+                errors.add(format!("scopes[{i}]"), "duplicate");
+            }
+        }
+        errors.into_result()
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CreatedApiTokenResponse {
+    #[serde(flatten)]
+    pub info: ApiTokenResponse,
+    /// Bearer token for scripts. Shown only here; it cannot be read back.
+    pub token: String,
+}
+
+/// Issue a long-lived API token limited to `scopes`.
+///
+/// Scripts send it as `Authorization: Bearer <token>` instead of the token
+/// cookies. It stays valid until deleted.
+#[utoipa::path(
+    post,
+    path = "/auth/api-tokens",
+    tag = "api-tokens",
+    security(("gatewayIdentity" = [])),
+    request_body = CreateApiTokenRequest,
+    responses(
+        (status = 201, body = CreatedApiTokenResponse),
+        (status = 400, description = "The caller has the maximum number of tokens", body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Called with an API token, or while impersonating", body = ErrorBody),
+        (status = 422, description = "Invalid name or scopes", body = ErrorBody),
+    ),
+)]
+pub async fn create_api_token(
+    State(state): State<AppState>,
+    identity: IdentityHeaders,
+    ValidatedJson(body): ValidatedJson<CreateApiTokenRequest>,
+) -> Result<(StatusCode, Json<CreatedApiTokenResponse>), AuthServiceError> {
+    let uc = CreateApiTokenUseCase {
+        tokens: state.api_token_repo(),
+    };
+    let out = uc
+        .execute(
+            identity.user_id.into(),
+            identity.impersonator,
+            CreateApiTokenInput {
+                name: body.name.trim().to_owned(),
+                scopes: body.scopes,
+            },
+        )
+        .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedApiTokenResponse {
+            info: out.info.into(),
+            token: out.token,
+        }),
+    ))
+}
+
+// ── GET /auth/api-tokens ──────────────────────────────────────────────────────
+
+/// API tokens of the caller, newest first. The tokens themselves are not shown.
+#[utoipa::path(
+    get,
+    path = "/auth/api-tokens",
+    tag = "api-tokens",
+    security(("gatewayIdentity" = [])),
+    responses(
+        (status = 200, body = Vec<ApiTokenResponse>),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Called with an API token", body = ErrorBody),
+    ),
+)]
+pub async fn list_api_tokens(
+    State(state): State<AppState>,
+    identity: IdentityHeaders,
+) -> Result<Json<Vec<ApiTokenResponse>>, AuthServiceError> {
+    let uc = ListApiTokensUseCase {
+        tokens: state.api_token_repo(),
+    };
+    let list = uc.execute(identity.user_id.into()).await?;
+    Ok(Json(list.into_iter().map(ApiTokenResponse::from).collect()))
+}
+
+// ── DELETE /auth/api-tokens/{id} ──────────────────────────────────────────────
+
+/// Revoke an API token.
+#[utoipa::path(
+    delete,
+    path = "/auth/api-tokens/{id}",
+    tag = "api-tokens",
+    security(("gatewayIdentity" = [])),
+    params(("id" = uuid::Uuid, Path, description = "API token id")),
+    responses(
+        (status = 204, description = "Token revoked"),
+        (status = 400, description = "Malformed id", body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Called with an API token", body = ErrorBody),
+        (status = 404, description = "No such token for the caller", body = ErrorBody),
+    ),
+)]
+pub async fn delete_api_token(
+    State(state): State<AppState>,
+    identity: IdentityHeaders,
+    Path(token_id): Path<String>,
+) -> Result<StatusCode, AuthServiceError> {
+    let token_id = token_id
+        .parse::<Uuid>()
+        .map_err(|_| AuthServiceError::BadRequest("invalid api token id".to_owned()))?;
+
+    let uc = DeleteApiTokenUseCase {
+        tokens: state.api_token_repo(),
+    };
+    uc.execute(token_id, identity.user_id.into()).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,24 +1,57 @@
-use axum::{Json, extract::State, http::StatusCode};
+use axum::{extract::State, http::StatusCode};
 use serde::Deserialize;
+use utoipa::ToSchema;
+
+use madome_core::error_catalog::ErrorBody;
+use madome_core::rate_limit::RateLimit;
+use madome_core::validation::{Validate, ValidatedJson, ValidationErrors};
 
 use crate::error::AuthServiceError;
 use crate::state::AppState;
 use crate::usecase::authcode::{CreateAuthcodeInput, CreateAuthcodeUseCase};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateAuthcodeRequest {
     pub email: String,
 }
 
+impl Validate for CreateAuthcodeRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        errors.check_email("email", &self.email);
+        errors.into_result()
+    }
+}
+
+/// Mail a one-time sign-in code.
+#[utoipa::path(
+    post,
+    path = "/auth/code",
+    tag = "login",
+    params(("idempotency-key" = Option<String>, Header, description = "Replays within 24 h return the first response")),
+    request_body = CreateAuthcodeRequest,
+    responses(
+        (status = 201, description = "Code mailed", headers(
+            ("ratelimit-limit" = u64, description = "Active codes allowed per user"),
+            ("ratelimit-remaining" = u64, description = "Codes left before 429"),
+            ("ratelimit-reset" = u64, description = "Seconds until a code slot frees up, at most"),
+        )),
+        (status = 404, description = "No user with this email", body = ErrorBody),
+        (status = 422, description = "Invalid email", body = ErrorBody),
+        (status = 429, description = "Too many active codes; `ratelimit-*` and `retry-after` headers set", body = ErrorBody),
+    ),
+)]
 pub async fn create_authcode(
     State(state): State<AppState>,
-    Json(body): Json<CreateAuthcodeRequest>,
-) -> Result<StatusCode, AuthServiceError> {
+    ValidatedJson(body): ValidatedJson<CreateAuthcodeRequest>,
+) -> Result<(RateLimit, StatusCode), AuthServiceError> {
     let uc = CreateAuthcodeUseCase {
         users: state.user_repo(),
         auth_codes: state.auth_code_repo(),
+        code_secret: state.jwt_secret.current().into_inner(),
     };
-    uc.execute(CreateAuthcodeInput { email: body.email })
+    let quota = uc
+        .execute(CreateAuthcodeInput { email: body.email })
         .await?;
-    Ok(StatusCode::CREATED)
+    Ok((quota, StatusCode::CREATED))
 }
@@ -0,0 +1,29 @@
+use axum::{Json, extract::State};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::state::AppState;
+
+// ── GET /auth/config ──────────────────────────────────────────────────────────
+
+#[derive(Serialize, ToSchema)]
+pub struct AuthConfigResponse {
+    /// Access-token lifetime in seconds.
+    pub access_token_ttl_secs: u64,
+    /// Refresh-token lifetime in seconds; after it the user signs in again.
+    pub refresh_token_ttl_secs: u64,
+}
+
+/// Effective token lifetimes, so the frontend can schedule refreshes.
+#[utoipa::path(
+    get,
+    path = "/auth/config",
+    tag = "token",
+    responses((status = 200, body = AuthConfigResponse)),
+)]
+pub async fn get_config(State(state): State<AppState>) -> Json<AuthConfigResponse> {
+    Json(AuthConfigResponse {
+        access_token_ttl_secs: state.token_lifetimes.access_secs,
+        refresh_token_ttl_secs: state.token_lifetimes.refresh_secs,
+    })
+}
@@ -0,0 +1,29 @@
+use axum::{Json, extract::State, response::IntoResponse};
+use axum_extra::extract::CookieJar;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use madome_core::csrf::{generate_csrf_token, set_csrf_cookie};
+
+use crate::state::AppState;
+
+// ── GET /auth/csrf ────────────────────────────────────────────────────────────
+
+#[derive(Serialize, ToSchema)]
+pub struct CsrfResponse {
+    pub csrf_token: String,
+}
+
+/// Issue a fresh double-submit token, both as the `madome_csrf` cookie and in
+/// the body for clients that cannot read cookies.
+#[utoipa::path(
+    get,
+    path = "/auth/csrf",
+    tag = "csrf",
+    responses((status = 200, description = "Token issued; `madome_csrf` cookie set", body = CsrfResponse)),
+)]
+pub async fn get_csrf_token(State(state): State<AppState>, jar: CookieJar) -> impl IntoResponse {
+    let token = generate_csrf_token();
+    let jar = set_csrf_cookie(jar, token.clone(), &state.cookies);
+    (jar, Json(CsrfResponse { csrf_token: token }))
+}
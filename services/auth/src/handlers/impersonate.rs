@@ -0,0 +1,77 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use madome_auth_types::identity::IdentityHeaders;
+use madome_core::error_catalog::ErrorBody;
+
+use crate::error::AuthServiceError;
+use crate::state::AppState;
+use crate::usecase::token::ImpersonateUseCase;
+
+// ── POST /auth/impersonate/{user_id} ──────────────────────────────────────────
+
+#[derive(Serialize, ToSchema)]
+pub struct ImpersonateResponse {
+    pub user_id: Uuid,
+    pub user_role: u8,
+    /// Access token carrying the `impersonator` claim; no refresh token is issued.
+    pub access_token: String,
+    pub access_token_exp: u64,
+}
+
+/// Issue a short-lived access token acting as another user (staff only).
+///
+/// The token is returned in the body rather than as a cookie so the caller's
+/// own session is left untouched.
+#[utoipa::path(
+    post,
+    path = "/auth/impersonate/{user_id}",
+    tag = "impersonation",
+    security(("gatewayIdentity" = [])),
+    params(("user_id" = uuid::Uuid, Path, description = "User to act as")),
+    responses(
+        (status = 201, body = ImpersonateResponse),
+        (status = 400, description = "Malformed user id", body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Caller is not staff, or is already impersonating", body = ErrorBody),
+        (status = 404, description = "No such user", body = ErrorBody),
+    ),
+)]
+pub async fn impersonate(
+    State(state): State<AppState>,
+    identity: IdentityHeaders,
+    Path(user_id): Path<String>,
+) -> Result<(StatusCode, Json<ImpersonateResponse>), AuthServiceError> {
+    let user_id = user_id
+        .parse::<Uuid>()
+        .map_err(|_| AuthServiceError::BadRequest("invalid user id".to_owned()))?;
+
+    let uc = ImpersonateUseCase {
+        users: state.user_repo(),
+        jwt_secret: state.jwt_secret.current().into_inner(),
+    };
+    let out = uc
+        .execute(
+            identity.user_id.into(),
+            identity.user_role,
+            identity.impersonator,
+            user_id.into(),
+        )
+        .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ImpersonateResponse {
+            user_id: out.user_id.into(),
+            user_role: out.user_role,
+            access_token: out.access_token,
+            access_token_exp: out.access_token_exp,
+        }),
+    ))
+}
@@ -0,0 +1,121 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect},
+};
+use axum_extra::extract::CookieJar;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use madome_auth_types::cookie::{set_access_token_cookie, set_refresh_token_cookie};
+use madome_core::config::Secret;
+use madome_core::error_catalog::ErrorBody;
+use madome_core::query::QsQuery;
+use madome_core::rate_limit::RateLimit;
+use madome_core::validation::{Validate, ValidatedJson, ValidationErrors};
+
+use crate::error::AuthServiceError;
+use crate::handlers::session::client_info;
+use crate::state::AppState;
+use crate::usecase::login_link::{
+    ConsumeLoginLinkUseCase, CreateLoginLinkInput, CreateLoginLinkUseCase,
+};
+
+// ── POST /auth/link ───────────────────────────────────────────────────────────
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateLoginLinkRequest {
+    pub email: String,
+}
+
+impl Validate for CreateLoginLinkRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        errors.check_email("email", &self.email);
+        errors.into_result()
+    }
+}
+
+/// Mail a single-use magic sign-in link.
+#[utoipa::path(
+    post,
+    path = "/auth/link",
+    tag = "login",
+    params(("idempotency-key" = Option<String>, Header, description = "Replays within 24 h return the first response")),
+    request_body = CreateLoginLinkRequest,
+    responses(
+        (status = 201, description = "Link mailed", headers(
+            ("ratelimit-limit" = u64, description = "Active links allowed per user"),
+            ("ratelimit-remaining" = u64, description = "Links left before 429"),
+            ("ratelimit-reset" = u64, description = "Seconds until a link slot frees up, at most"),
+        )),
+        (status = 404, description = "No user with this email", body = ErrorBody),
+        (status = 422, description = "Invalid email", body = ErrorBody),
+        (status = 429, description = "Too many active links; `ratelimit-*` and `retry-after` headers set", body = ErrorBody),
+    ),
+)]
+pub async fn create_login_link(
+    State(state): State<AppState>,
+    ValidatedJson(body): ValidatedJson<CreateLoginLinkRequest>,
+) -> Result<(RateLimit, StatusCode), AuthServiceError> {
+    let uc = CreateLoginLinkUseCase {
+        users: state.user_repo(),
+        login_links: state.login_link_repo(),
+    };
+    let quota = uc
+        .execute(CreateLoginLinkInput { email: body.email })
+        .await?;
+    Ok((quota, StatusCode::CREATED))
+}
+
+// ── GET /auth/link/callback ───────────────────────────────────────────────────
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LoginLinkCallbackQuery {
+    pub token: String,
+}
+
+/// Redeem a magic link, sign in and go to the app.
+#[utoipa::path(
+    get,
+    path = "/auth/link/callback",
+    tag = "login",
+    params(LoginLinkCallbackQuery),
+    responses(
+        (status = 303, description = "Signed in; token cookies set", headers(("location" = String, description = "`APP_URL`"))),
+        (status = 401, description = "Invalid, expired or used link", body = ErrorBody),
+    ),
+)]
+pub async fn login_link_callback(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    req_headers: HeaderMap,
+    QsQuery(q): QsQuery<LoginLinkCallbackQuery>,
+) -> Result<impl IntoResponse, AuthServiceError> {
+    let uc = ConsumeLoginLinkUseCase {
+        users: state.user_repo(),
+        login_links: state.login_link_repo(),
+        sessions: state.session_repo(),
+        jwt_secret: state.jwt_secret.current().into_inner(),
+        previous_jwt_secret: state.jwt_secret.previous().map(Secret::into_inner),
+        lifetimes: state.token_lifetimes,
+    };
+    let out = uc.execute(&q.token, &client_info(&req_headers)).await?;
+
+    let jar = set_access_token_cookie(
+        jar,
+        out.access_token,
+        &state.cookies,
+        Some(state.token_lifetimes.refresh_secs),
+    );
+    let jar = set_refresh_token_cookie(
+        jar,
+        out.refresh_token,
+        &state.cookies,
+        Some(state.token_lifetimes.refresh_secs),
+    );
+
+    // Opened from a mail, so land the browser in the app.
+    Ok((jar, Redirect::to(&state.app_url)))
+}
@@ -0,0 +1,119 @@
+use axum::{Json, extract::State, http::StatusCode};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use madome_auth_types::identity::IdentityHeaders;
+use madome_core::error_catalog::ErrorBody;
+use madome_core::validation::{Validate, ValidatedJson, ValidationErrors};
+
+use crate::error::AuthServiceError;
+use crate::state::AppState;
+use crate::usecase::machine::{
+    IssueMachineTokenInput, IssueMachineTokenUseCase, MACHINE_TOKEN_MAX_TTL_SECS,
+};
+
+// ── POST /auth/machine-tokens ─────────────────────────────────────────────────
+
+/// Upper bound on client names and on each scope.
+const MAX_NAME_LEN: usize = 64;
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateMachineTokenRequest {
+    /// Tool the token is for, e.g. "image-gen": lowercase letters, digits and `-`.
+    pub client: String,
+    /// e.g. "library.renew": lowercase letters, digits, `.`, `_` and `-`.
+    pub scopes: Vec<String>,
+    /// Lifetime in seconds; 1 day by default, at most 30 days.
+    pub ttl_secs: Option<u64>,
+}
+
+impl Validate for CreateMachineTokenRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        errors.check_length("client", &self.client, MAX_NAME_LEN);
+        if !self
+            .client
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            errors.add("client", "invalid_client");
+        }
+        if self.scopes.is_empty() {
+            errors.add("scopes", "required");
+        }
+        for (i, scope) in self.scopes.iter().enumerate() {
+            let valid = !scope.is_empty()
+                && scope.len() <= MAX_NAME_LEN
+                && scope.chars().all(|c| {
+                    c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '.' | '_' | '-')
+                });
+            if !valid {
+                errors.add(format!("scopes[{i}]"), "invalid_scope");
+            }
+        }
+        if self
+            .ttl_secs
+            .is_some_and(|ttl| ttl == 0 || ttl > MACHINE_TOKEN_MAX_TTL_SECS)
+        {
+            errors.add("ttl_secs", "out_of_range");
+        }
+        errors.into_result()
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct MachineTokenResponse {
+    pub client: String,
+    pub scopes: Vec<String>,
+    /// Bearer token for the scoped gRPC methods.
+    pub token: String,
+    /// Expiry (Unix seconds).
+    pub exp: u64,
+}
+
+/// Issue a scoped token for an internal tool (staff only).
+///
+/// Tools send it to internal gRPC services instead of a user's token. It
+/// cannot be revoked, so keep `ttl_secs` short.
+#[utoipa::path(
+    post,
+    path = "/auth/machine-tokens",
+    tag = "machine",
+    security(("gatewayIdentity" = [])),
+    request_body = CreateMachineTokenRequest,
+    responses(
+        (status = 201, body = MachineTokenResponse),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Caller is not staff, or is impersonating", body = ErrorBody),
+        (status = 422, description = "Invalid client, scopes or lifetime", body = ErrorBody),
+    ),
+)]
+pub async fn create_machine_token(
+    State(state): State<AppState>,
+    identity: IdentityHeaders,
+    ValidatedJson(body): ValidatedJson<CreateMachineTokenRequest>,
+) -> Result<(StatusCode, Json<MachineTokenResponse>), AuthServiceError> {
+    let uc = IssueMachineTokenUseCase {
+        jwt_secret: state.jwt_secret.current().into_inner(),
+    };
+    let out = uc.execute(
+        identity.user_id.into(),
+        identity.user_role,
+        identity.impersonator,
+        IssueMachineTokenInput {
+            client: body.client.clone(),
+            scopes: body.scopes.clone(),
+            ttl_secs: body.ttl_secs,
+        },
+    )?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(MachineTokenResponse {
+            client: body.client,
+            scopes: body.scopes,
+            token: out.token,
+            exp: out.exp,
+        }),
+    ))
+}
@@ -1,3 +1,13 @@
+pub mod api_token;
 pub mod auth_code;
+pub mod config;
+pub mod csrf;
+pub mod impersonate;
+pub mod login_link;
+pub mod machine_token;
+pub mod oauth;
+pub mod outbox;
 pub mod passkeys;
+pub mod session;
+pub mod stats;
 pub mod token;
@@ -0,0 +1,149 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::HeaderMap,
+    response::{IntoResponse, Redirect},
+};
+use axum_extra::extract::CookieJar;
+use axum_extra::extract::cookie::{Cookie, SameSite};
+use serde::{Deserialize, Serialize};
+use time::Duration;
+use utoipa::{IntoParams, ToSchema};
+
+use madome_auth_types::cookie::{CookieConfig, set_access_token_cookie, set_refresh_token_cookie};
+
+use madome_core::error_catalog::ErrorBody;
+use madome_core::query::QsQuery;
+
+use crate::error::AuthServiceError;
+use crate::handlers::session::client_info;
+use crate::state::AppState;
+use crate::usecase::oauth::{
+    FinishOAuthInput, FinishOAuthUseCase, OAUTH_STATE_TTL_SECS, StartOAuthUseCase,
+};
+
+/// Cookie tying a flow to the browser that started it; holds the `state`.
+pub const OAUTH_STATE_COOKIE: &str = "madome_oauth_state";
+
+/// `OAUTH_STATE_COOKIE` with `value`, sent only to the OAuth routes.
+fn oauth_state_cookie(config: &CookieConfig, value: String, max_age: Duration) -> Cookie<'static> {
+    let mut cookie = config.cookie(OAUTH_STATE_COOKIE, value, "/auth/oauth", Some(max_age));
+    cookie.set_http_only(true);
+    // `Lax` even when the token cookies are `Strict`: the callback is a
+    // navigation coming from the provider's site.
+    cookie.set_same_site(SameSite::Lax);
+    cookie
+}
+
+// ── POST /auth/oauth/{provider}/start ─────────────────────────────────────────
+
+#[derive(Serialize, ToSchema)]
+pub struct StartOAuthResponse {
+    pub authorize_url: String,
+}
+
+/// Begin provider sign-in; the client navigates to `authorize_url`.
+///
+/// Sets the `madome_oauth_state` cookie; the callback fails without it, so
+/// the flow must finish in the browser that started it.
+#[utoipa::path(
+    post,
+    path = "/auth/oauth/{provider}/start",
+    tag = "login",
+    params(("provider" = String, Path, description = "Configured provider, e.g. `google`")),
+    responses(
+        (status = 200, body = StartOAuthResponse),
+        (status = 404, description = "Provider not configured", body = ErrorBody),
+    ),
+)]
+pub async fn start_oauth(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(provider): Path<String>,
+) -> Result<(CookieJar, Json<StartOAuthResponse>), AuthServiceError> {
+    let uc = StartOAuthUseCase {
+        oauth: state.oauth_client(),
+        cache: state.oauth_state_cache(),
+    };
+    let out = uc.execute(&provider).await?;
+    let cookie = oauth_state_cookie(
+        &state.cookies,
+        out.state,
+        Duration::seconds(OAUTH_STATE_TTL_SECS as i64),
+    );
+    Ok((
+        jar.add(cookie),
+        Json(StartOAuthResponse {
+            authorize_url: out.authorize_url,
+        }),
+    ))
+}
+
+// ── GET /auth/oauth/{provider}/callback ───────────────────────────────────────
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OAuthCallbackQuery {
+    pub code: String,
+    pub state: String,
+}
+
+/// Provider redirect target: finish sign-in and send the browser to the app.
+#[utoipa::path(
+    get,
+    path = "/auth/oauth/{provider}/callback",
+    tag = "login",
+    params(("provider" = String, Path, description = "Configured provider"), OAuthCallbackQuery),
+    responses(
+        (status = 303, description = "Signed in; token cookies set", headers(("location" = String, description = "`APP_URL`"))),
+        (status = 401, description = "Unknown state, flow started in another browser, rejected code or unverified email", body = ErrorBody),
+        (status = 404, description = "No user with the provider's email", body = ErrorBody),
+    ),
+)]
+pub async fn oauth_callback(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    req_headers: HeaderMap,
+    Path(provider): Path<String>,
+    QsQuery(q): QsQuery<OAuthCallbackQuery>,
+) -> Result<impl IntoResponse, AuthServiceError> {
+    let uc = FinishOAuthUseCase {
+        users: state.user_repo(),
+        identities: state.linked_identity_repo(),
+        sessions: state.session_repo(),
+        oauth: state.oauth_client(),
+        cache: state.oauth_state_cache(),
+        jwt_secret: state.jwt_secret.current().into_inner(),
+        lifetimes: state.token_lifetimes,
+    };
+    let out = uc
+        .execute(FinishOAuthInput {
+            provider,
+            code: q.code,
+            state: q.state,
+            browser_state: jar.get(OAUTH_STATE_COOKIE).map(|c| c.value().to_owned()),
+            client: client_info(&req_headers),
+        })
+        .await?;
+
+    let jar = jar.add(oauth_state_cookie(
+        &state.cookies,
+        String::new(),
+        Duration::ZERO,
+    ));
+
+    let jar = set_access_token_cookie(
+        jar,
+        out.access_token,
+        &state.cookies,
+        Some(state.token_lifetimes.refresh_secs),
+    );
+    let jar = set_refresh_token_cookie(
+        jar,
+        out.refresh_token,
+        &state.cookies,
+        Some(state.token_lifetimes.refresh_secs),
+    );
+
+    Ok((jar, Redirect::to(&state.app_url)))
+}
@@ -0,0 +1,206 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use madome_auth_types::identity::IdentityHeaders;
+use madome_core::error_catalog::ErrorBody;
+use madome_core::pagination::total_count_header;
+use madome_core::query::QsQuery;
+use madome_domain::pagination::PageRequest;
+
+use crate::error::AuthServiceError;
+use crate::state::AppState;
+use crate::usecase::outbox::{
+    DeleteOutboxEventUseCase, DeliveryStatus, ListOutboxEventsUseCase, OutboxEventInfo,
+    RetryOutboxEventUseCase,
+};
+
+fn parse_event_id(id: &str) -> Result<Uuid, AuthServiceError> {
+    id.parse()
+        .map_err(|_| AuthServiceError::BadRequest("invalid event id".to_owned()))
+}
+
+// ── GET /auth/admin/outbox ────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OutboxEventStatus {
+    Pending,
+    Processed,
+    #[default]
+    Failed,
+}
+
+impl From<OutboxEventStatus> for DeliveryStatus {
+    fn from(status: OutboxEventStatus) -> Self {
+        match status {
+            OutboxEventStatus::Pending => Self::Pending,
+            OutboxEventStatus::Processed => Self::Processed,
+            OutboxEventStatus::Failed => Self::Failed,
+        }
+    }
+}
+
+impl From<DeliveryStatus> for OutboxEventStatus {
+    fn from(status: DeliveryStatus) -> Self {
+        match status {
+            DeliveryStatus::Pending => Self::Pending,
+            DeliveryStatus::Processed => Self::Processed,
+            DeliveryStatus::Failed => Self::Failed,
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OutboxQuery {
+    /// Delivery state to list; `failed` (the dead letters) when omitted.
+    #[serde(default)]
+    #[param(inline)]
+    pub status: OutboxEventStatus,
+    /// 1–100, default 25.
+    #[serde(default, rename = "per-page")]
+    pub per_page: Option<u32>,
+    /// ≥ 1, default 1.
+    #[serde(default)]
+    pub page: Option<u32>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct OutboxEventResponse {
+    pub id: Uuid,
+    pub kind: String,
+    /// Event payload; secrets such as sign-in codes read `[redacted]`.
+    pub payload: serde_json::Value,
+    pub idempotency_key: String,
+    pub status: OutboxEventStatus,
+    pub attempts: u32,
+    /// Error of the most recent failed attempt.
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub processed_at: Option<DateTime<Utc>>,
+    pub failed_at: Option<DateTime<Utc>>,
+}
+
+impl From<OutboxEventInfo> for OutboxEventResponse {
+    fn from(e: OutboxEventInfo) -> Self {
+        Self {
+            id: e.id,
+            kind: e.kind,
+            payload: e.payload,
+            idempotency_key: e.idempotency_key,
+            status: e.status.into(),
+            attempts: e.attempts,
+            last_error: e.last_error,
+            created_at: e.created_at,
+            next_attempt_at: e.next_attempt_at,
+            processed_at: e.processed_at,
+            failed_at: e.failed_at,
+        }
+    }
+}
+
+/// Outbox events in one delivery state, newest first (staff only).
+#[utoipa::path(
+    get,
+    path = "/auth/admin/outbox",
+    tag = "outbox",
+    security(("gatewayIdentity" = [])),
+    params(OutboxQuery),
+    responses(
+        (status = 200, body = Vec<OutboxEventResponse>,
+            headers(("x-madome-total-count" = u64, description = "Events in this state"))),
+        (status = 400, description = "Malformed query", body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Caller is not staff", body = ErrorBody),
+    ),
+)]
+pub async fn list_outbox_events(
+    State(state): State<AppState>,
+    QsQuery(q): QsQuery<OutboxQuery>,
+) -> Result<impl IntoResponse, AuthServiceError> {
+    let defaults = PageRequest::default();
+    let page = PageRequest {
+        per_page: q.per_page.unwrap_or(defaults.per_page),
+        page: q.page.unwrap_or(defaults.page),
+    };
+
+    let uc = ListOutboxEventsUseCase {
+        outbox: state.outbox_repo(),
+    };
+    let list = uc
+        .execute(q.status.into(), page)
+        .await?
+        .map(OutboxEventResponse::from);
+    Ok((total_count_header(list.total), Json(list.items)))
+}
+
+// ── POST /auth/admin/outbox/{id}/retry ────────────────────────────────────────
+
+/// Requeue a failed event for immediate delivery (staff only).
+#[utoipa::path(
+    post,
+    path = "/auth/admin/outbox/{id}/retry",
+    tag = "outbox",
+    security(("gatewayIdentity" = [])),
+    params(("id" = uuid::Uuid, Path, description = "Outbox event id")),
+    responses(
+        (status = 204, description = "Event requeued"),
+        (status = 400, description = "Malformed id", body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Caller is not staff", body = ErrorBody),
+        (status = 404, description = "No failed event with this id", body = ErrorBody),
+    ),
+)]
+pub async fn retry_outbox_event(
+    State(state): State<AppState>,
+    identity: IdentityHeaders,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AuthServiceError> {
+    let id = parse_event_id(&id)?;
+
+    let uc = RetryOutboxEventUseCase {
+        outbox: state.outbox_repo(),
+    };
+    uc.execute(identity.user_id.into(), id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ── DELETE /auth/admin/outbox/{id} ────────────────────────────────────────────
+
+/// Discard a failed event without delivering it (staff only).
+#[utoipa::path(
+    delete,
+    path = "/auth/admin/outbox/{id}",
+    tag = "outbox",
+    security(("gatewayIdentity" = [])),
+    params(("id" = uuid::Uuid, Path, description = "Outbox event id")),
+    responses(
+        (status = 204, description = "Event discarded"),
+        (status = 400, description = "Malformed id", body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Caller is not staff", body = ErrorBody),
+        (status = 404, description = "No failed event with this id", body = ErrorBody),
+    ),
+)]
+pub async fn delete_outbox_event(
+    State(state): State<AppState>,
+    identity: IdentityHeaders,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AuthServiceError> {
+    let id = parse_event_id(&id)?;
+
+    let uc = DeleteOutboxEventUseCase {
+        outbox: state.outbox_repo(),
+    };
+    uc.execute(identity.user_id.into(), id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
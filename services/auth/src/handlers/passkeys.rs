@@ -1,6 +1,6 @@
 use axum::{
     Json,
-    extract::{Path, Query, State},
+    extract::{Path, State},
     http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
     response::IntoResponse,
 };
@@ -8,14 +8,19 @@ use axum_extra::extract::CookieJar;
 use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use webauthn_rs::prelude::{PublicKeyCredential, RegisterPublicKeyCredential};
 
 use madome_auth_types::{
     cookie::{set_access_token_cookie, set_refresh_token_cookie},
     identity::IdentityHeaders,
 };
+use madome_core::error_catalog::ErrorBody;
+use madome_core::query::QsQuery;
 
 use crate::error::AuthServiceError;
+use crate::handlers::session::client_info;
+use crate::handlers::token::default_remember;
 use crate::state::AppState;
 use crate::usecase::passkey::{
     DeletePasskeyUseCase, FinishAuthenticationUseCase, FinishRegistrationUseCase,
@@ -24,12 +29,31 @@ use crate::usecase::passkey::{
 
 // ── GET /auth/passkeys ────────────────────────────────────────────────────────
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct PasskeyResponse {
+    /// Unpadded base64url.
     pub credential_id: String,
+    /// The authenticator can sync this passkey to other devices.
+    pub backup_eligible: bool,
+    /// The passkey was backed up when last used. A passkey that is not is
+    /// lost with its device.
+    pub backup_state: bool,
+    /// Transports reported at registration, e.g. `internal`, `hybrid`, `usb`.
+    pub transports: Vec<String>,
     pub created_at: DateTime<Utc>,
 }
 
+/// Passkeys registered by the caller.
+#[utoipa::path(
+    get,
+    path = "/auth/passkeys",
+    tag = "passkeys",
+    security(("gatewayIdentity" = [])),
+    responses(
+        (status = 200, body = Vec<PasskeyResponse>),
+        (status = 401, body = ErrorBody),
+    ),
+)]
 pub async fn list_passkeys(
     State(state): State<AppState>,
     identity: IdentityHeaders,
@@ -37,11 +61,14 @@ pub async fn list_passkeys(
     let uc = ListPasskeysUseCase {
         passkeys: state.passkey_repo(),
     };
-    let list = uc.execute(identity.user_id).await?;
+    let list = uc.execute(identity.user_id.into()).await?;
     let body: Vec<PasskeyResponse> = list
         .into_iter()
         .map(|p| PasskeyResponse {
             credential_id: URL_SAFE_NO_PAD.encode(&p.credential_id),
+            backup_eligible: p.backup_eligible,
+            backup_state: p.backup_state,
+            transports: p.transports,
             created_at: p.created_at,
         })
         .collect();
@@ -50,6 +77,21 @@ pub async fn list_passkeys(
 
 // ── DELETE /auth/passkeys/{credential_id} ─────────────────────────────────────
 
+/// Remove one of the caller's passkeys.
+#[utoipa::path(
+    delete,
+    path = "/auth/passkeys/{credential_id}",
+    tag = "passkeys",
+    security(("gatewayIdentity" = [])),
+    params(("credential_id" = String, Path, description = "Unpadded base64url credential id")),
+    responses(
+        (status = 204, description = "Passkey removed"),
+        (status = 400, description = "Malformed credential id", body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Called while impersonating", body = ErrorBody),
+        (status = 404, description = "No such passkey for the caller", body = ErrorBody),
+    ),
+)]
 pub async fn delete_passkey(
     State(state): State<AppState>,
     identity: IdentityHeaders,
@@ -62,12 +104,29 @@ pub async fn delete_passkey(
     let uc = DeletePasskeyUseCase {
         passkeys: state.passkey_repo(),
     };
-    uc.execute(&credential_id, identity.user_id).await?;
+    uc.execute(
+        &credential_id,
+        identity.user_id.into(),
+        identity.impersonator,
+    )
+    .await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
 // ── POST /auth/passkey/registration ──────────────────────────────────────────
 
+/// Begin registering a passkey; the body is WebAuthn `CreationChallengeResponse`.
+#[utoipa::path(
+    post,
+    path = "/auth/passkey/registration",
+    tag = "passkeys",
+    security(("gatewayIdentity" = [])),
+    responses(
+        (status = 200, body = serde_json::Value, headers(("x-madome-passkey-registration-id" = String, description = "Pass back to finish registration"))),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Called while impersonating", body = ErrorBody),
+    ),
+)]
 pub async fn start_registration(
     State(state): State<AppState>,
     identity: IdentityHeaders,
@@ -78,7 +137,9 @@ pub async fn start_registration(
         cache: state.passkey_cache(),
         webauthn: state.webauthn.clone(),
     };
-    let out = uc.execute(identity.user_id).await?;
+    let out = uc
+        .execute(identity.user_id.into(), identity.impersonator)
+        .await?;
 
     let mut headers = HeaderMap::new();
     headers.insert(
@@ -91,16 +152,32 @@ pub async fn start_registration(
 
 // ── PATCH /auth/passkey/registration?registration-id={id} ────────────────────
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct RegistrationQuery {
     #[serde(rename = "registration-id")]
     pub registration_id: String,
 }
 
+/// Finish registration with the authenticator's `RegisterPublicKeyCredential`.
+#[utoipa::path(
+    patch,
+    path = "/auth/passkey/registration",
+    tag = "passkeys",
+    security(("gatewayIdentity" = [])),
+    params(RegistrationQuery),
+    request_body(content = serde_json::Value, description = "WebAuthn `RegisterPublicKeyCredential`"),
+    responses(
+        (status = 201, description = "Passkey registered"),
+        (status = 400, description = "Rejected credential", body = ErrorBody),
+        (status = 401, description = "Unknown or expired registration", body = ErrorBody),
+        (status = 403, description = "Called while impersonating", body = ErrorBody),
+    ),
+)]
 pub async fn finish_registration(
     State(state): State<AppState>,
     identity: IdentityHeaders,
-    Query(q): Query<RegistrationQuery>,
+    QsQuery(q): QsQuery<RegistrationQuery>,
     Json(credential): Json<RegisterPublicKeyCredential>,
 ) -> Result<StatusCode, AuthServiceError> {
     let uc = FinishRegistrationUseCase {
@@ -108,21 +185,38 @@ pub async fn finish_registration(
         cache: state.passkey_cache(),
         webauthn: state.webauthn.clone(),
     };
-    uc.execute(identity.user_id, &q.registration_id, credential)
-        .await?;
+    uc.execute(
+        identity.user_id.into(),
+        identity.impersonator,
+        &q.registration_id,
+        credential,
+    )
+    .await?;
     Ok(StatusCode::CREATED)
 }
 
 // ── POST /auth/passkey/authentication?email={email} ───────────────────────────
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct StartAuthQuery {
     pub email: String,
 }
 
+/// Begin passkey sign-in; the body is WebAuthn `RequestChallengeResponse`.
+#[utoipa::path(
+    post,
+    path = "/auth/passkey/authentication",
+    tag = "login",
+    params(StartAuthQuery),
+    responses(
+        (status = 200, body = serde_json::Value, headers(("x-madome-passkey-authentication-id" = String, description = "Pass back to finish authentication"))),
+        (status = 404, description = "No user or no passkeys for this email", body = ErrorBody),
+    ),
+)]
 pub async fn start_authentication(
     State(state): State<AppState>,
-    Query(q): Query<StartAuthQuery>,
+    QsQuery(q): QsQuery<StartAuthQuery>,
 ) -> Result<impl IntoResponse, AuthServiceError> {
     let uc = StartAuthenticationUseCase {
         users: state.user_repo(),
@@ -143,32 +237,61 @@ pub async fn start_authentication(
 
 // ── PATCH /auth/passkey/authentication?authentication-id={id}&email={email} ───
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct FinishAuthQuery {
     #[serde(rename = "authentication-id")]
     pub authentication_id: String,
     pub email: String,
+    /// Stay signed in across browser restarts (default); see `POST /auth/token`.
+    #[serde(default = "default_remember")]
+    #[param(default = true)]
+    pub remember: bool,
 }
 
+/// Finish passkey sign-in with the authenticator's `PublicKeyCredential`.
+#[utoipa::path(
+    patch,
+    path = "/auth/passkey/authentication",
+    tag = "login",
+    params(FinishAuthQuery),
+    request_body(content = serde_json::Value, description = "WebAuthn `PublicKeyCredential`"),
+    responses(
+        (status = 201, description = "Signed in; token cookies set", headers(("x-madome-access-token-expires" = u64, description = "Access token expiry (Unix seconds)"))),
+        (status = 400, description = "Rejected credential", body = ErrorBody),
+        (status = 401, description = "Unknown or expired authentication", body = ErrorBody),
+        (status = 404, description = "Unknown user or passkey", body = ErrorBody),
+    ),
+)]
 pub async fn finish_authentication(
     State(state): State<AppState>,
     jar: CookieJar,
-    Query(q): Query<FinishAuthQuery>,
+    req_headers: HeaderMap,
+    QsQuery(q): QsQuery<FinishAuthQuery>,
     Json(credential): Json<PublicKeyCredential>,
 ) -> Result<impl IntoResponse, AuthServiceError> {
     let uc = FinishAuthenticationUseCase {
         users: state.user_repo(),
         passkeys: state.passkey_repo(),
         cache: state.passkey_cache(),
+        sessions: state.session_repo(),
         webauthn: state.webauthn.clone(),
-        jwt_secret: state.jwt_secret.clone(),
+        jwt_secret: state.jwt_secret.current().into_inner(),
+        lifetimes: state.token_lifetimes,
     };
     let out = uc
-        .execute(&q.email, &q.authentication_id, credential)
+        .execute(
+            &q.email,
+            &q.authentication_id,
+            credential,
+            &client_info(&req_headers),
+            q.remember,
+        )
         .await?;
 
-    let jar = set_access_token_cookie(jar, out.access_token, state.cookie_domain.clone());
-    let jar = set_refresh_token_cookie(jar, out.refresh_token, state.cookie_domain.clone());
+    let max_age = state.token_lifetimes.cookie_max_age(q.remember);
+    let jar = set_access_token_cookie(jar, out.access_token, &state.cookies, max_age);
+    let jar = set_refresh_token_cookie(jar, out.refresh_token, &state.cookies, max_age);
 
     let mut headers = HeaderMap::new();
     headers.insert(
@@ -0,0 +1,109 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, header::USER_AGENT},
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use madome_auth_types::identity::IdentityHeaders;
+use madome_core::error_catalog::ErrorBody;
+
+use crate::error::AuthServiceError;
+use crate::state::AppState;
+use crate::usecase::session::{ClientInfo, DeleteSessionUseCase, ListSessionsUseCase};
+
+/// Capture the client context recorded on a session.
+///
+/// The service runs behind the gateway, so the peer address is never the client;
+/// the first `x-forwarded-for` hop is used instead.
+pub(crate) fn client_info(headers: &HeaderMap) -> ClientInfo {
+    let user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned);
+    let ip = headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .map(|v| v.trim().to_owned())
+        .filter(|v| !v.is_empty());
+    ClientInfo { user_agent, ip }
+}
+
+// ── GET /auth/sessions ────────────────────────────────────────────────────────
+
+#[derive(Serialize, ToSchema)]
+pub struct SessionResponse {
+    pub id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+}
+
+/// Signed-in devices of the caller, most recently used first.
+#[utoipa::path(
+    get,
+    path = "/auth/sessions",
+    tag = "sessions",
+    security(("gatewayIdentity" = [])),
+    responses(
+        (status = 200, body = Vec<SessionResponse>),
+        (status = 401, body = ErrorBody),
+    ),
+)]
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    identity: IdentityHeaders,
+) -> Result<Json<Vec<SessionResponse>>, AuthServiceError> {
+    let uc = ListSessionsUseCase {
+        sessions: state.session_repo(),
+    };
+    let list = uc.execute(identity.user_id.into()).await?;
+    let body: Vec<SessionResponse> = list
+        .into_iter()
+        .map(|s| SessionResponse {
+            id: s.id,
+            user_agent: s.user_agent,
+            ip: s.ip,
+            created_at: s.created_at,
+            last_used_at: s.last_used_at,
+        })
+        .collect();
+    Ok(Json(body))
+}
+
+// ── DELETE /auth/sessions/{id} ────────────────────────────────────────────────
+
+/// Sign a device out; its refresh token stops working.
+#[utoipa::path(
+    delete,
+    path = "/auth/sessions/{id}",
+    tag = "sessions",
+    security(("gatewayIdentity" = [])),
+    params(("id" = uuid::Uuid, Path, description = "Session id")),
+    responses(
+        (status = 204, description = "Session revoked"),
+        (status = 400, description = "Malformed id", body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 404, description = "No such session for the caller", body = ErrorBody),
+    ),
+)]
+pub async fn delete_session(
+    State(state): State<AppState>,
+    identity: IdentityHeaders,
+    Path(session_id): Path<String>,
+) -> Result<StatusCode, AuthServiceError> {
+    let session_id = session_id
+        .parse::<Uuid>()
+        .map_err(|_| AuthServiceError::BadRequest("invalid session id".to_owned()))?;
+
+    let uc = DeleteSessionUseCase {
+        sessions: state.session_repo(),
+    };
+    uc.execute(session_id, identity.user_id.into()).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,82 @@
+use axum::{Json, extract::State};
+use chrono::{Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use madome_core::error_catalog::ErrorBody;
+use madome_core::query::QsQuery;
+
+use crate::error::AuthServiceError;
+use crate::state::AppState;
+use crate::usecase::stats::{DailyStatsInfo, ListDailyStatsUseCase};
+
+/// Days listed when the query names no `from`.
+const DEFAULT_DAYS: i64 = 30;
+
+// ── GET /auth/admin/stats ─────────────────────────────────────────────────────
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StatsQuery {
+    /// First UTC day (`YYYY-MM-DD`); 29 days before `to` by default.
+    #[serde(default)]
+    pub from: Option<NaiveDate>,
+    /// Last UTC day, inclusive; today by default.
+    #[serde(default)]
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DailyStatsResponse {
+    pub day: NaiveDate,
+    /// Users who used a session that day (refreshed their tokens).
+    pub active_users: u64,
+    /// Sessions started that day.
+    pub sign_ins: u64,
+}
+
+impl From<DailyStatsInfo> for DailyStatsResponse {
+    fn from(s: DailyStatsInfo) -> Self {
+        Self {
+            day: s.day,
+            active_users: s.active_users,
+            sign_ins: s.sign_ins,
+        }
+    }
+}
+
+/// Daily sign-in activity, oldest day first (staff only).
+///
+/// Read from figures a background job rolls up every 15 minutes, so today's
+/// row trails live activity a little. Days before the job first ran are
+/// missing.
+#[utoipa::path(
+    get,
+    path = "/auth/admin/stats",
+    tag = "stats",
+    security(("gatewayIdentity" = [])),
+    params(StatsQuery),
+    responses(
+        (status = 200, body = Vec<DailyStatsResponse>),
+        (status = 400, description = "Malformed dates, `from` after `to`, or over 366 days", body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, description = "Caller is not staff", body = ErrorBody),
+    ),
+)]
+pub async fn list_daily_stats(
+    State(state): State<AppState>,
+    QsQuery(q): QsQuery<StatsQuery>,
+) -> Result<Json<Vec<DailyStatsResponse>>, AuthServiceError> {
+    let to = q.to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = q
+        .from
+        .unwrap_or_else(|| to - Duration::days(DEFAULT_DAYS - 1));
+
+    let uc = ListDailyStatsUseCase {
+        stats: state.stats_repo(),
+    };
+    let list = uc.execute(from, to).await?;
+    Ok(Json(
+        list.into_iter().map(DailyStatsResponse::from).collect(),
+    ))
+}
@@ -1,11 +1,12 @@
 use axum::{
     Json,
-    extract::{Query, State},
+    extract::State,
     http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
     response::IntoResponse,
 };
 use axum_extra::extract::CookieJar;
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
 use madome_auth_types::{
     cookie::{
@@ -15,14 +16,21 @@ use madome_auth_types::{
     identity::IdentityHeaders,
     token::validate_access_token,
 };
+use madome_core::config::Secret;
+use madome_core::error_catalog::ErrorBody;
+use madome_core::query::QsQuery;
+use madome_core::validation::{Validate, ValidatedJson, ValidationErrors};
 
 use crate::error::AuthServiceError;
+use crate::handlers::session::client_info;
 use crate::state::AppState;
-use crate::usecase::token::{CreateTokenInput, CreateTokenUseCase, RefreshTokenUseCase};
+use crate::usecase::token::{
+    CreateTokenInput, CreateTokenUseCase, RefreshTokenUseCase, RevokeTokenUseCase,
+};
 
 const X_MADOME_ACCESS_TOKEN_EXPIRES: &str = "x-madome-access-token-expires";
 
-fn token_expires_header(exp: u64) -> (HeaderName, HeaderValue) {
+pub(crate) fn token_expires_header(exp: u64) -> (HeaderName, HeaderValue) {
     (
         HeaderName::from_static(X_MADOME_ACCESS_TOKEN_EXPIRES),
         HeaderValue::from_str(&exp.to_string()).unwrap(),
@@ -31,29 +39,47 @@ fn token_expires_header(exp: u64) -> (HeaderName, HeaderValue) {
 
 // ── GET /auth/token ───────────────────────────────────────────────────────────
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct CheckTokenQuery {
+    /// Minimum role; a lower-ranked token is rejected.
     pub role: Option<u8>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct CheckTokenResponse {
     pub user_id: uuid::Uuid,
     pub user_role: u8,
     pub access_token_exp: u64,
 }
 
+/// Validate the access token cookie.
+#[utoipa::path(
+    get,
+    path = "/auth/token",
+    tag = "token",
+    params(CheckTokenQuery),
+    security(("accessTokenCookie" = [])),
+    responses(
+        (status = 200, body = CheckTokenResponse, headers(("x-madome-access-token-expires" = u64, description = "Access token expiry (Unix seconds)"))),
+        (status = 401, description = "Missing, invalid or under-privileged token", body = ErrorBody),
+    ),
+)]
 pub async fn check_token(
     State(state): State<AppState>,
     jar: CookieJar,
-    Query(q): Query<CheckTokenQuery>,
+    QsQuery(q): QsQuery<CheckTokenQuery>,
 ) -> Result<impl IntoResponse, AuthServiceError> {
     let token_value = jar
         .get(MADOME_ACCESS_TOKEN)
         .map(|c| c.value().to_owned())
         .ok_or(AuthServiceError::Unauthorized)?;
 
-    let info = validate_access_token(&token_value, &state.jwt_secret)
+    let info = validate_access_token(&token_value, state.jwt_secret.current().expose())
+        .or_else(|e| match state.jwt_secret.previous() {
+            Some(previous) => validate_access_token(&token_value, previous.expose()),
+            None => Err(e),
+        })
         .map_err(|_| AuthServiceError::Unauthorized)?;
 
     if let Some(min_role) = q.role {
@@ -77,32 +103,75 @@ pub async fn check_token(
 
 // ── POST /auth/token ──────────────────────────────────────────────────────────
 
-#[derive(Deserialize)]
+/// Upper bound on a submitted code; only rejects junk, the use case checks the code itself.
+const MAX_AUTHCODE_LEN: usize = 32;
+
+pub(crate) fn default_remember() -> bool {
+    true
+}
+
+#[derive(Deserialize, ToSchema)]
 pub struct CreateTokenRequest {
     pub email: String,
     pub code: String,
+    /// Stay signed in across browser restarts (default). `false` gives session
+    /// cookies and a refresh token valid for 1 day at most.
+    #[serde(default = "default_remember")]
+    #[schema(default = true)]
+    pub remember: bool,
 }
 
+impl Validate for CreateTokenRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        errors.check_email("email", &self.email);
+        errors.check_length("code", &self.code, MAX_AUTHCODE_LEN);
+        errors.into_result()
+    }
+}
+
+/// Exchange an emailed code for a token pair.
+#[utoipa::path(
+    post,
+    path = "/auth/token",
+    tag = "token",
+    request_body = CreateTokenRequest,
+    responses(
+        (status = 201, description = "Signed in; token cookies set", headers(("x-madome-access-token-expires" = u64, description = "Access token expiry (Unix seconds)"))),
+        (status = 404, description = "Unknown user or code", body = ErrorBody),
+        (status = 422, description = "Invalid email or code", body = ErrorBody),
+        (status = 429, description = "Too many wrong codes; `ratelimit-*` and `retry-after` headers tell when to try again", body = ErrorBody),
+    ),
+)]
 pub async fn create_token(
     State(state): State<AppState>,
     jar: CookieJar,
-    Json(body): Json<CreateTokenRequest>,
+    req_headers: HeaderMap,
+    ValidatedJson(body): ValidatedJson<CreateTokenRequest>,
 ) -> Result<impl IntoResponse, AuthServiceError> {
     let uc = CreateTokenUseCase {
         users: state.user_repo(),
         auth_codes: state.auth_code_repo(),
-        jwt_secret: state.jwt_secret.clone(),
+        attempts: state.auth_code_attempts(),
+        sessions: state.session_repo(),
+        jwt_secret: state.jwt_secret.current().into_inner(),
+        previous_jwt_secret: state.jwt_secret.previous().map(Secret::into_inner),
+        lifetimes: state.token_lifetimes,
     };
 
+    let remember = body.remember;
     let out = uc
         .execute(CreateTokenInput {
             email: body.email,
             code: body.code,
+            client: client_info(&req_headers),
+            remember,
         })
         .await?;
 
-    let jar = set_access_token_cookie(jar, out.access_token, state.cookie_domain.clone());
-    let jar = set_refresh_token_cookie(jar, out.refresh_token, state.cookie_domain.clone());
+    let max_age = state.token_lifetimes.cookie_max_age(remember);
+    let jar = set_access_token_cookie(jar, out.access_token, &state.cookies, max_age);
+    let jar = set_refresh_token_cookie(jar, out.refresh_token, &state.cookies, max_age);
 
     let mut headers = HeaderMap::new();
     let (name, value) = token_expires_header(out.access_token_exp);
@@ -113,9 +182,21 @@ pub async fn create_token(
 
 // ── PATCH /auth/token ─────────────────────────────────────────────────────────
 
+/// Rotate the token pair using the refresh token cookie.
+#[utoipa::path(
+    patch,
+    path = "/auth/token",
+    tag = "token",
+    security(("refreshTokenCookie" = [])),
+    responses(
+        (status = 201, description = "Token cookies rotated", headers(("x-madome-access-token-expires" = u64, description = "Access token expiry (Unix seconds)"))),
+        (status = 401, description = "Missing, expired or revoked refresh token", body = ErrorBody),
+    ),
+)]
 pub async fn refresh_token(
     State(state): State<AppState>,
     jar: CookieJar,
+    req_headers: HeaderMap,
 ) -> Result<impl IntoResponse, AuthServiceError> {
     let refresh_value = jar
         .get(MADOME_REFRESH_TOKEN)
@@ -124,13 +205,21 @@ pub async fn refresh_token(
 
     let uc = RefreshTokenUseCase {
         users: state.user_repo(),
-        jwt_secret: state.jwt_secret.clone(),
+        sessions: state.session_repo(),
+        jwt_secret: state.jwt_secret.current().into_inner(),
+        previous_jwt_secret: state.jwt_secret.previous().map(Secret::into_inner),
+        lifetimes: state.token_lifetimes,
+        legacy_cutoff: state.legacy_token_cutoff,
     };
 
-    let out = uc.execute(&refresh_value).await?;
+    let out = uc
+        .execute(&refresh_value, &client_info(&req_headers))
+        .await?;
 
-    let jar = set_access_token_cookie(jar, out.access_token, state.cookie_domain.clone());
-    let jar = set_refresh_token_cookie(jar, out.refresh_token, state.cookie_domain.clone());
+    // Keep the "remember me" choice of the sign-in.
+    let max_age = state.token_lifetimes.cookie_max_age(out.remember);
+    let jar = set_access_token_cookie(jar, out.access_token, &state.cookies, max_age);
+    let jar = set_refresh_token_cookie(jar, out.refresh_token, &state.cookies, max_age);
 
     let mut headers = HeaderMap::new();
     let (name, value) = token_expires_header(out.access_token_exp);
@@ -141,11 +230,34 @@ pub async fn refresh_token(
 
 // ── DELETE /auth/token ────────────────────────────────────────────────────────
 
+/// Sign out: revoke the current session and clear the token cookies.
+#[utoipa::path(
+    delete,
+    path = "/auth/token",
+    tag = "token",
+    security(("gatewayIdentity" = [])),
+    responses(
+        (status = 204, description = "Signed out"),
+        (status = 401, body = ErrorBody),
+    ),
+)]
 pub async fn revoke_token(
     State(state): State<AppState>,
-    _identity: IdentityHeaders,
+    identity: IdentityHeaders,
     jar: CookieJar,
 ) -> Result<impl IntoResponse, AuthServiceError> {
-    let jar = clear_cookies(jar, state.cookie_domain.clone());
+    // The refresh cookie is scoped to `/auth/token`, so it is sent here too.
+    let uc = RevokeTokenUseCase {
+        sessions: state.session_repo(),
+        jwt_secret: state.jwt_secret.current().into_inner(),
+        previous_jwt_secret: state.jwt_secret.previous().map(Secret::into_inner),
+    };
+    uc.execute(
+        identity.user_id.into(),
+        jar.get(MADOME_REFRESH_TOKEN).map(|c| c.value()),
+    )
+    .await?;
+
+    let jar = clear_cookies(jar, &state.cookies);
     Ok((StatusCode::NO_CONTENT, jar))
 }
@@ -1,9 +1,12 @@
 use deadpool_redis::Pool;
-use deadpool_redis::redis::AsyncCommands;
-use uuid::Uuid;
+use deadpool_redis::redis::{self, AsyncCommands};
+use madome_core::idempotency::IdempotencyStore;
+use madome_domain::id::UserId;
 
-use crate::domain::repository::PasskeyCache;
-use crate::domain::types::PASSKEY_STATE_TTL_SECS;
+use crate::domain::repository::{AuthCodeAttemptStore, OAuthStateCache, PasskeyCache};
+use crate::domain::types::{
+    AUTHCODE_LOCKOUT_SECS, MAX_AUTHCODE_ATTEMPTS, OAUTH_STATE_TTL_SECS, PASSKEY_STATE_TTL_SECS,
+};
 use crate::error::AuthServiceError;
 
 #[derive(Clone)]
@@ -11,7 +14,7 @@ pub struct RedisPasskeyCache {
     pub pool: Pool,
 }
 
-fn reg_state_key(user_id: Uuid, reg_id: &str) -> String {
+fn reg_state_key(user_id: UserId, reg_id: &str) -> String {
     format!("passkey_reg:{}:{}", user_id, reg_id)
 }
 
@@ -22,7 +25,7 @@ fn auth_state_key(email: &str, auth_id: &str) -> String {
 impl PasskeyCache for RedisPasskeyCache {
     async fn set_registration_state(
         &self,
-        user_id: Uuid,
+        user_id: UserId,
         reg_id: &str,
         state_json: &[u8],
     ) -> Result<(), AuthServiceError> {
@@ -41,7 +44,7 @@ impl PasskeyCache for RedisPasskeyCache {
 
     async fn take_registration_state(
         &self,
-        user_id: Uuid,
+        user_id: UserId,
         reg_id: &str,
     ) -> Result<Option<Vec<u8>>, AuthServiceError> {
         let mut conn = self
@@ -94,3 +97,139 @@ impl PasskeyCache for RedisPasskeyCache {
         Ok(value)
     }
 }
+
+#[derive(Clone)]
+pub struct RedisOAuthStateCache {
+    pub pool: Pool,
+}
+
+fn oauth_state_key(state: &str) -> String {
+    format!("oauth_state:{}", state)
+}
+
+impl OAuthStateCache for RedisOAuthStateCache {
+    async fn set_state(&self, state: &str, pending_json: &[u8]) -> Result<(), AuthServiceError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| AuthServiceError::Internal(e.into()))?;
+        let key = oauth_state_key(state);
+        let (): () = conn
+            .set_ex(&key, pending_json.to_vec(), OAUTH_STATE_TTL_SECS as u64)
+            .await
+            .map_err(|e: deadpool_redis::redis::RedisError| AuthServiceError::Internal(e.into()))?;
+        Ok(())
+    }
+
+    async fn take_state(&self, state: &str) -> Result<Option<Vec<u8>>, AuthServiceError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| AuthServiceError::Internal(e.into()))?;
+        let key = oauth_state_key(state);
+        let value: Option<Vec<u8>> = conn
+            .get_del(&key)
+            .await
+            .map_err(|e| AuthServiceError::Internal(e.into()))?;
+        Ok(value)
+    }
+}
+
+#[derive(Clone)]
+pub struct RedisAuthCodeAttemptStore {
+    pub pool: Pool,
+}
+
+fn authcode_attempts_key(user_id: UserId) -> String {
+    format!("authcode_attempts:{}", user_id)
+}
+
+impl AuthCodeAttemptStore for RedisAuthCodeAttemptStore {
+    async fn failures(&self, user_id: UserId) -> Result<u32, AuthServiceError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| AuthServiceError::Internal(e.into()))?;
+        let count: Option<u32> = conn
+            .get(authcode_attempts_key(user_id))
+            .await
+            .map_err(|e| AuthServiceError::Internal(e.into()))?;
+        Ok(count.unwrap_or(0))
+    }
+
+    async fn record_failure(&self, user_id: UserId) -> Result<u32, AuthServiceError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| AuthServiceError::Internal(e.into()))?;
+        let key = authcode_attempts_key(user_id);
+        let count: u32 = conn
+            .incr(&key, 1)
+            .await
+            .map_err(|e: deadpool_redis::redis::RedisError| AuthServiceError::Internal(e.into()))?;
+        // The first failure opens the window; reaching the limit restarts it
+        // so the lock lasts the full cooldown.
+        if count == 1 || count == MAX_AUTHCODE_ATTEMPTS {
+            let (): () = conn.expire(&key, AUTHCODE_LOCKOUT_SECS).await.map_err(
+                |e: deadpool_redis::redis::RedisError| AuthServiceError::Internal(e.into()),
+            )?;
+        }
+        Ok(count)
+    }
+
+    async fn clear(&self, user_id: UserId) -> Result<(), AuthServiceError> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| AuthServiceError::Internal(e.into()))?;
+        let (): () = conn
+            .del(authcode_attempts_key(user_id))
+            .await
+            .map_err(|e: deadpool_redis::redis::RedisError| AuthServiceError::Internal(e.into()))?;
+        Ok(())
+    }
+}
+
+// ── Idempotency-Key store ────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct RedisIdempotencyStore {
+    pub pool: Pool,
+}
+
+impl IdempotencyStore for RedisIdempotencyStore {
+    async fn set_nx(&self, key: &str, value: &[u8], ttl_secs: u64) -> anyhow::Result<bool> {
+        let mut conn = self.pool.get().await?;
+        let reply: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(value)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_secs)
+            .query_async(&mut conn)
+            .await?;
+        Ok(reply.is_some())
+    }
+
+    async fn set(&self, key: &str, value: &[u8], ttl_secs: u64) -> anyhow::Result<()> {
+        let mut conn = self.pool.get().await?;
+        let (): () = conn.set_ex(key, value, ttl_secs).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let mut conn = self.pool.get().await?;
+        Ok(conn.get(key).await?)
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let mut conn = self.pool.get().await?;
+        let (): () = conn.del(key).await?;
+        Ok(())
+    }
+}
@@ -1,15 +1,32 @@
-use anyhow::Context as _;
-use chrono::Utc;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use sea_orm::{
-    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DatabaseTransaction,
-    EntityTrait, QueryFilter, TransactionTrait,
+    ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, Condition, DatabaseConnection, DatabaseTransaction, EntityTrait, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+    sea_query::{Expr, OnConflict},
 };
+use subtle::ConstantTimeEq;
 use uuid::Uuid;
 
-use madome_auth_schema::{auth_codes, outbox_events, passkeys, users};
+use madome_core::query_metrics::TimedQuery;
+use madome_domain::id::UserId;
+use madome_domain::pagination::{PageRequest, Paged};
 
-use crate::domain::repository::{AuthCodeRepository, PasskeyRepository, UserRepository};
-use crate::domain::types::{AuthCode, AuthUser, OutboxEvent, PasskeyRecord};
+use madome_auth_schema::{
+    api_tokens, auth_codes, auth_daily_stats, id_aliases, linked_identities, login_links,
+    outbox_events, passkeys, sessions, users,
+};
+
+use crate::domain::repository::{
+    ApiTokenRepository, AuthCodeRepository, IdAliasRepository, LinkedIdentityRepository,
+    LoginLinkRepository, OutboxRepository, PasskeyRepository, SessionRepository, StatsRepository,
+    UserRepository,
+};
+use crate::domain::types::{
+    API_TOKEN_TOUCH_INTERVAL_SECS, ApiToken, AuthCode, AuthUser, DailyStats, LinkedIdentity,
+    LoginLink, OutboxEntry, OutboxEvent, OutboxStatus, PasskeyRecord, Session,
+};
 use crate::error::AuthServiceError;
 
 // ── User repository ──────────────────────────────────────────────────────────
@@ -23,24 +40,26 @@ impl UserRepository for DbUserRepository {
     async fn find_by_email(&self, email: &str) -> Result<Option<AuthUser>, AuthServiceError> {
         let model = users::Entity::find()
             .filter(users::Column::Email.eq(email))
+            .filter(users::Column::DeletedAt.is_null())
             .one(&self.db)
-            .await
-            .context("find user by email")?;
+            .timed("find user by email")
+            .await?;
         Ok(model.map(user_from_model))
     }
 
-    async fn find_by_id(&self, id: Uuid) -> Result<Option<AuthUser>, AuthServiceError> {
+    async fn find_by_id(&self, id: UserId) -> Result<Option<AuthUser>, AuthServiceError> {
         let model = users::Entity::find_by_id(id)
+            .filter(users::Column::DeletedAt.is_null())
             .one(&self.db)
-            .await
-            .context("find user by id")?;
+            .timed("find user by id")
+            .await?;
         Ok(model.map(user_from_model))
     }
 }
 
 fn user_from_model(m: users::Model) -> AuthUser {
     AuthUser {
-        id: m.id,
+        id: m.id.into(),
         email: m.email,
         role: m.role as u8,
     }
@@ -54,7 +73,7 @@ pub struct DbAuthCodeRepository {
 }
 
 impl AuthCodeRepository for DbAuthCodeRepository {
-    async fn count_active(&self, user_id: Uuid) -> Result<u64, AuthServiceError> {
+    async fn count_active(&self, user_id: UserId) -> Result<u64, AuthServiceError> {
         use sea_orm::PaginatorTrait;
         let now = Utc::now();
         let count = auth_codes::Entity::find()
@@ -62,8 +81,8 @@ impl AuthCodeRepository for DbAuthCodeRepository {
             .filter(auth_codes::Column::UsedAt.is_null())
             .filter(auth_codes::Column::ExpiresAt.gt(now))
             .count(&self.db)
-            .await
-            .context("count active authcodes")?;
+            .timed("count active authcodes")
+            .await?;
         Ok(count)
     }
 
@@ -77,31 +96,36 @@ impl AuthCodeRepository for DbAuthCodeRepository {
                 let code = code.clone();
                 let event = event.clone();
                 Box::pin(async move {
-                    insert_auth_code(txn, &code).await?;
                     insert_outbox_event(txn, &event).await?;
+                    insert_auth_code(txn, &code).await?;
                     Ok(())
                 })
             })
-            .await
-            .context("create authcode with outbox")?;
+            .timed("create authcode with outbox")
+            .await?;
         Ok(())
     }
 
     async fn find_valid(
         &self,
-        user_id: Uuid,
-        code: &str,
+        user_id: UserId,
+        code_hash: &str,
     ) -> Result<Option<AuthCode>, AuthServiceError> {
         let now = Utc::now();
-        let model = auth_codes::Entity::find()
+        // At most MAX_ACTIVE_AUTHCODES rows; every one is compared so the
+        // time taken does not depend on which (if any) matches.
+        let models = auth_codes::Entity::find()
             .filter(auth_codes::Column::UserId.eq(user_id))
-            .filter(auth_codes::Column::Code.eq(code))
             .filter(auth_codes::Column::UsedAt.is_null())
             .filter(auth_codes::Column::ExpiresAt.gt(now))
-            .one(&self.db)
-            .await
-            .context("find valid authcode")?;
-        Ok(model.map(authcode_from_model))
+            .all(&self.db)
+            .timed("find valid authcode")
+            .await?;
+        let found = models.into_iter().fold(None, |found, m| {
+            let matches: bool = m.code_hash.as_bytes().ct_eq(code_hash.as_bytes()).into();
+            if matches { Some(m) } else { found }
+        });
+        Ok(found.map(authcode_from_model))
     }
 
     async fn mark_used(&self, id: Uuid) -> Result<(), AuthServiceError> {
@@ -112,8 +136,8 @@ impl AuthCodeRepository for DbAuthCodeRepository {
             ..Default::default()
         }
         .update(&self.db)
-        .await
-        .context("mark authcode used")?;
+        .timed("mark authcode used")
+        .await?;
         Ok(())
     }
 }
@@ -125,7 +149,7 @@ async fn insert_auth_code(
     auth_codes::ActiveModel {
         id: Set(code.id),
         user_id: Set(code.user_id),
-        code: Set(code.code.clone()),
+        code_hash: Set(code.code_hash.clone()),
         expires_at: Set(code.expires_at),
         used_at: Set(None),
         created_at: Set(code.created_at),
@@ -157,11 +181,113 @@ async fn insert_outbox_event(
     Ok(())
 }
 
+/// Like [`insert_outbox_event`], but a duplicate idempotency key is a no-op
+/// instead of a unique-constraint error.
+async fn insert_outbox_event_if_absent(
+    txn: &DatabaseTransaction,
+    event: &OutboxEvent,
+) -> Result<(), sea_orm::DbErr> {
+    let now = Utc::now();
+    outbox_events::Entity::insert(outbox_events::ActiveModel {
+        id: Set(event.id),
+        kind: Set(event.kind.clone()),
+        payload: Set(event.payload.clone()),
+        idempotency_key: Set(event.idempotency_key.clone()),
+        attempts: Set(0),
+        last_error: Set(None),
+        created_at: Set(now),
+        next_attempt_at: Set(now),
+        processed_at: Set(None),
+        failed_at: Set(None),
+    })
+    .on_conflict(
+        OnConflict::column(outbox_events::Column::IdempotencyKey)
+            .do_nothing()
+            .to_owned(),
+    )
+    .exec_without_returning(txn)
+    .await?;
+    Ok(())
+}
+
 fn authcode_from_model(m: auth_codes::Model) -> AuthCode {
     AuthCode {
         id: m.id,
         user_id: m.user_id,
-        code: m.code,
+        code_hash: m.code_hash,
+        expires_at: m.expires_at,
+        used_at: m.used_at,
+        created_at: m.created_at,
+    }
+}
+
+// ── Login link repository ────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct DbLoginLinkRepository {
+    pub db: DatabaseConnection,
+}
+
+impl LoginLinkRepository for DbLoginLinkRepository {
+    async fn count_active(&self, user_id: UserId) -> Result<u64, AuthServiceError> {
+        use sea_orm::PaginatorTrait;
+        let now = Utc::now();
+        let count = login_links::Entity::find()
+            .filter(login_links::Column::UserId.eq(user_id))
+            .filter(login_links::Column::UsedAt.is_null())
+            .filter(login_links::Column::ExpiresAt.gt(now))
+            .count(&self.db)
+            .timed("count active login links")
+            .await?;
+        Ok(count)
+    }
+
+    async fn create_with_outbox(
+        &self,
+        link: &LoginLink,
+        event: &OutboxEvent,
+    ) -> Result<(), AuthServiceError> {
+        self.db
+            .transaction::<_, (), sea_orm::DbErr>(|txn| {
+                let link = link.clone();
+                let event = event.clone();
+                Box::pin(async move {
+                    login_links::ActiveModel {
+                        id: Set(link.id),
+                        user_id: Set(link.user_id),
+                        expires_at: Set(link.expires_at),
+                        used_at: Set(None),
+                        created_at: Set(link.created_at),
+                    }
+                    .insert(txn)
+                    .await?;
+                    insert_outbox_event(txn, &event).await?;
+                    Ok(())
+                })
+            })
+            .timed("create login link with outbox")
+            .await?;
+        Ok(())
+    }
+
+    async fn consume(&self, id: Uuid) -> Result<Option<LoginLink>, AuthServiceError> {
+        let now = Utc::now();
+        let models = login_links::Entity::update_many()
+            .col_expr(login_links::Column::UsedAt, Expr::value(now))
+            .filter(login_links::Column::Id.eq(id))
+            .filter(login_links::Column::UsedAt.is_null())
+            .filter(login_links::Column::ExpiresAt.gt(now))
+            .exec_with_returning(&self.db)
+            .timed("consume login link")
+            .await?;
+        Ok(models.into_iter().next().map(login_link_from_model))
+    }
+}
+
+fn login_link_from_model(m: login_links::Model) -> LoginLink {
+    LoginLink {
+        id: m.id,
+        user_id: m.user_id,
         expires_at: m.expires_at,
         used_at: m.used_at,
         created_at: m.created_at,
@@ -173,15 +299,17 @@ fn authcode_from_model(m: auth_codes::Model) -> AuthCode {
 #[derive(Clone)]
 pub struct DbPasskeyRepository {
     pub db: DatabaseConnection,
+    /// Replica for listings; equals `db` when no replica is configured.
+    pub read: DatabaseConnection,
 }
 
 impl PasskeyRepository for DbPasskeyRepository {
-    async fn list_by_user(&self, user_id: Uuid) -> Result<Vec<PasskeyRecord>, AuthServiceError> {
+    async fn list_by_user(&self, user_id: UserId) -> Result<Vec<PasskeyRecord>, AuthServiceError> {
         let models = passkeys::Entity::find()
             .filter(passkeys::Column::UserId.eq(user_id))
-            .all(&self.db)
-            .await
-            .context("list passkeys by user")?;
+            .all(&self.read)
+            .timed("list passkeys by user")
+            .await?;
         Ok(models.into_iter().map(passkey_from_model).collect())
     }
 
@@ -191,32 +319,50 @@ impl PasskeyRepository for DbPasskeyRepository {
     ) -> Result<Option<PasskeyRecord>, AuthServiceError> {
         let model = passkeys::Entity::find_by_id(credential_id.to_vec())
             .one(&self.db)
-            .await
-            .context("find passkey by id")?;
+            .timed("find passkey by id")
+            .await?;
         Ok(model.map(passkey_from_model))
     }
 
     async fn create(&self, record: &PasskeyRecord) -> Result<(), AuthServiceError> {
-        passkeys::ActiveModel {
-            credential_id: Set(record.credential_id.clone()),
-            user_id: Set(record.user_id),
-            aaguid: Set(record.aaguid),
-            credential: Set(record.credential.clone()),
-            created_at: Set(record.created_at),
-        }
-        .insert(&self.db)
-        .await
-        .context("create passkey")?;
+        passkey_model(record)
+            .insert(&self.db)
+            .timed("create passkey")
+            .await?;
+        Ok(())
+    }
+
+    async fn create_with_outbox(
+        &self,
+        record: &PasskeyRecord,
+        event: &OutboxEvent,
+    ) -> Result<(), AuthServiceError> {
+        self.db
+            .transaction::<_, (), sea_orm::DbErr>(|txn| {
+                let model = passkey_model(record);
+                let event = event.clone();
+                Box::pin(async move {
+                    model.insert(txn).await?;
+                    insert_outbox_event_if_absent(txn, &event).await?;
+                    Ok(())
+                })
+            })
+            .timed("create passkey with outbox")
+            .await?;
         Ok(())
     }
 
-    async fn delete(&self, credential_id: &[u8], user_id: Uuid) -> Result<bool, AuthServiceError> {
+    async fn delete(
+        &self,
+        credential_id: &[u8],
+        user_id: UserId,
+    ) -> Result<bool, AuthServiceError> {
         let result = passkeys::Entity::delete_many()
             .filter(passkeys::Column::CredentialId.eq(credential_id.to_vec()))
             .filter(passkeys::Column::UserId.eq(user_id))
             .exec(&self.db)
-            .await
-            .context("delete passkey")?;
+            .timed("delete passkey")
+            .await?;
         Ok(result.rows_affected > 0)
     }
 
@@ -224,25 +370,522 @@ impl PasskeyRepository for DbPasskeyRepository {
         &self,
         credential_id: &[u8],
         credential: &[u8],
+        backup_state: bool,
     ) -> Result<(), AuthServiceError> {
         passkeys::ActiveModel {
             credential_id: Set(credential_id.to_vec()),
             credential: Set(credential.to_vec()),
+            backup_state: Set(backup_state),
             ..Default::default()
         }
         .update(&self.db)
-        .await
-        .context("update passkey credential")?;
+        .timed("update passkey credential")
+        .await?;
         Ok(())
     }
 }
 
+fn passkey_model(record: &PasskeyRecord) -> passkeys::ActiveModel {
+    passkeys::ActiveModel {
+        credential_id: Set(record.credential_id.clone()),
+        user_id: Set(record.user_id),
+        aaguid: Set(record.aaguid),
+        credential: Set(record.credential.clone()),
+        backup_eligible: Set(record.backup_eligible),
+        backup_state: Set(record.backup_state),
+        transports: Set(serde_json::json!(record.transports)),
+        created_at: Set(record.created_at),
+    }
+}
+
 fn passkey_from_model(m: passkeys::Model) -> PasskeyRecord {
     PasskeyRecord {
         credential_id: m.credential_id,
         user_id: m.user_id,
         aaguid: m.aaguid,
         credential: m.credential,
+        backup_eligible: m.backup_eligible,
+        backup_state: m.backup_state,
+        // The column defaults to `[]`; anything else unreadable counts as unknown.
+        transports: serde_json::from_value(m.transports).unwrap_or_default(),
+        created_at: m.created_at,
+    }
+}
+
+// ── Session repository ────────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct DbSessionRepository {
+    pub db: DatabaseConnection,
+    /// Replica for listings; equals `db` when no replica is configured.
+    pub read: DatabaseConnection,
+}
+
+impl SessionRepository for DbSessionRepository {
+    async fn create(&self, session: &Session) -> Result<(), AuthServiceError> {
+        sessions::ActiveModel {
+            id: Set(session.id),
+            user_id: Set(session.user_id),
+            user_agent: Set(session.user_agent.clone()),
+            ip: Set(session.ip.clone()),
+            created_at: Set(session.created_at),
+            last_used_at: Set(session.last_used_at),
+        }
+        .insert(&self.db)
+        .timed("create session")
+        .await?;
+        Ok(())
+    }
+
+    async fn create_with_outbox(
+        &self,
+        session: &Session,
+        event: &OutboxEvent,
+    ) -> Result<(), AuthServiceError> {
+        self.db
+            .transaction::<_, (), sea_orm::DbErr>(|txn| {
+                let session = session.clone();
+                let event = event.clone();
+                Box::pin(async move {
+                    insert_session(txn, &session).await?;
+                    insert_outbox_event_if_absent(txn, &event).await?;
+                    Ok(())
+                })
+            })
+            .timed("create session with outbox")
+            .await?;
+        Ok(())
+    }
+
+    async fn find_by_id(
+        &self,
+        id: Uuid,
+        user_id: UserId,
+    ) -> Result<Option<Session>, AuthServiceError> {
+        let model = sessions::Entity::find_by_id(id)
+            .filter(sessions::Column::UserId.eq(user_id))
+            .one(&self.db)
+            .timed("find session by id")
+            .await?;
+        Ok(model.map(session_from_model))
+    }
+
+    async fn list_by_user(&self, user_id: UserId) -> Result<Vec<Session>, AuthServiceError> {
+        let models = sessions::Entity::find()
+            .filter(sessions::Column::UserId.eq(user_id))
+            .order_by_desc(sessions::Column::LastUsedAt)
+            .all(&self.read)
+            .timed("list sessions by user")
+            .await?;
+        Ok(models.into_iter().map(session_from_model).collect())
+    }
+
+    async fn touch(&self, id: Uuid) -> Result<(), AuthServiceError> {
+        sessions::ActiveModel {
+            id: Set(id),
+            last_used_at: Set(Utc::now()),
+            ..Default::default()
+        }
+        .update(&self.db)
+        .timed("touch session")
+        .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, id: Uuid, user_id: UserId) -> Result<bool, AuthServiceError> {
+        let result = sessions::Entity::delete_many()
+            .filter(sessions::Column::Id.eq(id))
+            .filter(sessions::Column::UserId.eq(user_id))
+            .exec(&self.db)
+            .timed("delete session")
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+}
+
+async fn insert_session(
+    txn: &DatabaseTransaction,
+    session: &Session,
+) -> Result<(), sea_orm::DbErr> {
+    sessions::ActiveModel {
+        id: Set(session.id),
+        user_id: Set(session.user_id),
+        user_agent: Set(session.user_agent.clone()),
+        ip: Set(session.ip.clone()),
+        created_at: Set(session.created_at),
+        last_used_at: Set(session.last_used_at),
+    }
+    .insert(txn)
+    .await?;
+    Ok(())
+}
+
+fn session_from_model(m: sessions::Model) -> Session {
+    Session {
+        id: m.id,
+        user_id: m.user_id,
+        user_agent: m.user_agent,
+        ip: m.ip,
+        created_at: m.created_at,
+        last_used_at: m.last_used_at,
+    }
+}
+
+// ── API token repository ──────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct DbApiTokenRepository {
+    pub db: DatabaseConnection,
+    /// Replica for listings; equals `db` when no replica is configured.
+    pub read: DatabaseConnection,
+}
+
+impl ApiTokenRepository for DbApiTokenRepository {
+    async fn count_by_user(&self, user_id: UserId) -> Result<u64, AuthServiceError> {
+        let count = api_tokens::Entity::find()
+            .filter(api_tokens::Column::UserId.eq(user_id))
+            .count(&self.db)
+            .timed("count api tokens by user")
+            .await?;
+        Ok(count)
+    }
+
+    async fn create(&self, token: &ApiToken) -> Result<(), AuthServiceError> {
+        api_tokens::ActiveModel {
+            id: Set(token.id),
+            user_id: Set(token.user_id),
+            name: Set(token.name.clone()),
+            secret_hash: Set(token.secret_hash.clone()),
+            scopes: Set(serde_json::json!(token.scopes)),
+            created_at: Set(token.created_at),
+            last_used_at: Set(token.last_used_at),
+        }
+        .insert(&self.db)
+        .timed("create api token")
+        .await?;
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<ApiToken>, AuthServiceError> {
+        // Primary, so a revoked token stops working at once.
+        let model = api_tokens::Entity::find_by_id(id)
+            .one(&self.db)
+            .timed("find api token by id")
+            .await?;
+        Ok(model.map(api_token_from_model))
+    }
+
+    async fn list_by_user(&self, user_id: UserId) -> Result<Vec<ApiToken>, AuthServiceError> {
+        let models = api_tokens::Entity::find()
+            .filter(api_tokens::Column::UserId.eq(user_id))
+            .order_by_desc(api_tokens::Column::CreatedAt)
+            .all(&self.read)
+            .timed("list api tokens by user")
+            .await?;
+        Ok(models.into_iter().map(api_token_from_model).collect())
+    }
+
+    async fn touch(&self, id: Uuid) -> Result<(), AuthServiceError> {
+        let now = Utc::now();
+        let stale = now - chrono::Duration::seconds(API_TOKEN_TOUCH_INTERVAL_SECS);
+        api_tokens::Entity::update_many()
+            .col_expr(api_tokens::Column::LastUsedAt, Expr::value(now))
+            .filter(api_tokens::Column::Id.eq(id))
+            .filter(
+                Condition::any()
+                    .add(api_tokens::Column::LastUsedAt.is_null())
+                    .add(api_tokens::Column::LastUsedAt.lt(stale)),
+            )
+            .exec(&self.db)
+            .timed("touch api token")
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, id: Uuid, user_id: UserId) -> Result<bool, AuthServiceError> {
+        let result = api_tokens::Entity::delete_many()
+            .filter(api_tokens::Column::Id.eq(id))
+            .filter(api_tokens::Column::UserId.eq(user_id))
+            .exec(&self.db)
+            .timed("delete api token")
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+}
+
+fn api_token_from_model(m: api_tokens::Model) -> ApiToken {
+    ApiToken {
+        id: m.id,
+        user_id: m.user_id,
+        name: m.name,
+        secret_hash: m.secret_hash,
+        // Written from a `Vec<String>`; anything else unreadable grants nothing.
+        scopes: serde_json::from_value(m.scopes).unwrap_or_default(),
+        created_at: m.created_at,
+        last_used_at: m.last_used_at,
+    }
+}
+
+// ── Stats repository ─────────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct DbStatsRepository {
+    pub db: DatabaseConnection,
+    /// Replica for the dashboard; equals `db` when no replica is configured.
+    pub read: DatabaseConnection,
+}
+
+impl StatsRepository for DbStatsRepository {
+    async fn rollup(&self, day: NaiveDate) -> Result<DailyStats, AuthServiceError> {
+        let start = day.and_time(NaiveTime::MIN).and_utc();
+        let end = start + chrono::Duration::days(1);
+        let active_users = sessions::Entity::find()
+            .select_only()
+            .column(sessions::Column::UserId)
+            .distinct()
+            .filter(sessions::Column::LastUsedAt.gte(start))
+            .filter(sessions::Column::LastUsedAt.lt(end))
+            .count(&self.db)
+            .timed("count active users of day")
+            .await?;
+        let sign_ins = sessions::Entity::find()
+            .filter(sessions::Column::CreatedAt.gte(start))
+            .filter(sessions::Column::CreatedAt.lt(end))
+            .count(&self.db)
+            .timed("count sign-ins of day")
+            .await?;
+
+        let stats = self
+            .db
+            .transaction::<_, DailyStats, sea_orm::DbErr>(|txn| {
+                Box::pin(async move {
+                    // Create the row first so concurrent rollups queue on its lock.
+                    auth_daily_stats::Entity::insert(auth_daily_stats::ActiveModel {
+                        day: Set(day),
+                        active_users: Set(0),
+                        sign_ins: Set(0),
+                        updated_at: Set(Utc::now()),
+                    })
+                    .on_conflict(
+                        OnConflict::column(auth_daily_stats::Column::Day)
+                            .do_nothing()
+                            .to_owned(),
+                    )
+                    .exec_without_returning(txn)
+                    .await?;
+                    let stored = auth_daily_stats::Entity::find_by_id(day)
+                        .lock_exclusive()
+                        .one(txn)
+                        .await?
+                        .ok_or_else(|| sea_orm::DbErr::RecordNotFound(day.to_string()))?;
+                    let stats = DailyStats {
+                        day,
+                        active_users: active_users.max(stored.active_users as u64),
+                        sign_ins: sign_ins.max(stored.sign_ins as u64),
+                    };
+                    auth_daily_stats::ActiveModel {
+                        day: Set(day),
+                        active_users: Set(stats.active_users as i64),
+                        sign_ins: Set(stats.sign_ins as i64),
+                        updated_at: Set(Utc::now()),
+                    }
+                    .update(txn)
+                    .await?;
+                    Ok(stats)
+                })
+            })
+            .timed("store daily stats")
+            .await?;
+        Ok(stats)
+    }
+
+    async fn list(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DailyStats>, AuthServiceError> {
+        let models = auth_daily_stats::Entity::find()
+            .filter(auth_daily_stats::Column::Day.gte(from))
+            .filter(auth_daily_stats::Column::Day.lte(to))
+            .order_by_asc(auth_daily_stats::Column::Day)
+            .all(&self.read)
+            .timed("list daily stats")
+            .await?;
+        Ok(models
+            .into_iter()
+            .map(|m| DailyStats {
+                day: m.day,
+                active_users: m.active_users as u64,
+                sign_ins: m.sign_ins as u64,
+            })
+            .collect())
+    }
+}
+
+// ── Linked identity repository ────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct DbLinkedIdentityRepository {
+    pub db: DatabaseConnection,
+}
+
+impl LinkedIdentityRepository for DbLinkedIdentityRepository {
+    async fn find_by_subject(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<LinkedIdentity>, AuthServiceError> {
+        let model = linked_identities::Entity::find()
+            .filter(linked_identities::Column::Provider.eq(provider))
+            .filter(linked_identities::Column::Subject.eq(subject))
+            .one(&self.db)
+            .timed("find linked identity by subject")
+            .await?;
+        Ok(model.map(linked_identity_from_model))
+    }
+
+    async fn list_by_user(&self, user_id: UserId) -> Result<Vec<LinkedIdentity>, AuthServiceError> {
+        let models = linked_identities::Entity::find()
+            .filter(linked_identities::Column::UserId.eq(user_id))
+            .order_by_asc(linked_identities::Column::CreatedAt)
+            .all(&self.db)
+            .timed("list linked identities by user")
+            .await?;
+        Ok(models.into_iter().map(linked_identity_from_model).collect())
+    }
+
+    async fn create(&self, identity: &LinkedIdentity) -> Result<(), AuthServiceError> {
+        linked_identities::ActiveModel {
+            id: Set(identity.id),
+            user_id: Set(identity.user_id),
+            provider: Set(identity.provider.clone()),
+            subject: Set(identity.subject.clone()),
+            email: Set(identity.email.clone()),
+            created_at: Set(identity.created_at),
+        }
+        .insert(&self.db)
+        .timed("create linked identity")
+        .await?;
+        Ok(())
+    }
+}
+
+fn linked_identity_from_model(m: linked_identities::Model) -> LinkedIdentity {
+    LinkedIdentity {
+        id: m.id,
+        user_id: m.user_id,
+        provider: m.provider,
+        subject: m.subject,
+        email: m.email,
+        created_at: m.created_at,
+    }
+}
+
+// ── Id alias repository ──────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct DbIdAliasRepository {
+    pub read: DatabaseConnection,
+}
+
+impl IdAliasRepository for DbIdAliasRepository {
+    async fn resolve(&self, legacy_id: i64) -> Result<Option<UserId>, AuthServiceError> {
+        // Aliases never change, so replica lag only matters right after a backfill.
+        let alias = id_aliases::Entity::find_by_id(legacy_id)
+            .inner_join(users::Entity)
+            .filter(users::Column::DeletedAt.is_null())
+            .one(&self.read)
+            .timed("resolve legacy id")
+            .await?;
+        Ok(alias.map(|a| a.user_id))
+    }
+}
+
+// ── Outbox repository ────────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct DbOutboxRepository {
+    pub db: DatabaseConnection,
+    pub read: DatabaseConnection,
+}
+
+impl OutboxRepository for DbOutboxRepository {
+    async fn list(
+        &self,
+        status: OutboxStatus,
+        page: PageRequest,
+    ) -> Result<Paged<OutboxEntry>, AuthServiceError> {
+        let query = outbox_events::Entity::find();
+        let query = match status {
+            OutboxStatus::Pending => query
+                .filter(outbox_events::Column::ProcessedAt.is_null())
+                .filter(outbox_events::Column::FailedAt.is_null()),
+            OutboxStatus::Processed => query
+                .filter(outbox_events::Column::ProcessedAt.is_not_null())
+                .filter(outbox_events::Column::FailedAt.is_null()),
+            OutboxStatus::Failed => query.filter(outbox_events::Column::FailedAt.is_not_null()),
+        };
+        let total = query
+            .clone()
+            .count(&self.read)
+            .timed("count outbox events")
+            .await?;
+        let models = query
+            .order_by_desc(outbox_events::Column::CreatedAt)
+            .offset(page.offset())
+            .limit(u64::from(page.per_page))
+            .all(&self.read)
+            .timed("list outbox events")
+            .await?;
+        Ok(Paged::new(
+            models.into_iter().map(outbox_entry_from_model).collect(),
+            total,
+        ))
+    }
+
+    async fn retry_failed(&self, id: Uuid) -> Result<bool, AuthServiceError> {
+        let result = outbox_events::Entity::update_many()
+            .col_expr(
+                outbox_events::Column::FailedAt,
+                Expr::value(None::<DateTime<Utc>>),
+            )
+            .col_expr(outbox_events::Column::Attempts, Expr::value(0))
+            .col_expr(
+                outbox_events::Column::NextAttemptAt,
+                Expr::value(Utc::now()),
+            )
+            .filter(outbox_events::Column::Id.eq(id))
+            .filter(outbox_events::Column::FailedAt.is_not_null())
+            .exec(&self.db)
+            .timed("retry outbox event")
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    async fn delete_failed(&self, id: Uuid) -> Result<bool, AuthServiceError> {
+        let result = outbox_events::Entity::delete_many()
+            .filter(outbox_events::Column::Id.eq(id))
+            .filter(outbox_events::Column::FailedAt.is_not_null())
+            .exec(&self.db)
+            .timed("delete outbox event")
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+}
+
+fn outbox_entry_from_model(m: outbox_events::Model) -> OutboxEntry {
+    OutboxEntry {
+        event: OutboxEvent {
+            id: m.id,
+            kind: m.kind,
+            payload: m.payload,
+            idempotency_key: m.idempotency_key,
+        },
+        attempts: m.attempts.max(0) as u32,
+        last_error: m.last_error,
         created_at: m.created_at,
+        next_attempt_at: m.next_attempt_at,
+        processed_at: m.processed_at,
+        failed_at: m.failed_at,
     }
 }
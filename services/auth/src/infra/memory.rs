@@ -0,0 +1,812 @@
+//! In-memory implementations of the repository and cache traits, for running
+//! use cases and tests without Postgres or Redis.
+//!
+//! Every repository handed out by an [`InMemoryStore`] shares its tables, so an
+//! outbox event written by `create_with_outbox` shows up in
+//! [`InMemoryOutboxRepository`]. Behavior the use cases rely on follows the
+//! database: soft-deleted users are unknown, primary and unique keys are
+//! enforced, listings come in the same order. Redis expiry is not modelled;
+//! cached states and failure counters live until taken or cleared.
+//!
+//! Behind the `test-util` feature.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::anyhow;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use uuid::Uuid;
+
+use madome_domain::id::UserId;
+use madome_domain::pagination::{PageRequest, Paged};
+
+use crate::domain::repository::{
+    ApiTokenRepository, AuthCodeAttemptStore, AuthCodeRepository, IdAliasRepository,
+    LinkedIdentityRepository, LoginLinkRepository, OAuthStateCache, OutboxRepository, PasskeyCache,
+    PasskeyRepository, SessionRepository, StatsRepository, UserRepository,
+};
+use crate::domain::types::{
+    API_TOKEN_TOUCH_INTERVAL_SECS, ApiToken, AuthCode, AuthUser, DailyStats, LinkedIdentity,
+    LoginLink, OutboxEntry, OutboxEvent, OutboxStatus, PasskeyRecord, Session,
+};
+use crate::error::AuthServiceError;
+
+/// The service's tables and caches. Cheap to clone; clones share the data.
+#[derive(Clone, Default)]
+pub struct InMemoryStore {
+    tables: Arc<Mutex<Tables>>,
+}
+
+#[derive(Default)]
+struct Tables {
+    /// With the soft-delete flag.
+    users: Vec<(AuthUser, bool)>,
+    auth_codes: Vec<AuthCode>,
+    auth_code_failures: HashMap<UserId, u32>,
+    passkeys: Vec<PasskeyRecord>,
+    login_links: Vec<LoginLink>,
+    sessions: Vec<Session>,
+    api_tokens: Vec<ApiToken>,
+    linked_identities: Vec<LinkedIdentity>,
+    id_aliases: HashMap<i64, UserId>,
+    daily_stats: BTreeMap<NaiveDate, DailyStats>,
+    /// Oldest first.
+    outbox: Vec<OutboxEntry>,
+    oauth_states: HashMap<String, Vec<u8>>,
+    registration_states: HashMap<(UserId, String), Vec<u8>>,
+    authentication_states: HashMap<(String, String), Vec<u8>>,
+}
+
+impl Tables {
+    /// `if_absent` skips an event whose idempotency key is taken; otherwise
+    /// that is an error, as the unique index makes it.
+    fn insert_outbox_event(
+        &mut self,
+        event: &OutboxEvent,
+        if_absent: bool,
+    ) -> Result<(), AuthServiceError> {
+        if self
+            .outbox
+            .iter()
+            .any(|e| e.event.idempotency_key == event.idempotency_key)
+        {
+            return if if_absent {
+                Ok(())
+            } else {
+                Err(duplicate("outbox idempotency key"))
+            };
+        }
+        let now = Utc::now();
+        self.outbox.push(OutboxEntry {
+            event: event.clone(),
+            attempts: 0,
+            last_error: None,
+            created_at: now,
+            next_attempt_at: now,
+            processed_at: None,
+            failed_at: None,
+        });
+        Ok(())
+    }
+
+    fn insert_session(&mut self, session: &Session) -> Result<(), AuthServiceError> {
+        if self.sessions.iter().any(|s| s.id == session.id) {
+            return Err(duplicate("session id"));
+        }
+        self.sessions.push(session.clone());
+        Ok(())
+    }
+
+    fn insert_passkey(&mut self, record: &PasskeyRecord) -> Result<(), AuthServiceError> {
+        if self
+            .passkeys
+            .iter()
+            .any(|p| p.credential_id == record.credential_id)
+        {
+            return Err(duplicate("passkey credential id"));
+        }
+        self.passkeys.push(record.clone());
+        Ok(())
+    }
+}
+
+fn duplicate(key: &str) -> AuthServiceError {
+    AuthServiceError::Internal(anyhow!("duplicate {key}"))
+}
+
+fn is_active(used_at: Option<DateTime<Utc>>, expires_at: DateTime<Utc>) -> bool {
+    used_at.is_none() && expires_at > Utc::now()
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Tables> {
+        self.tables.lock().unwrap()
+    }
+
+    /// Add a user, as signing up through the users service would.
+    pub fn add_user(&self, user: AuthUser) {
+        self.lock().users.push((user, false));
+    }
+
+    /// Soft-delete a user: lookups treat them as unknown from now on.
+    pub fn delete_user(&self, id: UserId) {
+        for (user, deleted) in &mut self.lock().users {
+            if user.id == id {
+                *deleted = true;
+            }
+        }
+    }
+
+    /// Map a legacy numeric id, as the legacy import does.
+    pub fn add_id_alias(&self, legacy_id: i64, user_id: UserId) {
+        self.lock().id_aliases.insert(legacy_id, user_id);
+    }
+
+    /// Every stored outbox event, oldest first.
+    pub fn outbox(&self) -> Vec<OutboxEntry> {
+        self.lock().outbox.clone()
+    }
+
+    /// Dead-letter an outbox event, as the worker does after its last retry.
+    pub fn fail_outbox_event(&self, id: Uuid, error: &str) {
+        for entry in &mut self.lock().outbox {
+            if entry.event.id == id {
+                entry.attempts += 1;
+                entry.last_error = Some(error.to_owned());
+                entry.failed_at = Some(Utc::now());
+            }
+        }
+    }
+
+    pub fn user_repo(&self) -> InMemoryUserRepository {
+        InMemoryUserRepository(self.clone())
+    }
+
+    pub fn auth_code_repo(&self) -> InMemoryAuthCodeRepository {
+        InMemoryAuthCodeRepository(self.clone())
+    }
+
+    pub fn auth_code_attempts(&self) -> InMemoryAuthCodeAttemptStore {
+        InMemoryAuthCodeAttemptStore(self.clone())
+    }
+
+    pub fn passkey_repo(&self) -> InMemoryPasskeyRepository {
+        InMemoryPasskeyRepository(self.clone())
+    }
+
+    pub fn login_link_repo(&self) -> InMemoryLoginLinkRepository {
+        InMemoryLoginLinkRepository(self.clone())
+    }
+
+    pub fn session_repo(&self) -> InMemorySessionRepository {
+        InMemorySessionRepository(self.clone())
+    }
+
+    pub fn api_token_repo(&self) -> InMemoryApiTokenRepository {
+        InMemoryApiTokenRepository(self.clone())
+    }
+
+    pub fn linked_identity_repo(&self) -> InMemoryLinkedIdentityRepository {
+        InMemoryLinkedIdentityRepository(self.clone())
+    }
+
+    pub fn id_alias_repo(&self) -> InMemoryIdAliasRepository {
+        InMemoryIdAliasRepository(self.clone())
+    }
+
+    pub fn outbox_repo(&self) -> InMemoryOutboxRepository {
+        InMemoryOutboxRepository(self.clone())
+    }
+
+    pub fn stats_repo(&self) -> InMemoryStatsRepository {
+        InMemoryStatsRepository(self.clone())
+    }
+
+    pub fn oauth_state_cache(&self) -> InMemoryOAuthStateCache {
+        InMemoryOAuthStateCache(self.clone())
+    }
+
+    pub fn passkey_cache(&self) -> InMemoryPasskeyCache {
+        InMemoryPasskeyCache(self.clone())
+    }
+}
+
+// ── User repository ──────────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct InMemoryUserRepository(InMemoryStore);
+
+impl InMemoryUserRepository {
+    fn find(&self, matches: impl Fn(&AuthUser) -> bool) -> Option<AuthUser> {
+        self.0
+            .lock()
+            .users
+            .iter()
+            .find(|(user, deleted)| !deleted && matches(user))
+            .map(|(user, _)| user.clone())
+    }
+}
+
+impl UserRepository for InMemoryUserRepository {
+    async fn find_by_email(&self, email: &str) -> Result<Option<AuthUser>, AuthServiceError> {
+        Ok(self.find(|u| u.email == email))
+    }
+
+    async fn find_by_id(&self, id: UserId) -> Result<Option<AuthUser>, AuthServiceError> {
+        Ok(self.find(|u| u.id == id))
+    }
+}
+
+// ── AuthCode repository ──────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct InMemoryAuthCodeRepository(InMemoryStore);
+
+impl AuthCodeRepository for InMemoryAuthCodeRepository {
+    async fn count_active(&self, user_id: UserId) -> Result<u64, AuthServiceError> {
+        let tables = self.0.lock();
+        let count = tables
+            .auth_codes
+            .iter()
+            .filter(|c| c.user_id == user_id && is_active(c.used_at, c.expires_at))
+            .count();
+        Ok(count as u64)
+    }
+
+    async fn create_with_outbox(
+        &self,
+        code: &AuthCode,
+        event: &OutboxEvent,
+    ) -> Result<(), AuthServiceError> {
+        let mut tables = self.0.lock();
+        if tables.auth_codes.iter().any(|c| c.id == code.id) {
+            return Err(duplicate("auth code id"));
+        }
+        tables.insert_outbox_event(event, false)?;
+        tables.auth_codes.push(code.clone());
+        Ok(())
+    }
+
+    async fn find_valid(
+        &self,
+        user_id: UserId,
+        code_hash: &str,
+    ) -> Result<Option<AuthCode>, AuthServiceError> {
+        let tables = self.0.lock();
+        Ok(tables
+            .auth_codes
+            .iter()
+            .find(|c| {
+                c.user_id == user_id
+                    && c.code_hash == code_hash
+                    && is_active(c.used_at, c.expires_at)
+            })
+            .cloned())
+    }
+
+    async fn mark_used(&self, id: Uuid) -> Result<(), AuthServiceError> {
+        let mut tables = self.0.lock();
+        let code = tables
+            .auth_codes
+            .iter_mut()
+            .find(|c| c.id == id)
+            .ok_or_else(|| AuthServiceError::Internal(anyhow!("no auth code {id}")))?;
+        code.used_at = Some(Utc::now());
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct InMemoryAuthCodeAttemptStore(InMemoryStore);
+
+impl AuthCodeAttemptStore for InMemoryAuthCodeAttemptStore {
+    async fn failures(&self, user_id: UserId) -> Result<u32, AuthServiceError> {
+        let tables = self.0.lock();
+        Ok(tables
+            .auth_code_failures
+            .get(&user_id)
+            .copied()
+            .unwrap_or(0))
+    }
+
+    async fn record_failure(&self, user_id: UserId) -> Result<u32, AuthServiceError> {
+        let mut tables = self.0.lock();
+        let count = tables.auth_code_failures.entry(user_id).or_insert(0);
+        *count += 1;
+        Ok(*count)
+    }
+
+    async fn clear(&self, user_id: UserId) -> Result<(), AuthServiceError> {
+        self.0.lock().auth_code_failures.remove(&user_id);
+        Ok(())
+    }
+}
+
+// ── Passkey repository ───────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct InMemoryPasskeyRepository(InMemoryStore);
+
+impl PasskeyRepository for InMemoryPasskeyRepository {
+    async fn list_by_user(&self, user_id: UserId) -> Result<Vec<PasskeyRecord>, AuthServiceError> {
+        let tables = self.0.lock();
+        Ok(tables
+            .passkeys
+            .iter()
+            .filter(|p| p.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn find_by_id(
+        &self,
+        credential_id: &[u8],
+    ) -> Result<Option<PasskeyRecord>, AuthServiceError> {
+        let tables = self.0.lock();
+        Ok(tables
+            .passkeys
+            .iter()
+            .find(|p| p.credential_id == credential_id)
+            .cloned())
+    }
+
+    async fn create(&self, record: &PasskeyRecord) -> Result<(), AuthServiceError> {
+        self.0.lock().insert_passkey(record)
+    }
+
+    async fn create_with_outbox(
+        &self,
+        record: &PasskeyRecord,
+        event: &OutboxEvent,
+    ) -> Result<(), AuthServiceError> {
+        let mut tables = self.0.lock();
+        tables.insert_passkey(record)?;
+        tables.insert_outbox_event(event, true)
+    }
+
+    async fn delete(
+        &self,
+        credential_id: &[u8],
+        user_id: UserId,
+    ) -> Result<bool, AuthServiceError> {
+        let mut tables = self.0.lock();
+        let before = tables.passkeys.len();
+        tables
+            .passkeys
+            .retain(|p| !(p.credential_id == credential_id && p.user_id == user_id));
+        Ok(tables.passkeys.len() < before)
+    }
+
+    async fn update_credential(
+        &self,
+        credential_id: &[u8],
+        credential: &[u8],
+        backup_state: bool,
+    ) -> Result<(), AuthServiceError> {
+        let mut tables = self.0.lock();
+        let passkey = tables
+            .passkeys
+            .iter_mut()
+            .find(|p| p.credential_id == credential_id)
+            .ok_or_else(|| AuthServiceError::Internal(anyhow!("no such passkey")))?;
+        passkey.credential = credential.to_vec();
+        passkey.backup_state = backup_state;
+        Ok(())
+    }
+}
+
+// ── Login link repository ────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct InMemoryLoginLinkRepository(InMemoryStore);
+
+impl LoginLinkRepository for InMemoryLoginLinkRepository {
+    async fn count_active(&self, user_id: UserId) -> Result<u64, AuthServiceError> {
+        let tables = self.0.lock();
+        let count = tables
+            .login_links
+            .iter()
+            .filter(|l| l.user_id == user_id && is_active(l.used_at, l.expires_at))
+            .count();
+        Ok(count as u64)
+    }
+
+    async fn create_with_outbox(
+        &self,
+        link: &LoginLink,
+        event: &OutboxEvent,
+    ) -> Result<(), AuthServiceError> {
+        let mut tables = self.0.lock();
+        if tables.login_links.iter().any(|l| l.id == link.id) {
+            return Err(duplicate("login link id"));
+        }
+        tables.insert_outbox_event(event, false)?;
+        tables.login_links.push(link.clone());
+        Ok(())
+    }
+
+    async fn consume(&self, id: Uuid) -> Result<Option<LoginLink>, AuthServiceError> {
+        let mut tables = self.0.lock();
+        let link = tables
+            .login_links
+            .iter_mut()
+            .find(|l| l.id == id && is_active(l.used_at, l.expires_at));
+        Ok(link.map(|l| {
+            l.used_at = Some(Utc::now());
+            l.clone()
+        }))
+    }
+}
+
+// ── Session repository ───────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct InMemorySessionRepository(InMemoryStore);
+
+impl SessionRepository for InMemorySessionRepository {
+    async fn create(&self, session: &Session) -> Result<(), AuthServiceError> {
+        self.0.lock().insert_session(session)
+    }
+
+    async fn create_with_outbox(
+        &self,
+        session: &Session,
+        event: &OutboxEvent,
+    ) -> Result<(), AuthServiceError> {
+        let mut tables = self.0.lock();
+        tables.insert_session(session)?;
+        tables.insert_outbox_event(event, true)
+    }
+
+    async fn find_by_id(
+        &self,
+        id: Uuid,
+        user_id: UserId,
+    ) -> Result<Option<Session>, AuthServiceError> {
+        let tables = self.0.lock();
+        Ok(tables
+            .sessions
+            .iter()
+            .find(|s| s.id == id && s.user_id == user_id)
+            .cloned())
+    }
+
+    async fn list_by_user(&self, user_id: UserId) -> Result<Vec<Session>, AuthServiceError> {
+        let tables = self.0.lock();
+        let mut sessions: Vec<Session> = tables
+            .sessions
+            .iter()
+            .filter(|s| s.user_id == user_id)
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.last_used_at));
+        Ok(sessions)
+    }
+
+    async fn touch(&self, id: Uuid) -> Result<(), AuthServiceError> {
+        let mut tables = self.0.lock();
+        let session = tables
+            .sessions
+            .iter_mut()
+            .find(|s| s.id == id)
+            .ok_or_else(|| AuthServiceError::Internal(anyhow!("no session {id}")))?;
+        session.last_used_at = Utc::now();
+        Ok(())
+    }
+
+    async fn delete(&self, id: Uuid, user_id: UserId) -> Result<bool, AuthServiceError> {
+        let mut tables = self.0.lock();
+        let before = tables.sessions.len();
+        tables
+            .sessions
+            .retain(|s| !(s.id == id && s.user_id == user_id));
+        Ok(tables.sessions.len() < before)
+    }
+}
+
+// ── API token repository ─────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct InMemoryApiTokenRepository(InMemoryStore);
+
+impl ApiTokenRepository for InMemoryApiTokenRepository {
+    async fn count_by_user(&self, user_id: UserId) -> Result<u64, AuthServiceError> {
+        let tables = self.0.lock();
+        Ok(tables
+            .api_tokens
+            .iter()
+            .filter(|t| t.user_id == user_id)
+            .count() as u64)
+    }
+
+    async fn create(&self, token: &ApiToken) -> Result<(), AuthServiceError> {
+        let mut tables = self.0.lock();
+        if tables.api_tokens.iter().any(|t| t.id == token.id) {
+            return Err(duplicate("api token id"));
+        }
+        tables.api_tokens.push(token.clone());
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<ApiToken>, AuthServiceError> {
+        let tables = self.0.lock();
+        Ok(tables.api_tokens.iter().find(|t| t.id == id).cloned())
+    }
+
+    async fn list_by_user(&self, user_id: UserId) -> Result<Vec<ApiToken>, AuthServiceError> {
+        let tables = self.0.lock();
+        let mut tokens: Vec<ApiToken> = tables
+            .api_tokens
+            .iter()
+            .filter(|t| t.user_id == user_id)
+            .cloned()
+            .collect();
+        tokens.sort_by_key(|t| std::cmp::Reverse(t.created_at));
+        Ok(tokens)
+    }
+
+    async fn touch(&self, id: Uuid) -> Result<(), AuthServiceError> {
+        let now = Utc::now();
+        let stale = now - chrono::Duration::seconds(API_TOKEN_TOUCH_INTERVAL_SECS);
+        let mut tables = self.0.lock();
+        if let Some(token) = tables.api_tokens.iter_mut().find(|t| t.id == id) {
+            if token.last_used_at.is_none_or(|at| at < stale) {
+                token.last_used_at = Some(now);
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, id: Uuid, user_id: UserId) -> Result<bool, AuthServiceError> {
+        let mut tables = self.0.lock();
+        let before = tables.api_tokens.len();
+        tables
+            .api_tokens
+            .retain(|t| !(t.id == id && t.user_id == user_id));
+        Ok(tables.api_tokens.len() < before)
+    }
+}
+
+// ── Stats repository ─────────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct InMemoryStatsRepository(InMemoryStore);
+
+impl StatsRepository for InMemoryStatsRepository {
+    async fn rollup(&self, day: NaiveDate) -> Result<DailyStats, AuthServiceError> {
+        let start = day.and_time(NaiveTime::MIN).and_utc();
+        let end = start + chrono::Duration::days(1);
+        let mut tables = self.0.lock();
+        let active: HashSet<UserId> = tables
+            .sessions
+            .iter()
+            .filter(|s| s.last_used_at >= start && s.last_used_at < end)
+            .map(|s| s.user_id)
+            .collect();
+        let sign_ins = tables
+            .sessions
+            .iter()
+            .filter(|s| s.created_at >= start && s.created_at < end)
+            .count() as u64;
+
+        let stats = tables.daily_stats.entry(day).or_insert(DailyStats {
+            day,
+            active_users: 0,
+            sign_ins: 0,
+        });
+        stats.active_users = stats.active_users.max(active.len() as u64);
+        stats.sign_ins = stats.sign_ins.max(sign_ins);
+        Ok(stats.clone())
+    }
+
+    async fn list(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DailyStats>, AuthServiceError> {
+        let tables = self.0.lock();
+        Ok(tables
+            .daily_stats
+            .range(from..=to)
+            .map(|(_, s)| s.clone())
+            .collect())
+    }
+}
+
+// ── Linked identity repository ───────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct InMemoryLinkedIdentityRepository(InMemoryStore);
+
+impl LinkedIdentityRepository for InMemoryLinkedIdentityRepository {
+    async fn find_by_subject(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<LinkedIdentity>, AuthServiceError> {
+        let tables = self.0.lock();
+        Ok(tables
+            .linked_identities
+            .iter()
+            .find(|i| i.provider == provider && i.subject == subject)
+            .cloned())
+    }
+
+    async fn list_by_user(&self, user_id: UserId) -> Result<Vec<LinkedIdentity>, AuthServiceError> {
+        let tables = self.0.lock();
+        let mut identities: Vec<LinkedIdentity> = tables
+            .linked_identities
+            .iter()
+            .filter(|i| i.user_id == user_id)
+            .cloned()
+            .collect();
+        identities.sort_by_key(|i| i.created_at);
+        Ok(identities)
+    }
+
+    async fn create(&self, identity: &LinkedIdentity) -> Result<(), AuthServiceError> {
+        let mut tables = self.0.lock();
+        if tables.linked_identities.iter().any(|i| {
+            i.id == identity.id
+                || (i.provider == identity.provider && i.subject == identity.subject)
+        }) {
+            return Err(duplicate("linked identity"));
+        }
+        tables.linked_identities.push(identity.clone());
+        Ok(())
+    }
+}
+
+// ── Id alias repository ──────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct InMemoryIdAliasRepository(InMemoryStore);
+
+impl IdAliasRepository for InMemoryIdAliasRepository {
+    async fn resolve(&self, legacy_id: i64) -> Result<Option<UserId>, AuthServiceError> {
+        let tables = self.0.lock();
+        let user_id = tables.id_aliases.get(&legacy_id).copied();
+        let live = |id| {
+            tables
+                .users
+                .iter()
+                .any(|(u, deleted)| u.id == id && !deleted)
+        };
+        Ok(user_id.filter(|&id| live(id)))
+    }
+}
+
+// ── Outbox repository ────────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct InMemoryOutboxRepository(InMemoryStore);
+
+impl InMemoryOutboxRepository {
+    fn failed(tables: &mut Tables, id: Uuid) -> Option<&mut OutboxEntry> {
+        tables
+            .outbox
+            .iter_mut()
+            .find(|e| e.event.id == id && e.status() == OutboxStatus::Failed)
+    }
+}
+
+impl OutboxRepository for InMemoryOutboxRepository {
+    async fn list(
+        &self,
+        status: OutboxStatus,
+        page: PageRequest,
+    ) -> Result<Paged<OutboxEntry>, AuthServiceError> {
+        let tables = self.0.lock();
+        let matching: Vec<&OutboxEntry> = tables
+            .outbox
+            .iter()
+            .rev()
+            .filter(|e| e.status() == status)
+            .collect();
+        let items = matching
+            .iter()
+            .skip(page.offset() as usize)
+            .take(page.per_page as usize)
+            .map(|&e| e.clone())
+            .collect();
+        Ok(Paged::new(items, matching.len() as u64))
+    }
+
+    async fn retry_failed(&self, id: Uuid) -> Result<bool, AuthServiceError> {
+        let mut tables = self.0.lock();
+        let Some(entry) = Self::failed(&mut tables, id) else {
+            return Ok(false);
+        };
+        entry.failed_at = None;
+        entry.attempts = 0;
+        entry.next_attempt_at = Utc::now();
+        Ok(true)
+    }
+
+    async fn delete_failed(&self, id: Uuid) -> Result<bool, AuthServiceError> {
+        let mut tables = self.0.lock();
+        if Self::failed(&mut tables, id).is_none() {
+            return Ok(false);
+        }
+        tables.outbox.retain(|e| e.event.id != id);
+        Ok(true)
+    }
+}
+
+// ── Caches ───────────────────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct InMemoryOAuthStateCache(InMemoryStore);
+
+impl OAuthStateCache for InMemoryOAuthStateCache {
+    async fn set_state(&self, state: &str, pending_json: &[u8]) -> Result<(), AuthServiceError> {
+        self.0
+            .lock()
+            .oauth_states
+            .insert(state.to_owned(), pending_json.to_vec());
+        Ok(())
+    }
+
+    async fn take_state(&self, state: &str) -> Result<Option<Vec<u8>>, AuthServiceError> {
+        Ok(self.0.lock().oauth_states.remove(state))
+    }
+}
+
+#[derive(Clone)]
+pub struct InMemoryPasskeyCache(InMemoryStore);
+
+impl PasskeyCache for InMemoryPasskeyCache {
+    async fn set_registration_state(
+        &self,
+        user_id: UserId,
+        reg_id: &str,
+        state_json: &[u8],
+    ) -> Result<(), AuthServiceError> {
+        self.0
+            .lock()
+            .registration_states
+            .insert((user_id, reg_id.to_owned()), state_json.to_vec());
+        Ok(())
+    }
+
+    async fn take_registration_state(
+        &self,
+        user_id: UserId,
+        reg_id: &str,
+    ) -> Result<Option<Vec<u8>>, AuthServiceError> {
+        Ok(self
+            .0
+            .lock()
+            .registration_states
+            .remove(&(user_id, reg_id.to_owned())))
+    }
+
+    async fn set_authentication_state(
+        &self,
+        email: &str,
+        auth_id: &str,
+        state_json: &[u8],
+    ) -> Result<(), AuthServiceError> {
+        self.0
+            .lock()
+            .authentication_states
+            .insert((email.to_owned(), auth_id.to_owned()), state_json.to_vec());
+        Ok(())
+    }
+
+    async fn take_authentication_state(
+        &self,
+        email: &str,
+        auth_id: &str,
+    ) -> Result<Option<Vec<u8>>, AuthServiceError> {
+        Ok(self
+            .0
+            .lock()
+            .authentication_states
+            .remove(&(email.to_owned(), auth_id.to_owned())))
+    }
+}
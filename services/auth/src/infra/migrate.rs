@@ -0,0 +1,27 @@
+use madome_auth_migration::Migrator;
+use sea_orm::{ConnectionTrait, Database, DbBackend, DbErr, TransactionTrait};
+use sea_orm_migration::MigratorTrait;
+
+/// `pg_advisory_xact_lock` key serializing auth migrations across replicas.
+const MIGRATION_LOCK_KEY: i64 = 0x6d61_646f_6d65_0001;
+
+/// Apply pending migrations, one replica at a time.
+///
+/// Runs in a single transaction holding an advisory lock: replicas starting
+/// together queue on the lock, and the ones after the first find nothing left
+/// to apply. Uses its own connection so the pool's `statement_timeout` does not
+/// cut a long migration short. SQLite has no advisory locks; its write lock,
+/// taken by the first statement, serializes migrations instead.
+pub async fn run_migrations(database_url: &str) -> Result<(), DbErr> {
+    let db = Database::connect(database_url).await?;
+    let txn = db.begin().await?;
+    if db.get_database_backend() == DbBackend::Postgres {
+        txn.execute_unprepared(&format!(
+            "SELECT pg_advisory_xact_lock({MIGRATION_LOCK_KEY})"
+        ))
+        .await?;
+    }
+    Migrator::up(&txn, None).await?;
+    txn.commit().await?;
+    db.close().await
+}
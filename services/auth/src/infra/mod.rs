@@ -1,2 +1,6 @@
 pub mod cache;
 pub mod db;
+#[cfg(feature = "test-util")]
+pub mod memory;
+pub mod migrate;
+pub mod oauth;
@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use reqwest::header::{ACCEPT, USER_AGENT};
+use serde::Deserialize;
+use url::Url;
+
+use crate::config::{OAuthConfig, OAuthProviderConfig, OAuthProviderKind};
+use crate::domain::repository::OAuthClient;
+use crate::domain::types::OAuthProfile;
+use crate::error::AuthServiceError;
+
+/// GitHub's REST API rejects requests without a `User-Agent`.
+const GITHUB_USER_AGENT: &str = "madome-auth";
+
+#[derive(Clone)]
+pub struct HttpOAuthClient {
+    pub config: Arc<OAuthConfig>,
+    pub http: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    /// Absent when the code is rejected (GitHub answers 200 with an `error` field).
+    access_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OidcUserInfo {
+    sub: String,
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: bool,
+}
+
+#[derive(Deserialize)]
+struct GitHubUser {
+    id: u64,
+}
+
+#[derive(Deserialize)]
+struct GitHubEmail {
+    email: String,
+    primary: bool,
+    verified: bool,
+}
+
+impl HttpOAuthClient {
+    fn provider(&self, provider: &str) -> Result<&OAuthProviderConfig, AuthServiceError> {
+        self.config
+            .providers
+            .get(provider)
+            .ok_or(AuthServiceError::NotFound)
+    }
+
+    fn redirect_uri(&self, provider: &str) -> String {
+        format!(
+            "{}/auth/oauth/{}/callback",
+            self.config.redirect_base, provider
+        )
+    }
+
+    async fn oidc_profile(
+        &self,
+        p: &OAuthProviderConfig,
+        access_token: &str,
+    ) -> Result<OAuthProfile, AuthServiceError> {
+        let info: OidcUserInfo = self
+            .http
+            .get(&p.userinfo_url)
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .context("fetch oidc userinfo")?
+            .json()
+            .await
+            .context("decode oidc userinfo")?;
+        Ok(OAuthProfile {
+            subject: info.sub,
+            email: info.email,
+            email_verified: info.email_verified,
+        })
+    }
+
+    async fn github_profile(
+        &self,
+        p: &OAuthProviderConfig,
+        access_token: &str,
+    ) -> Result<OAuthProfile, AuthServiceError> {
+        let user: GitHubUser = self
+            .http
+            .get(&p.userinfo_url)
+            .bearer_auth(access_token)
+            .header(USER_AGENT, GITHUB_USER_AGENT)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .context("fetch github user")?
+            .json()
+            .await
+            .context("decode github user")?;
+
+        // The profile email may be unset or unverified; only the primary verified
+        // address from `/user/emails` is trusted for linking.
+        let emails: Vec<GitHubEmail> = self
+            .http
+            .get(format!("{}/emails", p.userinfo_url))
+            .bearer_auth(access_token)
+            .header(USER_AGENT, GITHUB_USER_AGENT)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .context("fetch github emails")?
+            .json()
+            .await
+            .context("decode github emails")?;
+        let primary = emails.into_iter().find(|e| e.primary && e.verified);
+
+        Ok(OAuthProfile {
+            subject: user.id.to_string(),
+            email_verified: primary.is_some(),
+            email: primary.map(|e| e.email),
+        })
+    }
+}
+
+impl OAuthClient for HttpOAuthClient {
+    fn authorize_url(
+        &self,
+        provider: &str,
+        state: &str,
+        code_challenge: &str,
+    ) -> Result<String, AuthServiceError> {
+        let p = self.provider(provider)?;
+        let url = Url::parse_with_params(
+            &p.authorize_url,
+            [
+                ("response_type", "code"),
+                ("client_id", p.client_id.as_str()),
+                ("redirect_uri", self.redirect_uri(provider).as_str()),
+                ("scope", p.scopes.as_str()),
+                ("state", state),
+                ("code_challenge", code_challenge),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .context("build authorize url")?;
+        Ok(url.into())
+    }
+
+    async fn exchange_code(
+        &self,
+        provider: &str,
+        code: &str,
+        code_verifier: &str,
+    ) -> Result<OAuthProfile, AuthServiceError> {
+        let p = self.provider(provider)?;
+        let resp = self
+            .http
+            .post(&p.token_url)
+            .header(ACCEPT, "application/json")
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", self.redirect_uri(provider).as_str()),
+                ("client_id", p.client_id.as_str()),
+                ("client_secret", p.client_secret.expose().as_str()),
+                ("code_verifier", code_verifier),
+            ])
+            .send()
+            .await
+            .context("exchange oauth code")?;
+        if !resp.status().is_success() {
+            return Err(AuthServiceError::Unauthorized);
+        }
+        let token: TokenResponse = resp.json().await.context("decode oauth token response")?;
+        let access_token = token.access_token.ok_or(AuthServiceError::Unauthorized)?;
+
+        match p.kind {
+            OAuthProviderKind::Oidc => self.oidc_profile(p, &access_token).await,
+            OAuthProviderKind::GitHub => self.github_profile(p, &access_token).await,
+        }
+    }
+}
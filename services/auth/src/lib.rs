@@ -1,8 +1,11 @@
 pub mod config;
 pub mod domain;
 pub mod error;
+pub mod grpc_server;
 pub mod handlers;
 pub mod infra;
+pub mod openapi;
 pub mod router;
 pub mod state;
 pub mod usecase;
+pub mod webauthn_util;
@@ -1,51 +1,218 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use madome_core::config::Config as _;
-use sea_orm::Database;
+use axum::Extension;
+use madome_core::config::Config;
+use madome_core::cors::cors_layer;
+use madome_core::grpc::{GrpcAuthLayer, GrpcTlsConfig, GrpcTraceLayer, parse_caller_tokens};
+use madome_core::health::Readiness;
+use madome_core::jobs::{Job, Schedule, Scheduler};
+use madome_core::query_metrics::QueryMetrics;
+use madome_core::secrets::{Provider, RefreshingSecret, load_config};
+use madome_core::startup::{bind, wait_for};
+use madome_core::supervisor::Supervisor;
+use sea_orm::{ConnectionTrait, Database, DbBackend};
+use tonic::transport::Server;
+use tonic::transport::server::TcpIncoming;
 use tracing::info;
 use url::Url;
 use webauthn_rs::prelude::WebauthnBuilder;
 
-use madome_auth::config::AuthConfig;
+use madome_auth::config::{AuthConfig, OAuthConfig, csrf_enforce_from_env};
+use madome_auth::domain::types::STATS_ROLLUP_INTERVAL_SECS;
+use madome_auth::grpc_server;
+use madome_auth::infra::db::DbStatsRepository;
+use madome_auth::infra::migrate::run_migrations;
 use madome_auth::router::build_router;
 use madome_auth::state::AppState;
+use madome_auth::usecase::stats::RollupDailyStatsUseCase;
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt::init();
+    madome_core::telemetry::init("auth", env!("CARGO_PKG_VERSION"));
 
-    let config = AuthConfig::from_env();
+    // Load every setting before connecting to anything, so a bad deploy fails
+    // with the full list of problems instead of the first one hit.
+    let secrets = Arc::new(Provider::from_env().unwrap_or_else(|e| panic!("{e}")));
+    let config: AuthConfig = load_config(&*secrets, &AuthConfig::SECRETS)
+        .await
+        .unwrap_or_else(|e| panic!("{e}"));
+    // Checked with the rest by `AuthConfig::validate` above.
+    let oauth = OAuthConfig::from_env().unwrap_or_else(|e| panic!("{e}"));
+    let cors = cors_layer(&config.cors_allowed_origins).unwrap_or_else(|e| panic!("{e}"));
+    let cookies = config.cookie_config();
+    let token_lifetimes = config.token_lifetimes();
+    let grpc_callers = config
+        .grpc_caller_tokens
+        .as_ref()
+        .map(|tokens| parse_caller_tokens(tokens).unwrap_or_else(|e| panic!("{e}")));
+    let grpc_tls = GrpcTlsConfig::from_env()
+        .server()
+        .unwrap_or_else(|e| panic!("{e}"));
+
+    // Postgres and Redis may still be starting (docker-compose, rollouts).
+    let backoff = config.startup_backoff();
+
+    let db = wait_for("database", backoff, || {
+        Database::connect(config.db_connect_options(config.database_url.expose()))
+    })
+    .await
+    .expect("failed to connect to database");
 
-    let db = Database::connect(&config.database_url)
+    // `--migrate-only` is for init containers: apply migrations and exit.
+    let migrate_only = std::env::args().any(|arg| arg == "--migrate-only");
+    if migrate_only || config.run_migrations {
+        run_migrations(config.database_url.expose())
+            .await
+            .expect("failed to run migrations");
+        info!("migrations applied");
+        if migrate_only {
+            return;
+        }
+    }
+
+    let db_read = match &config.database_read_url {
+        Some(url) => wait_for("read replica", backoff, || {
+            Database::connect(config.db_connect_options(url.expose()))
+        })
         .await
-        .expect("failed to connect to database");
+        .expect("failed to connect to read replica"),
+        None => db.clone(),
+    };
 
-    let redis_cfg = deadpool_redis::Config::from_url(&config.redis_url);
+    let redis_cfg = deadpool_redis::Config::from_url(config.redis_url.expose());
     let redis = redis_cfg
         .create_pool(Some(deadpool_redis::Runtime::Tokio1))
         .expect("failed to create Redis pool");
+    // The pool connects lazily; ping once so a missing Redis fails startup
+    // rather than the first sign-in.
+    wait_for("redis", backoff, || async {
+        let mut conn = redis.get().await.map_err(anyhow::Error::from)?;
+        deadpool_redis::redis::cmd("PING")
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(anyhow::Error::from)
+    })
+    .await
+    .expect("failed to reach Redis");
 
-    let rp_origin = Url::parse(&config.webauthn_origin).expect("invalid WEBAUTHN_ORIGIN");
+    let rp_origin =
+        Url::parse(&config.webauthn_origin).expect("WEBAUTHN_ORIGIN is checked on load");
     let webauthn = WebauthnBuilder::new(&config.webauthn_rp_id, &rp_origin)
         .expect("invalid WebAuthn configuration")
         .rp_name("Madome")
         .build()
         .expect("failed to build Webauthn");
 
+    // Tokens, codes and login links signed with a rotated-away key keep
+    // verifying until every refresh token it signed has expired.
+    let mut jwt_secret = RefreshingSecret::new(config.jwt_secret.clone())
+        .keep_previous(Duration::from_secs(token_lifetimes.refresh_secs));
+    if let Some(previous) = config.jwt_secret_previous.clone() {
+        jwt_secret = jwt_secret.with_previous(previous);
+    }
+    let mut jobs = Scheduler::new();
+    QueryMetrics::global().set_slow_threshold(config.slow_query_threshold());
+    jobs = jobs.job(QueryMetrics::global().report_job(Duration::from_secs(60)));
+    // Counts only grow, so replicas racing on a tick agree on the result.
+    let stats = DbStatsRepository {
+        db: db.clone(),
+        read: db_read.clone(),
+    };
+    jobs = jobs.job(
+        Job::new(
+            "auth_daily_stats_rollup",
+            Schedule::every(Duration::from_secs(STATS_ROLLUP_INTERVAL_SECS as u64)),
+            move || {
+                let uc = RollupDailyStatsUseCase {
+                    stats: stats.clone(),
+                };
+                async move { Ok(uc.execute(chrono::Utc::now()).await?) }
+            },
+        )
+        .jitter(Duration::from_secs(30)),
+    );
+    if let Some(every) = config.secrets_refresh() {
+        jobs = jobs.job(jwt_secret.refresh_job(Arc::clone(&secrets), "JWT_SECRET", every, |_| {}));
+        // Rotated database credentials apply to new connections; open ones
+        // stay valid until the pool recycles them. With no replica configured
+        // `db_read` shares this pool. A SQLite file has no credentials.
+        if db.get_database_backend() == DbBackend::Postgres {
+            let pool = db.get_postgres_connection_pool().clone();
+            let db_config = config.clone();
+            jobs = jobs.job(
+                RefreshingSecret::new(config.database_url.clone()).refresh_job(
+                    Arc::clone(&secrets),
+                    "DATABASE_URL",
+                    every,
+                    move |url| match db_config.pg_connect_options(url.expose()) {
+                        Ok(opts) => pool.set_connect_options(opts),
+                        Err(e) => tracing::error!(error = %e, "rotated DATABASE_URL is invalid"),
+                    },
+                ),
+            );
+        }
+    }
+    let _jobs = jobs.start();
+
     let state = AppState {
         db,
+        db_read,
         redis,
         webauthn: Arc::new(webauthn),
-        jwt_secret: config.jwt_secret,
-        cookie_domain: config.cookie_domain,
+        jwt_secret,
+        cookies,
+        token_lifetimes,
+        legacy_token_cutoff: config.legacy_token_cutoff,
+        oauth: Arc::new(oauth),
+        app_url: config.app_url.clone(),
+        http: reqwest::Client::new(),
+        csrf_enforce: csrf_enforce_from_env(),
+        cors,
     };
 
-    let router = build_router(state);
-    let addr = format!("0.0.0.0:{}", config.auth_port);
-    let listener = tokio::net::TcpListener::bind(&addr)
-        .await
-        .expect("failed to bind");
+    // Other services introspect tokens over gRPC; without callers nobody may.
+    let grpc = match grpc_callers {
+        Some(callers) => {
+            let addr = SocketAddr::from(([0, 0, 0, 0], config.auth_grpc_port));
+            let listener = bind("gRPC", addr, backoff)
+                .await
+                .expect("failed to bind gRPC port");
+            let mut server = Server::builder();
+            if let Some(tls) = grpc_tls {
+                server = server
+                    .tls_config(tls)
+                    .expect("invalid gRPC TLS configuration");
+            }
+            info!("auth gRPC listening on {addr}");
+            let router = server
+                .layer(GrpcTraceLayer)
+                .layer(GrpcAuthLayer::new(grpc_server::policy(&callers)))
+                .add_service(grpc_server::service(state.clone()));
+            Some((router, listener))
+        }
+        None => None,
+    };
 
+    let readiness = Readiness::new();
+    let router = build_router(state).layer(Extension(readiness.clone()));
+    let addr = SocketAddr::from(([0, 0, 0, 0], config.auth_port));
+    let listener = bind("HTTP", addr, backoff).await.expect("failed to bind");
     info!("auth service listening on {addr}");
-    axum::serve(listener, router).await.expect("server error");
+
+    // Both servers stop together, and /readyz is 503 unless both are up.
+    let mut supervisor = Supervisor::new(readiness).serve("http", |shutdown| {
+        axum::serve(listener, router)
+            .with_graceful_shutdown(shutdown.signalled())
+            .into_future()
+    });
+    if let Some((grpc, listener)) = grpc {
+        supervisor = supervisor.serve("grpc", |shutdown| {
+            grpc.serve_with_incoming_shutdown(TcpIncoming::from(listener), shutdown.signalled())
+        });
+    }
+    if let Err(e) = supervisor.run().await {
+        panic!("{e:#}");
+    }
 }
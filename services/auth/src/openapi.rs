@@ -0,0 +1,92 @@
+//! OpenAPI description of the auth HTTP API.
+//!
+//! Built from the `#[utoipa::path]` annotations on the handlers, so it is always
+//! available (e.g. to export for SDK generation). The `openapi` feature serves
+//! it at `GET /openapi.json` with Swagger UI at `/docs`.
+
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use madome_auth_types::cookie::{MADOME_ACCESS_TOKEN, MADOME_REFRESH_TOKEN};
+use madome_core::error_catalog::{ErrorBody, ErrorKind};
+use madome_core::validation::FieldError;
+
+use crate::handlers::{
+    api_token, auth_code, config, csrf, impersonate, login_link, machine_token, oauth, outbox,
+    passkeys, session, stats, token,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Madome auth", description = "Sign-in, tokens, sessions and passkeys."),
+    paths(
+        auth_code::create_authcode,
+        csrf::get_csrf_token,
+        login_link::create_login_link,
+        login_link::login_link_callback,
+        oauth::start_oauth,
+        oauth::oauth_callback,
+        token::check_token,
+        token::create_token,
+        token::refresh_token,
+        token::revoke_token,
+        config::get_config,
+        session::list_sessions,
+        session::delete_session,
+        api_token::create_api_token,
+        api_token::list_api_tokens,
+        api_token::delete_api_token,
+        passkeys::list_passkeys,
+        passkeys::delete_passkey,
+        passkeys::start_registration,
+        passkeys::finish_registration,
+        passkeys::start_authentication,
+        passkeys::finish_authentication,
+        impersonate::impersonate,
+        machine_token::create_machine_token,
+        outbox::list_outbox_events,
+        outbox::retry_outbox_event,
+        outbox::delete_outbox_event,
+        stats::list_daily_stats,
+    ),
+    components(schemas(ErrorBody, ErrorKind, FieldError)),
+    modifiers(&SecuritySchemes),
+    tags(
+        (name = "login", description = "Ways to obtain a token pair"),
+        (name = "token", description = "Check, refresh and revoke tokens"),
+        (name = "sessions", description = "Signed-in devices"),
+        (name = "api-tokens", description = "Long-lived, scope-limited tokens for scripts"),
+        (name = "passkeys", description = "WebAuthn credential management"),
+        (name = "impersonation", description = "Staff access as another user, for reproducing issues"),
+        (name = "machine", description = "Scoped tokens for internal tools"),
+        (name = "outbox", description = "Dead-lettered outbox events: inspect, replay, discard"),
+        (name = "stats", description = "Daily usage figures for the admin dashboard"),
+        (name = "csrf", description = "Double-submit token for cookie-authenticated mutations"),
+    ),
+)]
+pub struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "accessTokenCookie",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(MADOME_ACCESS_TOKEN))),
+        );
+        components.add_security_scheme(
+            "refreshTokenCookie",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(MADOME_REFRESH_TOKEN))),
+        );
+        // Set by the gateway after it validates the access token; clients send
+        // the cookie to the gateway, never this header.
+        components.add_security_scheme(
+            "gatewayIdentity",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "x-madome-user-id",
+                "Injected by the gateway from `madome_access_token` or an API token",
+            ))),
+        );
+    }
+}
@@ -1,40 +1,178 @@
 use axum::{
     Router,
+    http::Method,
+    middleware,
     routing::{delete, get, patch, post},
 };
 
+use madome_core::csrf::require_csrf;
 use madome_core::health::{healthz, readyz};
+use madome_core::idempotency::idempotency;
+use madome_core::middleware::{BodyLimit, audit_impersonation, default_stack, trace_id};
+use madome_core::permission::{Access, RoutePermission, require_role};
+use madome_domain::user::UserRole;
+#[cfg(feature = "openapi")]
+use utoipa::OpenApi;
+#[cfg(feature = "openapi")]
+use utoipa_swagger_ui::SwaggerUi;
 
 use crate::handlers::{
+    api_token::{create_api_token, delete_api_token, list_api_tokens},
     auth_code::create_authcode,
+    config::get_config,
+    csrf::get_csrf_token,
+    impersonate::impersonate,
+    login_link::{create_login_link, login_link_callback},
+    machine_token::create_machine_token,
+    oauth::{oauth_callback, start_oauth},
+    outbox::{delete_outbox_event, list_outbox_events, retry_outbox_event},
     passkeys::{
         delete_passkey, finish_authentication, finish_registration, list_passkeys,
         start_authentication, start_registration,
     },
+    session::{delete_session, list_sessions},
+    stats::list_daily_stats,
     token::{check_token, create_token, refresh_token, revoke_token},
 };
+use crate::infra::cache::RedisIdempotencyStore;
+#[cfg(feature = "openapi")]
+use crate::openapi::ApiDoc;
 use crate::state::AppState;
 
+const PUBLIC: Access = Access::Public;
+const SIGNED_IN: Access = Access::Role(UserRole::Normal);
+const STAFF: Access = Access::Role(UserRole::Bot);
+
+/// Who may call each route. Every route below must appear here; unlisted
+/// routes are refused. `Public` routes check the token cookies themselves.
+/// No route is opened to API tokens, so a leaked one cannot mint more of
+/// itself, act as staff, or take over the account's sessions and passkeys.
+pub static ROUTE_PERMISSIONS: &[RoutePermission] = &[
+    RoutePermission::new(Method::GET, "/healthz", PUBLIC),
+    RoutePermission::new(Method::GET, "/readyz", PUBLIC),
+    RoutePermission::new(Method::GET, "/auth/csrf", PUBLIC),
+    RoutePermission::new(Method::GET, "/auth/config", PUBLIC),
+    RoutePermission::new(Method::POST, "/auth/code", PUBLIC),
+    RoutePermission::new(Method::POST, "/auth/link", PUBLIC),
+    RoutePermission::new(Method::GET, "/auth/link/callback", PUBLIC),
+    RoutePermission::new(Method::POST, "/auth/oauth/{provider}/start", PUBLIC),
+    RoutePermission::new(Method::GET, "/auth/oauth/{provider}/callback", PUBLIC),
+    RoutePermission::new(Method::GET, "/auth/token", PUBLIC),
+    RoutePermission::new(Method::POST, "/auth/token", PUBLIC),
+    RoutePermission::new(Method::PATCH, "/auth/token", PUBLIC),
+    RoutePermission::new(Method::DELETE, "/auth/token", SIGNED_IN),
+    RoutePermission::new(Method::GET, "/auth/sessions", SIGNED_IN),
+    RoutePermission::new(Method::DELETE, "/auth/sessions/{id}", SIGNED_IN),
+    RoutePermission::new(Method::GET, "/auth/api-tokens", SIGNED_IN),
+    RoutePermission::new(Method::POST, "/auth/api-tokens", SIGNED_IN),
+    RoutePermission::new(Method::DELETE, "/auth/api-tokens/{id}", SIGNED_IN),
+    RoutePermission::new(Method::GET, "/auth/passkeys", SIGNED_IN),
+    RoutePermission::new(Method::DELETE, "/auth/passkeys/{credential_id}", SIGNED_IN),
+    RoutePermission::new(Method::POST, "/auth/passkey/registration", SIGNED_IN),
+    RoutePermission::new(Method::PATCH, "/auth/passkey/registration", SIGNED_IN),
+    RoutePermission::new(Method::POST, "/auth/passkey/authentication", PUBLIC),
+    RoutePermission::new(Method::PATCH, "/auth/passkey/authentication", PUBLIC),
+    RoutePermission::new(Method::POST, "/auth/impersonate/{user_id}", STAFF),
+    RoutePermission::new(Method::POST, "/auth/machine-tokens", STAFF),
+    RoutePermission::new(Method::GET, "/auth/admin/outbox", STAFF),
+    RoutePermission::new(Method::POST, "/auth/admin/outbox/{id}/retry", STAFF),
+    RoutePermission::new(Method::DELETE, "/auth/admin/outbox/{id}", STAFF),
+    RoutePermission::new(Method::GET, "/auth/admin/stats", STAFF),
+];
+
+/// Sign-in bodies are a few hundred bytes; anyone can send them, so they get
+/// far less than the default limit.
+const SIGN_IN_BODY: usize = 16 * 1024;
+
+/// Routes whose request body limit differs from
+/// [`madome_core::middleware::DEFAULT_BODY_LIMIT`].
+pub static BODY_LIMITS: &[BodyLimit] = &[
+    BodyLimit::new(Method::POST, "/auth/code", SIGN_IN_BODY),
+    BodyLimit::new(Method::POST, "/auth/link", SIGN_IN_BODY),
+    BodyLimit::new(Method::POST, "/auth/token", SIGN_IN_BODY),
+    BodyLimit::new(Method::POST, "/auth/passkey/authentication", SIGN_IN_BODY),
+    BodyLimit::new(Method::PATCH, "/auth/passkey/authentication", SIGN_IN_BODY),
+];
+
 pub fn build_router(state: AppState) -> Router {
-    Router::new()
+    // Mutations authorized by the token cookies. Sign-in routes are not listed:
+    // they carry no session for a forged request to ride on.
+    let mut cookie_authenticated = Router::new()
+        .route("/auth/token", patch(refresh_token))
+        .route("/auth/token", delete(revoke_token))
+        .route("/auth/sessions/{id}", delete(delete_session))
+        .route("/auth/api-tokens", post(create_api_token))
+        .route("/auth/api-tokens/{id}", delete(delete_api_token))
+        .route("/auth/passkeys/{credential_id}", delete(delete_passkey))
+        .route("/auth/passkey/registration", post(start_registration))
+        .route("/auth/passkey/registration", patch(finish_registration))
+        .route("/auth/impersonate/{user_id}", post(impersonate))
+        .route("/auth/machine-tokens", post(create_machine_token))
+        .route("/auth/admin/outbox/{id}/retry", post(retry_outbox_event))
+        .route("/auth/admin/outbox/{id}", delete(delete_outbox_event));
+    if state.csrf_enforce {
+        cookie_authenticated = cookie_authenticated.route_layer(middleware::from_fn(require_csrf));
+    }
+
+    // Requests a flaky client may retry; replays must not mail a second code or
+    // link. `POST /auth/token` stays out: its response is the session cookies,
+    // and a retry re-runs it against the already used code instead.
+    let idempotent = Router::new()
+        .route("/auth/code", post(create_authcode))
+        .route("/auth/link", post(create_login_link))
+        .route_layer(middleware::from_fn_with_state(
+            state.idempotency_store(),
+            idempotency::<RedisIdempotencyStore>,
+        ));
+
+    let router = Router::new()
         // Health
         .route("/healthz", get(healthz))
         .route("/readyz", get(readyz))
-        // Auth code
-        .route("/auth/code", post(create_authcode))
+        // CSRF
+        .route("/auth/csrf", get(get_csrf_token))
+        // Token lifetimes
+        .route("/auth/config", get(get_config))
+        // Magic link
+        .route("/auth/link/callback", get(login_link_callback))
+        // OAuth / OIDC federation
+        .route("/auth/oauth/{provider}/start", post(start_oauth))
+        .route("/auth/oauth/{provider}/callback", get(oauth_callback))
         // Token
-        .route("/auth/token", get(check_token))
         .route("/auth/token", post(create_token))
-        .route("/auth/token", patch(refresh_token))
-        .route("/auth/token", delete(revoke_token))
+        .route("/auth/token", get(check_token))
+        // Sessions
+        .route("/auth/sessions", get(list_sessions))
+        // API tokens
+        .route("/auth/api-tokens", get(list_api_tokens))
         // Passkeys
         .route("/auth/passkeys", get(list_passkeys))
-        .route("/auth/passkeys/{credential_id}", delete(delete_passkey))
-        // WebAuthn registration
-        .route("/auth/passkey/registration", post(start_registration))
-        .route("/auth/passkey/registration", patch(finish_registration))
         // WebAuthn authentication
         .route("/auth/passkey/authentication", post(start_authentication))
         .route("/auth/passkey/authentication", patch(finish_authentication))
-        .with_state(state)
+        // Outbox dead letters (staff)
+        .route("/auth/admin/outbox", get(list_outbox_events))
+        // Usage stats (staff)
+        .route("/auth/admin/stats", get(list_daily_stats))
+        .merge(idempotent)
+        .merge(cookie_authenticated)
+        .route_layer(middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            require_role,
+        ));
+
+    #[cfg(feature = "openapi")]
+    let router = router.merge(SwaggerUi::new("/docs").url("/openapi.json", ApiDoc::openapi()));
+
+    let router = router
+        .layer(middleware::from_fn(audit_impersonation))
+        .layer(middleware::from_fn(trace_id))
+        .layer(default_stack(BODY_LIMITS));
+
+    // Outermost, so preflights and refused requests get CORS headers too.
+    let router = match state.cors.clone() {
+        Some(cors) => router.layer(cors),
+        None => router,
+    };
+    router.with_state(state)
 }
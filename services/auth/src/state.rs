@@ -1,26 +1,58 @@
 use std::sync::Arc;
 
 use deadpool_redis::Pool as RedisPool;
+use madome_auth_types::cookie::{CookieConfig, TokenLifetimes};
+use madome_core::secrets::RefreshingSecret;
 use sea_orm::DatabaseConnection;
+use tower_http::cors::CorsLayer;
 use webauthn_rs::Webauthn;
 
-use crate::infra::cache::RedisPasskeyCache;
-use crate::infra::db::{DbAuthCodeRepository, DbPasskeyRepository, DbUserRepository};
+use crate::config::OAuthConfig;
+use crate::infra::cache::{
+    RedisAuthCodeAttemptStore, RedisIdempotencyStore, RedisOAuthStateCache, RedisPasskeyCache,
+};
+use crate::infra::db::{
+    DbApiTokenRepository, DbAuthCodeRepository, DbIdAliasRepository, DbLinkedIdentityRepository,
+    DbLoginLinkRepository, DbOutboxRepository, DbPasskeyRepository, DbSessionRepository,
+    DbStatsRepository, DbUserRepository,
+};
+use crate::infra::oauth::HttpOAuthClient;
 
 /// Shared application state passed to every handler via axum `State`.
 #[derive(Clone)]
 pub struct AppState {
+    /// Primary (read-write) connection.
     pub db: DatabaseConnection,
+    /// Read replica (`DATABASE_READ_URL`), or a clone of `db` when unset.
+    ///
+    /// Only listings and user lookups read from it. Reads that gate a write
+    /// (auth codes, login links, refresh-token sessions) stay on the primary so
+    /// replication lag cannot reject a token issued a moment ago.
+    pub db_read: DatabaseConnection,
     pub redis: RedisPool,
     pub webauthn: Arc<Webauthn>,
-    pub jwt_secret: String,
-    pub cookie_domain: String,
+    /// Re-read from the secrets provider while running; see [`madome_core::secrets`].
+    pub jwt_secret: RefreshingSecret,
+    pub cookies: CookieConfig,
+    /// Access/refresh token lifetimes; the refresh one is also the cookie Max-Age.
+    pub token_lifetimes: TokenLifetimes,
+    /// `LEGACY_TOKEN_CUTOFF`; see [`crate::config::AuthConfig::legacy_token_cutoff`].
+    pub legacy_token_cutoff: Option<u64>,
+    pub oauth: Arc<OAuthConfig>,
+    /// `APP_URL`: where sign-in callbacks send the browser.
+    pub app_url: String,
+    /// Shared HTTP client for outbound calls to OAuth providers.
+    pub http: reqwest::Client,
+    /// Require the double-submit CSRF token on cookie-authenticated mutations.
+    pub csrf_enforce: bool,
+    /// Built from `CORS_ALLOWED_ORIGINS`; `None` when no origin is allowed.
+    pub cors: Option<CorsLayer>,
 }
 
 impl AppState {
     pub fn user_repo(&self) -> DbUserRepository {
         DbUserRepository {
-            db: self.db.clone(),
+            db: self.db_read.clone(),
         }
     }
 
@@ -30,9 +62,81 @@ impl AppState {
         }
     }
 
+    pub fn auth_code_attempts(&self) -> RedisAuthCodeAttemptStore {
+        RedisAuthCodeAttemptStore {
+            pool: self.redis.clone(),
+        }
+    }
+
     pub fn passkey_repo(&self) -> DbPasskeyRepository {
         DbPasskeyRepository {
             db: self.db.clone(),
+            read: self.db_read.clone(),
+        }
+    }
+
+    pub fn login_link_repo(&self) -> DbLoginLinkRepository {
+        DbLoginLinkRepository {
+            db: self.db.clone(),
+        }
+    }
+
+    pub fn session_repo(&self) -> DbSessionRepository {
+        DbSessionRepository {
+            db: self.db.clone(),
+            read: self.db_read.clone(),
+        }
+    }
+
+    pub fn api_token_repo(&self) -> DbApiTokenRepository {
+        DbApiTokenRepository {
+            db: self.db.clone(),
+            read: self.db_read.clone(),
+        }
+    }
+
+    pub fn stats_repo(&self) -> DbStatsRepository {
+        DbStatsRepository {
+            db: self.db.clone(),
+            read: self.db_read.clone(),
+        }
+    }
+
+    pub fn linked_identity_repo(&self) -> DbLinkedIdentityRepository {
+        DbLinkedIdentityRepository {
+            db: self.db.clone(),
+        }
+    }
+
+    pub fn id_alias_repo(&self) -> DbIdAliasRepository {
+        DbIdAliasRepository {
+            read: self.db_read.clone(),
+        }
+    }
+
+    pub fn outbox_repo(&self) -> DbOutboxRepository {
+        DbOutboxRepository {
+            db: self.db.clone(),
+            read: self.db_read.clone(),
+        }
+    }
+
+    pub fn oauth_client(&self) -> HttpOAuthClient {
+        HttpOAuthClient {
+            config: self.oauth.clone(),
+            http: self.http.clone(),
+        }
+    }
+
+    pub fn oauth_state_cache(&self) -> RedisOAuthStateCache {
+        RedisOAuthStateCache {
+            pool: self.redis.clone(),
+        }
+    }
+
+    pub fn idempotency_store(&self) -> RedisIdempotencyStore {
+        RedisIdempotencyStore {
+            pool: self.redis.clone(),
         }
     }
 
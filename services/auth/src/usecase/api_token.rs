@@ -0,0 +1,187 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use rand::RngExt;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use uuid::Uuid;
+
+use madome_auth_types::api_token::{format_api_token, parse_api_token};
+use madome_domain::id::UserId;
+
+use crate::domain::repository::{ApiTokenRepository, UserRepository};
+use crate::domain::types::{ApiToken, MAX_API_TOKENS};
+use crate::error::AuthServiceError;
+
+/// SHA-256 of a token secret, as stored in `api_tokens`.
+///
+/// Unkeyed: the secret is 32 random bytes, so there is nothing to brute-force.
+fn hash_secret(secret: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(secret.as_bytes()))
+}
+
+#[derive(Debug)]
+pub struct ApiTokenInfo {
+    pub id: Uuid,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl From<ApiToken> for ApiTokenInfo {
+    fn from(t: ApiToken) -> Self {
+        Self {
+            id: t.id,
+            name: t.name,
+            scopes: t.scopes,
+            created_at: t.created_at,
+            last_used_at: t.last_used_at,
+        }
+    }
+}
+
+// ── Create API token ─────────────────────────────────────────────────────────
+
+pub struct CreateApiTokenInput {
+    pub name: String,
+    /// Checked against `API_TOKEN_SCOPES` by the caller.
+    pub scopes: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct CreateApiTokenOutput {
+    pub info: ApiTokenInfo,
+    /// The token itself; only its hash is stored, so this is the one chance to read it.
+    pub token: String,
+}
+
+pub struct CreateApiTokenUseCase<T: ApiTokenRepository> {
+    pub tokens: T,
+}
+
+impl<T: ApiTokenRepository> CreateApiTokenUseCase<T> {
+    /// Issue a token for `user_id`. Forbidden while impersonating: a staff
+    /// member must not walk away with a credential that outlives the session.
+    pub async fn execute(
+        &self,
+        user_id: UserId,
+        impersonator: Option<Uuid>,
+        input: CreateApiTokenInput,
+    ) -> Result<CreateApiTokenOutput, AuthServiceError> {
+        if impersonator.is_some() {
+            return Err(AuthServiceError::Forbidden);
+        }
+        if self.tokens.count_by_user(user_id).await? >= MAX_API_TOKENS {
+            return Err(AuthServiceError::BadRequest(format!(
+                "at most {MAX_API_TOKENS} API tokens per user; delete one first"
+            )));
+        }
+
+        let bytes: [u8; 32] = rand::rng().random();
+        let secret = URL_SAFE_NO_PAD.encode(bytes);
+        let record = ApiToken {
+            id: Uuid::new_v4(),
+            user_id,
+            name: input.name,
+            secret_hash: hash_secret(&secret),
+            scopes: input.scopes,
+            created_at: Utc::now(),
+            last_used_at: None,
+        };
+        self.tokens.create(&record).await?;
+
+        tracing::info!(
+            target: "audit",
+            user_id = %user_id,
+            token_id = %record.id,
+            scopes = %record.scopes.join(" "),
+            "api token created",
+        );
+
+        Ok(CreateApiTokenOutput {
+            token: format_api_token(record.id, &secret),
+            info: record.into(),
+        })
+    }
+}
+
+// ── List API tokens ──────────────────────────────────────────────────────────
+
+pub struct ListApiTokensUseCase<T: ApiTokenRepository> {
+    pub tokens: T,
+}
+
+impl<T: ApiTokenRepository> ListApiTokensUseCase<T> {
+    pub async fn execute(&self, user_id: UserId) -> Result<Vec<ApiTokenInfo>, AuthServiceError> {
+        let tokens = self.tokens.list_by_user(user_id).await?;
+        Ok(tokens.into_iter().map(ApiTokenInfo::from).collect())
+    }
+}
+
+// ── Delete API token ─────────────────────────────────────────────────────────
+
+pub struct DeleteApiTokenUseCase<T: ApiTokenRepository> {
+    pub tokens: T,
+}
+
+impl<T: ApiTokenRepository> DeleteApiTokenUseCase<T> {
+    /// Returns 404 if not found or belongs to a different user. The token
+    /// stops working with the next request that presents it.
+    pub async fn execute(&self, token_id: Uuid, user_id: UserId) -> Result<(), AuthServiceError> {
+        if !self.tokens.delete(token_id, user_id).await? {
+            return Err(AuthServiceError::NotFound);
+        }
+        tracing::info!(
+            target: "audit",
+            user_id = %user_id,
+            token_id = %token_id,
+            "api token deleted",
+        );
+        Ok(())
+    }
+}
+
+// ── Introspect API token ─────────────────────────────────────────────────────
+
+/// Holder of a valid API token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiTokenIntrospection {
+    pub user_id: UserId,
+    pub user_role: u8,
+    pub scopes: Vec<String>,
+}
+
+pub struct IntrospectApiTokenUseCase<T: ApiTokenRepository, U: UserRepository> {
+    pub tokens: T,
+    pub users: U,
+}
+
+impl<T: ApiTokenRepository, U: UserRepository> IntrospectApiTokenUseCase<T, U> {
+    /// `None` for a token that is malformed, deleted, has the wrong secret, or
+    /// whose owner was deleted. The role is read now, so a demoted user's
+    /// tokens lose the role at once.
+    pub async fn execute(
+        &self,
+        token: &str,
+    ) -> Result<Option<ApiTokenIntrospection>, AuthServiceError> {
+        let Some(parsed) = parse_api_token(token) else {
+            return Ok(None);
+        };
+        let Some(record) = self.tokens.find_by_id(parsed.id).await? else {
+            return Ok(None);
+        };
+        let presented = hash_secret(parsed.secret);
+        if !bool::from(presented.as_bytes().ct_eq(record.secret_hash.as_bytes())) {
+            return Ok(None);
+        }
+        let Some(user) = self.users.find_by_id(record.user_id).await? else {
+            return Ok(None);
+        };
+        self.tokens.touch(record.id).await?;
+        Ok(Some(ApiTokenIntrospection {
+            user_id: user.id,
+            user_role: user.role,
+            scopes: record.scopes,
+        }))
+    }
+}
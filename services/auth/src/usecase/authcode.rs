@@ -1,8 +1,13 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
 use rand::RngExt;
 use serde_json::json;
+use sha2::Sha256;
 use uuid::Uuid;
 
+use madome_core::rate_limit::RateLimit;
+
 use crate::domain::repository::{AuthCodeRepository, UserRepository};
 use crate::domain::types::{
     AUTHCODE_LEN, AUTHCODE_TTL_SECS, AuthCode, MAX_ACTIVE_AUTHCODES, OutboxEvent,
@@ -19,6 +24,16 @@ fn generate_code() -> String {
         .collect()
 }
 
+/// HMAC-SHA256 of `code` keyed with `secret`, as stored in `auth_codes`.
+///
+/// Keyed so that a leaked table cannot be brute-forced offline.
+pub fn hash_code(secret: &str, code: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(code.as_bytes());
+    URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+}
+
 pub struct CreateAuthcodeInput {
     pub email: String,
 }
@@ -26,10 +41,14 @@ pub struct CreateAuthcodeInput {
 pub struct CreateAuthcodeUseCase<U: UserRepository, A: AuthCodeRepository> {
     pub users: U,
     pub auth_codes: A,
+    /// Key for [`hash_code`] (the JWT secret).
+    pub code_secret: String,
 }
 
 impl<U: UserRepository, A: AuthCodeRepository> CreateAuthcodeUseCase<U, A> {
-    pub async fn execute(&self, input: CreateAuthcodeInput) -> Result<(), AuthServiceError> {
+    /// Returns the user's code quota after this one; a code frees its slot
+    /// once used or expired, so the reset is at most [`AUTHCODE_TTL_SECS`].
+    pub async fn execute(&self, input: CreateAuthcodeInput) -> Result<RateLimit, AuthServiceError> {
         // 1. Find user by email → 404 if not found
         let user = self
             .users
@@ -39,8 +58,12 @@ impl<U: UserRepository, A: AuthCodeRepository> CreateAuthcodeUseCase<U, A> {
 
         // 2. Check active code limit → 429 if at or over limit
         let active = self.auth_codes.count_active(user.id).await?;
+        let reset_secs = AUTHCODE_TTL_SECS as u64;
         if active >= MAX_ACTIVE_AUTHCODES {
-            return Err(AuthServiceError::TooManyRequests);
+            return Err(AuthServiceError::TooManyRequests(RateLimit::exhausted(
+                MAX_ACTIVE_AUTHCODES,
+                reset_secs,
+            )));
         }
 
         // 3. Generate code + authcode record
@@ -49,13 +72,14 @@ impl<U: UserRepository, A: AuthCodeRepository> CreateAuthcodeUseCase<U, A> {
         let code = AuthCode {
             id: Uuid::new_v4(),
             user_id: user.id,
-            code: code_str.clone(),
+            code_hash: hash_code(&self.code_secret, &code_str),
             expires_at: now + Duration::seconds(AUTHCODE_TTL_SECS),
             used_at: None,
             created_at: now,
         };
 
-        // 4. Write authcode + outbox event in same transaction
+        // 4. Write authcode + outbox event in same transaction; only the
+        //    event keeps the plain code, for the mail
         let event = OutboxEvent {
             id: Uuid::new_v4(),
             kind: "authcode_created".to_owned(),
@@ -64,6 +88,6 @@ impl<U: UserRepository, A: AuthCodeRepository> CreateAuthcodeUseCase<U, A> {
         };
 
         self.auth_codes.create_with_outbox(&code, &event).await?;
-        Ok(())
+        Ok(RateLimit::new(MAX_ACTIVE_AUTHCODES, active + 1, reset_secs))
     }
 }
@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use madome_domain::id::UserId;
+
+use crate::domain::repository::{
+    LinkedIdentityRepository, PasskeyRepository, SessionRepository, UserRepository,
+};
+use crate::domain::types::{LinkedIdentity, Session};
+use crate::error::AuthServiceError;
+
+/// Everything the auth service stores about one user, for a data export.
+#[derive(Debug, Clone)]
+pub struct AuthDataExport {
+    pub email: String,
+    pub role: u8,
+    pub sessions: Vec<Session>,
+    pub passkeys: Vec<PasskeyMetadata>,
+    pub linked_identities: Vec<LinkedIdentity>,
+}
+
+/// A passkey without its public key: the key identifies nothing to the user
+/// and is useless outside this relying party.
+#[derive(Debug, Clone)]
+pub struct PasskeyMetadata {
+    pub credential_id: Vec<u8>,
+    pub aaguid: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct ExportAuthDataUseCase<U, S, P, L>
+where
+    U: UserRepository,
+    S: SessionRepository,
+    P: PasskeyRepository,
+    L: LinkedIdentityRepository,
+{
+    pub users: U,
+    pub sessions: S,
+    pub passkeys: P,
+    pub identities: L,
+}
+
+impl<U, S, P, L> ExportAuthDataUseCase<U, S, P, L>
+where
+    U: UserRepository,
+    S: SessionRepository,
+    P: PasskeyRepository,
+    L: LinkedIdentityRepository,
+{
+    /// Returns 404 for an unknown (or soft-deleted) user.
+    ///
+    /// One-time codes and magic links are left out: they expire within a day
+    /// and carry nothing beyond the email already exported.
+    pub async fn execute(&self, user_id: UserId) -> Result<AuthDataExport, AuthServiceError> {
+        let user = self
+            .users
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AuthServiceError::NotFound)?;
+
+        let passkeys = self
+            .passkeys
+            .list_by_user(user_id)
+            .await?
+            .into_iter()
+            .map(|p| PasskeyMetadata {
+                credential_id: p.credential_id,
+                aaguid: p.aaguid,
+                created_at: p.created_at,
+            })
+            .collect();
+
+        Ok(AuthDataExport {
+            email: user.email,
+            role: user.role,
+            sessions: self.sessions.list_by_user(user_id).await?,
+            passkeys,
+            linked_identities: self.identities.list_by_user(user_id).await?,
+        })
+    }
+}
@@ -0,0 +1,26 @@
+use madome_domain::id::UserId;
+
+use crate::domain::repository::IdAliasRepository;
+use crate::error::AuthServiceError;
+
+pub struct ResolveLegacyIdUseCase<A: IdAliasRepository> {
+    pub aliases: A,
+}
+
+impl<A: IdAliasRepository> ResolveLegacyIdUseCase<A> {
+    /// Current id of the user a legacy numeric id belongs to.
+    ///
+    /// 404 for ids that were never imported and for deleted users; legacy ids
+    /// start at 1, so anything lower is a 400.
+    pub async fn execute(&self, legacy_id: i64) -> Result<UserId, AuthServiceError> {
+        if legacy_id < 1 {
+            return Err(AuthServiceError::BadRequest(
+                "legacy_id must be positive".to_owned(),
+            ));
+        }
+        self.aliases
+            .resolve(legacy_id)
+            .await?
+            .ok_or(AuthServiceError::NotFound)
+    }
+}
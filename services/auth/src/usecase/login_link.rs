@@ -0,0 +1,143 @@
+use chrono::{Duration, Utc};
+use serde_json::json;
+use uuid::Uuid;
+
+use madome_auth_types::cookie::TokenLifetimes;
+use madome_auth_types::login_link::validate_login_link_token;
+use madome_core::rate_limit::RateLimit;
+
+use crate::domain::repository::{LoginLinkRepository, SessionRepository, UserRepository};
+use crate::domain::types::{LOGIN_LINK_TTL_SECS, LoginLink, MAX_ACTIVE_LOGIN_LINKS, OutboxEvent};
+use crate::error::AuthServiceError;
+use crate::usecase::session::{ClientInfo, start_session};
+use crate::usecase::token::{CreateTokenOutput, issue_access_token, issue_refresh_token};
+
+// ── CreateLoginLink ───────────────────────────────────────────────────────────
+
+pub struct CreateLoginLinkInput {
+    pub email: String,
+}
+
+pub struct CreateLoginLinkUseCase<U: UserRepository, L: LoginLinkRepository> {
+    pub users: U,
+    pub login_links: L,
+}
+
+impl<U: UserRepository, L: LoginLinkRepository> CreateLoginLinkUseCase<U, L> {
+    /// Returns the user's link quota after this one; the reset is at most
+    /// [`LOGIN_LINK_TTL_SECS`].
+    pub async fn execute(
+        &self,
+        input: CreateLoginLinkInput,
+    ) -> Result<RateLimit, AuthServiceError> {
+        let user = self
+            .users
+            .find_by_email(&input.email)
+            .await?
+            .ok_or(AuthServiceError::NotFound)?;
+
+        let active = self.login_links.count_active(user.id).await?;
+        let reset_secs = LOGIN_LINK_TTL_SECS as u64;
+        if active >= MAX_ACTIVE_LOGIN_LINKS {
+            return Err(AuthServiceError::TooManyRequests(RateLimit::exhausted(
+                MAX_ACTIVE_LOGIN_LINKS,
+                reset_secs,
+            )));
+        }
+
+        let now = Utc::now();
+        let link = LoginLink {
+            id: Uuid::new_v4(),
+            user_id: user.id,
+            expires_at: now + Duration::seconds(LOGIN_LINK_TTL_SECS),
+            used_at: None,
+            created_at: now,
+        };
+        // No token here: the mail worker signs one from the id and expiry
+        // when it sends the mail (`madome_auth_types::login_link`), so the
+        // outbox and its staff listing never hold a usable link.
+        let event = OutboxEvent {
+            id: Uuid::new_v4(),
+            kind: "login_link_created".to_owned(),
+            payload: json!({
+                "email": input.email,
+                "login_link_id": link.id,
+                "expires_at": link.expires_at,
+            }),
+            idempotency_key: format!("login_link_created:{}", link.id),
+        };
+
+        self.login_links.create_with_outbox(&link, &event).await?;
+        Ok(RateLimit::new(
+            MAX_ACTIVE_LOGIN_LINKS,
+            active + 1,
+            reset_secs,
+        ))
+    }
+}
+
+// ── ConsumeLoginLink (callback) ──────────────────────────────────────────────
+
+pub struct ConsumeLoginLinkUseCase<U: UserRepository, L: LoginLinkRepository, S: SessionRepository>
+{
+    pub users: U,
+    pub login_links: L,
+    pub sessions: S,
+    pub jwt_secret: String,
+    /// Key replaced by the last rotation, still accepted when verifying.
+    pub previous_jwt_secret: Option<String>,
+    pub lifetimes: TokenLifetimes,
+}
+
+impl<U: UserRepository, L: LoginLinkRepository, S: SessionRepository>
+    ConsumeLoginLinkUseCase<U, L, S>
+{
+    /// Exchange a login link token for a token pair.
+    ///
+    /// Returns 401 for a bad signature, an expired link, or a link that was
+    /// already used.
+    pub async fn execute(
+        &self,
+        token: &str,
+        client: &ClientInfo,
+    ) -> Result<CreateTokenOutput, AuthServiceError> {
+        // Links mailed before a rotation were signed with the previous key.
+        let link_id = validate_login_link_token(token, &self.jwt_secret)
+            .or_else(|e| match &self.previous_jwt_secret {
+                Some(previous) => validate_login_link_token(token, previous),
+                None => Err(e),
+            })
+            .map_err(|_| AuthServiceError::Unauthorized)?;
+
+        let link = self
+            .login_links
+            .consume(link_id)
+            .await?
+            .ok_or(AuthServiceError::Unauthorized)?;
+
+        let user = self
+            .users
+            .find_by_id(link.user_id)
+            .await?
+            .ok_or(AuthServiceError::Unauthorized)?;
+
+        let session_id = start_session(&self.sessions, user.id, client).await?;
+
+        let (access_token, access_token_exp) =
+            issue_access_token(&user, &self.jwt_secret, self.lifetimes.access_secs)?;
+        let refresh_token = issue_refresh_token(
+            &user,
+            session_id,
+            &self.jwt_secret,
+            self.lifetimes.refresh_secs,
+            true,
+        )?;
+
+        Ok(CreateTokenOutput {
+            user,
+            access_token,
+            access_token_exp,
+            refresh_token,
+        })
+    }
+}
@@ -0,0 +1,65 @@
+use uuid::Uuid;
+
+use madome_auth_types::machine::issue_machine_token;
+use madome_domain::id::UserId;
+use madome_domain::user::UserRole;
+
+use crate::error::AuthServiceError;
+use crate::usecase::token::now_secs;
+
+/// Machine-token lifetime when the request names none, in seconds (1 day).
+pub const MACHINE_TOKEN_DEFAULT_TTL_SECS: u64 = 86400;
+
+/// Longest machine-token lifetime, in seconds (30 days). The tokens cannot be
+/// revoked, so tools get a new one instead of a long-lived one.
+pub const MACHINE_TOKEN_MAX_TTL_SECS: u64 = 30 * 86400;
+
+pub struct IssueMachineTokenInput {
+    pub client: String,
+    pub scopes: Vec<String>,
+    pub ttl_secs: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct IssueMachineTokenOutput {
+    pub token: String,
+    pub exp: u64,
+}
+
+pub struct IssueMachineTokenUseCase {
+    pub jwt_secret: String,
+}
+
+impl IssueMachineTokenUseCase {
+    /// Sign a scoped token for an internal tool on behalf of a staff user.
+    ///
+    /// Only [`UserRole::Bot`] callers may issue them, and never while
+    /// impersonating. The caller checks `ttl_secs` against
+    /// [`MACHINE_TOKEN_MAX_TTL_SECS`].
+    pub fn execute(
+        &self,
+        staff_id: UserId,
+        staff_role: u8,
+        staff_impersonator: Option<Uuid>,
+        input: IssueMachineTokenInput,
+    ) -> Result<IssueMachineTokenOutput, AuthServiceError> {
+        if staff_role != UserRole::Bot.as_u8() || staff_impersonator.is_some() {
+            return Err(AuthServiceError::Forbidden);
+        }
+
+        let exp = now_secs() + input.ttl_secs.unwrap_or(MACHINE_TOKEN_DEFAULT_TTL_SECS);
+        let token = issue_machine_token(&input.client, &input.scopes, exp, &self.jwt_secret)
+            .map_err(|e| AuthServiceError::Internal(e.into()))?;
+
+        tracing::info!(
+            target: "audit",
+            issuer = %staff_id,
+            client = %input.client,
+            scopes = %input.scopes.join(" "),
+            exp,
+            "machine token issued",
+        );
+
+        Ok(IssueMachineTokenOutput { token, exp })
+    }
+}
@@ -1,3 +1,12 @@
+pub mod api_token;
 pub mod authcode;
+pub mod export;
+pub mod id_alias;
+pub mod login_link;
+pub mod machine;
+pub mod oauth;
+pub mod outbox;
 pub mod passkey;
+pub mod session;
+pub mod stats;
 pub mod token;
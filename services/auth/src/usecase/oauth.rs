@@ -0,0 +1,207 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::Utc;
+use rand::RngExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use uuid::Uuid;
+
+use madome_auth_types::cookie::TokenLifetimes;
+
+use crate::domain::repository::{
+    LinkedIdentityRepository, OAuthClient, OAuthStateCache, SessionRepository, UserRepository,
+};
+use crate::domain::types::LinkedIdentity;
+use crate::error::AuthServiceError;
+use crate::usecase::session::{ClientInfo, start_session};
+use crate::usecase::token::{CreateTokenOutput, issue_access_token, issue_refresh_token};
+
+/// How long a started authorization can be finished; the state cookie lasts
+/// as long.
+pub use crate::domain::types::OAUTH_STATE_TTL_SECS;
+
+/// Authorization in flight between start and callback, cached under its `state`.
+#[derive(Serialize, Deserialize)]
+struct PendingAuthorization {
+    provider: String,
+    code_verifier: String,
+}
+
+/// 32 random bytes, base64url without padding (43 chars).
+fn random_token() -> String {
+    let bytes: [u8; 32] = rand::rng().random();
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// PKCE S256 challenge for `verifier` (RFC 7636 §4.2).
+pub fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+// ── Start ────────────────────────────────────────────────────────────────────
+
+#[derive(Debug)]
+pub struct StartOAuthOutput {
+    pub authorize_url: String,
+    /// Also kept in the browser, to be handed back with the callback.
+    pub state: String,
+}
+
+pub struct StartOAuthUseCase<O: OAuthClient, C: OAuthStateCache> {
+    pub oauth: O,
+    pub cache: C,
+}
+
+impl<O: OAuthClient, C: OAuthStateCache> StartOAuthUseCase<O, C> {
+    /// Returns the provider URL the browser should be sent to.
+    ///
+    /// Returns 404 if `provider` is not configured.
+    pub async fn execute(&self, provider: &str) -> Result<StartOAuthOutput, AuthServiceError> {
+        let state = random_token();
+        let code_verifier = random_token();
+        let authorize_url =
+            self.oauth
+                .authorize_url(provider, &state, &pkce_challenge(&code_verifier))?;
+
+        let pending = PendingAuthorization {
+            provider: provider.to_owned(),
+            code_verifier,
+        };
+        let pending_json =
+            serde_json::to_vec(&pending).map_err(|e| AuthServiceError::Internal(e.into()))?;
+        self.cache.set_state(&state, &pending_json).await?;
+
+        Ok(StartOAuthOutput {
+            authorize_url,
+            state,
+        })
+    }
+}
+
+// ── Callback ─────────────────────────────────────────────────────────────────
+
+pub struct FinishOAuthInput {
+    pub provider: String,
+    pub code: String,
+    pub state: String,
+    /// `state` as the browser kept it at start; `None` if it kept nothing.
+    pub browser_state: Option<String>,
+    pub client: ClientInfo,
+}
+
+pub struct FinishOAuthUseCase<
+    U: UserRepository,
+    L: LinkedIdentityRepository,
+    S: SessionRepository,
+    O: OAuthClient,
+    C: OAuthStateCache,
+> {
+    pub users: U,
+    pub identities: L,
+    pub sessions: S,
+    pub oauth: O,
+    pub cache: C,
+    pub jwt_secret: String,
+    pub lifetimes: TokenLifetimes,
+}
+
+impl<U, L, S, O, C> FinishOAuthUseCase<U, L, S, O, C>
+where
+    U: UserRepository,
+    L: LinkedIdentityRepository,
+    S: SessionRepository,
+    O: OAuthClient,
+    C: OAuthStateCache,
+{
+    /// Complete the authorization-code flow and sign the user in.
+    ///
+    /// A provider account already linked signs in its user. Otherwise it is linked
+    /// to the user whose email matches the provider's *verified* email; accounts
+    /// are never created here. Returns 401 for an unknown/expired `state`, one
+    /// the browser did not start, a rejected code, or an unverified email, and
+    /// 404 if no user has that email.
+    pub async fn execute(
+        &self,
+        input: FinishOAuthInput,
+    ) -> Result<CreateTokenOutput, AuthServiceError> {
+        // A callback URL opened in another browser would sign that browser in
+        // to whoever started the flow. Checked before the state is consumed,
+        // so the browser that did start it can still finish.
+        let started_here = input
+            .browser_state
+            .as_deref()
+            .is_some_and(|kept| bool::from(kept.as_bytes().ct_eq(input.state.as_bytes())));
+        if !started_here {
+            return Err(AuthServiceError::Unauthorized);
+        }
+
+        let pending_json = self
+            .cache
+            .take_state(&input.state)
+            .await?
+            .ok_or(AuthServiceError::Unauthorized)?;
+        let pending: PendingAuthorization = serde_json::from_slice(&pending_json)
+            .map_err(|e| AuthServiceError::Internal(e.into()))?;
+        if pending.provider != input.provider {
+            return Err(AuthServiceError::Unauthorized);
+        }
+
+        let profile = self
+            .oauth
+            .exchange_code(&input.provider, &input.code, &pending.code_verifier)
+            .await?;
+
+        let linked = self
+            .identities
+            .find_by_subject(&input.provider, &profile.subject)
+            .await?;
+        let user = match linked {
+            Some(identity) => self
+                .users
+                .find_by_id(identity.user_id)
+                .await?
+                .ok_or(AuthServiceError::Unauthorized)?,
+            None => {
+                let email = profile
+                    .email
+                    .filter(|_| profile.email_verified)
+                    .ok_or(AuthServiceError::Unauthorized)?;
+                let user = self
+                    .users
+                    .find_by_email(&email)
+                    .await?
+                    .ok_or(AuthServiceError::NotFound)?;
+                self.identities
+                    .create(&LinkedIdentity {
+                        id: Uuid::new_v4(),
+                        user_id: user.id,
+                        provider: input.provider.clone(),
+                        subject: profile.subject,
+                        email,
+                        created_at: Utc::now(),
+                    })
+                    .await?;
+                user
+            }
+        };
+
+        let session_id = start_session(&self.sessions, user.id, &input.client).await?;
+
+        let (access_token, access_token_exp) =
+            issue_access_token(&user, &self.jwt_secret, self.lifetimes.access_secs)?;
+        let refresh_token = issue_refresh_token(
+            &user,
+            session_id,
+            &self.jwt_secret,
+            self.lifetimes.refresh_secs,
+            true,
+        )?;
+
+        Ok(CreateTokenOutput {
+            user,
+            access_token,
+            access_token_exp,
+            refresh_token,
+        })
+    }
+}
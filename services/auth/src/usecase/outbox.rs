@@ -0,0 +1,144 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use madome_domain::id::UserId;
+use madome_domain::pagination::{PageRequest, Paged};
+
+use crate::domain::repository::OutboxRepository;
+use crate::domain::types::{OutboxEntry, OutboxStatus};
+use crate::error::AuthServiceError;
+
+/// Where an outbox event is in its delivery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    Pending,
+    Processed,
+    /// Dead-lettered, waiting for an operator.
+    Failed,
+}
+
+impl From<DeliveryStatus> for OutboxStatus {
+    fn from(status: DeliveryStatus) -> Self {
+        match status {
+            DeliveryStatus::Pending => Self::Pending,
+            DeliveryStatus::Processed => Self::Processed,
+            DeliveryStatus::Failed => Self::Failed,
+        }
+    }
+}
+
+impl From<OutboxStatus> for DeliveryStatus {
+    fn from(status: OutboxStatus) -> Self {
+        match status {
+            OutboxStatus::Pending => Self::Pending,
+            OutboxStatus::Processed => Self::Processed,
+            OutboxStatus::Failed => Self::Failed,
+        }
+    }
+}
+
+/// Payload fields that would let the reader sign in as the recipient, such as
+/// the plain code of `authcode_created`. The mail worker reads the stored
+/// payload; staff listings only need to know the field was there.
+const SECRET_PAYLOAD_FIELDS: &[&str] = &["code", "token"];
+
+const REDACTED: &str = "[redacted]";
+
+fn redact(mut payload: serde_json::Value) -> serde_json::Value {
+    if let Some(fields) = payload.as_object_mut() {
+        for (key, value) in fields.iter_mut() {
+            if SECRET_PAYLOAD_FIELDS.contains(&key.as_str()) {
+                *value = REDACTED.into();
+            }
+        }
+    }
+    payload
+}
+
+#[derive(Debug)]
+pub struct OutboxEventInfo {
+    pub id: Uuid,
+    pub kind: String,
+    /// The stored payload with secret fields redacted.
+    pub payload: serde_json::Value,
+    pub idempotency_key: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub processed_at: Option<DateTime<Utc>>,
+    pub failed_at: Option<DateTime<Utc>>,
+}
+
+impl From<OutboxEntry> for OutboxEventInfo {
+    fn from(entry: OutboxEntry) -> Self {
+        Self {
+            status: entry.status().into(),
+            id: entry.event.id,
+            kind: entry.event.kind,
+            payload: redact(entry.event.payload),
+            idempotency_key: entry.event.idempotency_key,
+            attempts: entry.attempts,
+            last_error: entry.last_error,
+            created_at: entry.created_at,
+            next_attempt_at: entry.next_attempt_at,
+            processed_at: entry.processed_at,
+            failed_at: entry.failed_at,
+        }
+    }
+}
+
+// ── List outbox events ───────────────────────────────────────────────────────
+
+pub struct ListOutboxEventsUseCase<O: OutboxRepository> {
+    pub outbox: O,
+}
+
+impl<O: OutboxRepository> ListOutboxEventsUseCase<O> {
+    pub async fn execute(
+        &self,
+        status: DeliveryStatus,
+        page: PageRequest,
+    ) -> Result<Paged<OutboxEventInfo>, AuthServiceError> {
+        let list = self.outbox.list(status.into(), page.clamped()).await?;
+        Ok(list.map(OutboxEventInfo::from))
+    }
+}
+
+// ── Retry a failed event ─────────────────────────────────────────────────────
+
+pub struct RetryOutboxEventUseCase<O: OutboxRepository> {
+    pub outbox: O,
+}
+
+impl<O: OutboxRepository> RetryOutboxEventUseCase<O> {
+    /// Hand a dead-lettered event back to the worker.
+    ///
+    /// Returns 404 unless the event exists and has failed; pending events are
+    /// already queued and processed ones must not be delivered twice.
+    pub async fn execute(&self, operator_id: UserId, id: Uuid) -> Result<(), AuthServiceError> {
+        if !self.outbox.retry_failed(id).await? {
+            return Err(AuthServiceError::NotFound);
+        }
+        tracing::info!(target: "audit", operator = %operator_id, event_id = %id, "outbox event requeued");
+        Ok(())
+    }
+}
+
+// ── Delete a failed event ────────────────────────────────────────────────────
+
+pub struct DeleteOutboxEventUseCase<O: OutboxRepository> {
+    pub outbox: O,
+}
+
+impl<O: OutboxRepository> DeleteOutboxEventUseCase<O> {
+    /// Discard a dead-lettered event. Returns 404 unless the event has failed.
+    pub async fn execute(&self, operator_id: UserId, id: Uuid) -> Result<(), AuthServiceError> {
+        if !self.outbox.delete_failed(id).await? {
+            return Err(AuthServiceError::NotFound);
+        }
+        tracing::info!(target: "audit", operator = %operator_id, event_id = %id, "outbox event discarded");
+        Ok(())
+    }
+}
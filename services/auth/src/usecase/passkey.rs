@@ -1,13 +1,22 @@
 use std::sync::Arc;
 
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 use chrono::Utc;
+use serde_json::json;
 use uuid::Uuid;
 use webauthn_rs::prelude::*;
 
-use crate::domain::repository::{PasskeyCache, PasskeyRepository, UserRepository};
-use crate::domain::types::PasskeyRecord;
+use madome_auth_types::cookie::TokenLifetimes;
+use madome_domain::id::UserId;
+
+use crate::domain::repository::{
+    PasskeyCache, PasskeyRepository, SessionRepository, UserRepository,
+};
+use crate::domain::types::{OutboxEvent, PASSKEY_NOT_BACKED_UP_EVENT, PasskeyRecord};
 use crate::error::AuthServiceError;
+use crate::usecase::session::{ClientInfo, start_session};
 use crate::usecase::token::{CreateTokenOutput, issue_access_token, issue_refresh_token};
+use crate::webauthn_util::{AttestedCredential, parse_attestation};
 
 // ── List passkeys ─────────────────────────────────────────────────────────────
 
@@ -17,16 +26,22 @@ pub struct ListPasskeysUseCase<P: PasskeyRepository> {
 
 pub struct PasskeyInfo {
     pub credential_id: Vec<u8>,
+    pub backup_eligible: bool,
+    pub backup_state: bool,
+    pub transports: Vec<String>,
     pub created_at: chrono::DateTime<Utc>,
 }
 
 impl<P: PasskeyRepository> ListPasskeysUseCase<P> {
-    pub async fn execute(&self, user_id: Uuid) -> Result<Vec<PasskeyInfo>, AuthServiceError> {
+    pub async fn execute(&self, user_id: UserId) -> Result<Vec<PasskeyInfo>, AuthServiceError> {
         let records = self.passkeys.list_by_user(user_id).await?;
         Ok(records
             .into_iter()
             .map(|r| PasskeyInfo {
                 credential_id: r.credential_id,
+                backup_eligible: r.backup_eligible,
+                backup_state: r.backup_state,
+                transports: r.transports,
                 created_at: r.created_at,
             })
             .collect())
@@ -40,12 +55,17 @@ pub struct DeletePasskeyUseCase<P: PasskeyRepository> {
 }
 
 impl<P: PasskeyRepository> DeletePasskeyUseCase<P> {
-    /// Returns 404 if not found or belongs to a different user.
+    /// Returns 404 if not found or belongs to a different user. Forbidden
+    /// while impersonating, like every change to how the user signs in.
     pub async fn execute(
         &self,
         credential_id: &[u8],
-        user_id: Uuid,
+        user_id: UserId,
+        impersonator: Option<Uuid>,
     ) -> Result<(), AuthServiceError> {
+        if impersonator.is_some() {
+            return Err(AuthServiceError::Forbidden);
+        }
         let deleted = self.passkeys.delete(credential_id, user_id).await?;
         if !deleted {
             return Err(AuthServiceError::NotFound);
@@ -69,10 +89,16 @@ pub struct StartRegistrationUseCase<U: UserRepository, P: PasskeyRepository, C:
 }
 
 impl<U: UserRepository, P: PasskeyRepository, C: PasskeyCache> StartRegistrationUseCase<U, P, C> {
+    /// Forbidden while impersonating: a staff member must not leave a way to
+    /// sign in as the user behind.
     pub async fn execute(
         &self,
-        user_id: Uuid,
+        user_id: UserId,
+        impersonator: Option<Uuid>,
     ) -> Result<StartRegistrationOutput, AuthServiceError> {
+        if impersonator.is_some() {
+            return Err(AuthServiceError::Forbidden);
+        }
         let user = self
             .users
             .find_by_id(user_id)
@@ -94,7 +120,7 @@ impl<U: UserRepository, P: PasskeyRepository, C: PasskeyCache> StartRegistration
 
         let (ccr, reg_state) = self
             .webauthn
-            .start_passkey_registration(user_id, &user.email, &user.email, exclude)
+            .start_passkey_registration(user_id.into(), &user.email, &user.email, exclude)
             .map_err(|e| AuthServiceError::Internal(anyhow::anyhow!("{e}")))?;
 
         let reg_id = Uuid::new_v4().to_string();
@@ -120,12 +146,18 @@ pub struct FinishRegistrationUseCase<P: PasskeyRepository, C: PasskeyCache> {
 }
 
 impl<P: PasskeyRepository, C: PasskeyCache> FinishRegistrationUseCase<P, C> {
+    /// Forbidden while impersonating, even for a registration the user
+    /// started themselves.
     pub async fn execute(
         &self,
-        user_id: Uuid,
+        user_id: UserId,
+        impersonator: Option<Uuid>,
         registration_id: &str,
         credential: RegisterPublicKeyCredential,
     ) -> Result<(), AuthServiceError> {
+        if impersonator.is_some() {
+            return Err(AuthServiceError::Forbidden);
+        }
         let state_json = self
             .cache
             .take_registration_state(user_id, registration_id)
@@ -141,22 +173,70 @@ impl<P: PasskeyRepository, C: PasskeyCache> FinishRegistrationUseCase<P, C> {
             .map_err(|e| AuthServiceError::BadRequest(e.to_string()))?;
 
         let cred_id = passkey.cred_id().to_vec();
-        let aaguid = parse_aaguid_from_credential(&credential).unwrap_or(Uuid::nil());
+        // The AAGUID and flags only describe the passkey; a registration
+        // webauthn-rs accepted is not refused over them.
+        let attested =
+            parse_attestation(&credential.response.attestation_object).unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "cannot read passkey attestation data");
+                AttestedCredential {
+                    aaguid: Uuid::nil(),
+                    backup_eligible: false,
+                    backup_state: false,
+                }
+            });
+        let transports = credential
+            .response
+            .transports
+            .iter()
+            .flatten()
+            .filter_map(|t| serde_json::to_value(t).ok()?.as_str().map(str::to_owned))
+            .collect();
         let credential_bytes =
             serde_json::to_vec(&passkey).map_err(|e| AuthServiceError::Internal(e.into()))?;
 
         let record = PasskeyRecord {
             credential_id: cred_id,
             user_id,
-            aaguid,
+            aaguid: attested.aaguid,
             credential: credential_bytes,
+            backup_eligible: attested.backup_eligible,
+            backup_state: attested.backup_state,
+            transports,
             created_at: Utc::now(),
         };
-        self.passkeys.create(&record).await?;
+        let is_only_passkey = self.passkeys.list_by_user(user_id).await?.is_empty();
+        if is_only_passkey && !record.backup_state {
+            self.passkeys
+                .create_with_outbox(&record, &not_backed_up_event(&record))
+                .await?;
+        } else {
+            self.passkeys.create(&record).await?;
+        }
         Ok(())
     }
 }
 
+/// Build the `passkey.not_backed_up` event for a user's first passkey.
+///
+/// Keyed by credential, so a retried registration warns once.
+fn not_backed_up_event(record: &PasskeyRecord) -> OutboxEvent {
+    OutboxEvent {
+        id: Uuid::new_v4(),
+        kind: PASSKEY_NOT_BACKED_UP_EVENT.to_owned(),
+        payload: json!({
+            "user_id": record.user_id,
+            "credential_id": URL_SAFE_NO_PAD.encode(&record.credential_id),
+            "aaguid": record.aaguid,
+            "backup_eligible": record.backup_eligible,
+            "registered_at": record.created_at,
+        }),
+        idempotency_key: format!(
+            "{PASSKEY_NOT_BACKED_UP_EVENT}:{}",
+            URL_SAFE_NO_PAD.encode(&record.credential_id)
+        ),
+    }
+}
+
 // ── Start authentication ──────────────────────────────────────────────────────
 
 pub struct StartAuthenticationOutput {
@@ -212,22 +292,31 @@ impl<U: UserRepository, P: PasskeyRepository, C: PasskeyCache> StartAuthenticati
 
 // ── Finish authentication ─────────────────────────────────────────────────────
 
-pub struct FinishAuthenticationUseCase<U: UserRepository, P: PasskeyRepository, C: PasskeyCache> {
+pub struct FinishAuthenticationUseCase<
+    U: UserRepository,
+    P: PasskeyRepository,
+    C: PasskeyCache,
+    S: SessionRepository,
+> {
     pub users: U,
     pub passkeys: P,
     pub cache: C,
+    pub sessions: S,
     pub webauthn: Arc<Webauthn>,
     pub jwt_secret: String,
+    pub lifetimes: TokenLifetimes,
 }
 
-impl<U: UserRepository, P: PasskeyRepository, C: PasskeyCache>
-    FinishAuthenticationUseCase<U, P, C>
+impl<U: UserRepository, P: PasskeyRepository, C: PasskeyCache, S: SessionRepository>
+    FinishAuthenticationUseCase<U, P, C, S>
 {
     pub async fn execute(
         &self,
         email: &str,
         authentication_id: &str,
         credential: PublicKeyCredential,
+        client: &ClientInfo,
+        remember: bool,
     ) -> Result<CreateTokenOutput, AuthServiceError> {
         let user = self
             .users
@@ -255,19 +344,32 @@ impl<U: UserRepository, P: PasskeyRepository, C: PasskeyCache>
             .finish_passkey_authentication(&credential, &auth_state)
             .map_err(|e| AuthServiceError::BadRequest(e.to_string()))?;
 
-        // Persist counter updates for any passkey that changed.
+        // Persist counter and backup-state updates for the passkey that was used.
         for (pk, record) in passkey_list.iter_mut().zip(stored.iter()) {
             if pk.update_credential(&auth_result) == Some(true) {
                 let updated_bytes =
                     serde_json::to_vec(&pk).map_err(|e| AuthServiceError::Internal(e.into()))?;
                 self.passkeys
-                    .update_credential(&record.credential_id, &updated_bytes)
+                    .update_credential(
+                        &record.credential_id,
+                        &updated_bytes,
+                        auth_result.backup_state(),
+                    )
                     .await?;
             }
         }
 
-        let (access_token, access_token_exp) = issue_access_token(&user, &self.jwt_secret)?;
-        let refresh_token = issue_refresh_token(&user, &self.jwt_secret)?;
+        let session_id = start_session(&self.sessions, user.id, client).await?;
+
+        let (access_token, access_token_exp) =
+            issue_access_token(&user, &self.jwt_secret, self.lifetimes.access_secs)?;
+        let refresh_token = issue_refresh_token(
+            &user,
+            session_id,
+            &self.jwt_secret,
+            self.lifetimes.refresh_for(remember),
+            remember,
+        )?;
 
         Ok(CreateTokenOutput {
             user,
@@ -277,38 +379,3 @@ impl<U: UserRepository, P: PasskeyRepository, C: PasskeyCache>
         })
     }
 }
-
-// ── AAGUID extraction ─────────────────────────────────────────────────────────
-
-/// Extract the AAGUID from a `RegisterPublicKeyCredential` by parsing its
-/// raw attestation object (CBOR). Per the WebAuthn spec the AAGUID occupies
-/// bytes 37..53 of the `authData` field inside the attestation object.
-///
-/// Mirrors the legacy `parse_aaguid` implementation in
-/// `previous/auth-madome-app`.
-fn parse_aaguid_from_credential(credential: &RegisterPublicKeyCredential) -> Option<Uuid> {
-    // https://www.rfc-editor.org/rfc/rfc8949.html#section-3.2.2
-    let mut decoder = minicbor::Decoder::new(&credential.response.attestation_object);
-    decoder.map().ok()?;
-
-    // fmt
-    decoder.skip().ok()?;
-    decoder.skip().ok()?;
-
-    // attStmt
-    decoder.skip().ok()?;
-    decoder.skip().ok()?;
-
-    let key = decoder.str().ok()?;
-    if key != "authData" {
-        return None;
-    }
-    let auth_data = decoder.bytes().ok()?;
-    if auth_data.len() < 53 {
-        return None;
-    }
-
-    let mut aaguid = [0u8; 16];
-    aaguid.copy_from_slice(&auth_data[37..53]);
-    Some(Uuid::from_bytes(aaguid))
-}
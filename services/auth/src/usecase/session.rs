@@ -0,0 +1,140 @@
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use uuid::Uuid;
+
+use madome_domain::id::UserId;
+
+use crate::domain::repository::SessionRepository;
+use crate::domain::types::{
+    LOGIN_NEW_DEVICE_EVENT, NEW_DEVICE_NOTIFY_WINDOW_SECS, OutboxEvent, Session,
+};
+use crate::error::AuthServiceError;
+
+/// Client context captured from the login/refresh request.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+fn new_session(user_id: UserId, client: &ClientInfo) -> Session {
+    let now = Utc::now();
+    Session {
+        id: Uuid::new_v4(),
+        user_id,
+        user_agent: client.user_agent.clone(),
+        ip: client.ip.clone(),
+        created_at: now,
+        last_used_at: now,
+    }
+}
+
+/// Create a new session for a sign-in and return its id (the refresh token `sid`).
+///
+/// If the user already has sessions and none of them matches this client's
+/// user agent and IP, a `login.new_device` outbox event is written with the
+/// session. The first sign-in (no sessions to compare against) is not reported.
+pub(crate) async fn start_session<S: SessionRepository>(
+    sessions: &S,
+    user_id: UserId,
+    client: &ClientInfo,
+) -> Result<Uuid, AuthServiceError> {
+    let session = new_session(user_id, client);
+    let known = sessions.list_by_user(user_id).await?;
+    let is_new_device = !known.is_empty()
+        && !known
+            .iter()
+            .any(|s| s.user_agent == session.user_agent && s.ip == session.ip);
+
+    if is_new_device {
+        sessions
+            .create_with_outbox(&session, &new_device_event(&session))
+            .await?;
+    } else {
+        sessions.create(&session).await?;
+    }
+    Ok(session.id)
+}
+
+/// Create a session for a refresh token issued before sessions were tracked.
+///
+/// The device is already signed in, so no new-device event is emitted.
+pub(crate) async fn adopt_session<S: SessionRepository>(
+    sessions: &S,
+    user_id: UserId,
+    client: &ClientInfo,
+) -> Result<Uuid, AuthServiceError> {
+    let session = new_session(user_id, client);
+    sessions.create(&session).await?;
+    Ok(session.id)
+}
+
+/// Build the `login.new_device` event for `session`.
+///
+/// The idempotency key buckets time into `NEW_DEVICE_NOTIFY_WINDOW_SECS` windows,
+/// so repeated new-device sign-ins inside one window produce a single event.
+fn new_device_event(session: &Session) -> OutboxEvent {
+    let window = session.created_at.timestamp() / NEW_DEVICE_NOTIFY_WINDOW_SECS;
+    OutboxEvent {
+        id: Uuid::new_v4(),
+        kind: LOGIN_NEW_DEVICE_EVENT.to_owned(),
+        payload: json!({
+            "user_id": session.user_id,
+            "session_id": session.id,
+            "user_agent": session.user_agent,
+            "ip": session.ip,
+            "signed_in_at": session.created_at,
+        }),
+        idempotency_key: format!("{LOGIN_NEW_DEVICE_EVENT}:{}:{window}", session.user_id),
+    }
+}
+
+// ── List sessions ─────────────────────────────────────────────────────────────
+
+pub struct SessionInfo {
+    pub id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+}
+
+pub struct ListSessionsUseCase<S: SessionRepository> {
+    pub sessions: S,
+}
+
+impl<S: SessionRepository> ListSessionsUseCase<S> {
+    pub async fn execute(&self, user_id: UserId) -> Result<Vec<SessionInfo>, AuthServiceError> {
+        let sessions = self.sessions.list_by_user(user_id).await?;
+        Ok(sessions
+            .into_iter()
+            .map(|s| SessionInfo {
+                id: s.id,
+                user_agent: s.user_agent,
+                ip: s.ip,
+                created_at: s.created_at,
+                last_used_at: s.last_used_at,
+            })
+            .collect())
+    }
+}
+
+// ── Delete session ────────────────────────────────────────────────────────────
+
+pub struct DeleteSessionUseCase<S: SessionRepository> {
+    pub sessions: S,
+}
+
+impl<S: SessionRepository> DeleteSessionUseCase<S> {
+    /// Returns 404 if not found or belongs to a different user.
+    ///
+    /// The device keeps its current access token until it expires; its next
+    /// refresh fails with 401.
+    pub async fn execute(&self, session_id: Uuid, user_id: UserId) -> Result<(), AuthServiceError> {
+        let deleted = self.sessions.delete(session_id, user_id).await?;
+        if !deleted {
+            return Err(AuthServiceError::NotFound);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use crate::domain::repository::StatsRepository;
+use crate::domain::types::{DailyStats, MAX_STATS_DAYS, STATS_ROLLUP_INTERVAL_SECS};
+use crate::error::AuthServiceError;
+
+// ── Roll up daily stats ──────────────────────────────────────────────────────
+
+pub struct RollupDailyStatsUseCase<S: StatsRepository> {
+    pub stats: S,
+}
+
+impl<S: StatsRepository> RollupDailyStatsUseCase<S> {
+    /// Recount today, and yesterday too on the first run after midnight so
+    /// the activity since the previous run is not lost.
+    pub async fn execute(&self, now: DateTime<Utc>) -> Result<(), AuthServiceError> {
+        let today = now.date_naive();
+        let previous_run = (now - Duration::seconds(STATS_ROLLUP_INTERVAL_SECS)).date_naive();
+        if previous_run != today {
+            self.stats.rollup(previous_run).await?;
+        }
+        self.stats.rollup(today).await?;
+        Ok(())
+    }
+}
+
+// ── List daily stats ─────────────────────────────────────────────────────────
+
+#[derive(Debug)]
+pub struct DailyStatsInfo {
+    pub day: NaiveDate,
+    pub active_users: u64,
+    pub sign_ins: u64,
+}
+
+impl From<DailyStats> for DailyStatsInfo {
+    fn from(s: DailyStats) -> Self {
+        Self {
+            day: s.day,
+            active_users: s.active_users,
+            sign_ins: s.sign_ins,
+        }
+    }
+}
+
+pub struct ListDailyStatsUseCase<S: StatsRepository> {
+    pub stats: S,
+}
+
+impl<S: StatsRepository> ListDailyStatsUseCase<S> {
+    /// Days from `from` to `to`, both inclusive. A reversed range or one
+    /// longer than [`MAX_STATS_DAYS`] is a 400.
+    pub async fn execute(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DailyStatsInfo>, AuthServiceError> {
+        let days = (to - from).num_days() + 1;
+        if days < 1 {
+            return Err(AuthServiceError::BadRequest(
+                "`from` is after `to`".to_owned(),
+            ));
+        }
+        if days > MAX_STATS_DAYS {
+            return Err(AuthServiceError::BadRequest(format!(
+                "at most {MAX_STATS_DAYS} days at a time"
+            )));
+        }
+        let list = self.stats.list(from, to).await?;
+        Ok(list.into_iter().map(DailyStatsInfo::from).collect())
+    }
+}
@@ -3,11 +3,26 @@ use serde::{Deserialize, Serialize};
 use std::time::{SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
-use madome_auth_types::cookie::{ACCESS_TOKEN_EXP, REFRESH_TOKEN_EXP};
+use madome_auth_types::cookie::TokenLifetimes;
+use madome_core::rate_limit::RateLimit;
+use madome_domain::id::UserId;
+use madome_domain::user::UserRole;
 
-use crate::domain::repository::{AuthCodeRepository, UserRepository};
-use crate::domain::types::AuthUser;
+use crate::domain::repository::{
+    AuthCodeAttemptStore, AuthCodeRepository, SessionRepository, UserRepository,
+};
+use crate::domain::types::{AUTHCODE_LOCKOUT_SECS, AuthUser, MAX_AUTHCODE_ATTEMPTS};
 use crate::error::AuthServiceError;
+use crate::usecase::authcode::hash_code;
+use crate::usecase::session::{ClientInfo, adopt_session, start_session};
+
+/// What a token may be used for, carried in the `typ` claim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenType {
+    Access,
+    Refresh,
+}
 
 /// JWT claims for both access and refresh tokens.
 #[derive(Debug, Serialize, Deserialize)]
@@ -15,24 +30,56 @@ pub struct TokenClaims {
     pub sub: String,
     pub role: u8,
     pub exp: u64,
+    /// Absent on tokens issued before the claim was added, the legacy
+    /// service's included.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub typ: Option<TokenType>,
+    /// Session id. Set on refresh tokens only; absent on access tokens and on
+    /// refresh tokens issued before sessions were tracked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Uuid>,
+    /// Staff user acting as `sub`. Set on impersonation access tokens only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonator: Option<Uuid>,
+    /// Set on refresh tokens of a sign-in without "remember me", so refreshing
+    /// keeps the short lifetime and session cookies.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub session_only: bool,
+}
+
+impl TokenClaims {
+    /// Whether these are a refresh token's claims: `typ` says so, or, on
+    /// tokens issued before `typ`, the session id does.
+    pub fn is_refresh(&self) -> bool {
+        self.typ == Some(TokenType::Refresh) || self.sid.is_some()
+    }
 }
 
-fn now_secs() -> u64 {
+/// Impersonation access-token lifetime in seconds (15 minutes).
+pub const IMPERSONATION_TOKEN_EXP: u64 = 900;
+
+pub(crate) fn now_secs() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .expect("system clock before UNIX epoch")
         .as_secs()
 }
 
+/// Sign an access token valid for `ttl_secs`; returns it with its expiry.
 pub fn issue_access_token(
     user: &AuthUser,
     secret: &str,
+    ttl_secs: u64,
 ) -> Result<(String, u64), AuthServiceError> {
-    let exp = now_secs() + ACCESS_TOKEN_EXP;
+    let exp = now_secs() + ttl_secs;
     let claims = TokenClaims {
         sub: user.id.to_string(),
         role: user.role,
         exp,
+        typ: Some(TokenType::Access),
+        sid: None,
+        impersonator: None,
+        session_only: false,
     };
     let token = encode(
         &Header::default(),
@@ -43,12 +90,24 @@ pub fn issue_access_token(
     Ok((token, exp))
 }
 
-pub fn issue_refresh_token(user: &AuthUser, secret: &str) -> Result<String, AuthServiceError> {
-    let exp = now_secs() + REFRESH_TOKEN_EXP;
+/// Sign a refresh token for `session_id` valid for `ttl_secs`; `remember` is
+/// carried over to the tokens it is refreshed into.
+pub fn issue_refresh_token(
+    user: &AuthUser,
+    session_id: Uuid,
+    secret: &str,
+    ttl_secs: u64,
+    remember: bool,
+) -> Result<String, AuthServiceError> {
+    let exp = now_secs() + ttl_secs;
     let claims = TokenClaims {
         sub: user.id.to_string(),
         role: user.role,
         exp,
+        typ: Some(TokenType::Refresh),
+        sid: Some(session_id),
+        impersonator: None,
+        session_only: !remember,
     };
     encode(
         &Header::default(),
@@ -75,11 +134,27 @@ pub fn validate_token(token: &str, secret: &str) -> Result<TokenClaims, AuthServ
     Ok(data.claims)
 }
 
+/// [`validate_token`] with `secret`, then with `previous`: the key the last
+/// rotation replaced, while it is kept to verify what it signed.
+pub fn validate_token_with_previous(
+    token: &str,
+    secret: &str,
+    previous: Option<&str>,
+) -> Result<TokenClaims, AuthServiceError> {
+    validate_token(token, secret).or_else(|e| match previous {
+        Some(previous) => validate_token(token, previous),
+        None => Err(e),
+    })
+}
+
 // ── CreateToken (login) ───────────────────────────────────────────────────────
 
 pub struct CreateTokenInput {
     pub email: String,
     pub code: String,
+    pub client: ClientInfo,
+    /// Keep the user signed in across browser restarts.
+    pub remember: bool,
 }
 
 #[derive(Debug)]
@@ -90,13 +165,27 @@ pub struct CreateTokenOutput {
     pub refresh_token: String,
 }
 
-pub struct CreateTokenUseCase<U: UserRepository, A: AuthCodeRepository> {
+pub struct CreateTokenUseCase<
+    U: UserRepository,
+    A: AuthCodeRepository,
+    T: AuthCodeAttemptStore,
+    S: SessionRepository,
+> {
     pub users: U,
     pub auth_codes: A,
+    pub attempts: T,
+    pub sessions: S,
     pub jwt_secret: String,
+    /// Key replaced by the last rotation, still accepted when verifying.
+    pub previous_jwt_secret: Option<String>,
+    pub lifetimes: TokenLifetimes,
 }
 
-impl<U: UserRepository, A: AuthCodeRepository> CreateTokenUseCase<U, A> {
+impl<U: UserRepository, A: AuthCodeRepository, T: AuthCodeAttemptStore, S: SessionRepository>
+    CreateTokenUseCase<U, A, T, S>
+{
+    /// After [`MAX_AUTHCODE_ATTEMPTS`] wrong codes every code is refused, the
+    /// right one included, until the lock expires.
     pub async fn execute(
         &self,
         input: CreateTokenInput,
@@ -107,16 +196,43 @@ impl<U: UserRepository, A: AuthCodeRepository> CreateTokenUseCase<U, A> {
             .await?
             .ok_or(AuthServiceError::NotFound)?;
 
-        let auth_code = self
+        if self.attempts.failures(user.id).await? >= MAX_AUTHCODE_ATTEMPTS {
+            return Err(AuthServiceError::TooManyAttempts(RateLimit::exhausted(
+                MAX_AUTHCODE_ATTEMPTS.into(),
+                AUTHCODE_LOCKOUT_SECS as u64,
+            )));
+        }
+
+        // Codes sent before a rotation were hashed with the previous key.
+        let mut auth_code = self
             .auth_codes
-            .find_valid(user.id, &input.code)
-            .await?
-            .ok_or(AuthServiceError::NotFound)?;
+            .find_valid(user.id, &hash_code(&self.jwt_secret, &input.code))
+            .await?;
+        if let (None, Some(previous)) = (&auth_code, &self.previous_jwt_secret) {
+            auth_code = self
+                .auth_codes
+                .find_valid(user.id, &hash_code(previous, &input.code))
+                .await?;
+        }
+        let Some(auth_code) = auth_code else {
+            self.attempts.record_failure(user.id).await?;
+            return Err(AuthServiceError::NotFound);
+        };
 
         self.auth_codes.mark_used(auth_code.id).await?;
+        self.attempts.clear(user.id).await?;
+
+        let session_id = start_session(&self.sessions, user.id, &input.client).await?;
 
-        let (access_token, access_token_exp) = issue_access_token(&user, &self.jwt_secret)?;
-        let refresh_token = issue_refresh_token(&user, &self.jwt_secret)?;
+        let (access_token, access_token_exp) =
+            issue_access_token(&user, &self.jwt_secret, self.lifetimes.access_secs)?;
+        let refresh_token = issue_refresh_token(
+            &user,
+            session_id,
+            &self.jwt_secret,
+            self.lifetimes.refresh_for(input.remember),
+            input.remember,
+        )?;
 
         Ok(CreateTokenOutput {
             user,
@@ -131,29 +247,57 @@ impl<U: UserRepository, A: AuthCodeRepository> CreateTokenUseCase<U, A> {
 
 #[derive(Debug)]
 pub struct RefreshTokenOutput {
-    pub user_id: Uuid,
+    pub user_id: UserId,
     pub user_role: u8,
     pub access_token: String,
     pub access_token_exp: u64,
     pub refresh_token: String,
+    /// Whether the sign-in chose "remember me".
+    pub remember: bool,
 }
 
-pub struct RefreshTokenUseCase<U: UserRepository> {
+pub struct RefreshTokenUseCase<U: UserRepository, S: SessionRepository> {
     pub users: U,
+    pub sessions: S,
     pub jwt_secret: String,
+    /// Key replaced by the last rotation, still accepted when verifying.
+    pub previous_jwt_secret: Option<String>,
+    pub lifetimes: TokenLifetimes,
+    /// When the legacy service stopped issuing tokens (Unix seconds); `None`
+    /// refuses every legacy refresh token.
+    pub legacy_cutoff: Option<u64>,
+}
+
+/// Whether `claims` without `typ` or `sid` can only be a refresh token the
+/// legacy service issued before `cutoff`: every access token from then has
+/// expired, and the refresh lifetime puts its issue time before the cutoff.
+fn is_legacy_refresh(claims: &TokenClaims, lifetimes: TokenLifetimes, cutoff: u64) -> bool {
+    claims.typ.is_none()
+        && claims.exp > cutoff + lifetimes.access_secs
+        && claims.exp <= cutoff + lifetimes.refresh_secs
 }
 
-impl<U: UserRepository> RefreshTokenUseCase<U> {
+impl<U: UserRepository, S: SessionRepository> RefreshTokenUseCase<U, S> {
     pub async fn execute(
         &self,
         refresh_token_value: &str,
+        client: &ClientInfo,
     ) -> Result<RefreshTokenOutput, AuthServiceError> {
         // Validate refresh token (sig + exp); expired access token is irrelevant here.
-        let claims = validate_token(refresh_token_value, &self.jwt_secret)?;
+        let claims = validate_token_with_previous(
+            refresh_token_value,
+            &self.jwt_secret,
+            self.previous_jwt_secret.as_deref(),
+        )?;
+        // Access tokens, impersonation ones included, must not mint a
+        // session and outlive their short expiry.
+        if claims.typ == Some(TokenType::Access) || claims.impersonator.is_some() {
+            return Err(AuthServiceError::Unauthorized);
+        }
 
         let user_id = claims
             .sub
-            .parse::<Uuid>()
+            .parse::<UserId>()
             .map_err(|_| AuthServiceError::Unauthorized)?;
 
         let user = self
@@ -162,8 +306,37 @@ impl<U: UserRepository> RefreshTokenUseCase<U> {
             .await?
             .ok_or(AuthServiceError::Unauthorized)?;
 
-        let (access_token, access_token_exp) = issue_access_token(&user, &self.jwt_secret)?;
-        let refresh_token = issue_refresh_token(&user, &self.jwt_secret)?;
+        let session_id = match claims.sid {
+            Some(sid) => {
+                // A deleted session means the device was signed out remotely.
+                self.sessions
+                    .find_by_id(sid, user.id)
+                    .await?
+                    .ok_or(AuthServiceError::Unauthorized)?;
+                self.sessions.touch(sid).await?;
+                sid
+            }
+            // Legacy refresh tokens carry no `sid`; adopt them into a new
+            // session instead of forcing a re-login.
+            None if self
+                .legacy_cutoff
+                .is_some_and(|cutoff| is_legacy_refresh(&claims, self.lifetimes, cutoff)) =>
+            {
+                adopt_session(&self.sessions, user.id, client).await?
+            }
+            None => return Err(AuthServiceError::Unauthorized),
+        };
+
+        let remember = !claims.session_only;
+        let (access_token, access_token_exp) =
+            issue_access_token(&user, &self.jwt_secret, self.lifetimes.access_secs)?;
+        let refresh_token = issue_refresh_token(
+            &user,
+            session_id,
+            &self.jwt_secret,
+            self.lifetimes.refresh_for(remember),
+            remember,
+        )?;
 
         Ok(RefreshTokenOutput {
             user_id: user.id,
@@ -171,6 +344,149 @@ impl<U: UserRepository> RefreshTokenUseCase<U> {
             access_token,
             access_token_exp,
             refresh_token,
+            remember,
+        })
+    }
+}
+
+// ── RevokeToken (logout) ─────────────────────────────────────────────────────
+
+pub struct RevokeTokenUseCase<S: SessionRepository> {
+    pub sessions: S,
+    pub jwt_secret: String,
+    /// Key replaced by the last rotation, still accepted when verifying.
+    pub previous_jwt_secret: Option<String>,
+}
+
+impl<S: SessionRepository> RevokeTokenUseCase<S> {
+    /// Delete the session referenced by the refresh token, if any.
+    ///
+    /// Best effort: a missing, invalid, or session-less refresh token is not an
+    /// error because logout must always clear the cookies.
+    pub async fn execute(
+        &self,
+        user_id: UserId,
+        refresh_token_value: Option<&str>,
+    ) -> Result<(), AuthServiceError> {
+        let Some(claims) = refresh_token_value.and_then(|v| {
+            validate_token_with_previous(v, &self.jwt_secret, self.previous_jwt_secret.as_deref())
+                .ok()
+        }) else {
+            return Ok(());
+        };
+        if let Some(sid) = claims.sid {
+            self.sessions.delete(sid, user_id).await?;
+        }
+        Ok(())
+    }
+}
+
+// ── IntrospectToken ──────────────────────────────────────────────────────────
+
+/// What a valid access token says about its holder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Introspection {
+    pub user_id: UserId,
+    pub user_role: u8,
+    pub exp: u64,
+    pub impersonator: Option<Uuid>,
+}
+
+pub struct IntrospectTokenUseCase {
+    pub jwt_secret: String,
+    /// Key replaced by the last rotation, still accepted when verifying.
+    pub previous_jwt_secret: Option<String>,
+}
+
+impl IntrospectTokenUseCase {
+    /// `None` for a token that is malformed, badly signed, expired, or not an
+    /// access token. Refresh tokens only ever go back to `PATCH /auth/token`.
+    pub fn execute(&self, token: &str) -> Option<Introspection> {
+        let claims = validate_token_with_previous(
+            token,
+            &self.jwt_secret,
+            self.previous_jwt_secret.as_deref(),
+        )
+        .ok()?;
+        if claims.is_refresh() {
+            return None;
+        }
+        Some(Introspection {
+            user_id: claims.sub.parse().ok()?,
+            user_role: claims.role,
+            exp: claims.exp,
+            impersonator: claims.impersonator,
+        })
+    }
+}
+
+// ── Impersonate ──────────────────────────────────────────────────────────────
+
+#[derive(Debug)]
+pub struct ImpersonateOutput {
+    pub user_id: UserId,
+    pub user_role: u8,
+    pub access_token: String,
+    pub access_token_exp: u64,
+}
+
+pub struct ImpersonateUseCase<U: UserRepository> {
+    pub users: U,
+    pub jwt_secret: String,
+}
+
+impl<U: UserRepository> ImpersonateUseCase<U> {
+    /// Mint a short-lived access token for `target_id` on behalf of a staff user.
+    ///
+    /// Only [`UserRole::Bot`] callers may impersonate, and never from a token
+    /// that is itself impersonated. No refresh token or session is issued, so
+    /// the access lasts [`IMPERSONATION_TOKEN_EXP`] at most.
+    pub async fn execute(
+        &self,
+        staff_id: UserId,
+        staff_role: u8,
+        staff_impersonator: Option<Uuid>,
+        target_id: UserId,
+    ) -> Result<ImpersonateOutput, AuthServiceError> {
+        if staff_role != UserRole::Bot.as_u8() || staff_impersonator.is_some() {
+            return Err(AuthServiceError::Forbidden);
+        }
+        let user = self
+            .users
+            .find_by_id(target_id)
+            .await?
+            .ok_or(AuthServiceError::NotFound)?;
+
+        let exp = now_secs() + IMPERSONATION_TOKEN_EXP;
+        let claims = TokenClaims {
+            sub: user.id.to_string(),
+            role: user.role,
+            exp,
+            typ: Some(TokenType::Access),
+            sid: None,
+            impersonator: Some(staff_id.into()),
+            session_only: false,
+        };
+        let access_token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(self.jwt_secret.as_bytes()),
+        )
+        .map_err(|e| AuthServiceError::Internal(e.into()))?;
+
+        tracing::info!(
+            target: "audit",
+            impersonator = %staff_id,
+            user_id = %user.id,
+            exp,
+            "impersonation token issued",
+        );
+
+        Ok(ImpersonateOutput {
+            user_id: user.id,
+            user_role: user.role,
+            access_token,
+            access_token_exp: exp,
         })
     }
 }
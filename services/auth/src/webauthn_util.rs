@@ -0,0 +1,134 @@
+//! WebAuthn helpers that `webauthn-rs` does not expose.
+//!
+//! `Passkey` keeps the AAGUID and backup flags of the authenticator that
+//! created it private, so they are read straight from the attestation object
+//! the client sent.
+
+use std::borrow::Cow;
+
+use minicbor::Decoder;
+use minicbor::data::Type;
+use uuid::Uuid;
+
+/// Backup eligible: the credential may be synced (authenticator data flags, bit 3).
+const FLAG_BE: u8 = 0x08;
+/// Backup state: the credential is currently backed up (bit 4).
+const FLAG_BS: u8 = 0x10;
+/// Attested credential data present (bit 6).
+const FLAG_AT: u8 = 0x40;
+
+/// Offset of the flags byte in authenticator data, after the 32-byte rpIdHash.
+const FLAGS: usize = 32;
+
+/// The AAGUID follows rpIdHash (32), flags (1) and signCount (4).
+const AAGUID: std::ops::Range<usize> = 37..53;
+
+/// What a registration's authenticator data says about the new credential.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttestedCredential {
+    /// Zero ([`Uuid::nil`]) for `none` attestation.
+    pub aaguid: Uuid,
+    pub backup_eligible: bool,
+    pub backup_state: bool,
+}
+
+/// Why an attestation object could not be read.
+#[derive(Debug, thiserror::Error)]
+pub enum AttestationError {
+    #[error("malformed attestation object: {0}")]
+    Malformed(#[from] minicbor::decode::Error),
+    #[error("attestation object is not a CBOR map")]
+    NotAMap,
+    #[error("attestation object has no authData")]
+    MissingAuthData,
+    #[error("authData is not a byte string")]
+    AuthDataNotBytes,
+    #[error("authData has no attested credential data")]
+    NoAttestedCredentialData,
+    #[error("authData is {0} bytes, too short for an AAGUID")]
+    AuthDataTooShort(usize),
+}
+
+/// Read the AAGUID and backup flags from a raw attestation object (CBOR).
+///
+/// `authData` is found by key wherever it sits in the top-level map, and
+/// indefinite-length maps, keys and byte strings are accepted.
+pub fn parse_attestation(
+    attestation_object: &[u8],
+) -> Result<AttestedCredential, AttestationError> {
+    let auth_data = find_auth_data(attestation_object)?;
+    if auth_data.len() < AAGUID.end {
+        return Err(AttestationError::AuthDataTooShort(auth_data.len()));
+    }
+    let flags = auth_data[FLAGS];
+    if flags & FLAG_AT == 0 {
+        return Err(AttestationError::NoAttestedCredentialData);
+    }
+    let mut aaguid = [0u8; 16];
+    aaguid.copy_from_slice(&auth_data[AAGUID]);
+    Ok(AttestedCredential {
+        aaguid: Uuid::from_bytes(aaguid),
+        backup_eligible: flags & FLAG_BE != 0,
+        backup_state: flags & FLAG_BS != 0,
+    })
+}
+
+/// Extract only the AAGUID; see [`parse_attestation`].
+pub fn parse_aaguid(attestation_object: &[u8]) -> Result<Uuid, AttestationError> {
+    parse_attestation(attestation_object).map(|credential| credential.aaguid)
+}
+
+/// The `authData` value of the top-level attestation map.
+fn find_auth_data(attestation_object: &[u8]) -> Result<Cow<'_, [u8]>, AttestationError> {
+    let mut decoder = Decoder::new(attestation_object);
+    if !matches!(decoder.datatype()?, Type::Map | Type::MapIndef) {
+        return Err(AttestationError::NotAMap);
+    }
+    // `None` for an indefinite-length map, which ends at a break marker.
+    let mut remaining = decoder.map()?;
+    loop {
+        match remaining {
+            Some(0) => break,
+            Some(n) => remaining = Some(n - 1),
+            None if decoder.datatype()? == Type::Break => break,
+            None => {}
+        }
+        if text_key(&mut decoder)?.as_deref() == Some("authData") {
+            return byte_string(&mut decoder);
+        }
+        decoder.skip()?;
+    }
+    Err(AttestationError::MissingAuthData)
+}
+
+/// Decode a map key, returning `None` (after skipping it) if it is not text.
+fn text_key<'b>(decoder: &mut Decoder<'b>) -> Result<Option<Cow<'b, str>>, AttestationError> {
+    match decoder.datatype()? {
+        Type::String => Ok(Some(Cow::Borrowed(decoder.str()?))),
+        Type::StringIndef => {
+            let mut key = String::new();
+            for chunk in decoder.str_iter()? {
+                key.push_str(chunk?);
+            }
+            Ok(Some(Cow::Owned(key)))
+        }
+        _ => {
+            decoder.skip()?;
+            Ok(None)
+        }
+    }
+}
+
+fn byte_string<'b>(decoder: &mut Decoder<'b>) -> Result<Cow<'b, [u8]>, AttestationError> {
+    match decoder.datatype()? {
+        Type::Bytes => Ok(Cow::Borrowed(decoder.bytes()?)),
+        Type::BytesIndef => {
+            let mut bytes = Vec::new();
+            for chunk in decoder.bytes_iter()? {
+                bytes.extend_from_slice(chunk?);
+            }
+            Ok(Cow::Owned(bytes))
+        }
+        _ => Err(AttestationError::AuthDataNotBytes),
+    }
+}
@@ -0,0 +1,124 @@
+use uuid::Uuid;
+
+use madome_auth::domain::repository::ApiTokenRepository;
+use madome_auth::domain::types::MAX_API_TOKENS;
+use madome_auth::error::AuthServiceError;
+use madome_auth::infra::memory::{
+    InMemoryApiTokenRepository, InMemoryStore, InMemoryUserRepository,
+};
+use madome_auth::usecase::api_token::{
+    CreateApiTokenInput, CreateApiTokenOutput, CreateApiTokenUseCase, DeleteApiTokenUseCase,
+    IntrospectApiTokenUseCase,
+};
+use madome_auth_types::api_token::{format_api_token, parse_api_token};
+
+use crate::helpers::test_user;
+
+fn input() -> CreateApiTokenInput {
+    CreateApiTokenInput {
+        name: "backup script".to_owned(),
+        scopes: vec!["library.read".to_owned(), "tastes.read".to_owned()],
+    }
+}
+
+fn introspect(
+    store: &InMemoryStore,
+) -> IntrospectApiTokenUseCase<InMemoryApiTokenRepository, InMemoryUserRepository> {
+    IntrospectApiTokenUseCase {
+        tokens: store.api_token_repo(),
+        users: store.user_repo(),
+    }
+}
+
+async fn create(store: &InMemoryStore) -> CreateApiTokenOutput {
+    CreateApiTokenUseCase {
+        tokens: store.api_token_repo(),
+    }
+    .execute(test_user().id, None, input())
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn should_introspect_a_created_token_with_its_scopes() {
+    let store = InMemoryStore::new();
+    let user = test_user();
+    store.add_user(user.clone());
+
+    let out = create(&store).await;
+    assert_eq!(parse_api_token(&out.token).unwrap().id, out.info.id);
+
+    let info = introspect(&store)
+        .execute(&out.token)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(info.user_id, user.id);
+    assert_eq!(info.scopes, ["library.read", "tastes.read"]);
+
+    let stored = store.api_token_repo().list_by_user(user.id).await.unwrap();
+    assert_eq!(stored.len(), 1);
+    assert!(stored[0].last_used_at.is_some());
+    // Only the hash is kept.
+    let secret = parse_api_token(&out.token).unwrap().secret;
+    assert_ne!(stored[0].secret_hash, secret);
+}
+
+#[tokio::test]
+async fn should_reject_wrong_secrets_deleted_tokens_and_deleted_users() {
+    let store = InMemoryStore::new();
+    let user = test_user();
+    store.add_user(user.clone());
+    let out = create(&store).await;
+    let uc = introspect(&store);
+
+    let forged = format_api_token(out.info.id, "not-the-secret");
+    assert_eq!(uc.execute(&forged).await.unwrap(), None);
+    assert_eq!(
+        uc.execute("eyJhbGciOiJIUzI1NiJ9.e30.sig").await.unwrap(),
+        None
+    );
+
+    store.delete_user(user.id);
+    assert_eq!(uc.execute(&out.token).await.unwrap(), None);
+
+    let other = create(&store).await;
+    DeleteApiTokenUseCase {
+        tokens: store.api_token_repo(),
+    }
+    .execute(other.info.id, user.id)
+    .await
+    .unwrap();
+    assert_eq!(uc.execute(&other.token).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn should_not_delete_another_users_token() {
+    let store = InMemoryStore::new();
+    let out = create(&store).await;
+
+    let result = DeleteApiTokenUseCase {
+        tokens: store.api_token_repo(),
+    }
+    .execute(out.info.id, Uuid::new_v4().into())
+    .await;
+    assert!(matches!(result, Err(AuthServiceError::NotFound)));
+}
+
+#[tokio::test]
+async fn should_refuse_tokens_while_impersonating_or_over_the_limit() {
+    let store = InMemoryStore::new();
+    let uc = CreateApiTokenUseCase {
+        tokens: store.api_token_repo(),
+    };
+    let user_id = test_user().id;
+
+    let result = uc.execute(user_id, Some(Uuid::new_v4()), input()).await;
+    assert!(matches!(result, Err(AuthServiceError::Forbidden)));
+
+    for _ in 0..MAX_API_TOKENS {
+        uc.execute(user_id, None, input()).await.unwrap();
+    }
+    let result = uc.execute(user_id, None, input()).await;
+    assert!(matches!(result, Err(AuthServiceError::BadRequest(_))));
+}
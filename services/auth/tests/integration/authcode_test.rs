@@ -1,7 +1,9 @@
+use madome_auth::domain::types::{AUTHCODE_TTL_SECS, MAX_ACTIVE_AUTHCODES};
 use madome_auth::error::AuthServiceError;
-use madome_auth::usecase::authcode::{CreateAuthcodeInput, CreateAuthcodeUseCase};
+use madome_auth::usecase::authcode::{CreateAuthcodeInput, CreateAuthcodeUseCase, hash_code};
+use madome_core::rate_limit::RateLimit;
 
-use crate::helpers::{MockAuthCodeRepo, MockUserRepo, test_user};
+use crate::helpers::{MockAuthCodeRepo, MockUserRepo, TEST_JWT_SECRET, test_user};
 
 #[tokio::test]
 async fn should_create_authcode_for_known_user() {
@@ -13,6 +15,7 @@ async fn should_create_authcode_for_known_user() {
     let uc = CreateAuthcodeUseCase {
         users: MockUserRepo::new(vec![user.clone()]),
         auth_codes: mock_repo,
+        code_secret: TEST_JWT_SECRET.to_owned(),
     };
 
     uc.execute(CreateAuthcodeInput {
@@ -31,7 +34,6 @@ async fn should_create_authcode_for_known_user() {
 
     let created = &codes[0];
     assert_eq!(created.user_id, user.id);
-    assert_eq!(created.code.len(), 12, "auth code should be 12 characters");
     assert!(created.used_at.is_none(), "new code should not be used");
     assert!(
         created.expires_at > chrono::Utc::now(),
@@ -39,11 +41,49 @@ async fn should_create_authcode_for_known_user() {
     );
 }
 
+#[tokio::test]
+async fn should_store_only_the_hash_of_the_mailed_code() {
+    let user = test_user();
+
+    let mock_repo = MockAuthCodeRepo::empty();
+    let codes_handle = mock_repo.codes_handle();
+    let events_handle = mock_repo.events_handle();
+
+    let uc = CreateAuthcodeUseCase {
+        users: MockUserRepo::new(vec![user.clone()]),
+        auth_codes: mock_repo,
+        code_secret: TEST_JWT_SECRET.to_owned(),
+    };
+
+    uc.execute(CreateAuthcodeInput {
+        email: user.email.clone(),
+    })
+    .await
+    .unwrap();
+
+    let events = events_handle.lock().unwrap();
+    let mailed = events[0].payload["code"].as_str().unwrap();
+    assert_eq!(mailed.len(), 12, "auth code should be 12 characters");
+
+    let stored = &codes_handle.lock().unwrap()[0].code_hash;
+    assert_ne!(stored, mailed);
+    assert_eq!(stored, &hash_code(TEST_JWT_SECRET, mailed));
+}
+
+#[test]
+fn should_hash_code_with_secret() {
+    let hash = hash_code(TEST_JWT_SECRET, "ABCDEF123456");
+    assert_eq!(hash, hash_code(TEST_JWT_SECRET, "ABCDEF123456"));
+    assert_ne!(hash, hash_code("another-secret", "ABCDEF123456"));
+    assert_ne!(hash, hash_code(TEST_JWT_SECRET, "ABCDEF123457"));
+}
+
 #[tokio::test]
 async fn should_return_not_found_when_user_unknown_for_authcode() {
     let uc = CreateAuthcodeUseCase {
         users: MockUserRepo::empty(),
         auth_codes: MockAuthCodeRepo::empty(),
+        code_secret: TEST_JWT_SECRET.to_owned(),
     };
 
     let result = uc
@@ -65,6 +105,7 @@ async fn should_return_too_many_requests_when_active_code_limit_reached() {
     let uc = CreateAuthcodeUseCase {
         users: MockUserRepo::new(vec![user.clone()]),
         auth_codes: MockAuthCodeRepo::new(vec![], 5), // at the limit
+        code_secret: TEST_JWT_SECRET.to_owned(),
     };
 
     let result = uc
@@ -74,11 +115,35 @@ async fn should_return_too_many_requests_when_active_code_limit_reached() {
         .await;
 
     assert!(
-        matches!(result, Err(AuthServiceError::TooManyRequests)),
+        matches!(result, Err(AuthServiceError::TooManyRequests(_))),
         "expected TooManyRequests, got {result:?}"
     );
 }
 
+#[tokio::test]
+async fn should_report_remaining_code_quota() {
+    let user = test_user();
+
+    let uc = CreateAuthcodeUseCase {
+        users: MockUserRepo::new(vec![user.clone()]),
+        auth_codes: MockAuthCodeRepo::new(vec![], 2),
+        code_secret: TEST_JWT_SECRET.to_owned(),
+    };
+
+    let quota = uc
+        .execute(CreateAuthcodeInput {
+            email: user.email.clone(),
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(
+        quota,
+        RateLimit::new(MAX_ACTIVE_AUTHCODES, 3, AUTHCODE_TTL_SECS as u64)
+    );
+    assert_eq!(quota.remaining, 2);
+}
+
 #[tokio::test]
 async fn should_return_too_many_requests_when_active_code_count_exceeds_limit() {
     let user = test_user();
@@ -86,6 +151,7 @@ async fn should_return_too_many_requests_when_active_code_count_exceeds_limit()
     let uc = CreateAuthcodeUseCase {
         users: MockUserRepo::new(vec![user.clone()]),
         auth_codes: MockAuthCodeRepo::new(vec![], 10), // well over limit
+        code_secret: TEST_JWT_SECRET.to_owned(),
     };
 
     let result = uc
@@ -95,7 +161,7 @@ async fn should_return_too_many_requests_when_active_code_count_exceeds_limit()
         .await;
 
     assert!(
-        matches!(result, Err(AuthServiceError::TooManyRequests)),
+        matches!(result, Err(AuthServiceError::TooManyRequests(_))),
         "expected TooManyRequests, got {result:?}"
     );
 }
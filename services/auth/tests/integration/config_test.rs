@@ -0,0 +1,320 @@
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+
+use axum_extra::extract::cookie::SameSite;
+use log::LevelFilter;
+use madome_auth_types::cookie::{CookieConfig, TokenLifetimes};
+use madome_core::config::{Config, ConfigError, Secret, from_vars};
+use madome_core::startup::Backoff;
+
+use madome_auth::config::{
+    AuthConfig, CookieSameSite, DbPoolPreset, OAuthConfig, OAuthProviderKind,
+};
+
+fn config(preset: DbPoolPreset) -> AuthConfig {
+    AuthConfig {
+        database_url: Secret::new("postgres://localhost/auth".to_owned()),
+        database_read_url: None,
+        redis_url: Secret::new("redis://localhost".to_owned()),
+        jwt_secret: Secret::new("secret".to_owned()),
+        jwt_secret_previous: None,
+        webauthn_rp_id: "example.com".to_owned(),
+        webauthn_origin: "https://example.com".to_owned(),
+        cookie_domain: "example.com".to_owned(),
+        cookie_secure: None,
+        cookie_same_site: None,
+        access_token_ttl_secs: None,
+        refresh_token_ttl_secs: None,
+        legacy_token_cutoff: None,
+        app_url: "/".to_owned(),
+        cors_allowed_origins: Vec::new(),
+        auth_port: 3112,
+        auth_grpc_port: 50051,
+        grpc_caller_tokens: None,
+        db_pool_preset: preset,
+        db_max_connections: None,
+        db_min_connections: None,
+        db_acquire_timeout_secs: None,
+        db_statement_timeout_ms: None,
+        db_log_level: None,
+        db_slow_query_ms: None,
+        run_migrations: false,
+        startup_attempts: None,
+        startup_max_delay_ms: None,
+        secrets_refresh_secs: None,
+    }
+}
+
+#[test]
+fn should_apply_preset_when_no_overrides_set() {
+    let opts = config(DbPoolPreset::Production).db_connect_options("postgres://localhost/auth");
+
+    let preset = DbPoolPreset::Production.settings();
+    assert_eq!(opts.get_max_connections(), Some(preset.max_connections));
+    assert_eq!(opts.get_min_connections(), Some(preset.min_connections));
+    assert_eq!(
+        opts.get_acquire_timeout(),
+        Some(Duration::from_secs(preset.acquire_timeout_secs))
+    );
+    assert!(!opts.get_sqlx_logging());
+}
+
+#[test]
+fn should_prefer_explicit_settings_over_preset() {
+    let config = AuthConfig {
+        db_max_connections: Some(50),
+        db_acquire_timeout_secs: Some(1),
+        db_log_level: Some("warn".to_owned()),
+        ..config(DbPoolPreset::Development)
+    };
+
+    let opts = config.db_connect_options("postgres://localhost/auth");
+
+    assert_eq!(opts.get_max_connections(), Some(50));
+    assert_eq!(
+        opts.get_min_connections(),
+        Some(DbPoolPreset::Development.settings().min_connections)
+    );
+    assert_eq!(opts.get_acquire_timeout(), Some(Duration::from_secs(1)));
+    assert!(opts.get_sqlx_logging());
+    assert_eq!(opts.get_sqlx_logging_level(), LevelFilter::Warn);
+}
+
+#[test]
+fn should_report_invalid_log_level_and_origin_together() {
+    let invalid = AuthConfig {
+        db_log_level: Some("loud".to_owned()),
+        webauthn_origin: "example.com".to_owned(),
+        ..config(DbPoolPreset::Production)
+    };
+
+    let keys: Vec<String> = invalid
+        .validate()
+        .unwrap_err()
+        .0
+        .into_iter()
+        .map(|e| match e {
+            ConfigError::Invalid { key, .. } => key,
+            other => panic!("unexpected {other:?}"),
+        })
+        .collect();
+
+    assert_eq!(keys, ["DB_LOG_LEVEL", "WEBAUTHN_ORIGIN"]);
+    assert!(config(DbPoolPreset::Production).validate().is_ok());
+}
+
+#[test]
+fn should_report_every_missing_oauth_setting() {
+    let vars = HashMap::from([("AUTH_OAUTH_PROVIDERS".to_owned(), "github, corp".to_owned())]);
+
+    let missing: BTreeSet<String> = OAuthConfig::from_vars(&vars)
+        .unwrap_err()
+        .0
+        .into_iter()
+        .map(|e| match e {
+            ConfigError::Missing(key) => key,
+            other => panic!("unexpected {other:?}"),
+        })
+        .collect();
+
+    assert_eq!(
+        missing,
+        BTreeSet::from(
+            [
+                "AUTH_OAUTH_REDIRECT_BASE",
+                "AUTH_OAUTH_GITHUB_CLIENT_ID",
+                "AUTH_OAUTH_GITHUB_CLIENT_SECRET",
+                "AUTH_OAUTH_CORP_CLIENT_ID",
+                "AUTH_OAUTH_CORP_CLIENT_SECRET",
+                "AUTH_OAUTH_CORP_AUTHORIZE_URL",
+                "AUTH_OAUTH_CORP_TOKEN_URL",
+                "AUTH_OAUTH_CORP_USERINFO_URL",
+            ]
+            .map(str::to_owned)
+        )
+    );
+}
+
+#[test]
+fn should_fill_in_builtin_oauth_endpoints() {
+    let vars: HashMap<String, String> = [
+        ("AUTH_OAUTH_PROVIDERS", "github"),
+        ("AUTH_OAUTH_REDIRECT_BASE", "https://example.com/"),
+        ("AUTH_OAUTH_GITHUB_CLIENT_ID", "id"),
+        ("AUTH_OAUTH_GITHUB_CLIENT_SECRET", "secret"),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_owned(), v.to_owned()))
+    .collect();
+
+    let oauth = OAuthConfig::from_vars(&vars).unwrap();
+
+    assert_eq!(oauth.redirect_base, "https://example.com");
+    let github = &oauth.providers["github"];
+    assert_eq!(github.kind, OAuthProviderKind::GitHub);
+    assert_eq!(
+        github.token_url,
+        "https://github.com/login/oauth/access_token"
+    );
+    assert_eq!(github.scopes, "read:user user:email");
+}
+
+#[test]
+fn should_override_startup_backoff() {
+    let config = AuthConfig {
+        startup_attempts: Some(30),
+        startup_max_delay_ms: Some(10_000),
+        ..config(DbPoolPreset::Production)
+    };
+
+    let backoff = config.startup_backoff();
+
+    assert_eq!(backoff.attempts, 30);
+    assert_eq!(backoff.max, Duration::from_secs(10));
+    assert_eq!(backoff.initial, Backoff::default().initial);
+}
+
+#[test]
+fn should_refresh_secrets_every_five_minutes_unless_disabled() {
+    let config = config(DbPoolPreset::Production);
+    assert_eq!(config.secrets_refresh(), Some(Duration::from_secs(300)));
+
+    let disabled = AuthConfig {
+        secrets_refresh_secs: Some(0),
+        ..config
+    };
+    assert_eq!(disabled.secrets_refresh(), None);
+}
+
+#[test]
+fn should_log_queries_over_half_a_second_unless_disabled() {
+    let config = config(DbPoolPreset::Production);
+    assert_eq!(
+        config.slow_query_threshold(),
+        Some(Duration::from_millis(500))
+    );
+
+    let disabled = AuthConfig {
+        db_slow_query_ms: Some(0),
+        ..config
+    };
+    assert_eq!(disabled.slow_query_threshold(), None);
+}
+
+#[test]
+fn should_keep_statement_timeout_for_rotated_credentials() {
+    let opts = config(DbPoolPreset::Production)
+        .pg_connect_options("postgres://rotated:pw@localhost/auth")
+        .unwrap();
+
+    assert_eq!(opts.get_username(), "rotated");
+    assert_eq!(opts.get_database(), Some("auth"));
+    assert_eq!(opts.get_options(), Some("-c statement_timeout=10000"));
+}
+
+#[test]
+fn should_read_cors_origins_as_comma_separated_list() {
+    let vars: HashMap<String, String> = [
+        ("DATABASE_URL", "postgres://localhost/auth"),
+        ("REDIS_URL", "redis://localhost"),
+        ("JWT_SECRET", "secret"),
+        ("WEBAUTHN_RP_ID", "example.com"),
+        ("WEBAUTHN_ORIGIN", "https://example.com"),
+        ("COOKIE_DOMAIN", "example.com"),
+        (
+            "CORS_ALLOWED_ORIGINS",
+            "https://example.com, http://localhost:5173",
+        ),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_owned(), v.to_owned()))
+    .collect();
+
+    let config: AuthConfig = from_vars(vars).unwrap();
+    assert_eq!(
+        config.cors_allowed_origins,
+        ["https://example.com", "http://localhost:5173"]
+    );
+}
+
+#[test]
+fn should_keep_production_cookie_attributes_by_default() {
+    let cookies = config(DbPoolPreset::Production).cookie_config();
+    assert_eq!(cookies, CookieConfig::new("example.com"));
+    assert!(cookies.secure);
+    assert_eq!(cookies.same_site, SameSite::Lax);
+}
+
+#[test]
+fn should_allow_insecure_cookies_for_local_development() {
+    let config = AuthConfig {
+        cookie_secure: Some(false),
+        cookie_same_site: Some(CookieSameSite::Strict),
+        ..config(DbPoolPreset::Development)
+    };
+    let cookies = config.cookie_config();
+    assert!(!cookies.secure);
+    assert_eq!(cookies.same_site, SameSite::Strict);
+    assert_eq!(cookies.refresh_path, "/auth/token");
+}
+
+#[test]
+fn should_refuse_same_site_none_without_secure() {
+    let config = AuthConfig {
+        cookie_secure: Some(false),
+        cookie_same_site: Some(CookieSameSite::None),
+        ..config(DbPoolPreset::Development)
+    };
+
+    let errors = config.validate().unwrap_err();
+
+    assert_eq!(
+        errors.0,
+        [ConfigError::Invalid {
+            key: "COOKIE_SAME_SITE".to_owned(),
+            message: "none requires COOKIE_SECURE=true".to_owned(),
+        }]
+    );
+}
+
+#[test]
+fn should_default_token_lifetimes_to_legacy_values() {
+    assert_eq!(
+        config(DbPoolPreset::Production).token_lifetimes(),
+        TokenLifetimes::default()
+    );
+}
+
+#[test]
+fn should_override_token_lifetimes() {
+    let config = AuthConfig {
+        access_token_ttl_secs: Some(300),
+        refresh_token_ttl_secs: Some(3600),
+        ..config(DbPoolPreset::Production)
+    };
+    assert_eq!(
+        config.token_lifetimes(),
+        TokenLifetimes {
+            access_secs: 300,
+            refresh_secs: 3600
+        }
+    );
+}
+
+#[test]
+fn should_refuse_access_token_outliving_refresh_token() {
+    for (access, refresh) in [(7200, 3600), (0, 3600)] {
+        let config = AuthConfig {
+            access_token_ttl_secs: Some(access),
+            refresh_token_ttl_secs: Some(refresh),
+            ..config(DbPoolPreset::Production)
+        };
+
+        let errors = config.validate().unwrap_err();
+
+        assert!(
+            matches!(&errors.0[..], [ConfigError::Invalid { key, .. }] if key == "ACCESS_TOKEN_TTL_SECS"),
+            "{access}/{refresh}: {errors}"
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use madome_auth::domain::types::LinkedIdentity;
+use madome_auth::error::AuthServiceError;
+use madome_auth::usecase::export::ExportAuthDataUseCase;
+use madome_domain::id::UserId;
+
+use crate::helpers::{
+    MockLinkedIdentityRepo, MockPasskeyRepo, MockSessionRepo, MockUserRepo, test_passkey_record,
+    test_session, test_user,
+};
+
+fn linked_identity(user_id: UserId) -> LinkedIdentity {
+    LinkedIdentity {
+        id: Uuid::new_v4(),
+        user_id,
+        provider: "github".to_owned(),
+        subject: "provider-account-1".to_owned(),
+        email: "user@example.com".to_owned(),
+        created_at: Utc::now(),
+    }
+}
+
+#[tokio::test]
+async fn should_export_only_the_users_own_auth_data() {
+    let user = test_user();
+    let other = UserId(Uuid::new_v4());
+    let uc = ExportAuthDataUseCase {
+        users: MockUserRepo::new(vec![user.clone()]),
+        sessions: MockSessionRepo::new(vec![test_session(user.id), test_session(other)]),
+        passkeys: MockPasskeyRepo::new(vec![test_passkey_record(user.id)]),
+        identities: MockLinkedIdentityRepo::new(vec![
+            linked_identity(user.id),
+            linked_identity(other),
+        ]),
+    };
+
+    let export = uc.execute(user.id).await.unwrap();
+
+    assert_eq!(export.email, user.email);
+    assert_eq!(export.sessions.len(), 1);
+    assert!(export.sessions.iter().all(|s| s.user_id == user.id));
+    assert_eq!(export.passkeys.len(), 1);
+    assert_eq!(export.passkeys[0].credential_id, vec![1, 2, 3, 4]);
+    assert_eq!(export.linked_identities.len(), 1);
+    assert_eq!(export.linked_identities[0].user_id, user.id);
+}
+
+#[tokio::test]
+async fn should_return_not_found_for_unknown_user() {
+    let uc = ExportAuthDataUseCase {
+        users: MockUserRepo::empty(),
+        sessions: MockSessionRepo::empty(),
+        passkeys: MockPasskeyRepo::empty(),
+        identities: MockLinkedIdentityRepo::empty(),
+    };
+
+    let result = uc.execute(UserId(Uuid::new_v4())).await;
+
+    assert!(matches!(result, Err(AuthServiceError::NotFound)));
+}
@@ -1,11 +1,22 @@
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 use chrono::Utc;
 use uuid::Uuid;
 
-use madome_auth::domain::repository::{AuthCodeRepository, PasskeyRepository, UserRepository};
-use madome_auth::domain::types::{AuthCode, AuthUser, OutboxEvent, PasskeyRecord};
+use madome_auth::domain::repository::{
+    AuthCodeAttemptStore, AuthCodeRepository, IdAliasRepository, LinkedIdentityRepository,
+    LoginLinkRepository, OAuthClient, OAuthStateCache, OutboxRepository, PasskeyCache,
+    PasskeyRepository, SessionRepository, UserRepository,
+};
+use madome_auth::domain::types::{
+    AuthCode, AuthUser, LinkedIdentity, LoginLink, OAuthProfile, OutboxEntry, OutboxEvent,
+    OutboxStatus, PasskeyRecord, Session,
+};
 use madome_auth::error::AuthServiceError;
+use madome_auth::usecase::authcode::hash_code;
+use madome_domain::id::UserId;
+use madome_domain::pagination::{PageRequest, Paged};
 
 // ── MockUserRepo ─────────────────────────────────────────────────────────────
 
@@ -28,7 +39,7 @@ impl UserRepository for MockUserRepo {
         Ok(self.users.iter().find(|u| u.email == email).cloned())
     }
 
-    async fn find_by_id(&self, id: Uuid) -> Result<Option<AuthUser>, AuthServiceError> {
+    async fn find_by_id(&self, id: UserId) -> Result<Option<AuthUser>, AuthServiceError> {
         Ok(self.users.iter().find(|u| u.id == id).cloned())
     }
 }
@@ -37,6 +48,7 @@ impl UserRepository for MockUserRepo {
 
 pub struct MockAuthCodeRepo {
     pub codes: Arc<Mutex<Vec<AuthCode>>>,
+    pub events: Arc<Mutex<Vec<OutboxEvent>>>,
     pub active_count: u64,
 }
 
@@ -44,6 +56,7 @@ impl MockAuthCodeRepo {
     pub fn new(codes: Vec<AuthCode>, active_count: u64) -> Self {
         Self {
             codes: Arc::new(Mutex::new(codes)),
+            events: Arc::new(Mutex::new(vec![])),
             active_count,
         }
     }
@@ -56,33 +69,39 @@ impl MockAuthCodeRepo {
     pub fn codes_handle(&self) -> Arc<Mutex<Vec<AuthCode>>> {
         Arc::clone(&self.codes)
     }
+
+    /// Returns a shared handle to the outbox events written alongside codes.
+    pub fn events_handle(&self) -> Arc<Mutex<Vec<OutboxEvent>>> {
+        Arc::clone(&self.events)
+    }
 }
 
 impl AuthCodeRepository for MockAuthCodeRepo {
-    async fn count_active(&self, _user_id: Uuid) -> Result<u64, AuthServiceError> {
+    async fn count_active(&self, _user_id: UserId) -> Result<u64, AuthServiceError> {
         Ok(self.active_count)
     }
 
     async fn create_with_outbox(
         &self,
         code: &AuthCode,
-        _event: &OutboxEvent,
+        event: &OutboxEvent,
     ) -> Result<(), AuthServiceError> {
+        self.events.lock().unwrap().push(event.clone());
         self.codes.lock().unwrap().push(code.clone());
         Ok(())
     }
 
     async fn find_valid(
         &self,
-        user_id: Uuid,
-        code: &str,
+        user_id: UserId,
+        code_hash: &str,
     ) -> Result<Option<AuthCode>, AuthServiceError> {
         Ok(self
             .codes
             .lock()
             .unwrap()
             .iter()
-            .find(|c| c.user_id == user_id && c.code == code && c.is_valid())
+            .find(|c| c.user_id == user_id && c.code_hash == code_hash && c.is_valid())
             .cloned())
     }
 
@@ -95,6 +114,55 @@ impl AuthCodeRepository for MockAuthCodeRepo {
     }
 }
 
+// ── MockAuthCodeAttemptStore ─────────────────────────────────────────────────
+
+#[derive(Default)]
+pub struct MockAuthCodeAttemptStore {
+    pub failures: Arc<Mutex<HashMap<UserId, u32>>>,
+}
+
+impl MockAuthCodeAttemptStore {
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// A store that already counts `failures` wrong codes for `user_id`.
+    pub fn with_failures(user_id: UserId, failures: u32) -> Self {
+        let store = Self::default();
+        store.failures.lock().unwrap().insert(user_id, failures);
+        store
+    }
+
+    /// Returns a shared handle to the counters for post-execution inspection.
+    pub fn failures_handle(&self) -> Arc<Mutex<HashMap<UserId, u32>>> {
+        Arc::clone(&self.failures)
+    }
+}
+
+impl AuthCodeAttemptStore for MockAuthCodeAttemptStore {
+    async fn failures(&self, user_id: UserId) -> Result<u32, AuthServiceError> {
+        Ok(self
+            .failures
+            .lock()
+            .unwrap()
+            .get(&user_id)
+            .copied()
+            .unwrap_or(0))
+    }
+
+    async fn record_failure(&self, user_id: UserId) -> Result<u32, AuthServiceError> {
+        let mut failures = self.failures.lock().unwrap();
+        let count = failures.entry(user_id).or_insert(0);
+        *count += 1;
+        Ok(*count)
+    }
+
+    async fn clear(&self, user_id: UserId) -> Result<(), AuthServiceError> {
+        self.failures.lock().unwrap().remove(&user_id);
+        Ok(())
+    }
+}
+
 // ── MockPasskeyRepo ──────────────────────────────────────────────────────────
 
 pub struct MockPasskeyRepo {
@@ -112,7 +180,7 @@ impl MockPasskeyRepo {
 }
 
 impl PasskeyRepository for MockPasskeyRepo {
-    async fn list_by_user(&self, user_id: Uuid) -> Result<Vec<PasskeyRecord>, AuthServiceError> {
+    async fn list_by_user(&self, user_id: UserId) -> Result<Vec<PasskeyRecord>, AuthServiceError> {
         Ok(self
             .records
             .iter()
@@ -136,7 +204,19 @@ impl PasskeyRepository for MockPasskeyRepo {
         Ok(())
     }
 
-    async fn delete(&self, credential_id: &[u8], user_id: Uuid) -> Result<bool, AuthServiceError> {
+    async fn create_with_outbox(
+        &self,
+        _record: &PasskeyRecord,
+        _event: &OutboxEvent,
+    ) -> Result<(), AuthServiceError> {
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        credential_id: &[u8],
+        user_id: UserId,
+    ) -> Result<bool, AuthServiceError> {
         Ok(self
             .records
             .iter()
@@ -147,40 +227,524 @@ impl PasskeyRepository for MockPasskeyRepo {
         &self,
         _credential_id: &[u8],
         _credential: &[u8],
+        _backup_state: bool,
+    ) -> Result<(), AuthServiceError> {
+        Ok(())
+    }
+}
+
+// ── MockPasskeyCache ─────────────────────────────────────────────────────────
+
+/// Ceremony states keyed by `user_id:reg_id` or `email:auth_id`.
+#[derive(Clone, Default)]
+pub struct MockPasskeyCache {
+    pub states: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+}
+
+impl PasskeyCache for MockPasskeyCache {
+    async fn set_registration_state(
+        &self,
+        user_id: UserId,
+        reg_id: &str,
+        state_json: &[u8],
+    ) -> Result<(), AuthServiceError> {
+        self.states
+            .lock()
+            .unwrap()
+            .insert(format!("{user_id}:{reg_id}"), state_json.to_vec());
+        Ok(())
+    }
+
+    async fn take_registration_state(
+        &self,
+        user_id: UserId,
+        reg_id: &str,
+    ) -> Result<Option<Vec<u8>>, AuthServiceError> {
+        Ok(self
+            .states
+            .lock()
+            .unwrap()
+            .remove(&format!("{user_id}:{reg_id}")))
+    }
+
+    async fn set_authentication_state(
+        &self,
+        email: &str,
+        auth_id: &str,
+        state_json: &[u8],
     ) -> Result<(), AuthServiceError> {
+        self.states
+            .lock()
+            .unwrap()
+            .insert(format!("{email}:{auth_id}"), state_json.to_vec());
+        Ok(())
+    }
+
+    async fn take_authentication_state(
+        &self,
+        email: &str,
+        auth_id: &str,
+    ) -> Result<Option<Vec<u8>>, AuthServiceError> {
+        Ok(self
+            .states
+            .lock()
+            .unwrap()
+            .remove(&format!("{email}:{auth_id}")))
+    }
+}
+
+// ── MockLoginLinkRepo ────────────────────────────────────────────────────────
+
+pub struct MockLoginLinkRepo {
+    pub links: Arc<Mutex<Vec<LoginLink>>>,
+    pub events: Arc<Mutex<Vec<OutboxEvent>>>,
+}
+
+impl MockLoginLinkRepo {
+    pub fn new(links: Vec<LoginLink>) -> Self {
+        Self {
+            links: Arc::new(Mutex::new(links)),
+            events: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    pub fn empty() -> Self {
+        Self::new(vec![])
+    }
+
+    /// Returns a shared handle to the internal link list for post-execution inspection.
+    pub fn links_handle(&self) -> Arc<Mutex<Vec<LoginLink>>> {
+        Arc::clone(&self.links)
+    }
+
+    /// Returns a shared handle to the outbox events written alongside links.
+    pub fn events_handle(&self) -> Arc<Mutex<Vec<OutboxEvent>>> {
+        Arc::clone(&self.events)
+    }
+}
+
+impl LoginLinkRepository for MockLoginLinkRepo {
+    async fn count_active(&self, user_id: UserId) -> Result<u64, AuthServiceError> {
+        Ok(self
+            .links
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|l| l.user_id == user_id && l.is_valid())
+            .count() as u64)
+    }
+
+    async fn create_with_outbox(
+        &self,
+        link: &LoginLink,
+        event: &OutboxEvent,
+    ) -> Result<(), AuthServiceError> {
+        self.links.lock().unwrap().push(link.clone());
+        self.events.lock().unwrap().push(event.clone());
+        Ok(())
+    }
+
+    async fn consume(&self, id: Uuid) -> Result<Option<LoginLink>, AuthServiceError> {
+        let mut links = self.links.lock().unwrap();
+        let Some(link) = links.iter_mut().find(|l| l.id == id && l.is_valid()) else {
+            return Ok(None);
+        };
+        link.used_at = Some(Utc::now());
+        Ok(Some(link.clone()))
+    }
+}
+
+// ── MockSessionRepo ──────────────────────────────────────────────────────────
+
+pub struct MockSessionRepo {
+    pub sessions: Arc<Mutex<Vec<Session>>>,
+    pub events: Arc<Mutex<Vec<OutboxEvent>>>,
+}
+
+impl MockSessionRepo {
+    pub fn new(sessions: Vec<Session>) -> Self {
+        Self {
+            sessions: Arc::new(Mutex::new(sessions)),
+            events: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    pub fn empty() -> Self {
+        Self::new(vec![])
+    }
+
+    /// Returns a shared handle to the internal session list for post-execution inspection.
+    pub fn sessions_handle(&self) -> Arc<Mutex<Vec<Session>>> {
+        Arc::clone(&self.sessions)
+    }
+
+    /// Returns a shared handle to the outbox events written alongside sessions.
+    pub fn events_handle(&self) -> Arc<Mutex<Vec<OutboxEvent>>> {
+        Arc::clone(&self.events)
+    }
+}
+
+impl SessionRepository for MockSessionRepo {
+    async fn create(&self, session: &Session) -> Result<(), AuthServiceError> {
+        self.sessions.lock().unwrap().push(session.clone());
+        Ok(())
+    }
+
+    async fn create_with_outbox(
+        &self,
+        session: &Session,
+        event: &OutboxEvent,
+    ) -> Result<(), AuthServiceError> {
+        self.sessions.lock().unwrap().push(session.clone());
+        let mut events = self.events.lock().unwrap();
+        if !events
+            .iter()
+            .any(|e| e.idempotency_key == event.idempotency_key)
+        {
+            events.push(event.clone());
+        }
+        Ok(())
+    }
+
+    async fn find_by_id(
+        &self,
+        id: Uuid,
+        user_id: UserId,
+    ) -> Result<Option<Session>, AuthServiceError> {
+        Ok(self
+            .sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|s| s.id == id && s.user_id == user_id)
+            .cloned())
+    }
+
+    async fn list_by_user(&self, user_id: UserId) -> Result<Vec<Session>, AuthServiceError> {
+        let mut list: Vec<Session> = self
+            .sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|s| s.user_id == user_id)
+            .cloned()
+            .collect();
+        list.sort_by_key(|s| std::cmp::Reverse(s.last_used_at));
+        Ok(list)
+    }
+
+    async fn touch(&self, id: Uuid) -> Result<(), AuthServiceError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(s) = sessions.iter_mut().find(|s| s.id == id) {
+            s.last_used_at = Utc::now();
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, id: Uuid, user_id: UserId) -> Result<bool, AuthServiceError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let before = sessions.len();
+        sessions.retain(|s| !(s.id == id && s.user_id == user_id));
+        Ok(sessions.len() < before)
+    }
+}
+
+// ── MockLinkedIdentityRepo ───────────────────────────────────────────────────
+
+pub struct MockLinkedIdentityRepo {
+    pub identities: Arc<Mutex<Vec<LinkedIdentity>>>,
+}
+
+impl MockLinkedIdentityRepo {
+    pub fn new(identities: Vec<LinkedIdentity>) -> Self {
+        Self {
+            identities: Arc::new(Mutex::new(identities)),
+        }
+    }
+
+    pub fn empty() -> Self {
+        Self::new(vec![])
+    }
+
+    /// Returns a shared handle to the internal identity list for post-execution inspection.
+    pub fn identities_handle(&self) -> Arc<Mutex<Vec<LinkedIdentity>>> {
+        Arc::clone(&self.identities)
+    }
+}
+
+impl LinkedIdentityRepository for MockLinkedIdentityRepo {
+    async fn find_by_subject(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<LinkedIdentity>, AuthServiceError> {
+        Ok(self
+            .identities
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|i| i.provider == provider && i.subject == subject)
+            .cloned())
+    }
+
+    async fn list_by_user(&self, user_id: UserId) -> Result<Vec<LinkedIdentity>, AuthServiceError> {
+        Ok(self
+            .identities
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|i| i.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn create(&self, identity: &LinkedIdentity) -> Result<(), AuthServiceError> {
+        self.identities.lock().unwrap().push(identity.clone());
         Ok(())
     }
 }
 
+// ── MockIdAliasRepo ──────────────────────────────────────────────────────────
+
+pub struct MockIdAliasRepo {
+    pub aliases: HashMap<i64, UserId>,
+}
+
+impl MockIdAliasRepo {
+    pub fn new(aliases: Vec<(i64, UserId)>) -> Self {
+        Self {
+            aliases: aliases.into_iter().collect(),
+        }
+    }
+}
+
+impl IdAliasRepository for MockIdAliasRepo {
+    async fn resolve(&self, legacy_id: i64) -> Result<Option<UserId>, AuthServiceError> {
+        Ok(self.aliases.get(&legacy_id).copied())
+    }
+}
+
+// ── MockOAuthStateCache ──────────────────────────────────────────────────────
+
+#[derive(Clone, Default)]
+pub struct MockOAuthStateCache {
+    pub states: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+}
+
+impl OAuthStateCache for MockOAuthStateCache {
+    async fn set_state(&self, state: &str, pending_json: &[u8]) -> Result<(), AuthServiceError> {
+        self.states
+            .lock()
+            .unwrap()
+            .insert(state.to_owned(), pending_json.to_vec());
+        Ok(())
+    }
+
+    async fn take_state(&self, state: &str) -> Result<Option<Vec<u8>>, AuthServiceError> {
+        Ok(self.states.lock().unwrap().remove(state))
+    }
+}
+
+// ── MockOAuthClient ──────────────────────────────────────────────────────────
+
+/// Fake provider set. `profile = None` makes every code exchange fail with 401.
+#[derive(Clone)]
+pub struct MockOAuthClient {
+    pub providers: Vec<String>,
+    pub profile: Option<OAuthProfile>,
+    /// `code_verifier` received by the last `exchange_code` call.
+    pub last_verifier: Arc<Mutex<Option<String>>>,
+}
+
+impl MockOAuthClient {
+    pub fn new(profile: Option<OAuthProfile>) -> Self {
+        Self {
+            providers: vec!["google".to_owned(), "github".to_owned()],
+            profile,
+            last_verifier: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl OAuthClient for MockOAuthClient {
+    fn authorize_url(
+        &self,
+        provider: &str,
+        state: &str,
+        code_challenge: &str,
+    ) -> Result<String, AuthServiceError> {
+        if !self.providers.iter().any(|p| p == provider) {
+            return Err(AuthServiceError::NotFound);
+        }
+        Ok(format!(
+            "https://{provider}.example.com/authorize?state={state}&code_challenge={code_challenge}"
+        ))
+    }
+
+    async fn exchange_code(
+        &self,
+        provider: &str,
+        _code: &str,
+        code_verifier: &str,
+    ) -> Result<OAuthProfile, AuthServiceError> {
+        if !self.providers.iter().any(|p| p == provider) {
+            return Err(AuthServiceError::NotFound);
+        }
+        *self.last_verifier.lock().unwrap() = Some(code_verifier.to_owned());
+        self.profile.clone().ok_or(AuthServiceError::Unauthorized)
+    }
+}
+
+// ── MockOutboxRepo ───────────────────────────────────────────────────────────
+
+pub struct MockOutboxRepo {
+    pub entries: Arc<Mutex<Vec<OutboxEntry>>>,
+}
+
+impl MockOutboxRepo {
+    pub fn new(entries: Vec<OutboxEntry>) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(entries)),
+        }
+    }
+
+    pub fn entries_handle(&self) -> Arc<Mutex<Vec<OutboxEntry>>> {
+        self.entries.clone()
+    }
+}
+
+impl OutboxRepository for MockOutboxRepo {
+    async fn list(
+        &self,
+        status: OutboxStatus,
+        page: PageRequest,
+    ) -> Result<Paged<OutboxEntry>, AuthServiceError> {
+        let mut matching: Vec<OutboxEntry> = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| e.status() == status)
+            .cloned()
+            .collect();
+        matching.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+        let total = matching.len() as u64;
+        let items = matching
+            .into_iter()
+            .skip(page.offset() as usize)
+            .take(page.per_page as usize)
+            .collect();
+        Ok(Paged::new(items, total))
+    }
+
+    async fn retry_failed(&self, id: Uuid) -> Result<bool, AuthServiceError> {
+        let mut entries = self.entries.lock().unwrap();
+        let Some(entry) = entries
+            .iter_mut()
+            .find(|e| e.event.id == id && e.failed_at.is_some())
+        else {
+            return Ok(false);
+        };
+        entry.failed_at = None;
+        entry.attempts = 0;
+        entry.next_attempt_at = Utc::now();
+        Ok(true)
+    }
+
+    async fn delete_failed(&self, id: Uuid) -> Result<bool, AuthServiceError> {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|e| !(e.event.id == id && e.failed_at.is_some()));
+        Ok(entries.len() < before)
+    }
+}
+
 // ── Test fixture helpers ─────────────────────────────────────────────────────
 
 pub fn test_user() -> AuthUser {
     AuthUser {
-        id: Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap(),
+        id: UserId(Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap()),
         email: "user@example.com".to_owned(),
         role: 0,
     }
 }
 
-pub fn test_auth_code(user_id: Uuid) -> AuthCode {
+/// Plain code of [`test_auth_code`].
+pub const TEST_AUTH_CODE: &str = "ABCDEF123456";
+
+pub fn test_auth_code(user_id: UserId) -> AuthCode {
     AuthCode {
         id: Uuid::new_v4(),
         user_id,
-        code: "ABCDEF123456".to_owned(),
+        code_hash: hash_code(TEST_JWT_SECRET, TEST_AUTH_CODE),
         expires_at: Utc::now() + chrono::Duration::seconds(120),
         used_at: None,
         created_at: Utc::now(),
     }
 }
 
-pub fn test_passkey_record(user_id: Uuid) -> PasskeyRecord {
+pub fn test_passkey_record(user_id: UserId) -> PasskeyRecord {
     PasskeyRecord {
         credential_id: vec![1, 2, 3, 4],
         user_id,
         aaguid: Uuid::nil(),
         credential: vec![],
+        backup_eligible: true,
+        backup_state: true,
+        transports: vec!["internal".to_owned(), "hybrid".to_owned()],
         created_at: Utc::now(),
     }
 }
 
+pub fn test_session(user_id: UserId) -> Session {
+    let created_at = Utc::now() - chrono::Duration::hours(1);
+    Session {
+        id: Uuid::new_v4(),
+        user_id,
+        user_agent: Some("Mozilla/5.0".to_owned()),
+        ip: Some("203.0.113.7".to_owned()),
+        created_at,
+        last_used_at: created_at,
+    }
+}
+
+pub fn test_login_link(user_id: UserId) -> LoginLink {
+    let now = Utc::now();
+    LoginLink {
+        id: Uuid::new_v4(),
+        user_id,
+        expires_at: now + chrono::Duration::minutes(10),
+        used_at: None,
+        created_at: now,
+    }
+}
+
+pub fn test_oauth_profile(email: &str) -> OAuthProfile {
+    OAuthProfile {
+        subject: "provider-account-1".to_owned(),
+        email: Some(email.to_owned()),
+        email_verified: true,
+    }
+}
+
 pub const TEST_JWT_SECRET: &str = "test-jwt-secret-for-unit-tests-only";
+
+/// Outbox entry in `status`, created `age_secs` ago.
+pub fn test_outbox_entry(status: OutboxStatus, age_secs: i64) -> OutboxEntry {
+    let created_at = Utc::now() - chrono::Duration::seconds(age_secs);
+    let id = Uuid::new_v4();
+    OutboxEntry {
+        event: OutboxEvent {
+            id,
+            kind: "authcode_created".to_owned(),
+            payload: serde_json::json!({ "email": "user@example.com" }),
+            idempotency_key: format!("authcode_created:{id}"),
+        },
+        attempts: if status == OutboxStatus::Failed { 5 } else { 0 },
+        last_error: (status == OutboxStatus::Failed).then(|| "smtp timeout".to_owned()),
+        created_at,
+        next_attempt_at: created_at,
+        processed_at: (status == OutboxStatus::Processed).then_some(created_at),
+        failed_at: (status == OutboxStatus::Failed).then_some(created_at),
+    }
+}
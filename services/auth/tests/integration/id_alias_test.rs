@@ -0,0 +1,32 @@
+use madome_auth::error::AuthServiceError;
+use madome_auth::usecase::id_alias::ResolveLegacyIdUseCase;
+
+use crate::helpers::{MockIdAliasRepo, test_user};
+
+#[tokio::test]
+async fn should_resolve_legacy_id_to_current_user_id() {
+    let user = test_user();
+    let uc = ResolveLegacyIdUseCase {
+        aliases: MockIdAliasRepo::new(vec![(42, user.id)]),
+    };
+
+    assert_eq!(uc.execute(42).await.unwrap(), user.id);
+    assert!(matches!(
+        uc.execute(43).await,
+        Err(AuthServiceError::NotFound)
+    ));
+}
+
+#[tokio::test]
+async fn should_reject_non_positive_legacy_ids() {
+    let uc = ResolveLegacyIdUseCase {
+        aliases: MockIdAliasRepo::new(vec![]),
+    };
+
+    for legacy_id in [0, -1] {
+        assert!(matches!(
+            uc.execute(legacy_id).await,
+            Err(AuthServiceError::BadRequest(_))
+        ));
+    }
+}
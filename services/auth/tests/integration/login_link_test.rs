@@ -0,0 +1,205 @@
+use chrono::{Duration, Utc};
+
+use madome_auth::domain::types::LoginLink;
+use madome_auth::error::AuthServiceError;
+use madome_auth::usecase::login_link::{
+    ConsumeLoginLinkUseCase, CreateLoginLinkInput, CreateLoginLinkUseCase,
+};
+use madome_auth::usecase::session::ClientInfo;
+use madome_auth::usecase::token::validate_token;
+use madome_auth_types::cookie::TokenLifetimes;
+use madome_auth_types::login_link::issue_login_link_token;
+
+use crate::helpers::{
+    MockLoginLinkRepo, MockSessionRepo, MockUserRepo, TEST_JWT_SECRET, test_login_link, test_user,
+};
+
+fn consume_uc(
+    users: MockUserRepo,
+    login_links: MockLoginLinkRepo,
+) -> ConsumeLoginLinkUseCase<MockUserRepo, MockLoginLinkRepo, MockSessionRepo> {
+    ConsumeLoginLinkUseCase {
+        users,
+        login_links,
+        sessions: MockSessionRepo::empty(),
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+    }
+}
+
+/// The token the mail worker signs for `link`.
+fn link_token(link: &LoginLink, secret: &str) -> String {
+    issue_login_link_token(link.id, link.expires_at.timestamp() as u64, secret).unwrap()
+}
+
+// ── CreateLoginLinkUseCase ───────────────────────────────────────────────────
+
+#[tokio::test]
+async fn should_create_login_link_and_outbox_event_for_known_user() {
+    let user = test_user();
+    let repo = MockLoginLinkRepo::empty();
+    let links = repo.links_handle();
+    let events = repo.events_handle();
+
+    let uc = CreateLoginLinkUseCase {
+        users: MockUserRepo::new(vec![user.clone()]),
+        login_links: repo,
+    };
+
+    uc.execute(CreateLoginLinkInput {
+        email: user.email.clone(),
+    })
+    .await
+    .unwrap();
+
+    let links = links.lock().unwrap();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].user_id, user.id);
+    assert!(links[0].used_at.is_none());
+    assert!(links[0].expires_at > Utc::now());
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, "login_link_created");
+    assert_eq!(events[0].payload["email"], user.email);
+    assert_eq!(events[0].payload["login_link_id"], links[0].id.to_string());
+    // Nothing in the event signs the user in.
+    assert_eq!(
+        events[0].payload.as_object().unwrap().len(),
+        3,
+        "{}",
+        events[0].payload
+    );
+}
+
+#[tokio::test]
+async fn should_return_not_found_when_user_unknown_for_login_link() {
+    let uc = CreateLoginLinkUseCase {
+        users: MockUserRepo::empty(),
+        login_links: MockLoginLinkRepo::empty(),
+    };
+
+    let result = uc
+        .execute(CreateLoginLinkInput {
+            email: "nobody@example.com".to_owned(),
+        })
+        .await;
+
+    assert!(
+        matches!(result, Err(AuthServiceError::NotFound)),
+        "expected NotFound, got {result:?}"
+    );
+}
+
+#[tokio::test]
+async fn should_return_too_many_requests_when_login_link_limit_reached() {
+    let user = test_user();
+    let links = (0..5).map(|_| test_login_link(user.id)).collect();
+
+    let uc = CreateLoginLinkUseCase {
+        users: MockUserRepo::new(vec![user.clone()]),
+        login_links: MockLoginLinkRepo::new(links),
+    };
+
+    let result = uc
+        .execute(CreateLoginLinkInput {
+            email: user.email.clone(),
+        })
+        .await;
+
+    assert!(
+        matches!(result, Err(AuthServiceError::TooManyRequests(_))),
+        "expected TooManyRequests, got {result:?}"
+    );
+}
+
+// ── ConsumeLoginLinkUseCase ──────────────────────────────────────────────────
+
+#[tokio::test]
+async fn should_issue_token_pair_for_valid_login_link() {
+    let user = test_user();
+    let link = test_login_link(user.id);
+    let token = link_token(&link, TEST_JWT_SECRET);
+
+    let uc = consume_uc(
+        MockUserRepo::new(vec![user.clone()]),
+        MockLoginLinkRepo::new(vec![link]),
+    );
+
+    let output = uc.execute(&token, &ClientInfo::default()).await.unwrap();
+
+    assert_eq!(output.user.id, user.id);
+    let claims = validate_token(&output.access_token, TEST_JWT_SECRET).unwrap();
+    assert_eq!(claims.sub, user.id.to_string());
+    let refresh_claims = validate_token(&output.refresh_token, TEST_JWT_SECRET).unwrap();
+    assert!(refresh_claims.sid.is_some());
+}
+
+#[tokio::test]
+async fn should_return_unauthorized_when_login_link_reused() {
+    let user = test_user();
+    let link = test_login_link(user.id);
+    let token = link_token(&link, TEST_JWT_SECRET);
+
+    let uc = consume_uc(
+        MockUserRepo::new(vec![user]),
+        MockLoginLinkRepo::new(vec![link]),
+    );
+
+    uc.execute(&token, &ClientInfo::default()).await.unwrap();
+    let result = uc.execute(&token, &ClientInfo::default()).await;
+
+    assert!(
+        matches!(result, Err(AuthServiceError::Unauthorized)),
+        "expected Unauthorized, got {result:?}"
+    );
+}
+
+#[tokio::test]
+async fn should_return_unauthorized_when_login_link_expired() {
+    let user = test_user();
+    let mut link = test_login_link(user.id);
+    link.expires_at = Utc::now() - Duration::minutes(5);
+    let token = link_token(&link, TEST_JWT_SECRET);
+
+    let uc = consume_uc(
+        MockUserRepo::new(vec![user]),
+        MockLoginLinkRepo::new(vec![link]),
+    );
+
+    let result = uc.execute(&token, &ClientInfo::default()).await;
+
+    assert!(
+        matches!(result, Err(AuthServiceError::Unauthorized)),
+        "expected Unauthorized, got {result:?}"
+    );
+}
+
+#[tokio::test]
+async fn should_return_unauthorized_when_login_link_signed_with_wrong_secret() {
+    let user = test_user();
+    let link = test_login_link(user.id);
+    let token = link_token(&link, "other-secret");
+
+    let uc = consume_uc(
+        MockUserRepo::new(vec![user]),
+        MockLoginLinkRepo::new(vec![link]),
+    );
+
+    let result = uc.execute(&token, &ClientInfo::default()).await;
+
+    assert!(
+        matches!(result, Err(AuthServiceError::Unauthorized)),
+        "expected Unauthorized, got {result:?}"
+    );
+}
+
+#[tokio::test]
+async fn should_reject_login_link_token_as_session_token() {
+    let user = test_user();
+    let link = test_login_link(user.id);
+    let token = link_token(&link, TEST_JWT_SECRET);
+
+    assert!(validate_token(&token, TEST_JWT_SECRET).is_err());
+}
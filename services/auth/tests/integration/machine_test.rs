@@ -0,0 +1,90 @@
+use uuid::Uuid;
+
+use madome_auth::error::AuthServiceError;
+use madome_auth::usecase::machine::{
+    IssueMachineTokenInput, IssueMachineTokenUseCase, MACHINE_TOKEN_DEFAULT_TTL_SECS,
+};
+use madome_auth::usecase::token::{IntrospectTokenUseCase, validate_token};
+use madome_auth_types::machine::validate_machine_token;
+use madome_domain::id::UserId;
+
+use crate::helpers::TEST_JWT_SECRET;
+
+const STAFF_ROLE: u8 = 2;
+
+fn machine_uc() -> IssueMachineTokenUseCase {
+    IssueMachineTokenUseCase {
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+    }
+}
+
+fn input(ttl_secs: Option<u64>) -> IssueMachineTokenInput {
+    IssueMachineTokenInput {
+        client: "image-gen".to_owned(),
+        scopes: vec!["library.read".to_owned(), "library.renew".to_owned()],
+        ttl_secs,
+    }
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[test]
+fn should_issue_machine_token_with_requested_scopes() {
+    let out = machine_uc()
+        .execute(UserId(Uuid::new_v4()), STAFF_ROLE, None, input(Some(600)))
+        .unwrap();
+
+    let info = validate_machine_token(&out.token, TEST_JWT_SECRET).unwrap();
+    assert_eq!(info.client, "image-gen");
+    assert!(info.has_scope("library.renew"));
+    assert!(!info.has_scope("users.write"));
+    assert_eq!(info.exp, out.exp);
+    assert!(out.exp <= now_secs() + 600);
+}
+
+#[test]
+fn should_default_machine_token_lifetime_to_one_day() {
+    let before = now_secs();
+    let out = machine_uc()
+        .execute(UserId(Uuid::new_v4()), STAFF_ROLE, None, input(None))
+        .unwrap();
+
+    assert!(out.exp >= before + MACHINE_TOKEN_DEFAULT_TTL_SECS);
+    assert!(out.exp <= now_secs() + MACHINE_TOKEN_DEFAULT_TTL_SECS);
+}
+
+#[test]
+fn should_forbid_machine_tokens_below_staff_role_or_while_impersonating() {
+    for role in [0, 1] {
+        let result = machine_uc().execute(UserId(Uuid::new_v4()), role, None, input(None));
+        assert!(matches!(result, Err(AuthServiceError::Forbidden)));
+    }
+
+    let result = machine_uc().execute(
+        UserId(Uuid::new_v4()),
+        STAFF_ROLE,
+        Some(Uuid::new_v4()),
+        input(None),
+    );
+    assert!(matches!(result, Err(AuthServiceError::Forbidden)));
+}
+
+#[test]
+fn should_not_accept_machine_token_as_user_token() {
+    let out = machine_uc()
+        .execute(UserId(Uuid::new_v4()), STAFF_ROLE, None, input(None))
+        .unwrap();
+
+    assert!(validate_token(&out.token, TEST_JWT_SECRET).is_err());
+
+    let introspect = IntrospectTokenUseCase {
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+    };
+    assert!(introspect.execute(&out.token).is_none());
+}
@@ -1,5 +1,20 @@
 mod helpers;
 
+mod api_token_test;
 mod authcode_test;
+mod config_test;
+mod export_test;
+mod id_alias_test;
+mod login_link_test;
+mod machine_test;
+mod memory_test;
+mod oauth_test;
+mod openapi_test;
+mod outbox_test;
 mod passkey_test;
+mod permission_test;
+mod session_test;
+mod sqlite_test;
+mod stats_test;
 mod token_test;
+mod webauthn_util_test;
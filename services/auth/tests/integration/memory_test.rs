@@ -0,0 +1,120 @@
+use madome_auth::domain::repository::{IdAliasRepository, OutboxRepository, SessionRepository};
+use madome_auth::domain::types::OutboxStatus;
+use madome_auth::error::AuthServiceError;
+use madome_auth::infra::memory::{
+    InMemoryAuthCodeRepository, InMemoryStore, InMemoryUserRepository,
+};
+use madome_auth::usecase::authcode::{CreateAuthcodeInput, CreateAuthcodeUseCase};
+use madome_auth::usecase::session::ClientInfo;
+use madome_auth::usecase::token::{CreateTokenInput, CreateTokenUseCase};
+use madome_auth_types::cookie::TokenLifetimes;
+use madome_domain::pagination::PageRequest;
+
+use crate::helpers::{TEST_JWT_SECRET, test_user};
+
+fn create_authcode(
+    store: &InMemoryStore,
+) -> CreateAuthcodeUseCase<InMemoryUserRepository, InMemoryAuthCodeRepository> {
+    CreateAuthcodeUseCase {
+        users: store.user_repo(),
+        auth_codes: store.auth_code_repo(),
+        code_secret: TEST_JWT_SECRET.to_owned(),
+    }
+}
+
+#[tokio::test]
+async fn should_sign_in_with_the_mailed_code_against_the_in_memory_store() {
+    let store = InMemoryStore::new();
+    let user = test_user();
+    store.add_user(user.clone());
+
+    create_authcode(&store)
+        .execute(CreateAuthcodeInput {
+            email: user.email.clone(),
+        })
+        .await
+        .unwrap();
+    let outbox = store.outbox();
+    assert_eq!(outbox.len(), 1);
+    let code = outbox[0].event.payload["code"].as_str().unwrap().to_owned();
+
+    let sign_in = CreateTokenUseCase {
+        users: store.user_repo(),
+        auth_codes: store.auth_code_repo(),
+        attempts: store.auth_code_attempts(),
+        sessions: store.session_repo(),
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+    };
+    let input = || CreateTokenInput {
+        email: user.email.clone(),
+        code: code.clone(),
+        client: ClientInfo::default(),
+        remember: false,
+    };
+    sign_in.execute(input()).await.unwrap();
+    let sessions = store.session_repo().list_by_user(user.id).await.unwrap();
+    assert_eq!(sessions.len(), 1);
+    // The first sign-in used the code up.
+    assert!(matches!(
+        sign_in.execute(input()).await,
+        Err(AuthServiceError::NotFound)
+    ));
+
+    let pending = store
+        .outbox_repo()
+        .list(OutboxStatus::Pending, PageRequest::default())
+        .await
+        .unwrap();
+    assert_eq!(pending.total, 1);
+}
+
+#[tokio::test]
+async fn should_hide_soft_deleted_users_from_every_lookup() {
+    let store = InMemoryStore::new();
+    let user = test_user();
+    store.add_user(user.clone());
+    store.add_id_alias(42, user.id);
+    store.delete_user(user.id);
+
+    let result = create_authcode(&store)
+        .execute(CreateAuthcodeInput { email: user.email })
+        .await;
+    assert!(matches!(result, Err(AuthServiceError::NotFound)));
+    assert_eq!(store.id_alias_repo().resolve(42).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn should_replay_and_discard_only_failed_outbox_events() {
+    let store = InMemoryStore::new();
+    let user = test_user();
+    store.add_user(user.clone());
+    for _ in 0..2 {
+        create_authcode(&store)
+            .execute(CreateAuthcodeInput {
+                email: user.email.clone(),
+            })
+            .await
+            .unwrap();
+    }
+    let ids: Vec<_> = store.outbox().iter().map(|e| e.event.id).collect();
+    let (first, second) = (ids[0], ids[1]);
+    store.fail_outbox_event(first, "smtp timeout");
+
+    let outbox = store.outbox_repo();
+    assert!(!outbox.retry_failed(second).await.unwrap());
+    assert!(outbox.retry_failed(first).await.unwrap());
+    assert_eq!(
+        outbox
+            .list(OutboxStatus::Pending, PageRequest::default())
+            .await
+            .unwrap()
+            .total,
+        2
+    );
+
+    store.fail_outbox_event(second, "smtp timeout");
+    assert!(outbox.delete_failed(second).await.unwrap());
+    assert_eq!(store.outbox().len(), 1);
+}
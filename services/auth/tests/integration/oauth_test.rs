@@ -0,0 +1,332 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use madome_auth::domain::types::{LinkedIdentity, OAuthProfile};
+use madome_auth::error::AuthServiceError;
+use madome_auth::usecase::oauth::{
+    FinishOAuthInput, FinishOAuthUseCase, StartOAuthUseCase, pkce_challenge,
+};
+use madome_auth::usecase::session::ClientInfo;
+use madome_auth::usecase::token::validate_token;
+use madome_auth_types::cookie::TokenLifetimes;
+
+use crate::helpers::{
+    MockLinkedIdentityRepo, MockOAuthClient, MockOAuthStateCache, MockSessionRepo, MockUserRepo,
+    TEST_JWT_SECRET, test_oauth_profile, test_user,
+};
+
+/// Run the start step and return the `state` embedded in the authorize URL.
+async fn start(oauth: &MockOAuthClient, cache: &MockOAuthStateCache, provider: &str) -> String {
+    let uc = StartOAuthUseCase {
+        oauth: oauth.clone(),
+        cache: cache.clone(),
+    };
+    let out = uc.execute(provider).await.unwrap();
+    query_param(&out.authorize_url, "state")
+}
+
+fn query_param(url: &str, name: &str) -> String {
+    url.split_once('?')
+        .unwrap()
+        .1
+        .split('&')
+        .find_map(|kv| kv.strip_prefix(&format!("{name}=")))
+        .unwrap()
+        .to_owned()
+}
+
+fn finish_uc(
+    users: MockUserRepo,
+    identities: MockLinkedIdentityRepo,
+    oauth: MockOAuthClient,
+    cache: MockOAuthStateCache,
+) -> FinishOAuthUseCase<
+    MockUserRepo,
+    MockLinkedIdentityRepo,
+    MockSessionRepo,
+    MockOAuthClient,
+    MockOAuthStateCache,
+> {
+    FinishOAuthUseCase {
+        users,
+        identities,
+        sessions: MockSessionRepo::empty(),
+        oauth,
+        cache,
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        lifetimes: TokenLifetimes::default(),
+    }
+}
+
+fn finish_input(provider: &str, state: String) -> FinishOAuthInput {
+    FinishOAuthInput {
+        provider: provider.to_owned(),
+        code: "provider-code".to_owned(),
+        browser_state: Some(state.clone()),
+        state,
+        client: ClientInfo::default(),
+    }
+}
+
+// ── StartOAuthUseCase ────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn should_return_authorize_url_and_cache_state_on_start() {
+    let oauth = MockOAuthClient::new(None);
+    let cache = MockOAuthStateCache::default();
+
+    let state = start(&oauth, &cache, "google").await;
+
+    assert_eq!(state.len(), 43, "state should be 32 base64url bytes");
+    assert!(cache.states.lock().unwrap().contains_key(&state));
+}
+
+#[tokio::test]
+async fn should_return_not_found_for_unconfigured_provider_on_start() {
+    let cache = MockOAuthStateCache::default();
+    let uc = StartOAuthUseCase {
+        oauth: MockOAuthClient::new(None),
+        cache: cache.clone(),
+    };
+
+    let result = uc.execute("myspace").await;
+
+    assert!(
+        matches!(result, Err(AuthServiceError::NotFound)),
+        "expected NotFound, got {result:?}"
+    );
+    assert!(cache.states.lock().unwrap().is_empty());
+}
+
+// ── FinishOAuthUseCase ───────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn should_link_identity_by_verified_email_and_issue_tokens() {
+    let user = test_user();
+    let oauth = MockOAuthClient::new(Some(test_oauth_profile(&user.email)));
+    let cache = MockOAuthStateCache::default();
+    let uc_start = StartOAuthUseCase {
+        oauth: oauth.clone(),
+        cache: cache.clone(),
+    };
+    let authorize_url = uc_start.execute("google").await.unwrap().authorize_url;
+    let state = query_param(&authorize_url, "state");
+
+    let identities = MockLinkedIdentityRepo::empty();
+    let handle = identities.identities_handle();
+    let uc = finish_uc(
+        MockUserRepo::new(vec![user.clone()]),
+        identities,
+        oauth.clone(),
+        cache,
+    );
+
+    let output = uc.execute(finish_input("google", state)).await.unwrap();
+
+    assert_eq!(output.user.id, user.id);
+    let claims = validate_token(&output.access_token, TEST_JWT_SECRET).unwrap();
+    assert_eq!(claims.sub, user.id.to_string());
+
+    let linked = handle.lock().unwrap();
+    assert_eq!(linked.len(), 1);
+    assert_eq!(linked[0].user_id, user.id);
+    assert_eq!(linked[0].provider, "google");
+    assert_eq!(linked[0].subject, "provider-account-1");
+    assert_eq!(linked[0].email, user.email);
+
+    // The verifier sent at exchange must match the challenge sent at authorization.
+    let verifier = oauth.last_verifier.lock().unwrap().clone().unwrap();
+    assert_eq!(
+        pkce_challenge(&verifier),
+        query_param(&authorize_url, "code_challenge")
+    );
+}
+
+#[tokio::test]
+async fn should_sign_in_with_already_linked_identity() {
+    let user = test_user();
+    // Provider email changed since linking; the link still resolves by subject.
+    let oauth = MockOAuthClient::new(Some(test_oauth_profile("renamed@example.com")));
+    let cache = MockOAuthStateCache::default();
+    let state = start(&oauth, &cache, "github").await;
+
+    let identities = MockLinkedIdentityRepo::new(vec![LinkedIdentity {
+        id: Uuid::new_v4(),
+        user_id: user.id,
+        provider: "github".to_owned(),
+        subject: "provider-account-1".to_owned(),
+        email: user.email.clone(),
+        created_at: Utc::now(),
+    }]);
+    let handle = identities.identities_handle();
+    let uc = finish_uc(
+        MockUserRepo::new(vec![user.clone()]),
+        identities,
+        oauth,
+        cache,
+    );
+
+    let output = uc.execute(finish_input("github", state)).await.unwrap();
+
+    assert_eq!(output.user.id, user.id);
+    assert_eq!(handle.lock().unwrap().len(), 1, "no new link expected");
+}
+
+#[tokio::test]
+async fn should_return_unauthorized_when_provider_email_not_verified() {
+    let user = test_user();
+    let oauth = MockOAuthClient::new(Some(OAuthProfile {
+        email_verified: false,
+        ..test_oauth_profile(&user.email)
+    }));
+    let cache = MockOAuthStateCache::default();
+    let state = start(&oauth, &cache, "google").await;
+
+    let identities = MockLinkedIdentityRepo::empty();
+    let handle = identities.identities_handle();
+    let uc = finish_uc(MockUserRepo::new(vec![user]), identities, oauth, cache);
+
+    let result = uc.execute(finish_input("google", state)).await;
+
+    assert!(
+        matches!(result, Err(AuthServiceError::Unauthorized)),
+        "expected Unauthorized, got {result:?}"
+    );
+    assert!(handle.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn should_return_not_found_when_no_user_has_provider_email() {
+    let oauth = MockOAuthClient::new(Some(test_oauth_profile("stranger@example.com")));
+    let cache = MockOAuthStateCache::default();
+    let state = start(&oauth, &cache, "google").await;
+
+    let uc = finish_uc(
+        MockUserRepo::new(vec![test_user()]),
+        MockLinkedIdentityRepo::empty(),
+        oauth,
+        cache,
+    );
+
+    let result = uc.execute(finish_input("google", state)).await;
+
+    assert!(
+        matches!(result, Err(AuthServiceError::NotFound)),
+        "expected NotFound, got {result:?}"
+    );
+}
+
+#[tokio::test]
+async fn should_return_unauthorized_when_state_unknown() {
+    let user = test_user();
+    let uc = finish_uc(
+        MockUserRepo::new(vec![user.clone()]),
+        MockLinkedIdentityRepo::empty(),
+        MockOAuthClient::new(Some(test_oauth_profile(&user.email))),
+        MockOAuthStateCache::default(),
+    );
+
+    let result = uc
+        .execute(finish_input("google", "forged-state".to_owned()))
+        .await;
+
+    assert!(
+        matches!(result, Err(AuthServiceError::Unauthorized)),
+        "expected Unauthorized, got {result:?}"
+    );
+}
+
+#[tokio::test]
+async fn should_return_unauthorized_when_state_reused() {
+    let user = test_user();
+    let oauth = MockOAuthClient::new(Some(test_oauth_profile(&user.email)));
+    let cache = MockOAuthStateCache::default();
+    let state = start(&oauth, &cache, "google").await;
+    let uc = finish_uc(
+        MockUserRepo::new(vec![user]),
+        MockLinkedIdentityRepo::empty(),
+        oauth,
+        cache,
+    );
+
+    uc.execute(finish_input("google", state.clone()))
+        .await
+        .unwrap();
+    let result = uc.execute(finish_input("google", state)).await;
+
+    assert!(
+        matches!(result, Err(AuthServiceError::Unauthorized)),
+        "expected Unauthorized, got {result:?}"
+    );
+}
+
+#[tokio::test]
+async fn should_return_unauthorized_when_state_issued_for_other_provider() {
+    let user = test_user();
+    let oauth = MockOAuthClient::new(Some(test_oauth_profile(&user.email)));
+    let cache = MockOAuthStateCache::default();
+    let state = start(&oauth, &cache, "google").await;
+    let uc = finish_uc(
+        MockUserRepo::new(vec![user]),
+        MockLinkedIdentityRepo::empty(),
+        oauth,
+        cache,
+    );
+
+    let result = uc.execute(finish_input("github", state)).await;
+
+    assert!(
+        matches!(result, Err(AuthServiceError::Unauthorized)),
+        "expected Unauthorized, got {result:?}"
+    );
+}
+
+#[tokio::test]
+async fn should_return_unauthorized_when_provider_rejects_code() {
+    let oauth = MockOAuthClient::new(None);
+    let cache = MockOAuthStateCache::default();
+    let state = start(&oauth, &cache, "google").await;
+    let uc = finish_uc(
+        MockUserRepo::new(vec![test_user()]),
+        MockLinkedIdentityRepo::empty(),
+        oauth,
+        cache,
+    );
+
+    let result = uc.execute(finish_input("google", state)).await;
+
+    assert!(
+        matches!(result, Err(AuthServiceError::Unauthorized)),
+        "expected Unauthorized, got {result:?}"
+    );
+}
+
+#[tokio::test]
+async fn should_return_unauthorized_when_callback_opened_in_another_browser() {
+    let user = test_user();
+    let oauth = MockOAuthClient::new(Some(test_oauth_profile(&user.email)));
+    let cache = MockOAuthStateCache::default();
+    let state = start(&oauth, &cache, "google").await;
+    let uc = finish_uc(
+        MockUserRepo::new(vec![user]),
+        MockLinkedIdentityRepo::empty(),
+        oauth,
+        cache.clone(),
+    );
+
+    for browser_state in [
+        None,
+        Some(start(&MockOAuthClient::new(None), &cache, "google").await),
+    ] {
+        let input = FinishOAuthInput {
+            browser_state,
+            ..finish_input("google", state.clone())
+        };
+        let result = uc.execute(input).await;
+        assert!(matches!(result, Err(AuthServiceError::Unauthorized)));
+    }
+
+    // Still redeemable by the browser that started the flow.
+    assert!(cache.states.lock().unwrap().contains_key(&state));
+    uc.execute(finish_input("google", state)).await.unwrap();
+}
@@ -0,0 +1,87 @@
+use utoipa::OpenApi;
+
+use madome_auth::openapi::ApiDoc;
+
+#[test]
+fn should_document_every_route() {
+    let doc = ApiDoc::openapi();
+
+    let operations: Vec<String> = doc
+        .paths
+        .paths
+        .iter()
+        .flat_map(|(path, item)| {
+            let methods = [
+                ("GET", item.get.is_some()),
+                ("POST", item.post.is_some()),
+                ("PATCH", item.patch.is_some()),
+                ("DELETE", item.delete.is_some()),
+            ];
+            methods
+                .into_iter()
+                .filter(|(_, present)| *present)
+                .map(move |(method, _)| format!("{method} {path}"))
+        })
+        .collect();
+
+    for expected in [
+        "POST /auth/code",
+        "GET /auth/csrf",
+        "GET /auth/config",
+        "POST /auth/link",
+        "GET /auth/link/callback",
+        "POST /auth/oauth/{provider}/start",
+        "GET /auth/oauth/{provider}/callback",
+        "GET /auth/token",
+        "POST /auth/token",
+        "PATCH /auth/token",
+        "DELETE /auth/token",
+        "GET /auth/sessions",
+        "DELETE /auth/sessions/{id}",
+        "GET /auth/api-tokens",
+        "POST /auth/api-tokens",
+        "DELETE /auth/api-tokens/{id}",
+        "GET /auth/passkeys",
+        "DELETE /auth/passkeys/{credential_id}",
+        "POST /auth/passkey/registration",
+        "PATCH /auth/passkey/registration",
+        "POST /auth/passkey/authentication",
+        "PATCH /auth/passkey/authentication",
+        "POST /auth/impersonate/{user_id}",
+        "POST /auth/machine-tokens",
+        "GET /auth/admin/outbox",
+        "POST /auth/admin/outbox/{id}/retry",
+        "DELETE /auth/admin/outbox/{id}",
+        "GET /auth/admin/stats",
+    ] {
+        assert!(
+            operations.iter().any(|op| op == expected),
+            "{expected} undocumented"
+        );
+    }
+    assert_eq!(operations.len(), 28);
+}
+
+#[test]
+fn should_include_error_catalog_schemas_and_cookie_schemes() {
+    let doc = ApiDoc::openapi();
+    let components = doc.components.unwrap();
+
+    for schema in ["ErrorBody", "ErrorKind", "FieldError", "CreateTokenRequest"] {
+        assert!(components.schemas.contains_key(schema), "{schema} missing");
+    }
+    for scheme in ["accessTokenCookie", "refreshTokenCookie", "gatewayIdentity"] {
+        assert!(
+            components.security_schemes.contains_key(scheme),
+            "{scheme} missing"
+        );
+    }
+}
+
+#[test]
+fn should_use_wire_names_for_query_parameters() {
+    let json = ApiDoc::openapi().to_json().unwrap();
+
+    assert!(json.contains("\"registration-id\""));
+    assert!(json.contains("\"authentication-id\""));
+}
@@ -0,0 +1,131 @@
+use serde_json::json;
+use uuid::Uuid;
+
+use madome_auth::domain::types::OutboxStatus;
+use madome_auth::error::AuthServiceError;
+use madome_auth::usecase::outbox::{
+    DeleteOutboxEventUseCase, DeliveryStatus, ListOutboxEventsUseCase, RetryOutboxEventUseCase,
+};
+use madome_domain::pagination::PageRequest;
+
+use crate::helpers::{MockOutboxRepo, test_outbox_entry, test_user};
+
+// ── ListOutboxEventsUseCase ──────────────────────────────────────────────────
+
+#[tokio::test]
+async fn should_list_only_events_in_requested_status_newest_first() {
+    let older = test_outbox_entry(OutboxStatus::Failed, 120);
+    let newer = test_outbox_entry(OutboxStatus::Failed, 60);
+    let uc = ListOutboxEventsUseCase {
+        outbox: MockOutboxRepo::new(vec![
+            older.clone(),
+            test_outbox_entry(OutboxStatus::Pending, 30),
+            test_outbox_entry(OutboxStatus::Processed, 10),
+            newer.clone(),
+        ]),
+    };
+
+    let page = uc
+        .execute(DeliveryStatus::Failed, PageRequest::default())
+        .await
+        .unwrap();
+
+    let ids: Vec<Uuid> = page.items.iter().map(|e| e.id).collect();
+    assert_eq!(ids, vec![newer.event.id, older.event.id]);
+    assert_eq!(page.total, 2);
+}
+
+#[tokio::test]
+async fn should_redact_code_of_listed_authcode_event() {
+    let mut entry = test_outbox_entry(OutboxStatus::Pending, 60);
+    entry.event.payload = json!({ "email": "user@example.com", "code": "AB12CD34EF56" });
+    let uc = ListOutboxEventsUseCase {
+        outbox: MockOutboxRepo::new(vec![entry]),
+    };
+
+    let page = uc
+        .execute(DeliveryStatus::Pending, PageRequest::default())
+        .await
+        .unwrap();
+
+    let payload = &page.items[0].payload;
+    assert_eq!(payload["email"], "user@example.com");
+    assert_eq!(payload["code"], "[redacted]");
+    assert!(!payload.to_string().contains("AB12CD34EF56"));
+}
+
+#[tokio::test]
+async fn should_page_events_and_report_total() {
+    let entries = (0..3)
+        .map(|age| test_outbox_entry(OutboxStatus::Failed, age))
+        .collect();
+    let uc = ListOutboxEventsUseCase {
+        outbox: MockOutboxRepo::new(entries),
+    };
+
+    let page = uc
+        .execute(
+            DeliveryStatus::Failed,
+            PageRequest {
+                per_page: 2,
+                page: 2,
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.total, 3);
+}
+
+// ── RetryOutboxEventUseCase ──────────────────────────────────────────────────
+
+#[tokio::test]
+async fn should_requeue_failed_event_keeping_last_error() {
+    let entry = test_outbox_entry(OutboxStatus::Failed, 60);
+    let repo = MockOutboxRepo::new(vec![entry.clone()]);
+    let handle = repo.entries_handle();
+    let uc = RetryOutboxEventUseCase { outbox: repo };
+
+    uc.execute(test_user().id, entry.event.id).await.unwrap();
+
+    let entries = handle.lock().unwrap();
+    assert_eq!(entries[0].status(), OutboxStatus::Pending);
+    assert_eq!(entries[0].attempts, 0);
+    assert_eq!(entries[0].last_error, entry.last_error);
+}
+
+#[tokio::test]
+async fn should_return_not_found_when_retrying_event_that_has_not_failed() {
+    for status in [OutboxStatus::Pending, OutboxStatus::Processed] {
+        let entry = test_outbox_entry(status, 60);
+        let uc = RetryOutboxEventUseCase {
+            outbox: MockOutboxRepo::new(vec![entry.clone()]),
+        };
+
+        let result = uc.execute(test_user().id, entry.event.id).await;
+
+        assert!(
+            matches!(result, Err(AuthServiceError::NotFound)),
+            "expected NotFound for {status:?}, got {result:?}"
+        );
+    }
+}
+
+// ── DeleteOutboxEventUseCase ─────────────────────────────────────────────────
+
+#[tokio::test]
+async fn should_delete_only_failed_events() {
+    let failed = test_outbox_entry(OutboxStatus::Failed, 60);
+    let pending = test_outbox_entry(OutboxStatus::Pending, 60);
+    let repo = MockOutboxRepo::new(vec![failed.clone(), pending.clone()]);
+    let handle = repo.entries_handle();
+    let uc = DeleteOutboxEventUseCase { outbox: repo };
+
+    uc.execute(test_user().id, failed.event.id).await.unwrap();
+    let result = uc.execute(test_user().id, pending.event.id).await;
+
+    assert!(matches!(result, Err(AuthServiceError::NotFound)));
+    let remaining: Vec<Uuid> = handle.lock().unwrap().iter().map(|e| e.event.id).collect();
+    assert_eq!(remaining, vec![pending.event.id]);
+}
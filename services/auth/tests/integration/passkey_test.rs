@@ -1,9 +1,19 @@
+use std::sync::Arc;
+
+use url::Url;
 use uuid::Uuid;
+use webauthn_rs::prelude::{RegisterPublicKeyCredential, WebauthnBuilder};
 
+use madome_auth::domain::types::PasskeyRecord;
 use madome_auth::error::AuthServiceError;
-use madome_auth::usecase::passkey::{DeletePasskeyUseCase, ListPasskeysUseCase};
+use madome_auth::usecase::passkey::{
+    DeletePasskeyUseCase, FinishRegistrationUseCase, ListPasskeysUseCase, StartRegistrationUseCase,
+};
+use madome_domain::id::UserId;
 
-use crate::helpers::{MockPasskeyRepo, test_passkey_record, test_user};
+use crate::helpers::{
+    MockPasskeyCache, MockPasskeyRepo, MockUserRepo, test_passkey_record, test_user,
+};
 
 // ── ListPasskeysUseCase ──────────────────────────────────────────────────────
 
@@ -34,10 +44,30 @@ async fn should_return_passkey_records_for_user() {
     assert_eq!(result[0].credential_id, expected_cred_id);
 }
 
+#[tokio::test]
+async fn should_return_backup_flags_and_transports() {
+    let user = test_user();
+    let record = PasskeyRecord {
+        backup_eligible: false,
+        backup_state: false,
+        transports: vec!["usb".to_owned(), "nfc".to_owned()],
+        ..test_passkey_record(user.id)
+    };
+
+    let uc = ListPasskeysUseCase {
+        passkeys: MockPasskeyRepo::new(vec![record]),
+    };
+
+    let result = uc.execute(user.id).await.unwrap();
+    assert!(!result[0].backup_eligible);
+    assert!(!result[0].backup_state);
+    assert_eq!(result[0].transports, ["usb", "nfc"]);
+}
+
 #[tokio::test]
 async fn should_not_return_passkeys_belonging_to_other_users() {
     let user = test_user();
-    let other_user_id = Uuid::parse_str("00000000-0000-0000-0000-000000000099").unwrap();
+    let other_user_id = UserId(Uuid::parse_str("00000000-0000-0000-0000-000000000099").unwrap());
     let record = test_passkey_record(other_user_id);
 
     let uc = ListPasskeysUseCase {
@@ -63,7 +93,7 @@ async fn should_delete_passkey_for_existing_credential() {
         passkeys: MockPasskeyRepo::new(vec![record]),
     };
 
-    let result = uc.execute(&cred_id, user.id).await;
+    let result = uc.execute(&cred_id, user.id, None).await;
     assert!(result.is_ok());
 }
 
@@ -75,7 +105,7 @@ async fn should_return_not_found_when_deleting_missing_credential() {
         passkeys: MockPasskeyRepo::empty(),
     };
 
-    let result = uc.execute(&[1, 2, 3], user.id).await;
+    let result = uc.execute(&[1, 2, 3], user.id, None).await;
     assert!(
         matches!(result, Err(AuthServiceError::NotFound)),
         "expected NotFound, got {result:?}"
@@ -85,7 +115,7 @@ async fn should_return_not_found_when_deleting_missing_credential() {
 #[tokio::test]
 async fn should_return_not_found_when_deleting_credential_of_other_user() {
     let user = test_user();
-    let other_user_id = Uuid::parse_str("00000000-0000-0000-0000-000000000099").unwrap();
+    let other_user_id = UserId(Uuid::parse_str("00000000-0000-0000-0000-000000000099").unwrap());
     let record = test_passkey_record(other_user_id);
     let cred_id = record.credential_id.clone();
 
@@ -94,9 +124,58 @@ async fn should_return_not_found_when_deleting_credential_of_other_user() {
     };
 
     // Try to delete another user's credential.
-    let result = uc.execute(&cred_id, user.id).await;
+    let result = uc.execute(&cred_id, user.id, None).await;
     assert!(
         matches!(result, Err(AuthServiceError::NotFound)),
         "expected NotFound when deleting other user's credential, got {result:?}"
     );
 }
+
+// ── Impersonation ────────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn should_refuse_passkey_changes_while_impersonating() {
+    let user = test_user();
+    let staff = Some(Uuid::new_v4());
+    let record = test_passkey_record(user.id);
+    let cred_id = record.credential_id.clone();
+    let cache = MockPasskeyCache::default();
+    let webauthn = Arc::new(
+        WebauthnBuilder::new("example.com", &Url::parse("https://example.com").unwrap())
+            .unwrap()
+            .build()
+            .unwrap(),
+    );
+
+    let delete = DeletePasskeyUseCase {
+        passkeys: MockPasskeyRepo::new(vec![record]),
+    };
+    let result = delete.execute(&cred_id, user.id, staff).await;
+    assert!(matches!(result, Err(AuthServiceError::Forbidden)));
+
+    let start = StartRegistrationUseCase {
+        users: MockUserRepo::new(vec![user.clone()]),
+        passkeys: MockPasskeyRepo::empty(),
+        cache: cache.clone(),
+        webauthn: webauthn.clone(),
+    };
+    let result = start.execute(user.id, staff).await;
+    assert!(matches!(result, Err(AuthServiceError::Forbidden)));
+    assert!(cache.states.lock().unwrap().is_empty());
+
+    let credential: RegisterPublicKeyCredential = serde_json::from_value(serde_json::json!({
+        "id": "AQID",
+        "rawId": "AQID",
+        "response": { "attestationObject": "AQID", "clientDataJSON": "AQID" },
+        "type": "public-key",
+        "extensions": {},
+    }))
+    .unwrap();
+    let finish = FinishRegistrationUseCase {
+        passkeys: MockPasskeyRepo::empty(),
+        cache,
+        webauthn,
+    };
+    let result = finish.execute(user.id, staff, "reg", credential).await;
+    assert!(matches!(result, Err(AuthServiceError::Forbidden)));
+}
@@ -0,0 +1,156 @@
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use axum::routing::{MethodFilter, on};
+use axum::{Router, middleware};
+use tower::ServiceExt;
+use utoipa::OpenApi;
+
+use madome_auth::openapi::ApiDoc;
+use madome_auth::router::{BODY_LIMITS, ROUTE_PERMISSIONS};
+use madome_auth_types::api_token::TOKEN_SCOPES_HEADER;
+use madome_core::permission::{Access, lookup, render, require_role};
+
+/// Changing who may call a route must show up here; review the diff like a
+/// permission change, not a test fixture.
+const EXPECTED: &str = "\
+GET    /healthz public\n\
+GET    /readyz public\n\
+GET    /auth/csrf public\n\
+GET    /auth/config public\n\
+POST   /auth/code public\n\
+POST   /auth/link public\n\
+GET    /auth/link/callback public\n\
+POST   /auth/oauth/{provider}/start public\n\
+GET    /auth/oauth/{provider}/callback public\n\
+GET    /auth/token public\n\
+POST   /auth/token public\n\
+PATCH  /auth/token public\n\
+DELETE /auth/token role>=0 (Normal)\n\
+GET    /auth/sessions role>=0 (Normal)\n\
+DELETE /auth/sessions/{id} role>=0 (Normal)\n\
+GET    /auth/api-tokens role>=0 (Normal)\n\
+POST   /auth/api-tokens role>=0 (Normal)\n\
+DELETE /auth/api-tokens/{id} role>=0 (Normal)\n\
+GET    /auth/passkeys role>=0 (Normal)\n\
+DELETE /auth/passkeys/{credential_id} role>=0 (Normal)\n\
+POST   /auth/passkey/registration role>=0 (Normal)\n\
+PATCH  /auth/passkey/registration role>=0 (Normal)\n\
+POST   /auth/passkey/authentication public\n\
+PATCH  /auth/passkey/authentication public\n\
+POST   /auth/impersonate/{user_id} role>=2 (Bot)\n\
+POST   /auth/machine-tokens role>=2 (Bot)\n\
+GET    /auth/admin/outbox role>=2 (Bot)\n\
+POST   /auth/admin/outbox/{id}/retry role>=2 (Bot)\n\
+DELETE /auth/admin/outbox/{id} role>=2 (Bot)\n\
+GET    /auth/admin/stats role>=2 (Bot)\n\
+";
+
+#[test]
+fn should_match_permission_matrix_snapshot() {
+    assert_eq!(render(ROUTE_PERMISSIONS), EXPECTED);
+}
+
+#[test]
+fn should_list_every_documented_operation() {
+    let doc = ApiDoc::openapi();
+
+    for (path, item) in &doc.paths.paths {
+        let operations = [
+            (Method::GET, &item.get),
+            (Method::POST, &item.post),
+            (Method::PATCH, &item.patch),
+            (Method::DELETE, &item.delete),
+        ];
+        for (method, operation) in operations {
+            let Some(operation) = operation else { continue };
+            let permission = lookup(ROUTE_PERMISSIONS, &method, path)
+                .unwrap_or_else(|| panic!("{method} {path} missing from ROUTE_PERMISSIONS"));
+
+            // Routes documented as taking the gateway identity must require a role.
+            let takes_identity = operation.security.iter().flatten().any(|s| {
+                serde_json::to_value(s)
+                    .unwrap()
+                    .get("gatewayIdentity")
+                    .is_some()
+            });
+            assert_eq!(
+                takes_identity,
+                permission.access != Access::Public,
+                "{method} {path}: matrix and documented security disagree"
+            );
+        }
+    }
+}
+
+#[test]
+fn should_limit_bodies_of_served_routes_only() {
+    // A limit on a path that is not served is never applied.
+    for limit in BODY_LIMITS {
+        assert!(
+            lookup(ROUTE_PERMISSIONS, &limit.method, limit.path).is_some(),
+            "{} {} in BODY_LIMITS is not a route",
+            limit.method,
+            limit.path
+        );
+    }
+}
+
+/// Every route of the matrix over a stub handler, so only `require_role`
+/// decides what is served.
+fn matrix_router() -> Router {
+    let mut router = Router::new();
+    for p in ROUTE_PERMISSIONS {
+        let filter = MethodFilter::try_from(p.method.clone()).unwrap();
+        router = router.route(p.path, on(filter, || async {}));
+    }
+    router.route_layer(middleware::from_fn_with_state(
+        ROUTE_PERMISSIONS,
+        require_role,
+    ))
+}
+
+async fn status_as_staff(method: Method, uri: &str, scopes: Option<&str>) -> StatusCode {
+    let mut req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("x-madome-user-role", "2");
+    if let Some(scopes) = scopes {
+        req = req.header(TOKEN_SCOPES_HEADER, scopes);
+    }
+    matrix_router()
+        .oneshot(req.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+async fn should_refuse_api_tokens_on_staff_session_and_passkey_routes() {
+    let id = "0190a3f6-7c2b-7d4e-9f10-2a3b4c5d6e7f";
+    let routes = [
+        (Method::POST, format!("/auth/impersonate/{id}")),
+        (Method::POST, "/auth/machine-tokens".to_owned()),
+        (Method::GET, "/auth/admin/outbox".to_owned()),
+        (Method::POST, format!("/auth/admin/outbox/{id}/retry")),
+        (Method::DELETE, format!("/auth/admin/outbox/{id}")),
+        (Method::GET, "/auth/admin/stats".to_owned()),
+        (Method::DELETE, format!("/auth/sessions/{id}")),
+        (Method::POST, "/auth/api-tokens".to_owned()),
+        (Method::DELETE, "/auth/passkeys/credential".to_owned()),
+        (Method::POST, "/auth/passkey/registration".to_owned()),
+        (Method::PATCH, "/auth/passkey/registration".to_owned()),
+    ];
+
+    for (method, uri) in routes {
+        assert_eq!(
+            status_as_staff(method.clone(), &uri, Some("library.read")).await,
+            StatusCode::FORBIDDEN,
+            "{method} {uri} served to an API token"
+        );
+        assert_eq!(
+            status_as_staff(method.clone(), &uri, None).await,
+            StatusCode::OK,
+            "{method} {uri} refused to a browser session"
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use chrono::Duration;
+use uuid::Uuid;
+
+use madome_auth::domain::types::LOGIN_NEW_DEVICE_EVENT;
+use madome_auth::error::AuthServiceError;
+use madome_auth::usecase::session::{ClientInfo, DeleteSessionUseCase, ListSessionsUseCase};
+use madome_auth::usecase::token::{CreateTokenInput, CreateTokenUseCase};
+use madome_auth_types::cookie::TokenLifetimes;
+use madome_domain::id::UserId;
+
+use crate::helpers::{
+    MockAuthCodeAttemptStore, MockAuthCodeRepo, MockSessionRepo, MockUserRepo, TEST_AUTH_CODE,
+    TEST_JWT_SECRET, test_auth_code, test_session, test_user,
+};
+
+/// Sign `test_user()` in with a fresh auth code from `client`.
+async fn sign_in(sessions: &MockSessionRepo, client: ClientInfo) {
+    let user = test_user();
+    let code = test_auth_code(user.id);
+    let code_str = TEST_AUTH_CODE.to_owned();
+    let uc = CreateTokenUseCase {
+        users: MockUserRepo::new(vec![user.clone()]),
+        auth_codes: MockAuthCodeRepo::new(vec![code], 1),
+        attempts: MockAuthCodeAttemptStore::empty(),
+        sessions: MockSessionRepo {
+            sessions: sessions.sessions_handle(),
+            events: sessions.events_handle(),
+        },
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+    };
+    uc.execute(CreateTokenInput {
+        email: user.email,
+        code: code_str,
+        client,
+        remember: true,
+    })
+    .await
+    .unwrap();
+}
+
+fn client(user_agent: &str, ip: &str) -> ClientInfo {
+    ClientInfo {
+        user_agent: Some(user_agent.to_owned()),
+        ip: Some(ip.to_owned()),
+    }
+}
+
+// ── ListSessionsUseCase ──────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn should_list_only_own_sessions_most_recent_first() {
+    let user_id = UserId(Uuid::new_v4());
+    let older = test_session(user_id);
+    let mut newer = test_session(user_id);
+    newer.last_used_at = older.last_used_at + Duration::minutes(30);
+    let other = test_session(UserId(Uuid::new_v4()));
+
+    let uc = ListSessionsUseCase {
+        sessions: MockSessionRepo::new(vec![older.clone(), other, newer.clone()]),
+    };
+
+    let sessions = uc.execute(user_id).await.unwrap();
+
+    let ids: Vec<Uuid> = sessions.iter().map(|s| s.id).collect();
+    assert_eq!(ids, vec![newer.id, older.id]);
+}
+
+#[tokio::test]
+async fn should_return_empty_list_when_user_has_no_sessions() {
+    let uc = ListSessionsUseCase {
+        sessions: MockSessionRepo::new(vec![test_session(UserId(Uuid::new_v4()))]),
+    };
+
+    let sessions = uc.execute(UserId(Uuid::new_v4())).await.unwrap();
+
+    assert!(sessions.is_empty());
+}
+
+// ── DeleteSessionUseCase ─────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn should_delete_own_session() {
+    let user_id = UserId(Uuid::new_v4());
+    let session = test_session(user_id);
+    let repo = MockSessionRepo::new(vec![session.clone()]);
+    let handle = repo.sessions_handle();
+    let uc = DeleteSessionUseCase { sessions: repo };
+
+    uc.execute(session.id, user_id).await.unwrap();
+
+    assert!(handle.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn should_return_not_found_when_deleting_missing_session() {
+    let uc = DeleteSessionUseCase {
+        sessions: MockSessionRepo::empty(),
+    };
+
+    let result = uc.execute(Uuid::new_v4(), UserId(Uuid::new_v4())).await;
+
+    assert!(
+        matches!(result, Err(AuthServiceError::NotFound)),
+        "expected NotFound, got {result:?}"
+    );
+}
+
+#[tokio::test]
+async fn should_return_not_found_when_deleting_other_users_session() {
+    let session = test_session(UserId(Uuid::new_v4()));
+    let repo = MockSessionRepo::new(vec![session.clone()]);
+    let handle = repo.sessions_handle();
+    let uc = DeleteSessionUseCase { sessions: repo };
+
+    let result = uc.execute(session.id, UserId(Uuid::new_v4())).await;
+
+    assert!(
+        matches!(result, Err(AuthServiceError::NotFound)),
+        "expected NotFound, got {result:?}"
+    );
+    assert_eq!(handle.lock().unwrap().len(), 1);
+}
+
+// ── New-device notification ─────────────────────────────────────────────────
+
+#[tokio::test]
+async fn should_emit_new_device_event_when_signing_in_from_unseen_device() {
+    let user = test_user();
+    let sessions = MockSessionRepo::new(vec![test_session(user.id)]);
+
+    sign_in(&sessions, client("curl/8.0", "198.51.100.1")).await;
+
+    let events = sessions.events_handle();
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, LOGIN_NEW_DEVICE_EVENT);
+    assert_eq!(events[0].payload["user_id"], user.id.to_string());
+    assert_eq!(events[0].payload["user_agent"], "curl/8.0");
+    assert_eq!(events[0].payload["ip"], "198.51.100.1");
+}
+
+#[tokio::test]
+async fn should_not_emit_new_device_event_for_known_device() {
+    let user = test_user();
+    let sessions = MockSessionRepo::new(vec![test_session(user.id)]);
+
+    // Same user agent and IP as `test_session`.
+    sign_in(&sessions, client("Mozilla/5.0", "203.0.113.7")).await;
+
+    assert!(sessions.events_handle().lock().unwrap().is_empty());
+    assert_eq!(sessions.sessions_handle().lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn should_not_emit_new_device_event_on_first_sign_in() {
+    let sessions = MockSessionRepo::empty();
+
+    sign_in(&sessions, client("curl/8.0", "198.51.100.1")).await;
+
+    assert!(sessions.events_handle().lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn should_emit_one_new_device_event_per_window() {
+    let user = test_user();
+    let sessions = MockSessionRepo::new(vec![test_session(user.id)]);
+
+    sign_in(&sessions, client("curl/8.0", "198.51.100.1")).await;
+    sign_in(&sessions, client("Wget/1.21", "198.51.100.2")).await;
+
+    assert_eq!(sessions.events_handle().lock().unwrap().len(), 1);
+    assert_eq!(sessions.sessions_handle().lock().unwrap().len(), 3);
+}
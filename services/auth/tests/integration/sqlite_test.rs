@@ -0,0 +1,242 @@
+//! Smoke tests of the Postgres repositories against `sqlite://` databases.
+
+use std::path::PathBuf;
+
+use chrono::{Duration, Utc};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, Database, DatabaseConnection};
+use uuid::Uuid;
+
+use madome_auth::domain::repository::{
+    IdAliasRepository, LoginLinkRepository, OutboxRepository, PasskeyRepository, SessionRepository,
+    StatsRepository,
+};
+use madome_auth::domain::types::{AuthUser, LoginLink, OutboxEvent, OutboxStatus, PasskeyRecord};
+use madome_auth::error::AuthServiceError;
+use madome_auth::infra::db::{
+    DbAuthCodeRepository, DbIdAliasRepository, DbLoginLinkRepository, DbOutboxRepository,
+    DbPasskeyRepository, DbSessionRepository, DbStatsRepository, DbUserRepository,
+};
+use madome_auth::infra::memory::InMemoryStore;
+use madome_auth::infra::migrate::run_migrations;
+use madome_auth::usecase::authcode::{CreateAuthcodeInput, CreateAuthcodeUseCase};
+use madome_auth::usecase::session::ClientInfo;
+use madome_auth::usecase::token::{CreateTokenInput, CreateTokenUseCase};
+use madome_auth_schema::{id_aliases, users};
+use madome_auth_types::cookie::TokenLifetimes;
+use madome_domain::pagination::PageRequest;
+
+use crate::helpers::{TEST_JWT_SECRET, test_session, test_user};
+
+/// A migrated database file, removed on drop.
+struct SqliteDb {
+    path: PathBuf,
+    conn: DatabaseConnection,
+}
+
+impl SqliteDb {
+    async fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("madome-auth-{}.db", Uuid::new_v4()));
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+        run_migrations(&url).await.unwrap();
+        // Applying them again finds nothing to do.
+        run_migrations(&url).await.unwrap();
+        let conn = Database::connect(&url).await.unwrap();
+        Self { path, conn }
+    }
+
+    async fn add_user(&self, user: &AuthUser) {
+        users::ActiveModel {
+            id: Set(user.id.0),
+            email: Set(user.email.clone()),
+            role: Set(i16::from(user.role)),
+            deleted_at: Set(None),
+        }
+        .insert(&self.conn)
+        .await
+        .unwrap();
+    }
+}
+
+impl Drop for SqliteDb {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn outbox_event(kind: &str) -> OutboxEvent {
+    OutboxEvent {
+        id: Uuid::new_v4(),
+        kind: kind.to_owned(),
+        payload: serde_json::json!({}),
+        idempotency_key: Uuid::new_v4().to_string(),
+    }
+}
+
+#[tokio::test]
+async fn should_sign_in_with_the_mailed_code_against_sqlite() {
+    let db = SqliteDb::new().await;
+    let user = test_user();
+    db.add_user(&user).await;
+
+    CreateAuthcodeUseCase {
+        users: DbUserRepository {
+            db: db.conn.clone(),
+        },
+        auth_codes: DbAuthCodeRepository {
+            db: db.conn.clone(),
+        },
+        code_secret: TEST_JWT_SECRET.to_owned(),
+    }
+    .execute(CreateAuthcodeInput {
+        email: user.email.clone(),
+    })
+    .await
+    .unwrap();
+    let outbox = DbOutboxRepository {
+        db: db.conn.clone(),
+        read: db.conn.clone(),
+    };
+    let pending = outbox
+        .list(OutboxStatus::Pending, PageRequest::default())
+        .await
+        .unwrap();
+    assert_eq!(pending.total, 1);
+    let code = pending.items[0].event.payload["code"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    let sessions = DbSessionRepository {
+        db: db.conn.clone(),
+        read: db.conn.clone(),
+    };
+    let sign_in = CreateTokenUseCase {
+        users: DbUserRepository {
+            db: db.conn.clone(),
+        },
+        auth_codes: DbAuthCodeRepository {
+            db: db.conn.clone(),
+        },
+        attempts: InMemoryStore::new().auth_code_attempts(),
+        sessions: sessions.clone(),
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+    };
+    let input = || CreateTokenInput {
+        email: user.email.clone(),
+        code: code.clone(),
+        client: ClientInfo::default(),
+        remember: false,
+    };
+    sign_in.execute(input()).await.unwrap();
+    assert_eq!(sessions.list_by_user(user.id).await.unwrap().len(), 1);
+    assert!(matches!(
+        sign_in.execute(input()).await,
+        Err(AuthServiceError::NotFound)
+    ));
+}
+
+#[tokio::test]
+async fn should_consume_login_links_once_and_round_trip_passkeys_on_sqlite() {
+    let db = SqliteDb::new().await;
+    let user = test_user();
+    db.add_user(&user).await;
+
+    let links = DbLoginLinkRepository {
+        db: db.conn.clone(),
+    };
+    let now = Utc::now();
+    let link = LoginLink {
+        id: Uuid::new_v4(),
+        user_id: user.id,
+        expires_at: now + Duration::minutes(10),
+        used_at: None,
+        created_at: now,
+    };
+    links
+        .create_with_outbox(&link, &outbox_event("login_link"))
+        .await
+        .unwrap();
+    assert_eq!(links.count_active(user.id).await.unwrap(), 1);
+    let consumed = links.consume(link.id).await.unwrap().unwrap();
+    assert!(consumed.used_at.is_some());
+    assert!(links.consume(link.id).await.unwrap().is_none());
+
+    let passkeys = DbPasskeyRepository {
+        db: db.conn.clone(),
+        read: db.conn.clone(),
+    };
+    let record = PasskeyRecord {
+        credential_id: vec![0, 255, 16],
+        user_id: user.id,
+        aaguid: Uuid::nil(),
+        credential: br#"{"cred":{}}"#.to_vec(),
+        backup_eligible: true,
+        backup_state: false,
+        transports: vec!["usb".to_owned(), "internal".to_owned()],
+        created_at: now,
+    };
+    passkeys.create(&record).await.unwrap();
+    let listed = passkeys.list_by_user(user.id).await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].transports, record.transports);
+    assert!(listed[0].backup_eligible && !listed[0].backup_state);
+}
+
+#[tokio::test]
+async fn should_resolve_legacy_ids_of_live_users_only_on_sqlite() {
+    let db = SqliteDb::new().await;
+    let user = test_user();
+    db.add_user(&user).await;
+    id_aliases::ActiveModel {
+        legacy_id: Set(42),
+        user_id: Set(user.id),
+        created_at: Set(Utc::now()),
+    }
+    .insert(&db.conn)
+    .await
+    .unwrap();
+
+    let aliases = DbIdAliasRepository {
+        read: db.conn.clone(),
+    };
+    assert_eq!(aliases.resolve(42).await.unwrap(), Some(user.id));
+
+    users::ActiveModel {
+        id: Set(user.id.0),
+        deleted_at: Set(Some(Utc::now().fixed_offset())),
+        ..Default::default()
+    }
+    .update(&db.conn)
+    .await
+    .unwrap();
+    assert_eq!(aliases.resolve(42).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn should_roll_up_daily_stats_without_lowering_them_on_sqlite() {
+    let db = SqliteDb::new().await;
+    let user = test_user();
+    db.add_user(&user).await;
+    let sessions = DbSessionRepository {
+        db: db.conn.clone(),
+        read: db.conn.clone(),
+    };
+    let session = test_session(user.id);
+    sessions.create(&session).await.unwrap();
+    sessions.create(&test_session(user.id)).await.unwrap();
+
+    let stats = DbStatsRepository {
+        db: db.conn.clone(),
+        read: db.conn.clone(),
+    };
+    let day = session.last_used_at.date_naive();
+    let rolled = stats.rollup(day).await.unwrap();
+    assert_eq!((rolled.active_users, rolled.sign_ins), (1, 2));
+
+    sessions.delete(session.id, user.id).await.unwrap();
+    let rolled = stats.rollup(day).await.unwrap();
+    assert_eq!((rolled.active_users, rolled.sign_ins), (1, 2));
+    assert_eq!(stats.list(day, day).await.unwrap(), [rolled]);
+}
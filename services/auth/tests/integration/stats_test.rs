@@ -0,0 +1,86 @@
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use uuid::Uuid;
+
+use madome_auth::domain::repository::{SessionRepository, StatsRepository};
+use madome_auth::domain::types::DailyStats;
+use madome_auth::error::AuthServiceError;
+use madome_auth::infra::memory::InMemoryStore;
+use madome_auth::usecase::stats::{ListDailyStatsUseCase, RollupDailyStatsUseCase};
+use madome_domain::id::UserId;
+
+use crate::helpers::test_session;
+
+fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, day, hour, minute, 0)
+        .unwrap()
+}
+
+fn day(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+}
+
+async fn add_session(store: &InMemoryStore, user_id: UserId, created_at: DateTime<Utc>) -> Uuid {
+    let mut session = test_session(user_id);
+    session.created_at = created_at;
+    session.last_used_at = created_at;
+    store.session_repo().create(&session).await.unwrap();
+    session.id
+}
+
+#[tokio::test]
+async fn should_count_distinct_active_users_and_sign_ins_per_day() {
+    let store = InMemoryStore::new();
+    let (a, b) = (UserId(Uuid::new_v4()), UserId(Uuid::new_v4()));
+    add_session(&store, a, at(15, 9, 0)).await;
+    add_session(&store, a, at(15, 20, 0)).await;
+    add_session(&store, b, at(14, 23, 0)).await;
+
+    let stats = store.stats_repo().rollup(day(15)).await.unwrap();
+    assert_eq!(
+        stats,
+        DailyStats {
+            day: day(15),
+            active_users: 1,
+            sign_ins: 2,
+        }
+    );
+}
+
+#[tokio::test]
+async fn should_keep_the_highest_count_once_users_move_on_to_the_next_day() {
+    let store = InMemoryStore::new();
+    let user = UserId(Uuid::new_v4());
+    let first = add_session(&store, user, at(15, 23, 50)).await;
+    let uc = RollupDailyStatsUseCase {
+        stats: store.stats_repo(),
+    };
+    uc.execute(at(15, 23, 55)).await.unwrap();
+
+    // Signed out and back in after midnight: no session shows the 15th now.
+    store.session_repo().delete(first, user).await.unwrap();
+    add_session(&store, user, at(16, 0, 5)).await;
+    uc.execute(at(16, 0, 10)).await.unwrap();
+
+    let list = ListDailyStatsUseCase {
+        stats: store.stats_repo(),
+    }
+    .execute(day(1), day(31))
+    .await
+    .unwrap();
+    let active: Vec<_> = list.iter().map(|s| (s.day, s.active_users)).collect();
+    assert_eq!(active, [(day(15), 1), (day(16), 1)]);
+}
+
+#[tokio::test]
+async fn should_reject_reversed_or_overlong_ranges() {
+    let uc = ListDailyStatsUseCase {
+        stats: InMemoryStore::new().stats_repo(),
+    };
+    for (from, to) in [(day(2), day(1)), (day(1), day(1) + Duration::days(366))] {
+        assert!(matches!(
+            uc.execute(from, to).await,
+            Err(AuthServiceError::BadRequest(_))
+        ));
+    }
+    assert!(uc.execute(day(1), day(1)).await.unwrap().is_empty());
+}
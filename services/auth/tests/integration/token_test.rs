@@ -1,17 +1,52 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::response::IntoResponse;
+use jsonwebtoken::{EncodingKey, Header, encode};
+use uuid::Uuid;
+
+use madome_auth::domain::types::MAX_AUTHCODE_ATTEMPTS;
 use madome_auth::error::AuthServiceError;
+use madome_auth::usecase::session::ClientInfo;
 use madome_auth::usecase::token::{
-    CreateTokenInput, CreateTokenUseCase, RefreshTokenUseCase, issue_access_token,
-    issue_refresh_token, validate_token,
+    CreateTokenInput, CreateTokenUseCase, IMPERSONATION_TOKEN_EXP, ImpersonateUseCase,
+    IntrospectTokenUseCase, RefreshTokenUseCase, RevokeTokenUseCase, TokenClaims,
+    issue_access_token, issue_refresh_token, validate_token,
+};
+use madome_auth_types::cookie::{
+    ACCESS_TOKEN_EXP, REFRESH_TOKEN_EXP, SESSION_REFRESH_TOKEN_EXP, TokenLifetimes,
+};
+use madome_auth_types::token::{AuthError, validate_access_token};
+use madome_core::config::Secret;
+use madome_core::error_catalog::ErrorKind;
+use madome_core::rate_limit::RateLimit;
+use madome_core::secrets::RefreshingSecret;
+use madome_domain::id::UserId;
+
+use crate::helpers::{
+    MockAuthCodeAttemptStore, MockAuthCodeRepo, MockSessionRepo, MockUserRepo, TEST_AUTH_CODE,
+    TEST_JWT_SECRET, test_auth_code, test_session, test_user,
 };
 
-use crate::helpers::{MockAuthCodeRepo, MockUserRepo, TEST_JWT_SECRET, test_auth_code, test_user};
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn test_client() -> ClientInfo {
+    ClientInfo {
+        user_agent: Some("Mozilla/5.0".to_owned()),
+        ip: Some("203.0.113.7".to_owned()),
+    }
+}
 
 // ── issue_access_token / validate_token ──────────────────────────────────────
 
 #[tokio::test]
 async fn should_issue_access_token_that_validates_successfully() {
     let user = test_user();
-    let (token, exp) = issue_access_token(&user, TEST_JWT_SECRET).unwrap();
+    let (token, exp) = issue_access_token(&user, TEST_JWT_SECRET, ACCESS_TOKEN_EXP).unwrap();
 
     assert!(!token.is_empty());
     assert!(exp > 0);
@@ -20,12 +55,27 @@ async fn should_issue_access_token_that_validates_successfully() {
     assert_eq!(claims.sub, user.id.to_string());
     assert_eq!(claims.role, user.role);
     assert_eq!(claims.exp, exp);
+    assert!(
+        claims.sid.is_none(),
+        "access token must not carry a session id"
+    );
+}
+
+#[tokio::test]
+async fn should_issue_tokens_with_given_lifetimes() {
+    let user = test_user();
+    let (token, exp) = issue_access_token(&user, TEST_JWT_SECRET, 60).unwrap();
+    let refresh = issue_refresh_token(&user, Uuid::new_v4(), TEST_JWT_SECRET, 120, true).unwrap();
+
+    let refresh_exp = validate_token(&refresh, TEST_JWT_SECRET).unwrap().exp;
+    assert_eq!(validate_token(&token, TEST_JWT_SECRET).unwrap().exp, exp);
+    assert_eq!(refresh_exp, exp + 60);
 }
 
 #[tokio::test]
 async fn should_reject_token_signed_with_wrong_secret() {
     let user = test_user();
-    let (token, _) = issue_access_token(&user, TEST_JWT_SECRET).unwrap();
+    let (token, _) = issue_access_token(&user, TEST_JWT_SECRET, ACCESS_TOKEN_EXP).unwrap();
 
     let result = validate_token(&token, "wrong-secret");
     assert!(
@@ -46,13 +96,16 @@ async fn should_reject_invalid_token_string() {
 #[tokio::test]
 async fn should_issue_refresh_token_that_validates_successfully() {
     let user = test_user();
-    let token = issue_refresh_token(&user, TEST_JWT_SECRET).unwrap();
+    let session_id = Uuid::new_v4();
+    let token =
+        issue_refresh_token(&user, session_id, TEST_JWT_SECRET, REFRESH_TOKEN_EXP, true).unwrap();
 
     assert!(!token.is_empty());
 
     let claims = validate_token(&token, TEST_JWT_SECRET).unwrap();
     assert_eq!(claims.sub, user.id.to_string());
     assert_eq!(claims.role, user.role);
+    assert_eq!(claims.sid, Some(session_id));
 }
 
 // ── CreateTokenUseCase ───────────────────────────────────────────────────────
@@ -61,18 +114,24 @@ async fn should_issue_refresh_token_that_validates_successfully() {
 async fn should_create_token_pair_with_valid_auth_code() {
     let user = test_user();
     let code = test_auth_code(user.id);
-    let code_str = code.code.clone();
+    let code_str = TEST_AUTH_CODE.to_owned();
 
     let uc = CreateTokenUseCase {
         users: MockUserRepo::new(vec![user.clone()]),
         auth_codes: MockAuthCodeRepo::new(vec![code], 1),
+        attempts: MockAuthCodeAttemptStore::empty(),
+        sessions: MockSessionRepo::empty(),
         jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
     };
 
     let output = uc
         .execute(CreateTokenInput {
             email: user.email.clone(),
             code: code_str,
+            client: test_client(),
+            remember: true,
         })
         .await
         .unwrap();
@@ -90,11 +149,78 @@ async fn should_create_token_pair_with_valid_auth_code() {
     assert_eq!(refresh_claims.sub, user.id.to_string());
 }
 
+#[tokio::test]
+async fn should_issue_short_session_token_without_remember() {
+    let user = test_user();
+    let uc = CreateTokenUseCase {
+        users: MockUserRepo::new(vec![user.clone()]),
+        auth_codes: MockAuthCodeRepo::new(vec![test_auth_code(user.id)], 1),
+        attempts: MockAuthCodeAttemptStore::empty(),
+        sessions: MockSessionRepo::empty(),
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+    };
+
+    let output = uc
+        .execute(CreateTokenInput {
+            email: user.email.clone(),
+            code: TEST_AUTH_CODE.to_owned(),
+            client: test_client(),
+            remember: false,
+        })
+        .await
+        .unwrap();
+
+    let claims = validate_token(&output.refresh_token, TEST_JWT_SECRET).unwrap();
+    assert!(claims.session_only);
+    let ttl = claims.exp - (output.access_token_exp - ACCESS_TOKEN_EXP);
+    assert!(ttl <= SESSION_REFRESH_TOKEN_EXP + 1, "refresh ttl {ttl}");
+}
+
+#[tokio::test]
+async fn should_start_session_with_client_info_on_create_token() {
+    let user = test_user();
+    let code = test_auth_code(user.id);
+    let code_str = TEST_AUTH_CODE.to_owned();
+
+    let sessions = MockSessionRepo::empty();
+    let handle = sessions.sessions_handle();
+    let uc = CreateTokenUseCase {
+        users: MockUserRepo::new(vec![user.clone()]),
+        auth_codes: MockAuthCodeRepo::new(vec![code], 1),
+        attempts: MockAuthCodeAttemptStore::empty(),
+        sessions,
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+    };
+
+    let output = uc
+        .execute(CreateTokenInput {
+            email: user.email.clone(),
+            code: code_str,
+            client: test_client(),
+            remember: true,
+        })
+        .await
+        .unwrap();
+
+    let stored = handle.lock().unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].user_id, user.id);
+    assert_eq!(stored[0].user_agent.as_deref(), Some("Mozilla/5.0"));
+    assert_eq!(stored[0].ip.as_deref(), Some("203.0.113.7"));
+
+    let refresh_claims = validate_token(&output.refresh_token, TEST_JWT_SECRET).unwrap();
+    assert_eq!(refresh_claims.sid, Some(stored[0].id));
+}
+
 #[tokio::test]
 async fn should_mark_auth_code_as_used_after_create_token() {
     let user = test_user();
     let code = test_auth_code(user.id);
-    let code_str = code.code.clone();
+    let code_str = TEST_AUTH_CODE.to_owned();
     let code_id = code.id;
 
     let mock_repo = MockAuthCodeRepo::new(vec![code], 1);
@@ -103,12 +229,18 @@ async fn should_mark_auth_code_as_used_after_create_token() {
     let uc = CreateTokenUseCase {
         users: MockUserRepo::new(vec![user.clone()]),
         auth_codes: mock_repo,
+        attempts: MockAuthCodeAttemptStore::empty(),
+        sessions: MockSessionRepo::empty(),
         jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
     };
 
     uc.execute(CreateTokenInput {
         email: user.email.clone(),
         code: code_str,
+        client: test_client(),
+        remember: true,
     })
     .await
     .unwrap();
@@ -127,13 +259,19 @@ async fn should_return_not_found_when_user_unknown_for_create_token() {
     let uc = CreateTokenUseCase {
         users: MockUserRepo::empty(),
         auth_codes: MockAuthCodeRepo::empty(),
+        attempts: MockAuthCodeAttemptStore::empty(),
+        sessions: MockSessionRepo::empty(),
         jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
     };
 
     let result = uc
         .execute(CreateTokenInput {
             email: "nobody@example.com".to_owned(),
             code: "ABCDEF123456".to_owned(),
+            client: test_client(),
+            remember: true,
         })
         .await;
 
@@ -150,13 +288,19 @@ async fn should_return_not_found_when_auth_code_invalid_for_create_token() {
     let uc = CreateTokenUseCase {
         users: MockUserRepo::new(vec![user.clone()]),
         auth_codes: MockAuthCodeRepo::empty(), // no codes at all
+        attempts: MockAuthCodeAttemptStore::empty(),
+        sessions: MockSessionRepo::empty(),
         jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
     };
 
     let result = uc
         .execute(CreateTokenInput {
             email: user.email.clone(),
             code: "WRONGCODE123".to_owned(),
+            client: test_client(),
+            remember: true,
         })
         .await;
 
@@ -166,19 +310,134 @@ async fn should_return_not_found_when_auth_code_invalid_for_create_token() {
     );
 }
 
+#[tokio::test]
+async fn should_count_failed_attempt_when_auth_code_invalid() {
+    let user = test_user();
+    let attempts = MockAuthCodeAttemptStore::empty();
+    let failures = attempts.failures_handle();
+
+    let uc = CreateTokenUseCase {
+        users: MockUserRepo::new(vec![user.clone()]),
+        auth_codes: MockAuthCodeRepo::new(vec![test_auth_code(user.id)], 1),
+        attempts,
+        sessions: MockSessionRepo::empty(),
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+    };
+
+    for _ in 0..2 {
+        let _ = uc
+            .execute(CreateTokenInput {
+                email: user.email.clone(),
+                code: "WRONGCODE123".to_owned(),
+                client: test_client(),
+                remember: true,
+            })
+            .await;
+    }
+
+    assert_eq!(failures.lock().unwrap().get(&user.id), Some(&2));
+}
+
+#[tokio::test]
+async fn should_reject_valid_auth_code_when_attempts_exhausted() {
+    let user = test_user();
+    let code = test_auth_code(user.id);
+    let code_str = TEST_AUTH_CODE.to_owned();
+
+    let mock_repo = MockAuthCodeRepo::new(vec![code], 1);
+    let codes_handle = mock_repo.codes_handle();
+    let uc = CreateTokenUseCase {
+        users: MockUserRepo::new(vec![user.clone()]),
+        auth_codes: mock_repo,
+        attempts: MockAuthCodeAttemptStore::with_failures(user.id, MAX_AUTHCODE_ATTEMPTS),
+        sessions: MockSessionRepo::empty(),
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+    };
+
+    let result = uc
+        .execute(CreateTokenInput {
+            email: user.email.clone(),
+            code: code_str,
+            client: test_client(),
+            remember: true,
+        })
+        .await;
+
+    assert!(
+        matches!(result, Err(AuthServiceError::TooManyAttempts(_))),
+        "expected TooManyAttempts, got {result:?}"
+    );
+    assert!(
+        codes_handle.lock().unwrap()[0].used_at.is_none(),
+        "a locked attempt must not consume the code"
+    );
+}
+
+#[tokio::test]
+async fn should_clear_failed_attempts_after_create_token() {
+    let user = test_user();
+    let code = test_auth_code(user.id);
+    let code_str = TEST_AUTH_CODE.to_owned();
+
+    let attempts = MockAuthCodeAttemptStore::with_failures(user.id, MAX_AUTHCODE_ATTEMPTS - 1);
+    let failures = attempts.failures_handle();
+    let uc = CreateTokenUseCase {
+        users: MockUserRepo::new(vec![user.clone()]),
+        auth_codes: MockAuthCodeRepo::new(vec![code], 1),
+        attempts,
+        sessions: MockSessionRepo::empty(),
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+    };
+
+    uc.execute(CreateTokenInput {
+        email: user.email.clone(),
+        code: code_str,
+        client: test_client(),
+        remember: true,
+    })
+    .await
+    .unwrap();
+
+    assert!(failures.lock().unwrap().get(&user.id).is_none());
+}
+
+#[test]
+fn should_answer_too_many_attempts_with_429() {
+    let err = AuthServiceError::TooManyAttempts(RateLimit::exhausted(5, 900));
+    assert_eq!(err.kind(), ErrorKind::TooManyRequests);
+
+    let response = err.into_response();
+    assert_eq!(response.status().as_u16(), 429);
+    assert_eq!(response.headers()["ratelimit-limit"], "5");
+    assert_eq!(response.headers()["ratelimit-remaining"], "0");
+    assert_eq!(response.headers()["retry-after"], "900");
+}
+
 // ── RefreshTokenUseCase ──────────────────────────────────────────────────────
 
 #[tokio::test]
 async fn should_refresh_token_pair_with_valid_refresh_jwt() {
     let user = test_user();
-    let refresh = issue_refresh_token(&user, TEST_JWT_SECRET).unwrap();
+    let session = test_session(user.id);
+    let refresh =
+        issue_refresh_token(&user, session.id, TEST_JWT_SECRET, REFRESH_TOKEN_EXP, true).unwrap();
 
     let uc = RefreshTokenUseCase {
         users: MockUserRepo::new(vec![user.clone()]),
+        sessions: MockSessionRepo::new(vec![session.clone()]),
         jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+        legacy_cutoff: None,
     };
 
-    let output = uc.execute(&refresh).await.unwrap();
+    let output = uc.execute(&refresh, &test_client()).await.unwrap();
 
     assert_eq!(output.user_id, user.id);
     assert_eq!(output.user_role, user.role);
@@ -189,6 +448,10 @@ async fn should_refresh_token_pair_with_valid_refresh_jwt() {
     // New tokens should be valid.
     let claims = validate_token(&output.access_token, TEST_JWT_SECRET).unwrap();
     assert_eq!(claims.sub, user.id.to_string());
+
+    // The rotated refresh token stays bound to the same session.
+    let refresh_claims = validate_token(&output.refresh_token, TEST_JWT_SECRET).unwrap();
+    assert_eq!(refresh_claims.sid, Some(session.id));
 }
 
 #[tokio::test]
@@ -197,10 +460,14 @@ async fn should_return_unauthorized_when_refresh_jwt_invalid() {
 
     let uc = RefreshTokenUseCase {
         users: MockUserRepo::new(vec![user]),
+        sessions: MockSessionRepo::empty(),
         jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+        legacy_cutoff: None,
     };
 
-    let result = uc.execute("not-a-valid-jwt").await;
+    let result = uc.execute("not-a-valid-jwt", &test_client()).await;
 
     assert!(
         matches!(result, Err(AuthServiceError::Unauthorized)),
@@ -211,14 +478,20 @@ async fn should_return_unauthorized_when_refresh_jwt_invalid() {
 #[tokio::test]
 async fn should_return_unauthorized_when_refresh_jwt_signed_with_wrong_secret() {
     let user = test_user();
-    let refresh = issue_refresh_token(&user, "other-secret").unwrap();
+    let session = test_session(user.id);
+    let refresh =
+        issue_refresh_token(&user, session.id, "other-secret", REFRESH_TOKEN_EXP, true).unwrap();
 
     let uc = RefreshTokenUseCase {
         users: MockUserRepo::new(vec![user]),
+        sessions: MockSessionRepo::new(vec![session]),
         jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+        legacy_cutoff: None,
     };
 
-    let result = uc.execute(&refresh).await;
+    let result = uc.execute(&refresh, &test_client()).await;
 
     assert!(
         matches!(result, Err(AuthServiceError::Unauthorized)),
@@ -226,20 +499,420 @@ async fn should_return_unauthorized_when_refresh_jwt_signed_with_wrong_secret()
     );
 }
 
+#[tokio::test]
+async fn should_refresh_token_signed_before_rotation() {
+    let user = test_user();
+    let session = test_session(user.id);
+    let refresh =
+        issue_refresh_token(&user, session.id, "old-secret", REFRESH_TOKEN_EXP, true).unwrap();
+    // Rotated on a restart: the new key is current, the old one previous.
+    let jwt_secret = RefreshingSecret::new(Secret::new(TEST_JWT_SECRET.to_owned()))
+        .keep_previous(Duration::from_secs(REFRESH_TOKEN_EXP))
+        .with_previous(Secret::new("old-secret".to_owned()));
+
+    let uc = RefreshTokenUseCase {
+        users: MockUserRepo::new(vec![user]),
+        sessions: MockSessionRepo::new(vec![session]),
+        jwt_secret: jwt_secret.current().into_inner(),
+        previous_jwt_secret: jwt_secret.previous().map(Secret::into_inner),
+        lifetimes: TokenLifetimes::default(),
+        legacy_cutoff: None,
+    };
+
+    let output = uc.execute(&refresh, &test_client()).await.unwrap();
+
+    // The new pair is signed with the current key only.
+    assert!(validate_token(&output.refresh_token, TEST_JWT_SECRET).is_ok());
+    assert!(validate_token(&output.refresh_token, "old-secret").is_err());
+}
+
 #[tokio::test]
 async fn should_return_unauthorized_when_user_deleted_during_refresh() {
     let user = test_user();
-    let refresh = issue_refresh_token(&user, TEST_JWT_SECRET).unwrap();
+    let session = test_session(user.id);
+    let refresh =
+        issue_refresh_token(&user, session.id, TEST_JWT_SECRET, REFRESH_TOKEN_EXP, true).unwrap();
 
     let uc = RefreshTokenUseCase {
         users: MockUserRepo::empty(), // user no longer exists
+        sessions: MockSessionRepo::new(vec![session]),
         jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+        legacy_cutoff: None,
     };
 
-    let result = uc.execute(&refresh).await;
+    let result = uc.execute(&refresh, &test_client()).await;
 
     assert!(
         matches!(result, Err(AuthServiceError::Unauthorized)),
         "expected Unauthorized, got {result:?}"
     );
 }
+
+#[tokio::test]
+async fn should_return_unauthorized_when_refresh_session_deleted() {
+    let user = test_user();
+    let refresh = issue_refresh_token(
+        &user,
+        Uuid::new_v4(),
+        TEST_JWT_SECRET,
+        REFRESH_TOKEN_EXP,
+        true,
+    )
+    .unwrap();
+
+    let uc = RefreshTokenUseCase {
+        users: MockUserRepo::new(vec![user]),
+        sessions: MockSessionRepo::empty(), // session revoked from another device
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+        legacy_cutoff: None,
+    };
+
+    let result = uc.execute(&refresh, &test_client()).await;
+
+    assert!(
+        matches!(result, Err(AuthServiceError::Unauthorized)),
+        "expected Unauthorized, got {result:?}"
+    );
+}
+
+#[tokio::test]
+async fn should_touch_session_on_refresh() {
+    let user = test_user();
+    let session = test_session(user.id);
+    let refresh =
+        issue_refresh_token(&user, session.id, TEST_JWT_SECRET, REFRESH_TOKEN_EXP, true).unwrap();
+
+    let sessions = MockSessionRepo::new(vec![session.clone()]);
+    let handle = sessions.sessions_handle();
+    let uc = RefreshTokenUseCase {
+        users: MockUserRepo::new(vec![user]),
+        sessions,
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+        legacy_cutoff: None,
+    };
+
+    uc.execute(&refresh, &test_client()).await.unwrap();
+
+    let stored = handle.lock().unwrap();
+    assert_eq!(stored.len(), 1);
+    assert!(stored[0].last_used_at > session.last_used_at);
+}
+
+#[tokio::test]
+async fn should_adopt_legacy_refresh_token_without_session_id() {
+    let user = test_user();
+    let cutoff = now() - 60;
+    let legacy_claims = TokenClaims {
+        sub: user.id.to_string(),
+        role: user.role,
+        exp: cutoff + REFRESH_TOKEN_EXP / 2,
+        typ: None,
+        sid: None,
+        impersonator: None,
+        session_only: false,
+    };
+    let refresh = encode(
+        &Header::default(),
+        &legacy_claims,
+        &EncodingKey::from_secret(TEST_JWT_SECRET.as_bytes()),
+    )
+    .unwrap();
+
+    let sessions = MockSessionRepo::empty();
+    let handle = sessions.sessions_handle();
+    let uc = RefreshTokenUseCase {
+        users: MockUserRepo::new(vec![user.clone()]),
+        sessions,
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+        legacy_cutoff: Some(cutoff),
+    };
+
+    let output = uc.execute(&refresh, &test_client()).await.unwrap();
+
+    let stored = handle.lock().unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].user_id, user.id);
+    let claims = validate_token(&output.refresh_token, TEST_JWT_SECRET).unwrap();
+    assert_eq!(claims.sid, Some(stored[0].id));
+}
+
+#[tokio::test]
+async fn should_refuse_access_tokens_and_unproven_legacy_tokens_on_refresh() {
+    let user = test_user();
+    let cutoff = now() - 60;
+    let (access, _) = issue_access_token(&user, TEST_JWT_SECRET, ACCESS_TOKEN_EXP).unwrap();
+    let untyped = |exp| {
+        let claims = TokenClaims {
+            sub: user.id.to_string(),
+            role: user.role,
+            exp,
+            typ: None,
+            sid: None,
+            impersonator: None,
+            session_only: false,
+        };
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(TEST_JWT_SECRET.as_bytes()),
+        )
+        .unwrap()
+    };
+    let cases = [
+        (access, Some(cutoff)),
+        // Could be an access token issued just before the cutoff.
+        (untyped(cutoff + ACCESS_TOKEN_EXP), Some(cutoff)),
+        // Issued after the cutoff, so not by the legacy service.
+        (untyped(cutoff + REFRESH_TOKEN_EXP + 60), Some(cutoff)),
+        (untyped(cutoff + REFRESH_TOKEN_EXP / 2), None),
+    ];
+
+    for (token, legacy_cutoff) in cases {
+        let sessions = MockSessionRepo::empty();
+        let handle = sessions.sessions_handle();
+        let uc = RefreshTokenUseCase {
+            users: MockUserRepo::new(vec![user.clone()]),
+            sessions,
+            jwt_secret: TEST_JWT_SECRET.to_owned(),
+            previous_jwt_secret: None,
+            lifetimes: TokenLifetimes::default(),
+            legacy_cutoff,
+        };
+
+        let result = uc.execute(&token, &test_client()).await;
+
+        assert!(matches!(result, Err(AuthServiceError::Unauthorized)));
+        assert!(handle.lock().unwrap().is_empty());
+    }
+}
+
+#[tokio::test]
+async fn should_keep_session_only_on_refresh() {
+    let user = test_user();
+    let session = test_session(user.id);
+    let refresh = issue_refresh_token(
+        &user,
+        session.id,
+        TEST_JWT_SECRET,
+        SESSION_REFRESH_TOKEN_EXP,
+        false,
+    )
+    .unwrap();
+
+    let uc = RefreshTokenUseCase {
+        users: MockUserRepo::new(vec![user.clone()]),
+        sessions: MockSessionRepo::new(vec![session]),
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+        legacy_cutoff: None,
+    };
+
+    let output = uc.execute(&refresh, &test_client()).await.unwrap();
+
+    assert!(!output.remember);
+    let claims = validate_token(&output.refresh_token, TEST_JWT_SECRET).unwrap();
+    assert!(claims.session_only);
+}
+
+// ── IntrospectTokenUseCase ───────────────────────────────────────────────────
+
+#[test]
+fn should_introspect_access_token_only() {
+    let user = test_user();
+    let (access, exp) = issue_access_token(&user, TEST_JWT_SECRET, ACCESS_TOKEN_EXP).unwrap();
+    let refresh = issue_refresh_token(
+        &user,
+        Uuid::new_v4(),
+        TEST_JWT_SECRET,
+        REFRESH_TOKEN_EXP,
+        true,
+    )
+    .unwrap();
+    let uc = IntrospectTokenUseCase {
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+    };
+
+    let info = uc.execute(&access).unwrap();
+    assert_eq!(info.user_id, user.id);
+    assert_eq!(info.user_role, user.role);
+    assert_eq!(info.exp, exp);
+
+    assert_eq!(uc.execute(&refresh), None);
+}
+
+#[test]
+fn should_report_invalid_token_as_inactive() {
+    let user = test_user();
+    let (foreign, _) = issue_access_token(&user, "other-secret", ACCESS_TOKEN_EXP).unwrap();
+    let uc = IntrospectTokenUseCase {
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+    };
+
+    assert_eq!(uc.execute("not-a-jwt"), None);
+    assert_eq!(uc.execute(&foreign), None);
+}
+
+#[test]
+fn should_not_check_refresh_token_as_access_token() {
+    // `GET /auth/token` validates the access cookie with `validate_access_token`.
+    let user = test_user();
+    let refresh = issue_refresh_token(
+        &user,
+        Uuid::new_v4(),
+        TEST_JWT_SECRET,
+        REFRESH_TOKEN_EXP,
+        true,
+    )
+    .unwrap();
+
+    let err = validate_access_token(&refresh, TEST_JWT_SECRET).unwrap_err();
+    assert!(matches!(err, AuthError::NotAccessToken));
+}
+
+// ── RevokeTokenUseCase ───────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn should_delete_session_on_revoke() {
+    let user = test_user();
+    let session = test_session(user.id);
+    let refresh =
+        issue_refresh_token(&user, session.id, TEST_JWT_SECRET, REFRESH_TOKEN_EXP, true).unwrap();
+
+    let sessions = MockSessionRepo::new(vec![session]);
+    let handle = sessions.sessions_handle();
+    let uc = RevokeTokenUseCase {
+        sessions,
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+    };
+
+    uc.execute(user.id, Some(&refresh)).await.unwrap();
+
+    assert!(handle.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn should_succeed_revoke_without_refresh_cookie() {
+    let user = test_user();
+    let sessions = MockSessionRepo::new(vec![test_session(user.id)]);
+    let handle = sessions.sessions_handle();
+    let uc = RevokeTokenUseCase {
+        sessions,
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+    };
+
+    uc.execute(user.id, None).await.unwrap();
+    uc.execute(user.id, Some("garbage")).await.unwrap();
+
+    assert_eq!(handle.lock().unwrap().len(), 1);
+}
+
+// ── Impersonate ──────────────────────────────────────────────────────────────
+
+const STAFF_ROLE: u8 = 2;
+
+fn impersonate_uc(users: MockUserRepo) -> ImpersonateUseCase<MockUserRepo> {
+    ImpersonateUseCase {
+        users,
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+    }
+}
+
+#[tokio::test]
+async fn should_issue_short_lived_token_with_impersonator_claim() {
+    let user = test_user();
+    let staff_id = UserId(Uuid::new_v4());
+
+    let out = impersonate_uc(MockUserRepo::new(vec![user.clone()]))
+        .execute(staff_id, STAFF_ROLE, None, user.id)
+        .await
+        .unwrap();
+
+    let info = validate_access_token(&out.access_token, TEST_JWT_SECRET).unwrap();
+    assert_eq!(UserId(info.user_id), user.id);
+    assert_eq!(info.user_role, user.role);
+    assert_eq!(info.impersonator, Some(staff_id.into()));
+
+    let claims = validate_token(&out.access_token, TEST_JWT_SECRET).unwrap();
+    assert!(claims.sid.is_none());
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    assert!(out.access_token_exp <= now + IMPERSONATION_TOKEN_EXP);
+}
+
+#[tokio::test]
+async fn should_forbid_impersonation_below_staff_role() {
+    let user = test_user();
+
+    for role in [0, 1] {
+        let result = impersonate_uc(MockUserRepo::new(vec![user.clone()]))
+            .execute(UserId(Uuid::new_v4()), role, None, user.id)
+            .await;
+        assert!(matches!(result, Err(AuthServiceError::Forbidden)));
+    }
+}
+
+#[tokio::test]
+async fn should_forbid_impersonating_from_an_impersonated_token() {
+    let user = test_user();
+
+    let result = impersonate_uc(MockUserRepo::new(vec![user.clone()]))
+        .execute(
+            UserId(Uuid::new_v4()),
+            STAFF_ROLE,
+            Some(Uuid::new_v4()),
+            user.id,
+        )
+        .await;
+
+    assert!(matches!(result, Err(AuthServiceError::Forbidden)));
+}
+
+#[tokio::test]
+async fn should_return_not_found_when_impersonating_unknown_user() {
+    let result = impersonate_uc(MockUserRepo::empty())
+        .execute(
+            UserId(Uuid::new_v4()),
+            STAFF_ROLE,
+            None,
+            UserId(Uuid::new_v4()),
+        )
+        .await;
+
+    assert!(matches!(result, Err(AuthServiceError::NotFound)));
+}
+
+#[tokio::test]
+async fn should_reject_impersonation_token_as_refresh_token() {
+    let user = test_user();
+    let out = impersonate_uc(MockUserRepo::new(vec![user.clone()]))
+        .execute(UserId(Uuid::new_v4()), STAFF_ROLE, None, user.id)
+        .await
+        .unwrap();
+
+    let uc = RefreshTokenUseCase {
+        users: MockUserRepo::new(vec![user]),
+        sessions: MockSessionRepo::empty(),
+        jwt_secret: TEST_JWT_SECRET.to_owned(),
+        previous_jwt_secret: None,
+        lifetimes: TokenLifetimes::default(),
+        legacy_cutoff: None,
+    };
+    let result = uc.execute(&out.access_token, &test_client()).await;
+
+    assert!(matches!(result, Err(AuthServiceError::Unauthorized)));
+}
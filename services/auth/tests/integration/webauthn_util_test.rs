@@ -0,0 +1,195 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use uuid::Uuid;
+
+use madome_auth::webauthn_util::{AttestationError, parse_aaguid, parse_attestation};
+
+/// `none` attestation in canonical key order, as platform authenticators send
+/// it; AAGUID of Google Password Manager.
+const NONE_ATTESTATION: &str = "o2NmbXRkbm9uZWdhdHRTdG10oGhhdXRoRGF0YViUo3mm9u6vuaVeN4wRgDTidR5oL6ufLTCrE9ISVYbOGUddAAAAAOqbjWZNAR0hPOS2tIy1ddQAELwCBtHb1LJhOMuN1AY1nFqlAQIDJiABIVggFZWLnC71EsGW6wnT6k86GBiKN-pW7GARgfHkUc4gszoiWCA2jh6aUsbF3h3nkqwyJ0d5Uz_Mo6xJrAQwlFH7NQ8AlA";
+
+/// `packed` attestation with an `x5c` chain, as security keys send it;
+/// AAGUID of a YubiKey 5 series key.
+const PACKED_ATTESTATION: &str = concat!(
+    "o2NmbXRmcGFja2VkZ2F0dFN0bXSjY2FsZyZjc2lnWEAAc-wmbU-0rb89EEqnFPnxEDL9irbYgp_EC1LI",
+    "b2SF15KMwuvUZG8_4_N0vhHZBb9L4nX6hvOInYKp99xeQd0yY3g1Y4FYQMb1ve3zKjIgZoE8v4z-QrxR",
+    "qvG6pSmj3C-8ZygZwpd59hJpaU5khvCDDbxLAmKpM2D7L72f3LJNrST1DOSMJt1oYXV0aERhdGFYlKN5",
+    "pvbur7mlXjeMEYA04nUeaC-rny0wqxPSElWGzhlHRQAAAAPLaUgej_dAOZPsCicpoVSoABCxYxrL6QbE",
+    "rCORftOWZXD3pQECAyYgASFYIEq31mi4jSNg_V2u9rPHSejvhZx7Nu3767Ig1SohSpFzIlgg_b7mK6yO",
+    "y6curSH45vyxIYOt0rgax2ZI7XPC4CNwXek",
+);
+
+const YUBIKEY_5: Uuid = Uuid::from_u128(0xcb69481e_8ff7_4039_93ec_0a2729a154a8);
+const FLAGS_UP_UV_AT: u8 = 0x45;
+
+fn decode(blob: &str) -> Vec<u8> {
+    URL_SAFE_NO_PAD.decode(blob).unwrap()
+}
+
+/// rpIdHash, flags, signCount and AAGUID; enough for the parser.
+fn auth_data(flags: u8, aaguid: Uuid) -> Vec<u8> {
+    let mut data = vec![0u8; 32];
+    data.push(flags);
+    data.extend_from_slice(&[0, 0, 0, 1]);
+    data.extend_from_slice(aaguid.as_bytes());
+    data
+}
+
+/// Definite-length CBOR text string (< 24 bytes).
+fn text(s: &str) -> Vec<u8> {
+    let mut out = vec![0x60 | s.len() as u8];
+    out.extend_from_slice(s.as_bytes());
+    out
+}
+
+/// Definite-length CBOR byte string (< 256 bytes).
+fn bytes(b: &[u8]) -> Vec<u8> {
+    let mut out = match b.len() {
+        n @ 0..24 => vec![0x40 | n as u8],
+        n => vec![0x58, n as u8],
+    };
+    out.extend_from_slice(b);
+    out
+}
+
+/// Definite-length map of already encoded pairs.
+fn map(pairs: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+    let mut out = vec![0xa0 | pairs.len() as u8];
+    for (k, v) in pairs {
+        out.extend_from_slice(k);
+        out.extend_from_slice(v);
+    }
+    out
+}
+
+fn none_attestation(auth_data: Vec<u8>) -> Vec<u8> {
+    map(&[
+        (text("fmt"), text("none")),
+        (text("attStmt"), vec![0xa0]),
+        (text("authData"), auth_data),
+    ])
+}
+
+#[test]
+fn should_read_aaguid_from_none_attestation() {
+    let aaguid = parse_aaguid(&decode(NONE_ATTESTATION)).unwrap();
+    assert_eq!(aaguid.to_string(), "ea9b8d66-4d01-1d21-3ce4-b6b48cb575d4");
+}
+
+#[test]
+fn should_read_aaguid_from_packed_attestation() {
+    assert_eq!(
+        parse_aaguid(&decode(PACKED_ATTESTATION)).unwrap(),
+        YUBIKEY_5
+    );
+}
+
+#[test]
+fn should_read_backup_flags_of_synced_passkey() {
+    let attested = parse_attestation(&decode(NONE_ATTESTATION)).unwrap();
+    assert!(attested.backup_eligible);
+    assert!(attested.backup_state);
+}
+
+#[test]
+fn should_read_backup_flags_of_device_bound_key() {
+    let attested = parse_attestation(&decode(PACKED_ATTESTATION)).unwrap();
+    assert!(!attested.backup_eligible);
+    assert!(!attested.backup_state);
+}
+
+#[test]
+fn should_return_nil_for_zero_aaguid() {
+    let blob = none_attestation(bytes(&auth_data(FLAGS_UP_UV_AT, Uuid::nil())));
+    assert_eq!(parse_aaguid(&blob).unwrap(), Uuid::nil());
+}
+
+#[test]
+fn should_find_auth_data_regardless_of_key_order() {
+    let blob = map(&[
+        (
+            text("authData"),
+            bytes(&auth_data(FLAGS_UP_UV_AT, YUBIKEY_5)),
+        ),
+        (vec![0x01], text("unknown integer key")),
+        (text("fmt"), text("none")),
+        (text("attStmt"), vec![0xa0]),
+    ]);
+    assert_eq!(parse_aaguid(&blob).unwrap(), YUBIKEY_5);
+}
+
+#[test]
+fn should_read_indefinite_length_map_key_and_auth_data() {
+    let data = auth_data(FLAGS_UP_UV_AT, YUBIKEY_5);
+    let mut blob = vec![0xbf];
+    blob.extend(text("fmt"));
+    blob.extend(text("none"));
+    // attStmt: an indefinite-length empty map
+    blob.extend(text("attStmt"));
+    blob.extend([0xbf, 0xff]);
+    // "authData" in two chunks
+    blob.push(0x7f);
+    blob.extend(text("auth"));
+    blob.extend(text("Data"));
+    blob.push(0xff);
+    // authData in two chunks
+    blob.push(0x5f);
+    blob.extend(bytes(&data[..30]));
+    blob.extend(bytes(&data[30..]));
+    blob.push(0xff);
+    blob.push(0xff);
+
+    assert_eq!(parse_aaguid(&blob).unwrap(), YUBIKEY_5);
+}
+
+#[test]
+fn should_reject_attestation_without_auth_data() {
+    let blob = map(&[(text("fmt"), text("none")), (text("attStmt"), vec![0xa0])]);
+    assert!(matches!(
+        parse_aaguid(&blob),
+        Err(AttestationError::MissingAuthData)
+    ));
+}
+
+#[test]
+fn should_reject_attestation_that_is_not_a_map() {
+    assert!(matches!(
+        parse_aaguid(&[0x82, 0x01, 0x02]),
+        Err(AttestationError::NotAMap)
+    ));
+}
+
+#[test]
+fn should_reject_auth_data_that_is_not_bytes() {
+    let blob = none_attestation(text("authData"));
+    assert!(matches!(
+        parse_aaguid(&blob),
+        Err(AttestationError::AuthDataNotBytes)
+    ));
+}
+
+#[test]
+fn should_reject_auth_data_without_attested_credential() {
+    let blob = none_attestation(bytes(&auth_data(0x05, YUBIKEY_5)));
+    assert!(matches!(
+        parse_aaguid(&blob),
+        Err(AttestationError::NoAttestedCredentialData)
+    ));
+}
+
+#[test]
+fn should_reject_short_auth_data() {
+    let blob = none_attestation(bytes(&auth_data(FLAGS_UP_UV_AT, YUBIKEY_5)[..40]));
+    assert!(matches!(
+        parse_aaguid(&blob),
+        Err(AttestationError::AuthDataTooShort(40))
+    ));
+}
+
+#[test]
+fn should_reject_truncated_attestation() {
+    let blob = decode(NONE_ATTESTATION);
+    assert!(matches!(
+        parse_aaguid(&blob[..40]),
+        Err(AttestationError::Malformed(_))
+    ));
+}
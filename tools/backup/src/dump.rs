@@ -0,0 +1,96 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use sea_orm::{
+    AccessMode, ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbBackend, EntityTrait,
+    IsolationLevel, PaginatorTrait, QueryOrder, TransactionTrait,
+};
+use serde::Serialize;
+
+use crate::ndjson::{Header, Writer};
+use crate::tables::{
+    self, ApiTokens, IdAliases, LinkedIdentities, Passkeys, Sessions, Table, Users,
+};
+
+const PAGE_SIZE: u64 = 1000;
+
+/// Write every table to `{dir}/{NAME}.ndjson`, all from one snapshot.
+/// Returns the row count of each table.
+pub async fn run(db: &DatabaseConnection, dir: &Path) -> Result<Vec<(&'static str, u64)>> {
+    tables::ensure_current(db).await?;
+    std::fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+    let version = tables::schema_version();
+
+    // A SQLite transaction reads from one snapshot anyway.
+    let txn = match db.get_database_backend() {
+        DbBackend::Postgres => {
+            db.begin_with_config(
+                Some(IsolationLevel::RepeatableRead),
+                Some(AccessMode::ReadOnly),
+            )
+            .await?
+        }
+        _ => db.begin().await?,
+    };
+    let counts = vec![
+        (Users::NAME, table::<Users>(&txn, dir, &version).await?),
+        (
+            Passkeys::NAME,
+            table::<Passkeys>(&txn, dir, &version).await?,
+        ),
+        (
+            Sessions::NAME,
+            table::<Sessions>(&txn, dir, &version).await?,
+        ),
+        (
+            ApiTokens::NAME,
+            table::<ApiTokens>(&txn, dir, &version).await?,
+        ),
+        (
+            LinkedIdentities::NAME,
+            table::<LinkedIdentities>(&txn, dir, &version).await?,
+        ),
+        (
+            IdAliases::NAME,
+            table::<IdAliases>(&txn, dir, &version).await?,
+        ),
+    ];
+    txn.commit().await?;
+    Ok(counts)
+}
+
+async fn table<T: Table>(txn: &DatabaseTransaction, dir: &Path, version: &str) -> Result<u64>
+where
+    <T::Entity as EntityTrait>::Model: Serialize + Sync,
+{
+    let path = dir.join(format!("{}.ndjson", T::NAME));
+    let file = File::create(&path).with_context(|| format!("create {}", path.display()))?;
+    let mut writer = Writer::new(
+        BufWriter::new(file),
+        &Header {
+            table: T::NAME.to_owned(),
+            schema_version: version.to_owned(),
+        },
+    )?;
+
+    let mut pages = T::Entity::find()
+        .order_by_asc(T::KEY)
+        .paginate(txn, PAGE_SIZE);
+    let mut rows = 0;
+    while let Some(models) = pages
+        .fetch_and_next()
+        .await
+        .with_context(|| format!("read {}", T::NAME))?
+    {
+        for model in &models {
+            writer.row(model)?;
+        }
+        rows += models.len() as u64;
+    }
+    writer
+        .finish()
+        .with_context(|| format!("write {}", path.display()))?;
+    Ok(rows)
+}
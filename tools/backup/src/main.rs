@@ -0,0 +1,84 @@
+//! Backup and restore of the auth database's user data.
+//!
+//! Dumps each table to `<table>.ndjson`, one JSON object per row after a
+//! header naming the schema version, and loads such a dump back:
+//!
+//! ```bash
+//! DATABASE_URL=... cargo run -p backup -- dump --out ./backup
+//! DATABASE_URL=... cargo run -p backup -- restore --from ./backup --user <uuid>
+//! ```
+//!
+//! A restore runs in one transaction and either loads everything or nothing.
+
+mod dump;
+mod ndjson;
+mod restore;
+mod tables;
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use sea_orm::Database;
+use uuid::Uuid;
+
+#[derive(Parser)]
+#[command(about = "Dump and restore the auth database as NDJSON")]
+struct Args {
+    /// Auth service database URL.
+    #[arg(long, env = "DATABASE_URL")]
+    database_url: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Write every table to `<out>/<table>.ndjson`.
+    Dump {
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Load a dump made by the same build.
+    Restore {
+        #[arg(long)]
+        from: PathBuf,
+
+        /// Restore only this user, replacing their current rows.
+        #[arg(long)]
+        user: Option<Uuid>,
+
+        /// Apply pending migrations before restoring.
+        #[arg(long)]
+        migrate: bool,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenvy::dotenv().ok();
+    let args = Args::parse();
+
+    let counts = match args.command {
+        Command::Dump { out } => {
+            let db = Database::connect(&args.database_url).await?;
+            dump::run(&db, &out).await?
+        }
+        Command::Restore {
+            from,
+            user,
+            migrate,
+        } => {
+            if migrate {
+                madome_auth::infra::migrate::run_migrations(&args.database_url).await?;
+            }
+            let db = Database::connect(&args.database_url).await?;
+            restore::run(&db, &from, user).await?
+        }
+    };
+    for (table, rows) in counts {
+        println!("{table}: {rows} rows");
+    }
+    Ok(())
+}
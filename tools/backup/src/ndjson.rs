@@ -0,0 +1,121 @@
+//! One file per table: a header line naming the table and the schema it was
+//! dumped from, then one JSON object per row.
+
+use std::io::{BufRead, Lines, Write};
+
+use anyhow::{Context, Result, bail};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Header {
+    pub table: String,
+    /// Last migration applied to the database the rows came from.
+    pub schema_version: String,
+}
+
+pub struct Writer<W> {
+    out: W,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(mut out: W, header: &Header) -> Result<Self> {
+        serde_json::to_writer(&mut out, header)?;
+        out.write_all(b"\n")?;
+        Ok(Self { out })
+    }
+
+    pub fn row(&mut self, row: &impl Serialize) -> Result<()> {
+        serde_json::to_writer(&mut self.out, row)?;
+        self.out.write_all(b"\n")?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+pub struct Reader<R> {
+    lines: Lines<R>,
+    /// 1-based number of the last line read.
+    line: usize,
+}
+
+impl<R: BufRead> Reader<R> {
+    /// Reads the header and checks it is `expected`'s.
+    pub fn new(input: R, expected: &Header) -> Result<Self> {
+        let mut reader = Self {
+            lines: input.lines(),
+            line: 0,
+        };
+        let Some(header) = reader.next_row::<Header>()? else {
+            bail!("empty file, expected a `{}` header", expected.table);
+        };
+        if header.table != expected.table {
+            bail!("holds `{}`, expected `{}`", header.table, expected.table);
+        }
+        if header.schema_version != expected.schema_version {
+            bail!(
+                "dumped at schema {}, but this build is at {}; restore with the build that \
+                 made the dump, then migrate",
+                header.schema_version,
+                expected.schema_version
+            );
+        }
+        Ok(reader)
+    }
+
+    pub fn next_row<T: DeserializeOwned>(&mut self) -> Result<Option<T>> {
+        let Some(line) = self.lines.next() else {
+            return Ok(None);
+        };
+        self.line += 1;
+        let line = line.with_context(|| format!("line {}", self.line))?;
+        serde_json::from_str(&line)
+            .map(Some)
+            .with_context(|| format!("line {}", self.line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(table: &str, schema_version: &str) -> Header {
+        Header {
+            table: table.to_owned(),
+            schema_version: schema_version.to_owned(),
+        }
+    }
+
+    #[test]
+    fn should_read_back_written_rows_after_the_header() {
+        let mut out = Vec::new();
+        let mut writer = Writer::new(&mut out, &header("users", "m1")).unwrap();
+        writer.row(&serde_json::json!({"id": 1})).unwrap();
+        writer.row(&serde_json::json!({"id": 2})).unwrap();
+        writer.finish().unwrap();
+
+        let mut reader = Reader::new(out.as_slice(), &header("users", "m1")).unwrap();
+        let ids: Vec<u64> = std::iter::from_fn(|| reader.next_row::<serde_json::Value>().unwrap())
+            .map(|row| row["id"].as_u64().unwrap())
+            .collect();
+        assert_eq!(ids, [1, 2]);
+    }
+
+    #[test]
+    fn should_reject_another_table_or_schema_version() {
+        let input = "{\"table\":\"users\",\"schema_version\":\"m1\"}\n{oops\n";
+        let error = Reader::new(input.as_bytes(), &header("passkeys", "m1"))
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "holds `users`, expected `passkeys`");
+        assert!(Reader::new(input.as_bytes(), &header("users", "m2")).is_err());
+
+        let mut reader = Reader::new(input.as_bytes(), &header("users", "m1")).unwrap();
+        let error = reader.next_row::<serde_json::Value>().unwrap_err();
+        assert_eq!(error.to_string(), "line 2");
+    }
+}
@@ -0,0 +1,255 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DatabaseTransaction, EntityTrait, IntoActiveModel, ModelTrait,
+    PaginatorTrait, QueryFilter, TransactionTrait, Value,
+};
+use serde::de::DeserializeOwned;
+use uuid::Uuid;
+
+use crate::ndjson::{Header, Reader};
+use crate::tables::{
+    self, ApiTokens, IdAliases, LinkedIdentities, Passkeys, Sessions, Table, Users,
+};
+
+const BATCH_SIZE: usize = 500;
+
+/// Load the dump in `dir` in one transaction, so a failed restore changes
+/// nothing. Returns the rows restored per table.
+///
+/// With `user`, only that user's rows are restored and replace the ones they
+/// have now; other users are untouched. Without, the database must have no
+/// users yet.
+pub async fn run(
+    db: &DatabaseConnection,
+    dir: &Path,
+    user: Option<Uuid>,
+) -> Result<Vec<(&'static str, u64)>> {
+    tables::ensure_current(db).await?;
+    let version = tables::schema_version();
+
+    let txn = db.begin().await?;
+    match user {
+        Some(id) => {
+            clear::<IdAliases>(&txn, id).await?;
+            clear::<LinkedIdentities>(&txn, id).await?;
+            clear::<ApiTokens>(&txn, id).await?;
+            clear::<Sessions>(&txn, id).await?;
+            clear::<Passkeys>(&txn, id).await?;
+            clear::<Users>(&txn, id).await?;
+        }
+        None => {
+            if <Users as Table>::Entity::find().count(&txn).await? > 0 {
+                bail!(
+                    "the database already has users; restore into an empty one, or one user with --user"
+                );
+            }
+        }
+    }
+    let counts = vec![
+        (
+            Users::NAME,
+            table::<Users>(&txn, dir, &version, user).await?,
+        ),
+        (
+            Passkeys::NAME,
+            table::<Passkeys>(&txn, dir, &version, user).await?,
+        ),
+        (
+            Sessions::NAME,
+            table::<Sessions>(&txn, dir, &version, user).await?,
+        ),
+        (
+            ApiTokens::NAME,
+            table::<ApiTokens>(&txn, dir, &version, user).await?,
+        ),
+        (
+            LinkedIdentities::NAME,
+            table::<LinkedIdentities>(&txn, dir, &version, user).await?,
+        ),
+        (
+            IdAliases::NAME,
+            table::<IdAliases>(&txn, dir, &version, user).await?,
+        ),
+    ];
+    if let (Some(id), (_, 0)) = (user, counts[0]) {
+        bail!("user {id} is not in the dump");
+    }
+    txn.commit().await?;
+    Ok(counts)
+}
+
+/// Delete the rows of `T` that belong to `user`.
+async fn clear<T: Table>(txn: &DatabaseTransaction, user: Uuid) -> Result<()> {
+    T::Entity::delete_many()
+        .filter(T::OWNER.eq(user))
+        .exec(txn)
+        .await
+        .with_context(|| format!("clear {}", T::NAME))?;
+    Ok(())
+}
+
+async fn table<T: Table>(
+    txn: &DatabaseTransaction,
+    dir: &Path,
+    version: &str,
+    user: Option<Uuid>,
+) -> Result<u64>
+where
+    <T::Entity as EntityTrait>::Model:
+        DeserializeOwned + IntoActiveModel<<T::Entity as EntityTrait>::ActiveModel>,
+{
+    let path = dir.join(format!("{}.ndjson", T::NAME));
+    let file = File::open(&path).with_context(|| format!("open {}", path.display()))?;
+    let mut reader = Reader::new(
+        BufReader::new(file),
+        &Header {
+            table: T::NAME.to_owned(),
+            schema_version: version.to_owned(),
+        },
+    )
+    .with_context(|| path.display().to_string())?;
+    let owner = user.map(Value::from);
+
+    let mut rows = 0;
+    loop {
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        while batch.len() < BATCH_SIZE {
+            let Some(model) = reader
+                .next_row::<<T::Entity as EntityTrait>::Model>()
+                .with_context(|| path.display().to_string())?
+            else {
+                break;
+            };
+            if owner.as_ref().is_none_or(|id| model.get(T::OWNER) == *id) {
+                batch.push(model.into_active_model());
+            }
+        }
+        if batch.is_empty() {
+            return Ok(rows);
+        }
+        rows += batch.len() as u64;
+        T::Entity::insert_many(batch)
+            .exec_without_returning(txn)
+            .await
+            .with_context(|| format!("insert {}", T::NAME))?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::Utc;
+    use madome_auth::infra::migrate::run_migrations;
+    use madome_auth_schema::{passkeys, users};
+    use madome_domain::id::UserId;
+    use sea_orm::{ActiveModelTrait, Database, QueryOrder, Set};
+
+    use super::*;
+    use crate::dump;
+
+    /// Directory for the databases and dump of one test, removed on drop.
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("madome-backup-{}", Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        async fn database(&self, name: &str) -> DatabaseConnection {
+            let url = format!("sqlite://{}?mode=rwc", self.0.join(name).display());
+            run_migrations(&url).await.unwrap();
+            Database::connect(&url).await.unwrap()
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// A user with one passkey, whose credential id is their email.
+    async fn add_user(db: &DatabaseConnection, email: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        users::ActiveModel {
+            id: Set(id),
+            email: Set(email.to_owned()),
+            role: Set(0),
+            deleted_at: Set(None),
+        }
+        .insert(db)
+        .await
+        .unwrap();
+        passkeys::ActiveModel {
+            credential_id: Set(email.as_bytes().to_vec()),
+            user_id: Set(UserId(id)),
+            aaguid: Set(Uuid::nil()),
+            credential: Set(b"{}".to_vec()),
+            backup_eligible: Set(true),
+            backup_state: Set(false),
+            transports: Set(serde_json::json!(["usb"])),
+            created_at: Set(Utc::now()),
+        }
+        .insert(db)
+        .await
+        .unwrap();
+        id
+    }
+
+    async fn all_passkeys(db: &DatabaseConnection) -> Vec<passkeys::Model> {
+        passkeys::Entity::find()
+            .order_by_asc(passkeys::Column::CredentialId)
+            .all(db)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_restore_a_dump_into_an_empty_database() {
+        let scratch = Scratch::new();
+        let source = scratch.database("source.db").await;
+        add_user(&source, "a@example.com").await;
+        add_user(&source, "b@example.com").await;
+        let dir = scratch.0.join("dump");
+        dump::run(&source, &dir).await.unwrap();
+
+        let target = scratch.database("target.db").await;
+        let counts = run(&target, &dir, None).await.unwrap();
+        assert_eq!(counts[..2], [("users", 2), ("passkeys", 2)]);
+        assert_eq!(all_passkeys(&target).await, all_passkeys(&source).await);
+        // Restoring on top would mix two states.
+        assert!(run(&target, &dir, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn should_restore_one_user_without_touching_the_others() {
+        let scratch = Scratch::new();
+        let db = scratch.database("auth.db").await;
+        let a = add_user(&db, "a@example.com").await;
+        add_user(&db, "b@example.com").await;
+        let dir = scratch.0.join("dump");
+        dump::run(&db, &dir).await.unwrap();
+        let before = all_passkeys(&db).await;
+
+        users::Entity::delete_by_id(a).exec(&db).await.unwrap();
+        add_user(&db, "c@example.com").await;
+        let counts = run(&db, &dir, Some(a)).await.unwrap();
+        assert_eq!(counts[..2], [("users", 1), ("passkeys", 1)]);
+        let after = all_passkeys(&db).await;
+        assert_eq!(after[..2], before[..]);
+        assert_eq!(after[2].credential_id, b"c@example.com");
+
+        let error = run(&db, &dir, Some(Uuid::nil())).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "user 00000000-0000-0000-0000-000000000000 is not in the dump"
+        );
+    }
+}
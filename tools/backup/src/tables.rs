@@ -0,0 +1,101 @@
+//! What a backup holds: the auth tables with lasting user data. Auth codes
+//! and login links expire within minutes and outbox events are delivery
+//! bookkeeping, so all three are left out.
+
+use anyhow::{Result, bail};
+use sea_orm::{DatabaseConnection, EntityTrait};
+use sea_orm_migration::MigratorTrait;
+
+use madome_auth_migration::Migrator;
+use madome_auth_schema::{api_tokens, id_aliases, linked_identities, passkeys, sessions, users};
+
+pub type Column<T> = <<T as Table>::Entity as EntityTrait>::Column;
+
+pub trait Table {
+    /// Table name and file stem: `users` is dumped to `users.ndjson`.
+    const NAME: &'static str;
+    /// Column rows are dumped in order of.
+    const KEY: Column<Self>;
+    /// Column holding the id of the user a row belongs to.
+    const OWNER: Column<Self>;
+
+    type Entity: EntityTrait;
+}
+
+pub struct Users;
+pub struct Passkeys;
+pub struct Sessions;
+pub struct ApiTokens;
+pub struct LinkedIdentities;
+pub struct IdAliases;
+
+impl Table for Users {
+    const NAME: &'static str = "users";
+    const KEY: Column<Self> = users::Column::Id;
+    const OWNER: Column<Self> = users::Column::Id;
+
+    type Entity = users::Entity;
+}
+
+impl Table for Passkeys {
+    const NAME: &'static str = "passkeys";
+    const KEY: Column<Self> = passkeys::Column::CredentialId;
+    const OWNER: Column<Self> = passkeys::Column::UserId;
+
+    type Entity = passkeys::Entity;
+}
+
+impl Table for Sessions {
+    const NAME: &'static str = "sessions";
+    const KEY: Column<Self> = sessions::Column::Id;
+    const OWNER: Column<Self> = sessions::Column::UserId;
+
+    type Entity = sessions::Entity;
+}
+
+impl Table for ApiTokens {
+    const NAME: &'static str = "api_tokens";
+    const KEY: Column<Self> = api_tokens::Column::Id;
+    const OWNER: Column<Self> = api_tokens::Column::UserId;
+
+    type Entity = api_tokens::Entity;
+}
+
+impl Table for LinkedIdentities {
+    const NAME: &'static str = "linked_identities";
+    const KEY: Column<Self> = linked_identities::Column::Id;
+    const OWNER: Column<Self> = linked_identities::Column::UserId;
+
+    type Entity = linked_identities::Entity;
+}
+
+impl Table for IdAliases {
+    const NAME: &'static str = "id_aliases";
+    const KEY: Column<Self> = id_aliases::Column::LegacyId;
+    const OWNER: Column<Self> = id_aliases::Column::UserId;
+
+    type Entity = id_aliases::Entity;
+}
+
+/// Last migration this build knows; every file of a dump carries it.
+pub fn schema_version() -> String {
+    Migrator::migrations()
+        .last()
+        .map(|m| m.name().to_owned())
+        .unwrap_or_default()
+}
+
+/// Fails unless the database is at exactly [`schema_version`], the only
+/// schema the entities here describe.
+pub async fn ensure_current(db: &DatabaseConnection) -> Result<()> {
+    // Also fails if the database has migrations this build does not know.
+    let pending = Migrator::get_pending_migrations(db).await?;
+    if let Some(first) = pending.first() {
+        bail!(
+            "{} migrations pending, starting at {}; migrate first (restore has --migrate)",
+            pending.len(),
+            first.name()
+        );
+    }
+    Ok(())
+}
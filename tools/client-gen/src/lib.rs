@@ -0,0 +1,58 @@
+//! TypeScript clients generated from the services' OpenAPI documents.
+//!
+//! [`typescript::render`] turns a document into a single `index.ts` with one
+//! type per component schema and one method per operation. The contract
+//! harness uses [`validate`] to check live responses against the same schemas,
+//! so a response the generated types cannot describe fails the harness.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+pub mod typescript;
+pub mod validate;
+
+/// One generated client package.
+pub struct Target {
+    pub service: &'static str,
+    /// Name of the exported client class.
+    pub class_name: &'static str,
+    /// Generated file, relative to the workspace root.
+    pub path: &'static str,
+    pub spec: fn() -> Value,
+}
+
+impl Target {
+    pub fn render(&self) -> String {
+        typescript::render(&(self.spec)(), self.class_name)
+    }
+
+    pub fn file(&self, workspace_root: &Path) -> PathBuf {
+        workspace_root.join(self.path)
+    }
+
+    /// Whether the committed file matches what the current spec generates.
+    pub fn is_up_to_date(&self, workspace_root: &Path) -> bool {
+        std::fs::read_to_string(self.file(workspace_root)).is_ok_and(|f| f == self.render())
+    }
+}
+
+/// Every client compiled in (one per enabled service feature).
+pub fn targets() -> Vec<Target> {
+    #[allow(unused_mut)]
+    let mut targets = Vec::new();
+
+    #[cfg(feature = "auth")]
+    targets.push(Target {
+        service: "auth",
+        class_name: "AuthClient",
+        path: "packages/auth-client/src/index.ts",
+        spec: || {
+            use utoipa::OpenApi;
+            serde_json::to_value(madome_auth::openapi::ApiDoc::openapi())
+                .expect("OpenAPI document serializes")
+        },
+    });
+
+    targets
+}
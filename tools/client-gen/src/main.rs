@@ -0,0 +1,46 @@
+//! Regenerate the TypeScript client packages under `packages/`:
+//!
+//! ```bash
+//! cargo run -p client-gen            # rewrite every generated client
+//! cargo run -p client-gen -- --check # exit 1 if any is out of date
+//! ```
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+
+#[derive(Parser)]
+#[command(about = "Generate TypeScript clients from the services' OpenAPI documents")]
+struct Args {
+    /// Compare instead of writing; exit 1 when a generated file is stale.
+    #[arg(long)]
+    check: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let workspace_root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+
+    let mut stale = false;
+    for target in client_gen::targets() {
+        let file = target.file(&workspace_root);
+        if args.check {
+            if target.is_up_to_date(&workspace_root) {
+                println!("{}: up to date", target.path);
+            } else {
+                println!("{}: stale, run `cargo run -p client-gen`", target.path);
+                stale = true;
+            }
+            continue;
+        }
+        std::fs::write(&file, target.render())
+            .with_context(|| format!("cannot write {}", file.display()))?;
+        println!("{}: written", target.path);
+    }
+
+    if stale {
+        std::process::exit(1);
+    }
+    Ok(())
+}
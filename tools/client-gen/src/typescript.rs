@@ -0,0 +1,481 @@
+//! OpenAPI (as JSON) → TypeScript.
+//!
+//! Only the subset the services' documents use is supported: component
+//! schemas, `$ref`, `type` (including `["string", "null"]`), `enum`, arrays,
+//! objects, `oneOf`/`anyOf`/`allOf`, and path/query/header parameters with JSON
+//! request and response bodies. Anything else becomes `unknown`. Keys are
+//! sorted, so the output does not depend on serializer map ordering.
+
+use std::fmt::Write;
+
+use serde_json::{Map, Value};
+
+const HEADER: &str = "\
+// Generated by `cargo run -p client-gen`; do not edit by hand.
+// The contract harness fails when this file is out of date.
+";
+
+/// Methods in the order they are emitted within a path.
+const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+/// Render the whole client module: component types, the runtime and `class_name`.
+pub fn render(spec: &Value, class_name: &str) -> String {
+    let mut out = String::from(HEADER);
+    let schemas = spec
+        .pointer("/components/schemas")
+        .and_then(Value::as_object);
+
+    for (name, schema) in sorted(schemas) {
+        out.push('\n');
+        doc(&mut out, "", schema.get("description"));
+        match object_fields(schema, "") {
+            Some(fields) => writeln!(out, "export interface {name} {fields}").unwrap(),
+            None => writeln!(out, "export type {name} = {};", ts_type(schema)).unwrap(),
+        }
+    }
+
+    let error_type = if schemas.is_some_and(|s| s.contains_key("ErrorBody")) {
+        "ErrorBody"
+    } else {
+        "unknown"
+    };
+    out.push('\n');
+    out.push_str(&RUNTIME.replace("$ERROR", error_type));
+
+    writeln!(out, "\nexport class {class_name} extends BaseClient {{").unwrap();
+    let paths = spec.get("paths").and_then(Value::as_object);
+    let mut first = true;
+    for (path, item) in sorted(paths) {
+        for method in METHODS {
+            let Some(op) = item.get(method) else { continue };
+            if !first {
+                out.push('\n');
+            }
+            first = false;
+            operation(&mut out, path, method, op);
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Shared by every generated client.
+const RUNTIME: &str = r#"export interface ApiResponse<T> {
+  status: number;
+  headers: Headers;
+  data: T;
+}
+
+/** Non-2xx response; `body` is the parsed error body, if any. */
+export class ApiError extends Error {
+  constructor(
+    readonly status: number,
+    readonly body: $ERROR | undefined,
+  ) {
+    super(`HTTP ${status}`);
+  }
+}
+
+export interface ClientOptions {
+  /** Origin of the gateway, e.g. `https://example.com`. */
+  baseUrl: string;
+  fetch?: typeof fetch;
+  /** Sent with every request, e.g. `x-madome-csrf`. */
+  headers?: Record<string, string>;
+}
+
+type Params = Record<string, string | number | boolean | null | undefined>;
+
+export abstract class BaseClient {
+  private readonly baseUrl: string;
+  private readonly fetchImpl: typeof fetch;
+  private readonly headers: Record<string, string>;
+
+  constructor(options: ClientOptions) {
+    this.baseUrl = options.baseUrl.replace(/\/+$/, "");
+    this.fetchImpl = options.fetch ?? globalThis.fetch.bind(globalThis);
+    this.headers = options.headers ?? {};
+  }
+
+  protected async request<T>(
+    method: string,
+    path: string,
+    query?: Params,
+    headers?: Params,
+    body?: unknown,
+  ): Promise<ApiResponse<T>> {
+    const url = new URL(this.baseUrl + path);
+    for (const [key, value] of Object.entries(query ?? {})) {
+      if (value != null) url.searchParams.set(key, String(value));
+    }
+    const sent: Record<string, string> = { ...this.headers };
+    for (const [key, value] of Object.entries(headers ?? {})) {
+      if (value != null) sent[key] = String(value);
+    }
+    if (body !== undefined) sent["content-type"] = "application/json";
+
+    const response = await this.fetchImpl(url, {
+      method,
+      headers: sent,
+      body: body === undefined ? undefined : JSON.stringify(body),
+      credentials: "include",
+    });
+    const text = await response.text();
+    const data = text ? JSON.parse(text) : undefined;
+    if (!response.ok) throw new ApiError(response.status, data);
+    return { status: response.status, headers: response.headers, data };
+  }
+}
+"#;
+
+fn operation(out: &mut String, path: &str, method: &str, op: &Value) {
+    let params = op
+        .get("parameters")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let located = |location: &str| -> Vec<&Value> {
+        params
+            .iter()
+            .filter(|p| p.get("in").and_then(Value::as_str) == Some(location))
+            .collect()
+    };
+    let path_params = located("path");
+    let query_params = located("query");
+    let header_params = located("header");
+
+    let mut args = Vec::new();
+    for p in &path_params {
+        args.push(format!(
+            "{}: {}",
+            camel_case(param_name(p)),
+            ts_type(p.get("schema").unwrap_or(&Value::Null))
+        ));
+    }
+    if !query_params.is_empty() {
+        args.push(params_arg("query", &query_params));
+    }
+    let body = op.pointer("/requestBody/content/application~1json/schema");
+    if let Some(schema) = body {
+        args.push(format!("body: {}", ts_type(schema)));
+    }
+    if !header_params.is_empty() {
+        args.push(params_arg("headers", &header_params));
+    }
+
+    let name = camel_case(
+        op.get("operationId")
+            .and_then(Value::as_str)
+            .unwrap_or(path),
+    );
+    doc(out, "  ", op.get("summary").or(op.get("description")));
+    let response = success_type(op);
+    writeln!(
+        out,
+        "  {name}({}): Promise<ApiResponse<{response}>> {{",
+        args.join(", ")
+    )
+    .unwrap();
+
+    let mut url = format!("\"{path}\"");
+    if !path_params.is_empty() {
+        let mut template = path.to_owned();
+        for p in &path_params {
+            let name = param_name(p);
+            template = template.replace(
+                &format!("{{{name}}}"),
+                &format!("${{encodeURIComponent({})}}", camel_case(name)),
+            );
+        }
+        url = format!("`{template}`");
+    }
+    let mut call = vec![format!("\"{}\"", method.to_uppercase()), url];
+    let trailing = [
+        (!query_params.is_empty()).then_some("query"),
+        (!header_params.is_empty()).then_some("headers"),
+        body.is_some().then_some("body"),
+    ];
+    let last = trailing.iter().rposition(Option::is_some);
+    for arg in trailing.iter().take(last.map_or(0, |i| i + 1)) {
+        call.push(arg.unwrap_or("undefined").to_owned());
+    }
+    writeln!(
+        out,
+        "    return this.request<{response}>({});",
+        call.join(", ")
+    )
+    .unwrap();
+    out.push_str("  }\n");
+}
+
+/// `name: { "a": T; b?: U }`, optional when no parameter is required.
+fn params_arg(name: &str, params: &[&Value]) -> String {
+    let required = params.iter().any(|p| is_required(p));
+    let fields: Vec<String> = params
+        .iter()
+        .map(|p| {
+            format!(
+                "{}{}: {}",
+                property_key(param_name(p)),
+                if is_required(p) { "" } else { "?" },
+                ts_type(p.get("schema").unwrap_or(&Value::Null))
+            )
+        })
+        .collect();
+    format!(
+        "{name}{}: {{ {} }}",
+        if required { "" } else { "?" },
+        fields.join("; ")
+    )
+}
+
+fn param_name(param: &Value) -> &str {
+    param
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+}
+
+fn is_required(param: &Value) -> bool {
+    param
+        .get("required")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// Body type of the lowest documented 2xx response, `void` when it has none.
+fn success_type(op: &Value) -> String {
+    let responses = op.get("responses").and_then(Value::as_object);
+    sorted(responses)
+        .into_iter()
+        .find(|(status, _)| status.starts_with('2'))
+        .and_then(|(_, r)| r.pointer("/content/application~1json/schema"))
+        .map_or_else(|| "void".to_owned(), ts_type)
+}
+
+/// TypeScript type expression for a schema.
+pub fn ts_type(schema: &Value) -> String {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return reference.rsplit('/').next().unwrap_or(reference).to_owned();
+    }
+    for (key, separator) in [("oneOf", " | "), ("anyOf", " | "), ("allOf", " & ")] {
+        if let Some(parts) = schema.get(key).and_then(Value::as_array) {
+            let parts: Vec<String> = parts.iter().map(|s| grouped(ts_type(s))).collect();
+            return parts.join(separator);
+        }
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        let literals: Vec<String> = values.iter().map(Value::to_string).collect();
+        return literals.join(" | ");
+    }
+
+    let types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(ts)) => ts.iter().filter_map(Value::as_str).collect(),
+        _ => return "unknown".to_owned(),
+    };
+    let parts: Vec<String> = types
+        .into_iter()
+        .map(|t| match t {
+            "string" => "string".to_owned(),
+            "integer" | "number" => "number".to_owned(),
+            "boolean" => "boolean".to_owned(),
+            "null" => "null".to_owned(),
+            "array" => format!(
+                "{}[]",
+                grouped(ts_type(schema.get("items").unwrap_or(&Value::Null)))
+            ),
+            "object" => object_type(schema),
+            _ => "unknown".to_owned(),
+        })
+        .collect();
+    parts.join(" | ")
+}
+
+fn object_type(schema: &Value) -> String {
+    if let Some(fields) = object_fields(schema, "") {
+        return fields;
+    }
+    match schema.get("additionalProperties") {
+        Some(value @ Value::Object(_)) => format!("Record<string, {}>", ts_type(value)),
+        _ => "Record<string, unknown>".to_owned(),
+    }
+}
+
+/// `{ ... }` body for an object schema with properties, one field per line.
+fn object_fields(schema: &Value, indent: &str) -> Option<String> {
+    let properties = schema.get("properties")?.as_object()?;
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    let mut out = String::from("{\n");
+    for (name, property) in sorted(Some(properties)) {
+        let field_indent = format!("{indent}  ");
+        doc(&mut out, &field_indent, property.get("description"));
+        let optional = if required.contains(&name.as_str()) {
+            ""
+        } else {
+            "?"
+        };
+        writeln!(
+            out,
+            "{field_indent}{}{optional}: {};",
+            property_key(name),
+            ts_type(property)
+        )
+        .unwrap();
+    }
+    out.push_str(indent);
+    out.push('}');
+    Some(out)
+}
+
+/// Parenthesize unions so `[]` and `&` bind to the whole type.
+fn grouped(ty: String) -> String {
+    if ty.contains(" | ") || ty.contains(" & ") {
+        format!("({ty})")
+    } else {
+        ty
+    }
+}
+
+fn property_key(name: &str) -> String {
+    let identifier = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if identifier {
+        name.to_owned()
+    } else {
+        format!("\"{name}\"")
+    }
+}
+
+/// `create_authcode` → `createAuthcode`, `registration-id` → `registrationId`.
+fn camel_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' || c == '-' {
+            upper = !out.is_empty();
+        } else if upper {
+            out.push(c.to_ascii_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn doc(out: &mut String, indent: &str, text: Option<&Value>) {
+    let Some(text) = text.and_then(Value::as_str).filter(|t| !t.is_empty()) else {
+        return;
+    };
+    let text = text.replace("*/", "*\\/");
+    let lines: Vec<&str> = text.lines().collect();
+    if let [line] = lines.as_slice() {
+        writeln!(out, "{indent}/** {line} */").unwrap();
+        return;
+    }
+    writeln!(out, "{indent}/**").unwrap();
+    for line in lines {
+        let line = format!("{indent} * {line}");
+        writeln!(out, "{}", line.trim_end()).unwrap();
+    }
+    writeln!(out, "{indent} */").unwrap();
+}
+
+fn sorted(map: Option<&Map<String, Value>>) -> Vec<(&String, &Value)> {
+    let mut entries: Vec<_> = map.into_iter().flatten().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn should_map_schema_types() {
+        assert_eq!(
+            ts_type(&json!({ "type": ["string", "null"] })),
+            "string | null"
+        );
+        assert_eq!(
+            ts_type(
+                &json!({ "type": "array", "items": { "$ref": "#/components/schemas/FieldError" } })
+            ),
+            "FieldError[]"
+        );
+        assert_eq!(
+            ts_type(&json!({ "type": "array", "items": { "type": ["integer", "null"] } })),
+            "(number | null)[]"
+        );
+        assert_eq!(
+            ts_type(&json!({ "type": "string", "enum": ["a", "b"] })),
+            "\"a\" | \"b\""
+        );
+        assert_eq!(ts_type(&json!({})), "unknown");
+    }
+
+    #[test]
+    fn should_render_interfaces_and_operations() {
+        let spec = json!({
+            "components": { "schemas": {
+                "SessionResponse": {
+                    "type": "object",
+                    "required": ["id"],
+                    "properties": {
+                        "id": { "type": "string", "format": "uuid" },
+                        "ip": { "type": ["string", "null"], "description": "Client address." }
+                    }
+                }
+            }},
+            "paths": {
+                "/auth/sessions/{id}": { "delete": {
+                    "operationId": "delete_session",
+                    "summary": "Sign a device out.",
+                    "parameters": [{ "name": "id", "in": "path", "required": true, "schema": { "type": "string" } }],
+                    "responses": { "204": { "description": "" } }
+                }},
+                "/auth/passkey/registration": { "patch": {
+                    "operationId": "finish_registration",
+                    "parameters": [{ "name": "registration-id", "in": "query", "required": true, "schema": { "type": "string" } }],
+                    "requestBody": { "content": { "application/json": { "schema": {} } } },
+                    "responses": { "201": { "description": "" } }
+                }},
+                "/auth/sessions": { "get": {
+                    "operationId": "list_sessions",
+                    "responses": { "200": { "content": { "application/json": { "schema": {
+                        "type": "array", "items": { "$ref": "#/components/schemas/SessionResponse" }
+                    }}}}}
+                }}
+            }
+        });
+
+        let ts = render(&spec, "AuthClient");
+
+        assert!(ts.contains(
+            "export interface SessionResponse {\n  id: string;\n  /** Client address. */\n  ip?: string | null;\n}"
+        ));
+        assert!(ts.contains("body: unknown | undefined"));
+        assert!(ts.contains(
+            "  /** Sign a device out. */\n  deleteSession(id: string): Promise<ApiResponse<void>> {\n    return this.request<void>(\"DELETE\", `/auth/sessions/${encodeURIComponent(id)}`);\n  }"
+        ));
+        assert!(ts.contains(
+            "  finishRegistration(query: { \"registration-id\": string }, body: unknown): Promise<ApiResponse<void>> {\n    return this.request<void>(\"PATCH\", \"/auth/passkey/registration\", query, undefined, body);"
+        ));
+        assert!(ts.contains("listSessions(): Promise<ApiResponse<SessionResponse[]>>"));
+        // Paths are sorted: registration < sessions < sessions/{id}.
+        let order =
+            ["finishRegistration", "listSessions", "deleteSession"].map(|m| ts.find(m).unwrap());
+        assert!(order.is_sorted());
+    }
+}
@@ -0,0 +1,213 @@
+//! Check a JSON value against an OpenAPI schema.
+//!
+//! Stricter than the generated TypeScript in one way: properties the schema
+//! does not declare are reported, since a field the client types do not know
+//! about means the document (and so the client) is behind the service.
+
+use serde_json::Value;
+
+/// Response body schema documented for `method path` answering `status`.
+///
+/// `path` is a concrete request path (a query string is ignored); it matches
+/// templated document paths such as `/auth/sessions/{id}`.
+pub fn response_schema<'a>(
+    spec: &'a Value,
+    method: &str,
+    path: &str,
+    status: u16,
+) -> Option<&'a Value> {
+    let path = path.split('?').next().unwrap_or(path);
+    let (_, item) = spec
+        .get("paths")?
+        .as_object()?
+        .iter()
+        .find(|(template, _)| matches_template(template, path))?;
+    item.get(method.to_lowercase())?
+        .get("responses")?
+        .get(status.to_string())?
+        .pointer("/content/application~1json/schema")
+}
+
+fn matches_template(template: &str, path: &str) -> bool {
+    let template: Vec<&str> = template.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    template.len() == path.len()
+        && template
+            .iter()
+            .zip(&path)
+            .all(|(t, p)| (t.starts_with('{') && t.ends_with('}') && !p.is_empty()) || t == p)
+}
+
+/// Every way `value` deviates from `schema`, as `$.field: problem` lines.
+pub fn check(spec: &Value, schema: &Value, value: &Value) -> Vec<String> {
+    let mut problems = Vec::new();
+    check_at(spec, schema, value, "$", &mut problems);
+    problems
+}
+
+fn check_at(spec: &Value, schema: &Value, value: &Value, at: &str, problems: &mut Vec<String>) {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let pointer = reference.trim_start_matches('#');
+        match spec.pointer(pointer) {
+            Some(resolved) => check_at(spec, resolved, value, at, problems),
+            None => problems.push(format!("{at}: unresolved {reference}")),
+        }
+        return;
+    }
+    for key in ["oneOf", "anyOf"] {
+        if let Some(options) = schema.get(key).and_then(Value::as_array) {
+            if !options.iter().any(|o| check(spec, o, value).is_empty()) {
+                problems.push(format!("{at}: matches none of the {key} schemas"));
+            }
+            return;
+        }
+    }
+    if let Some(parts) = schema.get("allOf").and_then(Value::as_array) {
+        for part in parts {
+            check_at(spec, part, value, at, problems);
+        }
+        return;
+    }
+
+    let types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(ts)) => ts.iter().filter_map(Value::as_str).collect(),
+        // `{}`: any value.
+        _ => return,
+    };
+    if !types.iter().any(|t| has_type(value, t)) {
+        problems.push(format!(
+            "{at}: expected {}, got {}",
+            types.join(" or "),
+            type_name(value)
+        ));
+        return;
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            problems.push(format!("{at}: {value} is not one of the documented values"));
+        }
+    }
+
+    match value {
+        Value::Array(items) => {
+            let items_schema = schema.get("items").unwrap_or(&Value::Null);
+            for (i, item) in items.iter().enumerate() {
+                check_at(spec, items_schema, item, &format!("{at}[{i}]"), problems);
+            }
+        }
+        Value::Object(fields) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            let required = schema.get("required").and_then(Value::as_array);
+            for name in required.into_iter().flatten().filter_map(Value::as_str) {
+                if !fields.contains_key(name) {
+                    problems.push(format!("{at}.{name}: required but missing"));
+                }
+            }
+            let Some(properties) = properties else { return };
+            for (name, field) in fields {
+                match properties.get(name) {
+                    Some(property) => {
+                        check_at(spec, property, field, &format!("{at}.{name}"), problems)
+                    }
+                    None => problems.push(format!("{at}.{name}: not in the schema")),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn has_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => false,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn spec() -> Value {
+        json!({
+            "paths": { "/auth/sessions/{id}": { "delete": { "responses": {
+                "404": { "content": { "application/json": { "schema": {
+                    "$ref": "#/components/schemas/ErrorBody"
+                }}}}
+            }}}},
+            "components": { "schemas": {
+                "ErrorBody": {
+                    "type": "object",
+                    "required": ["kind", "message"],
+                    "properties": {
+                        "kind": { "$ref": "#/components/schemas/ErrorKind" },
+                        "message": { "type": "string" },
+                        "trace_id": { "type": ["string", "null"] }
+                    }
+                },
+                "ErrorKind": { "type": "string", "enum": ["not_found", "unauthorized"] }
+            }}
+        })
+    }
+
+    #[test]
+    fn should_find_schema_for_templated_path() {
+        let spec = spec();
+
+        assert!(response_schema(&spec, "DELETE", "/auth/sessions/abc?x=1", 404).is_some());
+        assert!(response_schema(&spec, "DELETE", "/auth/sessions/abc", 500).is_none());
+        assert!(response_schema(&spec, "DELETE", "/auth/sessions/", 404).is_none());
+        assert!(response_schema(&spec, "GET", "/auth/sessions/abc", 404).is_none());
+    }
+
+    #[test]
+    fn should_accept_documented_body() {
+        let spec = spec();
+        let schema = response_schema(&spec, "DELETE", "/auth/sessions/abc", 404).unwrap();
+
+        let body = json!({ "kind": "not_found", "message": "not found", "trace_id": null });
+
+        assert!(check(&spec, schema, &body).is_empty());
+    }
+
+    #[test]
+    fn should_report_each_deviation_with_its_location() {
+        let spec = spec();
+        let schema = response_schema(&spec, "DELETE", "/auth/sessions/abc", 404).unwrap();
+
+        let body = json!({ "kind": "gone", "trace_id": 7, "retry": true });
+
+        let mut problems = check(&spec, schema, &body);
+        problems.sort();
+
+        assert_eq!(
+            problems,
+            [
+                "$.kind: \"gone\" is not one of the documented values",
+                "$.message: required but missing",
+                "$.retry: not in the schema",
+                "$.trace_id: expected string or null, got number",
+            ]
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! Fixture `setup` seeding and `expect.db` assertions, both against the
+//! database of the service that answers the request.
+//!
+//! `setup` loads named datasets from `madome_testing::fixture` and then any
+//! inline SQL. Each `expect.db` assertion becomes one `SELECT count(*)`.
+//! Columns are compared as text (`"col"::text = $1`), so fixtures write UUIDs,
+//! numbers and booleans the way Postgres prints them and one parameter type
+//! fits every column.
+
+use serde_json::Value;
+
+use crate::fixture::DbExpect;
+
+/// `SELECT count(*)` for `expect` and its text parameters, in order.
+///
+/// Table and column names must be plain lowercase identifiers; anything else
+/// is rejected rather than quoted.
+pub fn count_query(expect: &DbExpect) -> Result<(String, Vec<String>), String> {
+    let mut sql = format!("SELECT count(*) FROM {}", identifier(&expect.table)?);
+    let mut params = Vec::new();
+    for (i, (column, value)) in expect.filter.iter().enumerate() {
+        sql.push_str(if i == 0 { " WHERE " } else { " AND " });
+        let column = identifier(column)?;
+        match value {
+            Value::Null => sql.push_str(&format!("{column} IS NULL")),
+            Value::String(s) => {
+                params.push(s.clone());
+                sql.push_str(&format!("{column}::text = ${}", params.len()));
+            }
+            Value::Number(_) | Value::Bool(_) => {
+                params.push(value.to_string());
+                sql.push_str(&format!("{column}::text = ${}", params.len()));
+            }
+            Value::Array(_) | Value::Object(_) => {
+                return Err(format!("{column}: only scalar values can be matched"));
+            }
+        }
+    }
+    Ok((sql, params))
+}
+
+/// Describe `expect` the way failures are reported, e.g. `taste_books where book_id = 42`.
+pub fn describe(expect: &DbExpect) -> String {
+    if expect.filter.is_empty() {
+        return expect.table.clone();
+    }
+    let filter: Vec<_> = expect
+        .filter
+        .iter()
+        .map(|(column, value)| format!("{column} = {value}"))
+        .collect();
+    format!("{} where {}", expect.table, filter.join(" and "))
+}
+
+fn identifier(name: &str) -> Result<String, String> {
+    let valid = name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if valid {
+        Ok(format!("\"{name}\""))
+    } else {
+        Err(format!("{name:?} is not a plain table or column name"))
+    }
+}
+
+// sqlx is only compiled in with a service feature.
+#[cfg(feature = "auth")]
+pub use postgres::{check, seed};
+
+#[cfg(feature = "auth")]
+mod postgres {
+    use sqlx::PgPool;
+
+    use super::{count_query, describe};
+    use crate::fixture::{DbExpect, Setup};
+
+    /// Load `setup` for a fixture of `service`: its datasets in order, then its SQL.
+    pub async fn seed(pool: &PgPool, service: &str, setup: &Setup) -> Result<(), String> {
+        for name in &setup.fixtures {
+            let dataset = madome_testing::fixture::dataset(name)
+                .ok_or_else(|| format!("setup: unknown dataset {name:?}"))?;
+            if dataset.service != service {
+                return Err(format!(
+                    "setup: dataset {name:?} is for {}, not {service}",
+                    dataset.service
+                ));
+            }
+            sqlx::raw_sql(dataset.sql)
+                .execute(pool)
+                .await
+                .map_err(|e| format!("setup: dataset {name:?}: {e}"))?;
+        }
+        if let Some(sql) = &setup.sql {
+            sqlx::raw_sql(sql)
+                .execute(pool)
+                .await
+                .map_err(|e| format!("setup: sql: {e}"))?;
+        }
+        Ok(())
+    }
+
+    /// Run every assertion; one message per assertion that did not hold.
+    pub async fn check(pool: &PgPool, expects: &[DbExpect]) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        for expect in expects {
+            let (sql, params) = match count_query(expect) {
+                Ok(query) => query,
+                Err(e) => {
+                    mismatches.push(e);
+                    continue;
+                }
+            };
+            let mut query = sqlx::query_scalar::<_, i64>(&sql);
+            for param in params {
+                query = query.bind(param);
+            }
+            match query.fetch_one(pool).await {
+                Ok(count) if count == expect.count => {}
+                Ok(count) => mismatches.push(format!(
+                    "{}: expected {} row(s), found {count}",
+                    describe(expect),
+                    expect.count
+                )),
+                Err(e) => mismatches.push(format!("{}: {e}", describe(expect))),
+            }
+        }
+        mismatches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn expect(value: Value) -> DbExpect {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn should_compare_columns_as_text_in_column_order() {
+        let e = expect(json!({
+            "table": "taste_books",
+            "where": {"user_id": "0190a0b0-0000-7000-8000-000000000001", "book_id": 42, "is_dislike": false, "deleted_at": null},
+        }));
+        let (sql, params) = count_query(&e).unwrap();
+        assert_eq!(
+            sql,
+            "SELECT count(*) FROM \"taste_books\" WHERE \"book_id\"::text = $1 \
+             AND \"deleted_at\" IS NULL AND \"is_dislike\"::text = $2 AND \"user_id\"::text = $3"
+        );
+        assert_eq!(
+            params,
+            ["42", "false", "0190a0b0-0000-7000-8000-000000000001"]
+        );
+        assert_eq!(e.count, 1);
+    }
+
+    #[test]
+    fn should_reject_identifier_that_needs_quoting() {
+        let e = expect(json!({"table": "users; drop table users", "count": 0}));
+        assert!(count_query(&e).is_err());
+        let e = expect(json!({"table": "users", "where": {"Email": "a"}}));
+        assert!(count_query(&e).is_err());
+    }
+
+    #[test]
+    fn should_describe_assertion() {
+        let e = expect(json!({"table": "sessions", "where": {"user_id": "u-1"}, "count": 0}));
+        assert_eq!(describe(&e), "sessions where user_id = \"u-1\"");
+    }
+}
@@ -3,7 +3,7 @@
 //! Each fixture file at `contracts/http/{service}/{id}.json` describes one HTTP
 //! assertion: the request to send and the expected response status.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -19,10 +19,29 @@ pub struct Fixture {
     pub id: String,
     /// Human-readable description shown in test output.
     pub description: String,
+    /// Data to seed before the request (Docker mode only).
+    #[serde(default)]
+    pub setup: Setup,
     pub request: Request,
     pub expect: Expect,
 }
 
+/// Seed data loaded into the service's database before the request.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Setup {
+    /// Named datasets from `madome_testing::fixture::DATASETS`, loaded in order.
+    #[serde(default)]
+    pub fixtures: Vec<String>,
+    /// SQL run after the datasets.
+    pub sql: Option<String>,
+}
+
+impl Setup {
+    pub fn is_empty(&self) -> bool {
+        self.fixtures.is_empty() && self.sql.is_none()
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Request {
     pub method: String,
@@ -39,6 +58,28 @@ pub struct Expect {
     /// Expected response headers (subset match — extra headers are allowed).
     #[serde(default)]
     pub headers: HashMap<String, String>,
+    /// Latency budget: the response, body included, must arrive within this
+    /// many milliseconds.
+    pub max_duration_ms: Option<u64>,
+    /// Rows expected in the service's database afterwards (Docker mode only).
+    #[serde(default)]
+    pub db: Vec<DbExpect>,
+}
+
+/// One `expect.db` assertion: how many rows of `table` match `where`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DbExpect {
+    pub table: String,
+    /// Column → value, compared as text; `null` matches SQL `NULL`.
+    #[serde(default, rename = "where")]
+    pub filter: BTreeMap<String, serde_json::Value>,
+    /// Matching rows; default 1 ("row exists").
+    #[serde(default = "one_row")]
+    pub count: i64,
+}
+
+fn one_row() -> i64 {
+    1
 }
 
 /// Load all fixture files from `{workspace_root}/contracts/http/`, optionally
@@ -1,6 +1,12 @@
 pub mod config;
+pub mod db;
 pub mod docker;
 pub mod fixture;
+#[cfg(feature = "auth")]
+pub mod redis_checks;
 pub mod reporter;
 pub mod runner;
+pub mod scenario;
+pub mod schema;
 pub mod services;
+pub mod webauthn_client;
@@ -12,8 +12,9 @@
 //!
 //! ## Docker mode (service feature flags)
 //!
-//! Spins up PostgreSQL + Redis containers, runs the service in-process,
-//! then always tears the containers down:
+//! Spins up PostgreSQL + Redis containers, runs every compiled-in service
+//! in-process against them, runs each service's fixtures, the cross-service
+//! scenarios and the Redis checks, and always tears the containers down:
 //!
 //! ```bash
 //! cargo run -p contract-harness --features auth
@@ -29,7 +30,9 @@ use anyhow::Result;
 #[cfg(feature = "auth")]
 mod docker_mode {
     use anyhow::{Result, anyhow};
-    use contract_harness::{config::ContractHarnessConfig, docker::DockerOrchestrator, services};
+    use contract_harness::{
+        config::ContractHarnessConfig, docker::DockerOrchestrator, redis_checks, scenario, services,
+    };
 
     pub async fn run() -> Result<()> {
         dotenv::dotenv().ok();
@@ -69,18 +72,32 @@ mod docker_mode {
         std::process::exit(if all_passed { 0 } else { 1 });
     }
 
+    /// Start every compiled-in service, then run each service's fixtures and
+    /// the cross-service scenarios against them, and the Redis checks.
     async fn run_services(
         infra: &services::InfraUrls,
         config: &ContractHarnessConfig,
         workspace_root: &std::path::Path,
     ) -> Result<bool> {
+        // Dependencies first: a service reads its peers' gRPC URLs from the
+        // cluster when it starts.
+        let mut cluster = services::Cluster::default();
+        #[cfg(feature = "auth")]
+        {
+            let auth = services::auth::start(infra, config, &cluster).await?;
+            cluster.push(auth);
+        }
+
         let mut all_passed = true;
 
         #[cfg(feature = "auth")]
         {
-            all_passed &= services::auth::run(infra, config, workspace_root).await?;
+            all_passed &= services::auth::run(&cluster, workspace_root).await?;
         }
 
+        all_passed &= scenario::run_all(&cluster, &config.webauthn_origin, workspace_root).await?;
+        all_passed &= redis_checks::run_all(&infra.redis_url).await?;
+
         Ok(all_passed)
     }
 }
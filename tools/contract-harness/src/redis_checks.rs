@@ -0,0 +1,173 @@
+//! Checks of the `madome_core` primitives built on Redis (locks and
+//! cross-replica wakeups), run in Docker mode against the run's `redis:8`
+//! container after the scenarios.
+//!
+//! The container is shared with the services under test, so every key and
+//! channel used here starts with `contract:` and never meets one of theirs.
+
+use std::time::Duration;
+
+use anyhow::{Context, Result, ensure};
+use madome_core::jobs::JobLock;
+use madome_core::lock::{DistributedLock, LockGuard};
+use madome_core::wakeup::RedisWakeups;
+
+use crate::reporter::Reporter;
+
+const TTL: Duration = Duration::from_millis(300);
+const WAKEUP_CHANNEL: &str = "contract:wakeups";
+
+/// Run every check against `redis_url`; `Ok(false)` if any failed.
+pub async fn run_all(redis_url: &str) -> Result<bool> {
+    let client = redis::Client::open(redis_url)?;
+    let conn = client.get_multiplexed_async_connection().await?;
+    // Two replicas of a service listening on the same channel.
+    let replicas = (
+        RedisWakeups::connect(client.clone(), WAKEUP_CHANNEL).await?,
+        RedisWakeups::connect(client, WAKEUP_CHANNEL).await?,
+    );
+    let lock = DistributedLock::new(conn.clone());
+    let mut rep = Reporter::new();
+
+    rep.check(
+        "redis/lock_held_until_released",
+        "a held lock is refused to others until released",
+        lock_held_until_released(&lock).await,
+    );
+    rep.check(
+        "redis/lock_taken_over_after_expiry",
+        "an expired holder can neither release nor extend the new holder's lock",
+        lock_taken_over_after_expiry(&lock).await,
+    );
+    rep.check(
+        "redis/lock_auto_extended",
+        "an auto-extended lock outlives its TTL",
+        lock_auto_extended(&lock).await,
+    );
+    rep.check(
+        "redis/lock_lost_once_deleted",
+        "an auto-extended lock reports itself lost once its key is deleted",
+        lock_lost_once_deleted(&lock, conn).await,
+    );
+    rep.check(
+        "redis/job_tick_claimed_once",
+        "each job tick is claimed by one replica only",
+        job_tick_claimed_once(&lock).await,
+    );
+    rep.check(
+        "redis/wakeup_every_replica",
+        "a wakeup reaches subscribers on every replica",
+        wakeup_every_replica(&replicas).await,
+    );
+    rep.check(
+        "redis/wakeup_other_keys",
+        "a wakeup leaves other keys' subscribers waiting",
+        wakeup_other_keys(&replicas).await,
+    );
+
+    rep.print_summary();
+    Ok(rep.all_passed())
+}
+
+async fn acquire(lock: &DistributedLock, key: &str) -> Result<LockGuard> {
+    lock.try_acquire(key, TTL)
+        .await?
+        .with_context(|| format!("{key} is already held"))
+}
+
+async fn lock_held_until_released(lock: &DistributedLock) -> Result<()> {
+    let key = "contract:lock:relay";
+    let guard = acquire(lock, key).await?;
+    ensure!(
+        lock.try_acquire(key, TTL).await?.is_none(),
+        "acquired while held"
+    );
+
+    ensure!(guard.release().await?, "release reported no lock");
+    acquire(lock, key).await?.release().await?;
+    Ok(())
+}
+
+async fn lock_taken_over_after_expiry(lock: &DistributedLock) -> Result<()> {
+    let key = "contract:lock:retention";
+    let stale = acquire(lock, key).await?;
+    tokio::time::sleep(TTL * 2).await;
+    let current = acquire(lock, key).await?;
+
+    ensure!(!stale.extend().await?, "stale holder extended the lock");
+    ensure!(!stale.release().await?, "stale holder released the lock");
+    ensure!(
+        lock.try_acquire(key, TTL).await?.is_none(),
+        "lock free after the stale release"
+    );
+    ensure!(current.release().await?, "release reported no lock");
+    Ok(())
+}
+
+async fn lock_auto_extended(lock: &DistributedLock) -> Result<()> {
+    let key = "contract:lock:outbox";
+    let guard = acquire(lock, key).await?.auto_extend();
+    tokio::time::sleep(TTL * 3).await;
+
+    ensure!(
+        lock.try_acquire(key, TTL).await?.is_none(),
+        "lock expired despite auto-extension"
+    );
+    ensure!(!guard.is_lost(), "lock reported lost");
+    ensure!(guard.release().await?, "release reported no lock");
+    Ok(())
+}
+
+async fn lock_lost_once_deleted(
+    lock: &DistributedLock,
+    mut conn: redis::aio::MultiplexedConnection,
+) -> Result<()> {
+    let key = "contract:lock:rollup";
+    let guard = acquire(lock, key).await?.auto_extend();
+    let () = redis::cmd("DEL").arg(key).query_async(&mut conn).await?;
+    tokio::time::sleep(TTL).await;
+
+    ensure!(guard.is_lost(), "deleted lock not reported lost");
+    Ok(())
+}
+
+async fn job_tick_claimed_once(lock: &DistributedLock) -> Result<()> {
+    let other_replica = lock.clone();
+    ensure!(
+        JobLock::try_acquire(lock, "contract:jobs:rollup:1", TTL).await?,
+        "first claim refused"
+    );
+    ensure!(
+        !JobLock::try_acquire(&other_replica, "contract:jobs:rollup:1", TTL).await?,
+        "tick claimed twice"
+    );
+    ensure!(
+        JobLock::try_acquire(&other_replica, "contract:jobs:rollup:2", TTL).await?,
+        "next tick refused"
+    );
+    Ok(())
+}
+
+async fn wakeup_every_replica((a, b): &(RedisWakeups, RedisWakeups)) -> Result<()> {
+    let mut on_a = a.subscribe("user-1");
+    let mut on_b = b.subscribe("user-1");
+
+    a.wake("user-1").await?;
+    let timeout = Duration::from_secs(2);
+    tokio::time::timeout(timeout, on_a.woken())
+        .await
+        .context("waking replica not woken")?;
+    tokio::time::timeout(timeout, on_b.woken())
+        .await
+        .context("other replica not woken")?;
+    Ok(())
+}
+
+async fn wakeup_other_keys((a, b): &(RedisWakeups, RedisWakeups)) -> Result<()> {
+    let mut other = b.subscribe("user-2");
+    a.wake("user-1").await?;
+
+    let woken = tokio::time::timeout(Duration::from_millis(300), other.woken()).await;
+    ensure!(woken.is_err(), "woken by another key");
+    Ok(())
+}
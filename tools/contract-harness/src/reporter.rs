@@ -1,10 +1,15 @@
 //! Test result reporter — formats PASS/FAIL output and prints a summary.
 
+use std::time::Duration;
+
 use crate::{fixture::Fixture, runner::RunResult};
 
 pub struct Reporter {
     passed: usize,
     failed: usize,
+    skipped: usize,
+    /// Response time of every request that got an answer.
+    durations: Vec<Duration>,
 }
 
 impl Default for Reporter {
@@ -18,10 +23,13 @@ impl Reporter {
         Self {
             passed: 0,
             failed: 0,
+            skipped: 0,
+            durations: Vec::new(),
         }
     }
 
     pub fn record(&mut self, fixture: &Fixture, result: RunResult) {
+        self.durations.extend(result.duration);
         if result.passed() {
             self.passed += 1;
             println!(
@@ -49,17 +57,107 @@ impl Reporter {
                 for mismatch in &result.header_mismatches {
                     println!("        header: {mismatch}");
                 }
+                for mismatch in &result.body_mismatches {
+                    println!("        body: {mismatch}");
+                }
+                for mismatch in &result.db_mismatches {
+                    println!("        db: {mismatch}");
+                }
+                if result.over_budget()
+                    && let (Some(duration), Some(max)) = (result.duration, result.max_duration)
+                {
+                    println!(
+                        "        time: {} ms, budget {} ms",
+                        duration.as_millis(),
+                        max.as_millis()
+                    );
+                }
+            }
+        }
+    }
+
+    /// Record a check that is not a fixture request, failing with its error.
+    pub fn check(&mut self, label: &str, description: &str, result: anyhow::Result<()>) {
+        match result {
+            Ok(()) => {
+                self.passed += 1;
+                println!("PASS  [{label}] {description}");
+            }
+            Err(err) => {
+                self.failed += 1;
+                println!("FAIL  [{label}] {description}");
+                println!("        error: {err:#}");
             }
         }
     }
 
+    /// Note a check that could not run in this build; it neither passes nor fails.
+    pub fn skip(&mut self, label: &str, description: &str, reason: &str) {
+        self.skipped += 1;
+        println!("SKIP  [{label}] {description} ({reason})");
+    }
+
     pub fn print_summary(&self) {
         println!();
         println!("────────────────────────────────────────────────────");
-        println!("Results: {} passed, {} failed", self.passed, self.failed);
+        if self.skipped == 0 {
+            println!("Results: {} passed, {} failed", self.passed, self.failed);
+        } else {
+            println!(
+                "Results: {} passed, {} failed, {} skipped",
+                self.passed, self.failed, self.skipped
+            );
+        }
+
+        let mut durations = self.durations.clone();
+        durations.sort();
+        if let (Some(p50), Some(p95), Some(max)) = (
+            percentile(&durations, 50),
+            percentile(&durations, 95),
+            durations.last(),
+        ) {
+            println!(
+                "Timing:  p50 {} ms, p95 {} ms, max {} ms over {} request(s)",
+                p50.as_millis(),
+                p95.as_millis(),
+                max.as_millis(),
+                durations.len()
+            );
+        }
     }
 
     pub fn all_passed(&self) -> bool {
         self.failed == 0
     }
 }
+
+/// Nearest-rank `p`th percentile of `sorted` (ascending); `None` when empty.
+fn percentile(sorted: &[Duration], p: usize) -> Option<Duration> {
+    let rank = (sorted.len() * p).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(values: &[u64]) -> Vec<Duration> {
+        values.iter().copied().map(Duration::from_millis).collect()
+    }
+
+    #[test]
+    fn should_take_nearest_rank_percentile() {
+        let sorted = ms(&(1..=20).collect::<Vec<_>>());
+        assert_eq!(percentile(&sorted, 50), Some(Duration::from_millis(10)));
+        assert_eq!(percentile(&sorted, 95), Some(Duration::from_millis(19)));
+        assert_eq!(percentile(&sorted, 100), Some(Duration::from_millis(20)));
+    }
+
+    #[test]
+    fn should_use_only_sample_for_single_request() {
+        let sorted = ms(&[42]);
+        assert_eq!(percentile(&sorted, 50), Some(Duration::from_millis(42)));
+        assert_eq!(percentile(&sorted, 95), Some(Duration::from_millis(42)));
+        assert_eq!(percentile(&[], 50), None);
+    }
+}
@@ -1,6 +1,10 @@
 //! HTTP request runner — sends one fixture request and captures the response.
 
+use std::time::{Duration, Instant};
+
 use reqwest::Client;
+use reqwest::header::HeaderMap;
+use serde_json::Value;
 
 use crate::fixture::Fixture;
 
@@ -10,21 +14,50 @@ pub struct RunResult {
     pub actual_status: Option<u16>,
     /// Headers that were expected but missing or had the wrong value.
     pub header_mismatches: Vec<String>,
+    /// Ways the body deviates from the documented response schema.
+    pub body_mismatches: Vec<String>,
+    /// `expect.db` assertions that did not hold.
+    pub db_mismatches: Vec<String>,
+    /// From sending the request to the last byte of the body.
+    pub duration: Option<Duration>,
+    /// `expect.max_duration_ms`, if the fixture sets one.
+    pub max_duration: Option<Duration>,
     /// Set when the request could not be sent (e.g. connection refused).
     pub error: Option<String>,
+    /// What came back, for scenarios that carry values into later requests.
+    pub response: Option<Response>,
+}
+
+/// Headers and raw body of a received response.
+pub struct Response {
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
 }
 
 impl RunResult {
+    /// The response took longer than the fixture allows.
+    pub fn over_budget(&self) -> bool {
+        matches!((self.duration, self.max_duration), (Some(d), Some(max)) if d > max)
+    }
+
     pub fn passed(&self) -> bool {
         self.error.is_none()
             && self.actual_status == Some(self.expected_status)
             && self.header_mismatches.is_empty()
+            && self.body_mismatches.is_empty()
+            && self.db_mismatches.is_empty()
+            && !self.over_budget()
     }
 }
 
 pub struct Runner {
     client: Client,
     base_url: String,
+    /// OpenAPI document (as JSON) to check response bodies against.
+    spec: Option<Value>,
+    /// The service's database, for `expect.db`.
+    #[cfg(feature = "auth")]
+    db: Option<sqlx::PgPool>,
 }
 
 impl Runner {
@@ -32,11 +65,30 @@ impl Runner {
         Self {
             client: Client::new(),
             base_url: base_url.trim_end_matches('/').to_owned(),
+            spec: None,
+            #[cfg(feature = "auth")]
+            db: None,
         }
     }
 
+    /// Also check each response body against the schema `spec` documents for
+    /// its path and status. Statuses without a JSON schema are not checked.
+    pub fn with_spec(mut self, spec: Value) -> Self {
+        self.spec = Some(spec);
+        self
+    }
+
+    /// Seed `setup` into and check `expect.db` against `pool`. Without a
+    /// database, fixtures with `setup` fail and `expect.db` is not checked.
+    #[cfg(feature = "auth")]
+    pub fn with_database(mut self, pool: sqlx::PgPool) -> Self {
+        self.db = Some(pool);
+        self
+    }
+
     pub async fn run(&self, fixture: &Fixture) -> RunResult {
         let url = format!("{}{}", self.base_url, fixture.request.path);
+        let max_duration = fixture.expect.max_duration_ms.map(Duration::from_millis);
 
         let method =
             match reqwest::Method::from_bytes(fixture.request.method.to_uppercase().as_bytes()) {
@@ -46,11 +98,30 @@ impl Runner {
                         expected_status: fixture.expect.status,
                         actual_status: None,
                         header_mismatches: Vec::new(),
+                        body_mismatches: Vec::new(),
+                        db_mismatches: Vec::new(),
+                        duration: None,
+                        max_duration,
                         error: Some(format!("unknown HTTP method: {}", fixture.request.method)),
+                        response: None,
                     };
                 }
             };
 
+        if let Err(error) = self.seed(fixture).await {
+            return RunResult {
+                expected_status: fixture.expect.status,
+                actual_status: None,
+                header_mismatches: Vec::new(),
+                body_mismatches: Vec::new(),
+                db_mismatches: Vec::new(),
+                duration: None,
+                max_duration,
+                error: Some(error),
+                response: None,
+            };
+        }
+
         let mut req = self.client.request(method, &url);
         for (k, v) in &fixture.request.headers {
             req = req.header(k, v);
@@ -59,6 +130,7 @@ impl Runner {
             req = req.json(body);
         }
 
+        let started = Instant::now();
         match req.send().await {
             Ok(resp) => {
                 let actual_status = resp.status().as_u16();
@@ -83,19 +155,90 @@ impl Runner {
                     }
                 }
 
+                let body = resp.bytes().await.unwrap_or_default();
+                let duration = started.elapsed();
+                let body_mismatches = self.check_body(fixture, actual_status, &body);
+                let db_mismatches = self.check_db(fixture).await;
+
                 RunResult {
                     expected_status: fixture.expect.status,
                     actual_status: Some(actual_status),
                     header_mismatches,
+                    body_mismatches,
+                    db_mismatches,
+                    duration: Some(duration),
+                    max_duration,
                     error: None,
+                    response: Some(Response {
+                        headers,
+                        body: body.to_vec(),
+                    }),
                 }
             }
             Err(e) => RunResult {
                 expected_status: fixture.expect.status,
                 actual_status: None,
                 header_mismatches: Vec::new(),
+                body_mismatches: Vec::new(),
+                db_mismatches: Vec::new(),
+                duration: None,
+                max_duration,
                 error: Some(e.to_string()),
+                response: None,
             },
         }
     }
+
+    fn check_body(&self, fixture: &Fixture, status: u16, body: &[u8]) -> Vec<String> {
+        let Some(spec) = &self.spec else {
+            return Vec::new();
+        };
+        let Some(schema) = client_gen::validate::response_schema(
+            spec,
+            &fixture.request.method,
+            &fixture.request.path,
+            status,
+        ) else {
+            return Vec::new();
+        };
+        match serde_json::from_slice(body) {
+            Ok(value) => client_gen::validate::check(spec, schema, &value),
+            Err(e) => vec![format!("$: not JSON ({e})")],
+        }
+    }
+
+    #[cfg(feature = "auth")]
+    async fn seed(&self, fixture: &Fixture) -> Result<(), String> {
+        if fixture.setup.is_empty() {
+            return Ok(());
+        }
+        match &self.db {
+            Some(pool) => crate::db::seed(pool, &fixture.service, &fixture.setup).await,
+            None => Err("setup needs the service's database (Docker mode)".to_owned()),
+        }
+    }
+
+    #[cfg(not(feature = "auth"))]
+    async fn seed(&self, fixture: &Fixture) -> Result<(), String> {
+        if fixture.setup.is_empty() {
+            Ok(())
+        } else {
+            Err("setup needs the service's database (Docker mode)".to_owned())
+        }
+    }
+
+    #[cfg(feature = "auth")]
+    async fn check_db(&self, fixture: &Fixture) -> Vec<String> {
+        match &self.db {
+            Some(pool) if !fixture.expect.db.is_empty() => {
+                crate::db::check(pool, &fixture.expect.db).await
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    #[cfg(not(feature = "auth"))]
+    async fn check_db(&self, _fixture: &Fixture) -> Vec<String> {
+        Vec::new()
+    }
 }
@@ -0,0 +1,453 @@
+//! Cross-service scenarios.
+//!
+//! A scenario file at `contracts/scenarios/{id}.json` is an ordered list of
+//! steps, each one a fixture request sent to the service it names. A step can
+//! capture values from its response (a cookie, a header, a JSON field) and
+//! later steps use them as `{{name}}` in their path, headers or body — e.g.
+//! sign in through auth, read the identity from `GET /auth/token`, then call
+//! users with the `x-madome-user-*` headers the gateway would forward.
+//!
+//! Placeholders are also filled in `expect.db` values, so a step can assert
+//! rows keyed by an id an earlier step captured.
+//!
+//! A step marked `"webauthn": "register"` or `"authenticate"` has its body
+//! written by a [`SoftAuthenticator`] answering the challenge in the previous
+//! step's response, so passkey flows can run end to end. The credential lives
+//! for the whole scenario and its id is available as `{{passkey_credential_id}}`.
+//!
+//! Steps run in order and a scenario stops at its first failing step, since
+//! the rest depend on it.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::fixture::{DbExpect, Expect, Fixture, Request, Setup};
+use crate::reporter::Reporter;
+use crate::runner::{Response, RunResult};
+use crate::services::Cluster;
+use crate::webauthn_client::SoftAuthenticator;
+
+/// An ordered multi-service flow loaded from a scenario file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scenario {
+    /// Unique identifier (matches the filename stem).
+    pub id: String,
+    pub description: String,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Step {
+    /// Service that receives the request (`auth`, `users`, ...).
+    pub service: String,
+    /// Data to seed into that service's database first.
+    #[serde(default)]
+    pub setup: Setup,
+    pub request: Request,
+    pub expect: Expect,
+    /// Variable name → where to read it from the response.
+    #[serde(default)]
+    pub capture: BTreeMap<String, Capture>,
+    /// Send the software authenticator's answer to the previous step's
+    /// challenge as the request body.
+    pub webauthn: Option<WebauthnCeremony>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebauthnCeremony {
+    /// Answer a `CreationChallengeResponse`.
+    Register,
+    /// Answer a `RequestChallengeResponse`.
+    Authenticate,
+}
+
+/// Source of a captured value, written `cookie:NAME`, `header:NAME` or
+/// `json:/json/pointer` in the fixture.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Capture {
+    /// Value of a `Set-Cookie` for this cookie name.
+    Cookie(String),
+    Header(String),
+    /// JSON pointer into the response body; non-string values are rendered as JSON.
+    Json(String),
+}
+
+impl TryFrom<String> for Capture {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        match source.split_once(':') {
+            Some(("cookie", name)) => Ok(Self::Cookie(name.to_owned())),
+            Some(("header", name)) => Ok(Self::Header(name.to_ascii_lowercase())),
+            Some(("json", pointer)) => Ok(Self::Json(pointer.to_owned())),
+            _ => Err(format!(
+                "capture {source:?} must start with cookie:, header: or json:"
+            )),
+        }
+    }
+}
+
+impl Capture {
+    /// The captured value, or `None` when the response does not carry it.
+    pub fn extract(&self, response: &Response) -> Option<String> {
+        match self {
+            Self::Cookie(name) => response
+                .headers
+                .get_all("set-cookie")
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .find_map(|cookie| {
+                    let pair = cookie.split(';').next()?;
+                    let (key, value) = pair.split_once('=')?;
+                    (key.trim() == name).then(|| value.trim().to_owned())
+                }),
+            Self::Header(name) => response
+                .headers
+                .get(name.as_str())
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_owned),
+            Self::Json(pointer) => {
+                let body: Value = serde_json::from_slice(&response.body).ok()?;
+                match body.pointer(pointer)? {
+                    Value::String(s) => Some(s.clone()),
+                    other => Some(other.to_string()),
+                }
+            }
+        }
+    }
+}
+
+impl Scenario {
+    /// Every service the scenario talks to.
+    pub fn services(&self) -> BTreeSet<&str> {
+        self.steps.iter().map(|s| s.service.as_str()).collect()
+    }
+}
+
+impl Step {
+    /// The step as a plain fixture, with `{{name}}` placeholders filled from `vars`.
+    ///
+    /// Fails on a placeholder no earlier step captured.
+    pub fn to_fixture(
+        &self,
+        scenario: &Scenario,
+        index: usize,
+        vars: &BTreeMap<String, String>,
+    ) -> Result<Fixture, String> {
+        let headers = self
+            .request
+            .headers
+            .iter()
+            .map(|(k, v)| Ok((k.clone(), substitute(v, vars)?)))
+            .collect::<Result<_, String>>()?;
+        let body = self
+            .request
+            .body
+            .as_ref()
+            .map(|b| substitute_json(b, vars))
+            .transpose()?;
+
+        Ok(Fixture {
+            service: self.service.clone(),
+            id: format!("{}#{}", scenario.id, index + 1),
+            description: scenario.description.clone(),
+            setup: self.setup.clone(),
+            request: Request {
+                method: self.request.method.clone(),
+                path: substitute(&self.request.path, vars)?,
+                headers,
+                body,
+            },
+            expect: Expect {
+                db: self
+                    .expect
+                    .db
+                    .iter()
+                    .map(|e| {
+                        Ok(DbExpect {
+                            filter: e
+                                .filter
+                                .iter()
+                                .map(|(k, v)| Ok((k.clone(), substitute_json(v, vars)?)))
+                                .collect::<Result<_, String>>()?,
+                            ..e.clone()
+                        })
+                    })
+                    .collect::<Result<_, String>>()?,
+                ..self.expect.clone()
+            },
+        })
+    }
+}
+
+/// Replace every `{{name}}` in `template` with its captured value.
+pub fn substitute(template: &str, vars: &BTreeMap<String, String>) -> Result<String, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + len].trim();
+        let value = vars
+            .get(name)
+            .ok_or_else(|| format!("{{{{{name}}}}} was not captured by an earlier step"))?;
+        out.push_str(&rest[..start]);
+        out.push_str(value);
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn substitute_json(value: &Value, vars: &BTreeMap<String, String>) -> Result<Value, String> {
+    Ok(match value {
+        Value::String(s) => Value::String(substitute(s, vars)?),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|v| substitute_json(v, vars))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), substitute_json(v, vars)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+/// Load every scenario under `{workspace_root}/contracts/scenarios/`.
+///
+/// A missing directory means no scenarios.
+pub fn load_all(workspace_root: &Path) -> Result<Vec<Scenario>> {
+    let dir = workspace_root.join("contracts/scenarios");
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut scenarios = Vec::new();
+    for entry in fs::read_dir(&dir)
+        .with_context(|| format!("cannot read {}", dir.display()))?
+        .filter_map(|e| e.ok())
+    {
+        let path = entry.path();
+        if path.extension().map(|e| e == "json").unwrap_or(false) {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("cannot read {}", path.display()))?;
+            let scenario: Scenario = serde_json::from_str(&content)
+                .with_context(|| format!("invalid scenario JSON in {}", path.display()))?;
+            scenarios.push(scenario);
+        }
+    }
+
+    scenarios.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(scenarios)
+}
+
+/// Run every scenario against the started services.
+///
+/// A scenario that needs a service missing from `cluster` (its feature is not
+/// compiled in) is skipped. Returns `true` if nothing failed.
+/// `webauthn_origin` is the relying-party origin the services were started with.
+pub async fn run_all(
+    cluster: &Cluster,
+    webauthn_origin: &str,
+    workspace_root: &Path,
+) -> Result<bool> {
+    let scenarios = load_all(workspace_root)?;
+    if scenarios.is_empty() {
+        return Ok(true);
+    }
+
+    let mut rep = Reporter::new();
+    for scenario in &scenarios {
+        let missing: Vec<_> = scenario
+            .services()
+            .into_iter()
+            .filter(|s| cluster.get(s).is_none())
+            .collect();
+        if !missing.is_empty() {
+            rep.skip(
+                &format!("scenarios/{}", scenario.id),
+                &scenario.description,
+                &format!("needs {}", missing.join(", ")),
+            );
+            continue;
+        }
+        run(cluster, scenario, webauthn_origin, &mut rep).await;
+    }
+
+    rep.print_summary();
+    Ok(rep.all_passed())
+}
+
+async fn run(cluster: &Cluster, scenario: &Scenario, webauthn_origin: &str, rep: &mut Reporter) {
+    let mut vars = BTreeMap::new();
+    let mut authenticator = SoftAuthenticator::new(webauthn_origin);
+    vars.insert(
+        "passkey_credential_id".to_owned(),
+        authenticator.credential_id(),
+    );
+    let mut previous_body: Option<Value> = None;
+
+    for (index, step) in scenario.steps.iter().enumerate() {
+        let runner = cluster
+            .runner(&step.service)
+            .expect("run_all skips scenarios with services not started");
+
+        let prepared = step
+            .to_fixture(scenario, index, &vars)
+            .and_then(|mut fixture| {
+                if let Some(ceremony) = step.webauthn {
+                    let challenge = previous_body
+                        .as_ref()
+                        .ok_or("webauthn step has no challenge: previous step returned no JSON")?;
+                    let answer = match ceremony {
+                        WebauthnCeremony::Register => authenticator.register(challenge),
+                        WebauthnCeremony::Authenticate => authenticator.authenticate(challenge),
+                    };
+                    fixture.request.body = Some(answer.map_err(|e| e.to_string())?);
+                }
+                Ok(fixture)
+            });
+
+        let (fixture, mut result) = match prepared {
+            Ok(fixture) => {
+                let result = runner.run(&fixture).await;
+                (fixture, result)
+            }
+            Err(error) => {
+                let fixture = Fixture {
+                    service: step.service.clone(),
+                    id: format!("{}#{}", scenario.id, index + 1),
+                    description: scenario.description.clone(),
+                    setup: step.setup.clone(),
+                    request: step.request.clone(),
+                    expect: step.expect.clone(),
+                };
+                (fixture, failure(step, error))
+            }
+        };
+
+        if result.passed()
+            && let Some(response) = &result.response
+        {
+            for (name, capture) in &step.capture {
+                match capture.extract(response) {
+                    Some(value) => {
+                        vars.insert(name.clone(), value);
+                    }
+                    None => result
+                        .body_mismatches
+                        .push(format!("capture {name}: {capture:?} not in response")),
+                }
+            }
+        }
+
+        previous_body = result
+            .response
+            .as_ref()
+            .and_then(|r| serde_json::from_slice(&r.body).ok());
+
+        let passed = result.passed();
+        rep.record(&fixture, result);
+        if !passed {
+            return;
+        }
+    }
+}
+
+fn failure(step: &Step, error: String) -> RunResult {
+    RunResult {
+        expected_status: step.expect.status,
+        actual_status: None,
+        header_mismatches: Vec::new(),
+        body_mismatches: Vec::new(),
+        db_mismatches: Vec::new(),
+        duration: None,
+        max_duration: None,
+        error: Some(error),
+        response: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::{HeaderMap, HeaderValue};
+    use serde_json::json;
+
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| ((*k).to_owned(), (*v).to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn should_substitute_captured_values() {
+        let vars = vars(&[("user_id", "42"), ("role", "2")]);
+        assert_eq!(
+            substitute("/users/{{user_id}}?role={{ role }}", &vars).unwrap(),
+            "/users/42?role=2"
+        );
+        assert_eq!(
+            substitute_json(&json!({"ids": ["{{user_id}}"], "n": 1}), &vars).unwrap(),
+            json!({"ids": ["42"], "n": 1})
+        );
+    }
+
+    #[test]
+    fn should_reject_value_not_captured_yet() {
+        let err = substitute("/users/{{user_id}}", &BTreeMap::new()).unwrap_err();
+        assert_eq!(err, "{{user_id}} was not captured by an earlier step");
+    }
+
+    #[test]
+    fn should_extract_cookie_header_and_json_field() {
+        let mut headers = HeaderMap::new();
+        headers.append(
+            "set-cookie",
+            HeaderValue::from_static("madome_access_token=abc; Path=/; HttpOnly"),
+        );
+        headers.append(
+            "set-cookie",
+            HeaderValue::from_static("madome_refresh_token=def; Path=/auth/token"),
+        );
+        headers.insert("x-request-id", HeaderValue::from_static("r-1"));
+        let response = Response {
+            headers,
+            body: br#"{"user_id":"u-1","user_role":2}"#.to_vec(),
+        };
+
+        let extract = |source: &str| {
+            Capture::try_from(source.to_owned())
+                .unwrap()
+                .extract(&response)
+        };
+        assert_eq!(
+            extract("cookie:madome_refresh_token").as_deref(),
+            Some("def")
+        );
+        assert_eq!(extract("header:X-Request-Id").as_deref(), Some("r-1"));
+        assert_eq!(extract("json:/user_id").as_deref(), Some("u-1"));
+        assert_eq!(extract("json:/user_role").as_deref(), Some("2"));
+        assert_eq!(extract("json:/missing"), None);
+    }
+
+    #[test]
+    fn should_reject_unknown_capture_source() {
+        assert!(Capture::try_from("body:/user_id".to_owned()).is_err());
+    }
+}
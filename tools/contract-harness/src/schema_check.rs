@@ -0,0 +1,61 @@
+//! Schema drift check.
+//!
+//! Starts a throwaway PostgreSQL container, builds each service's schema from
+//! its migrations and from its sea-orm entities, and fails when they differ:
+//!
+//! ```bash
+//! cargo run -p contract-harness --features auth --bin schema-check
+//! ```
+//!
+//! Exits 0 when every schema matches, exits 1 on drift.
+
+use anyhow::Result;
+use contract_harness::{config::ContractHarnessConfig, docker::DockerOrchestrator, services};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv::dotenv().ok();
+    tracing_subscriber::fmt::init();
+
+    let config = ContractHarnessConfig::from_env();
+    let mut orch = DockerOrchestrator::connect(&config.docker_host).await?;
+    orch.cleanup_stale().await?;
+
+    let result = async {
+        let infra = services::InfraUrls {
+            database_url: orch.start_postgres().await?,
+            redis_url: String::new(),
+        };
+        check_services(&infra).await
+    }
+    .await;
+
+    // Always tear down containers regardless of the outcome.
+    orch.cleanup().await.ok();
+
+    let clean = result?;
+    std::process::exit(if clean { 0 } else { 1 });
+}
+
+async fn check_services(infra: &services::InfraUrls) -> Result<bool> {
+    let mut clean = true;
+
+    #[cfg(feature = "auth")]
+    {
+        clean &= report("auth", &services::auth::check_schema(infra).await?);
+    }
+
+    Ok(clean)
+}
+
+fn report(service: &str, drifts: &[contract_harness::schema::Drift]) -> bool {
+    if drifts.is_empty() {
+        println!("{service}: schema matches entities");
+        return true;
+    }
+    println!("{service}: {} drift(s)", drifts.len());
+    for drift in drifts {
+        println!("  {drift}");
+    }
+    false
+}
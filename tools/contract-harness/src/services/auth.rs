@@ -3,30 +3,40 @@
 use std::path::Path;
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use deadpool_redis::Runtime;
-use madome_auth::{router::build_router, state::AppState};
+use madome_auth::{config::OAuthConfig, grpc_server, router::build_router, state::AppState};
 use madome_auth_migration::Migrator;
-use sea_orm::Database;
+use madome_auth_types::cookie::{CookieConfig, TokenLifetimes};
+use madome_core::config::Secret;
+use madome_core::grpc::{GrpcAuthLayer, GrpcTraceLayer};
+use madome_core::secrets::RefreshingSecret;
+use sea_orm::{ConnectionTrait, Database};
 use sea_orm_migration::MigratorTrait;
 use tokio::net::TcpListener;
+use tonic::transport::{Server, server::TcpIncoming};
 use url::Url;
 use webauthn_rs::prelude::WebauthnBuilder;
 
 use crate::{
-    config::ContractHarnessConfig, fixture, reporter, runner::Runner, services::InfraUrls,
+    config::ContractHarnessConfig,
+    fixture, reporter, schema,
+    services::{self, Cluster, InfraUrls, StartedService},
 };
 
-/// Run auth migrations, start the auth service in-process, run all auth fixtures.
-///
-/// Returns `true` if every fixture passed.
-pub async fn run(
+/// Callers, with their secrets, that services started later may use for
+/// `auth.AuthService`.
+pub const GRPC_CALLERS: [(&str, &str); 1] = [("gateway", "harness-gateway-token")];
+
+/// Run auth migrations in its own database and start the auth service
+/// in-process on random HTTP and gRPC ports.
+pub async fn start(
     infra: &InfraUrls,
     config: &ContractHarnessConfig,
-    workspace_root: &Path,
-) -> Result<bool> {
+    _cluster: &Cluster,
+) -> Result<StartedService> {
     // ── DB + migrations ────────────────────────────────────────────────────
-    let db = Database::connect(&infra.database_url).await?;
+    let db = services::service_database(infra, "auth").await?;
     Migrator::up(&db, None).await?;
 
     // ── Redis pool ─────────────────────────────────────────────────────────
@@ -44,22 +54,59 @@ pub async fn run(
     // ── Start auth service on a random OS-assigned port ────────────────────
     let listener = TcpListener::bind("127.0.0.1:0").await?;
     let port = listener.local_addr()?.port();
-    let base_url = format!("http://127.0.0.1:{port}");
 
+    let pool = db.get_postgres_connection_pool().clone();
     let state = AppState {
+        db_read: db.clone(),
         db,
         redis,
         webauthn,
-        jwt_secret: config.jwt_secret.clone(),
-        cookie_domain: config.cookie_domain.clone(),
+        jwt_secret: RefreshingSecret::new(Secret::new(config.jwt_secret.clone())),
+        cookies: CookieConfig::new(config.cookie_domain.clone()),
+        token_lifetimes: TokenLifetimes::default(),
+        legacy_token_cutoff: None,
+        app_url: "/".to_owned(),
+        oauth: Arc::new(OAuthConfig::default()),
+        http: reqwest::Client::new(),
+        csrf_enforce: false,
+        cors: None,
     };
+    let grpc_listener = TcpListener::bind("127.0.0.1:0").await?;
+    let grpc_port = grpc_listener.local_addr()?.port();
+    let callers =
+        GRPC_CALLERS.map(|(name, token)| (name.to_owned(), Secret::new(token.to_owned())));
+    let grpc = Server::builder()
+        .layer(GrpcTraceLayer)
+        .layer(GrpcAuthLayer::new(grpc_server::policy(&callers)))
+        .add_service(grpc_server::service(state.clone()));
+    tokio::spawn(async move {
+        grpc.serve_with_incoming(TcpIncoming::from(grpc_listener))
+            .await
+            .unwrap();
+    });
     tokio::spawn(async move {
         axum::serve(listener, build_router(state)).await.unwrap();
     });
 
-    // ── Load fixtures and run ──────────────────────────────────────────────
+    Ok(StartedService {
+        name: "auth",
+        base_url: format!("http://127.0.0.1:{port}"),
+        grpc_url: Some(format!("http://127.0.0.1:{grpc_port}")),
+        spec: Some((client().spec)()),
+        db: Some(pool),
+    })
+}
+
+/// Run all auth fixtures against the started service.
+///
+/// Response bodies are checked against the auth OpenAPI document, and the
+/// generated TypeScript client must be up to date with it. Returns `true` if
+/// every fixture passed and the client is current.
+pub async fn run(cluster: &Cluster, workspace_root: &Path) -> Result<bool> {
+    let runner = cluster
+        .runner("auth")
+        .ok_or_else(|| anyhow!("auth was not started"))?;
     let fixtures = fixture::load_all(workspace_root, Some("auth"))?;
-    let runner = Runner::new(&base_url);
     let mut rep = reporter::Reporter::new();
 
     for f in &fixtures {
@@ -67,6 +114,54 @@ pub async fn run(
         rep.record(f, result);
     }
 
+    let client = client();
+    let client_current = client.is_up_to_date(workspace_root);
+    if !client_current {
+        println!(
+            "FAIL  {} is stale; run `cargo run -p client-gen`",
+            client.path
+        );
+    }
+
     rep.print_summary();
-    Ok(rep.all_passed())
+    Ok(rep.all_passed() && client_current)
+}
+
+fn client() -> client_gen::Target {
+    client_gen::targets()
+        .into_iter()
+        .find(|t| t.service == "auth")
+        .expect("client-gen/auth is enabled by the auth feature")
+}
+
+/// Diff the schema built by the auth migrations against `madome-auth-schema`.
+///
+/// Migrations run in `infra.database_url`; the entity tables are created in a
+/// sibling `auth_entities` database on the same server.
+pub async fn check_schema(infra: &InfraUrls) -> Result<Vec<schema::Drift>> {
+    use madome_auth_schema::{
+        auth_codes, linked_identities, login_links, outbox_events, passkeys, sessions, users,
+    };
+
+    let migrated = Database::connect(&infra.database_url).await?;
+    Migrator::up(&migrated, None).await?;
+    migrated
+        .execute_unprepared("CREATE DATABASE auth_entities")
+        .await?;
+
+    let entities =
+        Database::connect(schema::with_database(&infra.database_url, "auth_entities")).await?;
+    // `users` first: the other tables reference it.
+    schema::create_entity_table(&entities, users::Entity).await?;
+    schema::create_entity_table(&entities, auth_codes::Entity).await?;
+    schema::create_entity_table(&entities, linked_identities::Entity).await?;
+    schema::create_entity_table(&entities, login_links::Entity).await?;
+    schema::create_entity_table(&entities, outbox_events::Entity).await?;
+    schema::create_entity_table(&entities, passkeys::Entity).await?;
+    schema::create_entity_table(&entities, sessions::Entity).await?;
+
+    Ok(schema::diff(
+        &schema::introspect(&migrated).await?,
+        &schema::introspect(&entities).await?,
+    ))
 }
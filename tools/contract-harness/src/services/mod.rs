@@ -1,4 +1,11 @@
 //! Per-service contract runners.
+//!
+//! Docker mode starts every compiled-in service before running any fixture,
+//! so services that call each other find their peers in the [`Cluster`].
+
+use serde_json::Value;
+
+use crate::runner::Runner;
 
 /// Infrastructure URLs for test containers.
 pub struct InfraUrls {
@@ -6,5 +13,72 @@ pub struct InfraUrls {
     pub redis_url: String,
 }
 
+/// A service running in-process for this harness run.
+pub struct StartedService {
+    pub name: &'static str,
+    pub base_url: String,
+    /// gRPC endpoint, for services that serve one; passed to services started later.
+    pub grpc_url: Option<String>,
+    /// OpenAPI document response bodies are checked against.
+    pub spec: Option<Value>,
+    /// The service's own database, for `expect.db` assertions.
+    #[cfg(feature = "auth")]
+    pub db: Option<sqlx::PgPool>,
+}
+
+/// Every service started for the run, in start order.
+#[derive(Default)]
+pub struct Cluster {
+    services: Vec<StartedService>,
+}
+
+impl Cluster {
+    pub fn push(&mut self, service: StartedService) {
+        self.services.push(service);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&StartedService> {
+        self.services.iter().find(|s| s.name == name)
+    }
+
+    /// gRPC endpoint of an already started peer.
+    pub fn grpc_url(&self, name: &str) -> Option<&str> {
+        self.get(name)?.grpc_url.as_deref()
+    }
+
+    /// Fixture runner for `name`, checking bodies against its spec and
+    /// `expect.db` against its database.
+    pub fn runner(&self, name: &str) -> Option<Runner> {
+        let service = self.get(name)?;
+        let mut runner = Runner::new(&service.base_url);
+        if let Some(spec) = &service.spec {
+            runner = runner.with_spec(spec.clone());
+        }
+        #[cfg(feature = "auth")]
+        if let Some(db) = &service.db {
+            runner = runner.with_database(db.clone());
+        }
+        Some(runner)
+    }
+}
+
+/// Create database `name` on the shared Postgres server and connect to it.
+///
+/// Each service migrates its own database, as in production, so their
+/// `seaql_migrations` tables do not collide.
+#[cfg(feature = "auth")]
+pub async fn service_database(
+    infra: &InfraUrls,
+    name: &str,
+) -> anyhow::Result<sea_orm::DatabaseConnection> {
+    use sea_orm::{ConnectionTrait, Database};
+
+    let server = Database::connect(&infra.database_url).await?;
+    server
+        .execute_unprepared(&format!("CREATE DATABASE {name}"))
+        .await?;
+    Ok(Database::connect(crate::schema::with_database(&infra.database_url, name)).await?)
+}
+
 #[cfg(feature = "auth")]
 pub mod auth;
@@ -0,0 +1,245 @@
+//! Software WebAuthn authenticator for passkey contracts.
+//!
+//! Answers the challenges the passkey endpoints hand out the way a browser and
+//! a platform authenticator would: one P-256 credential, `none` attestation,
+//! user present and verified. Registration turns a `CreationChallengeResponse`
+//! into a `RegisterPublicKeyCredential`; authentication turns a
+//! `RequestChallengeResponse` into a signed `PublicKeyCredential`. Both sides
+//! are the JSON shapes the auth service speaks.
+
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use ciborium::Value as Cbor;
+use p256::ecdsa::{Signature, SigningKey, signature::Signer};
+use rand::RngExt;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+
+/// User present.
+const FLAG_UP: u8 = 0x01;
+/// User verified.
+const FLAG_UV: u8 = 0x04;
+/// Attested credential data included.
+const FLAG_AT: u8 = 0x40;
+
+/// One software credential, registered at most once.
+pub struct SoftAuthenticator {
+    origin: String,
+    key: SigningKey,
+    credential_id: Vec<u8>,
+    /// `user.id` from registration, returned as `userHandle` when signing in.
+    user_handle: Option<String>,
+    sign_count: u32,
+}
+
+impl SoftAuthenticator {
+    /// A fresh credential for a relying party served at `origin`
+    /// (e.g. `http://localhost`).
+    pub fn new(origin: &str) -> Self {
+        let mut rng = rand::rng();
+        let key = loop {
+            let secret: [u8; 32] = rng.random();
+            // Out-of-range scalars are astronomically rare; draw again.
+            if let Ok(key) = SigningKey::from_bytes(&secret.into()) {
+                break key;
+            }
+        };
+        let credential_id: [u8; 16] = rng.random();
+        Self {
+            origin: origin.to_owned(),
+            key,
+            credential_id: credential_id.to_vec(),
+            user_handle: None,
+            sign_count: 0,
+        }
+    }
+
+    /// Unpadded base64url credential id, as `GET /auth/passkeys` lists it.
+    pub fn credential_id(&self) -> String {
+        URL_SAFE_NO_PAD.encode(&self.credential_id)
+    }
+
+    /// Answer a `CreationChallengeResponse` with a `RegisterPublicKeyCredential`.
+    pub fn register(&mut self, options: &Value) -> Result<Value> {
+        let public_key = &options["publicKey"];
+        let challenge = str_field(public_key, "/challenge")?;
+        let rp_id = str_field(public_key, "/rp/id")?;
+        self.user_handle = Some(str_field(public_key, "/user/id")?.to_owned());
+
+        let client_data = self.client_data("webauthn.create", challenge);
+
+        let mut auth_data = self.authenticator_data(rp_id, FLAG_UP | FLAG_UV | FLAG_AT);
+        auth_data.extend_from_slice(&[0; 16]); // AAGUID: none
+        auth_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&self.credential_id);
+        auth_data.extend_from_slice(&cbor(&self.cose_public_key())?);
+
+        let attestation = Cbor::Map(vec![
+            (Cbor::Text("fmt".into()), Cbor::Text("none".into())),
+            (Cbor::Text("attStmt".into()), Cbor::Map(Vec::new())),
+            (Cbor::Text("authData".into()), Cbor::Bytes(auth_data)),
+        ]);
+
+        Ok(json!({
+            "id": self.credential_id(),
+            "rawId": self.credential_id(),
+            "type": "public-key",
+            "response": {
+                "attestationObject": URL_SAFE_NO_PAD.encode(cbor(&attestation)?),
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(&client_data),
+            },
+            "extensions": {},
+        }))
+    }
+
+    /// Answer a `RequestChallengeResponse` with a signed `PublicKeyCredential`.
+    pub fn authenticate(&mut self, options: &Value) -> Result<Value> {
+        let public_key = &options["publicKey"];
+        let challenge = str_field(public_key, "/challenge")?;
+        let rp_id = str_field(public_key, "/rpId")?;
+
+        self.sign_count += 1;
+        let client_data = self.client_data("webauthn.get", challenge);
+        let auth_data = self.authenticator_data(rp_id, FLAG_UP | FLAG_UV);
+
+        let mut signed = auth_data.clone();
+        signed.extend_from_slice(&Sha256::digest(&client_data));
+        let signature: Signature = self.key.sign(&signed);
+
+        Ok(json!({
+            "id": self.credential_id(),
+            "rawId": self.credential_id(),
+            "type": "public-key",
+            "response": {
+                "authenticatorData": URL_SAFE_NO_PAD.encode(&auth_data),
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(&client_data),
+                "signature": URL_SAFE_NO_PAD.encode(signature.to_der()),
+                "userHandle": self.user_handle,
+            },
+            "extensions": {},
+        }))
+    }
+
+    /// `clientDataJSON` bytes; the challenge is echoed exactly as received.
+    fn client_data(&self, kind: &str, challenge: &str) -> Vec<u8> {
+        json!({
+            "type": kind,
+            "challenge": challenge,
+            "origin": self.origin,
+            "crossOrigin": false,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    /// rpIdHash ‖ flags ‖ signCount, without attested credential data.
+    fn authenticator_data(&self, rp_id: &str, flags: u8) -> Vec<u8> {
+        let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+        data
+    }
+
+    /// COSE_Key for the credential: EC2, ES256, P-256.
+    fn cose_public_key(&self) -> Cbor {
+        let point = self.key.verifying_key().to_encoded_point(false);
+        let x = point.x().expect("uncompressed point has x").to_vec();
+        let y = point.y().expect("uncompressed point has y").to_vec();
+        Cbor::Map(vec![
+            (Cbor::Integer(1.into()), Cbor::Integer(2.into())), // kty: EC2
+            (Cbor::Integer(3.into()), Cbor::Integer((-7).into())), // alg: ES256
+            (Cbor::Integer((-1).into()), Cbor::Integer(1.into())), // crv: P-256
+            (Cbor::Integer((-2).into()), Cbor::Bytes(x)),
+            (Cbor::Integer((-3).into()), Cbor::Bytes(y)),
+        ])
+    }
+}
+
+fn str_field<'a>(value: &'a Value, pointer: &str) -> Result<&'a str> {
+    value
+        .pointer(pointer)
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("challenge has no publicKey{pointer}"))
+}
+
+fn cbor(value: &Cbor) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    ciborium::into_writer(value, &mut out).context("cannot encode CBOR")?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use p256::ecdsa::{VerifyingKey, signature::Verifier};
+
+    use super::*;
+
+    fn creation_options() -> Value {
+        json!({"publicKey": {
+            "rp": {"id": "localhost", "name": "Madome"},
+            "user": {"id": "dXNlcg", "name": "user@example.com", "displayName": "user"},
+            "challenge": "Y2hhbGxlbmdl",
+        }})
+    }
+
+    fn decode(value: &Value, pointer: &str) -> Vec<u8> {
+        URL_SAFE_NO_PAD
+            .decode(value.pointer(pointer).unwrap().as_str().unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn should_register_with_none_attestation_for_rp() {
+        let mut authenticator = SoftAuthenticator::new("http://localhost");
+        let credential = authenticator.register(&creation_options()).unwrap();
+
+        let client_data: Value =
+            serde_json::from_slice(&decode(&credential, "/response/clientDataJSON")).unwrap();
+        assert_eq!(client_data["type"], "webauthn.create");
+        assert_eq!(client_data["challenge"], "Y2hhbGxlbmdl");
+        assert_eq!(client_data["origin"], "http://localhost");
+
+        let attestation: Cbor =
+            ciborium::from_reader(&decode(&credential, "/response/attestationObject")[..]).unwrap();
+        let fields = attestation.as_map().unwrap();
+        assert_eq!(fields[0].1, Cbor::Text("none".into()));
+        let auth_data = fields[2].1.as_bytes().unwrap();
+        assert_eq!(&auth_data[..32], &Sha256::digest(b"localhost")[..]);
+        assert_eq!(auth_data[32], FLAG_UP | FLAG_UV | FLAG_AT);
+        // rpIdHash(32) flags(1) signCount(4) aaguid(16) idLen(2) id
+        assert_eq!(&auth_data[55..71], &authenticator.credential_id[..]);
+        assert_eq!(credential["id"], authenticator.credential_id());
+    }
+
+    #[test]
+    fn should_sign_assertion_with_registered_key() {
+        let mut authenticator = SoftAuthenticator::new("http://localhost");
+        authenticator.register(&creation_options()).unwrap();
+
+        let request = json!({"publicKey": {"challenge": "c2lnbg", "rpId": "localhost"}});
+        let assertion = authenticator.authenticate(&request).unwrap();
+
+        let auth_data = decode(&assertion, "/response/authenticatorData");
+        assert_eq!(auth_data[32], FLAG_UP | FLAG_UV);
+        assert_eq!(&auth_data[33..37], &1u32.to_be_bytes());
+
+        let mut signed = auth_data.clone();
+        signed.extend_from_slice(&Sha256::digest(decode(
+            &assertion,
+            "/response/clientDataJSON",
+        )));
+        let signature = Signature::from_der(&decode(&assertion, "/response/signature")).unwrap();
+        let verifying_key = VerifyingKey::from(&authenticator.key);
+        assert!(verifying_key.verify(&signed, &signature).is_ok());
+        assert_eq!(assertion["response"]["userHandle"], "dXNlcg");
+    }
+
+    #[test]
+    fn should_reject_challenge_without_rp() {
+        let mut authenticator = SoftAuthenticator::new("http://localhost");
+        let err = authenticator
+            .register(&json!({"publicKey": {"challenge": "x", "user": {"id": "u"}}}))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "challenge has no publicKey/rp/id");
+    }
+}
@@ -0,0 +1,130 @@
+//! The import's own bookkeeping, kept in the target database so a run can be
+//! resumed: how many rows of each dump are done, and which new id each legacy
+//! id became. Both are written in the same transaction as the rows they
+//! describe.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, Schema, Set,
+};
+use uuid::Uuid;
+
+pub mod checkpoints {
+    use sea_orm::entity::prelude::*;
+
+    /// Rows of a dump imported so far, counting skipped ones.
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "legacy_import_checkpoints")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub source: String,
+        pub rows_done: i64,
+        pub updated_at: chrono::DateTime<chrono::Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod ids {
+    use sea_orm::entity::prelude::*;
+
+    /// New id of a legacy row, e.g. kind `user`.
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "legacy_import_ids")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub kind: String,
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub legacy_id: i64,
+        pub new_id: Uuid,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+/// Create the bookkeeping tables unless they exist.
+pub async fn ensure_tables(db: &DatabaseConnection) -> Result<()> {
+    let schema = Schema::new(db.get_database_backend());
+    for mut table in [
+        schema.create_table_from_entity(checkpoints::Entity),
+        schema.create_table_from_entity(ids::Entity),
+    ] {
+        db.execute(db.get_database_backend().build(table.if_not_exists()))
+            .await
+            .context("create import bookkeeping tables")?;
+    }
+    Ok(())
+}
+
+pub async fn rows_done(db: &impl ConnectionTrait, source: &str) -> Result<u64> {
+    let done = checkpoints::Entity::find_by_id(source.to_owned())
+        .one(db)
+        .await
+        .context("read checkpoint")?;
+    Ok(done.map_or(0, |c| c.rows_done as u64))
+}
+
+pub async fn set_rows_done(db: &impl ConnectionTrait, source: &str, rows: u64) -> Result<()> {
+    checkpoints::Entity::insert(checkpoints::ActiveModel {
+        source: Set(source.to_owned()),
+        rows_done: Set(rows as i64),
+        updated_at: Set(Utc::now()),
+    })
+    .on_conflict(
+        OnConflict::column(checkpoints::Column::Source)
+            .update_columns([
+                checkpoints::Column::RowsDone,
+                checkpoints::Column::UpdatedAt,
+            ])
+            .to_owned(),
+    )
+    .exec(db)
+    .await
+    .context("write checkpoint")?;
+    Ok(())
+}
+
+pub async fn record_ids(db: &impl ConnectionTrait, kind: &str, ids: &[(i64, Uuid)]) -> Result<()> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+    ids::Entity::insert_many(ids.iter().map(|&(legacy_id, new_id)| ids::ActiveModel {
+        kind: Set(kind.to_owned()),
+        legacy_id: Set(legacy_id),
+        new_id: Set(new_id),
+    }))
+    .on_conflict(
+        OnConflict::columns([ids::Column::Kind, ids::Column::LegacyId])
+            .update_column(ids::Column::NewId)
+            .to_owned(),
+    )
+    .exec(db)
+    .await
+    .context("record id mapping")?;
+    Ok(())
+}
+
+/// New ids of those `legacy` ids that were imported.
+pub async fn lookup_ids(
+    db: &impl ConnectionTrait,
+    kind: &str,
+    legacy: impl IntoIterator<Item = i64>,
+) -> Result<HashMap<i64, Uuid>> {
+    let rows = ids::Entity::find()
+        .filter(ids::Column::Kind.eq(kind))
+        .filter(ids::Column::LegacyId.is_in(legacy))
+        .all(db)
+        .await
+        .context("look up id mapping")?;
+    Ok(rows.into_iter().map(|r| (r.legacy_id, r.new_id)).collect())
+}
@@ -0,0 +1,196 @@
+//! Reader for the CSV that `COPY ... TO STDOUT WITH (FORMAT csv, HEADER)`
+//! writes: comma-separated, `"`-quoted fields that may span lines, `""` for a
+//! quote inside a quoted field.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::BufRead;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, anyhow, bail};
+
+/// One data row.
+pub struct Record {
+    /// Line the row starts on, counting the header as line 1.
+    pub line: usize,
+    columns: Rc<HashMap<String, usize>>,
+    /// An unquoted empty field is SQL `NULL` (`None`); `""` is an empty string.
+    fields: Vec<Option<String>>,
+}
+
+impl Record {
+    /// `None` for `NULL` or a column the dump does not have.
+    pub fn get(&self, column: &str) -> Option<&str> {
+        let i = *self.columns.get(column)?;
+        self.fields.get(i)?.as_deref()
+    }
+
+    pub fn required(&self, column: &str) -> Result<&str> {
+        self.get(column)
+            .ok_or_else(|| anyhow!("line {}: `{column}` is missing", self.line))
+    }
+
+    pub fn parse<T>(&self, column: &str) -> Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.get(column)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|e| anyhow!("line {}: `{column}` {value:?}: {e}", self.line))
+            })
+            .transpose()
+    }
+}
+
+pub struct CsvReader<R> {
+    input: R,
+    line: usize,
+    columns: Rc<HashMap<String, usize>>,
+}
+
+impl<R: BufRead> CsvReader<R> {
+    /// Read the header row; error unless it names every one of `required`.
+    pub fn new(input: R, required: &[&str]) -> Result<Self> {
+        let mut reader = Self {
+            input,
+            line: 0,
+            columns: Rc::default(),
+        };
+        let header = reader.next_fields()?.context("no header row")?;
+        let columns: HashMap<String, usize> = header
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| (name.unwrap_or_default(), i))
+            .collect();
+        let missing: Vec<&str> = required
+            .iter()
+            .copied()
+            .filter(|name| !columns.contains_key(*name))
+            .collect();
+        if !missing.is_empty() {
+            bail!("header lacks columns {}", missing.join(", "));
+        }
+        reader.columns = Rc::new(columns);
+        Ok(reader)
+    }
+
+    pub fn next_record(&mut self) -> Result<Option<Record>> {
+        let line = self.line + 1;
+        Ok(self.next_fields()?.map(|fields| Record {
+            line,
+            columns: Rc::clone(&self.columns),
+            fields,
+        }))
+    }
+
+    fn next_fields(&mut self) -> Result<Option<Vec<Option<String>>>> {
+        let start = self.line + 1;
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut in_quotes = false;
+        let mut buf = String::new();
+        loop {
+            buf.clear();
+            if self.input.read_line(&mut buf)? == 0 {
+                if in_quotes {
+                    bail!("line {start}: unterminated quoted field");
+                }
+                if fields.is_empty() && field.is_empty() && !quoted {
+                    return Ok(None);
+                }
+                // Last row without a trailing newline.
+                fields.push(finish(&mut field, &mut quoted));
+                return Ok(Some(fields));
+            }
+            self.line += 1;
+            let mut chars = buf.chars().peekable();
+            while let Some(c) = chars.next() {
+                if in_quotes {
+                    match c {
+                        '"' if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        '"' => in_quotes = false,
+                        c => field.push(c),
+                    }
+                    continue;
+                }
+                match c {
+                    '"' if field.is_empty() && !quoted => (quoted, in_quotes) = (true, true),
+                    ',' => fields.push(finish(&mut field, &mut quoted)),
+                    '\r' if chars.peek() == Some(&'\n') => {}
+                    // A blank line between rows.
+                    '\n' if fields.is_empty() && field.is_empty() && !quoted => {}
+                    '\n' => {
+                        fields.push(finish(&mut field, &mut quoted));
+                        return Ok(Some(fields));
+                    }
+                    c => field.push(c),
+                }
+            }
+        }
+    }
+}
+
+fn finish(field: &mut String, quoted: &mut bool) -> Option<String> {
+    let value = std::mem::take(field);
+    let quoted = std::mem::take(quoted);
+    (quoted || !value.is_empty()).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(csv: &str) -> Vec<Vec<Option<String>>> {
+        let mut reader = CsvReader::new(csv.as_bytes(), &["id"]).unwrap();
+        std::iter::from_fn(|| reader.next_record().unwrap())
+            .map(|r| r.fields)
+            .collect()
+    }
+
+    fn some(s: &str) -> Option<String> {
+        Some(s.to_owned())
+    }
+
+    #[test]
+    fn should_read_quoted_multiline_fields_and_tell_null_from_empty() {
+        let rows = records("id,name,note\n1,\"a, \"\"b\"\"\",\"x\ny\"\r\n2,,\"\"\n\n3,c,d");
+        assert_eq!(
+            rows,
+            [
+                vec![some("1"), some("a, \"b\""), some("x\ny")],
+                vec![some("2"), None, some("")],
+                vec![some("3"), some("c"), some("d")],
+            ]
+        );
+    }
+
+    #[test]
+    fn should_name_missing_columns_and_bad_values_with_their_line() {
+        let error = CsvReader::new("id,email\n".as_bytes(), &["id", "role"])
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "header lacks columns role");
+
+        let mut reader = CsvReader::new("id,role\n1,0\n\"2\nx\",9\n".as_bytes(), &["id"]).unwrap();
+        reader.next_record().unwrap();
+        let record = reader.next_record().unwrap().unwrap();
+        assert_eq!(record.line, 3);
+        assert_eq!(record.parse::<u8>("role").unwrap(), Some(9));
+        assert_eq!(
+            record.parse::<i64>("id").unwrap_err().to_string(),
+            "line 3: `id` \"2\\nx\": invalid digit found in string"
+        );
+        assert_eq!(
+            record.required("email").unwrap_err().to_string(),
+            "line 3: `email` is missing"
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! The batch loop every dump goes through.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use sea_orm::{DatabaseConnection, DatabaseTransaction, TransactionTrait};
+
+use crate::checkpoint;
+use crate::csv::{CsvReader, Record};
+
+/// One legacy dump and how its rows land in the auth database.
+pub trait Source {
+    /// Checkpoint key and file stem: `users` reads `users.csv`.
+    const NAME: &'static str;
+    const REQUIRED_COLUMNS: &'static [&'static str];
+
+    type Row;
+
+    fn parse(record: &Record) -> Result<Self::Row>;
+
+    /// Insert one batch. Returns how many rows were skipped because they were
+    /// already there or refer to something that was not imported.
+    async fn write(txn: &DatabaseTransaction, rows: Vec<Self::Row>) -> Result<u64>;
+}
+
+pub struct Progress {
+    /// Rows done, including those of earlier runs.
+    pub rows: u64,
+    /// Rows skipped in this run.
+    pub skipped: u64,
+}
+
+/// Import `{dir}/{NAME}.csv` in batches of `batch_size`, starting after the
+/// rows an earlier run committed. Each batch commits together with its
+/// checkpoint, so a failed run resumes at the batch that failed.
+pub async fn run<S: Source>(
+    db: &DatabaseConnection,
+    dir: &Path,
+    batch_size: usize,
+) -> Result<Progress> {
+    let path = dir.join(format!("{}.csv", S::NAME));
+    let file = File::open(&path).with_context(|| format!("open {}", path.display()))?;
+    let mut reader = CsvReader::new(BufReader::new(file), S::REQUIRED_COLUMNS)
+        .with_context(|| path.display().to_string())?;
+
+    let done = checkpoint::rows_done(db, S::NAME).await?;
+    for _ in 0..done {
+        if reader.next_record()?.is_none() {
+            break;
+        }
+    }
+    if done > 0 {
+        println!("{}: resuming after {done} rows", S::NAME);
+    }
+
+    let mut progress = Progress {
+        rows: done,
+        skipped: 0,
+    };
+    loop {
+        let mut batch = Vec::with_capacity(batch_size);
+        while batch.len() < batch_size {
+            let Some(record) = reader
+                .next_record()
+                .with_context(|| path.display().to_string())?
+            else {
+                break;
+            };
+            batch.push(S::parse(&record).with_context(|| path.display().to_string())?);
+        }
+        if batch.is_empty() {
+            return Ok(progress);
+        }
+
+        let rows = batch.len() as u64;
+        let txn = db.begin().await?;
+        progress.skipped += S::write(&txn, batch).await?;
+        checkpoint::set_rows_done(&txn, S::NAME, progress.rows + rows).await?;
+        txn.commit().await?;
+        progress.rows += rows;
+        println!("{}: {} rows", S::NAME, progress.rows);
+    }
+}
+
+/// A `timestamptz` as Postgres writes it (`2024-03-01 12:00:00.5+09`) or as
+/// RFC 3339.
+pub fn timestamp(record: &Record, column: &str) -> Result<Option<DateTime<Utc>>> {
+    record
+        .get(column)
+        .map(|value| {
+            DateTime::parse_from_rfc3339(value)
+                .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f%#z"))
+                .map(|t| t.to_utc())
+                .map_err(|e| anyhow!("line {}: `{column}` {value:?}: {e}", record.line))
+        })
+        .transpose()
+}
+
+/// A `boolean` as Postgres writes it (`t` / `f`) or spelled out.
+pub fn boolean(record: &Record, column: &str) -> Result<Option<bool>> {
+    record
+        .get(column)
+        .map(|value| match value {
+            "t" | "true" => Ok(true),
+            "f" | "false" => Ok(false),
+            other => Err(anyhow!(
+                "line {}: `{column}` {other:?} is not a boolean",
+                record.line
+            )),
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn should_parse_postgres_and_rfc3339_timestamps_and_booleans() {
+        let csv = "a,b,c\n2024-03-01 12:00:00.5+09,2024-03-01T03:00:00.5Z,t\n";
+        let mut reader = CsvReader::new(csv.as_bytes(), &[]).unwrap();
+        let record = reader.next_record().unwrap().unwrap();
+
+        let expected = Utc.with_ymd_and_hms(2024, 3, 1, 3, 0, 0).unwrap()
+            + chrono::Duration::milliseconds(500);
+        assert_eq!(timestamp(&record, "a").unwrap(), Some(expected));
+        assert_eq!(timestamp(&record, "b").unwrap(), Some(expected));
+        assert_eq!(boolean(&record, "c").unwrap(), Some(true));
+        assert!(boolean(&record, "a").is_err());
+        assert_eq!(timestamp(&record, "missing").unwrap(), None);
+    }
+}
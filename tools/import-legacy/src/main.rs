@@ -0,0 +1,72 @@
+//! One-off import of the legacy system's data.
+//!
+//! Reads CSV dumps of the legacy tables (`COPY ... TO STDOUT WITH (FORMAT csv,
+//! HEADER)`) and writes them into the auth database, giving every user a
+//! UUIDv7 id:
+//!
+//! ```bash
+//! DATABASE_URL=... cargo run -p import-legacy -- --dump-dir ./dump --migrate
+//! ```
+//!
+//! Rows go in batches, each committed together with a checkpoint, so an
+//! interrupted run continues where it stopped when started again.
+
+mod checkpoint;
+mod csv;
+mod import;
+mod passkeys;
+mod users;
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Parser;
+use sea_orm::Database;
+
+use crate::passkeys::Passkeys;
+use crate::users::Users;
+
+#[derive(Parser)]
+#[command(about = "Import legacy CSV dumps into the auth database")]
+struct Args {
+    /// Auth service database URL.
+    #[arg(long, env = "DATABASE_URL")]
+    database_url: String,
+
+    /// Directory holding `users.csv` and `passkeys.csv`.
+    #[arg(long)]
+    dump_dir: PathBuf,
+
+    /// Rows per transaction.
+    #[arg(long, default_value_t = 500, value_parser = clap::value_parser!(u32).range(1..=10_000))]
+    batch_size: u32,
+
+    /// Apply pending migrations before importing.
+    #[arg(long)]
+    migrate: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenvy::dotenv().ok();
+    let args = Args::parse();
+
+    if args.migrate {
+        madome_auth::infra::migrate::run_migrations(&args.database_url).await?;
+    }
+    let db = Database::connect(&args.database_url).await?;
+    checkpoint::ensure_tables(&db).await?;
+
+    let batch_size = args.batch_size as usize;
+    let users = import::run::<Users>(&db, &args.dump_dir, batch_size).await?;
+    println!(
+        "users: {} rows, {} already present",
+        users.rows, users.skipped
+    );
+    let passkeys = import::run::<Passkeys>(&db, &args.dump_dir, batch_size).await?;
+    println!(
+        "passkeys: {} rows, {} skipped (already present or owner not imported)",
+        passkeys.rows, passkeys.skipped
+    );
+    Ok(())
+}
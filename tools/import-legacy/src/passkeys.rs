@@ -0,0 +1,169 @@
+//! `passkeys.csv`: `credential_id`, `user_id` (the legacy one), `credential`
+//! and `created_at`, optionally `aaguid`, `backup_eligible`, `backup_state` and
+//! `transports` (a JSON array).
+
+use std::collections::HashSet;
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{DatabaseTransaction, EntityTrait, Set};
+use uuid::Uuid;
+
+use madome_auth_schema::passkeys;
+use madome_domain::id::UserId;
+
+use crate::checkpoint;
+use crate::csv::Record;
+use crate::import::{Source, boolean, timestamp};
+use crate::users;
+
+pub struct Passkeys;
+
+#[derive(Debug)]
+pub struct LegacyPasskey {
+    pub credential_id: Vec<u8>,
+    pub legacy_user_id: i64,
+    pub aaguid: Uuid,
+    /// JSON-serialized `webauthn_rs::Passkey`, as the auth service stores it.
+    pub credential: Vec<u8>,
+    pub backup_eligible: bool,
+    pub backup_state: bool,
+    pub transports: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Source for Passkeys {
+    const NAME: &'static str = "passkeys";
+    const REQUIRED_COLUMNS: &'static [&'static str] =
+        &["credential_id", "user_id", "credential", "created_at"];
+
+    type Row = LegacyPasskey;
+
+    fn parse(record: &Record) -> Result<LegacyPasskey> {
+        let line = record.line;
+        // Text when the legacy column is JSON, `\x` hex when it is `bytea`.
+        let credential = match record.required("credential")? {
+            value if value.starts_with("\\x") => {
+                bytes(value).with_context(|| format!("line {line}: `credential`"))?
+            }
+            value => value.as_bytes().to_vec(),
+        };
+        serde_json::from_slice::<serde_json::Value>(&credential)
+            .with_context(|| format!("line {line}: `credential` is not JSON"))?;
+        let transports = match record.get("transports") {
+            Some(value) => serde_json::from_str(value)
+                .ok()
+                .filter(serde_json::Value::is_array)
+                .ok_or_else(|| anyhow!("line {line}: `transports` is not a JSON array"))?,
+            None => serde_json::Value::Array(Vec::new()),
+        };
+        Ok(LegacyPasskey {
+            credential_id: bytes(record.required("credential_id")?)
+                .with_context(|| format!("line {line}: `credential_id`"))?,
+            legacy_user_id: record
+                .parse("user_id")?
+                .ok_or_else(|| anyhow!("line {line}: `user_id` is missing"))?,
+            aaguid: record.parse("aaguid")?.unwrap_or_default(),
+            credential,
+            backup_eligible: boolean(record, "backup_eligible")?.unwrap_or(false),
+            backup_state: boolean(record, "backup_state")?.unwrap_or(false),
+            transports,
+            created_at: timestamp(record, "created_at")?
+                .ok_or_else(|| anyhow!("line {line}: `created_at` is missing"))?,
+        })
+    }
+
+    /// Passkeys of users that were not imported, and credentials already
+    /// present, are skipped.
+    async fn write(txn: &DatabaseTransaction, rows: Vec<LegacyPasskey>) -> Result<u64> {
+        let owners = checkpoint::lookup_ids(
+            txn,
+            users::KIND,
+            rows.iter()
+                .map(|p| p.legacy_user_id)
+                .collect::<HashSet<_>>(),
+        )
+        .await?;
+        let total = rows.len() as u64;
+        let models: Vec<passkeys::ActiveModel> = rows
+            .into_iter()
+            .filter_map(|p| {
+                let user_id = *owners.get(&p.legacy_user_id)?;
+                Some(passkeys::ActiveModel {
+                    credential_id: Set(p.credential_id),
+                    user_id: Set(UserId(user_id)),
+                    aaguid: Set(p.aaguid),
+                    credential: Set(p.credential),
+                    backup_eligible: Set(p.backup_eligible),
+                    backup_state: Set(p.backup_state),
+                    transports: Set(p.transports),
+                    created_at: Set(p.created_at),
+                })
+            })
+            .collect();
+        if models.is_empty() {
+            return Ok(total);
+        }
+        let inserted = passkeys::Entity::insert_many(models)
+            .on_conflict(
+                OnConflict::column(passkeys::Column::CredentialId)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_without_returning(txn)
+            .await
+            .context("insert passkeys")?;
+        Ok(total - inserted)
+    }
+}
+
+/// A `bytea` as Postgres writes it (`\x` followed by hex), or base64url.
+fn bytes(value: &str) -> Result<Vec<u8>> {
+    let Some(hex) = value.strip_prefix("\\x") else {
+        return URL_SAFE_NO_PAD
+            .decode(value.trim_end_matches('='))
+            .context("neither `\\x` hex nor base64url");
+    };
+    if hex.len() % 2 != 0 {
+        bail!("odd number of hex digits");
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).context("invalid hex digit"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv::CsvReader;
+
+    #[test]
+    fn should_decode_bytea_hex_and_base64url_credential_ids() {
+        assert_eq!(bytes("\\x00ff10").unwrap(), [0x00, 0xff, 0x10]);
+        assert_eq!(bytes("AP8Q").unwrap(), [0x00, 0xff, 0x10]);
+        assert!(bytes("\\x0").is_err());
+        assert!(bytes("\\xzz").is_err());
+    }
+
+    #[test]
+    fn should_parse_passkeys_with_defaults_for_optional_columns() {
+        let csv = "credential_id,user_id,credential,created_at,backup_state,transports\n\
+                   AP8Q,7,\"{\"\"cred\"\":{}}\",2023-01-01 00:00:00+00,t,\"[\"\"usb\"\"]\"\n\
+                   \\x00,7,\\x7b,2023-01-01 00:00:00+00,,\n";
+        let mut reader = CsvReader::new(csv.as_bytes(), Passkeys::REQUIRED_COLUMNS).unwrap();
+
+        let passkey = Passkeys::parse(&reader.next_record().unwrap().unwrap()).unwrap();
+        assert_eq!(passkey.legacy_user_id, 7);
+        assert_eq!(passkey.credential, br#"{"cred":{}}"#);
+        assert_eq!(passkey.aaguid, Uuid::nil());
+        assert!(!passkey.backup_eligible && passkey.backup_state);
+        assert_eq!(passkey.transports, serde_json::json!(["usb"]));
+
+        let error = Passkeys::parse(&reader.next_record().unwrap().unwrap()).unwrap_err();
+        assert_eq!(error.to_string(), "line 3: `credential` is not JSON");
+    }
+}
@@ -0,0 +1,163 @@
+//! `users.csv`: `id`, `email` and `role`, optionally `created_at` and
+//! `deleted_at`.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ColumnTrait, DatabaseTransaction, EntityTrait, QueryFilter, Set};
+use uuid::{NoContext, Timestamp, Uuid};
+
+use madome_auth_schema::{id_aliases, users};
+use madome_domain::id::UserId;
+use madome_domain::user::UserRole;
+
+use crate::checkpoint;
+use crate::csv::Record;
+use crate::import::{Source, timestamp};
+
+/// Id-mapping kind of users; other tools resolve legacy user ids with it.
+pub const KIND: &str = "user";
+
+pub struct Users;
+
+#[derive(Debug)]
+pub struct LegacyUser {
+    pub legacy_id: i64,
+    pub email: String,
+    pub role: UserRole,
+    pub created_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl Source for Users {
+    const NAME: &'static str = "users";
+    const REQUIRED_COLUMNS: &'static [&'static str] = &["id", "email", "role"];
+
+    type Row = LegacyUser;
+
+    fn parse(record: &Record) -> Result<LegacyUser> {
+        let role = record.parse::<u8>("role")?;
+        Ok(LegacyUser {
+            legacy_id: record
+                .parse("id")?
+                .ok_or_else(|| anyhow!("line {}: `id` is missing", record.line))?,
+            email: record.required("email")?.to_owned(),
+            role: role.and_then(UserRole::from_u8).ok_or_else(|| {
+                anyhow!("line {}: `role` {role:?} is not a known role", record.line)
+            })?,
+            created_at: timestamp(record, "created_at")?,
+            deleted_at: timestamp(record, "deleted_at")?,
+        })
+    }
+
+    /// A user whose email is already registered keeps their current id; the
+    /// legacy id is mapped to it and the row counts as skipped.
+    async fn write(txn: &DatabaseTransaction, rows: Vec<LegacyUser>) -> Result<u64> {
+        let new_ids: Vec<Uuid> = rows.iter().map(|u| new_user_id(u.created_at)).collect();
+        users::Entity::insert_many(
+            rows.iter()
+                .zip(&new_ids)
+                .map(|(u, &id)| users::ActiveModel {
+                    id: Set(id),
+                    email: Set(u.email.clone()),
+                    role: Set(i16::from(u.role.as_u8())),
+                    deleted_at: Set(u.deleted_at.map(|t| t.fixed_offset())),
+                }),
+        )
+        .on_conflict(
+            OnConflict::column(users::Column::Email)
+                .do_nothing()
+                .to_owned(),
+        )
+        .do_nothing()
+        .exec(txn)
+        .await
+        .context("insert users")?;
+
+        let by_email: HashMap<String, Uuid> = users::Entity::find()
+            .filter(users::Column::Email.is_in(rows.iter().map(|u| u.email.as_str())))
+            .all(txn)
+            .await
+            .context("read back imported users")?
+            .into_iter()
+            .map(|u| (u.email, u.id))
+            .collect();
+        let mut ids = Vec::with_capacity(rows.len());
+        let mut skipped = 0;
+        for (user, new_id) in rows.iter().zip(new_ids) {
+            let id = by_email[&user.email];
+            if id != new_id {
+                skipped += 1;
+            }
+            ids.push((user.legacy_id, id));
+        }
+        checkpoint::record_ids(txn, KIND, &ids).await?;
+
+        // What the auth service resolves legacy ids with. A user who already
+        // has an alias keeps it.
+        let now = Utc::now();
+        id_aliases::Entity::insert_many(ids.iter().map(|&(legacy_id, user_id)| {
+            id_aliases::ActiveModel {
+                legacy_id: Set(legacy_id),
+                user_id: Set(UserId(user_id)),
+                created_at: Set(now),
+            }
+        }))
+        .on_conflict(OnConflict::new().do_nothing().to_owned())
+        .exec_without_returning(txn)
+        .await
+        .context("insert id aliases")?;
+        Ok(skipped)
+    }
+}
+
+/// A UUIDv7 carrying the legacy sign-up time, so new ids sort like the old
+/// ones; the current time when the dump has none.
+pub fn new_user_id(created_at: Option<DateTime<Utc>>) -> Uuid {
+    match created_at {
+        Some(t) => Uuid::new_v7(Timestamp::from_unix(
+            NoContext,
+            u64::try_from(t.timestamp()).unwrap_or(0),
+            t.timestamp_subsec_nanos(),
+        )),
+        None => Uuid::now_v7(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::csv::CsvReader;
+
+    #[test]
+    fn should_parse_users_and_reject_unknown_roles() {
+        let csv = "id,email,role,created_at\n7,a@example.com,1,2021-05-01 00:00:00+00\n8,b@example.com,9,\n";
+        let mut reader = CsvReader::new(csv.as_bytes(), Users::REQUIRED_COLUMNS).unwrap();
+
+        let user = Users::parse(&reader.next_record().unwrap().unwrap()).unwrap();
+        assert_eq!((user.legacy_id, user.role), (7, UserRole::Developer));
+        assert_eq!(
+            user.created_at,
+            Some(Utc.with_ymd_and_hms(2021, 5, 1, 0, 0, 0).unwrap())
+        );
+
+        let error = Users::parse(&reader.next_record().unwrap().unwrap()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 3: `role` Some(9) is not a known role"
+        );
+    }
+
+    #[test]
+    fn should_order_new_ids_by_legacy_sign_up_time() {
+        let at = |year| Some(Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0).unwrap());
+        let (older, newer) = (new_user_id(at(2019)), new_user_id(at(2022)));
+        assert_eq!(older.get_version_num(), 7);
+        assert!(older < newer);
+        assert!(newer < new_user_id(None));
+    }
+}
@@ -0,0 +1,211 @@
+//! Auth service rows: users, one-time codes, passkeys and sessions.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use rand::rngs::StdRng;
+use rand::{RngExt, SeedableRng};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use uuid::Uuid;
+
+use madome_auth::domain::repository::{PasskeyRepository, SessionRepository};
+use madome_auth::domain::types::{AUTHCODE_LEN, AuthCode, AuthUser, PasskeyRecord, Session};
+use madome_auth::infra::db::{DbPasskeyRepository, DbSessionRepository};
+use madome_auth::usecase::authcode::hash_code;
+use madome_auth_schema::{auth_codes, users};
+use madome_domain::user::UserRole;
+
+/// Same alphabet as issued codes, so seeded codes pass request validation.
+const CODE_CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+const USER_AGENTS: &[&str] = &[
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_5) AppleWebKit/605.1.15 Safari/605.1.15",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 Chrome/128.0 Safari/537.36",
+    "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15 Mobile/15E148",
+    "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 Chrome/128.0 Mobile Safari/537.36",
+];
+
+pub struct AuthSeed {
+    pub users: Vec<AuthUser>,
+    /// One unused code per user, valid for a day so it can be typed in by hand.
+    pub auth_codes: Vec<AuthCode>,
+    /// The plain code of each entry in `auth_codes`, in the same order.
+    pub plain_codes: Vec<String>,
+    /// Listing-only: the credential is a placeholder no authenticator can sign for.
+    pub passkeys: Vec<PasskeyRecord>,
+    pub sessions: Vec<Session>,
+}
+
+/// Rows for `users` users. Everything but the timestamps (relative to `now`)
+/// is a function of `seed`.
+///
+/// Codes are hashed with `code_secret`, which must be the service's JWT secret
+/// for them to be accepted.
+pub fn generate(seed: u64, users: usize, now: DateTime<Utc>, code_secret: &str) -> AuthSeed {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut data = AuthSeed {
+        users: Vec::with_capacity(users),
+        auth_codes: Vec::with_capacity(users),
+        plain_codes: Vec::with_capacity(users),
+        passkeys: Vec::new(),
+        sessions: Vec::new(),
+    };
+
+    for i in 0..users {
+        // The first user can reach developer-only endpoints.
+        let role = if i == 0 {
+            UserRole::Developer
+        } else {
+            UserRole::Normal
+        };
+        let user = AuthUser {
+            id: uuid(&mut rng).into(),
+            email: format!("user{i:04}@example.com"),
+            role: role.as_u8(),
+        };
+
+        let code: String = (0..AUTHCODE_LEN)
+            .map(|_| CODE_CHARSET[rng.random_range(0..CODE_CHARSET.len())] as char)
+            .collect();
+        data.auth_codes.push(AuthCode {
+            id: uuid(&mut rng),
+            user_id: user.id,
+            code_hash: hash_code(code_secret, &code),
+            expires_at: now + Duration::days(1),
+            used_at: None,
+            created_at: now,
+        });
+        data.plain_codes.push(code);
+
+        for _ in 0..rng.random_range(0..=2) {
+            // Platform passkeys sync; security keys do not.
+            let synced = rng.random_bool(0.7);
+            data.passkeys.push(PasskeyRecord {
+                credential_id: rng.random::<[u8; 16]>().to_vec(),
+                user_id: user.id,
+                aaguid: uuid(&mut rng),
+                credential: b"{}".to_vec(),
+                backup_eligible: synced,
+                backup_state: synced,
+                transports: if synced {
+                    vec!["internal".to_owned(), "hybrid".to_owned()]
+                } else {
+                    vec!["usb".to_owned()]
+                },
+                created_at: now - Duration::days(rng.random_range(1..90)),
+            });
+        }
+
+        for _ in 0..rng.random_range(1..=3) {
+            let created_at = now - Duration::days(rng.random_range(1..30));
+            data.sessions.push(Session {
+                id: uuid(&mut rng),
+                user_id: user.id,
+                user_agent: Some(USER_AGENTS[rng.random_range(0..USER_AGENTS.len())].to_owned()),
+                // TEST-NET-1 (RFC 5737): never routable.
+                ip: Some(format!("192.0.2.{}", rng.random_range(1..255))),
+                created_at,
+                last_used_at: created_at + Duration::hours(rng.random_range(0..24)),
+            });
+        }
+
+        data.users.push(user);
+    }
+    data
+}
+
+fn uuid(rng: &mut StdRng) -> Uuid {
+    uuid::Builder::from_random_bytes(rng.random()).into_uuid()
+}
+
+/// Replace previously seeded users (matched by email; their rows cascade) and
+/// insert `data`.
+///
+/// Users and codes are inserted directly: auth has no user-creation path, and
+/// `AuthCodeRepository::create_with_outbox` would queue a mail per code.
+pub async fn write(db: &DatabaseConnection, data: &AuthSeed) -> Result<()> {
+    let emails: Vec<&str> = data.users.iter().map(|u| u.email.as_str()).collect();
+    users::Entity::delete_many()
+        .filter(users::Column::Email.is_in(emails))
+        .exec(db)
+        .await
+        .context("delete previously seeded users")?;
+
+    users::Entity::insert_many(data.users.iter().map(|u| users::ActiveModel {
+        id: Set(u.id.into()),
+        email: Set(u.email.clone()),
+        role: Set(i16::from(u.role)),
+        deleted_at: Set(None),
+    }))
+    .exec(db)
+    .await
+    .context("insert users")?;
+
+    auth_codes::Entity::insert_many(data.auth_codes.iter().map(|c| auth_codes::ActiveModel {
+        id: Set(c.id),
+        user_id: Set(c.user_id),
+        code_hash: Set(c.code_hash.clone()),
+        expires_at: Set(c.expires_at),
+        used_at: Set(c.used_at),
+        created_at: Set(c.created_at),
+    }))
+    .exec(db)
+    .await
+    .context("insert auth codes")?;
+
+    let passkeys = DbPasskeyRepository {
+        db: db.clone(),
+        read: db.clone(),
+    };
+    for passkey in &data.passkeys {
+        passkeys.create(passkey).await?;
+    }
+
+    let sessions = DbSessionRepository {
+        db: db.clone(),
+        read: db.clone(),
+    };
+    for session in &data.sessions {
+        sessions.create(session).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "seed-test-secret";
+
+    #[test]
+    fn should_generate_same_rows_for_same_seed() {
+        let now = Utc::now();
+        let a = generate(7, 10, now, SECRET);
+        let b = generate(7, 10, now, SECRET);
+
+        let ids = |s: &AuthSeed| s.users.iter().map(|u| u.id).collect::<Vec<_>>();
+        assert_eq!(ids(&a), ids(&b));
+        assert_eq!(a.plain_codes, b.plain_codes);
+        assert_eq!(a.sessions.len(), b.sessions.len());
+        assert_ne!(ids(&a), ids(&generate(8, 10, now, SECRET)));
+    }
+
+    #[test]
+    fn should_give_every_user_a_valid_code_and_a_session() {
+        let data = generate(1, 25, Utc::now(), SECRET);
+
+        assert_eq!(data.users.len(), 25);
+        assert_eq!(data.users[0].role, UserRole::Developer.as_u8());
+        for user in &data.users {
+            let i = data
+                .auth_codes
+                .iter()
+                .position(|c| c.user_id == user.id)
+                .unwrap();
+            let plain = &data.plain_codes[i];
+            assert!(data.auth_codes[i].is_valid());
+            assert_eq!(plain.len(), AUTHCODE_LEN);
+            assert_eq!(data.auth_codes[i].code_hash, hash_code(SECRET, plain));
+            assert!(data.sessions.iter().any(|s| s.user_id == user.id));
+        }
+    }
+}